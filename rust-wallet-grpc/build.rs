@@ -19,7 +19,7 @@ fn main() {
     protoc_rust_grpc::run(protoc_rust_grpc::Args {
         out_dir: "src",
         includes: &[],
-        input: &["src/walletrpc.proto"],
+        input: &["src/walletrpc.proto", "src/reflection.proto"],
         rust_protobuf: true, // also generate protobuf messages, not just services
         ..Default::default()
     })
@@ -0,0 +1,362 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Access control
+//!
+//! A client sends its token in the `authorization` metadata as `Bearer <token>`. The token
+//! grants a `Permission` and may limit how often a method is called, e.g. a reporting system
+//! gets a read token and a payout service a spend token with a few SendCoins a minute.
+//! The tokens are read from a JSON file:
+//! ```json
+//! {
+//!     "anonymous": {"permission": "read"},
+//!     "tokens": {
+//!         "6f1c...": {"permission": "spend", "rate_limits": {"SendCoins": 10}},
+//!         "a93e...": {"permission": "admin"}
+//!     }
+//! }
+//! ```
+//! calls without a token are refused unless `anonymous` is there, Shutdown always needs one.
+//! Each method is listed with the permission it needs, one missing from the lists is refused
+//! whatever the token
+//!
+use serde_json::Value;
+
+use super::error::rpc_error;
+use super::walletrpc::ErrorCode;
+
+use std::{
+    collections::{HashMap, VecDeque},
+    error::Error,
+    fs,
+    path::Path,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+// the token, `None` for anonymous calls, and the method
+type RateKey = (Option<String>, String);
+
+/// what a token may do, each level includes the ones below it
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum Permission {
+    /// queries, new addresses and syncing
+    Read,
+    /// building, signing and broadcasting transactions, locking and freezing coins,
+    /// labels and watched scripts
+    Spend,
+    /// the spending policy, backups, wallets, the backend, logging and shutting the daemon down
+    Admin,
+}
+
+impl Permission {
+    fn parse(name: &str) -> Result<Permission, Box<dyn Error>> {
+        match name {
+            "read" => Ok(Permission::Read),
+            "spend" => Ok(Permission::Spend),
+            "admin" => Ok(Permission::Admin),
+            _ => Err(From::from(format!("unknown permission {}", name))),
+        }
+    }
+}
+
+const READ_METHODS: &[&str] = &[
+    "NewAddress",
+    "NewChangeAddress",
+    "GetUnusedAddress",
+    "ListAddresses",
+    "GetUtxoList",
+    "ListWatchedUtxos",
+    "WalletBalance",
+    "SyncWithTip",
+    "SyncWithTipStream",
+    "EstimateTxSize",
+    "GetPaymentCode",
+    "PaymentCodeAddress",
+    "GetSilentPaymentAddress",
+    "ListSilentPaymentUtxos",
+    "GetSpendingPolicy",
+    "ListPendingSpends",
+    "ListWallets",
+    "SubscribeEvents",
+    "GetInfo",
+    "GetConnectionStatus",
+    "GetTransactions",
+    "GetAccountStats",
+    "ExportHistory",
+    "NewTimelockedAddress",
+    "NewVaultAddress",
+    "GetScriptAccountXpub",
+    "NewScriptAddress",
+    "GetDescriptors",
+    "GetImportDescriptors",
+    "ListUnsignedTxs",
+    "EncodeQr",
+    "DecodeQr",
+];
+
+const SPEND_METHODS: &[&str] = &[
+    "MakeTx",
+    "SendCoins",
+    "PrepareTransaction",
+    "CommitTransaction",
+    "AbandonTransaction",
+    "AccelerateTransaction",
+    "CancelTransaction",
+    "NotifyPaymentCode",
+    "SweepPaymentCodes",
+    "SweepSilentPayments",
+    "ProveReserves",
+    "FundChannel",
+    "ReleaseFunding",
+    "PublishFunding",
+    "UnlockCoins",
+    "FreezeUtxo",
+    "UnfreezeUtxo",
+    "SetAddressLabel",
+    "WatchScript",
+    "SweepAddress",
+    "Unvault",
+    "SpendScriptCoins",
+    "FinalizeScriptPsbt",
+    "CreateUnsignedTx",
+    "BroadcastSignedPsbt",
+    "CancelUnsignedTx",
+    "LockWallet",
+];
+
+const ADMIN_METHODS: &[&str] = &[
+    "SetSpendingPolicy",
+    "ApproveSpend",
+    "RejectSpend",
+    "GetAuditLog",
+    "ExportBackup",
+    "RestoreBackup",
+    "CreateWallet",
+    "OpenWallet",
+    "Shutdown",
+    "Reconnect",
+    "SwitchBackend",
+    "RegisterPolicy",
+    "MigrateDerivation",
+    "SetVault",
+    "ExportElectrumWallet",
+    "ExportStateSnapshot",
+    "ImportStateSnapshot",
+    "CheckWallet",
+    "RescanStream",
+    "CancelOperation",
+    "UnlockWallet",
+    "SetLogLevel",
+    // it takes recovery phrases
+    "ValidateMnemonic",
+];
+
+/// the permission a call of `method`, a method of the Wallet service, needs,
+/// `None` for a method in none of the lists, nobody may call it
+pub fn required_permission(method: &str) -> Option<Permission> {
+    if ADMIN_METHODS.contains(&method) {
+        Some(Permission::Admin)
+    } else if SPEND_METHODS.contains(&method) {
+        Some(Permission::Spend)
+    } else if READ_METHODS.contains(&method) {
+        Some(Permission::Read)
+    } else {
+        None
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grant {
+    pub permission: Permission,
+    /// calls of a method allowed in a minute
+    pub rate_limits: HashMap<String, usize>,
+}
+
+impl Grant {
+    fn parse(value: &Value) -> Result<Grant, Box<dyn Error>> {
+        let permission = value["permission"].as_str().ok_or("a grant needs a permission")?;
+        let mut rate_limits = HashMap::new();
+        if let Some(limits) = value["rate_limits"].as_object() {
+            for (method, limit) in limits {
+                let limit = limit
+                    .as_u64()
+                    .ok_or_else(|| format!("rate limit of {} is not a number", method))?;
+                rate_limits.insert(method.clone(), limit as usize);
+            }
+        }
+        Ok(Grant {
+            permission: Permission::parse(permission)?,
+            rate_limits,
+        })
+    }
+}
+
+/// the tokens the daemon accepts
+pub struct Acl {
+    tokens: HashMap<String, Grant>,
+    anonymous: Option<Grant>,
+    // times of the recent calls to a rate limited method
+    calls: Mutex<HashMap<RateKey, VecDeque<Instant>>>,
+}
+
+impl Acl {
+    pub fn new(tokens: HashMap<String, Grant>, anonymous: Option<Grant>) -> Acl {
+        Acl {
+            tokens,
+            anonymous,
+            calls: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Acl, Box<dyn Error>> {
+        let config: Value = serde_json::from_str(text)?;
+        let mut tokens = HashMap::new();
+        if let Some(entries) = config["tokens"].as_object() {
+            for (token, grant) in entries {
+                tokens.insert(token.clone(), Grant::parse(grant)?);
+            }
+        }
+        let anonymous = match config.get("anonymous") {
+            Some(grant) => Some(Grant::parse(grant)?),
+            None => None,
+        };
+        Ok(Acl::new(tokens, anonymous))
+    }
+
+    pub fn load(path: &Path) -> Result<Acl, Box<dyn Error>> {
+        Acl::parse(&fs::read_to_string(path)?)
+    }
+
+    /// fails unless the `token`, `None` if the client sent none, may call `method` now
+    pub fn check(&self, token: Option<&str>, method: &str) -> Result<(), Box<dyn Error>> {
+        let grant = match token {
+            Some(token) => self
+                .tokens
+                .get(token)
+                .ok_or_else(|| rpc_error(ErrorCode::UNAUTHENTICATED, "unknown token"))?,
+            // whatever the anonymous grant, the daemon isn't stopped by whoever reaches it
+            None if method == "Shutdown" => {
+                let message = "Shutdown needs a token";
+                return Err(Box::new(rpc_error(ErrorCode::UNAUTHENTICATED, message)));
+            }
+            None => self
+                .anonymous
+                .as_ref()
+                .ok_or_else(|| rpc_error(ErrorCode::UNAUTHENTICATED, "the call needs a token"))?,
+        };
+        let required = required_permission(method).ok_or_else(|| {
+            let message = format!("{} is not granted by any permission", method);
+            rpc_error(ErrorCode::PERMISSION_DENIED, &message)
+        })?;
+        if grant.permission < required {
+            let message = format!("{} needs the {:?} permission", method, required);
+            return Err(Box::new(rpc_error(ErrorCode::PERMISSION_DENIED, &message)));
+        }
+
+        if let Some(&limit) = grant.rate_limits.get(method) {
+            let now = Instant::now();
+            let mut calls = self.calls.lock().unwrap();
+            let times = calls
+                .entry((token.map(str::to_owned), method.to_owned()))
+                .or_default();
+            while let Some(&time) = times.front() {
+                if now.duration_since(time) < RATE_WINDOW {
+                    break;
+                }
+                times.pop_front();
+            }
+            if times.len() >= limit {
+                let message = format!("{} is limited to {} calls a minute", method, limit);
+                return Err(Box::new(rpc_error(ErrorCode::RATE_LIMITED, &message)));
+            }
+            times.push_back(now);
+        }
+        Ok(())
+    }
+}
+
+/// the token of `Bearer <token>`, the value of the authorization metadata or header
+pub fn bearer_token(authorization: &str) -> Option<&str> {
+    let authorization = authorization.trim();
+    match (authorization.get(..7), authorization.get(7..)) {
+        (Some(scheme), Some(token)) if scheme.eq_ignore_ascii_case("bearer ") => Some(token.trim()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{bearer_token, required_permission, Acl};
+    use crate::walletrpc::{ErrorCode, ErrorDetails};
+
+    fn code(result: Result<(), Box<dyn std::error::Error>>) -> ErrorCode {
+        result.unwrap_err().downcast_ref::<ErrorDetails>().unwrap().code
+    }
+
+    #[test]
+    fn permissions_and_limits() {
+        let acl = Acl::parse(
+            r#"{
+                "anonymous": {"permission": "read"},
+                "tokens": {
+                    "report": {"permission": "read"},
+                    "payout": {"permission": "spend", "rate_limits": {"SendCoins": 2}}
+                }
+            }"#,
+        )
+        .unwrap();
+        acl.check(None, "WalletBalance").unwrap();
+        acl.check(Some("report"), "GetTransactions").unwrap();
+        assert_eq!(code(acl.check(Some("report"), "SendCoins")), ErrorCode::PERMISSION_DENIED);
+        assert_eq!(code(acl.check(Some("payout"), "Shutdown")), ErrorCode::PERMISSION_DENIED);
+        assert_eq!(code(acl.check(Some("stolen"), "GetInfo")), ErrorCode::UNAUTHENTICATED);
+
+        acl.check(Some("payout"), "SendCoins").unwrap();
+        acl.check(Some("payout"), "SendCoins").unwrap();
+        assert_eq!(code(acl.check(Some("payout"), "SendCoins")), ErrorCode::RATE_LIMITED);
+        // other methods have limits of their own
+        acl.check(Some("payout"), "MakeTx").unwrap();
+
+        assert!(Acl::parse(r#"{"tokens": {"t": {"permission": "root"}}}"#).is_err());
+        let closed = Acl::parse(r#"{"tokens": {}}"#).unwrap();
+        assert_eq!(code(closed.check(None, "GetInfo")), ErrorCode::UNAUTHENTICATED);
+        let open = Acl::parse(r#"{"anonymous": {"permission": "admin"}}"#).unwrap();
+        open.check(None, "UnlockWallet").unwrap();
+        assert_eq!(code(open.check(None, "Shutdown")), ErrorCode::UNAUTHENTICATED);
+        // a method nobody classified is closed even to admins
+        assert_eq!(code(open.check(None, "DropWallet")), ErrorCode::PERMISSION_DENIED);
+        assert_eq!(code(acl.check(Some("report"), "WatchScript")), ErrorCode::PERMISSION_DENIED);
+        let phrase = acl.check(Some("report"), "ValidateMnemonic");
+        assert_eq!(code(phrase), ErrorCode::PERMISSION_DENIED);
+
+        assert_eq!(bearer_token("Bearer report"), Some("report"));
+        assert_eq!(bearer_token("Basic dXNlcjpwYXNz"), None);
+    }
+
+    #[test]
+    fn every_method_classified() {
+        let methods = include_str!("walletrpc.proto")
+            .lines()
+            .map(str::trim)
+            .filter(|line| line.starts_with("rpc "))
+            .map(|line| line[4..].split('(').next().unwrap().trim());
+        for method in methods {
+            assert!(required_permission(method).is_some(), "{} needs a permission", method);
+        }
+    }
+}
@@ -0,0 +1,287 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Background sync
+//!
+//! The daemon follows the chain by itself. One loop takes the events of the chain and of
+//! the daemon in the order they come in:
+//!
+//! * every interval and on every block bitcoind announces on its `zmqpubrawblock` endpoint
+//!   the wallets are synced, so clients don't call SyncWithTip after each block
+//! * every transaction bitcoind announces on its `zmqpubrawtx` endpoint is handed to the
+//!   wallets right away, incoming payments show up unconfirmed without waiting for a sync
+//! * a backend restarted by the supervisor syncs the blocks missed while it was down
+//! * the shutdown of the daemon ends the loop
+//!
+//! The syncs run one at a time, a sync asked for during another one is skipped: the sync
+//! asks for the tip with every batch of blocks and the next one catches up. Transactions
+//! arriving during a sync are kept and handled after it
+//!
+use bitcoin::{Transaction, consensus::deserialize};
+use log::{debug, warn};
+use rand::Rng;
+
+use std::{
+    thread,
+    error::Error,
+    time::Duration,
+    collections::VecDeque,
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, Receiver, Sender},
+    },
+};
+
+const TOPIC_RAW_BLOCK: &[u8] = b"rawblock";
+const TOPIC_RAW_TX: &[u8] = b"rawtx";
+// a failed receive is made again after this long
+const ZMQ_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug)]
+pub struct AutoSyncConfig {
+    /// time between two syncs, `None` syncs on the blocks of `zmq_block_endpoint` only
+    pub interval: Option<Duration>,
+    /// up to this long is added to every interval at random,
+    /// daemons started together don't ask their backend at once
+    pub jitter: Duration,
+    /// bitcoind's zmqpubrawblock endpoint, each block announced there starts a sync
+    pub zmq_block_endpoint: Option<String>,
+    /// bitcoind's zmqpubrawtx endpoint, the transactions announced there are handed
+    /// to the wallets as they enter the mempool
+    pub zmq_tx_endpoint: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum ChainEvent {
+    /// the interval passed
+    Interval,
+    /// bitcoind announced a block
+    Block,
+    /// bitcoind announced a transaction, of its mempool or of a block it connected
+    Tx(Transaction),
+    /// a backend is back up, it is sent by the daemon
+    Reconnected,
+    /// the daemon stops, the loop ends once the events before it are handled
+    Shutdown,
+}
+
+impl ChainEvent {
+    fn is_sync(&self) -> bool {
+        match self {
+            ChainEvent::Interval | ChainEvent::Block | ChainEvent::Reconnected => true,
+            ChainEvent::Tx(_) | ChainEvent::Shutdown => false,
+        }
+    }
+}
+
+/// sends the events of the daemon to a running loop
+#[derive(Clone)]
+pub struct EventLoop(Arc<Mutex<Sender<ChainEvent>>>);
+
+impl EventLoop {
+    pub fn send(&self, event: ChainEvent) {
+        // the loop is gone only after a shutdown
+        let _ = self.0.lock().unwrap().send(event);
+    }
+}
+
+/// calls `handle` in a thread of its own with the events of `config` and the ones sent
+/// through the returned `EventLoop`. `Shutdown` isn't passed on, every other event is,
+/// the syncs skipped as described above
+pub fn start<F>(config: AutoSyncConfig, handle: F) -> Result<EventLoop, Box<dyn Error>>
+where
+    F: FnMut(&ChainEvent) + Send + 'static,
+{
+    let (events, received) = mpsc::channel();
+    if config.zmq_block_endpoint.is_some() || config.zmq_tx_endpoint.is_some() {
+        let context = zmq::Context::new();
+        let socket = context.socket(zmq::SUB)?;
+        if let Some(ref endpoint) = config.zmq_block_endpoint {
+            socket.connect(endpoint)?;
+            socket.set_subscribe(TOPIC_RAW_BLOCK)?;
+        }
+        if let Some(ref endpoint) = config.zmq_tx_endpoint {
+            // bitcoind publishes both on one endpoint by default
+            if config.zmq_block_endpoint.as_ref() != Some(endpoint) {
+                socket.connect(endpoint)?;
+            }
+            socket.set_subscribe(TOPIC_RAW_TX)?;
+        }
+        let events = events.clone();
+        thread::spawn(move || listen(context, socket, events));
+    }
+    if let Some(interval) = config.interval {
+        let jitter = config.jitter;
+        let events = events.clone();
+        thread::spawn(move || loop {
+            thread::sleep(jittered(interval, jitter));
+            if events.send(ChainEvent::Interval).is_err() {
+                return;
+            }
+        });
+    }
+    thread::spawn(move || run(received, handle));
+    Ok(EventLoop(Arc::new(Mutex::new(events))))
+}
+
+// the context has to live as long as the socket
+fn listen(_context: zmq::Context, socket: zmq::Socket, events: Sender<ChainEvent>) {
+    loop {
+        let event = match socket.recv_multipart(0) {
+            Ok(message) => match parse(&message) {
+                Ok(event) => event,
+                Err(e) => {
+                    warn!("failed to parse a notification of bitcoind: {}", e);
+                    continue;
+                }
+            },
+            Err(e) => {
+                warn!("failed to receive a notification of bitcoind: {}", e);
+                thread::sleep(ZMQ_RETRY_DELAY);
+                continue;
+            }
+        };
+        if events.send(event).is_err() {
+            return;
+        }
+    }
+}
+
+// topic, body and sequence number
+fn parse(message: &[Vec<u8>]) -> Result<ChainEvent, Box<dyn Error>> {
+    let topic = message.get(0).ok_or("an empty message")?;
+    if &topic[..] == TOPIC_RAW_BLOCK {
+        Ok(ChainEvent::Block)
+    } else if &topic[..] == TOPIC_RAW_TX {
+        let body = message.get(1).ok_or("a transaction without a body")?;
+        Ok(ChainEvent::Tx(deserialize(body)?))
+    } else {
+        Err(From::from(format!("unknown topic {}", String::from_utf8_lossy(topic))))
+    }
+}
+
+fn run<F: FnMut(&ChainEvent)>(events: Receiver<ChainEvent>, mut handle: F) {
+    let mut pending = VecDeque::new();
+    loop {
+        let event = match pending.pop_front() {
+            Some(event) => event,
+            None => match events.recv() {
+                Ok(event) => event,
+                Err(_) => return,
+            },
+        };
+        match event {
+            ChainEvent::Shutdown => return,
+            ChainEvent::Tx(_) => handle(&event),
+            _ => {
+                debug!("sync started by {:?}", event);
+                handle(&event);
+                let mut skipped = 0;
+                for event in events.try_iter() {
+                    if event.is_sync() {
+                        skipped += 1;
+                    } else {
+                        pending.push_back(event);
+                    }
+                }
+                if skipped > 0 {
+                    debug!("{} syncs were asked for during the sync, they are skipped", skipped);
+                }
+            }
+        }
+    }
+}
+
+fn jittered(interval: Duration, jitter: Duration) -> Duration {
+    let jitter_ms = jitter.as_millis() as u64;
+    interval + Duration::from_millis(rand::thread_rng().gen_range(0, jitter_ms + 1))
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::{Transaction, consensus::serialize};
+
+    use std::{sync::mpsc, time::Duration};
+
+    use super::{ChainEvent, run, parse, jittered, TOPIC_RAW_BLOCK, TOPIC_RAW_TX};
+
+    fn tx(lock_time: u32) -> Transaction {
+        Transaction {
+            version: 2,
+            lock_time,
+            input: Vec::new(),
+            output: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn skip_while_running() {
+        let (events, received) = mpsc::channel();
+        let mut syncs = 0;
+        // the first one starts a sync, the others are there by the time it is done
+        events.send(ChainEvent::Interval).unwrap();
+        events.send(ChainEvent::Block).unwrap();
+        events.send(ChainEvent::Reconnected).unwrap();
+        drop(events);
+        run(received, |_| syncs += 1);
+        assert_eq!(syncs, 1);
+    }
+
+    #[test]
+    fn keep_txs_and_stop() {
+        let (events, received) = mpsc::channel();
+        let mut handled = Vec::new();
+        events.send(ChainEvent::Block).unwrap();
+        events.send(ChainEvent::Tx(tx(1))).unwrap();
+        events.send(ChainEvent::Block).unwrap();
+        events.send(ChainEvent::Tx(tx(2))).unwrap();
+        events.send(ChainEvent::Shutdown).unwrap();
+        events.send(ChainEvent::Block).unwrap();
+        run(received, |event| {
+            handled.push(match event {
+                ChainEvent::Tx(tx) => tx.lock_time,
+                _ => 0,
+            })
+        });
+        assert_eq!(handled, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn parse_messages() {
+        let block = vec![TOPIC_RAW_BLOCK.to_vec(), vec![0; 80], vec![0; 4]];
+        assert!(match parse(&block).unwrap() {
+            ChainEvent::Block => true,
+            _ => false,
+        });
+        let raw = vec![TOPIC_RAW_TX.to_vec(), serialize(&tx(7)), vec![1, 0, 0, 0]];
+        assert!(match parse(&raw).unwrap() {
+            ChainEvent::Tx(tx) => tx.lock_time == 7,
+            _ => false,
+        });
+        assert!(parse(&[TOPIC_RAW_TX.to_vec(), vec![0xff]]).is_err());
+        assert!(parse(&[b"hashtx".to_vec(), vec![0; 32]]).is_err());
+    }
+
+    #[test]
+    fn jitter() {
+        let interval = Duration::from_secs(30);
+        let jitter = Duration::from_secs(5);
+        for _ in 0..100 {
+            let delay = jittered(interval, jitter);
+            assert!(delay >= interval && delay <= interval + jitter);
+        }
+        assert_eq!(jittered(interval, Duration::from_secs(0)), interval);
+    }
+}
@@ -18,14 +18,112 @@ extern crate tls_api_native_tls;
 extern crate wallet;
 extern crate rust_wallet_grpc;
 
-use structopt::clap::{Arg, App, SubCommand};
+use structopt::clap::{Arg, ArgGroup, App, ArgMatches, SubCommand};
 
 use wallet::account::AccountAddressType;
 use rust_wallet_grpc::{
     server::DEFAULT_WALLET_RPC_PORT,
     client::WalletClientWrapper,
+    walletrpc::{
+        OutPoint as RpcOutPoint, InputFilter as RpcInputFilter, SpendingPolicy,
+        ConnectionStatus, BackendKind, TxStatus, FiatValue, ExportFormat, QrFormat,
+        EncodeQrRequest_oneof_payload as QrPayload,
+    },
 };
 
+use std::{fs::{self, File}, io};
+
+// the parts of an animated QR code a scanner wrote to a file, one per line
+fn qr_parts(path: &str) -> Vec<String> {
+    fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(|line| line.trim().to_owned())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+fn out_point_arg<'a, 'b>(subcommand: App<'a, 'b>) -> App<'a, 'b> {
+    subcommand
+        .arg(Arg::with_name("txid")
+            .long("txid")
+            .takes_value(true)
+            .required(true)
+            .help("id of the transaction with the output"))
+        .arg(Arg::with_name("vout")
+            .long("vout")
+            .takes_value(true)
+            .required(true)
+            .help("index of the output"))
+}
+
+fn out_point(matches: &ArgMatches) -> RpcOutPoint {
+    // txids are displayed byte reversed
+    let mut txid = hex::decode(matches.value_of("txid").unwrap()).unwrap();
+    txid.reverse();
+
+    let mut op = RpcOutPoint::new();
+    op.set_txid(txid);
+    op.set_vout(matches.value_of("vout").unwrap().parse().unwrap());
+    op
+}
+
+fn input_filter(matches: &ArgMatches) -> Option<RpcInputFilter> {
+    if let Some(addr_type) = matches.value_of("from_addr_type") {
+        let mut input_filter = RpcInputFilter::new();
+        input_filter.set_addr_type(AccountAddressType::from(addr_type).into());
+        Some(input_filter)
+    } else if let Some(account) = matches.value_of("from_account") {
+        let mut input_filter = RpcInputFilter::new();
+        input_filter.set_account(account.parse().unwrap());
+        Some(input_filter)
+    } else if let Some(label) = matches.value_of("from_label") {
+        let mut input_filter = RpcInputFilter::new();
+        input_filter.set_label(label.to_owned());
+        Some(input_filter)
+    } else if matches.is_present("witness_only") {
+        let mut input_filter = RpcInputFilter::new();
+        input_filter.set_addr_type(AccountAddressType::P2WKH.into());
+        Some(input_filter)
+    } else {
+        None
+    }
+}
+
+// `None` leaves it to the privacy mode of the wallet
+fn privacy(matches: &ArgMatches) -> Option<bool> {
+    matches.value_of("privacy").map(|privacy| privacy == "on")
+}
+
+fn backend_name(backend: BackendKind) -> &'static str {
+    match backend {
+        BackendKind::FULL_NODE => "full node",
+        BackendKind::ELECTRUM => "electrum",
+    }
+}
+
+fn print_connection_status(status: &ConnectionStatus) {
+    println!("backend: {}", backend_name(status.backend));
+    println!("endpoint: {}", status.endpoint);
+    if status.last_success == 0 {
+        println!("last_success: never");
+    } else {
+        println!("last_success: {}", status.last_success);
+    }
+}
+
+// "<txid>:<vout>" with the txid as block explorers display it
+fn format_out_point(op: &RpcOutPoint) -> String {
+    let mut txid = op.txid.clone();
+    txid.reverse();
+    format!("{}:{}", hex::encode(txid), op.vout)
+}
+
+// "1234.50 usd at 9500.00"
+fn format_fiat_value(fiat_value: &FiatValue) -> String {
+    format!("{:.2} {} at {:.2}", fiat_value.value, fiat_value.currency, fiat_value.price)
+}
+
 fn main() {
     let default_wallet_rpc_port_str: &str = &DEFAULT_WALLET_RPC_PORT.to_string();
 
@@ -36,6 +134,16 @@ fn main() {
             .help("port of wallet's grpc server")
             .takes_value(true)
             .default_value(default_wallet_rpc_port_str))
+        .arg(Arg::with_name("wallet")
+            .long("wallet")
+            .help("name of the wallet, the default one if not specified")
+            .takes_value(true)
+            .default_value(""))
+        .arg(Arg::with_name("token")
+            .long("token")
+            .env("WALLET_TOKEN")
+            .help("token of the acl file of the daemon the requests are authenticated with")
+            .takes_value(true))
         .subcommand(SubCommand::with_name("newaddress")
             .arg(Arg::with_name("addr_type")
                 .long("addr_type")
@@ -43,12 +151,64 @@ fn main() {
                 .default_value("p2wkh")
                 .help("Bitcoin address type, should be one of p2pkh, p2shwh, p2wkh"))
             .about("Generate new bitcoin address, supported type are p2pkh, p2shwh, p2wkh"))
+        .subcommand(SubCommand::with_name("unusedaddress")
+            .arg(Arg::with_name("addr_type")
+                .long("addr_type")
+                .takes_value(true)
+                .default_value("p2wkh")
+                .help("Bitcoin address type, should be one of p2pkh, p2shwh, p2wkh"))
+            .about("Return the earliest address which never received funds, a new one if there is none"))
+        .subcommand(SubCommand::with_name("list_addresses")
+            .about("list all addresses of the wallet and whether they received funds"))
+        .subcommand(SubCommand::with_name("set_address_label")
+            .arg(Arg::with_name("address")
+                .long("address")
+                .takes_value(true)
+                .required(true)
+                .help("address of the wallet"))
+            .arg(Arg::with_name("label")
+                .long("label")
+                .takes_value(true)
+                .required(true)
+                .help("label of the address, an empty one removes it"))
+            .about("label an address, send_coins --from_label spends only the coins of the addresses with the label"))
         .subcommand(SubCommand::with_name("get_utxo_list")
             .about("return all utxos that wallet knows and can spend"))
         .subcommand(SubCommand::with_name("walletbalance")
             .about("return confirmed wallet balance"))
+        .subcommand(SubCommand::with_name("watch_script")
+            .arg(Arg::with_name("script")
+                .long("script")
+                .takes_value(true)
+                .required(true)
+                .help("hex encoded scriptPubKey to track"))
+            .arg(Arg::with_name("label")
+                .long("label")
+                .takes_value(true)
+                .default_value("")
+                .help("name the outputs and events of the script are reported with"))
+            .about("Track outputs of a script which doesn't belong to the wallet"))
+        .subcommand(SubCommand::with_name("list_watched_utxos")
+            .about("list outputs of the watched scripts, they are not part of the wallet balance"))
         .subcommand(SubCommand::with_name("sync_with_tip")
+            .arg(Arg::with_name("progress")
+                .long("progress")
+                .help("print the height scanned and the coins found as the sync goes"))
             .about("synchronize with current state of blockchain"))
+        .subcommand(SubCommand::with_name("rescan")
+            .arg(Arg::with_name("from_height")
+                .long("from_height")
+                .takes_value(true)
+                .required(true)
+                .help("height of the first block scanned again"))
+            .about("scan the blocks again, interrupting the client stops the rescan"))
+        .subcommand(SubCommand::with_name("cancel_operation")
+            .arg(Arg::with_name("id")
+                .long("id")
+                .takes_value(true)
+                .required(true)
+                .help("id of the operation printed by rescan"))
+            .about("stop a rescan or a sync after the blocks it is scanning"))
         .subcommand(SubCommand::with_name("send_coins")
             .arg(Arg::with_name("dest_addr")
                 .long("dest_addr")
@@ -67,13 +227,703 @@ fn main() {
             .arg(Arg::with_name("witness_only")
                 .long("witness_only")
                 .help("use only witness utxos to create transactions"))
+            .arg(Arg::with_name("from_addr_type")
+                .long("from_addr_type")
+                .takes_value(true)
+                .conflicts_with_all(&["witness_only", "from_account"])
+                .help("use only utxos of this address type, should be one of p2pkh, p2shwh, p2wkh"))
+            .arg(Arg::with_name("from_account")
+                .long("from_account")
+                .takes_value(true)
+                .conflicts_with("witness_only")
+                .help("use only utxos of the account with this index"))
+            .arg(Arg::with_name("from_label")
+                .long("from_label")
+                .takes_value(true)
+                .conflicts_with_all(&["witness_only", "from_addr_type", "from_account"])
+                .help("use only utxos of the addresses with this label"))
+            .arg(Arg::with_name("privacy")
+                .long("privacy")
+                .takes_value(true)
+                .possible_values(&["on", "off"])
+                .help("spend utxos of a single account and avoid change, overrides the privacy mode of the wallet"))
+            .arg(Arg::with_name("change_tolerance")
+                .long("change_tolerance")
+                .takes_value(true)
+                .help("satoshis above the amount and the fee left to the fee instead of change, overrides the tolerance of the wallet"))
+            .arg(Arg::with_name("fee_rate")
+                .long("fee_rate")
+                .takes_value(true)
+                .help("satoshi per virtual byte, overrides the fee rate of the wallet"))
+            .arg(Arg::with_name("change_addr_type")
+                .long("change_addr_type")
+                .takes_value(true)
+                .help("send the change to this address type, should be one of p2pkh, p2shwh, p2wkh"))
+            .arg(Arg::with_name("idempotency_key")
+                .long("idempotency_key")
+                .takes_value(true)
+                .help("unique key of the payment, running the command again with it doesn't pay twice"))
             .about("create, sign and probably broadcast transaction"))
+        .subcommand(SubCommand::with_name("prepare_tx")
+            .arg(Arg::with_name("dest_addr")
+                .long("dest_addr")
+                .takes_value(true)
+                .required(true)
+                .help("send coins to this bitcoin address"))
+            .arg(Arg::with_name("amt")
+                .long("amt")
+                .takes_value(true)
+                .required(true)
+                .help("amount in satoshis"))
+            .arg(Arg::with_name("from_addr_type")
+                .long("from_addr_type")
+                .takes_value(true)
+                .conflicts_with("from_account")
+                .help("use only utxos of this address type, should be one of p2pkh, p2shwh, p2wkh"))
+            .arg(Arg::with_name("from_account")
+                .long("from_account")
+                .takes_value(true)
+                .help("use only utxos of the account with this index"))
+            .arg(Arg::with_name("from_label")
+                .long("from_label")
+                .takes_value(true)
+                .conflicts_with_all(&["from_addr_type", "from_account"])
+                .help("use only utxos of the addresses with this label"))
+            .arg(Arg::with_name("privacy")
+                .long("privacy")
+                .takes_value(true)
+                .possible_values(&["on", "off"])
+                .help("spend utxos of a single account and avoid change, overrides the privacy mode of the wallet"))
+            .arg(Arg::with_name("change_addr_type")
+                .long("change_addr_type")
+                .takes_value(true)
+                .help("send the change to this address type, should be one of p2pkh, p2shwh, p2wkh"))
+            .about("Show inputs, outputs and fee of a transaction without signing it"))
+        .subcommand(SubCommand::with_name("commit_tx")
+            .arg(Arg::with_name("prepared_id")
+                .long("prepared_id")
+                .takes_value(true)
+                .required(true)
+                .help("prepared_id returned from prepare_tx command"))
+            .about("Sign and broadcast a transaction made by prepare_tx command"))
+        .subcommand(SubCommand::with_name("abandon_tx")
+            .arg(Arg::with_name("txid")
+                .long("txid")
+                .takes_value(true)
+                .required(true)
+                .help("id of the published transaction"))
+            .about("Stop rebroadcasting an unconfirmed transaction"))
+        .subcommand(SubCommand::with_name("accelerate_tx")
+            .arg(Arg::with_name("txid")
+                .long("txid")
+                .takes_value(true)
+                .required(true)
+                .help("id of the unconfirmed transaction"))
+            .arg(Arg::with_name("fee_rate")
+                .long("fee_rate")
+                .takes_value(true)
+                .required(true)
+                .help("fee of the transaction and its child together, in satoshi per virtual byte"))
+            .about("Spend outputs of an unconfirmed transaction with a high fee to get it confirmed"))
+        .subcommand(SubCommand::with_name("cancel_tx")
+            .arg(Arg::with_name("txid")
+                .long("txid")
+                .takes_value(true)
+                .required(true)
+                .help("id of the unconfirmed transaction"))
+            .arg(Arg::with_name("fee_rate")
+                .long("fee_rate")
+                .takes_value(true)
+                .required(true)
+                .help("fee of the replacement in satoshi per virtual byte"))
+            .about("Replace an unconfirmed transaction by one paying its coins back to the wallet"))
+        .subcommand(SubCommand::with_name("estimate_tx_size")
+            .arg(Arg::with_name("p2pkh")
+                .long("p2pkh")
+                .takes_value(true)
+                .default_value("0")
+                .help("legacy coins spent"))
+            .arg(Arg::with_name("p2shwh")
+                .long("p2shwh")
+                .takes_value(true)
+                .default_value("0")
+                .help("P2SH-wrapped segwit coins spent"))
+            .arg(Arg::with_name("p2wkh")
+                .long("p2wkh")
+                .takes_value(true)
+                .default_value("0")
+                .help("native segwit coins spent"))
+            .arg(Arg::with_name("p2tr")
+                .long("p2tr")
+                .takes_value(true)
+                .default_value("0")
+                .help("taproot coins spent by the key path"))
+            .arg(Arg::with_name("outputs")
+                .long("outputs")
+                .takes_value(true)
+                .default_value("0")
+                .help("outputs of the transaction, change included"))
+            .arg(Arg::with_name("fee_rate")
+                .long("fee_rate")
+                .takes_value(true)
+                .default_value("0")
+                .help("satoshi per virtual byte the fee is quoted at"))
+            .about("Print the weight, virtual size and fee of a signed transaction of the given inputs and outputs"))
+        .subcommand(SubCommand::with_name("payment_code")
+            .about("Return the BIP47 payment code of the wallet, it can be published instead of an address"))
+        .subcommand(SubCommand::with_name("notify_payment_code")
+            .arg(Arg::with_name("payment_code")
+                .long("payment_code")
+                .takes_value(true)
+                .required(true)
+                .help("payment code of the recipient"))
+            .about("Publish a notification transaction telling the payment code of the wallet to the recipient"))
+        .subcommand(SubCommand::with_name("payment_code_address")
+            .arg(Arg::with_name("payment_code")
+                .long("payment_code")
+                .takes_value(true)
+                .required(true)
+                .help("payment code of the recipient, it has to be notified"))
+            .about("Return the next address paying to a payment code, every call gives a new one"))
+        .subcommand(SubCommand::with_name("sweep_payment_codes")
+            .arg(Arg::with_name("addr_type")
+                .long("addr_type")
+                .takes_value(true)
+                .default_value("p2wkh")
+                .help("Bitcoin address type, should be one of p2pkh, p2shwh, p2wkh"))
+            .about("Move the coins received through payment codes into the wallet"))
+        .subcommand(SubCommand::with_name("silent_payment_address")
+            .about("Return the BIP352 silent payment address of the wallet"))
+        .subcommand(SubCommand::with_name("list_silent_payment_utxos")
+            .about("list outputs received by silent payments, they are not part of the wallet balance"))
+        .subcommand(SubCommand::with_name("sweep_silent_payments")
+            .arg(Arg::with_name("addr_type")
+                .long("addr_type")
+                .takes_value(true)
+                .default_value("p2wkh")
+                .help("Bitcoin address type, should be one of p2pkh, p2shwh, p2wkh"))
+            .about("Move the coins received by silent payments into the wallet"))
+        .subcommand(SubCommand::with_name("prove_reserves")
+            .arg(Arg::with_name("message")
+                .long("message")
+                .takes_value(true)
+                .required(true)
+                .help("message the proof commits to, e.g. the name of the audit"))
+            .about("Return a BIP127 proof of reserves of the confirmed coins, it can't be published"))
+        .subcommand(SubCommand::with_name("spending_policy")
+            .about("Show the spending policy and the satoshi spent within the last 24 hours"))
+        .subcommand(SubCommand::with_name("set_spending_policy")
+            .arg(Arg::with_name("daily_limit")
+                .long("daily_limit")
+                .takes_value(true)
+                .default_value("0")
+                .help("satoshi paid within 24 hours, 0 for no limit"))
+            .arg(Arg::with_name("max_per_tx")
+                .long("max_per_tx")
+                .takes_value(true)
+                .default_value("0")
+                .help("satoshi paid by a single transaction, 0 for no limit"))
+            .arg(Arg::with_name("whitelist")
+                .long("whitelist")
+                .takes_value(true)
+                .use_delimiter(true)
+                .help("comma separated addresses, the only destinations allowed"))
+            .arg(Arg::with_name("blacklist")
+                .long("blacklist")
+                .takes_value(true)
+                .use_delimiter(true)
+                .help("comma separated addresses which are never paid"))
+            .arg(Arg::with_name("approval_threshold")
+                .long("approval_threshold")
+                .takes_value(true)
+                .default_value("0")
+                .help("satoshi above which send_coins waits for approve_spend, 0 for none"))
+            .about("Replace the limits enforced on send_coins and commit_tx"))
+        .subcommand(SubCommand::with_name("pending_spends")
+            .about("List the payments of send_coins waiting for approval"))
+        .subcommand(SubCommand::with_name("approve_spend")
+            .arg(Arg::with_name("id")
+                .long("id")
+                .takes_value(true)
+                .required(true)
+                .help("approval_id returned from send_coins"))
+            .about("Sign and publish a payment waiting for approval"))
+        .subcommand(SubCommand::with_name("reject_spend")
+            .arg(Arg::with_name("id")
+                .long("id")
+                .takes_value(true)
+                .required(true)
+                .help("approval_id returned from send_coins"))
+            .about("Drop a payment waiting for approval"))
+        .subcommand(SubCommand::with_name("audit_log")
+            .arg(Arg::with_name("start")
+                .long("start")
+                .takes_value(true)
+                .default_value("0")
+                .help("sequence number of the first entry"))
+            .arg(Arg::with_name("limit")
+                .long("limit")
+                .takes_value(true)
+                .default_value("100")
+                .help("number of entries, at most 1000"))
+            .about("Show the audit log of address derivations, signed and published transactions"))
+        .subcommand(SubCommand::with_name("fund_channel")
+            .arg(Arg::with_name("witness_script")
+                .long("witness_script")
+                .takes_value(true)
+                .required(true)
+                .help("hex encoded script the P2WSH funding output pays to"))
+            .arg(Arg::with_name("amt")
+                .long("amt")
+                .takes_value(true)
+                .required(true)
+                .help("capacity of the channel in satoshi"))
+            .arg(Arg::with_name("fee_rate")
+                .long("fee_rate")
+                .takes_value(true)
+                .required(true)
+                .help("fee in satoshi per virtual byte"))
+            .about("Build an unsigned channel funding transaction and lock its coins"))
+        .subcommand(SubCommand::with_name("release_funding")
+            .arg(Arg::with_name("funding_id")
+                .long("funding_id")
+                .takes_value(true)
+                .required(true)
+                .help("funding_id returned from fund_channel command"))
+            .about("Drop a channel funding and unlock its coins"))
+        .subcommand(SubCommand::with_name("publish_funding")
+            .arg(Arg::with_name("funding_id")
+                .long("funding_id")
+                .takes_value(true)
+                .required(true)
+                .help("funding_id returned from fund_channel command"))
+            .about("Sign and broadcast a channel funding transaction"))
         .subcommand(SubCommand::with_name("unlock_coins")
             .arg(Arg::with_name("lock_id")
                 .long("lock_id")
                 .takes_value(true)
                 .help("lock_id returns from send_coins command"))
             .about("Unlock previously locked coins. We can lock coins with send_coins command."))
+        .subcommand(out_point_arg(SubCommand::with_name("freeze_utxo"))
+            .about("Exclude the output from coin selection until it is unfrozen"))
+        .subcommand(out_point_arg(SubCommand::with_name("unfreeze_utxo"))
+            .about("Return a frozen output to coin selection"))
+        .subcommand(SubCommand::with_name("export_backup")
+            .arg(Arg::with_name("path")
+                .long("path")
+                .takes_value(true)
+                .required(true)
+                .help("path of the backup file on the wallet server"))
+            .arg(Arg::with_name("passphrase")
+                .long("passphrase")
+                .takes_value(true)
+                .required(true)
+                .help("passphrase to encrypt the backup with"))
+            .about("Write an encrypted backup of the wallet"))
+        .subcommand(SubCommand::with_name("export_electrum_wallet")
+            .arg(Arg::with_name("path")
+                .long("path")
+                .takes_value(true)
+                .required(true)
+                .help("directory on the wallet server"))
+            .about("Write watch-only Electrum wallet files of the accounts"))
+        .subcommand(SubCommand::with_name("restore_backup")
+            .arg(Arg::with_name("path")
+                .long("path")
+                .takes_value(true)
+                .required(true)
+                .help("path of the backup file on the wallet server"))
+            .arg(Arg::with_name("passphrase")
+                .long("passphrase")
+                .takes_value(true)
+                .required(true)
+                .help("passphrase the backup is encrypted with"))
+            .arg(Arg::with_name("wallet_passphrase")
+                .long("wallet_passphrase")
+                .takes_value(true)
+                .default_value("")
+                .help("passphrase of the backed up wallet, default one if empty"))
+            .arg(Arg::with_name("salt")
+                .long("salt")
+                .takes_value(true)
+                .default_value("")
+                .help("salt of the backed up wallet, default one if empty"))
+            .about("Replace the wallet with an encrypted backup"))
+        .subcommand(SubCommand::with_name("export_state_snapshot")
+            .arg(Arg::with_name("path")
+                .long("path")
+                .takes_value(true)
+                .required(true)
+                .help("path of the snapshot file on the wallet server"))
+            .about("Write the coins, keys and tip of the wallet, it holds no private key"))
+        .subcommand(SubCommand::with_name("import_state_snapshot")
+            .arg(Arg::with_name("path")
+                .long("path")
+                .takes_value(true)
+                .required(true)
+                .help("path of the snapshot file on the wallet server"))
+            .about("Sync on from a snapshot of a wallet of the same seed instead of rescanning"))
+        .subcommand(SubCommand::with_name("check")
+            .arg(Arg::with_name("repair")
+                .long("repair")
+                .help("derive the missing entries again and drop the orphaned lock groups"))
+            .about("Cross-check the stored keys, addresses, coins and locks of the wallet"))
+        .subcommand(SubCommand::with_name("create_wallet")
+            .arg(Arg::with_name("passphrase")
+                .long("passphrase")
+                .takes_value(true)
+                .default_value("")
+                .help("passphrase of the new wallet, default one if empty"))
+            .arg(Arg::with_name("salt")
+                .long("salt")
+                .takes_value(true)
+                .default_value("")
+                .help("salt of the new wallet, default one if empty"))
+            .arg(Arg::with_name("mnemonic_words")
+                .long("mnemonic_words")
+                .takes_value(true)
+                .default_value("24")
+                .help("number of words in the mnemonic, should be one of 12, 18, 24"))
+            .arg(Arg::with_name("account_path")
+                .long("account_path")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("derivation path of an account, e.g. p2wkh=m/84'/0'/5'"))
+            .arg(Arg::with_name("watch_only_keys")
+                .long("watch_only_keys")
+                .takes_value(true)
+                .help("file written by wallet-signer export_keys, makes a watch-only wallet"))
+            .about("Create a new wallet named with --wallet and print its mnemonic"))
+        .subcommand(SubCommand::with_name("validate_mnemonic")
+            .arg(Arg::with_name("mnemonic")
+                .long("mnemonic")
+                .takes_value(true)
+                .required(true)
+                .help("words of the mnemonic separated by spaces"))
+            .about("Tell which word of a mnemonic is wrong before recovering a wallet from it"))
+        .subcommand(SubCommand::with_name("open_wallet")
+            .arg(Arg::with_name("passphrase")
+                .long("passphrase")
+                .takes_value(true)
+                .default_value("")
+                .help("passphrase of the wallet, default one if empty"))
+            .arg(Arg::with_name("salt")
+                .long("salt")
+                .takes_value(true)
+                .default_value("")
+                .help("salt of the wallet, default one if empty"))
+            .about("Open an existing wallet named with --wallet"))
+        .subcommand(SubCommand::with_name("list_wallets")
+            .about("list the wallets served by the server"))
+        .subcommand(SubCommand::with_name("lock_wallet")
+            .about("wipe the master key from the memory of the server, signing needs unlock_wallet"))
+        .subcommand(SubCommand::with_name("unlock_wallet")
+            .arg(Arg::with_name("passphrase")
+                .long("passphrase")
+                .takes_value(true)
+                .default_value("")
+                .help("passphrase of the wallet, default one if empty"))
+            .arg(Arg::with_name("salt")
+                .long("salt")
+                .takes_value(true)
+                .default_value("")
+                .help("salt of the wallet, default one if empty"))
+            .arg(Arg::with_name("timeout")
+                .long("timeout")
+                .takes_value(true)
+                .help("seconds until the wallet locks again, it stays unlocked without it"))
+            .about("decrypt the master key of a locked wallet"))
+        .subcommand(SubCommand::with_name("subscribe_events")
+            .about("print events of the wallet as they happen"))
+        .subcommand(SubCommand::with_name("get_info")
+            .about("print the API version of the server"))
+        .subcommand(SubCommand::with_name("set_log_level")
+            .arg(Arg::with_name("filter")
+                .takes_value(true)
+                .help("a level or the directives of RUST_LOG, e.g. info,wallet::default=debug"))
+            .about("change the log filter of the server, print the previous one"))
+        .subcommand(SubCommand::with_name("reconnect")
+            .arg(Arg::with_name("address")
+                .long("address")
+                .takes_value(true)
+                .default_value("")
+                .help("host:port of the backend to switch to, the current one if empty"))
+            .arg(Arg::with_name("user")
+                .long("user")
+                .takes_value(true)
+                .default_value("")
+                .help("rpc user of bitcoind, the current one if empty"))
+            .arg(Arg::with_name("password")
+                .long("password")
+                .takes_value(true)
+                .default_value("")
+                .help("rpc password of bitcoind, the current one if empty"))
+            .about("reconnect the wallet to its backend or switch to another one"))
+        .subcommand(SubCommand::with_name("switch_backend")
+            .arg(Arg::with_name("backend")
+                .long("backend")
+                .takes_value(true)
+                .required(true)
+                .possible_values(&["full_node", "electrum"])
+                .help("kind of the backend"))
+            .arg(Arg::with_name("address")
+                .long("address")
+                .takes_value(true)
+                .required(true)
+                .help("host:port of the backend"))
+            .arg(Arg::with_name("user")
+                .long("user")
+                .takes_value(true)
+                .default_value("")
+                .help("rpc user of bitcoind, the one of the daemon if empty"))
+            .arg(Arg::with_name("password")
+                .long("password")
+                .takes_value(true)
+                .default_value("")
+                .help("rpc password of bitcoind, the one of the daemon if empty"))
+            .about("move the wallet to a backend of another kind without restarting the daemon"))
+        .subcommand(SubCommand::with_name("connection_status")
+            .about("print the backend of the wallet and when it answered last"))
+        .subcommand(SubCommand::with_name("get_transactions")
+            .about("print the transaction history of the wallet and the fees it paid"))
+        .subcommand(SubCommand::with_name("get_account_stats")
+            .about("print what the accounts and their addresses received and sent"))
+        .subcommand(SubCommand::with_name("export_history")
+            .arg(Arg::with_name("format")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["csv", "json"])
+                .default_value("csv")
+                .help("format of the export"))
+            .arg(Arg::with_name("path")
+                .long("path")
+                .takes_value(true)
+                .help("file to write, the export is printed without one"))
+            .about("export the transaction history for accounting"))
+        .subcommand(SubCommand::with_name("new_timelocked_address")
+            .arg(Arg::with_name("blocks")
+                .long("blocks")
+                .takes_value(true)
+                .conflicts_with("height")
+                .required_unless("height")
+                .help("blocks after the confirmation the coins stay locked for"))
+            .arg(Arg::with_name("height")
+                .long("height")
+                .takes_value(true)
+                .help("block height the coins stay locked until"))
+            .about("return an address whose coins can't be spent before the lock expires"))
+        .subcommand(SubCommand::with_name("sweep_address")
+            .arg(Arg::with_name("address")
+                .long("address")
+                .takes_value(true)
+                .required(true)
+                .help("address of the wallet to empty"))
+            .arg(Arg::with_name("dest_addr")
+                .long("dest_addr")
+                .takes_value(true)
+                .required(true)
+                .help("destination address"))
+            .arg(Arg::with_name("fee_rate")
+                .long("fee_rate")
+                .takes_value(true)
+                .required(true)
+                .help("fee in satoshi per virtual byte"))
+            .about("send all coins of one address, e.g. an exposed one, to another address"))
+        .subcommand(SubCommand::with_name("set_vault")
+            .arg(Arg::with_name("recovery_pk")
+                .long("recovery_pk")
+                .takes_value(true)
+                .required(true)
+                .help("hex encoded public key of the offline recovery key"))
+            .arg(Arg::with_name("delay")
+                .long("delay")
+                .takes_value(true)
+                .required(true)
+                .help("blocks after the confirmation the hot key can spend a deposit"))
+            .about("set the keys and delay of the vault addresses created from now on"))
+        .subcommand(SubCommand::with_name("new_vault_address")
+            .about("return a vault address, its coins are spent by unvault after the delay"))
+        .subcommand(SubCommand::with_name("unvault")
+            .arg(Arg::with_name("dest_addr")
+                .long("dest_addr")
+                .takes_value(true)
+                .required(true)
+                .help("destination address"))
+            .arg(Arg::with_name("fee_rate")
+                .long("fee_rate")
+                .takes_value(true)
+                .required(true)
+                .help("fee in satoshi per virtual byte"))
+            .about("move the vault deposits confirmed for the delay to an address"))
+        .subcommand(SubCommand::with_name("descriptors")
+            .about("print the output descriptors of the accounts for other wallets"))
+        .subcommand(SubCommand::with_name("import_descriptors")
+            .about("print the argument of bitcoin-cli importdescriptors to watch the wallet"))
+        .subcommand(SubCommand::with_name("migrate_derivation")
+            .about("move the coins of an old wallet to the standard paths other wallets use"))
+        .subcommand(SubCommand::with_name("script_account_xpub")
+            .arg(Arg::with_name("origin")
+                .long("origin")
+                .help("prefix the key with its origin, [fingerprint/path], for cosigners"))
+            .about("print the extended key of the wallet to put into policies"))
+        .subcommand(SubCommand::with_name("register_policy")
+            .arg(Arg::with_name("name")
+                .long("name")
+                .takes_value(true)
+                .required(true)
+                .help("name of the script account"))
+            .arg(Arg::with_name("policy")
+                .long("policy")
+                .takes_value(true)
+                .required(true)
+                .help("e.g. or(pk(xpub...),and(pk(02...),older(1000)))"))
+            .about("add a script account whose coins the policy spends"))
+        .subcommand(SubCommand::with_name("new_script_address")
+            .arg(Arg::with_name("name")
+                .long("name")
+                .takes_value(true)
+                .required(true)
+                .help("name of the script account"))
+            .about("return the next address of a script account"))
+        .subcommand(SubCommand::with_name("spend_script_coins")
+            .arg(Arg::with_name("name")
+                .long("name")
+                .takes_value(true)
+                .required(true)
+                .help("name of the script account"))
+            .arg(Arg::with_name("dest_addr")
+                .long("dest_addr")
+                .takes_value(true)
+                .required(true)
+                .help("destination address"))
+            .arg(Arg::with_name("fee_rate")
+                .long("fee_rate")
+                .takes_value(true)
+                .required(true)
+                .help("fee in satoshi per virtual byte"))
+            .about("spend the coins of a script account, print the PSBT for the cosigners"))
+        .subcommand(SubCommand::with_name("finalize_script_psbt")
+            .arg(Arg::with_name("psbt")
+                .long("psbt")
+                .takes_value(true)
+                .required(true)
+                .help("hex of the PSBT signed by the cosigners"))
+            .about("publish the transaction of a PSBT of spend_script_coins"))
+        .subcommand(SubCommand::with_name("create_unsigned_tx")
+            .arg(Arg::with_name("dest_addr")
+                .long("dest_addr")
+                .takes_value(true)
+                .required(true)
+                .help("send coins to this bitcoin address"))
+            .arg(Arg::with_name("amt")
+                .long("amt")
+                .takes_value(true)
+                .required(true)
+                .help("amount in satoshis"))
+            .arg(Arg::with_name("from_addr_type")
+                .long("from_addr_type")
+                .takes_value(true)
+                .conflicts_with("from_account")
+                .help("use only utxos of this address type, should be one of p2pkh, p2shwh, p2wkh"))
+            .arg(Arg::with_name("from_account")
+                .long("from_account")
+                .takes_value(true)
+                .help("use only utxos of the account with this index"))
+            .arg(Arg::with_name("from_label")
+                .long("from_label")
+                .takes_value(true)
+                .conflicts_with_all(&["from_addr_type", "from_account"])
+                .help("use only utxos of the addresses with this label"))
+            .arg(Arg::with_name("privacy")
+                .long("privacy")
+                .takes_value(true)
+                .possible_values(&["on", "off"])
+                .help("spend utxos of a single account and avoid change, overrides the privacy mode of the wallet"))
+            .arg(Arg::with_name("change_addr_type")
+                .long("change_addr_type")
+                .takes_value(true)
+                .help("send the change to this address type, should be one of p2pkh, p2shwh, p2wkh"))
+            .arg(Arg::with_name("out")
+                .long("out")
+                .takes_value(true)
+                .required(true)
+                .help("file the PSBT is written to, for wallet-signer sign"))
+            .about("queue a payment of a watch-only wallet for the offline signer"))
+        .subcommand(SubCommand::with_name("list_unsigned_txs")
+            .about("list the payments waiting for the offline signer"))
+        .subcommand(SubCommand::with_name("broadcast_signed")
+            .arg(Arg::with_name("id")
+                .long("id")
+                .takes_value(true)
+                .required(true)
+                .help("id returned from create_unsigned_tx command"))
+            .arg(Arg::with_name("psbt")
+                .long("psbt")
+                .takes_value(true)
+                .conflicts_with("qr_parts")
+                .required_unless("qr_parts")
+                .help("file of the PSBT signed by wallet-signer sign"))
+            .arg(Arg::with_name("qr_parts")
+                .long("qr_parts")
+                .takes_value(true)
+                .help("file of the QR code parts of the signed PSBT, one per line"))
+            .about("finalize a payment signed by the offline signer and broadcast it"))
+        .subcommand(SubCommand::with_name("cancel_unsigned_tx")
+            .arg(Arg::with_name("id")
+                .long("id")
+                .takes_value(true)
+                .required(true)
+                .help("id returned from create_unsigned_tx command"))
+            .about("drop a payment waiting for the offline signer and unlock its coins"))
+        .subcommand(SubCommand::with_name("encode_qr")
+            .arg(Arg::with_name("format")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["ur", "bbqr"])
+                .default_value("ur")
+                .help("format of the parts"))
+            .arg(Arg::with_name("max_fragment_len")
+                .long("max_fragment_len")
+                .takes_value(true)
+                .default_value("0")
+                .help("payload bytes of a part at most, 0 for the default of the server"))
+            .arg(Arg::with_name("extra_parts")
+                .long("extra_parts")
+                .takes_value(true)
+                .default_value("0")
+                .help("fountain codes following the parts of a UR"))
+            .arg(Arg::with_name("unsigned_tx_id")
+                .long("unsigned_tx_id")
+                .takes_value(true)
+                .help("id returned from create_unsigned_tx command"))
+            .arg(Arg::with_name("psbt")
+                .long("psbt")
+                .takes_value(true)
+                .help("file of a PSBT"))
+            .arg(Arg::with_name("descriptor_index")
+                .long("descriptor_index")
+                .takes_value(true)
+                .help("index of a descriptor printed by get_descriptors"))
+            .arg(Arg::with_name("address")
+                .long("address")
+                .takes_value(true)
+                .help("P2PKH, P2SH or P2WPKH address"))
+            .group(ArgGroup::with_name("payload")
+                .args(&["unsigned_tx_id", "psbt", "descriptor_index", "address"])
+                .required(true))
+            .about("print the parts of an animated QR code, one per line"))
+        .subcommand(SubCommand::with_name("decode_qr")
+            .arg(Arg::with_name("parts")
+                .long("parts")
+                .takes_value(true)
+                .required(true)
+                .help("file of the scanned parts, one per line"))
+            .arg(Arg::with_name("out")
+                .long("out")
+                .takes_value(true)
+                .help("file a PSBT is written to, its hex is printed without one"))
+            .about("print the PSBT, descriptor or address of the parts of an animated QR code"))
         .subcommand(SubCommand::with_name("shutdown")
             .about("shutdown the wallet server"))
         .get_matches();
@@ -83,7 +933,11 @@ fn main() {
         .unwrap()
         .parse()
         .unwrap();
-    let client = WalletClientWrapper::new(wallet_rpc_port);
+    let wallet = matches.value_of("wallet").unwrap().to_string();
+    let mut client = WalletClientWrapper::new(wallet_rpc_port).with_wallet(wallet.clone());
+    if let Some(token) = matches.value_of("token") {
+        client = client.with_token(token.to_owned());
+    }
 
     if let Some(matches) = matches.subcommand_matches("newaddress") {
         let addr_type = matches.value_of("addr_type").unwrap();
@@ -93,18 +947,133 @@ fn main() {
         println!("{}", addr);
     }
 
+    if let Some(matches) = matches.subcommand_matches("unusedaddress") {
+        let addr_type = matches.value_of("addr_type").unwrap();
+        let addr_type: AccountAddressType = addr_type.into();
+
+        let addr = client.get_unused_address(addr_type.into());
+        println!("{}", addr);
+    }
+
+    if let Some(_matches) = matches.subcommand_matches("list_addresses") {
+        for info in client.list_addresses() {
+            println!(
+                "{} {:?} {} {} {} [{}{}] {}",
+                info.address,
+                info.addr_type,
+                if info.change { "change" } else { "external" },
+                info.index,
+                if info.used { "used" } else { "unused" },
+                info.master_fingerprint,
+                info.derivation_path.replacen("m", "", 1),
+                info.label,
+            );
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("set_address_label") {
+        let address = matches.value_of("address").unwrap().to_owned();
+        let label = matches.value_of("label").unwrap().to_owned();
+        client.set_address_label(address, label).unwrap();
+    }
+
     if let Some(_matches) = matches.subcommand_matches("get_utxo_list") {
         let utxo_list = client.get_utxo_list();
         println!("{:?}", utxo_list);
+        for utxo in client.get_timelocked_utxo_list() {
+            let state = if utxo.mature { "mature" } else { "locked" };
+            println!(
+                "timelocked {} {} {} at height {}",
+                format_out_point(utxo.get_out_point()),
+                utxo.value,
+                state,
+                utxo.mature_height,
+            );
+        }
+        for utxo in client.get_script_utxo_list() {
+            println!(
+                "script {} {} {} #{} at height {}",
+                format_out_point(utxo.get_out_point()),
+                utxo.value,
+                utxo.account,
+                utxo.index,
+                utxo.height,
+            );
+        }
+        for utxo in client.get_vault_utxo_list() {
+            let state = if utxo.mature { "mature" } else { "delayed" };
+            println!(
+                "vault {} {} {} at height {}",
+                format_out_point(utxo.get_out_point()),
+                utxo.value,
+                state,
+                utxo.mature_height,
+            );
+        }
     }
 
     if let Some(_matches) = matches.subcommand_matches("walletbalance") {
         let balance = client.wallet_balance();
         println!("{:?}", balance);
+        if let Some(fiat_balance) = client.fiat_balance().unwrap() {
+            println!("{}", format_fiat_value(&fiat_balance));
+        }
+        for entry in client.balances().unwrap() {
+            println!("{}: {}", format!("{:?}", entry.category).to_lowercase(), entry.value);
+        }
     }
 
-    if let Some(_matches) = matches.subcommand_matches("sync_with_tip") {
-        client.sync_with_tip();
+    if let Some(matches) = matches.subcommand_matches("watch_script") {
+        let script = hex::decode(matches.value_of("script").unwrap()).unwrap();
+        let label = matches.value_of("label").unwrap().to_owned();
+        client.watch_script(script, label).unwrap();
+    }
+
+    if let Some(_matches) = matches.subcommand_matches("list_watched_utxos") {
+        for utxo in client.list_watched_utxos().unwrap() {
+            let mut txid = utxo.get_out_point().txid.clone();
+            txid.reverse();
+            println!(
+                "{}:{} {} {}",
+                hex::encode(txid),
+                utxo.get_out_point().vout,
+                utxo.value,
+                utxo.label,
+            );
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("sync_with_tip") {
+        if matches.is_present("progress") {
+            for progress in client.sync_with_tip_stream() {
+                let progress = progress.unwrap();
+                println!(
+                    "{}/{} utxos_found: {}",
+                    progress.height_scanned, progress.tip_height, progress.utxos_found,
+                );
+            }
+        } else {
+            client.sync_with_tip();
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("rescan") {
+        let from_height = matches.value_of("from_height").unwrap().parse().unwrap();
+        for progress in client.rescan_stream(from_height) {
+            let progress = progress.unwrap();
+            println!(
+                "operation {}: {}/{} utxos_found: {}",
+                progress.operation_id,
+                progress.height_scanned,
+                progress.tip_height,
+                progress.utxos_found,
+            );
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("cancel_operation") {
+        let id = matches.value_of("id").unwrap().parse().unwrap();
+        client.cancel_operation(id).unwrap();
     }
 
     if let Some(matches) = matches.subcommand_matches("send_coins") {
@@ -112,9 +1081,237 @@ fn main() {
         let amt: u64 = matches.value_of("amt").unwrap().parse().unwrap();
         let submit = matches.is_present("submit");
         let lock_coins = matches.is_present("lock_coins");
-        client
-            .send_coins(dest_addr.to_string(), amt, submit, lock_coins)
+        let input_filter = input_filter(matches);
+        let change_addr_type = matches
+            .value_of("change_addr_type")
+            .map(|addr_type| AccountAddressType::from(addr_type).into());
+        let idempotency_key = matches.value_of("idempotency_key").map(str::to_owned);
+        let change_tolerance = matches
+            .value_of("change_tolerance")
+            .map(|tolerance| tolerance.parse().unwrap());
+        let fee_rate = matches.value_of("fee_rate").map(|fee_rate| fee_rate.parse().unwrap());
+        let resp = client
+            .send_coins(
+                dest_addr.to_string(),
+                amt,
+                submit,
+                lock_coins,
+                input_filter,
+                change_addr_type,
+                idempotency_key,
+                privacy(matches),
+                change_tolerance,
+                fee_rate,
+            )
             .unwrap();
+        if resp.approval_id != 0 {
+            println!("approval_id: {}", resp.approval_id);
+        } else {
+            println!("txid: {}", resp.txid);
+            println!("fee: {}", resp.fee);
+            if resp.has_change_outpoint() {
+                println!("change: {}", format_out_point(resp.get_change_outpoint()));
+            }
+            for op in resp.get_selected_inputs() {
+                println!("input: {}", format_out_point(op));
+            }
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("prepare_tx") {
+        let dest_addr = matches.value_of("dest_addr").unwrap();
+        let amt: u64 = matches.value_of("amt").unwrap().parse().unwrap();
+        let change_addr_type = matches
+            .value_of("change_addr_type")
+            .map(|addr_type| AccountAddressType::from(addr_type).into());
+        let prepared = client
+            .prepare_transaction(
+                dest_addr.to_string(),
+                amt,
+                input_filter(matches),
+                change_addr_type,
+                privacy(matches),
+            )
+            .unwrap();
+
+        println!("prepared_id: {}", prepared.prepared_id);
+        for utxo in prepared.get_inputs() {
+            let mut txid = utxo.get_out_point().txid.clone();
+            txid.reverse();
+            println!("input: {}:{} {}", hex::encode(txid), utxo.get_out_point().vout, utxo.value);
+        }
+        for output in prepared.get_outputs() {
+            let change = if output.change { " (change)" } else { "" };
+            println!("output: {} {}{}", output.address, output.value, change);
+        }
+        println!("fee: {}", prepared.fee);
+        println!("vsize: {}", prepared.vsize);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("commit_tx") {
+        let prepared_id: u64 = matches.value_of("prepared_id").unwrap().parse().unwrap();
+        let tx = client.commit_transaction(prepared_id).unwrap();
+        println!("{}", hex::encode(tx));
+    }
+
+    if let Some(matches) = matches.subcommand_matches("abandon_tx") {
+        let txid = matches.value_of("txid").unwrap();
+        client.abandon_transaction(txid.to_string()).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("accelerate_tx") {
+        let txid = matches.value_of("txid").unwrap();
+        let fee_rate: u64 = matches.value_of("fee_rate").unwrap().parse().unwrap();
+        let tx = client.accelerate_transaction(txid.to_string(), fee_rate).unwrap();
+        println!("{}", hex::encode(tx));
+    }
+
+    if let Some(matches) = matches.subcommand_matches("cancel_tx") {
+        let txid = matches.value_of("txid").unwrap();
+        let fee_rate: u64 = matches.value_of("fee_rate").unwrap().parse().unwrap();
+        let tx = client.cancel_transaction(txid.to_string(), fee_rate).unwrap();
+        println!("{}", hex::encode(tx));
+    }
+
+    if let Some(matches) = matches.subcommand_matches("estimate_tx_size") {
+        let count = |name| matches.value_of(name).unwrap().parse::<u32>().unwrap();
+        let inputs = [count("p2pkh"), count("p2shwh"), count("p2wkh"), count("p2tr")];
+        let fee_rate: u64 = matches.value_of("fee_rate").unwrap().parse().unwrap();
+        let resp = client.estimate_tx_size(inputs, count("outputs"), fee_rate).unwrap();
+        println!("weight: {}", resp.weight);
+        println!("vsize: {}", resp.vsize);
+        println!("fee: {}", resp.fee);
+    }
+
+    if let Some(_matches) = matches.subcommand_matches("payment_code") {
+        let payment_code = client.get_payment_code().unwrap();
+        println!("{}", payment_code);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("notify_payment_code") {
+        let payment_code = matches.value_of("payment_code").unwrap();
+        let tx = client.notify_payment_code(payment_code.to_string()).unwrap();
+        println!("{}", hex::encode(tx));
+    }
+
+    if let Some(matches) = matches.subcommand_matches("payment_code_address") {
+        let payment_code = matches.value_of("payment_code").unwrap();
+        let addr = client.payment_code_address(payment_code.to_string()).unwrap();
+        println!("{}", addr);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("sweep_payment_codes") {
+        let addr_type: AccountAddressType = matches.value_of("addr_type").unwrap().into();
+        let tx = client.sweep_payment_codes(addr_type.into()).unwrap();
+        println!("{}", hex::encode(tx));
+    }
+
+    if let Some(_matches) = matches.subcommand_matches("silent_payment_address") {
+        let addr = client.get_silent_payment_address().unwrap();
+        println!("{}", addr);
+    }
+
+    if let Some(_matches) = matches.subcommand_matches("list_silent_payment_utxos") {
+        for utxo in client.list_silent_payment_utxos().unwrap() {
+            let mut txid = utxo.get_out_point().txid.clone();
+            txid.reverse();
+            println!("{}:{} {}", hex::encode(txid), utxo.get_out_point().vout, utxo.value);
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("sweep_silent_payments") {
+        let addr_type: AccountAddressType = matches.value_of("addr_type").unwrap().into();
+        let tx = client.sweep_silent_payments(addr_type.into()).unwrap();
+        println!("{}", hex::encode(tx));
+    }
+
+    if let Some(matches) = matches.subcommand_matches("prove_reserves") {
+        let message = matches.value_of("message").unwrap();
+        let resp = client.prove_reserves(message.to_string()).unwrap();
+        println!("proof: {}", hex::encode(&resp.proof));
+        println!("block_height: {}", resp.block_height);
+        println!("amount: {}", resp.amount);
+    }
+
+    if let Some(_matches) = matches.subcommand_matches("spending_policy") {
+        let resp = client.get_spending_policy().unwrap();
+        let policy = resp.get_policy();
+        println!("daily_limit: {}", policy.daily_limit);
+        println!("max_per_tx: {}", policy.max_per_tx);
+        println!("whitelist: {}", policy.whitelist.join(","));
+        println!("blacklist: {}", policy.blacklist.join(","));
+        println!("approval_threshold: {}", policy.approval_threshold);
+        println!("spent_in_window: {}", resp.spent_in_window);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("set_spending_policy") {
+        let addr_list = |name| -> Vec<String> {
+            matches.values_of(name).map_or(Vec::new(), |values| values.map(String::from).collect())
+        };
+        let mut policy = SpendingPolicy::new();
+        policy.set_daily_limit(matches.value_of("daily_limit").unwrap().parse().unwrap());
+        policy.set_max_per_tx(matches.value_of("max_per_tx").unwrap().parse().unwrap());
+        policy.set_whitelist(addr_list("whitelist").into());
+        policy.set_blacklist(addr_list("blacklist").into());
+        let approval_threshold = matches.value_of("approval_threshold").unwrap().parse().unwrap();
+        policy.set_approval_threshold(approval_threshold);
+        client.set_spending_policy(policy).unwrap();
+    }
+
+    if let Some(_matches) = matches.subcommand_matches("pending_spends") {
+        for spend in client.list_pending_spends().unwrap() {
+            println!("{} {} {} {}", spend.id, spend.dest_addr, spend.amt, spend.expires_at);
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("approve_spend") {
+        let id: u64 = matches.value_of("id").unwrap().parse().unwrap();
+        let tx = client.approve_spend(id).unwrap();
+        println!("{}", hex::encode(tx));
+    }
+
+    if let Some(matches) = matches.subcommand_matches("reject_spend") {
+        let id: u64 = matches.value_of("id").unwrap().parse().unwrap();
+        client.reject_spend(id).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("audit_log") {
+        let start: u64 = matches.value_of("start").unwrap().parse().unwrap();
+        let limit: u32 = matches.value_of("limit").unwrap().parse().unwrap();
+        for entry in client.get_audit_log(start, limit).unwrap() {
+            println!(
+                "{} {} {} {} {} caller={} hash={}",
+                entry.seq,
+                entry.timestamp,
+                entry.operation,
+                entry.params,
+                entry.txid,
+                entry.caller,
+                entry.hash,
+            );
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("fund_channel") {
+        let witness_script = hex::decode(matches.value_of("witness_script").unwrap()).unwrap();
+        let amt: u64 = matches.value_of("amt").unwrap().parse().unwrap();
+        let fee_rate: u64 = matches.value_of("fee_rate").unwrap().parse().unwrap();
+        let funding = client.fund_channel(witness_script, amt, fee_rate).unwrap();
+        println!("funding_id: {}", funding.funding_id);
+        println!("psbt: {}", hex::encode(&funding.psbt));
+        println!("output_index: {}", funding.output_index);
+        println!("fee: {}", funding.fee);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("release_funding") {
+        let funding_id: u64 = matches.value_of("funding_id").unwrap().parse().unwrap();
+        client.release_funding(funding_id).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("publish_funding") {
+        let funding_id: u64 = matches.value_of("funding_id").unwrap().parse().unwrap();
+        let tx = client.publish_funding(funding_id).unwrap();
+        println!("{}", hex::encode(tx));
     }
 
     if let Some(matches) = matches.subcommand_matches("unlock_coins") {
@@ -122,6 +1319,439 @@ fn main() {
         client.unlock_coins(lock_id);
     }
 
+    if let Some(matches) = matches.subcommand_matches("freeze_utxo") {
+        client.freeze_utxo(out_point(matches)).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("unfreeze_utxo") {
+        client.unfreeze_utxo(out_point(matches)).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("export_backup") {
+        let path = matches.value_of("path").unwrap();
+        let passphrase = matches.value_of("passphrase").unwrap();
+        client
+            .export_backup(path.to_string(), passphrase.to_string())
+            .unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("export_electrum_wallet") {
+        let path = matches.value_of("path").unwrap();
+        client.export_electrum_wallet(path.to_string()).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("export_state_snapshot") {
+        let path = matches.value_of("path").unwrap();
+        client.export_state_snapshot(path.to_string()).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("import_state_snapshot") {
+        let path = matches.value_of("path").unwrap();
+        let height = client.import_state_snapshot(path.to_string()).unwrap();
+        println!("syncing on from height {}", height);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("check") {
+        let resp = client.check_wallet(matches.is_present("repair")).unwrap();
+        if resp.repaired > 0 {
+            println!("repaired {} issues", resp.repaired);
+        }
+        for issue in resp.issues.iter() {
+            let hint = if issue.repairable { "" } else { " (needs a rescan)" };
+            println!("{}{}", issue.description, hint);
+        }
+        if resp.issues.is_empty() {
+            println!("no issues found");
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("restore_backup") {
+        let path = matches.value_of("path").unwrap();
+        let passphrase = matches.value_of("passphrase").unwrap();
+        let wallet_passphrase = matches.value_of("wallet_passphrase").unwrap();
+        let salt = matches.value_of("salt").unwrap();
+        client
+            .restore_backup(
+                path.to_string(),
+                passphrase.to_string(),
+                wallet_passphrase.to_string(),
+                salt.to_string(),
+            )
+            .unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("create_wallet") {
+        if let Some(path) = matches.value_of("watch_only_keys") {
+            let keys = fs::read_to_string(path).unwrap();
+            client.create_watch_only_wallet(wallet.clone(), keys).unwrap();
+        } else {
+            let passphrase = matches.value_of("passphrase").unwrap();
+            let salt = matches.value_of("salt").unwrap();
+            let mnemonic_words: u32 =
+                matches.value_of("mnemonic_words").unwrap().parse().unwrap();
+            let account_paths = matches.values_of("account_path").map_or(Vec::new(), |values| {
+                values
+                    .map(|value| {
+                        let mut parts = value.splitn(2, '=');
+                        let addr_type: AccountAddressType = parts.next().unwrap().into();
+                        (addr_type.into(), parts.next().expect("expected type=path").to_string())
+                    })
+                    .collect()
+            });
+            let mnemonic = client
+                .create_wallet(
+                    wallet.clone(),
+                    passphrase.to_string(),
+                    salt.to_string(),
+                    mnemonic_words,
+                    account_paths,
+                )
+                .unwrap();
+            println!("{}", mnemonic);
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("validate_mnemonic") {
+        let mnemonic = matches.value_of("mnemonic").unwrap();
+        let resp = client.validate_mnemonic(mnemonic.to_string()).unwrap();
+        if resp.message.is_empty() {
+            println!("valid mnemonic of {} words", resp.word_count);
+        } else {
+            println!("{}", resp.message);
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("open_wallet") {
+        let passphrase = matches.value_of("passphrase").unwrap();
+        let salt = matches.value_of("salt").unwrap();
+        client
+            .open_wallet(wallet.clone(), passphrase.to_string(), salt.to_string())
+            .unwrap();
+    }
+
+    if let Some(_matches) = matches.subcommand_matches("lock_wallet") {
+        client.lock_wallet().unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("unlock_wallet") {
+        let passphrase = matches.value_of("passphrase").unwrap();
+        let salt = matches.value_of("salt").unwrap();
+        let timeout = matches.value_of("timeout").map(|timeout| timeout.parse().unwrap());
+        client
+            .unlock_wallet(passphrase.to_string(), salt.to_string(), timeout)
+            .unwrap();
+    }
+
+    if let Some(_matches) = matches.subcommand_matches("list_wallets") {
+        for wallet in client.list_wallets() {
+            println!("{}", wallet);
+        }
+    }
+
+    if let Some(_matches) = matches.subcommand_matches("get_info") {
+        let info = client.get_info().unwrap();
+        println!("api_version: {}", info.api_version);
+        println!("version: {}", info.version);
+        println!("read_only: {}", info.read_only);
+        println!("network: {}", format!("{:?}", info.network).to_lowercase());
+        println!("backend: {}", backend_name(info.backend));
+    }
+
+    if let Some(matches) = matches.subcommand_matches("set_log_level") {
+        let filter = matches.value_of("filter").unwrap_or_default().to_string();
+        println!("{}", client.set_log_level(filter).unwrap());
+    }
+
+    if let Some(matches) = matches.subcommand_matches("reconnect") {
+        let status = client
+            .reconnect(
+                matches.value_of("address").unwrap().to_string(),
+                matches.value_of("user").unwrap().to_string(),
+                matches.value_of("password").unwrap().to_string(),
+            )
+            .unwrap();
+        print_connection_status(&status);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("switch_backend") {
+        let kind = match matches.value_of("backend").unwrap() {
+            "electrum" => BackendKind::ELECTRUM,
+            _ => BackendKind::FULL_NODE,
+        };
+        let status = client
+            .switch_backend(
+                kind,
+                matches.value_of("address").unwrap().to_string(),
+                matches.value_of("user").unwrap().to_string(),
+                matches.value_of("password").unwrap().to_string(),
+            )
+            .unwrap();
+        print_connection_status(&status);
+    }
+
+    if let Some(_matches) = matches.subcommand_matches("connection_status") {
+        print_connection_status(&client.connection_status().unwrap());
+    }
+
+    if let Some(_matches) = matches.subcommand_matches("get_transactions") {
+        let resp = client.get_transactions().unwrap();
+        for record in resp.get_transactions() {
+            let status = match record.status {
+                TxStatus::UNCONFIRMED => "unconfirmed".to_owned(),
+                TxStatus::CONFIRMED => format!("confirmed at {}", record.height),
+                TxStatus::CONFLICTED => format!("conflicted by {}", record.conflicting_txid),
+                TxStatus::REPLACED => format!("replaced by {}", record.conflicting_txid),
+            };
+            println!("{} {} value: {} fee: {}", record.txid, status, record.value, record.fee);
+            if record.has_fiat_value() {
+                println!("    now: {}", format_fiat_value(record.get_fiat_value()));
+            }
+            if record.has_fiat_value_at_confirmation() {
+                let fiat_value = record.get_fiat_value_at_confirmation();
+                println!("    at confirmation: {}", format_fiat_value(fiat_value));
+            }
+        }
+        let fee_stats = resp.get_fee_stats();
+        println!("fees confirmed: {}", fee_stats.confirmed);
+        println!("fees unconfirmed: {}", fee_stats.unconfirmed);
+        println!("transactions paying fees: {}", fee_stats.tx_count);
+    }
+
+    if let Some(_matches) = matches.subcommand_matches("get_account_stats") {
+        let resp = client.get_account_stats().unwrap();
+        for account in resp.get_accounts() {
+            println!(
+                "{:?} received: {} sent: {}",
+                account.address_type, account.received, account.sent
+            );
+            for stats in account.get_addresses() {
+                println!("    {} received: {} sent: {}", stats.address, stats.received, stats.sent);
+            }
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("export_history") {
+        let format = match matches.value_of("format").unwrap() {
+            "json" => ExportFormat::JSON,
+            _ => ExportFormat::CSV,
+        };
+        match matches.value_of("path") {
+            Some(path) => client.export_history(format, &mut File::create(path).unwrap()),
+            None => client.export_history(format, &mut io::stdout()),
+        }
+        .unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("new_timelocked_address") {
+        let blocks: u32 = matches.value_of("blocks").map_or(0, |blocks| blocks.parse().unwrap());
+        let height: u32 = matches.value_of("height").map_or(0, |height| height.parse().unwrap());
+        println!("{}", client.new_timelocked_address(blocks, height).unwrap());
+    }
+
+    if let Some(matches) = matches.subcommand_matches("sweep_address") {
+        let address = matches.value_of("address").unwrap();
+        let dest_addr = matches.value_of("dest_addr").unwrap();
+        let fee_rate: u64 = matches.value_of("fee_rate").unwrap().parse().unwrap();
+        let tx = client
+            .sweep_address(address.to_string(), dest_addr.to_string(), fee_rate)
+            .unwrap();
+        println!("{}", hex::encode(tx));
+    }
+
+    if let Some(matches) = matches.subcommand_matches("set_vault") {
+        let recovery_pk = matches.value_of("recovery_pk").unwrap();
+        let delay: u32 = matches.value_of("delay").unwrap().parse().unwrap();
+        client.set_vault(recovery_pk.to_string(), delay).unwrap();
+    }
+
+    if let Some(_matches) = matches.subcommand_matches("new_vault_address") {
+        println!("{}", client.new_vault_address().unwrap());
+    }
+
+    if let Some(matches) = matches.subcommand_matches("unvault") {
+        let dest_addr = matches.value_of("dest_addr").unwrap();
+        let fee_rate: u64 = matches.value_of("fee_rate").unwrap().parse().unwrap();
+        println!("{}", hex::encode(client.unvault(dest_addr.to_string(), fee_rate).unwrap()));
+    }
+
+    if let Some(_matches) = matches.subcommand_matches("descriptors") {
+        for descriptor in client.get_descriptors().unwrap() {
+            println!("{}", descriptor);
+        }
+    }
+
+    if let Some(_matches) = matches.subcommand_matches("import_descriptors") {
+        println!("{}", client.get_import_descriptors().unwrap());
+    }
+
+    if let Some(_matches) = matches.subcommand_matches("migrate_derivation") {
+        let tx = client.migrate_derivation().unwrap();
+        println!("{}", hex::encode(tx));
+    }
+
+    if let Some(matches) = matches.subcommand_matches("script_account_xpub") {
+        if matches.is_present("origin") {
+            println!("{}", client.get_script_account_key().unwrap());
+        } else {
+            println!("{}", client.get_script_account_xpub().unwrap());
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("register_policy") {
+        let name = matches.value_of("name").unwrap().to_string();
+        let policy = matches.value_of("policy").unwrap().to_string();
+        client.register_policy(name, policy).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("new_script_address") {
+        let name = matches.value_of("name").unwrap().to_string();
+        println!("{}", client.new_script_address(name).unwrap());
+    }
+
+    if let Some(matches) = matches.subcommand_matches("spend_script_coins") {
+        let name = matches.value_of("name").unwrap();
+        let dest_addr = matches.value_of("dest_addr").unwrap();
+        let fee_rate: u64 = matches.value_of("fee_rate").unwrap().parse().unwrap();
+        let resp = client
+            .spend_script_coins(name.to_string(), dest_addr.to_string(), fee_rate)
+            .unwrap();
+        println!("psbt: {}", hex::encode(&resp.psbt));
+        if !resp.serialized_raw_tx.is_empty() {
+            println!("published: {}", hex::encode(&resp.serialized_raw_tx));
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("finalize_script_psbt") {
+        let psbt = hex::decode(matches.value_of("psbt").unwrap()).unwrap();
+        println!("{}", hex::encode(client.finalize_script_psbt(psbt).unwrap()));
+    }
+
+    if let Some(matches) = matches.subcommand_matches("create_unsigned_tx") {
+        let dest_addr = matches.value_of("dest_addr").unwrap();
+        let amt: u64 = matches.value_of("amt").unwrap().parse().unwrap();
+        let change_addr_type = matches
+            .value_of("change_addr_type")
+            .map(|addr_type| AccountAddressType::from(addr_type).into());
+        let unsigned = client
+            .create_unsigned_tx(
+                dest_addr.to_string(),
+                amt,
+                input_filter(matches),
+                change_addr_type,
+                privacy(matches),
+            )
+            .unwrap();
+        fs::write(matches.value_of("out").unwrap(), &unsigned.psbt).unwrap();
+        println!("id: {}", unsigned.id);
+        println!("fee: {}", unsigned.fee);
+    }
+
+    if let Some(_matches) = matches.subcommand_matches("list_unsigned_txs") {
+        for unsigned in client.list_unsigned_txs().unwrap() {
+            println!(
+                "id: {} dest_addr: {} amt: {} fee: {}",
+                unsigned.id, unsigned.dest_addr, unsigned.amt, unsigned.fee,
+            );
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("broadcast_signed") {
+        let id: u64 = matches.value_of("id").unwrap().parse().unwrap();
+        let psbt = match matches.value_of("qr_parts") {
+            Some(path) => client.decode_qr(qr_parts(path)).unwrap().get_psbt().to_vec(),
+            None => fs::read(matches.value_of("psbt").unwrap()).unwrap(),
+        };
+        println!("{}", hex::encode(client.broadcast_signed_psbt(id, psbt).unwrap()));
+    }
+
+    if let Some(matches) = matches.subcommand_matches("cancel_unsigned_tx") {
+        let id: u64 = matches.value_of("id").unwrap().parse().unwrap();
+        client.cancel_unsigned_tx(id).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("encode_qr") {
+        let format = match matches.value_of("format").unwrap() {
+            "bbqr" => QrFormat::BBQR,
+            _ => QrFormat::UR,
+        };
+        let payload = if let Some(id) = matches.value_of("unsigned_tx_id") {
+            QrPayload::unsigned_tx_id(id.parse().unwrap())
+        } else if let Some(path) = matches.value_of("psbt") {
+            QrPayload::psbt(fs::read(path).unwrap())
+        } else if let Some(index) = matches.value_of("descriptor_index") {
+            QrPayload::descriptor_index(index.parse().unwrap())
+        } else {
+            QrPayload::address(matches.value_of("address").unwrap().to_owned())
+        };
+        let max_fragment_len: u32 = matches.value_of("max_fragment_len").unwrap().parse().unwrap();
+        let extra_parts: u32 = matches.value_of("extra_parts").unwrap().parse().unwrap();
+        for part in client.encode_qr(payload, format, max_fragment_len, extra_parts).unwrap() {
+            println!("{}", part);
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("decode_qr") {
+        let decoded = client.decode_qr(qr_parts(matches.value_of("parts").unwrap())).unwrap();
+        if decoded.has_psbt() {
+            match matches.value_of("out") {
+                Some(path) => fs::write(path, decoded.get_psbt()).unwrap(),
+                None => println!("{}", hex::encode(decoded.get_psbt())),
+            }
+        } else if decoded.has_descriptor() {
+            println!("{}", decoded.get_descriptor());
+        } else {
+            println!("{}", decoded.get_address());
+        }
+    }
+
+    if let Some(_matches) = matches.subcommand_matches("subscribe_events") {
+        for event in client.subscribe_events() {
+            let event = event.unwrap();
+            if event.has_conflict() {
+                let conflict = event.get_conflict();
+                println!("conflict: {} lost to {}", conflict.txid, conflict.conflicting_txid);
+            } else if event.has_deposit() {
+                let deposit = event.get_deposit();
+                println!("deposit: {} {}", deposit.txid, deposit.value);
+            } else if event.has_confirmed() {
+                let confirmed = event.get_confirmed();
+                let direction = if confirmed.outgoing { "outgoing" } else { "incoming" };
+                println!(
+                    "confirmed: {} {} at {}",
+                    direction, confirmed.txid, confirmed.block_height,
+                );
+            } else if event.has_new_tip() {
+                println!("new tip: {}", event.get_new_tip().height);
+            } else if event.has_watched_funded() {
+                let funded = event.get_watched_funded();
+                println!("watched {}: received {}", funded.label, funded.value);
+            } else if event.has_watched_spent() {
+                let spent = event.get_watched_spent();
+                println!("watched {}: spent by {}", spent.label, spent.txid);
+            } else if event.has_unexpected_unvault() {
+                let unvault = event.get_unexpected_unvault();
+                println!(
+                    "unexpected unvault: {} spent by {}",
+                    format_out_point(unvault.get_out_point()),
+                    unvault.txid,
+                );
+            } else if event.has_backend_status() {
+                let status = event.get_backend_status();
+                let state = if status.up { "up" } else { "down" };
+                println!("backend {}: {}", status.name, state);
+            } else if event.has_backend_divergence() {
+                let divergence = event.get_backend_divergence();
+                println!(
+                    "backend {} diverges: tip {}, the wallet's node at {}{}",
+                    divergence.source,
+                    divergence.height,
+                    divergence.tip,
+                    if divergence.forked { ", another chain" } else { "" },
+                );
+            }
+        }
+    }
+
     if let Some(_matches) = matches.subcommand_matches("shutdown") {
         client.shutdown();
     }
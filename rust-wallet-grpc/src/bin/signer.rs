@@ -0,0 +1,170 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The offline half of a watch-only wallet, it holds the mnemonic on an air-gapped machine.
+//! `export_keys` writes the public keys to create the watch-only wallet with,
+//! `sign` signs the PSBT files of its `create_unsigned_tx` command, or with `--qr`
+//! the parts of the animated QR codes of its `encode_qr` command.
+
+extern crate wallet;
+
+use structopt::clap::{Arg, App, ArgMatches, SubCommand};
+use bitcoin::{
+    consensus::{serialize, deserialize},
+    util::{bip32::ExtendedPrivKey, psbt::PartiallySignedTransaction},
+};
+use zeroize::Zeroizing;
+
+use wallet::{
+    account::{AccountAddressType, DerivationMode, DerivationScheme},
+    address,
+    keyfactory::{KeyFactory, wipe_extended_private_key},
+    mnemonic::Mnemonic,
+    qr::{self, QrFormat, QrPayload, DEFAULT_MAX_FRAGMENT_LEN},
+    signer,
+    walletlibrary::{WalletLibrary, DEFAULT_NETWORK, DEFAULT_SALT},
+};
+
+use std::fs;
+
+fn mnemonic_args<'a, 'b>(subcommand: App<'a, 'b>) -> App<'a, 'b> {
+    subcommand
+        .arg(Arg::with_name("mnemonic_file")
+            .long("mnemonic_file")
+            .takes_value(true)
+            .required(true)
+            .help("file with the words of the mnemonic separated by spaces"))
+        .arg(Arg::with_name("salt")
+            .long("salt")
+            .takes_value(true)
+            .default_value(DEFAULT_SALT)
+            .help("salt the seed was made of the mnemonic with"))
+}
+
+fn master_key(matches: &ArgMatches) -> ExtendedPrivKey {
+    let path = matches.value_of("mnemonic_file").unwrap();
+    let words = Zeroizing::new(fs::read_to_string(path).unwrap());
+    let mnemonic = Mnemonic::from(words.trim()).unwrap();
+    let salt = matches.value_of("salt").unwrap();
+    KeyFactory::recover_from_mnemonic(&mnemonic, DEFAULT_NETWORK, salt).unwrap()
+}
+
+fn main() {
+    let matches = App::new("wallet-signer")
+        .about("Signs the payments of a watch-only wallet offline")
+        .subcommand(mnemonic_args(SubCommand::with_name("export_keys"))
+            .arg(Arg::with_name("account_path")
+                .long("account_path")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("derivation path of an account, e.g. p2wkh=m/84'/0'/5'"))
+            .arg(Arg::with_name("out")
+                .long("out")
+                .takes_value(true)
+                .required(true)
+                .help("file the keys are written to, for create_wallet --watch_only_keys"))
+            .about("Write the public keys a watch-only wallet follows the mnemonic with"))
+        .subcommand(mnemonic_args(SubCommand::with_name("sign"))
+            .arg(Arg::with_name("in")
+                .long("in")
+                .takes_value(true)
+                .required(true)
+                .help("file of the PSBT written by create_unsigned_tx"))
+            .arg(Arg::with_name("out")
+                .long("out")
+                .takes_value(true)
+                .required(true)
+                .help("file the signed PSBT is written to, for broadcast_signed"))
+            .arg(Arg::with_name("qr")
+                .long("qr")
+                .help("the files hold the parts of animated QR codes, one per line"))
+            .about("Show what a PSBT pays and sign the inputs of the mnemonic"))
+        .get_matches();
+
+    if let Some(matches) = matches.subcommand_matches("export_keys") {
+        let schemes: Vec<(AccountAddressType, DerivationScheme)> = matches
+            .values_of("account_path")
+            .map_or(Vec::new(), |values| {
+                values
+                    .map(|value| {
+                        let mut parts = value.splitn(2, '=');
+                        let addr_type: AccountAddressType = parts.next().unwrap().into();
+                        let path = parts.next().expect("expected type=path");
+                        (addr_type, path.parse().unwrap())
+                    })
+                    .collect()
+            });
+        let mut master_key = master_key(matches);
+        let keys = WalletLibrary::watch_only_keys(
+            &master_key,
+            DEFAULT_NETWORK,
+            DerivationMode::Standard,
+            &schemes,
+        );
+        wipe_extended_private_key(&mut master_key);
+
+        let keys = serde_json::to_string_pretty(&keys.unwrap()).unwrap();
+        fs::write(matches.value_of("out").unwrap(), keys).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("sign") {
+        let input = fs::read(matches.value_of("in").unwrap()).unwrap();
+        let input = if matches.is_present("qr") {
+            let parts: Vec<String> = String::from_utf8(input)
+                .unwrap()
+                .lines()
+                .map(|line| line.trim().to_owned())
+                .filter(|line| !line.is_empty())
+                .collect();
+            match qr::decode(&parts, DEFAULT_NETWORK).unwrap() {
+                QrPayload::Psbt(psbt) => psbt,
+                _ => panic!("the QR code is not of a PSBT"),
+            }
+        } else {
+            input
+        };
+        let mut psbt: PartiallySignedTransaction = deserialize(&input).unwrap();
+
+        // the payment is checked on the screen of the signer, not of the online machine
+        let tx = &psbt.global.unsigned_tx;
+        let mut input_total = 0;
+        for (i, input) in psbt.inputs.iter().enumerate() {
+            input_total += signer::spent_output(tx, i, input).unwrap().value;
+        }
+        let mut output_total = 0;
+        for output in &tx.output {
+            let dest = address::from_script(&output.script_pubkey, DEFAULT_NETWORK)
+                .unwrap_or_else(|| hex::encode(output.script_pubkey.as_bytes()));
+            println!("output: {} {}", dest, output.value);
+            output_total += output.value;
+        }
+        println!("fee: {}", input_total - output_total);
+
+        let mut master_key = master_key(matches);
+        let signed = signer::sign_psbt(&master_key, &mut psbt);
+        wipe_extended_private_key(&mut master_key);
+        println!("signatures: {}", signed.unwrap());
+
+        let output = if matches.is_present("qr") {
+            let payload = QrPayload::Psbt(serialize(&psbt));
+            let parts = qr::encode(&payload, QrFormat::Ur, DEFAULT_MAX_FRAGMENT_LEN, 0).unwrap();
+            parts.join("\n").into_bytes()
+        } else {
+            serialize(&psbt)
+        };
+        fs::write(matches.value_of("out").unwrap(), output).unwrap();
+    }
+}
@@ -13,19 +13,84 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use bytes::Bytes;
 use protobuf::RepeatedField;
 
-use std::error::Error;
+use std::{error::Error, io::Write};
 
+use super::error::from_grpc;
 use super::walletrpc_grpc::{Wallet, WalletClient};
 use super::walletrpc::{
     NewAddressRequest, NewChangeAddressRequest, GetUtxoListRequest, WalletBalanceRequest,
     MakeTxRequest, SendCoinsRequest, UnlockCoinsRequest, SyncWithTipRequest, ShutdownRequest,
-    AddressType as RpcAddressType, Utxo as RpcUtxo, OutPoint as RpcOutPoint,
+    ExportBackupRequest, RestoreBackupRequest, CreateWalletRequest, OpenWalletRequest,
+    ValidateMnemonicRequest, ValidateMnemonicResponse, RescanRequest, CancelOperationRequest,
+    ExportStateSnapshotRequest, ImportStateSnapshotRequest, CheckWalletRequest,
+    CheckWalletResponse,
+    ListWalletsRequest, LockWalletRequest, UnlockWalletRequest, GetUnusedAddressRequest,
+    ListAddressesRequest,
+    AddressInfo as RpcAddressInfo, FreezeUtxoRequest, UnfreezeUtxoRequest,
+    InputFilter as RpcInputFilter, PrepareTransactionRequest, PrepareTransactionResponse,
+    CommitTransactionRequest, AbandonTransactionRequest, SubscribeEventsRequest,
+    AccelerateTransactionRequest, CancelTransactionRequest, EstimateTxSizeRequest,
+    EstimateTxSizeResponse, GetPaymentCodeRequest,
+    NotifyPaymentCodeRequest, PaymentCodeAddressRequest, SweepPaymentCodesRequest,
+    GetSilentPaymentAddressRequest, ListSilentPaymentUtxosRequest,
+    SilentPaymentUtxo as RpcSilentPaymentUtxo, SweepSilentPaymentsRequest, ProveReservesRequest,
+    ProveReservesResponse, GetSpendingPolicyRequest, GetSpendingPolicyResponse,
+    SetSpendingPolicyRequest, SpendingPolicy as RpcSpendingPolicy, SendCoinsResponse,
+    ListPendingSpendsRequest, PendingSpend as RpcPendingSpend, ApproveSpendRequest,
+    RejectSpendRequest, GetAuditLogRequest, AuditEntry as RpcAuditEntry, GetInfoRequest,
+    GetInfoResponse, SetLogLevelRequest, SyncProgress as RpcSyncProgress,
+    FundChannelRequest, FundChannelResponse, ReleaseFundingRequest, PublishFundingRequest,
+    WatchScriptRequest, ListWatchedUtxosRequest, WatchedUtxo as RpcWatchedUtxo,
+    WalletEvent as RpcWalletEvent, ReconnectRequest, GetConnectionStatusRequest,
+    ConnectionStatus as RpcConnectionStatus, GetTransactionsRequest, GetTransactionsResponse,
+    SwitchBackendRequest, BackendKind,
+    GetAccountStatsRequest, GetAccountStatsResponse, SetAddressLabelRequest,
+    AddressType as RpcAddressType, AddressTypeValue, Utxo as RpcUtxo, OutPoint as RpcOutPoint,
+    FiatValue as RpcFiatValue, ExportHistoryRequest, ExportFormat as RpcExportFormat,
+    SweepAddressRequest, NewTimelockedAddressRequest, TimelockedUtxo as RpcTimelockedUtxo,
+    GetScriptAccountXpubRequest, RegisterPolicyRequest, NewScriptAddressRequest,
+    ScriptUtxo as RpcScriptUtxo, SpendScriptCoinsRequest, SpendScriptCoinsResponse,
+    FinalizeScriptPsbtRequest, AccountPath, GetDescriptorsRequest, MigrateDerivationRequest,
+    ExportElectrumWalletRequest, GetImportDescriptorsRequest, CreateUnsignedTxRequest,
+    CreateUnsignedTxResponse, UnsignedTx as RpcUnsignedTx, ListUnsignedTxsRequest,
+    BroadcastSignedPsbtRequest, CancelUnsignedTxRequest, QrFormat as RpcQrFormat,
+    EncodeQrRequest, EncodeQrRequest_oneof_payload, DecodeQrRequest, DecodeQrResponse,
+    CategoryBalance, PrivacyValue, ChangeToleranceValue, SetVaultRequest, NewVaultAddressRequest,
+    VaultUtxo as RpcVaultUtxo, UnvaultRequest,
 };
 
+fn address_type_value(addr_type: RpcAddressType) -> AddressTypeValue {
+    let mut value = AddressTypeValue::new();
+    value.set_addr_type(addr_type);
+    value
+}
+
+fn privacy_value(privacy: bool) -> PrivacyValue {
+    let mut value = PrivacyValue::new();
+    value.set_privacy(privacy);
+    value
+}
+
+fn change_tolerance_value(tolerance: u64) -> ChangeToleranceValue {
+    let mut value = ChangeToleranceValue::new();
+    value.set_tolerance(tolerance);
+    value
+}
+
+// the response of a call, errors sent by the server are decoded into `ErrorDetails`
+fn wait<T: Send + 'static>(resp: grpc::SingleResponse<T>) -> Result<T, Box<dyn Error>> {
+    resp.wait().map(|(_, resp, _)| resp).map_err(from_grpc)
+}
+
 pub struct WalletClientWrapper {
     client: WalletClient,
+    // name of the wallet requests are sent to, empty for the default one
+    wallet: String,
+    // sent as the authorization of every request if the daemon checks tokens
+    token: Option<String>,
 }
 
 impl WalletClientWrapper {
@@ -35,89 +100,1035 @@ impl WalletClientWrapper {
         // let port = 50051;
         let client_conf = Default::default();
         let client = WalletClient::new_plain("127.0.0.1", port, client_conf).unwrap();
-        WalletClientWrapper { client }
+        WalletClientWrapper {
+            client,
+            wallet: String::new(),
+            token: None,
+        }
+    }
+
+    /// send the requests to the wallet with the given name
+    pub fn with_wallet(mut self, wallet: String) -> WalletClientWrapper {
+        self.wallet = wallet;
+        self
+    }
+
+    /// authenticate the requests with a token of the acl file of the daemon
+    pub fn with_token(mut self, token: String) -> WalletClientWrapper {
+        self.token = Some(token);
+        self
+    }
+
+    fn options(&self) -> grpc::RequestOptions {
+        let mut options = grpc::RequestOptions::new();
+        if let Some(ref token) = self.token {
+            let value = Bytes::from(format!("Bearer {}", token));
+            options.metadata.add(grpc::MetadataKey::from("authorization"), value);
+        }
+        options
     }
 
     pub fn new_address(&self, addr_type: RpcAddressType) -> String {
         let mut req = NewAddressRequest::new();
+        req.set_wallet(self.wallet.clone());
         req.set_addr_type(addr_type);
 
-        let resp = self.client.new_address(grpc::RequestOptions::new(), req);
+        let resp = self.client.new_address(self.options(), req);
         resp.wait().unwrap().1.address
     }
 
     pub fn new_change_address(&self, addr_type: RpcAddressType) -> String {
         let mut req = NewChangeAddressRequest::new();
+        req.set_wallet(self.wallet.clone());
         req.set_addr_type(addr_type);
 
         let resp = self
             .client
-            .new_change_address(grpc::RequestOptions::new(), req);
+            .new_change_address(self.options(), req);
         resp.wait().unwrap().1.address
     }
 
+    pub fn get_unused_address(&self, addr_type: RpcAddressType) -> String {
+        let mut req = GetUnusedAddressRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_addr_type(addr_type);
+
+        let resp = self.client.get_unused_address(self.options(), req);
+        resp.wait().unwrap().1.address
+    }
+
+    pub fn list_addresses(&self) -> Vec<RpcAddressInfo> {
+        let mut req = ListAddressesRequest::new();
+        req.set_wallet(self.wallet.clone());
+
+        let resp = self.client.list_addresses(self.options(), req);
+        resp.wait().unwrap().1.addresses.into_vec()
+    }
+
     pub fn get_utxo_list(&self) -> Vec<RpcUtxo> {
-        let req = GetUtxoListRequest::new();
-        let resp = self.client.get_utxo_list(grpc::RequestOptions::new(), req);
+        let mut req = GetUtxoListRequest::new();
+        req.set_wallet(self.wallet.clone());
+        let resp = self.client.get_utxo_list(self.options(), req);
         resp.wait().unwrap().1.utxos.into_vec()
     }
 
+    pub fn get_timelocked_utxo_list(&self) -> Vec<RpcTimelockedUtxo> {
+        let mut req = GetUtxoListRequest::new();
+        req.set_wallet(self.wallet.clone());
+        let resp = self.client.get_utxo_list(self.options(), req);
+        resp.wait().unwrap().1.timelocked_utxos.into_vec()
+    }
+
+    pub fn get_script_utxo_list(&self) -> Vec<RpcScriptUtxo> {
+        let mut req = GetUtxoListRequest::new();
+        req.set_wallet(self.wallet.clone());
+        let resp = self.client.get_utxo_list(self.options(), req);
+        resp.wait().unwrap().1.script_utxos.into_vec()
+    }
+
+    pub fn get_vault_utxo_list(&self) -> Vec<RpcVaultUtxo> {
+        let mut req = GetUtxoListRequest::new();
+        req.set_wallet(self.wallet.clone());
+        let resp = self.client.get_utxo_list(self.options(), req);
+        resp.wait().unwrap().1.vault_utxos.into_vec()
+    }
+
     pub fn wallet_balance(&self) -> u64 {
-        let req = WalletBalanceRequest::new();
-        let resp = self.client.wallet_balance(grpc::RequestOptions::new(), req);
+        let mut req = WalletBalanceRequest::new();
+        req.set_wallet(self.wallet.clone());
+        let resp = self.client.wallet_balance(self.options(), req);
         resp.wait().unwrap().1.total_balance
     }
 
+    /// the balance at the current price, `None` unless the daemon has a price source
+    pub fn fiat_balance(&self) -> Result<Option<RpcFiatValue>, Box<dyn Error>> {
+        let mut req = WalletBalanceRequest::new();
+        req.set_wallet(self.wallet.clone());
+        let resp = self.client.wallet_balance(self.options(), req);
+        let mut resp = wait(resp)?;
+        Ok(if resp.has_fiat_balance() {
+            Some(resp.take_fiat_balance())
+        } else {
+            None
+        })
+    }
+
+    /// the balance split into spendable, frozen, timelocked and watched coins
+    pub fn balances(&self) -> Result<Vec<CategoryBalance>, Box<dyn Error>> {
+        let mut req = WalletBalanceRequest::new();
+        req.set_wallet(self.wallet.clone());
+        let resp = self.client.wallet_balance(self.options(), req);
+        Ok(wait(resp)?.take_balances().into_vec())
+    }
+
+    pub fn watch_script(
+        &self,
+        script_pubkey: Vec<u8>,
+        label: String,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut req = WatchScriptRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_script_pubkey(script_pubkey);
+        req.set_label(label);
+        let resp = self.client.watch_script(self.options(), req);
+        wait(resp)?;
+        Ok(())
+    }
+
+    pub fn list_watched_utxos(&self) -> Result<Vec<RpcWatchedUtxo>, Box<dyn Error>> {
+        let mut req = ListWatchedUtxosRequest::new();
+        req.set_wallet(self.wallet.clone());
+        let resp = self.client.list_watched_utxos(self.options(), req);
+        Ok(wait(resp)?.utxos.into_vec())
+    }
+
     pub fn make_tx(
         &self,
         ops: Vec<RpcOutPoint>,
         dest_addr: String,
         amt: u64,
         submit: bool,
+        change_addr_type: Option<RpcAddressType>,
     ) -> Vec<u8> {
         let mut req = MakeTxRequest::new();
+        req.set_wallet(self.wallet.clone());
         req.set_ops(RepeatedField::from_vec(ops));
         req.set_dest_addr(dest_addr);
         req.set_amt(amt);
         req.set_submit(submit);
-        let resp = self.client.make_tx(grpc::RequestOptions::new(), req);
+        if let Some(change_addr_type) = change_addr_type {
+            req.set_change_addr_type(address_type_value(change_addr_type));
+        }
+        let resp = self.client.make_tx(self.options(), req);
         resp.wait().unwrap().1.serialized_raw_tx
     }
 
+    /// `make_tx` of inputs given as out point, nSequence and sighash type
+    pub fn make_tx_advanced(
+        &self,
+        inputs: Vec<(RpcOutPoint, u32, u32)>,
+        dest_addr: String,
+        amt: u64,
+        submit: bool,
+        change_addr_type: Option<RpcAddressType>,
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut req = MakeTxRequest::new();
+        req.set_wallet(self.wallet.clone());
+        for (op, sequence, sighash_type) in inputs {
+            req.mut_ops().push(op);
+            req.mut_sequences().push(sequence);
+            req.mut_sighash_types().push(sighash_type);
+        }
+        req.set_dest_addr(dest_addr);
+        req.set_amt(amt);
+        req.set_submit(submit);
+        if let Some(change_addr_type) = change_addr_type {
+            req.set_change_addr_type(address_type_value(change_addr_type));
+        }
+        let resp = self.client.make_tx(self.options(), req);
+        Ok(wait(resp)?.serialized_raw_tx)
+    }
+
     pub fn send_coins(
         &self,
         dest_addr: String,
         amt: u64,
         submit: bool,
         lock_coins: bool,
-    ) -> Result<(Vec<u8>, u64), Box<dyn Error>> {
+        input_filter: Option<RpcInputFilter>,
+        change_addr_type: Option<RpcAddressType>,
+        idempotency_key: Option<String>,
+        privacy: Option<bool>,
+        change_tolerance: Option<u64>,
+        fee_rate: Option<u64>,
+    ) -> Result<SendCoinsResponse, Box<dyn Error>> {
         let mut req = SendCoinsRequest::new();
+        req.set_wallet(self.wallet.clone());
         req.set_dest_addr(dest_addr);
         req.set_amt(amt);
         req.set_submit(submit);
         req.set_lock_coins(lock_coins);
-        let resp = self.client.send_coins(grpc::RequestOptions::new(), req);
-        let resp = resp.wait()?.1;
-        Ok((resp.serialized_raw_tx, resp.lock_id))
+        if let Some(input_filter) = input_filter {
+            req.set_input_filter(input_filter);
+        }
+        if let Some(change_addr_type) = change_addr_type {
+            req.set_change_addr_type(address_type_value(change_addr_type));
+        }
+        if let Some(idempotency_key) = idempotency_key {
+            req.set_idempotency_key(idempotency_key);
+        }
+        if let Some(privacy) = privacy {
+            req.set_privacy(privacy_value(privacy));
+        }
+        if let Some(tolerance) = change_tolerance {
+            req.set_change_tolerance(change_tolerance_value(tolerance));
+        }
+        if let Some(fee_rate) = fee_rate {
+            req.set_fee_rate(fee_rate);
+        }
+        let resp = self.client.send_coins(self.options(), req);
+        wait(resp)
+    }
+
+    pub fn prepare_transaction(
+        &self,
+        dest_addr: String,
+        amt: u64,
+        input_filter: Option<RpcInputFilter>,
+        change_addr_type: Option<RpcAddressType>,
+        privacy: Option<bool>,
+    ) -> Result<PrepareTransactionResponse, Box<dyn Error>> {
+        let mut req = PrepareTransactionRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_dest_addr(dest_addr);
+        req.set_amt(amt);
+        if let Some(input_filter) = input_filter {
+            req.set_input_filter(input_filter);
+        }
+        if let Some(change_addr_type) = change_addr_type {
+            req.set_change_addr_type(address_type_value(change_addr_type));
+        }
+        if let Some(privacy) = privacy {
+            req.set_privacy(privacy_value(privacy));
+        }
+        let resp = self.client.prepare_transaction(self.options(), req);
+        wait(resp)
+    }
+
+    pub fn commit_transaction(&self, prepared_id: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut req = CommitTransactionRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_prepared_id(prepared_id);
+        let resp = self.client.commit_transaction(self.options(), req);
+        Ok(wait(resp)?.serialized_raw_tx)
+    }
+
+    pub fn abandon_transaction(&self, txid: String) -> Result<(), Box<dyn Error>> {
+        let mut req = AbandonTransactionRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_txid(txid);
+        let resp = self.client.abandon_transaction(self.options(), req);
+        wait(resp)?;
+        Ok(())
+    }
+
+    pub fn accelerate_transaction(
+        &self,
+        txid: String,
+        fee_rate: u64,
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut req = AccelerateTransactionRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_txid(txid);
+        req.set_fee_rate(fee_rate);
+        let resp = self.client.accelerate_transaction(self.options(), req);
+        Ok(wait(resp)?.serialized_raw_tx)
+    }
+
+    pub fn cancel_transaction(
+        &self,
+        txid: String,
+        fee_rate: u64,
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut req = CancelTransactionRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_txid(txid);
+        req.set_fee_rate(fee_rate);
+        let resp = self.client.cancel_transaction(self.options(), req);
+        Ok(wait(resp)?.serialized_raw_tx)
+    }
+
+    /// weight, virtual size and fee at `fee_rate` of a transaction spending the given
+    /// numbers of P2PKH, P2SHWH, P2WKH and P2TR coins to `outputs` outputs
+    pub fn estimate_tx_size(
+        &self,
+        inputs: [u32; 4],
+        outputs: u32,
+        fee_rate: u64,
+    ) -> Result<EstimateTxSizeResponse, Box<dyn Error>> {
+        let mut req = EstimateTxSizeRequest::new();
+        req.set_p2pkh_inputs(inputs[0]);
+        req.set_p2shwh_inputs(inputs[1]);
+        req.set_p2wkh_inputs(inputs[2]);
+        req.set_p2tr_inputs(inputs[3]);
+        req.set_outputs(outputs);
+        req.set_fee_rate(fee_rate);
+        let resp = self.client.estimate_tx_size(self.options(), req);
+        wait(resp)
+    }
+
+    pub fn get_payment_code(&self) -> Result<String, Box<dyn Error>> {
+        let mut req = GetPaymentCodeRequest::new();
+        req.set_wallet(self.wallet.clone());
+        let resp = self.client.get_payment_code(self.options(), req);
+        Ok(wait(resp)?.payment_code)
+    }
+
+    pub fn notify_payment_code(&self, payment_code: String) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut req = NotifyPaymentCodeRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_payment_code(payment_code);
+        let resp = self.client.notify_payment_code(self.options(), req);
+        Ok(wait(resp)?.serialized_raw_tx)
+    }
+
+    pub fn payment_code_address(&self, payment_code: String) -> Result<String, Box<dyn Error>> {
+        let mut req = PaymentCodeAddressRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_payment_code(payment_code);
+        let resp = self.client.payment_code_address(self.options(), req);
+        Ok(wait(resp)?.address)
+    }
+
+    pub fn sweep_payment_codes(
+        &self,
+        addr_type: RpcAddressType,
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut req = SweepPaymentCodesRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_addr_type(addr_type);
+        let resp = self.client.sweep_payment_codes(self.options(), req);
+        Ok(wait(resp)?.serialized_raw_tx)
+    }
+
+    pub fn get_silent_payment_address(&self) -> Result<String, Box<dyn Error>> {
+        let mut req = GetSilentPaymentAddressRequest::new();
+        req.set_wallet(self.wallet.clone());
+        let resp = self.client.get_silent_payment_address(self.options(), req);
+        Ok(wait(resp)?.address)
+    }
+
+    pub fn list_silent_payment_utxos(&self) -> Result<Vec<RpcSilentPaymentUtxo>, Box<dyn Error>> {
+        let mut req = ListSilentPaymentUtxosRequest::new();
+        req.set_wallet(self.wallet.clone());
+        let resp = self.client.list_silent_payment_utxos(self.options(), req);
+        Ok(wait(resp)?.utxos.into_vec())
+    }
+
+    pub fn sweep_silent_payments(
+        &self,
+        addr_type: RpcAddressType,
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut req = SweepSilentPaymentsRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_addr_type(addr_type);
+        let resp = self.client.sweep_silent_payments(self.options(), req);
+        Ok(wait(resp)?.serialized_raw_tx)
+    }
+
+    pub fn prove_reserves(&self, message: String) -> Result<ProveReservesResponse, Box<dyn Error>> {
+        let mut req = ProveReservesRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_message(message);
+        let resp = self.client.prove_reserves(self.options(), req);
+        wait(resp)
+    }
+
+    pub fn get_spending_policy(&self) -> Result<GetSpendingPolicyResponse, Box<dyn Error>> {
+        let mut req = GetSpendingPolicyRequest::new();
+        req.set_wallet(self.wallet.clone());
+        let resp = self.client.get_spending_policy(self.options(), req);
+        wait(resp)
+    }
+
+    pub fn set_spending_policy(&self, policy: RpcSpendingPolicy) -> Result<(), Box<dyn Error>> {
+        let mut req = SetSpendingPolicyRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_policy(policy);
+        let resp = self.client.set_spending_policy(self.options(), req);
+        wait(resp)?;
+        Ok(())
+    }
+
+    pub fn list_pending_spends(&self) -> Result<Vec<RpcPendingSpend>, Box<dyn Error>> {
+        let mut req = ListPendingSpendsRequest::new();
+        req.set_wallet(self.wallet.clone());
+        let resp = self.client.list_pending_spends(self.options(), req);
+        Ok(wait(resp)?.pending_spends.into_vec())
+    }
+
+    pub fn approve_spend(&self, id: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut req = ApproveSpendRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_id(id);
+        let resp = self.client.approve_spend(self.options(), req);
+        Ok(wait(resp)?.serialized_raw_tx)
+    }
+
+    pub fn reject_spend(&self, id: u64) -> Result<(), Box<dyn Error>> {
+        let mut req = RejectSpendRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_id(id);
+        let resp = self.client.reject_spend(self.options(), req);
+        wait(resp)?;
+        Ok(())
+    }
+
+    pub fn get_audit_log(
+        &self,
+        start: u64,
+        limit: u32,
+    ) -> Result<Vec<RpcAuditEntry>, Box<dyn Error>> {
+        let mut req = GetAuditLogRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_start(start);
+        req.set_limit(limit);
+        let resp = self.client.get_audit_log(self.options(), req);
+        Ok(wait(resp)?.entries.into_vec())
+    }
+
+    pub fn fund_channel(
+        &self,
+        witness_script: Vec<u8>,
+        amt: u64,
+        fee_rate: u64,
+    ) -> Result<FundChannelResponse, Box<dyn Error>> {
+        let mut req = FundChannelRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_witness_script(witness_script);
+        req.set_amt(amt);
+        req.set_fee_rate(fee_rate);
+        let resp = self.client.fund_channel(self.options(), req);
+        wait(resp)
+    }
+
+    pub fn release_funding(&self, funding_id: u64) -> Result<(), Box<dyn Error>> {
+        let mut req = ReleaseFundingRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_funding_id(funding_id);
+        let resp = self.client.release_funding(self.options(), req);
+        wait(resp)?;
+        Ok(())
+    }
+
+    pub fn publish_funding(&self, funding_id: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut req = PublishFundingRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_funding_id(funding_id);
+        let resp = self.client.publish_funding(self.options(), req);
+        Ok(wait(resp)?.serialized_raw_tx)
     }
 
     pub fn unlock_coins(&self, lock_id: u64) {
         let mut req = UnlockCoinsRequest::new();
+        req.set_wallet(self.wallet.clone());
         req.set_lock_id(lock_id);
 
-        let resp = self.client.unlock_coins(grpc::RequestOptions::new(), req);
+        let resp = self.client.unlock_coins(self.options(), req);
         resp.wait().unwrap();
     }
 
+    pub fn freeze_utxo(&self, out_point: RpcOutPoint) -> Result<(), Box<dyn Error>> {
+        let mut req = FreezeUtxoRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_out_point(out_point);
+        let resp = self.client.freeze_utxo(self.options(), req);
+        wait(resp)?;
+        Ok(())
+    }
+
+    pub fn set_address_label(&self, address: String, label: String) -> Result<(), Box<dyn Error>> {
+        let mut req = SetAddressLabelRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_address(address);
+        req.set_label(label);
+        let resp = self.client.set_address_label(self.options(), req);
+        wait(resp)?;
+        Ok(())
+    }
+
+    pub fn unfreeze_utxo(&self, out_point: RpcOutPoint) -> Result<(), Box<dyn Error>> {
+        let mut req = UnfreezeUtxoRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_out_point(out_point);
+        let resp = self.client.unfreeze_utxo(self.options(), req);
+        wait(resp)?;
+        Ok(())
+    }
+
     pub fn sync_with_tip(&self) {
-        let req = SyncWithTipRequest::new();
-        let resp = self.client.sync_with_tip(grpc::RequestOptions::new(), req);
+        let mut req = SyncWithTipRequest::new();
+        req.set_wallet(self.wallet.clone());
+        let resp = self.client.sync_with_tip(self.options(), req);
         resp.wait().unwrap();
     }
 
+    /// progress of the sync, the iterator ends after the message with `done` set
+    pub fn sync_with_tip_stream(
+        &self,
+    ) -> impl Iterator<Item = Result<RpcSyncProgress, grpc::Error>> {
+        let mut req = SyncWithTipRequest::new();
+        req.set_wallet(self.wallet.clone());
+        self.client
+            .sync_with_tip_stream(self.options(), req)
+            .wait_drop_metadata()
+    }
+
+    /// progress of the rescan from `from_height`, the first message carries the id
+    /// of the operation for `cancel_operation`
+    pub fn rescan_stream(
+        &self,
+        from_height: u64,
+    ) -> impl Iterator<Item = Result<RpcSyncProgress, grpc::Error>> {
+        let mut req = RescanRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_from_height(from_height);
+        self.client.rescan_stream(self.options(), req).wait_drop_metadata()
+    }
+
+    pub fn cancel_operation(&self, operation_id: u64) -> Result<(), Box<dyn Error>> {
+        let mut req = CancelOperationRequest::new();
+        req.set_operation_id(operation_id);
+        let resp = self.client.cancel_operation(self.options(), req);
+        wait(resp)?;
+        Ok(())
+    }
+
+    pub fn export_backup(&self, path: String, passphrase: String) -> Result<(), Box<dyn Error>> {
+        let mut req = ExportBackupRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_path(path);
+        req.set_passphrase(passphrase);
+        let resp = self.client.export_backup(self.options(), req);
+        wait(resp)?;
+        Ok(())
+    }
+
+    pub fn export_electrum_wallet(&self, path: String) -> Result<(), Box<dyn Error>> {
+        let mut req = ExportElectrumWalletRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_path(path);
+        let resp = self.client.export_electrum_wallet(self.options(), req);
+        wait(resp)?;
+        Ok(())
+    }
+
+    pub fn restore_backup(
+        &self,
+        path: String,
+        passphrase: String,
+        wallet_passphrase: String,
+        salt: String,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut req = RestoreBackupRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_path(path);
+        req.set_passphrase(passphrase);
+        req.set_wallet_passphrase(wallet_passphrase);
+        req.set_salt(salt);
+        let resp = self.client.restore_backup(self.options(), req);
+        wait(resp)?;
+        Ok(())
+    }
+
+    pub fn export_state_snapshot(&self, path: String) -> Result<(), Box<dyn Error>> {
+        let mut req = ExportStateSnapshotRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_path(path);
+        let resp = self.client.export_state_snapshot(self.options(), req);
+        wait(resp)?;
+        Ok(())
+    }
+
+    /// returns the height the wallet syncs on from
+    pub fn import_state_snapshot(&self, path: String) -> Result<u64, Box<dyn Error>> {
+        let mut req = ImportStateSnapshotRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_path(path);
+        let resp = self.client.import_state_snapshot(self.options(), req);
+        Ok(wait(resp)?.height)
+    }
+
+    /// the issues of the stored state, with `repair` the ones left after repairing
+    pub fn check_wallet(&self, repair: bool) -> Result<CheckWalletResponse, Box<dyn Error>> {
+        let mut req = CheckWalletRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_repair(repair);
+        let resp = self.client.check_wallet(self.options(), req);
+        wait(resp)
+    }
+
+    /// create a new wallet on the server, returns its mnemonic
+    pub fn create_wallet(
+        &self,
+        wallet: String,
+        passphrase: String,
+        salt: String,
+        mnemonic_words: u32,
+        account_paths: Vec<(RpcAddressType, String)>,
+    ) -> Result<String, Box<dyn Error>> {
+        let mut req = CreateWalletRequest::new();
+        req.set_wallet(wallet);
+        req.set_passphrase(passphrase);
+        req.set_salt(salt);
+        req.set_mnemonic_words(mnemonic_words);
+        let account_paths = account_paths
+            .into_iter()
+            .map(|(addr_type, path)| {
+                let mut account_path = AccountPath::new();
+                account_path.set_addr_type(addr_type);
+                account_path.set_path(path);
+                account_path
+            })
+            .collect();
+        req.set_account_paths(RepeatedField::from_vec(account_paths));
+        let resp = self.client.create_wallet(self.options(), req);
+        Ok(wait(resp)?.mnemonic)
+    }
+
+    /// `watch_only_keys` is the JSON written by `wallet-signer export_keys`
+    pub fn create_watch_only_wallet(
+        &self,
+        wallet: String,
+        watch_only_keys: String,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut req = CreateWalletRequest::new();
+        req.set_wallet(wallet);
+        req.set_watch_only_keys(watch_only_keys);
+        let resp = self.client.create_wallet(self.options(), req);
+        wait(resp)?;
+        Ok(())
+    }
+
+    /// which problem the words of `mnemonic` have, if any, nothing is sent to a wallet
+    pub fn validate_mnemonic(
+        &self,
+        mnemonic: String,
+    ) -> Result<ValidateMnemonicResponse, Box<dyn Error>> {
+        let mut req = ValidateMnemonicRequest::new();
+        req.set_mnemonic(mnemonic);
+        let resp = self.client.validate_mnemonic(self.options(), req);
+        wait(resp)
+    }
+
+    pub fn open_wallet(
+        &self,
+        wallet: String,
+        passphrase: String,
+        salt: String,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut req = OpenWalletRequest::new();
+        req.set_wallet(wallet);
+        req.set_passphrase(passphrase);
+        req.set_salt(salt);
+        let resp = self.client.open_wallet(self.options(), req);
+        wait(resp)?;
+        Ok(())
+    }
+
+    pub fn lock_wallet(&self) -> Result<(), Box<dyn Error>> {
+        let mut req = LockWalletRequest::new();
+        req.set_wallet(self.wallet.clone());
+        let resp = self.client.lock_wallet(self.options(), req);
+        wait(resp)?;
+        Ok(())
+    }
+
+    /// `timeout` in seconds, the wallet stays unlocked without one
+    pub fn unlock_wallet(
+        &self,
+        passphrase: String,
+        salt: String,
+        timeout: Option<u64>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut req = UnlockWalletRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_passphrase(passphrase);
+        req.set_salt(salt);
+        req.set_timeout(timeout.unwrap_or(0));
+        let resp = self.client.unlock_wallet(self.options(), req);
+        wait(resp)?;
+        Ok(())
+    }
+
+    pub fn list_wallets(&self) -> Vec<String> {
+        let req = ListWalletsRequest::new();
+        let resp = self.client.list_wallets(self.options(), req);
+        resp.wait().unwrap().1.wallets.into_vec()
+    }
+
+    /// events of the wallet as they happen, the iterator blocks waiting for the next one
+    pub fn subscribe_events(&self) -> impl Iterator<Item = Result<RpcWalletEvent, grpc::Error>> {
+        let mut req = SubscribeEventsRequest::new();
+        req.set_wallet(self.wallet.clone());
+        self.client
+            .subscribe_events(self.options(), req)
+            .wait_drop_metadata()
+    }
+
+    pub fn get_info(&self) -> Result<GetInfoResponse, Box<dyn Error>> {
+        let req = GetInfoRequest::new();
+        let resp = self.client.get_info(self.options(), req);
+        wait(resp)
+    }
+
+    /// replace the log filter of the daemon by `filter`, the previous one is returned,
+    /// an empty `filter` changes nothing
+    pub fn set_log_level(&self, filter: String) -> Result<String, Box<dyn Error>> {
+        let mut req = SetLogLevelRequest::new();
+        req.set_filter(filter);
+        let resp = self.client.set_log_level(self.options(), req);
+        Ok(wait(resp)?.previous)
+    }
+
+    /// reconnect the wallet to its backend, or to the one at `address` if it is not empty,
+    /// empty `user` and `password` keep the credentials of the current one
+    pub fn reconnect(
+        &self,
+        address: String,
+        user: String,
+        password: String,
+    ) -> Result<RpcConnectionStatus, Box<dyn Error>> {
+        let mut req = ReconnectRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_address(address);
+        req.set_user(user);
+        req.set_password(password);
+        let resp = self.client.reconnect(self.options(), req);
+        Ok(wait(resp)?.take_status())
+    }
+
+    /// move the wallet to the backend of `kind` at `address`, empty `user` and `password`
+    /// take the credentials of the daemon
+    pub fn switch_backend(
+        &self,
+        kind: BackendKind,
+        address: String,
+        user: String,
+        password: String,
+    ) -> Result<RpcConnectionStatus, Box<dyn Error>> {
+        let mut req = SwitchBackendRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_backend(kind);
+        req.set_address(address);
+        req.set_user(user);
+        req.set_password(password);
+        let resp = self.client.switch_backend(self.options(), req);
+        Ok(wait(resp)?.take_status())
+    }
+
+    pub fn connection_status(&self) -> Result<RpcConnectionStatus, Box<dyn Error>> {
+        let mut req = GetConnectionStatusRequest::new();
+        req.set_wallet(self.wallet.clone());
+        let resp = self.client.get_connection_status(self.options(), req);
+        Ok(wait(resp)?.take_status())
+    }
+
+    /// the transaction history of the wallet along with the fees it paid
+    pub fn get_transactions(&self) -> Result<GetTransactionsResponse, Box<dyn Error>> {
+        let mut req = GetTransactionsRequest::new();
+        req.set_wallet(self.wallet.clone());
+        let resp = self.client.get_transactions(self.options(), req);
+        wait(resp)
+    }
+
+    /// what the accounts and their addresses received and sent
+    pub fn get_account_stats(&self) -> Result<GetAccountStatsResponse, Box<dyn Error>> {
+        let mut req = GetAccountStatsRequest::new();
+        req.set_wallet(self.wallet.clone());
+        let resp = self.client.get_account_stats(self.options(), req);
+        wait(resp)
+    }
+
+    /// write the history of the wallet to `out` as the daemon streams it
+    pub fn export_history(
+        &self,
+        format: RpcExportFormat,
+        out: &mut dyn Write,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut req = ExportHistoryRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_format(format);
+        let chunks = self
+            .client
+            .export_history(self.options(), req)
+            .wait_drop_metadata();
+        for chunk in chunks {
+            out.write_all(&chunk.map_err(from_grpc)?.data)?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    /// move the coins of `address` to `dest_addr`, returns the published transaction
+    pub fn sweep_address(
+        &self,
+        address: String,
+        dest_addr: String,
+        fee_rate: u64,
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut req = SweepAddressRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_address(address);
+        req.set_dest_addr(dest_addr);
+        req.set_fee_rate(fee_rate);
+        let resp = self.client.sweep_address(self.options(), req);
+        Ok(wait(resp)?.serialized_raw_tx)
+    }
+
+    /// address locked for `blocks` after the output is confirmed, or until `height`,
+    /// either is 0
+    pub fn new_timelocked_address(
+        &self,
+        blocks: u32,
+        height: u32,
+    ) -> Result<String, Box<dyn Error>> {
+        let mut req = NewTimelockedAddressRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_blocks(blocks);
+        req.set_height(height);
+        let resp = self.client.new_timelocked_address(self.options(), req);
+        Ok(wait(resp)?.address)
+    }
+
+    /// `recovery_pk` is hex encoded, the hot key spends a deposit `delay` blocks after
+    /// it is confirmed
+    pub fn set_vault(&self, recovery_pk: String, delay: u32) -> Result<(), Box<dyn Error>> {
+        let mut req = SetVaultRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_recovery_pk(recovery_pk);
+        req.set_delay(delay);
+        let resp = self.client.set_vault(self.options(), req);
+        wait(resp)?;
+        Ok(())
+    }
+
+    pub fn new_vault_address(&self) -> Result<String, Box<dyn Error>> {
+        let mut req = NewVaultAddressRequest::new();
+        req.set_wallet(self.wallet.clone());
+        let resp = self.client.new_vault_address(self.options(), req);
+        Ok(wait(resp)?.address)
+    }
+
+    /// move the mature vault deposits to `dest_addr`, returns the published transaction
+    pub fn unvault(&self, dest_addr: String, fee_rate: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut req = UnvaultRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_dest_addr(dest_addr);
+        req.set_fee_rate(fee_rate);
+        let resp = self.client.unvault(self.options(), req);
+        Ok(wait(resp)?.serialized_raw_tx)
+    }
+
+    pub fn get_descriptors(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut req = GetDescriptorsRequest::new();
+        req.set_wallet(self.wallet.clone());
+        let resp = self.client.get_descriptors(self.options(), req);
+        Ok(wait(resp)?.descriptors.into_vec())
+    }
+
+    pub fn get_import_descriptors(&self) -> Result<String, Box<dyn Error>> {
+        let mut req = GetImportDescriptorsRequest::new();
+        req.set_wallet(self.wallet.clone());
+        let resp = self.client.get_import_descriptors(self.options(), req);
+        Ok(wait(resp)?.json)
+    }
+
+    pub fn migrate_derivation(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut req = MigrateDerivationRequest::new();
+        req.set_wallet(self.wallet.clone());
+        let resp = self.client.migrate_derivation(self.options(), req);
+        Ok(wait(resp)?.serialized_raw_tx)
+    }
+
+    pub fn get_script_account_xpub(&self) -> Result<String, Box<dyn Error>> {
+        let mut req = GetScriptAccountXpubRequest::new();
+        req.set_wallet(self.wallet.clone());
+        let resp = self.client.get_script_account_xpub(self.options(), req);
+        Ok(wait(resp)?.xpub)
+    }
+
+    /// the xpub of `get_script_account_xpub` with the origin cosigners put into policies
+    pub fn get_script_account_key(&self) -> Result<String, Box<dyn Error>> {
+        let mut req = GetScriptAccountXpubRequest::new();
+        req.set_wallet(self.wallet.clone());
+        let resp = self.client.get_script_account_xpub(self.options(), req);
+        Ok(wait(resp)?.key)
+    }
+
+    pub fn register_policy(&self, name: String, policy: String) -> Result<(), Box<dyn Error>> {
+        let mut req = RegisterPolicyRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_name(name);
+        req.set_policy(policy);
+        let resp = self.client.register_policy(self.options(), req);
+        wait(resp)?;
+        Ok(())
+    }
+
+    pub fn new_script_address(&self, name: String) -> Result<String, Box<dyn Error>> {
+        let mut req = NewScriptAddressRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_name(name);
+        let resp = self.client.new_script_address(self.options(), req);
+        Ok(wait(resp)?.address)
+    }
+
+    /// the PSBT signed by the wallet, along with the published transaction
+    /// if nobody else has to sign
+    pub fn spend_script_coins(
+        &self,
+        name: String,
+        dest_addr: String,
+        fee_rate: u64,
+    ) -> Result<SpendScriptCoinsResponse, Box<dyn Error>> {
+        let mut req = SpendScriptCoinsRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_name(name);
+        req.set_dest_addr(dest_addr);
+        req.set_fee_rate(fee_rate);
+        let resp = self.client.spend_script_coins(self.options(), req);
+        wait(resp)
+    }
+
+    /// publish the transaction of a PSBT of `spend_script_coins` the cosigners signed
+    pub fn finalize_script_psbt(&self, psbt: Vec<u8>) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut req = FinalizeScriptPsbtRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_psbt(psbt);
+        let resp = self.client.finalize_script_psbt(self.options(), req);
+        Ok(wait(resp)?.serialized_raw_tx)
+    }
+
+    pub fn create_unsigned_tx(
+        &self,
+        dest_addr: String,
+        amt: u64,
+        input_filter: Option<RpcInputFilter>,
+        change_addr_type: Option<RpcAddressType>,
+        privacy: Option<bool>,
+    ) -> Result<CreateUnsignedTxResponse, Box<dyn Error>> {
+        let mut req = CreateUnsignedTxRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_dest_addr(dest_addr);
+        req.set_amt(amt);
+        if let Some(input_filter) = input_filter {
+            req.set_input_filter(input_filter);
+        }
+        if let Some(change_addr_type) = change_addr_type {
+            req.set_change_addr_type(address_type_value(change_addr_type));
+        }
+        if let Some(privacy) = privacy {
+            req.set_privacy(privacy_value(privacy));
+        }
+        let resp = self.client.create_unsigned_tx(self.options(), req);
+        wait(resp)
+    }
+
+    pub fn list_unsigned_txs(&self) -> Result<Vec<RpcUnsignedTx>, Box<dyn Error>> {
+        let mut req = ListUnsignedTxsRequest::new();
+        req.set_wallet(self.wallet.clone());
+        let resp = self.client.list_unsigned_txs(self.options(), req);
+        Ok(wait(resp)?.unsigned_txs.into_vec())
+    }
+
+    pub fn broadcast_signed_psbt(&self, id: u64, psbt: Vec<u8>) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut req = BroadcastSignedPsbtRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_id(id);
+        req.set_psbt(psbt);
+        let resp = self.client.broadcast_signed_psbt(self.options(), req);
+        Ok(wait(resp)?.serialized_raw_tx)
+    }
+
+    pub fn cancel_unsigned_tx(&self, id: u64) -> Result<(), Box<dyn Error>> {
+        let mut req = CancelUnsignedTxRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_id(id);
+        let resp = self.client.cancel_unsigned_tx(self.options(), req);
+        wait(resp)?;
+        Ok(())
+    }
+
+    /// the frames of an animated QR code of `payload`, `max_fragment_len` 0 for the default
+    pub fn encode_qr(
+        &self,
+        payload: EncodeQrRequest_oneof_payload,
+        format: RpcQrFormat,
+        max_fragment_len: u32,
+        extra_parts: u32,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut req = EncodeQrRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_format(format);
+        req.set_max_fragment_len(max_fragment_len);
+        req.set_extra_parts(extra_parts);
+        req.payload = Some(payload);
+        let resp = self.client.encode_qr(self.options(), req);
+        Ok(wait(resp)?.parts.into_vec())
+    }
+
+    pub fn decode_qr(&self, parts: Vec<String>) -> Result<DecodeQrResponse, Box<dyn Error>> {
+        let mut req = DecodeQrRequest::new();
+        req.set_wallet(self.wallet.clone());
+        req.set_parts(RepeatedField::from_vec(parts));
+        let resp = self.client.decode_qr(self.options(), req);
+        wait(resp)
+    }
+
     pub fn shutdown(&self) {
         let req = ShutdownRequest::new();
-        let resp = self.client.shutdown(grpc::RequestOptions::new(), req);
+        let resp = self.client.shutdown(self.options(), req);
         resp.wait().unwrap();
     }
 }
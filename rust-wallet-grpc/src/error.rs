@@ -0,0 +1,176 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Errors of the RPC
+//!
+//! Failed calls carry an `ErrorDetails` message. grpc can't send it in the trailers,
+//! so it is serialized and hex encoded as the grpc-message, while the grpc-status is
+//! the closest standard status for clients which don't decode the details
+//!
+use bitcoin_rpc_client::Error as BitcoinClientError;
+use protobuf::{self, Message};
+use wallet::error::WalletError;
+
+use std::{fmt, io, error::Error};
+
+use super::walletrpc::{ErrorCode, ErrorDetails};
+
+// grpc status codes
+const CANCELLED: i32 = 1;
+const UNKNOWN: i32 = 2;
+const INVALID_ARGUMENT: i32 = 3;
+const NOT_FOUND: i32 = 5;
+const PERMISSION_DENIED: i32 = 7;
+const RESOURCE_EXHAUSTED: i32 = 8;
+const FAILED_PRECONDITION: i32 = 9;
+const UNAVAILABLE: i32 = 14;
+const UNAUTHENTICATED: i32 = 16;
+
+impl fmt::Display for ErrorDetails {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for ErrorDetails {}
+
+/// details of an error, it is retryable if the wallet couldn't reach its blockchain backend
+/// or the call was over the rate limit
+pub fn rpc_error(code: ErrorCode, message: &str) -> ErrorDetails {
+    let mut details = ErrorDetails::new();
+    details.set_code(code);
+    details.set_message(message.to_owned());
+    details.set_retryable(
+        code == ErrorCode::BACKEND_UNAVAILABLE || code == ErrorCode::RATE_LIMITED,
+    );
+    details
+}
+
+impl<'a> From<&'a (dyn Error + 'static)> for ErrorDetails {
+    fn from(e: &'a (dyn Error + 'static)) -> Self {
+        if let Some(details) = e.downcast_ref::<ErrorDetails>() {
+            return details.clone();
+        }
+        let code = match e.downcast_ref::<WalletError>() {
+            Some(&WalletError::IO(_)) | Some(&WalletError::BackendUnavailable(_)) => {
+                ErrorCode::BACKEND_UNAVAILABLE
+            }
+            Some(&WalletError::BackendRejected(_)) => ErrorCode::BACKEND_REJECTED,
+            Some(&WalletError::InsufficientFunds) => ErrorCode::INSUFFICIENT_FUNDS,
+            Some(&WalletError::TransactionLimitExceeded(_))
+            | Some(&WalletError::DailyLimitExceeded(_))
+            | Some(&WalletError::DestinationNotAllowed(_)) => ErrorCode::POLICY_VIOLATION,
+            Some(&WalletError::ApprovalRequired(_)) => ErrorCode::APPROVAL_REQUIRED,
+            Some(&WalletError::WatchOnly) => ErrorCode::WATCH_ONLY,
+            Some(&WalletError::Locked) => ErrorCode::WALLET_LOCKED,
+            Some(&WalletError::Cancelled) => ErrorCode::CANCELLED,
+            Some(&WalletError::InvalidBackup)
+            | Some(&WalletError::BackupAuthenticationFailed)
+            | Some(&WalletError::BackupKeyNotMatch)
+            | Some(&WalletError::InvalidSnapshot)
+            | Some(&WalletError::SnapshotChecksumNotMatch)
+            | Some(&WalletError::SnapshotWalletNotMatch) => ErrorCode::INVALID_BACKUP,
+            Some(&WalletError::UnknownMnemonicWord)
+            | Some(&WalletError::InvalidMnemonicLength)
+            | Some(&WalletError::InvalidMnemonicData)
+            | Some(&WalletError::MnemonicChecksumNotMatch)
+            | Some(&WalletError::InvalidShare)
+            | Some(&WalletError::InvalidShareSet)
+            | Some(&WalletError::InvalidShareParameters)
+            | Some(&WalletError::ShareDigestNotMatch)
+            | Some(&WalletError::InvalidPaymentCode)
+            | Some(&WalletError::InvalidWatchOnlyKeys)
+            | Some(&WalletError::InvalidQrPart)
+            | Some(&WalletError::WrongPassphrase)
+            | Some(&WalletError::DatabaseHoldsOtherWallet)
+            | Some(&WalletError::DatabaseNetworkMismatch(_))
+            | Some(&WalletError::InvalidAddress(_))
+            | Some(&WalletError::AddressNetworkMismatch(_)) => ErrorCode::INVALID_ARGUMENT,
+            Some(_) => ErrorCode::UNKNOWN,
+            None if e.is::<io::Error>() || e.is::<BitcoinClientError>() => {
+                ErrorCode::BACKEND_UNAVAILABLE
+            }
+            None => ErrorCode::UNKNOWN,
+        };
+        rpc_error(code, &e.to_string())
+    }
+}
+
+/// error a call fails with on the server
+pub fn to_grpc(e: &(dyn Error + 'static)) -> grpc::Error {
+    let details = ErrorDetails::from(e);
+    let grpc_status = match details.code {
+        ErrorCode::UNKNOWN => UNKNOWN,
+        ErrorCode::INVALID_ARGUMENT | ErrorCode::INVALID_BACKUP => INVALID_ARGUMENT,
+        ErrorCode::NOT_FOUND => NOT_FOUND,
+        ErrorCode::READ_ONLY | ErrorCode::PERMISSION_DENIED => PERMISSION_DENIED,
+        ErrorCode::UNAUTHENTICATED => UNAUTHENTICATED,
+        ErrorCode::RATE_LIMITED => RESOURCE_EXHAUSTED,
+        ErrorCode::INSUFFICIENT_FUNDS
+        | ErrorCode::POLICY_VIOLATION
+        | ErrorCode::APPROVAL_REQUIRED
+        | ErrorCode::BACKEND_REJECTED
+        | ErrorCode::WATCH_ONLY
+        | ErrorCode::WALLET_LOCKED => FAILED_PRECONDITION,
+        ErrorCode::BACKEND_UNAVAILABLE => UNAVAILABLE,
+        ErrorCode::CANCELLED => CANCELLED,
+    };
+    grpc::Error::GrpcMessage(grpc::GrpcMessageError {
+        grpc_status,
+        grpc_message: hex::encode(details.write_to_bytes().unwrap()),
+    })
+}
+
+/// error of a failed call on the client, `ErrorDetails` if the server sent them
+pub fn from_grpc(e: grpc::Error) -> Box<dyn Error> {
+    if let grpc::Error::GrpcMessage(ref message) = e {
+        let details = hex::decode(&message.grpc_message)
+            .ok()
+            .and_then(|bytes| protobuf::parse_from_bytes::<ErrorDetails>(&bytes).ok());
+        if let Some(details) = details {
+            return Box::new(details);
+        }
+    }
+    Box::new(e)
+}
+
+#[cfg(test)]
+mod test {
+    use wallet::error::WalletError;
+
+    use std::error::Error;
+
+    use super::super::walletrpc::{ErrorCode, ErrorDetails};
+
+    #[test]
+    fn details_round_trip() {
+        let e: Box<dyn Error> = Box::new(WalletError::DailyLimitExceeded(500));
+        let grpc_error = super::to_grpc(&*e);
+        match grpc_error {
+            grpc::Error::GrpcMessage(ref message) => assert_eq!(message.grpc_status, 9),
+            _ => panic!("unexpected error {:?}", grpc_error),
+        }
+
+        let e = super::from_grpc(grpc_error);
+        let details = e.downcast_ref::<ErrorDetails>().unwrap();
+        assert_eq!(details.code, ErrorCode::POLICY_VIOLATION);
+        assert_eq!(details.message, WalletError::DailyLimitExceeded(500).to_string());
+        assert!(!details.retryable);
+
+        // plain errors are passed through
+        let e = super::from_grpc(grpc::Error::Other("event stream failed"));
+        assert!(e.downcast_ref::<ErrorDetails>().is_none());
+    }
+}
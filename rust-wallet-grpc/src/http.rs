@@ -0,0 +1,126 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Plain HTTP requests
+//!
+//! Just enough HTTP for the webhook and the price source, a request is sent on a connection
+//! of its own and the response is read until the server closes it
+//!
+use std::{
+    error::Error,
+    io::{Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// parts of an `http://` or `https://` URL
+pub struct Url<'a> {
+    pub tls: bool,
+    pub host: &'a str,
+    pub port: u16,
+    /// host and port as given, for the Host header
+    pub authority: &'a str,
+    /// path along with the query, "/" if the URL has none
+    pub path: &'a str,
+}
+
+impl<'a> Url<'a> {
+    pub fn parse(url: &'a str) -> Result<Self, Box<dyn Error>> {
+        let (tls, rest) = if url.starts_with("https://") {
+            (true, &url["https://".len()..])
+        } else if url.starts_with("http://") {
+            (false, &url["http://".len()..])
+        } else {
+            return Err(From::from(format!("unsupported url {}", url)));
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rfind(':') {
+            Some(i) => (&authority[..i], authority[i + 1..].parse()?),
+            None => (authority, if tls { 443 } else { 80 }),
+        };
+        Ok(Url {
+            tls,
+            host,
+            port,
+            authority,
+            path,
+        })
+    }
+}
+
+/// send `request` to the server of `url`, returns the whole response
+pub fn exchange(url: &Url, request: &str) -> Result<String, Box<dyn Error>> {
+    let stream = TcpStream::connect((url.host, url.port))?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    if url.tls {
+        let connector = native_tls::TlsConnector::new()?;
+        send(connector.connect(url.host, stream)?, request)
+    } else {
+        send(stream, request)
+    }
+}
+
+fn send<S: Read + Write>(mut stream: S, request: &str) -> Result<String, Box<dyn Error>> {
+    stream.write_all(request.as_bytes())?;
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)?;
+    Ok(String::from_utf8_lossy(&response).into_owned())
+}
+
+/// status code of the response, "HTTP/1.1 200 OK" has "200"
+pub fn status(response: &str) -> &str {
+    response.split_whitespace().nth(1).unwrap_or("")
+}
+
+/// what follows the headers of the response
+pub fn body(response: &str) -> &str {
+    match response.find("\r\n\r\n") {
+        Some(i) => &response[i + 4..],
+        None => "",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Url, status, body};
+
+    #[test]
+    fn url() {
+        let url = Url::parse("https://example.com/price?currency=usd").unwrap();
+        assert!(url.tls);
+        assert_eq!((url.host, url.port), ("example.com", 443));
+        assert_eq!(url.path, "/price?currency=usd");
+
+        let url = Url::parse("http://127.0.0.1:8080").unwrap();
+        assert!(!url.tls);
+        assert_eq!((url.host, url.port, url.authority), ("127.0.0.1", 8080, "127.0.0.1:8080"));
+        assert_eq!(url.path, "/");
+
+        assert!(Url::parse("ftp://example.com").is_err());
+    }
+
+    #[test]
+    fn response() {
+        let response = "HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n\r\n{\"price\":1}";
+        assert_eq!(status(response), "200");
+        assert_eq!(body(response), "{\"price\":1}");
+    }
+}
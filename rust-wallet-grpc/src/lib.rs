@@ -15,5 +15,18 @@
 
 pub mod server;
 pub mod client;
+pub mod error;
+pub mod acl;
+pub mod logging;
+pub mod webhook;
+pub mod http;
+pub mod price;
+pub mod publisher;
+pub mod autosync;
+pub mod rest;
+pub mod corerpc;
+pub mod reflect;
 pub mod walletrpc;
 mod walletrpc_grpc;
+pub mod reflection;
+mod reflection_grpc;
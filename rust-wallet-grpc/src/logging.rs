@@ -0,0 +1,75 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Logging
+//!
+//! The daemon logs through tracing. Every gRPC call runs in an `rpc` span named by its method,
+//! a sync in a `sync` span holding a `block` span per block and a `tx` span per transaction of
+//! the wallet, so the lines of a slow sync tell which block took the time. A span logs how long
+//! it was busy when it closes. The `log` records of the libraries end up in the same output.
+//!
+//! The filter takes the directives of `RUST_LOG`, e.g. `info,wallet::default=debug`,
+//! and is replaced at runtime by the SetLogLevel call
+//!
+use tracing_subscriber::{fmt, fmt::format::FmtSpan, EnvFilter};
+
+use std::{error::Error, mem, sync::Mutex};
+
+type Reload = Box<dyn Fn(EnvFilter) -> Result<(), String> + Send + Sync>;
+
+/// the filter of the installed subscriber
+pub struct LogFilter {
+    reload: Reload,
+    directives: Mutex<String>,
+}
+
+impl LogFilter {
+    /// the directives in effect
+    pub fn current(&self) -> String {
+        self.directives.lock().unwrap().clone()
+    }
+
+    /// filter by `directives` from now on, the previous ones are returned
+    pub fn set(&self, directives: &str) -> Result<String, Box<dyn Error>> {
+        let filter = EnvFilter::try_new(directives)?;
+        // held while reloading, concurrent calls end up with the directives they report
+        let mut current = self.directives.lock().unwrap();
+        (self.reload)(filter)?;
+        Ok(mem::replace(&mut *current, directives.to_owned()))
+    }
+}
+
+/// install the subscriber of the process, it writes a JSON object per line if `json` is set
+pub fn init(directives: &str, json: bool) -> Result<LogFilter, Box<dyn Error>> {
+    let builder = fmt()
+        .with_env_filter(EnvFilter::try_new(directives)?)
+        .with_span_events(FmtSpan::CLOSE);
+    // the handle has the type of the formatter, the closure hides it
+    let reload: Reload = if json {
+        let builder = builder.json().with_filter_reloading();
+        let handle = builder.reload_handle();
+        builder.try_init()?;
+        Box::new(move |filter| handle.reload(filter).map_err(|e| e.to_string()))
+    } else {
+        let builder = builder.with_filter_reloading();
+        let handle = builder.reload_handle();
+        builder.try_init()?;
+        Box::new(move |filter| handle.reload(filter).map_err(|e| e.to_string()))
+    };
+    Ok(LogFilter {
+        reload,
+        directives: Mutex::new(directives.to_owned()),
+    })
+}
@@ -14,21 +14,40 @@
 // limitations under the License.
 
 use structopt::StructOpt;
-use std::path::PathBuf;
+use fs2::FileExt;
+use std::{
+    fs::{self, File, OpenOptions},
+    io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    process,
+    time::Duration,
+};
 use wallet::mnemonic::Mnemonic;
+use zeroize::Zeroizing;
+use rand::seq::index;
 
 #[derive(StructOpt, Debug)]
 #[structopt(name = "wallet")]
 /// Rust Wallet Config
 pub struct Config {
     #[structopt(long="log-level", default_value="INFO")]
-    /// should be one of ERROR, WARN, INFO, DEBUG, TRACE
+    /// one of ERROR, WARN, INFO, DEBUG, TRACE or the directives of RUST_LOG,
+    /// e.g. info,wallet::default=debug
     log_level: String,
 
+    #[structopt(long="log-json")]
+    /// write a JSON object per log line
+    log_json: bool,
+
     #[structopt(long="db-path", parse(from_os_str), default_value="target/db/wallet")]
     /// path to directory with wallet data
     db_path: PathBuf,
 
+    #[structopt(long="wallets-dir", parse(from_os_str), default_value="target/db/wallets")]
+    /// directory with data of the wallets created and opened over rpc, one subdirectory each
+    wallets_dir: PathBuf,
+
     #[structopt(long="rpc-port", default_value="5051")]
     /// port of wallet's grpc server
     rpc_port: u16,
@@ -64,74 +83,642 @@ pub struct Config {
     /// create electrumx wallet
     electrumx: bool,
 
-    #[structopt(long="mode", default_value="decrypt")]
-    /// should be one of create|decrypt|recover
+    #[structopt(
+        long="mode",
+        default_value="decrypt",
+        possible_values=&["create", "decrypt", "recover", "watch-only", "auto"],
+    )]
+    /// auto opens the wallet at `db-path`, or creates one and prints its mnemonic
+    /// if there is none
     mode: String,
 
     #[structopt(long="mnemonic")]
-    /// relevant only `mode` is recover
+    /// relevant only `mode` is recover, the words are prompted for one by one if it is
+    /// not specified, an argument is visible to other users in `ps`
     mnemonic: Option<String>,
+
+    #[structopt(long="force-overwrite")]
+    /// recover even if `db-path` holds a wallet of another seed, its state is wiped,
+    /// relevant only if `mode` is recover
+    force_overwrite: bool,
+
+    #[structopt(long="watch-only-keys", parse(from_os_str))]
+    /// file written by `wallet-signer export_keys`, relevant only if `mode` is watch-only
+    watch_only_keys: Option<PathBuf>,
+
+    #[structopt(long="mnemonic-words", default_value="24", possible_values=&["12", "18", "24"])]
+    /// number of words in the mnemonic of a new wallet, one of 12|18|24
+    /// relevant only if `mode` is create, or recover to tell how many words to prompt for
+    mnemonic_words: usize,
+
+    #[structopt(long="passphrase")]
+    /// passphrase the wallet's entropy is encrypted with in the database
+    passphrase: Option<String>,
+
+    #[structopt(long="salt")]
+    /// BIP39 passphrase the seed is derived with, in decrypt mode another one than at the
+    /// creation opens a hidden wallet of the seed
+    salt: Option<String>,
+
+    #[structopt(long="interactive")]
+    /// prompt for the passphrase and salt with hidden input if they are not specified,
+    /// the mnemonic of a new wallet is shown and some of its words asked back
+    interactive: bool,
+
+    #[structopt(long="show-mnemonic")]
+    /// print the wallet's mnemonic to stdout
+    show_mnemonic: bool,
+
+    #[structopt(long="change-addr-type", default_value="p2wkh")]
+    /// address type the change goes to unless a transaction asks for another one,
+    /// should be one of p2pkh|p2shwh|p2wkh
+    change_addr_type: String,
+
+    #[structopt(long="raw-blocks")]
+    /// fetch blocks from bitcoind as raw hex and deserialize them in the wallet,
+    /// faster on the initial sync, relevant only if `electrumx` flag is not set
+    raw_blocks: bool,
+
+    #[structopt(long="silent-payments")]
+    /// scan the blocks for BIP352 silent payments, the outputs spent by their taproot inputs
+    /// are fetched from bitcoind too, relevant only if `electrumx` flag is not set
+    silent_payments: bool,
+
+    #[structopt(long="core-wallet")]
+    /// sync through a watch-only descriptor wallet of bitcoind with this name instead of
+    /// scanning the blocks, it is created if the node doesn't have it. The wallets created
+    /// over gRPC get one named <name>-<wallet>, relevant only if `electrumx` flag is not set
+    core_wallet: Option<String>,
+
+    #[structopt(long="witness-address")]
+    /// address of the rpc server of another bitcoind, may be repeated. The wallets publish
+    /// their transactions through the witnesses too, and report a witness whose tip is away
+    /// from bitcoind's after a sync, relevant only if `electrumx` flag is not set
+    witness_addresses: Vec<String>,
+
+    #[structopt(long="witness-max-lag", default_value="2")]
+    /// blocks the tip of a witness may be away from the one of bitcoind
+    witness_max_lag: usize,
+
+    #[structopt(long="verify-proofs")]
+    /// check the transactions reported by electrs against their merkle proofs and block headers,
+    /// relevant only if `electrumx` flag is set
+    verify_proofs: bool,
+
+    #[structopt(long="prune-spent-after")]
+    /// forget the spent coins of the wallets once the spending transaction has this many
+    /// confirmations, they are archived for good if not specified
+    prune_spent_after: Option<usize>,
+
+    #[structopt(long="spend-unconfirmed-change")]
+    /// let payments spend the change of earlier ones before it is confirmed,
+    /// unconfirmed coins received from others are never spent
+    spend_unconfirmed_change: bool,
+
+    #[structopt(long="spend-uneconomical")]
+    /// select coins worth less than the fee of their input at the fee rate of a transaction,
+    /// e.g. to consolidate dust, they are left out otherwise
+    spend_uneconomical: bool,
+
+    #[structopt(long="privacy")]
+    /// payments spend coins of a single account and address type and avoid change where
+    /// a coin fits, each call may turn it on or off for itself
+    privacy: bool,
+
+    #[structopt(long="avoid-reuse")]
+    /// spend the coins of an address which received more than one payment all in the same
+    /// transaction, or none of them
+    avoid_reuse: bool,
+
+    #[structopt(long="change-tolerance")]
+    /// satoshis a payment may pay above its amount and fee to do without change, coins paying
+    /// it exactly within them are looked for first, SendCoins may set its own
+    change_tolerance: Option<u64>,
+
+    #[structopt(long="fee-rate", default_value="20")]
+    /// satoshi per virtual byte payments pay, SendCoins may set its own
+    fee_rate: u64,
+
+    #[structopt(long="backend-retries", default_value="3")]
+    /// times a call to bitcoind failing on the connection is made, with a delay doubling
+    /// after every failure, relevant only if `electrumx` flag is not set
+    backend_retries: u32,
+
+    #[structopt(long="connect-timeout", default_value="5")]
+    /// seconds connecting to electrs may take, bitcoind is given the read timeout
+    connect_timeout: u64,
+
+    #[structopt(long="read-timeout", default_value="60")]
+    /// seconds bitcoind or electrs may take to answer a call before it fails
+    read_timeout: u64,
+
+    #[structopt(long="read-only")]
+    /// serve balance and utxo queries only, reject calls changing the wallet
+    read_only: bool,
+
+    #[structopt(long="acl-file", parse(from_os_str))]
+    /// JSON file of the tokens gRPC clients authenticate with, the permission and rate limits
+    /// of each, every client may make every call without it
+    acl_file: Option<PathBuf>,
+
+    #[structopt(long="webhook-url")]
+    /// post deposits and confirmations of the wallets to this http(s) URL
+    webhook_url: Option<String>,
+
+    #[structopt(long="webhook-secret", default_value="")]
+    /// key the webhook requests are signed with, the signature is in the X-Signature header
+    webhook_secret: String,
+
+    #[structopt(long="webhook-confirmations", default_value="1")]
+    /// number of confirmations a transaction is reported confirmed at,
+    /// the electrumx backend reports the first confirmation only
+    webhook_confirmations: usize,
+
+    #[structopt(long="zmqpubwallet")]
+    /// publish wallettx, walletbalance and walletblock notifications on this ZMQ endpoint,
+    /// e.g. tcp://127.0.0.1:28340
+    zmqpubwallet: Option<String>,
+
+    #[structopt(long="rest-port")]
+    /// serve newaddress, balance, send, listunspent and history as JSON over HTTP on this port
+    /// of localhost, the routes are described by GET /v1/openapi.json
+    rest_port: Option<u16>,
+
+    #[structopt(long="core-rpc-port")]
+    /// answer getnewaddress, getbalance, sendtoaddress, listunspent and gettransaction like the
+    /// wallet RPC of bitcoind on this port of localhost, for tools written against bitcoind
+    core_rpc_port: Option<u16>,
+
+    #[structopt(long="fiat-currency")]
+    /// value balances and transactions in this currency, e.g. usd, needs --price-url
+    fiat_currency: Option<String>,
+
+    #[structopt(long="price-url")]
+    /// http(s) URL of the current price of a bitcoin, {currency} is replaced by the currency
+    price_url: Option<String>,
+
+    #[structopt(long="historical-price-url")]
+    /// URL of the price at a unix time, {time} is replaced by the time, used for the value
+    /// of a transaction when it was confirmed
+    historical_price_url: Option<String>,
+
+    #[structopt(long="price-pointer", default_value="/price")]
+    /// JSON pointer of the price in the responses, e.g. /bitcoin/{currency}
+    price_pointer: String,
+
+    #[structopt(long="price-ttl", default_value="60")]
+    /// seconds the current price is used for before it is asked again
+    price_ttl: u64,
+
+    #[structopt(long="auto-sync-interval")]
+    /// sync the open wallets in the background every this many seconds
+    auto_sync_interval: Option<u64>,
+
+    #[structopt(long="auto-sync-jitter", default_value="5")]
+    /// at most this many seconds are added to each interval at random
+    auto_sync_jitter: u64,
+
+    #[structopt(long="auto-sync-on-block")]
+    /// sync the open wallets on each block bitcoind announces on --zmqpubrawblock
+    auto_sync_on_block: bool,
+
+    #[structopt(long="auto-sync-mempool")]
+    /// hand the transactions bitcoind announces on --zmqpubrawtx to the open wallets
+    /// as they enter its mempool
+    auto_sync_mempool: bool,
 }
 
-fn main() {
-    use rust_wallet_grpc::server;
-    use std::str::FromStr;
+// two daemons on the same database corrupt it, the lock is held until the returned file
+// is dropped
+fn lock_db(db_path: &Path) -> io::Result<File> {
+    let lock_path = db_path.with_extension("lock");
+    if let Some(parent) = lock_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let lock = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .open(&lock_path)?;
+    lock.try_lock_exclusive()?;
+    Ok(lock)
+}
+
+// the lock of `lock_db` or the end of the process
+fn lock_db_or_exit(db_path: &Path) -> File {
+    lock_db(db_path).unwrap_or_else(|e| {
+        log::error!("database {} is used by another wallet: {}", db_path.display(), e);
+        process::exit(1);
+    })
+}
 
-    use wallet::{walletlibrary::{WalletLibraryMode, KeyGenConfig, DEFAULT_NETWORK}, context::GlobalContext};
+// takes a secret from the command line, or prompts for it, or falls back to the default
+fn read_secret(
+    value: Option<String>,
+    interactive: bool,
+    confirm: bool,
+    name: &str,
+    default: &str,
+) -> Zeroizing<String> {
+    if let Some(value) = value {
+        return Zeroizing::new(value);
+    }
+    if !interactive {
+        return Zeroizing::new(default.to_owned());
+    }
+
+    // asked again until the repetition matches
+    let value = loop {
+        let prompt = format!("{} (empty for default): ", name);
+        let value = Zeroizing::new(rpassword::read_password_from_tty(Some(&prompt)).unwrap());
+        if !confirm {
+            break value;
+        }
+        let prompt = format!("repeat {}: ", name);
+        let repeated = Zeroizing::new(rpassword::read_password_from_tty(Some(&prompt)).unwrap());
+        if *value == *repeated {
+            break value;
+        }
+        eprintln!("the {}s do not match, enter it again", name);
+    };
+    if value.is_empty() {
+        Zeroizing::new(default.to_owned())
+    } else {
+        value
+    }
+}
+
+// prompts for the words of a mnemonic one by one with hidden input, a word may be cut
+// to a prefix of the wordlist only one word starts with. Asks again for all of them
+// if they don't add up to a mnemonic with a valid checksum
+fn read_mnemonic(word_count: usize) -> Mnemonic {
+    loop {
+        let mut phrase = Zeroizing::new(String::new());
+        let mut typed_words = 0;
+        while typed_words < word_count {
+            let prompt = format!("word {} of {}: ", typed_words + 1, word_count);
+            let typed = Zeroizing::new(rpassword::read_password_from_tty(Some(&prompt)).unwrap());
+            let candidates = Mnemonic::complete(typed.trim());
+            match candidates.len() {
+                0 => eprintln!("no word of the wordlist starts with it"),
+                1 => {
+                    phrase.push_str(candidates[0]);
+                    phrase.push(' ');
+                    typed_words += 1;
+                }
+                n if n <= 8 => eprintln!("one of {}?", candidates.join(", ")),
+                n => eprintln!("{} words start with it, type more letters", n),
+            }
+        }
+        if let Ok(mnemonic) = Mnemonic::from(phrase.trim_end()) {
+            if mnemonic.check().is_ok() {
+                return mnemonic;
+            }
+        }
+        eprintln!("the words don't have a valid checksum, enter them again");
+    }
+}
+
+// shows the mnemonic of a new wallet and asks for three of its words back,
+// the daemon doesn't start before they are written down
+fn confirm_mnemonic(mnemonic: &Mnemonic) {
+    for (i, word) in mnemonic.words().iter().enumerate() {
+        println!("{:>2}. {}", i + 1, word);
+    }
+    println!("write the words down, they restore the wallet");
+
+    let word_count = mnemonic.words().len();
+    let mut positions = index::sample(&mut rand::thread_rng(), word_count, 3).into_vec();
+    positions.sort();
+    for position in positions {
+        loop {
+            let prompt = format!("word {}: ", position + 1);
+            let typed = Zeroizing::new(rpassword::read_password_from_tty(Some(&prompt)).unwrap());
+            if Mnemonic::complete(typed.trim()) == [mnemonic.words()[position]] {
+                break;
+            }
+            eprintln!("it is not word {}, look at the words written down", position + 1);
+        }
+    }
+}
+
+fn main() {
+    use rust_wallet_grpc::{
+        acl::Acl,
+        logging,
+        corerpc,
+        rest,
+        server::{self, WalletFactory, BackendSwitcher},
+        webhook::WebhookConfig,
+        price::{FiatConfig, HttpPriceConfig, HttpPriceSource},
+        autosync::AutoSyncConfig,
+    };
+    use wallet::{
+        walletlibrary::{
+            WalletLibraryMode, KeyGenConfig, DecryptConfig, DEFAULT_NETWORK, DEFAULT_PASSPHRASE,
+            DEFAULT_SALT,
+        },
+        keyfactory::MasterKeyEntropy,
+        account::AccountAddressType,
+        context::GlobalContext,
+        supervisor::Supervisor,
+        retry::RetryPolicy,
+        price::CachedPriceSource,
+        interface::BackendConfig,
+    };
 
     let config: Config = Config::from_args();
 
-    let log_level = log::Level::from_str(config.log_level.as_str()).unwrap();
-    simple_logger::init_with_level(log_level).unwrap();
+    let log_filter = logging::init(&config.log_level, config.log_json).unwrap();
+
+    let _lock = lock_db_or_exit(&config.db_path);
+    let _wallets_lock = lock_db_or_exit(&config.wallets_dir);
+
+    let bitcoind_address: Option<SocketAddr> =
+        config.bitcoind_address.as_ref().map(|s| s.parse().unwrap());
+    let electrumx_address: Option<SocketAddr> =
+        config.electrumx_address.as_ref().map(|s| s.parse().unwrap());
+    // with the credentials of bitcoind
+    let witnesses: Vec<BackendConfig> = config
+        .witness_addresses
+        .iter()
+        .map(|s| BackendConfig {
+            address: s.parse().unwrap(),
+            user: None,
+            password: None,
+        })
+        .collect();
+
+    let retry_policy = RetryPolicy {
+        max_attempts: config.backend_retries.max(1),
+        ..Default::default()
+    };
 
-    let context = GlobalContext::new(
+    let timeouts = (
+        Duration::from_secs(config.connect_timeout),
+        Duration::from_secs(config.read_timeout),
+    );
+
+    let factory: WalletFactory = {
+        let wallets_dir = config.wallets_dir.clone();
+        let user = config.user.clone();
+        let password = config.password.clone();
+        let electrumx = config.electrumx;
+        let change_addr_type = AccountAddressType::from(config.change_addr_type.as_str());
+        let raw_blocks = config.raw_blocks;
+        let silent_payments = config.silent_payments;
+        let core_wallet = config.core_wallet.clone();
+        let witnesses = witnesses.clone();
+        let witness_max_lag = config.witness_max_lag;
+        let verify_proofs = config.verify_proofs;
+        let prune_spent_after = config.prune_spent_after;
+        let spend_unconfirmed_change = config.spend_unconfirmed_change;
+        let spend_uneconomical = config.spend_uneconomical;
+        let privacy = config.privacy;
+        let avoid_reuse = config.avoid_reuse;
+        let change_tolerance = config.change_tolerance;
+        let fee_rate = config.fee_rate;
+        let retry_policy = retry_policy.clone();
+        let (connect_timeout, read_timeout) = timeouts;
+        Box::new(move |name, mode| {
+            let db_path = wallets_dir.join(name);
+            let creates = match mode {
+                WalletLibraryMode::Create(_) | WalletLibraryMode::WatchOnly(_) => true,
+                _ => false,
+            };
+            match (creates, db_path.exists()) {
+                (true, true) => return Err(format!("wallet {} already exists", name).into()),
+                (false, false) => return Err(format!("wallet {} does not exist", name).into()),
+                _ => (),
+            }
+
+            let mut context = GlobalContext::new(
+                DEFAULT_NETWORK,
+                user.clone(),
+                password.clone(),
+                Some(db_path.to_str().unwrap().to_owned()),
+                bitcoind_address,
+                electrumx_address,
+            );
+            context.set_change_addr_type(change_addr_type.clone());
+            context.set_raw_blocks(raw_blocks);
+            context.set_silent_payments(silent_payments);
+            let core_wallet_name =
+                core_wallet.as_ref().map(|prefix| format!("{}-{}", prefix, name));
+            context.set_core_wallet(core_wallet_name);
+            context.set_quorum(witnesses.clone(), witness_max_lag);
+            context.set_verify_proofs(verify_proofs);
+            context.set_prune_spent_after(prune_spent_after);
+            context.set_spend_unconfirmed_change(spend_unconfirmed_change);
+            context.set_spend_uneconomical(spend_uneconomical);
+            context.set_privacy(privacy);
+            context.set_avoid_reuse(avoid_reuse);
+            context.set_change_tolerance(change_tolerance);
+            context.set_fee_rate(fee_rate);
+            context.set_retry_policy(retry_policy.clone());
+            context.set_timeouts(connect_timeout, read_timeout);
+            let (wallet_context, mnemonic) = if electrumx {
+                context.electrs_context(mode)?
+            } else {
+                context.default_context(mode)?
+            };
+            let (wallet, _) = wallet_context.destruct();
+            Ok((wallet, mnemonic))
+        })
+    };
+
+    let mut context = GlobalContext::new(
         DEFAULT_NETWORK,
         config.user,
         config.password,
         Some(config.db_path.to_str().unwrap().to_owned()),
-        config.bitcoind_address.as_ref().map(|s| s.parse().unwrap()),
-        config.electrumx_address.as_ref().map(|s| s.parse().unwrap()),
+        bitcoind_address,
+        electrumx_address,
     );
+    context.set_change_addr_type(AccountAddressType::from(config.change_addr_type.as_str()));
+    context.set_raw_blocks(config.raw_blocks);
+    context.set_silent_payments(config.silent_payments);
+    context.set_core_wallet(config.core_wallet);
+    context.set_quorum(witnesses, config.witness_max_lag);
+    context.set_verify_proofs(config.verify_proofs);
+    context.set_prune_spent_after(config.prune_spent_after);
+    context.set_spend_unconfirmed_change(config.spend_unconfirmed_change);
+    context.set_spend_uneconomical(config.spend_uneconomical);
+    context.set_privacy(config.privacy);
+    context.set_avoid_reuse(config.avoid_reuse);
+    context.set_change_tolerance(config.change_tolerance);
+    context.set_fee_rate(config.fee_rate);
+    context.set_force_overwrite(config.force_overwrite);
+    context.set_retry_policy(retry_policy);
+    context.set_timeouts(timeouts.0, timeouts.1);
+
+    // processes run locally are restarted if they exit
+    let mut supervisor = Supervisor::new();
+    context.set_zmq_endpoints(config.zmqpubrawblock.clone(), config.zmqpubrawtx.clone());
+    let zmqpubrawblock = config.zmqpubrawblock.clone();
+    let zmqpubrawtx = config.zmqpubrawtx.clone();
 
     // if `bitcoind_uri` is not specified run bitcoind locally
-    let bitcoind = if config.bitcoind_address.is_none() {
-        Some(context.bitcoind(config.zmqpubrawblock, config.zmqpubrawtx).unwrap())
-    } else {
-        None
-    };
+    if config.bitcoind_address.is_none() {
+        let bitcoind = context.bitcoind(config.zmqpubrawblock, config.zmqpubrawtx).unwrap();
+        let context = context.clone();
+        supervisor.manage("bitcoind", bitcoind, move || context.spawn_bitcoind());
+    }
 
     // if `electrumx_uri` is not specified run electrs locally
-    let electrs = if config.electrumx_address.is_none() {
-        Some(context.electrs().unwrap())
-    } else {
-        None
-    };
+    if config.electrumx_address.is_none() {
+        let electrs = context.electrs().unwrap();
+        let context = context.clone();
+        supervisor.manage("electrs", electrs, move || context.spawn_electrs());
+    }
 
-    let mode = if config.mode == "create" {
-        WalletLibraryMode::Create(KeyGenConfig::default())
-    } else if config.mode == "recover" {
-        let mnemonic = config.mnemonic.unwrap();
-        WalletLibraryMode::RecoverFromMnemonic(Mnemonic::from(mnemonic.trim_matches('"')).unwrap())
-    } else {
-        WalletLibraryMode::Decrypt
+    // SwitchBackend moves the wallets with the settings of the daemon
+    let switch_context = context.clone();
+    let switcher: BackendSwitcher =
+        Box::new(move |kind, config| switch_context.switch_backend(kind, config));
+
+    // a new passphrase is typed twice, a typo would make the wallet unrecoverable
+    let confirm = config.mode != "decrypt";
+    let passphrase = read_secret(config.passphrase, config.interactive, confirm, "passphrase", DEFAULT_PASSPHRASE);
+    let salt = read_secret(config.salt, config.interactive, confirm, "salt", DEFAULT_SALT);
+
+    let mnemonic_words = config.mnemonic_words;
+    let key_gen_cfg = || {
+        let entropy = MasterKeyEntropy::from_word_count(mnemonic_words)
+            .expect("mnemonic should have 12, 18 or 24 words");
+        KeyGenConfig::new(entropy, passphrase.to_string(), salt.to_string())
+    };
+    let mode = match config.mode.as_str() {
+        "create" => WalletLibraryMode::Create(key_gen_cfg()),
+        "auto" => WalletLibraryMode::Auto(key_gen_cfg()),
+        "watch-only" => {
+            let path = config.watch_only_keys.expect("watch-only mode needs --watch-only-keys");
+            let keys = fs::read_to_string(path).unwrap();
+            WalletLibraryMode::WatchOnly(serde_json::from_str(&keys).unwrap())
+        }
+        "recover" => {
+            let mnemonic = match config.mnemonic {
+                Some(mnemonic) => {
+                    let mnemonic = Zeroizing::new(mnemonic);
+                    Mnemonic::from(mnemonic.trim_matches('"')).unwrap()
+                }
+                None => read_mnemonic(config.mnemonic_words),
+            };
+            WalletLibraryMode::RecoverFromMnemonic(
+                mnemonic,
+                DecryptConfig::new(passphrase.to_string(), salt.to_string()),
+            )
+        }
+        "decrypt" => WalletLibraryMode::Decrypt(DecryptConfig::new(
+            passphrase.to_string(),
+            salt.to_string(),
+        )),
+        mode => unreachable!("--mode {} is not one of its possible values", mode),
     };
+    drop(passphrase);
+    drop(salt);
 
     let (wallet_context, mnemonic) = if config.electrumx {
         context.electrs_context(mode).unwrap()
     } else {
         context.default_context(mode).unwrap()
     };
-    println!("{}", mnemonic.to_string());
+    // the mnemonic of a wallet created by the auto mode is asked back like a created one
+    let created = config.mode == "create" || (config.mode == "auto" && !mnemonic.is_empty());
+    if created && config.interactive {
+        confirm_mnemonic(&mnemonic);
+    } else if config.show_mnemonic {
+        let phrase = Zeroizing::new(mnemonic.to_string());
+        println!("{}", phrase.as_str());
+    }
+    drop(mnemonic);
 
     let (wallet, _) = wallet_context.destruct();
-    server::launch_server_new(wallet, config.rpc_port);
-
-    if let Some(mut process) = electrs {
-        log::info!("kill electrs");
-        match process.kill() { _ => () }
+    let webhook = config.webhook_url.map(|url| WebhookConfig {
+        url,
+        secret: config.webhook_secret,
+        confirmations: config.webhook_confirmations,
+    });
+    let fiat = match (config.fiat_currency, config.price_url) {
+        (Some(currency), Some(current_url)) => {
+            let source = HttpPriceSource::new(HttpPriceConfig {
+                current_url,
+                historical_url: config.historical_price_url,
+                pointer: config.price_pointer,
+            });
+            let ttl = Duration::from_secs(config.price_ttl);
+            Some(FiatConfig {
+                currency,
+                source: Box::new(CachedPriceSource::new(source, ttl)),
+            })
+        }
+        (Some(_), None) => panic!("--fiat-currency needs --price-url"),
+        (None, _) => None,
+    };
+    let acl = config.acl_file.map(|path| Acl::load(&path).expect("acl file"));
+    if config.auto_sync_on_block && config.electrumx {
+        panic!("--auto-sync-on-block needs the bitcoind backend");
+    }
+    if config.auto_sync_mempool && config.electrumx {
+        panic!("--auto-sync-mempool needs the bitcoind backend");
     }
-    if let Some(mut process) = bitcoind {
-        log::info!("kill bitcoind");
-        match process.kill() { _ => () }
+    let auto_sync = if config.auto_sync_interval.is_some()
+        || config.auto_sync_on_block
+        || config.auto_sync_mempool
+    {
+        Some(AutoSyncConfig {
+            interval: config.auto_sync_interval.map(Duration::from_secs),
+            jitter: Duration::from_secs(config.auto_sync_jitter),
+            zmq_block_endpoint: if config.auto_sync_on_block {
+                Some(zmqpubrawblock)
+            } else {
+                None
+            },
+            zmq_tx_endpoint: if config.auto_sync_mempool {
+                Some(zmqpubrawtx)
+            } else {
+                None
+            },
+        })
+    } else {
+        None
+    };
+    // clients of the grpc server, which is up by the time the first request comes in
+    let _rest = config
+        .rest_port
+        .map(|port| rest::start(port, config.rpc_port).expect("rest gateway"));
+    let _core_rpc = config
+        .core_rpc_port
+        .map(|port| corerpc::start(port, config.rpc_port).expect("core wallet rpc"));
+    let server_config = server::ServerConfig {
+        factory: Some(factory),
+        webhook,
+        zmq_endpoint: config.zmqpubwallet,
+        fiat,
+        read_only: config.read_only,
+        supervisor: Some(supervisor),
+        acl,
+        log_filter: Some(log_filter),
+        auto_sync,
+        switcher: Some(switcher),
+    };
+    server::launch_server_new(wallet, config.rpc_port, server_config);
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs};
+
+    use super::lock_db;
+
+    #[test]
+    fn second_lock_fails() {
+        let db_path = env::temp_dir().join("rust_wallet_grpc_lock_db");
+        let _ = fs::remove_file(db_path.with_extension("lock"));
+
+        let lock = lock_db(&db_path).unwrap();
+        // another daemon on the same database
+        assert!(lock_db(&db_path).is_err());
+        drop(lock);
+        lock_db(&db_path).unwrap();
     }
 }
@@ -0,0 +1,110 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Prices from an exchange API
+//!
+//! The price of a bitcoin is read from the JSON an HTTP GET of a configured URL returns.
+//! `{currency}` in the URLs and the JSON pointer is replaced by the currency code,
+//! `{time}` in the URL of historical prices by the unix time
+//!
+use wallet::price::PriceSource;
+
+use std::error::Error;
+
+use super::http;
+
+#[derive(Clone, Debug)]
+pub struct HttpPriceConfig {
+    /// URL of the current price, e.g. `https://example.com/price?currency={currency}`
+    pub current_url: String,
+    /// URL of the price at a time, without it no price is known at confirmation time
+    pub historical_url: Option<String>,
+    /// JSON pointer of the price in the response, e.g. `/bitcoin/{currency}`,
+    /// a number or a string of one
+    pub pointer: String,
+}
+
+/// fiat values of the balance and the transactions are in `currency` at the prices of `source`
+pub struct FiatConfig {
+    pub currency: String,
+    pub source: Box<dyn PriceSource>,
+}
+
+pub struct HttpPriceSource {
+    config: HttpPriceConfig,
+}
+
+impl HttpPriceSource {
+    pub fn new(config: HttpPriceConfig) -> Self {
+        HttpPriceSource { config }
+    }
+
+    fn fetch(&self, url: &str, currency: &str) -> Result<f64, Box<dyn Error>> {
+        let url = http::Url::parse(url)?;
+        let request = format!(
+            "GET {} HTTP/1.0\r\nHost: {}\r\nAccept: application/json\r\n\r\n",
+            url.path, url.authority,
+        );
+        let response = http::exchange(&url, &request)?;
+        let status = http::status(&response);
+        if !status.starts_with('2') {
+            return Err(From::from(format!("price source responded with status {:?}", status)));
+        }
+        let pointer = self.config.pointer.replace("{currency}", currency);
+        parse_price(http::body(&response), &pointer)
+    }
+}
+
+impl PriceSource for HttpPriceSource {
+    fn current_price(&self, currency: &str) -> Result<f64, Box<dyn Error>> {
+        let url = self.config.current_url.replace("{currency}", currency);
+        self.fetch(&url, currency)
+    }
+
+    fn historical_price(&self, currency: &str, time: u64) -> Result<f64, Box<dyn Error>> {
+        let url = self
+            .config
+            .historical_url
+            .as_ref()
+            .ok_or("no URL of historical prices is configured")?
+            .replace("{currency}", currency)
+            .replace("{time}", &time.to_string());
+        self.fetch(&url, currency)
+    }
+}
+
+fn parse_price(body: &str, pointer: &str) -> Result<f64, Box<dyn Error>> {
+    let json: serde_json::Value = serde_json::from_str(body)?;
+    let price = match json.pointer(pointer) {
+        Some(serde_json::Value::Number(number)) => number.as_f64(),
+        Some(serde_json::Value::String(string)) => string.parse().ok(),
+        _ => None,
+    };
+    price.ok_or_else(|| From::from(format!("no price at {} in {}", pointer, body)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_price;
+
+    #[test]
+    fn price() {
+        assert_eq!(parse_price(r#"{"bitcoin":{"usd":9500.5}}"#, "/bitcoin/usd").unwrap(), 9500.5);
+        let body = r#"{"data":{"amount":"9500.50"}}"#;
+        assert_eq!(parse_price(body, "/data/amount").unwrap(), 9500.5);
+        assert!(parse_price(r#"{"bitcoin":{}}"#, "/bitcoin/usd").is_err());
+        assert!(parse_price("<html>", "/price").is_err());
+    }
+}
@@ -0,0 +1,167 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # ZMQ notifications
+//!
+//! Wallet changes published on a ZMQ PUB socket the way bitcoind publishes its own,
+//! every message is multipart: topic, body, 4 byte little endian sequence number of the topic
+//! and the name of the wallet
+//!
+//! * `wallettx` - 32 byte hash of a transaction which paid to, confirmed in or conflicted
+//!   with the wallet, or touched a watched script, in the byte order of the rpc
+//! * `walletbalance` - 8 byte little endian balance in satoshi, published when it changes
+//! * `walletblock` - 4 byte little endian height of the block the wallet processed
+//!
+use bitcoin_hashes::{Hash, sha256d::Hash as Sha256dHash};
+use wallet::events::WalletEvent;
+
+use log::warn;
+
+use std::{
+    thread,
+    collections::HashMap,
+    error::Error,
+    sync::{
+        Mutex,
+        mpsc::{self, Receiver, Sender},
+    },
+};
+
+pub const TOPIC_TX: &str = "wallettx";
+pub const TOPIC_BALANCE: &str = "walletbalance";
+pub const TOPIC_BLOCK: &str = "walletblock";
+
+struct Message {
+    topic: &'static str,
+    body: Vec<u8>,
+    wallet: String,
+}
+
+/// owns the PUB socket, the watched wallets pass their notifications to it
+pub struct Publisher {
+    queue: Mutex<Sender<Message>>,
+}
+
+impl Publisher {
+    /// bind a PUB socket to `endpoint`, e.g. `tcp://127.0.0.1:28332`
+    pub fn bind(endpoint: &str) -> Result<Self, Box<dyn Error>> {
+        let context = zmq::Context::new();
+        let socket = context.socket(zmq::PUB)?;
+        socket.bind(endpoint)?;
+
+        let (queue, messages) = mpsc::channel();
+        thread::spawn(move || publish(context, socket, messages));
+        Ok(Publisher {
+            queue: Mutex::new(queue),
+        })
+    }
+
+    /// publish the events of the wallet named `wallet` until the wallet goes away,
+    /// `balance` is asked after every transaction and gives `None` once the wallet is closed
+    pub fn watch<B>(&self, wallet: &str, events: Receiver<WalletEvent>, balance: B)
+    where
+        B: Fn() -> Option<u64> + Send + 'static,
+    {
+        let wallet = wallet.to_owned();
+        let queue = self.queue.lock().unwrap().clone();
+        thread::spawn(move || {
+            let mut last_balance = None;
+            for event in events {
+                let txid = match event {
+                    WalletEvent::Deposit { txid, .. } => txid,
+                    WalletEvent::Confirmed { txid, .. } => txid,
+                    WalletEvent::Conflict { txid, .. } => txid,
+                    WalletEvent::WatchedFunded { out_point, .. } => out_point.txid,
+                    WalletEvent::WatchedSpent { txid, .. } => txid,
+                    WalletEvent::UnexpectedUnvault { txid, .. } => txid,
+                    // no topic of their own, the subscribers follow transactions and blocks
+                    WalletEvent::BackendDown { .. }
+                    | WalletEvent::BackendUp { .. }
+                    | WalletEvent::BackendDivergence { .. } => continue,
+                    WalletEvent::NewTip { height } => {
+                        let message = Message {
+                            topic: TOPIC_BLOCK,
+                            body: (height as u32).to_le_bytes().to_vec(),
+                            wallet: wallet.clone(),
+                        };
+                        if queue.send(message).is_err() {
+                            return;
+                        }
+                        continue;
+                    }
+                };
+
+                let mut messages = vec![Message {
+                    topic: TOPIC_TX,
+                    body: txid_bytes(&txid),
+                    wallet: wallet.clone(),
+                }];
+                let balance = match balance() {
+                    Some(balance) => balance,
+                    None => return,
+                };
+                if last_balance != Some(balance) {
+                    last_balance = Some(balance);
+                    messages.push(Message {
+                        topic: TOPIC_BALANCE,
+                        body: balance.to_le_bytes().to_vec(),
+                        wallet: wallet.clone(),
+                    });
+                }
+                for message in messages {
+                    if queue.send(message).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+    }
+}
+
+fn publish(_context: zmq::Context, socket: zmq::Socket, messages: Receiver<Message>) {
+    let mut sequence: HashMap<&'static str, u32> = HashMap::new();
+    for message in messages {
+        let sequence = sequence.entry(message.topic).or_insert(0);
+        let parts = [
+            message.topic.as_bytes(),
+            &message.body,
+            &sequence.to_le_bytes(),
+            message.wallet.as_bytes(),
+        ];
+        match socket.send_multipart(&parts, 0) {
+            Ok(()) => *sequence = sequence.wrapping_add(1),
+            Err(e) => warn!("cannot publish {}: {}", message.topic, e),
+        }
+    }
+}
+
+// bitcoind publishes hashes reversed, the way they are shown by the rpc
+fn txid_bytes(txid: &Sha256dHash) -> Vec<u8> {
+    let mut bytes = txid.into_inner().to_vec();
+    bytes.reverse();
+    bytes
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin_hashes::{hex::FromHex, sha256d::Hash as Sha256dHash};
+
+    #[test]
+    fn txid_byte_order() {
+        let hex = "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b";
+        let txid = Sha256dHash::from_hex(hex).unwrap();
+        assert_eq!(hex::encode(super::txid_bytes(&txid)), hex);
+    }
+}
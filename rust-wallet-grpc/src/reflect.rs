@@ -0,0 +1,163 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Server reflection
+//!
+//! grpc-rust has no reflection service of its own, this one answers the v1alpha protocol
+//! with the descriptors compiled into the daemon, so `grpcurl localhost:5051 list` and
+//! `grpcurl localhost:5051 describe walletrpc.Wallet` work without the .proto files
+//!
+use futures::Stream;
+use protobuf::{Message, RepeatedField, descriptor::FileDescriptorProto};
+
+use log::info;
+
+use super::reflection::{
+    ServerReflectionRequest, ServerReflectionResponse,
+    ServerReflectionRequest_oneof_message_request, FileDescriptorResponse, ExtensionNumberResponse,
+    ListServiceResponse, ServiceResponse, ErrorResponse,
+};
+use super::reflection_grpc::ServerReflection;
+
+// grpc status codes of the error responses
+const NOT_FOUND: i32 = 5;
+const INVALID_ARGUMENT: i32 = 3;
+
+/// the files the services of the daemon are described in
+fn files() -> Vec<&'static FileDescriptorProto> {
+    vec![super::walletrpc::file_descriptor_proto(), super::reflection::file_descriptor_proto()]
+}
+
+// the file which defines `symbol`, a package, service, method, message or enum
+fn file_of_symbol(symbol: &str) -> Option<&'static FileDescriptorProto> {
+    files().into_iter().find(|file| {
+        let package = file.get_package();
+        symbol == package
+            || (symbol.starts_with(package) && symbol[package.len()..].starts_with('.'))
+    })
+}
+
+fn file_response(file: &FileDescriptorProto) -> Result<FileDescriptorResponse, String> {
+    let bytes = file.write_to_bytes().map_err(|e| e.to_string())?;
+    let mut resp = FileDescriptorResponse::new();
+    resp.set_file_descriptor_proto(RepeatedField::from_vec(vec![bytes]));
+    Ok(resp)
+}
+
+fn error_response(code: i32, message: String) -> ErrorResponse {
+    let mut resp = ErrorResponse::new();
+    resp.set_error_code(code);
+    resp.set_error_message(message);
+    resp
+}
+
+/// the answer to a reflection request
+pub fn answer(req: &ServerReflectionRequest) -> ServerReflectionResponse {
+    use self::ServerReflectionRequest_oneof_message_request::*;
+
+    let mut resp = ServerReflectionResponse::new();
+    resp.set_valid_host(req.host.clone());
+    resp.set_original_request(req.clone());
+    let file = match req.message_request {
+        Some(file_by_filename(ref name)) => files()
+            .into_iter()
+            .find(|file| file.get_name() == name)
+            .ok_or_else(|| format!("no file {}", name)),
+        Some(file_containing_symbol(ref symbol)) => {
+            file_of_symbol(symbol).ok_or_else(|| format!("no symbol {}", symbol))
+        }
+        // proto3 has no extensions
+        Some(file_containing_extension(ref ext)) => Err(format!(
+            "no extension {} of {}",
+            ext.extension_number, ext.containing_type
+        )),
+        Some(all_extension_numbers_of_type(ref name)) => {
+            if file_of_symbol(name).is_none() {
+                resp.set_error_response(error_response(NOT_FOUND, format!("no type {}", name)));
+                return resp;
+            }
+            let mut numbers = ExtensionNumberResponse::new();
+            numbers.set_base_type_name(name.clone());
+            resp.set_all_extension_numbers_response(numbers);
+            return resp;
+        }
+        Some(list_services(_)) => {
+            let mut services = ListServiceResponse::new();
+            for file in files() {
+                for service in file.get_service() {
+                    let mut item = ServiceResponse::new();
+                    item.set_name(format!("{}.{}", file.get_package(), service.get_name()));
+                    services.mut_service().push(item);
+                }
+            }
+            resp.set_list_services_response(services);
+            return resp;
+        }
+        None => {
+            let message = "the request is empty".to_owned();
+            resp.set_error_response(error_response(INVALID_ARGUMENT, message));
+            return resp;
+        }
+    };
+    match file.and_then(file_response) {
+        Ok(file) => resp.set_file_descriptor_response(file),
+        Err(message) => resp.set_error_response(error_response(NOT_FOUND, message)),
+    }
+    resp
+}
+
+pub struct ReflectionImpl;
+
+impl ServerReflection for ReflectionImpl {
+    fn server_reflection_info(
+        &self,
+        _m: grpc::RequestOptions,
+        req: grpc::StreamingRequest<ServerReflectionRequest>,
+    ) -> grpc::StreamingResponse<ServerReflectionResponse> {
+        info!("server reflection was requested");
+        grpc::StreamingResponse::no_metadata(req.0.map(|req| answer(&req)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use protobuf::{parse_from_bytes, descriptor::FileDescriptorProto};
+
+    use super::answer;
+    use crate::reflection::ServerReflectionRequest;
+
+    #[test]
+    fn reflection() {
+        let mut req = ServerReflectionRequest::new();
+        req.set_list_services(String::new());
+        let names: Vec<String> = answer(&req)
+            .get_list_services_response()
+            .get_service()
+            .iter()
+            .map(|service| service.name.clone())
+            .collect();
+        assert_eq!(names, vec!["walletrpc.Wallet", "grpc.reflection.v1alpha.ServerReflection"]);
+
+        req.set_file_containing_symbol("walletrpc.Wallet.GetInfo".to_owned());
+        let resp = answer(&req);
+        let bytes = &resp.get_file_descriptor_response().get_file_descriptor_proto()[0];
+        let file: FileDescriptorProto = parse_from_bytes(bytes).unwrap();
+        assert_eq!(file.get_package(), "walletrpc");
+        assert!(file.get_enum_type().iter().any(|e| e.get_name() == "ErrorCode"));
+
+        req.set_file_containing_symbol("walletrpcx.Wallet".to_owned());
+        assert_eq!(answer(&req).get_error_response().get_error_code(), 5);
+    }
+}
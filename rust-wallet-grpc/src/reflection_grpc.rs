@@ -0,0 +1,80 @@
+// This file is generated. Do not edit
+// @generated
+
+// https://github.com/Manishearth/rust-clippy/issues/702
+#![allow(unknown_lints)]
+#![allow(clippy::all)]
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+
+#![allow(box_pointers)]
+#![allow(dead_code)]
+#![allow(missing_docs)]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+#![allow(non_upper_case_globals)]
+#![allow(trivial_casts)]
+#![allow(unsafe_code)]
+#![allow(unused_imports)]
+#![allow(unused_results)]
+
+
+// interface
+
+pub trait ServerReflection {
+    fn server_reflection_info(&self, o: ::grpc::RequestOptions, p: ::grpc::StreamingRequest<super::reflection::ServerReflectionRequest>) -> ::grpc::StreamingResponse<super::reflection::ServerReflectionResponse>;
+}
+
+// client
+
+pub struct ServerReflectionClient {
+    grpc_client: ::std::sync::Arc<::grpc::Client>,
+    method_ServerReflectionInfo: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::reflection::ServerReflectionRequest, super::reflection::ServerReflectionResponse>>,
+}
+
+impl ::grpc::ClientStub for ServerReflectionClient {
+    fn with_client(grpc_client: ::std::sync::Arc<::grpc::Client>) -> Self {
+        ServerReflectionClient {
+            grpc_client: grpc_client,
+            method_ServerReflectionInfo: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/grpc.reflection.v1alpha.ServerReflection/ServerReflectionInfo".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Bidi,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+        }
+    }
+}
+
+impl ServerReflection for ServerReflectionClient {
+    fn server_reflection_info(&self, o: ::grpc::RequestOptions, p: ::grpc::StreamingRequest<super::reflection::ServerReflectionRequest>) -> ::grpc::StreamingResponse<super::reflection::ServerReflectionResponse> {
+        self.grpc_client.call_bidi(o, p, self.method_ServerReflectionInfo.clone())
+    }
+}
+
+// server
+
+pub struct ServerReflectionServer;
+
+
+impl ServerReflectionServer {
+    pub fn new_service_def<H : ServerReflection + 'static + Sync + Send + 'static>(handler: H) -> ::grpc::rt::ServerServiceDefinition {
+        let handler_arc = ::std::sync::Arc::new(handler);
+        ::grpc::rt::ServerServiceDefinition::new("/grpc.reflection.v1alpha.ServerReflection",
+            vec![
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/grpc.reflection.v1alpha.ServerReflection/ServerReflectionInfo".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Bidi,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerBidi::new(move |o, p| handler_copy.server_reflection_info(o, p))
+                    },
+                ),
+            ],
+        )
+    }
+}
@@ -0,0 +1,572 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # REST gateway
+//!
+//! The calls integrators need most as JSON over plain HTTP, for dashboards and shell scripts
+//! which have no gRPC client. The gateway is a client of the gRPC server of the daemon, so a
+//! request goes through the same handler and checks, such as the read-only mode, as the call
+//! it maps to. `GET /v1/openapi.json` describes the routes, the spec is made of `ROUTES`
+//!
+use bitcoin_hashes::{sha256d::Hash as Sha256dHash, Hash};
+use bytes::Bytes;
+use serde_json::{json, Map, Value};
+
+use log::{info, warn};
+
+use super::error::from_grpc;
+use super::server::API_VERSION;
+use super::walletrpc::{
+    ErrorCode, ErrorDetails, NewAddressRequest, WalletBalanceRequest, SendCoinsRequest,
+    GetUtxoListRequest, GetTransactionsRequest, AddressType as RpcAddressType,
+    OutPoint as RpcOutPoint, FiatValue as RpcFiatValue,
+};
+use super::walletrpc_grpc::{Wallet, WalletClient};
+
+use std::{
+    error::Error,
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::Arc,
+    thread::{self, JoinHandle},
+};
+
+// requests are small, a larger body is refused rather than read
+const MAX_BODY_LEN: usize = 64 * 1024;
+
+/// a route of the gateway, the fields are (name, JSON type, description)
+pub struct Route {
+    pub method: &'static str,
+    pub path: &'static str,
+    pub summary: &'static str,
+    /// fields of the JSON body, the ones marked required have to be there
+    pub body: &'static [(&'static str, &'static str, &'static str)],
+    pub required: &'static [&'static str],
+    pub response: &'static [(&'static str, &'static str, &'static str)],
+}
+
+pub const ROUTES: &[Route] = &[
+    Route {
+        method: "POST",
+        path: "/v1/newaddress",
+        summary: "New receiving address",
+        body: &[("addr_type", "string", "p2pkh, p2shwh or p2wkh, p2wkh if not given")],
+        required: &[],
+        response: &[("address", "string", "the address")],
+    },
+    Route {
+        method: "GET",
+        path: "/v1/balance",
+        summary: "Balance of the wallet in satoshis",
+        body: &[],
+        required: &[],
+        response: &[
+            ("total_balance", "integer", "coins of the wallet"),
+            ("watched_balance", "integer", "outputs of the watched scripts"),
+            ("fiat_balance", "object", "currency, value and price, if the daemon has prices"),
+            ("balances", "object", "spendable, frozen, timelocked and watched satoshis"),
+        ],
+    },
+    Route {
+        method: "POST",
+        path: "/v1/send",
+        summary: "Pay an address",
+        body: &[
+            ("address", "string", "destination"),
+            ("amount", "integer", "satoshis"),
+            ("submit", "boolean", "broadcast the transaction, true if not given"),
+            ("idempotency_key", "string", "a retry with the key returns the first payment"),
+        ],
+        required: &["address", "amount"],
+        response: &[
+            ("txid", "string", "id of the transaction, empty if it needs approval"),
+            ("fee", "integer", "satoshis"),
+            ("approval_id", "integer", "nonzero if the spending policy wants it approved"),
+        ],
+    },
+    Route {
+        method: "GET",
+        path: "/v1/listunspent",
+        summary: "Coins of the wallet",
+        body: &[],
+        required: &[],
+        response: &[("utxos", "array", "txid, vout, value and addr_type of each coin")],
+    },
+    Route {
+        method: "GET",
+        path: "/v1/history",
+        summary: "Transactions of the wallet, oldest first",
+        body: &[],
+        required: &[],
+        response: &[
+            ("transactions", "array", "txid, status, height, fee and value of each transaction"),
+            ("fee_stats", "object", "fees paid by the wallet"),
+        ],
+    },
+];
+
+fn properties(fields: &[(&str, &str, &str)]) -> Value {
+    let mut properties = Map::new();
+    for &(name, kind, description) in fields {
+        properties.insert(name.to_owned(), json!({"type": kind, "description": description}));
+    }
+    Value::Object(properties)
+}
+
+/// OpenAPI 3.0 description of `ROUTES`
+pub fn openapi() -> Value {
+    let error = json!({
+        "description": "the call failed",
+        "content": {"application/json": {"schema": {"type": "object", "properties": properties(&[
+            ("code", "string", "ErrorCode of the gRPC API, e.g. INSUFFICIENT_FUNDS"),
+            ("message", "string", "what went wrong"),
+            ("retryable", "boolean", "the same request may succeed later"),
+        ])}}},
+    });
+    let mut paths = Map::new();
+    for route in ROUTES {
+        let mut operation = json!({
+            "summary": route.summary,
+            "parameters": [{
+                "name": "wallet",
+                "in": "query",
+                "required": false,
+                "schema": {"type": "string"},
+                "description": "name of the wallet, the default one if not given",
+            }],
+            "responses": {
+                "200": {
+                    "description": "OK",
+                    "content": {"application/json": {"schema": {
+                        "type": "object",
+                        "properties": properties(route.response),
+                    }}},
+                },
+                "default": error.clone(),
+            },
+        });
+        if !route.body.is_empty() {
+            operation["requestBody"] = json!({
+                "required": !route.required.is_empty(),
+                "content": {"application/json": {"schema": {
+                    "type": "object",
+                    "properties": properties(route.body),
+                    "required": route.required,
+                }}},
+            });
+        }
+        let mut item = Map::new();
+        item.insert(route.method.to_lowercase(), operation);
+        paths.insert(route.path.to_owned(), Value::Object(item));
+    }
+    json!({
+        "openapi": "3.0.0",
+        "info": {"title": "rust-wallet", "version": API_VERSION.to_string()},
+        "paths": paths,
+    })
+}
+
+/// a request as far as the gateway reads it
+#[derive(Debug, PartialEq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    /// value of the `wallet` query parameter, empty if there is none
+    pub wallet: String,
+    /// the Authorization header, passed on to the gRPC server
+    pub authorization: Option<String>,
+    pub body: Vec<u8>,
+}
+
+/// read a request off `reader`, the body is as long as the Content-Length header says
+pub fn read_request<R: BufRead>(reader: &mut R) -> Result<Request, Box<dyn Error>> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut words = line.split_whitespace();
+    let (method, target) = match (words.next(), words.next()) {
+        (Some(method), Some(target)) => (method.to_owned(), target.to_owned()),
+        _ => return Err(From::from("malformed request line")),
+    };
+
+    let mut content_len = 0;
+    let mut authorization = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        let mut parts = header.splitn(2, ':');
+        let name = parts.next().unwrap_or("").trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            content_len = parts.next().unwrap_or("").trim().parse()?;
+        } else if name.eq_ignore_ascii_case("authorization") {
+            authorization = parts.next().map(|value| value.trim().to_owned());
+        }
+    }
+    if content_len > MAX_BODY_LEN {
+        return Err(From::from("request body too large"));
+    }
+    let mut body = vec![0; content_len];
+    reader.read_exact(&mut body)?;
+
+    let mut parts = target.splitn(2, '?');
+    let path = parts.next().unwrap_or("").to_owned();
+    let wallet = parts
+        .next()
+        .unwrap_or("")
+        .split('&')
+        .filter_map(|pair| {
+            let mut pair = pair.splitn(2, '=');
+            match (pair.next(), pair.next()) {
+                (Some("wallet"), Some(value)) => Some(value.to_owned()),
+                _ => None,
+            }
+        })
+        .next()
+        .unwrap_or_default();
+    Ok(Request {
+        method,
+        path,
+        wallet,
+        authorization,
+        body,
+    })
+}
+
+/// options of a gRPC call made for a request with the `authorization` header
+pub fn call_options(authorization: Option<&str>) -> grpc::RequestOptions {
+    let mut options = grpc::RequestOptions::new();
+    if let Some(authorization) = authorization {
+        let value = Bytes::from(authorization.to_owned());
+        options.metadata.add(grpc::MetadataKey::from("authorization"), value);
+    }
+    options
+}
+
+/// HTTP status of a failed call
+pub fn http_status(code: ErrorCode) -> &'static str {
+    match code {
+        ErrorCode::UNKNOWN => "500 Internal Server Error",
+        ErrorCode::INVALID_ARGUMENT | ErrorCode::INVALID_BACKUP => "400 Bad Request",
+        ErrorCode::NOT_FOUND => "404 Not Found",
+        ErrorCode::UNAUTHENTICATED => "401 Unauthorized",
+        ErrorCode::READ_ONLY | ErrorCode::PERMISSION_DENIED => "403 Forbidden",
+        ErrorCode::RATE_LIMITED => "429 Too Many Requests",
+        ErrorCode::WALLET_LOCKED => "423 Locked",
+        ErrorCode::CANCELLED => "499 Client Closed Request",
+        ErrorCode::INSUFFICIENT_FUNDS
+        | ErrorCode::POLICY_VIOLATION
+        | ErrorCode::APPROVAL_REQUIRED
+        | ErrorCode::BACKEND_REJECTED
+        | ErrorCode::WATCH_ONLY => "422 Unprocessable Entity",
+        ErrorCode::BACKEND_UNAVAILABLE => "503 Service Unavailable",
+    }
+}
+
+fn error_json(e: &(dyn Error + 'static)) -> (&'static str, Value) {
+    let details = ErrorDetails::from(e);
+    let body = json!({
+        "code": format!("{:?}", details.code),
+        "message": details.message,
+        "retryable": details.retryable,
+    });
+    (http_status(details.code), body)
+}
+
+fn invalid(message: &str) -> Box<dyn Error> {
+    Box::new(super::error::rpc_error(ErrorCode::INVALID_ARGUMENT, message))
+}
+
+fn txid_to_json(txid: &[u8]) -> Value {
+    match Sha256dHash::from_slice(txid) {
+        Ok(txid) => Value::String(txid.to_string()),
+        Err(_) => Value::String(hex::encode(txid)),
+    }
+}
+
+fn out_point_to_json(op: &RpcOutPoint) -> (Value, u32) {
+    (txid_to_json(&op.txid), op.vout)
+}
+
+fn fiat_to_json(fiat: &RpcFiatValue) -> Value {
+    json!({"currency": fiat.currency, "value": fiat.value, "price": fiat.price})
+}
+
+fn addr_type_name(addr_type: RpcAddressType) -> &'static str {
+    match addr_type {
+        RpcAddressType::P2PKH => "p2pkh",
+        RpcAddressType::P2SHWH => "p2shwh",
+        RpcAddressType::P2WKH => "p2wkh",
+    }
+}
+
+/// answers the requests by calling the gRPC server
+pub struct Gateway {
+    client: WalletClient,
+}
+
+impl Gateway {
+    pub fn new(wallet_rpc_port: u16) -> Result<Self, Box<dyn Error>> {
+        use grpc::ClientStubExt;
+
+        let client = WalletClient::new_plain("127.0.0.1", wallet_rpc_port, Default::default())?;
+        Ok(Gateway { client })
+    }
+
+    /// status line and JSON body of the answer to `req`
+    pub fn handle(&self, req: &Request) -> (&'static str, Value) {
+        if req.method == "GET" && req.path == "/v1/openapi.json" {
+            return ("200 OK", openapi());
+        }
+        let body: Value = if req.body.is_empty() {
+            json!({})
+        } else {
+            match serde_json::from_slice(&req.body) {
+                Ok(body) => body,
+                Err(e) => return error_json(&*invalid(&format!("invalid JSON body: {}", e))),
+            }
+        };
+        let auth = req.authorization.as_deref();
+        let result = match (req.method.as_str(), req.path.as_str()) {
+            ("POST", "/v1/newaddress") => self.new_address(&req.wallet, auth, &body),
+            ("GET", "/v1/balance") => self.balance(&req.wallet, auth),
+            ("POST", "/v1/send") => self.send(&req.wallet, auth, &body),
+            ("GET", "/v1/listunspent") => self.list_unspent(&req.wallet, auth),
+            ("GET", "/v1/history") => self.history(&req.wallet, auth),
+            _ => {
+                let message = format!("no route {} {}", req.method, req.path);
+                return ("404 Not Found", json!({"code": "NOT_FOUND", "message": message}));
+            }
+        };
+        match result {
+            Ok(value) => ("200 OK", value),
+            Err(e) => error_json(&*e),
+        }
+    }
+
+    fn new_address(
+        &self,
+        wallet: &str,
+        auth: Option<&str>,
+        body: &Value,
+    ) -> Result<Value, Box<dyn Error>> {
+        let addr_type = match body["addr_type"].as_str().unwrap_or("p2wkh") {
+            "p2pkh" => RpcAddressType::P2PKH,
+            "p2shwh" => RpcAddressType::P2SHWH,
+            "p2wkh" => RpcAddressType::P2WKH,
+            other => return Err(invalid(&format!("unknown addr_type {}", other))),
+        };
+        let mut req = NewAddressRequest::new();
+        req.set_wallet(wallet.to_owned());
+        req.set_addr_type(addr_type);
+        let resp = self.client.new_address(call_options(auth), req);
+        let resp = resp.wait_drop_metadata().map_err(from_grpc)?;
+        Ok(json!({"address": resp.address}))
+    }
+
+    fn balance(&self, wallet: &str, auth: Option<&str>) -> Result<Value, Box<dyn Error>> {
+        let mut req = WalletBalanceRequest::new();
+        req.set_wallet(wallet.to_owned());
+        let resp = self.client.wallet_balance(call_options(auth), req);
+        let resp = resp.wait_drop_metadata().map_err(from_grpc)?;
+        let mut value = json!({
+            "total_balance": resp.total_balance,
+            "watched_balance": resp.watched_balance,
+        });
+        if resp.has_fiat_balance() {
+            value["fiat_balance"] = fiat_to_json(resp.get_fiat_balance());
+        }
+        let mut balances = Map::new();
+        for entry in resp.get_balances() {
+            balances.insert(format!("{:?}", entry.category).to_lowercase(), json!(entry.value));
+        }
+        value["balances"] = Value::Object(balances);
+        Ok(value)
+    }
+
+    fn send(
+        &self,
+        wallet: &str,
+        auth: Option<&str>,
+        body: &Value,
+    ) -> Result<Value, Box<dyn Error>> {
+        let address = body["address"].as_str().ok_or_else(|| invalid("address is required"))?;
+        let amount = body["amount"].as_u64().ok_or_else(|| invalid("amount is required"))?;
+        let mut req = SendCoinsRequest::new();
+        req.set_wallet(wallet.to_owned());
+        req.set_dest_addr(address.to_owned());
+        req.set_amt(amount);
+        req.set_submit(body["submit"].as_bool().unwrap_or(true));
+        if let Some(key) = body["idempotency_key"].as_str() {
+            req.set_idempotency_key(key.to_owned());
+        }
+        if let Some(fee_rate) = body["fee_rate"].as_u64() {
+            req.set_fee_rate(fee_rate);
+        }
+        let resp = self.client.send_coins(call_options(auth), req);
+        let resp = resp.wait_drop_metadata().map_err(from_grpc)?;
+        Ok(json!({"txid": resp.txid, "fee": resp.fee, "approval_id": resp.approval_id}))
+    }
+
+    fn list_unspent(&self, wallet: &str, auth: Option<&str>) -> Result<Value, Box<dyn Error>> {
+        let mut req = GetUtxoListRequest::new();
+        req.set_wallet(wallet.to_owned());
+        let resp = self.client.get_utxo_list(call_options(auth), req);
+        let resp = resp.wait_drop_metadata().map_err(from_grpc)?;
+        let utxos: Vec<Value> = resp
+            .get_utxos()
+            .iter()
+            .map(|utxo| {
+                let (txid, vout) = out_point_to_json(utxo.get_out_point());
+                json!({
+                    "txid": txid,
+                    "vout": vout,
+                    "value": utxo.value,
+                    "addr_type": addr_type_name(utxo.addr_type),
+                })
+            })
+            .collect();
+        Ok(json!({ "utxos": utxos }))
+    }
+
+    fn history(&self, wallet: &str, auth: Option<&str>) -> Result<Value, Box<dyn Error>> {
+        let mut req = GetTransactionsRequest::new();
+        req.set_wallet(wallet.to_owned());
+        let resp = self.client.get_transactions(call_options(auth), req);
+        let resp = resp.wait_drop_metadata().map_err(from_grpc)?;
+        let transactions: Vec<Value> = resp
+            .get_transactions()
+            .iter()
+            .map(|record| {
+                let mut value = json!({
+                    "txid": record.txid,
+                    "status": format!("{:?}", record.status).to_lowercase(),
+                    "height": record.height,
+                    "fee": record.fee,
+                    "value": record.value,
+                });
+                if !record.conflicting_txid.is_empty() {
+                    value["conflicting_txid"] = json!(record.conflicting_txid);
+                }
+                if record.has_fiat_value() {
+                    value["fiat_value"] = fiat_to_json(record.get_fiat_value());
+                }
+                value
+            })
+            .collect();
+        let fee_stats = resp.get_fee_stats();
+        Ok(json!({
+            "transactions": transactions,
+            "fee_stats": {
+                "confirmed": fee_stats.confirmed,
+                "unconfirmed": fee_stats.unconfirmed,
+                "tx_count": fee_stats.tx_count,
+            },
+        }))
+    }
+}
+
+/// status line and JSON body of the answer to a request, or to one which couldn't be read
+pub type Handler = dyn Fn(Result<Request, Box<dyn Error>>) -> (&'static str, Value) + Send + Sync;
+
+fn serve_connection(handler: &Handler, mut stream: TcpStream) -> Result<(), Box<dyn Error>> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let (status, body) = handler(read_request(&mut reader));
+    let body = body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
+
+/// answer the requests to `port` of localhost with `handler`, each connection on a thread
+/// of its own, `name` tells the servers apart in the log
+pub fn serve(
+    name: &'static str,
+    port: u16,
+    handler: Box<Handler>,
+) -> Result<JoinHandle<()>, Box<dyn Error>> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    let handler: Arc<Handler> = Arc::from(handler);
+    info!("{} started on port {}", name, port);
+    Ok(thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    warn!("{} failed to accept a connection: {}", name, e);
+                    continue;
+                }
+            };
+            let handler = Arc::clone(&handler);
+            thread::spawn(move || {
+                if let Err(e) = serve_connection(&*handler, stream) {
+                    warn!("{} failed to answer: {}", name, e);
+                }
+            });
+        }
+    }))
+}
+
+/// serve the gateway on `port` of localhost, with the gRPC server on `wallet_rpc_port`
+pub fn start(port: u16, wallet_rpc_port: u16) -> Result<JoinHandle<()>, Box<dyn Error>> {
+    let gateway = Gateway::new(wallet_rpc_port)?;
+    serve("rest gateway", port, Box::new(move |req| match req {
+        Ok(req) => gateway.handle(&req),
+        Err(e) => error_json(&*invalid(&e.to_string())),
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::{read_request, openapi, ROUTES};
+
+    #[test]
+    fn request() {
+        let raw = "POST /v1/send?wallet=savings HTTP/1.1\r\nHost: localhost\r\n\
+                   Authorization: Bearer payout\r\ncontent-length: 15\r\n\r\n{\"amount\":1000}";
+        let req = read_request(&mut Cursor::new(raw)).unwrap();
+        assert_eq!((req.method.as_str(), req.path.as_str()), ("POST", "/v1/send"));
+        assert_eq!(req.wallet, "savings");
+        assert_eq!(req.authorization, Some("Bearer payout".to_owned()));
+        assert_eq!(req.body, b"{\"amount\":1000}".to_vec());
+
+        let req = read_request(&mut Cursor::new("GET /v1/balance HTTP/1.1\r\n\r\n")).unwrap();
+        assert_eq!(req.path, "/v1/balance");
+        assert!(req.wallet.is_empty() && req.body.is_empty() && req.authorization.is_none());
+
+        assert!(read_request(&mut Cursor::new("\r\n")).is_err());
+    }
+
+    #[test]
+    fn spec() {
+        let spec = openapi();
+        for route in ROUTES {
+            let operation = &spec["paths"][route.path][route.method.to_lowercase()];
+            assert_eq!(operation["summary"], route.summary);
+        }
+        let send = &spec["paths"]["/v1/send"]["post"]["requestBody"]["content"];
+        let schema = &send["application/json"]["schema"];
+        assert_eq!(schema["required"], serde_json::json!(["address", "amount"]));
+        assert!(spec["paths"]["/v1/balance"]["get"]["requestBody"].is_null());
+    }
+}
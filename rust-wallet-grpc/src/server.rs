@@ -13,63 +13,649 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 use bitcoin::{
-    consensus::serialize,
-    blockdata::transaction::OutPoint,
+    consensus::{serialize, deserialize},
+    network::constants::Network,
+    blockdata::{transaction::{OutPoint, SigHashType}, script::Script},
+    util::{address::Address, key::PublicKey},
 };
 use bitcoin_hashes::sha256d::Hash as Sha256dHash;
+use futures::{Stream, sync::mpsc as stream_mpsc};
 use protobuf::RepeatedField;
 use tls_api_native_tls;
 use wallet::{
-    account::{Utxo, AccountAddressType},
-    walletlibrary::LockId,
-    interface::Wallet as WalletInterface,
+    account::{Utxo, AccountAddressType, AddressChain, AddressInfo, DerivationScheme},
+    walletlibrary::{
+        LockId, InputFilter, WalletLibraryMode, KeyGenConfig, DecryptConfig, DEFAULT_ENTROPY,
+        DEFAULT_PASSPHRASE, DEFAULT_SALT, WatchedUtxo, SilentPaymentUtxo, SpendingPolicy,
+        PendingSpend, InputSpec, TimelockedUtxo, ScriptUtxo, SendOutcome, IdempotentSend,
+        InputType, estimate_tx_size, VaultUtxo, StateIssue, SendOptions, WalletState,
+    },
+    error::WalletError,
+    audit::AuditEntry,
+    keyfactory::MasterKeyEntropy,
+    mnemonic::{Mnemonic, MnemonicError},
+    events::WalletEvent,
+    history::{TxRecord, TxStatus, FeeStats, ExportFormat},
+    interface::{
+        Wallet as WalletInterface, SyncProgress, ConnectionStatus, BackendConfig, BackendKind,
+        CancelToken,
+    },
+    supervisor::{Supervisor, ProcessEvent},
+    context::Attach,
+    price::fiat_value,
+    timelock::Timelock,
+    vault::Vault,
+    qr::{self, QrFormat, QrPayload, DEFAULT_MAX_FRAGMENT_LEN},
 };
 
-use log::info;
+use tracing::{info, warn, info_span, span::EnteredSpan};
 
 use std::{
     thread,
     error::Error,
-    time::Duration,
+    path::Path,
+    str::FromStr,
+    time::{Duration, Instant},
+    collections::HashMap,
     sync::{
         Arc, Mutex,
         mpsc::{self, Sender},
     },
 };
 
+use super::webhook::{Webhook, WebhookConfig};
+use super::publisher::Publisher;
+use super::autosync::{self, AutoSyncConfig, ChainEvent, EventLoop};
+use super::price::FiatConfig;
+use super::error::{rpc_error, to_grpc};
+use super::acl::{Acl, bearer_token};
+use super::logging::LogFilter;
 use super::walletrpc_grpc::{Wallet, WalletServer};
 use super::walletrpc::{
     NewAddressRequest, NewAddressResponse, NewChangeAddressRequest, NewChangeAddressResponse,
     GetUtxoListRequest, GetUtxoListResponse, SyncWithTipRequest, SyncWithTipResponse,
     MakeTxRequest, MakeTxResponse, SendCoinsRequest, SendCoinsResponse,
     WalletBalanceRequest, WalletBalanceResponse, AddressType as RpcAddressType, Utxo as RpcUtxo, OutPoint as RpcOutPoint,
-    UnlockCoinsRequest, UnlockCoinsResponse, ShutdownRequest, ShutdownResponse
+    UnlockCoinsRequest, UnlockCoinsResponse, ShutdownRequest, ShutdownResponse,
+    ExportBackupRequest, ExportBackupResponse, RestoreBackupRequest, RestoreBackupResponse,
+    ExportStateSnapshotRequest, ExportStateSnapshotResponse, ImportStateSnapshotRequest,
+    ImportStateSnapshotResponse, CheckWalletRequest, CheckWalletResponse,
+    StateIssue as RpcStateIssue,
+    CreateWalletRequest, CreateWalletResponse, OpenWalletRequest, OpenWalletResponse,
+    ValidateMnemonicRequest, ValidateMnemonicResponse, MnemonicProblem,
+    ListWalletsRequest, ListWalletsResponse, GetUnusedAddressRequest, GetUnusedAddressResponse,
+    ListAddressesRequest, ListAddressesResponse, AddressInfo as RpcAddressInfo,
+    FreezeUtxoRequest, FreezeUtxoResponse, UnfreezeUtxoRequest, UnfreezeUtxoResponse,
+    InputFilter as RpcInputFilter, PrepareTransactionRequest, PrepareTransactionResponse,
+    CommitTransactionRequest, CommitTransactionResponse, TxOutput as RpcTxOutput,
+    AbandonTransactionRequest, AbandonTransactionResponse, SubscribeEventsRequest,
+    AccelerateTransactionRequest, AccelerateTransactionResponse, CancelTransactionRequest,
+    CancelTransactionResponse, EstimateTxSizeRequest, EstimateTxSizeResponse,
+    GetPaymentCodeRequest, GetPaymentCodeResponse, NotifyPaymentCodeRequest,
+    NotifyPaymentCodeResponse, PaymentCodeAddressRequest, PaymentCodeAddressResponse,
+    SweepPaymentCodesRequest, SweepPaymentCodesResponse,
+    GetSilentPaymentAddressRequest, GetSilentPaymentAddressResponse,
+    ListSilentPaymentUtxosRequest, ListSilentPaymentUtxosResponse,
+    SilentPaymentUtxo as RpcSilentPaymentUtxo, SweepSilentPaymentsRequest,
+    SweepSilentPaymentsResponse, ProveReservesRequest, ProveReservesResponse,
+    GetSpendingPolicyRequest, GetSpendingPolicyResponse, SetSpendingPolicyRequest,
+    SetSpendingPolicyResponse, SpendingPolicy as RpcSpendingPolicy,
+    ListPendingSpendsRequest, ListPendingSpendsResponse, PendingSpend as RpcPendingSpend,
+    ApproveSpendRequest, ApproveSpendResponse, RejectSpendRequest, RejectSpendResponse,
+    GetAuditLogRequest, GetAuditLogResponse, AuditEntry as RpcAuditEntry, GetInfoRequest,
+    GetInfoResponse, ErrorCode, SyncProgress as RpcSyncProgress, RescanRequest,
+    CancelOperationRequest, CancelOperationResponse,
+    FundChannelRequest, FundChannelResponse, ReleaseFundingRequest, ReleaseFundingResponse,
+    PublishFundingRequest, PublishFundingResponse, WatchScriptRequest, WatchScriptResponse,
+    ListWatchedUtxosRequest, ListWatchedUtxosResponse, WatchedUtxo as RpcWatchedUtxo,
+    WatchedFundedEvent, WatchedSpentEvent, UnexpectedUnvaultEvent,
+    WalletEvent as RpcWalletEvent, ConflictEvent, DepositEvent, ConfirmedEvent, NewTipEvent,
+    BackendStatusEvent, ReconnectRequest, ReconnectResponse, GetConnectionStatusRequest,
+    GetConnectionStatusResponse, ConnectionStatus as RpcConnectionStatus,
+    BackendKind as RpcBackendKind, GetTransactionsRequest, GetTransactionsResponse,
+    TxRecord as RpcTxRecord, TxStatus as RpcTxStatus, FeeStats as RpcFeeStats,
+    FiatValue as RpcFiatValue, ExportHistoryRequest, ExportHistoryChunk,
+    ExportFormat as RpcExportFormat, SweepAddressRequest, SweepAddressResponse,
+    NewTimelockedAddressRequest, NewTimelockedAddressResponse,
+    TimelockedUtxo as RpcTimelockedUtxo, GetScriptAccountXpubRequest,
+    GetScriptAccountXpubResponse, RegisterPolicyRequest, RegisterPolicyResponse,
+    NewScriptAddressRequest, NewScriptAddressResponse, ScriptUtxo as RpcScriptUtxo,
+    SpendScriptCoinsRequest, SpendScriptCoinsResponse, FinalizeScriptPsbtRequest,
+    FinalizeScriptPsbtResponse, GetDescriptorsRequest, GetDescriptorsResponse,
+    MigrateDerivationRequest, MigrateDerivationResponse, ExportElectrumWalletRequest,
+    ExportElectrumWalletResponse, GetImportDescriptorsRequest, GetImportDescriptorsResponse,
+    CreateUnsignedTxRequest, CreateUnsignedTxResponse, UnsignedTx as RpcUnsignedTx,
+    ListUnsignedTxsRequest, ListUnsignedTxsResponse, BroadcastSignedPsbtRequest,
+    BroadcastSignedPsbtResponse, CancelUnsignedTxRequest, CancelUnsignedTxResponse,
+    QrFormat as RpcQrFormat, EncodeQrRequest, EncodeQrResponse, DecodeQrRequest,
+    DecodeQrResponse, Network as RpcNetwork, BalanceCategory, CategoryBalance,
+    LockWalletRequest, LockWalletResponse, UnlockWalletRequest, UnlockWalletResponse,
+    SetLogLevelRequest, SetLogLevelResponse, GetAccountStatsRequest, GetAccountStatsResponse,
+    SetAddressLabelRequest, SetAddressLabelResponse, SetVaultRequest, SetVaultResponse,
+    NewVaultAddressRequest, NewVaultAddressResponse, VaultUtxo as RpcVaultUtxo, UnvaultRequest,
+    UnvaultResponse, SwitchBackendRequest, SwitchBackendResponse, BackendDivergenceEvent,
+    AccountStats as RpcAccountStats, AddressStats as RpcAddressStats,
 };
+use super::reflection_grpc::ServerReflectionServer;
+use super::reflect::ReflectionImpl;
 
 pub const DEFAULT_WALLET_RPC_PORT: u16 = 5051;
+/// version of the calls served, see `GetInfoResponse`
+pub const API_VERSION: u32 = 39;
 const SHUTDOWN_TIMEOUT_IN_MS: u64 = 50;
+// a restarted backend takes a while until it serves requests
+const RECONNECT_ATTEMPTS: u32 = 30;
+const RECONNECT_INTERVAL_MS: u64 = 1_000;
+// how long a backend switch waits for the running calls of the wallet to finish
+const SWITCH_DRAIN_TIMEOUT_MS: u64 = 10_000;
+const SWITCH_DRAIN_POLL_MS: u64 = 50;
+// entries of the audit log returned by a single call
+const DEFAULT_AUDIT_PAGE: usize = 100;
+const MAX_AUDIT_PAGE: usize = 1000;
+// bytes of an exported history per message of the stream
+const EXPORT_CHUNK_SIZE: usize = 64 * 1024;
+// blocks a sync processes before it lets other requests use the wallet
+const SYNC_BATCH_BLOCKS: usize = 10;
+
+/// name of the wallet the daemon is started with, requests without a wallet name go to it
+pub const DEFAULT_WALLET_NAME: &str = "default";
+
+/// opens the wallet with the given name in a database of its own,
+/// `WalletLibraryMode::Create` makes a new one
+pub type WalletFactory = Box<
+    dyn Fn(&str, WalletLibraryMode) -> Result<(Box<dyn WalletInterface + Send>, Mnemonic), Box<dyn Error>>
+        + Send
+        + Sync,
+>;
+
+/// connects to the backend of the given kind and checks it follows the chain of the wallets,
+/// the returned `Attach` moves a wallet over to it. See `GlobalContext::switch_backend`
+pub type BackendSwitcher =
+    Box<dyn Fn(BackendKind, &BackendConfig) -> Result<Attach, Box<dyn Error>> + Send + Sync>;
+
+/// what the server runs along with the wallet it is started with,
+/// the default serves that wallet alone
+#[derive(Default)]
+pub struct ServerConfig {
+    /// opens the wallets created and opened over rpc
+    pub factory: Option<WalletFactory>,
+    pub webhook: Option<WebhookConfig>,
+    /// zmq endpoint the events of the wallets are published on
+    pub zmq_endpoint: Option<String>,
+    pub fiat: Option<FiatConfig>,
+    /// calls changing the state of the wallets are rejected
+    pub read_only: bool,
+    /// the managed backend processes, killed along with the server
+    pub supervisor: Option<Supervisor>,
+    pub acl: Option<Acl>,
+    /// `None` if the subscriber of the process isn't the one of `logging::init`
+    pub log_filter: Option<LogFilter>,
+    pub auto_sync: Option<AutoSyncConfig>,
+    pub switcher: Option<BackendSwitcher>,
+}
+
+type SharedWallet = Arc<Mutex<Box<dyn WalletInterface + Send>>>;
+
+// the wallet out of its `SharedWallet` once the calls holding it returned, handed back
+// if they take longer than `SWITCH_DRAIN_TIMEOUT_MS`
+fn drain(mut wallet: SharedWallet) -> Result<Box<dyn WalletInterface + Send>, SharedWallet> {
+    let started = Instant::now();
+    loop {
+        match Arc::try_unwrap(wallet) {
+            Ok(wallet) => return Ok(wallet.into_inner().unwrap()),
+            Err(shared) if started.elapsed() >= Duration::from_millis(SWITCH_DRAIN_TIMEOUT_MS) => {
+                return Err(shared)
+            }
+            Err(shared) => wallet = shared,
+        }
+        thread::sleep(Duration::from_millis(SWITCH_DRAIN_POLL_MS));
+    }
+}
 
 fn grpc_error<T: Send>(resp: Result<T, Box<dyn Error>>) -> grpc::SingleResponse<T> {
     match resp {
         Ok(resp) => grpc::SingleResponse::completed(resp),
-        Err(e) => grpc::SingleResponse::err(grpc::Error::Panic(e.to_string())),
+        Err(e) => grpc::SingleResponse::err(to_grpc(&*e)),
+    }
+}
+
+// name the client gives itself in the `caller` metadata, there is no authentication to check it
+fn caller(options: &grpc::RequestOptions) -> String {
+    options
+        .metadata
+        .get("caller")
+        .map(|value| String::from_utf8_lossy(value).into_owned())
+        .unwrap_or_default()
+}
+
+// runs `f` with the wallet locked, its audit log attributes the operations of `f` to `caller`
+fn as_caller<T, F>(wallet: SharedWallet, caller: String, f: F) -> Result<T, Box<dyn Error>>
+where
+    F: FnOnce(&mut Box<dyn WalletInterface + Send>) -> Result<T, Box<dyn Error>>,
+{
+    let mut wallet = wallet.lock().unwrap();
+    wallet.wallet_lib_mut().set_audit_caller(caller);
+    let result = f(&mut wallet);
+    wallet.wallet_lib_mut().set_audit_caller(String::new());
+    result
+}
+
+// the wallet is unlocked while a batch of blocks downloads and locked while it is processed,
+// requests arriving during a long sync are served meanwhile. A cancelled sync stops after
+// the batch, the height reached is stored with every block
+fn sync_shared(
+    wallet: &SharedWallet,
+    cancel: &CancelToken,
+    progress: &mut dyn FnMut(SyncProgress),
+) -> Result<(), Box<dyn Error>> {
+    loop {
+        let fetch = wallet.lock().unwrap().block_fetch(SYNC_BATCH_BLOCKS);
+        let done = match fetch {
+            Some(fetch) => {
+                let fetched = fetch();
+                wallet.lock().unwrap().sync_fetched(fetched, progress)?
+            }
+            None => wallet.lock().unwrap().sync_with_tip_batch(SYNC_BATCH_BLOCKS, progress)?,
+        };
+        if done {
+            return Ok(());
+        }
+        if cancel.is_cancelled() {
+            return Err(WalletError::Cancelled.into());
+        }
+    }
+}
+
+// the events of `autosync`, a wallet failing to sync doesn't keep the others from it.
+// The transactions of a block are announced as well, the known ones are left to the sync
+fn chain_event(wallets: &Mutex<HashMap<String, SharedWallet>>, event: &ChainEvent) {
+    let wallets: Vec<(String, SharedWallet)> = wallets
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(name, wallet)| (name.clone(), Arc::clone(wallet)))
+        .collect();
+    for (name, wallet) in wallets {
+        if let ChainEvent::Tx(tx) = event {
+            let mut wallet = wallet.lock().unwrap();
+            if !wallet.wallet_lib().knows_tx(&tx.txid()) {
+                wallet.wallet_lib_mut().process_tx(tx);
+            }
+        } else if let Err(e) = sync_shared(&wallet, &CancelToken::new(), &mut |_| ()) {
+            warn!("background sync of wallet {} failed: {}", name, e);
+        }
+    }
+}
+
+// tell the open wallets about a backend process going down, or reconnect them once it is back,
+// the blocks missed meanwhile are synced through `events`
+fn backend_changed(
+    wallets: &Mutex<HashMap<String, SharedWallet>>,
+    events: Option<&EventLoop>,
+    event: ProcessEvent,
+) {
+    let wallets: Vec<SharedWallet> = wallets.lock().unwrap().values().cloned().collect();
+    match event {
+        ProcessEvent::Exited { name, .. } => {
+            for wallet in wallets {
+                let event = WalletEvent::BackendDown { name: name.clone() };
+                wallet.lock().unwrap().wallet_lib_mut().emit(event);
+            }
+        }
+        // retried in a thread of its own, the supervisor goes on watching the processes
+        ProcessEvent::Restarted { name } => {
+            let events = events.cloned();
+            thread::spawn(move || {
+                for wallet in wallets {
+                    for attempt in 1..=RECONNECT_ATTEMPTS {
+                        thread::sleep(Duration::from_millis(RECONNECT_INTERVAL_MS));
+                        let mut wallet = wallet.lock().unwrap();
+                        match wallet.reconnect(None) {
+                            Ok(()) => {
+                                let event = WalletEvent::BackendUp { name: name.clone() };
+                                wallet.wallet_lib_mut().emit(event);
+                                break;
+                            }
+                            Err(e) if attempt == RECONNECT_ATTEMPTS => {
+                                warn!("failed to reconnect to {}: {}", name, e)
+                            }
+                            Err(_) => (),
+                        }
+                    }
+                }
+                if let Some(events) = events {
+                    events.send(ChainEvent::Reconnected);
+                }
+            });
+        }
+        // still down, the wallets were told when it exited
+        ProcessEvent::RestartFailed { .. } => (),
+    }
+}
+
+fn out_point_from_rpc(op: &RpcOutPoint) -> Result<OutPoint, Box<dyn Error>> {
+    use bitcoin_hashes::Hash;
+
+    Ok(OutPoint {
+        txid: Sha256dHash::from_slice(&op.txid[..])?,
+        vout: op.vout,
+    })
+}
+
+// `mature` as of the block following `tip_height`
+fn timelocked_utxo_to_rpc(utxo: TimelockedUtxo, tip_height: usize) -> RpcTimelockedUtxo {
+    let mut rpc_utxo = RpcTimelockedUtxo::new();
+    rpc_utxo.set_value(utxo.value);
+    rpc_utxo.set_out_point(out_point_to_rpc(utxo.out_point));
+    match utxo.timelock {
+        Timelock::Blocks(blocks) => rpc_utxo.set_blocks(blocks as u32),
+        Timelock::Height(height) => rpc_utxo.set_height(height),
+    }
+    rpc_utxo.set_mature_height(utxo.mature_height().unwrap_or(0) as u64);
+    rpc_utxo.set_mature(utxo.is_mature(tip_height));
+    rpc_utxo
+}
+
+// `mature` as of the block following `tip_height`
+fn vault_utxo_to_rpc(utxo: VaultUtxo, tip_height: usize) -> RpcVaultUtxo {
+    let mut rpc_utxo = RpcVaultUtxo::new();
+    rpc_utxo.set_value(utxo.value);
+    rpc_utxo.set_out_point(out_point_to_rpc(utxo.out_point));
+    rpc_utxo.set_delay(utxo.vault.delay as u32);
+    rpc_utxo.set_mature_height(utxo.mature_height().unwrap_or(0) as u64);
+    rpc_utxo.set_mature(utxo.is_mature(tip_height));
+    rpc_utxo
+}
+
+fn script_utxo_to_rpc(utxo: ScriptUtxo) -> RpcScriptUtxo {
+    let mut rpc_utxo = RpcScriptUtxo::new();
+    rpc_utxo.set_value(utxo.value);
+    rpc_utxo.set_out_point(out_point_to_rpc(utxo.out_point));
+    rpc_utxo.set_account(utxo.account);
+    rpc_utxo.set_index(utxo.index);
+    rpc_utxo.set_height(utxo.height.unwrap_or(0) as u64);
+    rpc_utxo
+}
+
+fn out_point_to_rpc(op: OutPoint) -> RpcOutPoint {
+    let mut rpc_op = RpcOutPoint::new();
+    rpc_op.set_txid(op.txid[..].to_vec());
+    rpc_op.set_vout(op.vout);
+    rpc_op
+}
+
+impl<'a> From<&'a RpcInputFilter> for InputFilter {
+    fn from(rpc_filter: &'a RpcInputFilter) -> Self {
+        if rpc_filter.has_addr_type() {
+            InputFilter::AddressType(rpc_filter.get_addr_type().into())
+        } else if rpc_filter.has_account() {
+            InputFilter::Account(rpc_filter.get_account())
+        } else if rpc_filter.has_label() {
+            InputFilter::Label(rpc_filter.get_label().to_owned())
+        } else {
+            InputFilter::Any
+        }
+    }
+}
+
+fn or_default(value: String, default: &str) -> String {
+    if value.is_empty() {
+        default.to_owned()
+    } else {
+        value
     }
 }
 
 impl Into<RpcUtxo> for Utxo {
     fn into(self) -> RpcUtxo {
-        let mut op = RpcOutPoint::new();
-        op.set_txid(self.out_point.txid[..].to_vec());
-        op.set_vout(self.out_point.vout);
-
         let mut rpc_utxo = RpcUtxo::new();
         rpc_utxo.set_value(self.value.into());
-        rpc_utxo.set_out_point(op);
+        rpc_utxo.set_out_point(out_point_to_rpc(self.out_point));
         rpc_utxo.set_addr_type(self.addr_type.into());
         rpc_utxo
     }
 }
 
+impl Into<RpcWatchedUtxo> for WatchedUtxo {
+    fn into(self) -> RpcWatchedUtxo {
+        let mut rpc_utxo = RpcWatchedUtxo::new();
+        rpc_utxo.set_out_point(out_point_to_rpc(self.out_point));
+        rpc_utxo.set_value(self.value);
+        rpc_utxo.set_script_pubkey(self.script_pubkey.into_bytes());
+        rpc_utxo.set_label(self.label);
+        rpc_utxo
+    }
+}
+
+impl Into<RpcSilentPaymentUtxo> for SilentPaymentUtxo {
+    fn into(self) -> RpcSilentPaymentUtxo {
+        let mut rpc_utxo = RpcSilentPaymentUtxo::new();
+        rpc_utxo.set_out_point(out_point_to_rpc(self.out_point));
+        rpc_utxo.set_value(self.value);
+        rpc_utxo
+    }
+}
+
+impl<'a> From<&'a RpcSpendingPolicy> for SpendingPolicy {
+    fn from(rpc_policy: &'a RpcSpendingPolicy) -> Self {
+        let limit = |value| if value == 0 { None } else { Some(value) };
+        SpendingPolicy {
+            daily_limit: limit(rpc_policy.daily_limit),
+            max_per_tx: limit(rpc_policy.max_per_tx),
+            whitelist: rpc_policy.whitelist.to_vec(),
+            blacklist: rpc_policy.blacklist.to_vec(),
+            approval_threshold: limit(rpc_policy.approval_threshold),
+        }
+    }
+}
+
+impl Into<RpcSpendingPolicy> for SpendingPolicy {
+    fn into(self) -> RpcSpendingPolicy {
+        let mut rpc_policy = RpcSpendingPolicy::new();
+        rpc_policy.set_daily_limit(self.daily_limit.unwrap_or(0));
+        rpc_policy.set_max_per_tx(self.max_per_tx.unwrap_or(0));
+        rpc_policy.set_whitelist(RepeatedField::from_vec(self.whitelist));
+        rpc_policy.set_blacklist(RepeatedField::from_vec(self.blacklist));
+        rpc_policy.set_approval_threshold(self.approval_threshold.unwrap_or(0));
+        rpc_policy
+    }
+}
+
+impl Into<RpcPendingSpend> for PendingSpend {
+    fn into(self) -> RpcPendingSpend {
+        let mut rpc_spend = RpcPendingSpend::new();
+        rpc_spend.set_id(self.id);
+        rpc_spend.set_dest_addr(self.dest_addr);
+        rpc_spend.set_amt(self.amt);
+        rpc_spend.set_expires_at(self.expires_at);
+        rpc_spend
+    }
+}
+
+impl Into<RpcAuditEntry> for AuditEntry {
+    fn into(self) -> RpcAuditEntry {
+        let mut rpc_entry = RpcAuditEntry::new();
+        rpc_entry.set_hash(self.hash().to_string());
+        rpc_entry.set_seq(self.seq);
+        rpc_entry.set_timestamp(self.timestamp);
+        rpc_entry.set_operation(self.operation);
+        rpc_entry.set_params(self.params);
+        rpc_entry.set_txid(self.txid.map_or(String::new(), |txid| txid.to_string()));
+        rpc_entry.set_caller(self.caller);
+        rpc_entry.set_prev_hash(self.prev_hash.to_string());
+        rpc_entry
+    }
+}
+
+impl Into<RpcSyncProgress> for SyncProgress {
+    fn into(self) -> RpcSyncProgress {
+        let mut rpc_progress = RpcSyncProgress::new();
+        rpc_progress.set_height_scanned(self.height_scanned as u64);
+        rpc_progress.set_tip_height(self.tip_height as u64);
+        rpc_progress.set_utxos_found(self.utxos_found as u64);
+        rpc_progress
+    }
+}
+
+fn rpc_backend_kind(backend: BackendKind) -> RpcBackendKind {
+    match backend {
+        BackendKind::FullNode => RpcBackendKind::FULL_NODE,
+        BackendKind::Electrum => RpcBackendKind::ELECTRUM,
+    }
+}
+
+impl Into<RpcConnectionStatus> for ConnectionStatus {
+    fn into(self) -> RpcConnectionStatus {
+        let mut rpc_status = RpcConnectionStatus::new();
+        rpc_status.set_backend(rpc_backend_kind(self.backend));
+        rpc_status.set_endpoint(self.endpoint);
+        rpc_status.set_last_success(self.last_success.unwrap_or(0));
+        rpc_status
+    }
+}
+
+impl Into<RpcTxRecord> for TxRecord {
+    fn into(self) -> RpcTxRecord {
+        let mut rpc_record = RpcTxRecord::new();
+        rpc_record.set_txid(self.txid().to_string());
+        match self.status {
+            TxStatus::Unconfirmed => rpc_record.set_status(RpcTxStatus::UNCONFIRMED),
+            TxStatus::Confirmed(height) => {
+                rpc_record.set_status(RpcTxStatus::CONFIRMED);
+                rpc_record.set_height(height as u64);
+            }
+            TxStatus::Conflicted(txid) => {
+                rpc_record.set_status(RpcTxStatus::CONFLICTED);
+                rpc_record.set_conflicting_txid(txid.to_string());
+            }
+            TxStatus::Replaced(txid) => {
+                rpc_record.set_status(RpcTxStatus::REPLACED);
+                rpc_record.set_conflicting_txid(txid.to_string());
+            }
+        }
+        rpc_record.set_fee(self.fee.unwrap_or(0));
+        rpc_record.set_serialized_raw_tx(serialize(&self.tx));
+        rpc_record
+    }
+}
+
+fn rpc_fiat_value(currency: &str, amount: i64, price: f64) -> RpcFiatValue {
+    let mut rpc_value = RpcFiatValue::new();
+    rpc_value.set_currency(currency.to_owned());
+    rpc_value.set_value(fiat_value(amount, price));
+    rpc_value.set_price(price);
+    rpc_value
+}
+
+impl Into<RpcFeeStats> for FeeStats {
+    fn into(self) -> RpcFeeStats {
+        let mut rpc_stats = RpcFeeStats::new();
+        rpc_stats.set_confirmed(self.confirmed);
+        rpc_stats.set_unconfirmed(self.unconfirmed);
+        rpc_stats.set_tx_count(self.tx_count as u64);
+        rpc_stats
+    }
+}
+
+impl Into<RpcWalletEvent> for WalletEvent {
+    fn into(self) -> RpcWalletEvent {
+        let mut rpc_event = RpcWalletEvent::new();
+        match self {
+            WalletEvent::Conflict { txid, conflicting_txid } => {
+                let mut conflict = ConflictEvent::new();
+                conflict.set_txid(txid.to_string());
+                conflict.set_conflicting_txid(conflicting_txid.to_string());
+                rpc_event.set_conflict(conflict);
+            }
+            WalletEvent::Deposit { txid, value } => {
+                let mut deposit = DepositEvent::new();
+                deposit.set_txid(txid.to_string());
+                deposit.set_value(value);
+                rpc_event.set_deposit(deposit);
+            }
+            WalletEvent::Confirmed { txid, block_height, outgoing } => {
+                let mut confirmed = ConfirmedEvent::new();
+                confirmed.set_txid(txid.to_string());
+                confirmed.set_block_height(block_height as u64);
+                confirmed.set_outgoing(outgoing);
+                rpc_event.set_confirmed(confirmed);
+            }
+            WalletEvent::NewTip { height } => {
+                let mut new_tip = NewTipEvent::new();
+                new_tip.set_height(height as u64);
+                rpc_event.set_new_tip(new_tip);
+            }
+            WalletEvent::WatchedFunded { label, out_point, value } => {
+                let mut funded = WatchedFundedEvent::new();
+                funded.set_label(label);
+                funded.set_out_point(out_point_to_rpc(out_point));
+                funded.set_value(value);
+                rpc_event.set_watched_funded(funded);
+            }
+            WalletEvent::WatchedSpent { label, out_point, txid } => {
+                let mut spent = WatchedSpentEvent::new();
+                spent.set_label(label);
+                spent.set_out_point(out_point_to_rpc(out_point));
+                spent.set_txid(txid.to_string());
+                rpc_event.set_watched_spent(spent);
+            }
+            WalletEvent::UnexpectedUnvault { out_point, txid } => {
+                let mut unvault = UnexpectedUnvaultEvent::new();
+                unvault.set_out_point(out_point_to_rpc(out_point));
+                unvault.set_txid(txid.to_string());
+                rpc_event.set_unexpected_unvault(unvault);
+            }
+            WalletEvent::BackendDown { name } => {
+                let mut status = BackendStatusEvent::new();
+                status.set_name(name);
+                rpc_event.set_backend_status(status);
+            }
+            WalletEvent::BackendUp { name } => {
+                let mut status = BackendStatusEvent::new();
+                status.set_name(name);
+                status.set_up(true);
+                rpc_event.set_backend_status(status);
+            }
+            WalletEvent::BackendDivergence { source, height, tip, forked } => {
+                let mut divergence = BackendDivergenceEvent::new();
+                divergence.set_source(source);
+                divergence.set_height(height as u64);
+                divergence.set_tip(tip as u64);
+                divergence.set_forked(forked);
+                rpc_event.set_backend_divergence(divergence);
+            }
+        }
+        rpc_event
+    }
+}
+
+impl Into<RpcStateIssue> for StateIssue {
+    fn into(self) -> RpcStateIssue {
+        let mut rpc_issue = RpcStateIssue::new();
+        rpc_issue.set_description(self.to_string());
+        rpc_issue.set_repairable(self.is_repairable());
+        rpc_issue
+    }
+}
+
+impl Into<RpcAddressInfo> for AddressInfo {
+    fn into(self) -> RpcAddressInfo {
+        let mut rpc_info = RpcAddressInfo::new();
+        rpc_info.set_address(self.address);
+        rpc_info.set_addr_type(self.addr_type.into());
+        rpc_info.set_change(*self.key_path.addr_chain() == AddressChain::Internal);
+        rpc_info.set_index(self.key_path.addr_index());
+        rpc_info.set_used(self.used);
+        let (fingerprint, path) = self.origin;
+        rpc_info.set_master_fingerprint(hex::encode(&fingerprint[..]));
+        rpc_info.set_derivation_path(path.to_string());
+        if let Some(label) = self.label {
+            rpc_info.set_label(label);
+        }
+        rpc_info
+    }
+}
+
 impl From<RpcAddressType> for AccountAddressType {
     fn from(rpc_addr_type: RpcAddressType) -> Self {
         match rpc_addr_type {
@@ -80,6 +666,24 @@ impl From<RpcAddressType> for AccountAddressType {
     }
 }
 
+impl From<RpcExportFormat> for ExportFormat {
+    fn from(rpc_format: RpcExportFormat) -> Self {
+        match rpc_format {
+            RpcExportFormat::CSV => ExportFormat::Csv,
+            RpcExportFormat::JSON => ExportFormat::Json,
+        }
+    }
+}
+
+impl From<RpcQrFormat> for QrFormat {
+    fn from(rpc_format: RpcQrFormat) -> Self {
+        match rpc_format {
+            RpcQrFormat::UR => QrFormat::Ur,
+            RpcQrFormat::BBQR => QrFormat::Bbqr,
+        }
+    }
+}
+
 impl Into<RpcAddressType> for AccountAddressType {
     fn into(self) -> RpcAddressType {
         match self {
@@ -92,29 +696,293 @@ impl Into<RpcAddressType> for AccountAddressType {
 
 struct ShutdownSignal;
 
+// syncs and rescans streaming their progress, by the id reported to their client
+#[derive(Default)]
+struct Operations {
+    next_id: u64,
+    // with the wallet each one runs on
+    tokens: HashMap<u64, (CancelToken, SharedWallet)>,
+}
+
+impl Operations {
+    fn start(&mut self, wallet: &SharedWallet) -> (u64, CancelToken) {
+        self.next_id += 1;
+        let cancel = CancelToken::new();
+        self.tokens.insert(self.next_id, (cancel.clone(), Arc::clone(wallet)));
+        (self.next_id, cancel)
+    }
+
+    fn cancel_on(&self, wallet: &SharedWallet) {
+        for (cancel, _) in self.tokens.values().filter(|(_, w)| Arc::ptr_eq(w, wallet)) {
+            cancel.cancel();
+        }
+    }
+}
+
 struct WalletImpl {
-    af: Arc<Mutex<Box<dyn WalletInterface + Send>>>,
+    wallets: Arc<Mutex<HashMap<String, SharedWallet>>>,
+    // the keys, coins and history of the wallets, read without waiting for a sync
+    states: Arc<Mutex<HashMap<String, WalletState>>>,
+    factory: Option<WalletFactory>,
+    webhook: Option<Webhook>,
+    publisher: Option<Publisher>,
+    fiat: Option<FiatConfig>,
     shutdown: Mutex<Sender<ShutdownSignal>>,
+    read_only: bool,
+    acl: Option<Acl>,
+    // `None` if the subscriber of the process isn't the one of `logging::init`
+    log_filter: Option<LogFilter>,
+    operations: Arc<Mutex<Operations>>,
+    switcher: Option<BackendSwitcher>,
+}
+
+// the parts of a `ServerConfig` the calls use, with the webhook and the publisher started
+#[derive(Default)]
+struct WalletImplConfig {
+    factory: Option<WalletFactory>,
+    webhook: Option<Webhook>,
+    publisher: Option<Publisher>,
+    fiat: Option<FiatConfig>,
+    read_only: bool,
+    acl: Option<Acl>,
+    log_filter: Option<LogFilter>,
+    switcher: Option<BackendSwitcher>,
 }
 
 impl WalletImpl {
     fn new(
-        af: Arc<Mutex<Box<dyn WalletInterface + Send>>>,
+        af: SharedWallet,
         shutdown: Mutex<Sender<ShutdownSignal>>,
+        config: WalletImplConfig,
     ) -> Self {
-        Self { af, shutdown }
+        let wallet_impl = Self {
+            wallets: Arc::new(Mutex::new(HashMap::new())),
+            states: Arc::new(Mutex::new(HashMap::new())),
+            factory: config.factory,
+            webhook: config.webhook,
+            publisher: config.publisher,
+            fiat: config.fiat,
+            shutdown,
+            read_only: config.read_only,
+            acl: config.acl,
+            log_filter: config.log_filter,
+            operations: Arc::new(Mutex::new(Operations::default())),
+            switcher: config.switcher,
+        };
+        wallet_impl.add_state(DEFAULT_WALLET_NAME, &af);
+        wallet_impl.watch(DEFAULT_WALLET_NAME, &af);
+        wallet_impl.wallets.lock().unwrap().insert(DEFAULT_WALLET_NAME.to_owned(), af);
+        wallet_impl
+    }
+
+    // pass the events of the wallet to the configured notifications
+    fn watch(&self, name: &str, wallet: &SharedWallet) {
+        if let Some(ref webhook) = self.webhook {
+            webhook.watch(name, wallet.lock().unwrap().wallet_lib_mut().subscribe());
+        }
+        if let Some(ref publisher) = self.publisher {
+            let events = wallet.lock().unwrap().wallet_lib_mut().subscribe();
+            // read without waiting for a sync of the wallet.
+            // Weak, the publisher must not keep the keys of a closed wallet alive
+            let states = Arc::downgrade(&self.states);
+            let key = name.to_owned();
+            publisher.watch(name, events, move || {
+                let state = states.upgrade()?.lock().unwrap().get(&key).cloned()?;
+                Some(state.wallet_balance())
+            });
+        }
+    }
+
+    // the state stays the one of the wallet when it reloads or moves to another backend
+    fn add_state(&self, name: &str, wallet: &SharedWallet) {
+        let state = wallet.lock().unwrap().wallet_lib().state();
+        self.states.lock().unwrap().insert(name.to_owned(), state);
+    }
+
+    fn state(&self, name: &str) -> Result<WalletState, Box<dyn Error>> {
+        let name = if name.is_empty() { DEFAULT_WALLET_NAME } else { name };
+        match self.states.lock().unwrap().get(name) {
+            Some(state) => Ok(state.clone()),
+            None => {
+                let message = format!("wallet {} is not open", name);
+                Err(Box::new(rpc_error(ErrorCode::NOT_FOUND, &message)))
+            }
+        }
+    }
+
+    fn wallet(&self, name: &str) -> Result<SharedWallet, Box<dyn Error>> {
+        let name = if name.is_empty() { DEFAULT_WALLET_NAME } else { name };
+        match self.wallets.lock().unwrap().get(name) {
+            Some(wallet) => Ok(Arc::clone(wallet)),
+            None => {
+                let message = format!("wallet {} is not open", name);
+                Err(Box::new(rpc_error(ErrorCode::NOT_FOUND, &message)))
+            }
+        }
+    }
+
+    fn open_wallet(&self, name: &str, mode: WalletLibraryMode) -> Result<Mnemonic, Box<dyn Error>> {
+        // the name becomes a directory
+        let valid = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+        if name.is_empty() || !name.chars().all(valid) {
+            return Err(From::from(format!("invalid wallet name: {:?}", name)));
+        }
+        let factory = self
+            .factory
+            .as_ref()
+            .ok_or("the daemon serves a single wallet")?;
+
+        // held while opening, the same database must not be opened twice
+        let mut wallets = self.wallets.lock().unwrap();
+        if wallets.contains_key(name) {
+            return Err(From::from(format!("wallet {} is already open", name)));
+        }
+        let (wallet, mnemonic) = factory(name, mode)?;
+        let wallet = Arc::new(Mutex::new(wallet));
+        self.add_state(name, &wallet);
+        self.watch(name, &wallet);
+        wallets.insert(name.to_owned(), wallet);
+        Ok(mnemonic)
+    }
+
+    // value of `amount` at the current price, an unavailable price source
+    // leaves the value out rather than failing the call
+    fn fiat_value(&self, amount: i64) -> Option<RpcFiatValue> {
+        let fiat = self.fiat.as_ref()?;
+        match fiat.source.current_price(&fiat.currency) {
+            Ok(price) => Some(rpc_fiat_value(&fiat.currency, amount, price)),
+            Err(e) => {
+                warn!("no current price in {}: {}", fiat.currency, e);
+                None
+            }
+        }
+    }
+
+    // every call is checked against the tokens of the daemon first, if it has any.
+    // Without tokens nobody is known to be an admin, the daemon is stopped by a signal then.
+    // The call runs in the span returned
+    fn authorize(
+        &self,
+        m: &grpc::RequestOptions,
+        method: &'static str,
+    ) -> Result<EnteredSpan, grpc::Error> {
+        let span = info_span!("rpc", method).entered();
+        let acl = match self.acl {
+            Some(ref acl) => acl,
+            None if method == "Shutdown" => {
+                let message = "Shutdown needs an admin token, start the daemon with --acl-file";
+                return Err(to_grpc(&rpc_error(ErrorCode::PERMISSION_DENIED, message)));
+            }
+            None => return Ok(span),
+        };
+        let authorization = m
+            .metadata
+            .get("authorization")
+            .map(|value| String::from_utf8_lossy(value).into_owned());
+        let token = match authorization {
+            Some(ref authorization) => match bearer_token(authorization) {
+                Some(token) => Some(token),
+                None => {
+                    let message = "authorization is not a bearer token";
+                    return Err(to_grpc(&rpc_error(ErrorCode::UNAUTHENTICATED, message)));
+                }
+            },
+            None => None,
+        };
+        acl.check(token, method).map_err(|e| {
+            warn!("{} was refused: {}", method, e);
+            to_grpc(&*e)
+        })?;
+        Ok(span)
+    }
+
+    // the sync goes on in a thread of its own while the event loop of the server streams
+    // the progress, `start` is called with the wallet locked before. It stops once the
+    // operation is cancelled or the client is gone
+    fn sync_stream<F>(
+        &self,
+        wallet: SharedWallet,
+        start: F,
+    ) -> grpc::StreamingResponse<RpcSyncProgress>
+    where
+        F: FnOnce(&mut Box<dyn WalletInterface + Send>) -> Result<(), Box<dyn Error>>
+            + Send
+            + 'static,
+    {
+        let (id, cancel) = self.operations.lock().unwrap().start(&wallet);
+        let operations = Arc::clone(&self.operations);
+        let (sender, receiver) = stream_mpsc::unbounded();
+        thread::spawn(move || {
+            let mut last = RpcSyncProgress::new();
+            last.set_operation_id(id);
+            let started = {
+                let mut wallet = wallet.lock().unwrap();
+                start(&mut wallet)
+                    .map(|()| wallet.wallet_lib().get_last_seen_block_height_from_memory())
+            };
+            let result = started.and_then(|height| {
+                // the client learns the id before the first block
+                last.set_height_scanned(height as u64);
+                if sender.unbounded_send(Ok(last.clone())).is_err() {
+                    cancel.cancel();
+                }
+                sync_shared(&wallet, &cancel, &mut |progress| {
+                    last = progress.into();
+                    last.set_operation_id(id);
+                    if sender.unbounded_send(Ok(last.clone())).is_err() {
+                        cancel.cancel();
+                    }
+                })
+            });
+            operations.lock().unwrap().tokens.remove(&id);
+            if let Err(ref e) = result {
+                info!("operation {} stopped: {}", id, e);
+            }
+            let done = result.map_err(|e| to_grpc(&*e)).map(|()| {
+                last.set_done(true);
+                last
+            });
+            let _ = sender.unbounded_send(done);
+        });
+        grpc::StreamingResponse::no_metadata(receiver.then(|progress| match progress {
+            Ok(progress) => progress,
+            Err(()) => Err(grpc::Error::Other("sync stream failed")),
+        }))
+    }
+
+    fn cancel_operation_helper(
+        &self,
+        req: &CancelOperationRequest,
+    ) -> Result<CancelOperationResponse, Box<dyn Error>> {
+        match self.operations.lock().unwrap().tokens.get(&req.operation_id) {
+            Some((cancel, _)) => cancel.cancel(),
+            None => {
+                let message = format!("no operation {} is running", req.operation_id);
+                return Err(Box::new(rpc_error(ErrorCode::NOT_FOUND, &message)));
+            }
+        }
+        Ok(CancelOperationResponse::new())
+    }
+
+    // calls changing the state of the wallet are rejected in read-only mode,
+    // following the blockchain with `sync_with_tip` is still allowed
+    fn check_writable(&self) -> Result<(), Box<dyn Error>> {
+        if self.read_only {
+            return Err(Box::new(rpc_error(ErrorCode::READ_ONLY, "wallet is in read-only mode")));
+        }
+        Ok(())
     }
 
     fn new_address_helper(
         &self,
         req: &NewAddressRequest,
+        caller: String,
     ) -> Result<NewAddressResponse, Box<dyn Error>> {
+        self.check_writable()?;
         let mut resp = NewAddressResponse::new();
-        let mut ac = self.af.lock().unwrap();
-        let account = ac
-            .wallet_lib_mut()
-            .get_account_mut(req.get_addr_type().into());
-        let addr = account.new_address()?;
+        let addr = as_caller(self.wallet(&req.wallet)?, caller, |wallet| {
+            wallet.wallet_lib_mut().new_address(req.get_addr_type().into())
+        })?;
         resp.set_address(addr);
         Ok(resp)
     }
@@ -122,20 +990,56 @@ impl WalletImpl {
     fn new_change_address(
         &self,
         req: &NewChangeAddressRequest,
+        caller: String,
     ) -> Result<NewChangeAddressResponse, Box<dyn Error>> {
+        self.check_writable()?;
         let mut resp = NewChangeAddressResponse::new();
-        let mut ac = self.af.lock().unwrap();
-        let account = ac
+        let addr = as_caller(self.wallet(&req.wallet)?, caller, |wallet| {
+            wallet.wallet_lib_mut().new_change_address(req.get_addr_type().into())
+        })?;
+        resp.set_address(addr);
+        Ok(resp)
+    }
+
+    fn get_unused_address_helper(
+        &self,
+        req: &GetUnusedAddressRequest,
+    ) -> Result<GetUnusedAddressResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        let addr = self
+            .wallet(&req.wallet)?
+            .lock()
+            .unwrap()
             .wallet_lib_mut()
-            .get_account_mut(req.get_addr_type().into());
-        let addr = account.new_change_address()?;
+            .get_unused_address(req.get_addr_type().into())?;
+
+        let mut resp = GetUnusedAddressResponse::new();
         resp.set_address(addr);
         Ok(resp)
     }
 
-    fn make_tx_helper(&self, req: MakeTxRequest) -> Result<MakeTxResponse, Box<dyn Error>> {
+    fn list_addresses_helper(
+        &self,
+        req: &ListAddressesRequest,
+    ) -> Result<ListAddressesResponse, Box<dyn Error>> {
+        let address_list = self.wallet(&req.wallet)?.lock().unwrap().wallet_lib().get_address_list();
+
+        let mut resp = ListAddressesResponse::new();
+        resp.set_addresses(RepeatedField::from_vec(
+            address_list.into_iter().map(|info| info.into()).collect(),
+        ));
+        Ok(resp)
+    }
+
+    fn make_tx_helper(
+        &self,
+        req: MakeTxRequest,
+        caller: String,
+    ) -> Result<MakeTxResponse, Box<dyn Error>> {
         use bitcoin_hashes::Hash;
 
+        self.check_writable()?;
+
         let mut ops = Vec::new();
         for op in req.ops.into_vec() {
             ops.push(OutPoint {
@@ -144,151 +1048,2594 @@ impl WalletImpl {
             })
         }
 
-        let tx = self
-            .af
-            .lock()
-            .unwrap()
-            .make_tx(ops, req.dest_addr, req.amt, req.submit)?;
+        let change_addr_type = if req.has_change_addr_type() {
+            Some(req.get_change_addr_type().get_addr_type().into())
+        } else {
+            None
+        };
+        let (dest_addr, amt, submit) = (req.dest_addr, req.amt, req.submit);
+        let (sequences, sighash_types) = (req.sequences, req.sighash_types);
+        if sequences.is_empty() && sighash_types.is_empty() {
+            let tx = as_caller(self.wallet(&req.wallet)?, caller, |wallet| {
+                wallet.make_tx(ops, dest_addr, amt, submit, change_addr_type)
+            })?;
+            let mut resp = MakeTxResponse::new();
+            resp.set_serialized_raw_tx(serialize(&tx));
+            return Ok(resp);
+        }
+
+        let mut inputs: Vec<InputSpec> = ops.into_iter().map(InputSpec::new).collect();
+        if !sequences.is_empty() {
+            if sequences.len() != inputs.len() {
+                return Err(From::from("expected a sequence for each of the ops"));
+            }
+            for (input, sequence) in inputs.iter_mut().zip(sequences) {
+                input.sequence = sequence;
+            }
+        }
+        if !sighash_types.is_empty() {
+            if sighash_types.len() != inputs.len() {
+                return Err(From::from("expected a sighash type for each of the ops"));
+            }
+            for (input, sighash_type) in inputs.iter_mut().zip(sighash_types) {
+                // from_u32 maps unknown values to some type, refuse them instead
+                input.sighash_type = SigHashType::from_u32(sighash_type);
+                if input.sighash_type.as_u32() != sighash_type {
+                    return Err(From::from(format!("unknown sighash type {:#x}", sighash_type)));
+                }
+            }
+        }
+        let tx = as_caller(self.wallet(&req.wallet)?, caller, |wallet| {
+            wallet.make_tx_advanced(inputs, dest_addr, amt, submit, change_addr_type)
+        })?;
 
         let mut resp = MakeTxResponse::new();
         resp.set_serialized_raw_tx(serialize(&tx));
         Ok(resp)
     }
 
-    fn send_coins_helper(&self, req: SendCoinsRequest) -> Result<SendCoinsResponse, Box<dyn Error>> {
-        let (tx, lock_id) = self.af.lock().unwrap().send_coins(
-            req.dest_addr,
-            req.amt,
-            req.lock_coins,
-            req.witness_only,
-            req.submit,
-        )?;
+    fn send_coins_helper(
+        &self,
+        req: SendCoinsRequest,
+        caller: String,
+    ) -> Result<SendCoinsResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        let change_addr_type = if req.has_change_addr_type() {
+            Some(req.get_change_addr_type().get_addr_type().into())
+        } else {
+            None
+        };
+        let input_filter = if req.has_input_filter() {
+            req.get_input_filter().into()
+        } else if req.witness_only {
+            InputFilter::AddressType(AccountAddressType::P2WKH)
+        } else {
+            InputFilter::Any
+        };
+        let privacy = if req.has_privacy() {
+            Some(req.get_privacy().get_privacy())
+        } else {
+            None
+        };
+        let change_tolerance = if req.has_change_tolerance() {
+            Some(req.get_change_tolerance().get_tolerance())
+        } else {
+            None
+        };
+        let options = SendOptions {
+            lock_coins: req.lock_coins,
+            input_filter,
+            change_addr_type,
+            privacy,
+            change_tolerance,
+            fee_rate: if req.fee_rate != 0 { Some(req.fee_rate) } else { None },
+        };
+        let lock_coins = req.lock_coins;
+        let key = req.idempotency_key;
+        // the wallet stays locked from the lookup of the key to recording the payment
+        let outcome = as_caller(self.wallet(&req.wallet)?, caller, |wallet| {
+            let repeated = if key.is_empty() {
+                None
+            } else {
+                wallet.wallet_lib().get_idempotent_send(&key)
+            };
+            if let Some(send) = repeated {
+                if send.dest_addr != req.dest_addr || send.amt != req.amt {
+                    let message = "the idempotency key was used for another payment";
+                    return Err(Box::new(rpc_error(ErrorCode::INVALID_ARGUMENT, message)));
+                }
+                info!("payment {} was made already", key);
+                return Ok(send.outcome);
+            }
+
+            let outcome = wallet.send_coins(req.dest_addr.clone(), req.amt, req.submit, options)?;
+            if !key.is_empty() {
+                let send = IdempotentSend::new(key, req.dest_addr, req.amt, outcome.clone());
+                wallet.wallet_lib_mut().put_idempotent_send(send);
+            }
+            Ok(outcome)
+        })?;
 
         let mut resp = SendCoinsResponse::new();
-        resp.set_serialized_raw_tx(serialize(&tx));
-        if req.lock_coins {
-            resp.set_lock_id(lock_id.into());
+        let sent = match outcome {
+            SendOutcome::Sent(sent) => sent,
+            SendOutcome::ApprovalRequired(id) => {
+                resp.set_approval_id(id);
+                return Ok(resp);
+            }
+        };
+        resp.set_serialized_raw_tx(serialize(&sent.tx));
+        if lock_coins {
+            resp.set_lock_id(sent.lock_id.into());
         }
+        resp.set_txid(sent.txid().to_string());
+        resp.set_fee(sent.fee);
+        if let Some(change_outpoint) = sent.change_outpoint {
+            resp.set_change_outpoint(out_point_to_rpc(change_outpoint));
+        }
+        let selected_inputs = sent.selected_inputs.into_iter().map(out_point_to_rpc).collect();
+        resp.set_selected_inputs(RepeatedField::from_vec(selected_inputs));
         Ok(resp)
     }
-}
 
-impl Wallet for WalletImpl {
-    fn new_address(
+    fn prepare_transaction_helper(
         &self,
-        _m: grpc::RequestOptions,
-        req: NewAddressRequest,
-    ) -> grpc::SingleResponse<NewAddressResponse> {
-        info!("new {:?} address was requested", req.addr_type);
-        grpc_error(self.new_address_helper(&req))
-    }
+        req: PrepareTransactionRequest,
+    ) -> Result<PrepareTransactionResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        let change_addr_type = if req.has_change_addr_type() {
+            Some(req.get_change_addr_type().get_addr_type().into())
+        } else {
+            None
+        };
+        let input_filter = if req.has_input_filter() {
+            req.get_input_filter().into()
+        } else {
+            InputFilter::Any
+        };
+        let privacy = if req.has_privacy() {
+            Some(req.get_privacy().get_privacy())
+        } else {
+            None
+        };
+        let (prepared_id, prepared) = self
+            .wallet(&req.wallet)?
+            .lock()
+            .unwrap()
+            .wallet_lib_mut()
+            .prepare_tx(req.dest_addr, req.amt, input_filter, change_addr_type, privacy)?;
 
-    fn new_change_address(
-        &self,
-        _m: grpc::RequestOptions,
-        req: NewChangeAddressRequest,
-    ) -> grpc::SingleResponse<NewChangeAddressResponse> {
-        info!("new {:?} change address was requested", req.addr_type);
-        grpc_error(self.new_change_address(&req))
+        let mut outputs = vec![(prepared.dest_addr, prepared.tx.output[0].value, false)];
+        // dust change is left to the fee
+        if !prepared.change_addr.is_empty() {
+            outputs.push((prepared.change_addr, prepared.change, true));
+        }
+        let mut resp = PrepareTransactionResponse::new();
+        resp.set_prepared_id(prepared_id);
+        resp.set_inputs(RepeatedField::from_vec(
+            prepared.inputs.into_iter().map(|utxo| utxo.into()).collect(),
+        ));
+        resp.set_outputs(RepeatedField::from_vec(
+            outputs
+                .into_iter()
+                .map(|(address, value, change)| {
+                    let mut output = RpcTxOutput::new();
+                    output.set_address(address);
+                    output.set_value(value);
+                    output.set_change(change);
+                    output
+                })
+                .collect(),
+        ));
+        resp.set_fee(prepared.fee);
+        resp.set_vsize(prepared.vsize as u64);
+        resp.set_change(prepared.change);
+        Ok(resp)
     }
 
-    fn get_utxo_list(
+    fn commit_transaction_helper(
         &self,
-        _m: grpc::RequestOptions,
-        _req: GetUtxoListRequest,
-    ) -> grpc::SingleResponse<GetUtxoListResponse> {
-        info!("utxo list was requested");
-        let mut resp = GetUtxoListResponse::new();
-        let utxo_list = self.af.lock().unwrap().wallet_lib().get_utxo_list();
-        resp.set_utxos(RepeatedField::from_vec(
-            utxo_list.into_iter().map(|utxo| utxo.into()).collect(),
-        ));
-        grpc::SingleResponse::completed(resp)
+        req: &CommitTransactionRequest,
+        caller: String,
+    ) -> Result<CommitTransactionResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        let tx = as_caller(self.wallet(&req.wallet)?, caller, |wallet| {
+            wallet.commit_tx(req.prepared_id)
+        })?;
+
+        let mut resp = CommitTransactionResponse::new();
+        resp.set_serialized_raw_tx(serialize(&tx));
+        Ok(resp)
     }
 
-    fn wallet_balance(
+    fn abandon_transaction_helper(
         &self,
-        _m: ::grpc::RequestOptions,
-        _req: WalletBalanceRequest,
-    ) -> grpc::SingleResponse<WalletBalanceResponse> {
-        info!("wallet balance was requested");
-        let mut resp = WalletBalanceResponse::new();
-        let balance = self.af.lock().unwrap().wallet_lib().wallet_balance();
-        resp.set_total_balance(balance);
-        grpc::SingleResponse::completed(resp)
+        req: &AbandonTransactionRequest,
+    ) -> Result<AbandonTransactionResponse, Box<dyn Error>> {
+        use bitcoin_hashes::hex::FromHex;
+
+        self.check_writable()?;
+        let txid = Sha256dHash::from_hex(&req.txid)?;
+        self.wallet(&req.wallet)?
+            .lock()
+            .unwrap()
+            .wallet_lib_mut()
+            .abandon_tx(&txid)?;
+        Ok(AbandonTransactionResponse::new())
     }
 
-    fn sync_with_tip(
+    fn accelerate_transaction_helper(
         &self,
-        _m: grpc::RequestOptions,
-        _req: SyncWithTipRequest,
-    ) -> grpc::SingleResponse<SyncWithTipResponse> {
-        info!("manual(not ZMQ) sync with tip was requested");
+        req: &AccelerateTransactionRequest,
+        caller: String,
+    ) -> Result<AccelerateTransactionResponse, Box<dyn Error>> {
+        use bitcoin_hashes::hex::FromHex;
 
-        let resp = self.af.lock().unwrap()
-            .sync_with_tip()
-            .map(|()| SyncWithTipResponse::new());
-        grpc_error(resp)
+        self.check_writable()?;
+        let txid = Sha256dHash::from_hex(&req.txid)?;
+        let tx = as_caller(self.wallet(&req.wallet)?, caller, |wallet| {
+            wallet.accelerate(&txid, req.fee_rate)
+        })?;
+
+        let mut resp = AccelerateTransactionResponse::new();
+        resp.set_serialized_raw_tx(serialize(&tx));
+        Ok(resp)
     }
 
-    fn make_tx(
+    fn cancel_transaction_helper(
         &self,
-        _m: grpc::RequestOptions,
-        req: MakeTxRequest,
-    ) -> grpc::SingleResponse<MakeTxResponse> {
-        info!("make_tx was requested");
-        grpc_error(self.make_tx_helper(req))
+        req: &CancelTransactionRequest,
+        caller: String,
+    ) -> Result<CancelTransactionResponse, Box<dyn Error>> {
+        use bitcoin_hashes::hex::FromHex;
+
+        self.check_writable()?;
+        let txid = Sha256dHash::from_hex(&req.txid)?;
+        let tx = as_caller(self.wallet(&req.wallet)?, caller, |wallet| {
+            wallet.cancel_tx(&txid, req.fee_rate)
+        })?;
+
+        let mut resp = CancelTransactionResponse::new();
+        resp.set_serialized_raw_tx(serialize(&tx));
+        Ok(resp)
     }
 
-    fn send_coins(
+    fn estimate_tx_size_helper(
         &self,
-        _m: grpc::RequestOptions,
-        req: SendCoinsRequest,
-    ) -> grpc::SingleResponse<SendCoinsResponse> {
-        info!("send_coins was requested");
-        grpc_error(self.send_coins_helper(req))
+        req: &EstimateTxSizeRequest,
+    ) -> Result<EstimateTxSizeResponse, Box<dyn Error>> {
+        let inputs = [
+            (InputType::P2PKH, req.p2pkh_inputs as usize),
+            (InputType::P2SHWH, req.p2shwh_inputs as usize),
+            (InputType::P2WKH, req.p2wkh_inputs as usize),
+            (InputType::P2TR, req.p2tr_inputs as usize),
+        ];
+        let size = estimate_tx_size(&inputs, req.outputs as usize);
+
+        let mut resp = EstimateTxSizeResponse::new();
+        resp.set_weight(size.weight as u64);
+        resp.set_vsize(size.vsize as u64);
+        resp.set_fee((size.vsize as u64).saturating_mul(req.fee_rate));
+        Ok(resp)
     }
 
-    fn unlock_coins(
+    fn get_payment_code_helper(
         &self,
-        _m: grpc::RequestOptions,
-        req: UnlockCoinsRequest,
-    ) -> grpc::SingleResponse<UnlockCoinsResponse> {
-        info!("unlock_coins was requested");
-        self.af
+        req: &GetPaymentCodeRequest,
+    ) -> Result<GetPaymentCodeResponse, Box<dyn Error>> {
+        let payment_code = self
+            .wallet(&req.wallet)?
             .lock()
             .unwrap()
-            .wallet_lib_mut()
-            .unlock_coins(LockId::from(req.lock_id));
+            .wallet_lib()
+            .payment_code();
 
-        let resp = UnlockCoinsResponse::new();
-        grpc::SingleResponse::completed(resp)
+        let mut resp = GetPaymentCodeResponse::new();
+        resp.set_payment_code(payment_code);
+        Ok(resp)
     }
 
-    fn shutdown(
+    fn notify_payment_code_helper(
         &self,
-        _m: grpc::RequestOptions,
-        _req: ShutdownRequest,
-    ) -> grpc::SingleResponse<ShutdownResponse> {
-        info!("shutdown was requested");
-
-        self.shutdown.lock().unwrap().send(ShutdownSignal).unwrap();
+        req: &NotifyPaymentCodeRequest,
+        caller: String,
+    ) -> Result<NotifyPaymentCodeResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        let tx = as_caller(self.wallet(&req.wallet)?, caller, |wallet| {
+            wallet.notify_payment_code(&req.payment_code)
+        })?;
 
-        let resp = ShutdownResponse::new();
+        let mut resp = NotifyPaymentCodeResponse::new();
+        resp.set_serialized_raw_tx(serialize(&tx));
+        Ok(resp)
+    }
+
+    fn payment_code_address_helper(
+        &self,
+        req: &PaymentCodeAddressRequest,
+    ) -> Result<PaymentCodeAddressResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        let addr = self
+            .wallet(&req.wallet)?
+            .lock()
+            .unwrap()
+            .wallet_lib_mut()
+            .payment_code_address(&req.payment_code)?;
+
+        let mut resp = PaymentCodeAddressResponse::new();
+        resp.set_address(addr);
+        Ok(resp)
+    }
+
+    fn sweep_payment_codes_helper(
+        &self,
+        req: &SweepPaymentCodesRequest,
+        caller: String,
+    ) -> Result<SweepPaymentCodesResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        let tx = as_caller(self.wallet(&req.wallet)?, caller, |wallet| {
+            wallet.sweep_payment_codes(req.get_addr_type().into())
+        })?;
+
+        let mut resp = SweepPaymentCodesResponse::new();
+        resp.set_serialized_raw_tx(serialize(&tx));
+        Ok(resp)
+    }
+
+    fn get_silent_payment_address_helper(
+        &self,
+        req: &GetSilentPaymentAddressRequest,
+    ) -> Result<GetSilentPaymentAddressResponse, Box<dyn Error>> {
+        let addr = self
+            .wallet(&req.wallet)?
+            .lock()
+            .unwrap()
+            .wallet_lib()
+            .silent_payment_address()?;
+
+        let mut resp = GetSilentPaymentAddressResponse::new();
+        resp.set_address(addr);
+        Ok(resp)
+    }
+
+    fn list_silent_payment_utxos_helper(
+        &self,
+        req: &ListSilentPaymentUtxosRequest,
+    ) -> Result<ListSilentPaymentUtxosResponse, Box<dyn Error>> {
+        let utxo_list = self
+            .wallet(&req.wallet)?
+            .lock()
+            .unwrap()
+            .wallet_lib()
+            .get_silent_payment_utxo_list();
+        let mut resp = ListSilentPaymentUtxosResponse::new();
+        resp.set_utxos(RepeatedField::from_vec(
+            utxo_list.into_iter().map(|utxo| utxo.into()).collect(),
+        ));
+        Ok(resp)
+    }
+
+    fn sweep_silent_payments_helper(
+        &self,
+        req: &SweepSilentPaymentsRequest,
+        caller: String,
+    ) -> Result<SweepSilentPaymentsResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        let tx = as_caller(self.wallet(&req.wallet)?, caller, |wallet| {
+            wallet.sweep_silent_payments(req.get_addr_type().into())
+        })?;
+
+        let mut resp = SweepSilentPaymentsResponse::new();
+        resp.set_serialized_raw_tx(serialize(&tx));
+        Ok(resp)
+    }
+
+    fn sweep_address_helper(
+        &self,
+        req: &SweepAddressRequest,
+        caller: String,
+    ) -> Result<SweepAddressResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        let tx = as_caller(self.wallet(&req.wallet)?, caller, |wallet| {
+            wallet.sweep_address(&req.address, req.dest_addr.clone(), req.fee_rate)
+        })?;
+
+        let mut resp = SweepAddressResponse::new();
+        resp.set_serialized_raw_tx(serialize(&tx));
+        Ok(resp)
+    }
+
+    fn new_timelocked_address_helper(
+        &self,
+        req: &NewTimelockedAddressRequest,
+        caller: String,
+    ) -> Result<NewTimelockedAddressResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        let timelock = match (req.blocks, req.height) {
+            (blocks, 0) if blocks <= u16::max_value() as u32 => Timelock::Blocks(blocks as u16),
+            (0, height) => Timelock::Height(height),
+            _ => return Err(From::from("expected either a number of blocks or a height")),
+        };
+        let addr = as_caller(self.wallet(&req.wallet)?, caller, |wallet| {
+            wallet.wallet_lib_mut().new_timelocked_address(timelock)
+        })?;
+
+        let mut resp = NewTimelockedAddressResponse::new();
+        resp.set_address(addr);
+        Ok(resp)
+    }
+
+    fn set_vault_helper(
+        &self,
+        req: &SetVaultRequest,
+        caller: String,
+    ) -> Result<SetVaultResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        if req.delay > u16::max_value() as u32 {
+            return Err(From::from(format!("a delay of {} blocks is too long", req.delay)));
+        }
+        let vault = Vault {
+            recovery_pk: PublicKey::from_str(&req.recovery_pk)?,
+            delay: req.delay as u16,
+        };
+        as_caller(self.wallet(&req.wallet)?, caller, |wallet| {
+            wallet.wallet_lib_mut().set_vault(vault)
+        })?;
+        Ok(SetVaultResponse::new())
+    }
+
+    fn new_vault_address_helper(
+        &self,
+        req: &NewVaultAddressRequest,
+        caller: String,
+    ) -> Result<NewVaultAddressResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        let addr = as_caller(self.wallet(&req.wallet)?, caller, |wallet| {
+            wallet.wallet_lib_mut().new_vault_address()
+        })?;
+
+        let mut resp = NewVaultAddressResponse::new();
+        resp.set_address(addr);
+        Ok(resp)
+    }
+
+    fn unvault_helper(
+        &self,
+        req: &UnvaultRequest,
+        caller: String,
+    ) -> Result<UnvaultResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        let tx = as_caller(self.wallet(&req.wallet)?, caller, |wallet| {
+            wallet.unvault(req.dest_addr.clone(), req.fee_rate)
+        })?;
+
+        let mut resp = UnvaultResponse::new();
+        resp.set_serialized_raw_tx(serialize(&tx));
+        Ok(resp)
+    }
+
+    fn get_script_account_xpub_helper(
+        &self,
+        req: &GetScriptAccountXpubRequest,
+    ) -> Result<GetScriptAccountXpubResponse, Box<dyn Error>> {
+        let wallet = self.wallet(&req.wallet)?;
+        let wallet = wallet.lock().unwrap();
+        let xpub = wallet.wallet_lib().script_account_xpub()?;
+        let key = wallet.wallet_lib().script_account_key()?;
+
+        let mut resp = GetScriptAccountXpubResponse::new();
+        resp.set_xpub(xpub.to_string());
+        resp.set_key(key);
+        Ok(resp)
+    }
+
+    fn register_policy_helper(
+        &self,
+        req: &RegisterPolicyRequest,
+        caller: String,
+    ) -> Result<RegisterPolicyResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        as_caller(self.wallet(&req.wallet)?, caller, |wallet| {
+            wallet.wallet_lib_mut().register_policy(&req.name, &req.policy)
+        })?;
+        Ok(RegisterPolicyResponse::new())
+    }
+
+    fn new_script_address_helper(
+        &self,
+        req: &NewScriptAddressRequest,
+        caller: String,
+    ) -> Result<NewScriptAddressResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        let addr = as_caller(self.wallet(&req.wallet)?, caller, |wallet| {
+            wallet.wallet_lib_mut().new_script_address(&req.name)
+        })?;
+
+        let mut resp = NewScriptAddressResponse::new();
+        resp.set_address(addr);
+        Ok(resp)
+    }
+
+    fn spend_script_coins_helper(
+        &self,
+        req: &SpendScriptCoinsRequest,
+        caller: String,
+    ) -> Result<SpendScriptCoinsResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        let spend = as_caller(self.wallet(&req.wallet)?, caller, |wallet| {
+            wallet.spend_script_coins(&req.name, req.dest_addr.clone(), req.fee_rate)
+        })?;
+
+        let mut resp = SpendScriptCoinsResponse::new();
+        resp.set_psbt(serialize(&spend.psbt));
+        if let Some(tx) = spend.tx {
+            resp.set_serialized_raw_tx(serialize(&tx));
+        }
+        Ok(resp)
+    }
+
+    fn finalize_script_psbt_helper(
+        &self,
+        req: &FinalizeScriptPsbtRequest,
+        caller: String,
+    ) -> Result<FinalizeScriptPsbtResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        let psbt = deserialize(&req.psbt)?;
+        let tx = as_caller(self.wallet(&req.wallet)?, caller, |wallet| {
+            wallet.finalize_script_psbt(psbt)
+        })?;
+
+        let mut resp = FinalizeScriptPsbtResponse::new();
+        resp.set_serialized_raw_tx(serialize(&tx));
+        Ok(resp)
+    }
+
+    fn get_descriptors_helper(
+        &self,
+        req: &GetDescriptorsRequest,
+    ) -> Result<GetDescriptorsResponse, Box<dyn Error>> {
+        let wallet = self.wallet(&req.wallet)?;
+        let descriptors = wallet.lock().unwrap().wallet_lib().descriptors();
+
+        let mut resp = GetDescriptorsResponse::new();
+        resp.set_descriptors(RepeatedField::from_vec(descriptors));
+        Ok(resp)
+    }
+
+    fn get_import_descriptors_helper(
+        &self,
+        req: &GetImportDescriptorsRequest,
+    ) -> Result<GetImportDescriptorsResponse, Box<dyn Error>> {
+        let wallet = self.wallet(&req.wallet)?;
+        let requests = wallet.lock().unwrap().wallet_lib().import_descriptors();
+
+        let mut resp = GetImportDescriptorsResponse::new();
+        resp.set_json(serde_json::to_string_pretty(&requests)?);
+        Ok(resp)
+    }
+
+    fn create_unsigned_tx_helper(
+        &self,
+        req: CreateUnsignedTxRequest,
+        caller: String,
+    ) -> Result<CreateUnsignedTxResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        let change_addr_type = if req.has_change_addr_type() {
+            Some(req.get_change_addr_type().get_addr_type().into())
+        } else {
+            None
+        };
+        let input_filter = if req.has_input_filter() {
+            req.get_input_filter().into()
+        } else {
+            InputFilter::Any
+        };
+        let privacy = if req.has_privacy() {
+            Some(req.get_privacy().get_privacy())
+        } else {
+            None
+        };
+        let (unsigned, psbt) = as_caller(self.wallet(&req.wallet)?, caller, |wallet| {
+            wallet.wallet_lib_mut().create_unsigned_tx(
+                req.dest_addr,
+                req.amt,
+                input_filter,
+                change_addr_type,
+                privacy,
+            )
+        })?;
+
+        let mut resp = CreateUnsignedTxResponse::new();
+        resp.set_id(unsigned.id);
+        resp.set_psbt(serialize(&psbt));
+        resp.set_fee(unsigned.fee);
+        Ok(resp)
+    }
+
+    fn list_unsigned_txs_helper(
+        &self,
+        req: &ListUnsignedTxsRequest,
+    ) -> Result<ListUnsignedTxsResponse, Box<dyn Error>> {
+        let wallet = self.wallet(&req.wallet)?;
+        let wallet = wallet.lock().unwrap();
+
+        let mut unsigned_txs = Vec::new();
+        for unsigned in wallet.wallet_lib().get_unsigned_txs() {
+            let mut rpc_unsigned = RpcUnsignedTx::new();
+            rpc_unsigned.set_psbt(serialize(&wallet.wallet_lib().unsigned_psbt(unsigned.id)?));
+            rpc_unsigned.set_id(unsigned.id);
+            rpc_unsigned.set_dest_addr(unsigned.dest_addr);
+            rpc_unsigned.set_amt(unsigned.amt);
+            rpc_unsigned.set_fee(unsigned.fee);
+            unsigned_txs.push(rpc_unsigned);
+        }
+        let mut resp = ListUnsignedTxsResponse::new();
+        resp.set_unsigned_txs(RepeatedField::from_vec(unsigned_txs));
+        Ok(resp)
+    }
+
+    fn broadcast_signed_psbt_helper(
+        &self,
+        req: &BroadcastSignedPsbtRequest,
+        caller: String,
+    ) -> Result<BroadcastSignedPsbtResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        let psbt = deserialize(&req.psbt)?;
+        let tx = as_caller(self.wallet(&req.wallet)?, caller, |wallet| {
+            wallet.broadcast_signed_psbt(req.id, &psbt)
+        })?;
+
+        let mut resp = BroadcastSignedPsbtResponse::new();
+        resp.set_serialized_raw_tx(serialize(&tx));
+        Ok(resp)
+    }
+
+    fn cancel_unsigned_tx_helper(
+        &self,
+        req: &CancelUnsignedTxRequest,
+    ) -> Result<CancelUnsignedTxResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        self.wallet(&req.wallet)?
+            .lock()
+            .unwrap()
+            .wallet_lib_mut()
+            .cancel_unsigned_tx(req.id)?;
+        Ok(CancelUnsignedTxResponse::new())
+    }
+
+    fn encode_qr_helper(&self, req: &EncodeQrRequest) -> Result<EncodeQrResponse, Box<dyn Error>> {
+        let wallet = self.wallet(&req.wallet)?;
+        let wallet = wallet.lock().unwrap();
+        let payload = if req.has_unsigned_tx_id() {
+            let psbt = wallet.wallet_lib().unsigned_psbt(req.get_unsigned_tx_id())?;
+            QrPayload::Psbt(serialize(&psbt))
+        } else if req.has_psbt() {
+            QrPayload::Psbt(req.get_psbt().to_vec())
+        } else if req.has_descriptor_index() {
+            let index = req.get_descriptor_index();
+            let descriptor = wallet.wallet_lib().descriptors().into_iter().nth(index as usize);
+            QrPayload::Descriptor(descriptor.ok_or_else(|| {
+                rpc_error(ErrorCode::NOT_FOUND, &format!("wallet has no descriptor {}", index))
+            })?)
+        } else if req.has_address() {
+            QrPayload::Address(Address::from_str(req.get_address())?)
+        } else {
+            return Err(Box::new(rpc_error(ErrorCode::INVALID_ARGUMENT, "payload is not set")));
+        };
+        let max_fragment_len = if req.max_fragment_len == 0 {
+            DEFAULT_MAX_FRAGMENT_LEN
+        } else {
+            req.max_fragment_len as usize
+        };
+        let format = req.get_format().into();
+        let parts = qr::encode(&payload, format, max_fragment_len, req.extra_parts as usize)?;
+
+        let mut resp = EncodeQrResponse::new();
+        resp.set_parts(RepeatedField::from_vec(parts));
+        Ok(resp)
+    }
+
+    fn decode_qr_helper(&self, req: &DecodeQrRequest) -> Result<DecodeQrResponse, Box<dyn Error>> {
+        let network = self.wallet(&req.wallet)?.lock().unwrap().wallet_lib().network();
+
+        let mut resp = DecodeQrResponse::new();
+        match qr::decode(req.get_parts(), network)? {
+            QrPayload::Psbt(psbt) => resp.set_psbt(psbt),
+            QrPayload::Descriptor(descriptor) => resp.set_descriptor(descriptor),
+            QrPayload::Address(addr) => resp.set_address(addr.to_string()),
+        }
+        Ok(resp)
+    }
+
+    fn migrate_derivation_helper(
+        &self,
+        req: &MigrateDerivationRequest,
+        caller: String,
+    ) -> Result<MigrateDerivationResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        let wallet = self.wallet(&req.wallet)?;
+        let tx = as_caller(wallet, caller, |wallet| wallet.migrate_derivation())?;
+
+        let mut resp = MigrateDerivationResponse::new();
+        resp.set_serialized_raw_tx(serialize(&tx));
+        Ok(resp)
+    }
+
+    fn prove_reserves_helper(
+        &self,
+        req: &ProveReservesRequest,
+    ) -> Result<ProveReservesResponse, Box<dyn Error>> {
+        let wallet = self.wallet(&req.wallet)?;
+        let wallet = wallet.lock().unwrap();
+        let proof = wallet.wallet_lib().prove_reserves(&req.message)?;
+
+        let mut resp = ProveReservesResponse::new();
+        resp.set_proof(serialize(&proof));
+        resp.set_block_height(wallet.wallet_lib().get_last_seen_block_height_from_memory() as u64);
+        resp.set_amount(proof.output[0].value);
+        Ok(resp)
+    }
+
+    fn get_spending_policy_helper(
+        &self,
+        req: &GetSpendingPolicyRequest,
+    ) -> Result<GetSpendingPolicyResponse, Box<dyn Error>> {
+        let wallet = self.wallet(&req.wallet)?;
+        let wallet = wallet.lock().unwrap();
+
+        let mut resp = GetSpendingPolicyResponse::new();
+        resp.set_policy(wallet.wallet_lib().get_spending_policy().into());
+        resp.set_spent_in_window(wallet.wallet_lib().spent_in_window());
+        Ok(resp)
+    }
+
+    fn set_spending_policy_helper(
+        &self,
+        req: &SetSpendingPolicyRequest,
+    ) -> Result<SetSpendingPolicyResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        self.wallet(&req.wallet)?
+            .lock()
+            .unwrap()
+            .wallet_lib_mut()
+            .set_spending_policy(req.get_policy().into())?;
+        Ok(SetSpendingPolicyResponse::new())
+    }
+
+    fn list_pending_spends_helper(
+        &self,
+        req: &ListPendingSpendsRequest,
+    ) -> Result<ListPendingSpendsResponse, Box<dyn Error>> {
+        let wallet = self.wallet(&req.wallet)?;
+        let pending_spends = wallet.lock().unwrap().wallet_lib().get_pending_spends();
+
+        let mut resp = ListPendingSpendsResponse::new();
+        resp.set_pending_spends(pending_spends.into_iter().map(Into::into).collect());
+        Ok(resp)
+    }
+
+    fn approve_spend_helper(
+        &self,
+        req: &ApproveSpendRequest,
+        caller: String,
+    ) -> Result<ApproveSpendResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        let tx = as_caller(self.wallet(&req.wallet)?, caller, |wallet| {
+            wallet.approve_spend(req.id)
+        })?;
+
+        let mut resp = ApproveSpendResponse::new();
+        resp.set_serialized_raw_tx(serialize(&tx));
+        Ok(resp)
+    }
+
+    fn reject_spend_helper(
+        &self,
+        req: &RejectSpendRequest,
+    ) -> Result<RejectSpendResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        self.wallet(&req.wallet)?.lock().unwrap().wallet_lib_mut().reject_spend(req.id)?;
+        Ok(RejectSpendResponse::new())
+    }
+
+    fn get_audit_log_helper(
+        &self,
+        req: &GetAuditLogRequest,
+    ) -> Result<GetAuditLogResponse, Box<dyn Error>> {
+        let limit = match req.limit {
+            0 => DEFAULT_AUDIT_PAGE,
+            limit => (limit as usize).min(MAX_AUDIT_PAGE),
+        };
+        let wallet = self.wallet(&req.wallet)?;
+        let entries = wallet.lock().unwrap().wallet_lib().get_audit_log(req.start, limit);
+
+        let mut resp = GetAuditLogResponse::new();
+        resp.set_entries(entries.into_iter().map(Into::into).collect());
+        Ok(resp)
+    }
+
+    fn fund_channel_helper(
+        &self,
+        req: &FundChannelRequest,
+    ) -> Result<FundChannelResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        let witness_script = Script::from(req.witness_script.clone());
+        let (funding_id, funding) = self
+            .wallet(&req.wallet)?
+            .lock()
+            .unwrap()
+            .wallet_lib_mut()
+            .fund_psbt_for_channel(&witness_script, req.amt, req.fee_rate)?;
+
+        let mut resp = FundChannelResponse::new();
+        resp.set_funding_id(funding_id.into());
+        resp.set_psbt(serialize(&funding.psbt));
+        resp.set_output_index(funding.output_index);
+        resp.set_fee(funding.fee);
+        Ok(resp)
+    }
+
+    fn release_funding_helper(
+        &self,
+        req: &ReleaseFundingRequest,
+    ) -> Result<ReleaseFundingResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        self.wallet(&req.wallet)?
+            .lock()
+            .unwrap()
+            .wallet_lib_mut()
+            .release_funding(req.funding_id.into())?;
+        Ok(ReleaseFundingResponse::new())
+    }
+
+    fn publish_funding_helper(
+        &self,
+        req: &PublishFundingRequest,
+        caller: String,
+    ) -> Result<PublishFundingResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        let tx = as_caller(self.wallet(&req.wallet)?, caller, |wallet| {
+            wallet.publish_funding(req.funding_id.into())
+        })?;
+
+        let mut resp = PublishFundingResponse::new();
+        resp.set_serialized_raw_tx(serialize(&tx));
+        Ok(resp)
+    }
+
+    fn freeze_utxo_helper(
+        &self,
+        req: &FreezeUtxoRequest,
+    ) -> Result<FreezeUtxoResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        let op = out_point_from_rpc(req.get_out_point())?;
+        self.wallet(&req.wallet)?
+            .lock()
+            .unwrap()
+            .wallet_lib_mut()
+            .freeze_utxo(op)?;
+        Ok(FreezeUtxoResponse::new())
+    }
+
+    fn set_address_label_helper(
+        &self,
+        req: &SetAddressLabelRequest,
+    ) -> Result<SetAddressLabelResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        self.wallet(&req.wallet)?
+            .lock()
+            .unwrap()
+            .wallet_lib_mut()
+            .set_address_label(&req.address, &req.label)?;
+        Ok(SetAddressLabelResponse::new())
+    }
+
+    fn unfreeze_utxo_helper(
+        &self,
+        req: &UnfreezeUtxoRequest,
+    ) -> Result<UnfreezeUtxoResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        let op = out_point_from_rpc(req.get_out_point())?;
+        self.wallet(&req.wallet)?
+            .lock()
+            .unwrap()
+            .wallet_lib_mut()
+            .unfreeze_utxo(op)?;
+        Ok(UnfreezeUtxoResponse::new())
+    }
+
+    fn export_backup_helper(
+        &self,
+        req: &ExportBackupRequest,
+    ) -> Result<ExportBackupResponse, Box<dyn Error>> {
+        self.wallet(&req.wallet)?
+            .lock()
+            .unwrap()
+            .wallet_lib()
+            .export_backup(Path::new(&req.path), &req.passphrase)?;
+        Ok(ExportBackupResponse::new())
+    }
+
+    fn export_electrum_wallet_helper(
+        &self,
+        req: &ExportElectrumWalletRequest,
+    ) -> Result<ExportElectrumWalletResponse, Box<dyn Error>> {
+        self.wallet(&req.wallet)?
+            .lock()
+            .unwrap()
+            .wallet_lib()
+            .export_electrum_wallet(Path::new(&req.path))?;
+        Ok(ExportElectrumWalletResponse::new())
+    }
+
+    fn restore_backup_helper(
+        &self,
+        req: RestoreBackupRequest,
+    ) -> Result<RestoreBackupResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        let decrypt_cfg = DecryptConfig::new(
+            or_default(req.wallet_passphrase, DEFAULT_PASSPHRASE),
+            or_default(req.salt, DEFAULT_SALT),
+        );
+        self.wallet(&req.wallet)?
+            .lock()
+            .unwrap()
+            .wallet_lib_mut()
+            .import_backup(Path::new(&req.path), &req.passphrase, decrypt_cfg)?;
+        Ok(RestoreBackupResponse::new())
+    }
+
+    fn export_state_snapshot_helper(
+        &self,
+        req: &ExportStateSnapshotRequest,
+    ) -> Result<ExportStateSnapshotResponse, Box<dyn Error>> {
+        self.wallet(&req.wallet)?
+            .lock()
+            .unwrap()
+            .wallet_lib()
+            .export_state_snapshot(Path::new(&req.path))?;
+        Ok(ExportStateSnapshotResponse::new())
+    }
+
+    fn import_state_snapshot_helper(
+        &self,
+        req: &ImportStateSnapshotRequest,
+        caller: String,
+    ) -> Result<ImportStateSnapshotResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        let height = as_caller(self.wallet(&req.wallet)?, caller, |wallet| {
+            wallet.wallet_lib_mut().import_state_snapshot(Path::new(&req.path))?;
+            Ok(wallet.wallet_lib().get_last_seen_block_height_from_memory())
+        })?;
+
+        let mut resp = ImportStateSnapshotResponse::new();
+        resp.set_height(height as u64);
+        Ok(resp)
+    }
+
+    fn check_wallet_helper(
+        &self,
+        req: &CheckWalletRequest,
+        caller: String,
+    ) -> Result<CheckWalletResponse, Box<dyn Error>> {
+        let mut resp = CheckWalletResponse::new();
+        let issues = if req.repair {
+            self.check_writable()?;
+            as_caller(self.wallet(&req.wallet)?, caller, |wallet| {
+                let found = wallet.wallet_lib().verify_state().len();
+                let left = wallet.wallet_lib_mut().repair_state()?;
+                resp.set_repaired(found.saturating_sub(left.len()) as u32);
+                Ok(left)
+            })?
+        } else {
+            self.wallet(&req.wallet)?.lock().unwrap().wallet_lib().verify_state()
+        };
+
+        resp.set_issues(RepeatedField::from_vec(
+            issues.into_iter().map(|issue| issue.into()).collect(),
+        ));
+        Ok(resp)
+    }
+
+    fn create_wallet_helper(
+        &self,
+        req: CreateWalletRequest,
+    ) -> Result<CreateWalletResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        let entropy = if req.mnemonic_words == 0 {
+            DEFAULT_ENTROPY
+        } else {
+            MasterKeyEntropy::from_word_count(req.mnemonic_words as usize)
+                .ok_or("mnemonic should have 12, 18 or 24 words")?
+        };
+        let mut key_gen_cfg = KeyGenConfig::new(
+            entropy,
+            or_default(req.passphrase, DEFAULT_PASSPHRASE),
+            or_default(req.salt, DEFAULT_SALT),
+        );
+        for account_path in req.account_paths.iter() {
+            let scheme: DerivationScheme = account_path.path.parse()?;
+            key_gen_cfg.set_derivation_scheme(account_path.addr_type.into(), scheme);
+        }
+        let mode = if req.watch_only_keys.is_empty() {
+            WalletLibraryMode::Create(key_gen_cfg)
+        } else {
+            WalletLibraryMode::WatchOnly(serde_json::from_str(&req.watch_only_keys)?)
+        };
+        let mnemonic = self.open_wallet(&req.wallet, mode)?;
+
+        let mut resp = CreateWalletResponse::new();
+        resp.set_mnemonic(mnemonic.to_string());
+        Ok(resp)
+    }
+
+    fn validate_mnemonic_helper(&self, req: &ValidateMnemonicRequest) -> ValidateMnemonicResponse {
+        // a form may leave double or trailing spaces between the words
+        let words: Vec<_> = req.mnemonic.split_whitespace().collect();
+        let mut resp = ValidateMnemonicResponse::new();
+        resp.set_word_count(words.len() as u32);
+        if let Err(err) = Mnemonic::validate(&words.join(" ")) {
+            let problem = match err {
+                MnemonicError::InvalidLength(_) => MnemonicProblem::INVALID_LENGTH,
+                MnemonicError::UnknownWord(i) => {
+                    resp.set_word_index(i as u32);
+                    MnemonicProblem::UNKNOWN_WORD
+                }
+                MnemonicError::ChecksumNotMatch => MnemonicProblem::CHECKSUM_NOT_MATCH,
+            };
+            resp.set_problem(problem);
+            resp.set_message(err.to_string());
+        }
+        resp
+    }
+
+    fn open_wallet_helper(&self, req: OpenWalletRequest) -> Result<OpenWalletResponse, Box<dyn Error>> {
+        let decrypt_cfg = DecryptConfig::new(
+            or_default(req.passphrase, DEFAULT_PASSPHRASE),
+            or_default(req.salt, DEFAULT_SALT),
+        );
+        self.open_wallet(&req.wallet, WalletLibraryMode::Decrypt(decrypt_cfg))?;
+        Ok(OpenWalletResponse::new())
+    }
+
+    fn set_log_level_helper(
+        &self,
+        req: &SetLogLevelRequest,
+    ) -> Result<SetLogLevelResponse, Box<dyn Error>> {
+        let log_filter = self
+            .log_filter
+            .as_ref()
+            .ok_or("the logging of the daemon can't be changed")?;
+        let previous = if req.filter.is_empty() {
+            log_filter.current()
+        } else {
+            log_filter
+                .set(&req.filter)
+                .map_err(|e| rpc_error(ErrorCode::INVALID_ARGUMENT, &e.to_string()))?
+        };
+        let mut resp = SetLogLevelResponse::new();
+        resp.set_previous(previous);
+        Ok(resp)
+    }
+
+    fn lock_wallet_helper(
+        &self,
+        req: &LockWalletRequest,
+    ) -> Result<LockWalletResponse, Box<dyn Error>> {
+        let wallet = self.wallet(&req.wallet)?;
+        wallet.lock().unwrap().wallet_lib_mut().lock()?;
+        Ok(LockWalletResponse::new())
+    }
+
+    // a thread wipes the keys when the timeout passes, signing is refused from then on anyway
+    fn unlock_wallet_helper(
+        &self,
+        req: UnlockWalletRequest,
+    ) -> Result<UnlockWalletResponse, Box<dyn Error>> {
+        let decrypt_cfg = DecryptConfig::new(
+            or_default(req.passphrase, DEFAULT_PASSPHRASE),
+            or_default(req.salt, DEFAULT_SALT),
+        );
+        let timeout = Some(req.timeout).filter(|&timeout| timeout != 0);
+        let wallet = self.wallet(&req.wallet)?;
+        wallet.lock().unwrap().wallet_lib_mut().unlock(&decrypt_cfg, timeout)?;
+        if let Some(timeout) = timeout {
+            thread::spawn(move || {
+                thread::sleep(Duration::from_secs(timeout));
+                wallet.lock().unwrap().wallet_lib_mut().relock_expired();
+            });
+        }
+        Ok(UnlockWalletResponse::new())
+    }
+
+    // switching to another backend changes the wallet, reconnecting to its own one doesn't
+    fn reconnect_helper(&self, req: ReconnectRequest) -> Result<ReconnectResponse, Box<dyn Error>> {
+        let config = if req.address.is_empty() {
+            None
+        } else {
+            self.check_writable()?;
+            let non_empty = |s: String| Some(s).filter(|s| !s.is_empty());
+            Some(BackendConfig {
+                address: req.address.parse()?,
+                user: non_empty(req.user),
+                password: non_empty(req.password),
+            })
+        };
+        let wallet = self.wallet(&req.wallet)?;
+        let mut wallet = wallet.lock().unwrap();
+        wallet.reconnect(config)?;
+
+        let mut resp = ReconnectResponse::new();
+        resp.set_status(wallet.connection_status().into());
+        Ok(resp)
+    }
+
+    // the new backend is checked before the wallet leaves the old one, a switch refused
+    // there leaves the wallet as it was. The sync afterwards goes on from the stored height
+    fn switch_backend_helper(
+        &self,
+        req: SwitchBackendRequest,
+    ) -> Result<SwitchBackendResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        let switcher = self
+            .switcher
+            .as_ref()
+            .ok_or("the backends of this daemon can't be switched")?;
+        let kind = match req.backend {
+            RpcBackendKind::FULL_NODE => BackendKind::FullNode,
+            RpcBackendKind::ELECTRUM => BackendKind::Electrum,
+        };
+        let non_empty = |s: String| Some(s).filter(|s| !s.is_empty());
+        let config = BackendConfig {
+            address: req.address.parse()?,
+            user: non_empty(req.user),
+            password: non_empty(req.password),
+        };
+        let attach = switcher(kind, &config)?;
+
+        let name = if req.wallet.is_empty() { DEFAULT_WALLET_NAME } else { &req.wallet };
+        // held until the wallet is back, calls for it wait instead of failing
+        let mut wallets = self.wallets.lock().unwrap();
+        let wallet = match wallets.remove(name) {
+            Some(wallet) => wallet,
+            None => {
+                let message = format!("wallet {} is not open", name);
+                return Err(Box::new(rpc_error(ErrorCode::NOT_FOUND, &message)));
+            }
+        };
+        self.operations.lock().unwrap().cancel_on(&wallet);
+        let wallet = match drain(wallet) {
+            Ok(wallet) => wallet,
+            Err(wallet) => {
+                wallets.insert(name.to_owned(), wallet);
+                return Err(From::from("calls of the wallet are still running, try again"));
+            }
+        };
+        let wallet = Arc::new(Mutex::new(attach(wallet.into_wallet_lib())));
+        wallets.insert(name.to_owned(), Arc::clone(&wallet));
+        drop(wallets);
+        info!("wallet {} moved to the {:?} backend at {}", name, kind, config.address);
+
+        if let Err(e) = sync_shared(&wallet, &CancelToken::new(), &mut |_| ()) {
+            warn!("sync of wallet {} after the switch failed: {}", name, e);
+        }
+        let mut resp = SwitchBackendResponse::new();
+        resp.set_status(wallet.lock().unwrap().connection_status().into());
+        Ok(resp)
+    }
+
+    fn get_connection_status_helper(
+        &self,
+        req: &GetConnectionStatusRequest,
+    ) -> Result<GetConnectionStatusResponse, Box<dyn Error>> {
+        let status = self.wallet(&req.wallet)?.lock().unwrap().connection_status();
+        let mut resp = GetConnectionStatusResponse::new();
+        resp.set_status(status.into());
+        Ok(resp)
+    }
+
+    fn get_transactions_helper(
+        &self,
+        req: &GetTransactionsRequest,
+    ) -> Result<GetTransactionsResponse, Box<dyn Error>> {
+        let wallet = self.wallet(&req.wallet)?;
+        let mut wallet = wallet.lock().unwrap();
+        let mut history = wallet.wallet_lib().get_tx_history();
+        // oldest first, the unconfirmed ones last
+        history.sort_by_key(|record| match record.status {
+            TxStatus::Confirmed(height) => height,
+            _ => usize::max_value(),
+        });
+
+        let mut transactions = Vec::new();
+        for record in history {
+            let value = wallet.wallet_lib().tx_value(&record);
+            let height = match record.status {
+                TxStatus::Confirmed(height) => Some(height),
+                _ => None,
+            };
+            let mut rpc_record: RpcTxRecord = record.into();
+            rpc_record.set_value(value);
+            if let Some(fiat_value) = self.fiat_value(value) {
+                rpc_record.set_fiat_value(fiat_value);
+            }
+            if let (Some(fiat), Some(height)) = (self.fiat.as_ref(), height) {
+                let currency = &fiat.currency;
+                match wallet.wallet_lib_mut().confirmation_price(&*fiat.source, currency, height) {
+                    Ok(Some(price)) => {
+                        let fiat_value = rpc_fiat_value(currency, value, price);
+                        rpc_record.set_fiat_value_at_confirmation(fiat_value);
+                    }
+                    Ok(None) => (),
+                    Err(e) => warn!("no price in {} at height {}: {}", currency, height, e),
+                }
+            }
+            transactions.push(rpc_record);
+        }
+
+        let mut resp = GetTransactionsResponse::new();
+        resp.set_transactions(RepeatedField::from_vec(transactions));
+        resp.set_fee_stats(wallet.wallet_lib().fee_stats().into());
+        resp.set_tip_height(wallet.wallet_lib().get_last_seen_block_height_from_memory() as u64);
+        Ok(resp)
+    }
+
+    fn get_account_stats_helper(
+        &self,
+        req: &GetAccountStatsRequest,
+    ) -> Result<GetAccountStatsResponse, Box<dyn Error>> {
+        let wallet = self.wallet(&req.wallet)?;
+        let wallet = wallet.lock().unwrap();
+        let address_stats = wallet.wallet_lib().address_stats();
+
+        let mut accounts = Vec::new();
+        for addr_type in &[
+            AccountAddressType::P2PKH,
+            AccountAddressType::P2SHWH,
+            AccountAddressType::P2WKH,
+        ] {
+            let stats = wallet.wallet_lib().account_stats(addr_type.clone());
+            let mut rpc_stats = RpcAccountStats::new();
+            rpc_stats.set_address_type(addr_type.clone().into());
+            rpc_stats.set_received(stats.received);
+            rpc_stats.set_sent(stats.sent);
+            let addresses = address_stats
+                .iter()
+                .filter(|(_, account, _)| account == addr_type)
+                .map(|(addr, _, stats)| {
+                    let mut rpc_addr_stats = RpcAddressStats::new();
+                    rpc_addr_stats.set_address(addr.clone());
+                    rpc_addr_stats.set_received(stats.received);
+                    rpc_addr_stats.set_sent(stats.sent);
+                    rpc_addr_stats
+                })
+                .collect();
+            rpc_stats.set_addresses(RepeatedField::from_vec(addresses));
+            accounts.push(rpc_stats);
+        }
+
+        let mut resp = GetAccountStatsResponse::new();
+        resp.set_accounts(RepeatedField::from_vec(accounts));
+        Ok(resp)
+    }
+
+    fn get_utxo_list_helper(
+        &self,
+        req: &GetUtxoListRequest,
+    ) -> Result<GetUtxoListResponse, Box<dyn Error>> {
+        let mut resp = GetUtxoListResponse::new();
+        let wallet = self.wallet(&req.wallet)?;
+        let wallet = wallet.lock().unwrap();
+        let utxo_list = wallet.wallet_lib().get_utxo_list();
+        let utxos = utxo_list.into_iter().map(|utxo| {
+            let reused = wallet.wallet_lib().is_reused(&utxo.pk_script);
+            let mut rpc_utxo: RpcUtxo = utxo.into();
+            rpc_utxo.set_reused(reused);
+            rpc_utxo
+        });
+        resp.set_utxos(RepeatedField::from_vec(utxos.collect()));
+        let tip_height = wallet.wallet_lib().get_last_seen_block_height_from_memory();
+        let timelocked_utxos = wallet.wallet_lib().get_timelocked_utxo_list();
+        resp.set_timelocked_utxos(RepeatedField::from_vec(
+            timelocked_utxos
+                .into_iter()
+                .map(|utxo| timelocked_utxo_to_rpc(utxo, tip_height))
+                .collect(),
+        ));
+        let script_utxos = wallet.wallet_lib().get_script_utxo_list();
+        resp.set_script_utxos(RepeatedField::from_vec(
+            script_utxos.into_iter().map(script_utxo_to_rpc).collect(),
+        ));
+        let vault_utxos = wallet.wallet_lib().get_vault_utxo_list();
+        resp.set_vault_utxos(RepeatedField::from_vec(
+            vault_utxos.into_iter().map(|utxo| vault_utxo_to_rpc(utxo, tip_height)).collect(),
+        ));
+        Ok(resp)
+    }
+
+    fn wallet_balance_helper(
+        &self,
+        req: &WalletBalanceRequest,
+    ) -> Result<WalletBalanceResponse, Box<dyn Error>> {
+        // a sync holds the wallet, not the state
+        let state = self.state(&req.wallet)?;
+        let mut resp = WalletBalanceResponse::new();
+        let balance = state.wallet_balance();
+        resp.set_total_balance(balance);
+        resp.set_watched_balance(state.watched_balance());
+        if let Some(fiat_balance) = self.fiat_value(balance as i64) {
+            resp.set_fiat_balance(fiat_balance);
+        }
+
+        let frozen = state.get_frozen_list();
+        let frozen_balance: u64 = state
+            .get_utxo_list()
+            .iter()
+            .filter(|utxo| frozen.contains(&utxo.out_point))
+            .map(|utxo| utxo.value)
+            .sum();
+        let timelocked_balance: u64 =
+            state.get_timelocked_utxo_list().iter().map(|utxo| utxo.value).sum();
+        let categories = [
+            (BalanceCategory::SPENDABLE, balance - frozen_balance),
+            (BalanceCategory::FROZEN, frozen_balance),
+            (BalanceCategory::TIMELOCKED, timelocked_balance),
+            (BalanceCategory::WATCHED, resp.watched_balance),
+        ];
+        for &(category, value) in &categories {
+            let mut entry = CategoryBalance::new();
+            entry.set_category(category);
+            entry.set_value(value);
+            resp.mut_balances().push(entry);
+        }
+        Ok(resp)
+    }
+
+    fn watch_script_helper(
+        &self,
+        req: &WatchScriptRequest,
+    ) -> Result<WatchScriptResponse, Box<dyn Error>> {
+        self.check_writable()?;
+        let script_pubkey = Script::from(req.script_pubkey.clone());
+        self.wallet(&req.wallet)?
+            .lock()
+            .unwrap()
+            .wallet_lib_mut()
+            .watch_script(script_pubkey, req.label.clone());
+        Ok(WatchScriptResponse::new())
+    }
+
+    fn list_watched_utxos_helper(
+        &self,
+        req: &ListWatchedUtxosRequest,
+    ) -> Result<ListWatchedUtxosResponse, Box<dyn Error>> {
+        let utxo_list = self
+            .wallet(&req.wallet)?
+            .lock()
+            .unwrap()
+            .wallet_lib()
+            .get_watched_utxo_list();
+        let mut resp = ListWatchedUtxosResponse::new();
+        resp.set_utxos(RepeatedField::from_vec(
+            utxo_list.into_iter().map(|utxo| utxo.into()).collect(),
+        ));
+        Ok(resp)
+    }
+}
+
+impl Wallet for WalletImpl {
+    fn new_address(
+        &self,
+        m: grpc::RequestOptions,
+        req: NewAddressRequest,
+    ) -> grpc::SingleResponse<NewAddressResponse> {
+        let _span = match self.authorize(&m, "NewAddress") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("new {:?} address was requested", req.addr_type);
+        grpc_error(self.new_address_helper(&req, caller(&m)))
+    }
+
+    fn new_change_address(
+        &self,
+        m: grpc::RequestOptions,
+        req: NewChangeAddressRequest,
+    ) -> grpc::SingleResponse<NewChangeAddressResponse> {
+        let _span = match self.authorize(&m, "NewChangeAddress") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("new {:?} change address was requested", req.addr_type);
+        grpc_error(self.new_change_address(&req, caller(&m)))
+    }
+
+    fn get_unused_address(
+        &self,
+        m: grpc::RequestOptions,
+        req: GetUnusedAddressRequest,
+    ) -> grpc::SingleResponse<GetUnusedAddressResponse> {
+        let _span = match self.authorize(&m, "GetUnusedAddress") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("unused {:?} address was requested", req.addr_type);
+        grpc_error(self.get_unused_address_helper(&req))
+    }
+
+    fn list_addresses(
+        &self,
+        m: grpc::RequestOptions,
+        req: ListAddressesRequest,
+    ) -> grpc::SingleResponse<ListAddressesResponse> {
+        let _span = match self.authorize(&m, "ListAddresses") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("address list was requested");
+        grpc_error(self.list_addresses_helper(&req))
+    }
+
+    fn get_utxo_list(
+        &self,
+        m: grpc::RequestOptions,
+        req: GetUtxoListRequest,
+    ) -> grpc::SingleResponse<GetUtxoListResponse> {
+        let _span = match self.authorize(&m, "GetUtxoList") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("utxo list was requested");
+        grpc_error(self.get_utxo_list_helper(&req))
+    }
+
+    fn watch_script(
+        &self,
+        m: grpc::RequestOptions,
+        req: WatchScriptRequest,
+    ) -> grpc::SingleResponse<WatchScriptResponse> {
+        let _span = match self.authorize(&m, "WatchScript") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("watching a script labeled {:?} was requested", req.label);
+        grpc_error(self.watch_script_helper(&req))
+    }
+
+    fn list_watched_utxos(
+        &self,
+        m: grpc::RequestOptions,
+        req: ListWatchedUtxosRequest,
+    ) -> grpc::SingleResponse<ListWatchedUtxosResponse> {
+        let _span = match self.authorize(&m, "ListWatchedUtxos") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("watched utxo list was requested");
+        grpc_error(self.list_watched_utxos_helper(&req))
+    }
+
+    fn wallet_balance(
+        &self,
+        m: grpc::RequestOptions,
+        req: WalletBalanceRequest,
+    ) -> grpc::SingleResponse<WalletBalanceResponse> {
+        let _span = match self.authorize(&m, "WalletBalance") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("wallet balance was requested");
+        grpc_error(self.wallet_balance_helper(&req))
+    }
+
+    fn sync_with_tip(
+        &self,
+        m: grpc::RequestOptions,
+        req: SyncWithTipRequest,
+    ) -> grpc::SingleResponse<SyncWithTipResponse> {
+        let _span = match self.authorize(&m, "SyncWithTip") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("manual(not ZMQ) sync with tip was requested");
+
+        let resp = self.wallet(&req.wallet).and_then(|wallet| {
+            let cancel = CancelToken::new();
+            sync_shared(&wallet, &cancel, &mut |_| ()).map(|()| SyncWithTipResponse::new())
+        });
+        grpc_error(resp)
+    }
+
+    fn sync_with_tip_stream(
+        &self,
+        m: grpc::RequestOptions,
+        req: SyncWithTipRequest,
+    ) -> grpc::StreamingResponse<RpcSyncProgress> {
+        let _span = match self.authorize(&m, "SyncWithTipStream") {
+            Ok(span) => span,
+            Err(e) => return grpc::StreamingResponse::err(e),
+        };
+        info!("sync with tip with progress was requested");
+
+        let wallet = match self.wallet(&req.wallet) {
+            Ok(wallet) => wallet,
+            Err(e) => return grpc::StreamingResponse::err(to_grpc(&*e)),
+        };
+        // the sync takes long on the first run
+        self.sync_stream(wallet, |_| Ok(()))
+    }
+
+    fn rescan_stream(
+        &self,
+        m: grpc::RequestOptions,
+        req: RescanRequest,
+    ) -> grpc::StreamingResponse<RpcSyncProgress> {
+        let _span = match self.authorize(&m, "RescanStream") {
+            Ok(span) => span,
+            Err(e) => return grpc::StreamingResponse::err(e),
+        };
+        info!("rescan from block {} was requested", req.from_height);
+
+        let wallet = match self.wallet(&req.wallet) {
+            Ok(wallet) => wallet,
+            Err(e) => return grpc::StreamingResponse::err(to_grpc(&*e)),
+        };
+        let from_height = req.from_height as usize;
+        self.sync_stream(wallet, move |wallet| wallet.rescan_from(from_height))
+    }
+
+    fn cancel_operation(
+        &self,
+        m: grpc::RequestOptions,
+        req: CancelOperationRequest,
+    ) -> grpc::SingleResponse<CancelOperationResponse> {
+        let _span = match self.authorize(&m, "CancelOperation") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("operation {} is cancelled", req.operation_id);
+        grpc_error(self.cancel_operation_helper(&req))
+    }
+
+    fn make_tx(
+        &self,
+        m: grpc::RequestOptions,
+        req: MakeTxRequest,
+    ) -> grpc::SingleResponse<MakeTxResponse> {
+        let _span = match self.authorize(&m, "MakeTx") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("make_tx was requested");
+        grpc_error(self.make_tx_helper(req, caller(&m)))
+    }
+
+    fn send_coins(
+        &self,
+        m: grpc::RequestOptions,
+        req: SendCoinsRequest,
+    ) -> grpc::SingleResponse<SendCoinsResponse> {
+        let _span = match self.authorize(&m, "SendCoins") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("send_coins was requested");
+        grpc_error(self.send_coins_helper(req, caller(&m)))
+    }
+
+    fn prepare_transaction(
+        &self,
+        m: grpc::RequestOptions,
+        req: PrepareTransactionRequest,
+    ) -> grpc::SingleResponse<PrepareTransactionResponse> {
+        let _span = match self.authorize(&m, "PrepareTransaction") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("prepare_transaction was requested");
+        grpc_error(self.prepare_transaction_helper(req))
+    }
+
+    fn commit_transaction(
+        &self,
+        m: grpc::RequestOptions,
+        req: CommitTransactionRequest,
+    ) -> grpc::SingleResponse<CommitTransactionResponse> {
+        let _span = match self.authorize(&m, "CommitTransaction") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("commit_transaction was requested");
+        grpc_error(self.commit_transaction_helper(&req, caller(&m)))
+    }
+
+    fn abandon_transaction(
+        &self,
+        m: grpc::RequestOptions,
+        req: AbandonTransactionRequest,
+    ) -> grpc::SingleResponse<AbandonTransactionResponse> {
+        let _span = match self.authorize(&m, "AbandonTransaction") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("abandon_transaction of {} was requested", req.txid);
+        grpc_error(self.abandon_transaction_helper(&req))
+    }
+
+    fn accelerate_transaction(
+        &self,
+        m: grpc::RequestOptions,
+        req: AccelerateTransactionRequest,
+    ) -> grpc::SingleResponse<AccelerateTransactionResponse> {
+        let _span = match self.authorize(&m, "AccelerateTransaction") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("accelerate_transaction of {} was requested", req.txid);
+        grpc_error(self.accelerate_transaction_helper(&req, caller(&m)))
+    }
+
+    fn cancel_transaction(
+        &self,
+        m: grpc::RequestOptions,
+        req: CancelTransactionRequest,
+    ) -> grpc::SingleResponse<CancelTransactionResponse> {
+        let _span = match self.authorize(&m, "CancelTransaction") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("cancel_transaction of {} was requested", req.txid);
+        grpc_error(self.cancel_transaction_helper(&req, caller(&m)))
+    }
+
+    fn estimate_tx_size(
+        &self,
+        m: grpc::RequestOptions,
+        req: EstimateTxSizeRequest,
+    ) -> grpc::SingleResponse<EstimateTxSizeResponse> {
+        let _span = match self.authorize(&m, "EstimateTxSize") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("size estimate of a transaction was requested");
+        grpc_error(self.estimate_tx_size_helper(&req))
+    }
+
+    fn get_payment_code(
+        &self,
+        m: grpc::RequestOptions,
+        req: GetPaymentCodeRequest,
+    ) -> grpc::SingleResponse<GetPaymentCodeResponse> {
+        let _span = match self.authorize(&m, "GetPaymentCode") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("get_payment_code was requested");
+        grpc_error(self.get_payment_code_helper(&req))
+    }
+
+    fn notify_payment_code(
+        &self,
+        m: grpc::RequestOptions,
+        req: NotifyPaymentCodeRequest,
+    ) -> grpc::SingleResponse<NotifyPaymentCodeResponse> {
+        let _span = match self.authorize(&m, "NotifyPaymentCode") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("notify_payment_code of {} was requested", req.payment_code);
+        grpc_error(self.notify_payment_code_helper(&req, caller(&m)))
+    }
+
+    fn payment_code_address(
+        &self,
+        m: grpc::RequestOptions,
+        req: PaymentCodeAddressRequest,
+    ) -> grpc::SingleResponse<PaymentCodeAddressResponse> {
+        let _span = match self.authorize(&m, "PaymentCodeAddress") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("payment_code_address of {} was requested", req.payment_code);
+        grpc_error(self.payment_code_address_helper(&req))
+    }
+
+    fn sweep_payment_codes(
+        &self,
+        m: grpc::RequestOptions,
+        req: SweepPaymentCodesRequest,
+    ) -> grpc::SingleResponse<SweepPaymentCodesResponse> {
+        let _span = match self.authorize(&m, "SweepPaymentCodes") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("sweep_payment_codes was requested");
+        grpc_error(self.sweep_payment_codes_helper(&req, caller(&m)))
+    }
+
+    fn get_silent_payment_address(
+        &self,
+        m: grpc::RequestOptions,
+        req: GetSilentPaymentAddressRequest,
+    ) -> grpc::SingleResponse<GetSilentPaymentAddressResponse> {
+        let _span = match self.authorize(&m, "GetSilentPaymentAddress") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("get_silent_payment_address was requested");
+        grpc_error(self.get_silent_payment_address_helper(&req))
+    }
+
+    fn list_silent_payment_utxos(
+        &self,
+        m: grpc::RequestOptions,
+        req: ListSilentPaymentUtxosRequest,
+    ) -> grpc::SingleResponse<ListSilentPaymentUtxosResponse> {
+        let _span = match self.authorize(&m, "ListSilentPaymentUtxos") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("list_silent_payment_utxos was requested");
+        grpc_error(self.list_silent_payment_utxos_helper(&req))
+    }
+
+    fn sweep_silent_payments(
+        &self,
+        m: grpc::RequestOptions,
+        req: SweepSilentPaymentsRequest,
+    ) -> grpc::SingleResponse<SweepSilentPaymentsResponse> {
+        let _span = match self.authorize(&m, "SweepSilentPayments") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("sweep_silent_payments was requested");
+        grpc_error(self.sweep_silent_payments_helper(&req, caller(&m)))
+    }
+
+    fn prove_reserves(
+        &self,
+        m: grpc::RequestOptions,
+        req: ProveReservesRequest,
+    ) -> grpc::SingleResponse<ProveReservesResponse> {
+        let _span = match self.authorize(&m, "ProveReserves") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("prove_reserves was requested");
+        grpc_error(self.prove_reserves_helper(&req))
+    }
+
+    fn get_spending_policy(
+        &self,
+        m: grpc::RequestOptions,
+        req: GetSpendingPolicyRequest,
+    ) -> grpc::SingleResponse<GetSpendingPolicyResponse> {
+        let _span = match self.authorize(&m, "GetSpendingPolicy") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("get_spending_policy was requested");
+        grpc_error(self.get_spending_policy_helper(&req))
+    }
+
+    fn set_spending_policy(
+        &self,
+        m: grpc::RequestOptions,
+        req: SetSpendingPolicyRequest,
+    ) -> grpc::SingleResponse<SetSpendingPolicyResponse> {
+        let _span = match self.authorize(&m, "SetSpendingPolicy") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("set_spending_policy was requested");
+        grpc_error(self.set_spending_policy_helper(&req))
+    }
+
+    fn list_pending_spends(
+        &self,
+        m: grpc::RequestOptions,
+        req: ListPendingSpendsRequest,
+    ) -> grpc::SingleResponse<ListPendingSpendsResponse> {
+        let _span = match self.authorize(&m, "ListPendingSpends") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("list_pending_spends was requested");
+        grpc_error(self.list_pending_spends_helper(&req))
+    }
+
+    fn approve_spend(
+        &self,
+        m: grpc::RequestOptions,
+        req: ApproveSpendRequest,
+    ) -> grpc::SingleResponse<ApproveSpendResponse> {
+        let _span = match self.authorize(&m, "ApproveSpend") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("approve_spend of {} was requested", req.id);
+        grpc_error(self.approve_spend_helper(&req, caller(&m)))
+    }
+
+    fn reject_spend(
+        &self,
+        m: grpc::RequestOptions,
+        req: RejectSpendRequest,
+    ) -> grpc::SingleResponse<RejectSpendResponse> {
+        let _span = match self.authorize(&m, "RejectSpend") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("reject_spend of {} was requested", req.id);
+        grpc_error(self.reject_spend_helper(&req))
+    }
+
+    fn get_audit_log(
+        &self,
+        m: grpc::RequestOptions,
+        req: GetAuditLogRequest,
+    ) -> grpc::SingleResponse<GetAuditLogResponse> {
+        let _span = match self.authorize(&m, "GetAuditLog") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("get_audit_log from {} was requested", req.start);
+        grpc_error(self.get_audit_log_helper(&req))
+    }
+
+    fn fund_channel(
+        &self,
+        m: grpc::RequestOptions,
+        req: FundChannelRequest,
+    ) -> grpc::SingleResponse<FundChannelResponse> {
+        let _span = match self.authorize(&m, "FundChannel") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("fund_channel of {} was requested", req.amt);
+        grpc_error(self.fund_channel_helper(&req))
+    }
+
+    fn release_funding(
+        &self,
+        m: grpc::RequestOptions,
+        req: ReleaseFundingRequest,
+    ) -> grpc::SingleResponse<ReleaseFundingResponse> {
+        let _span = match self.authorize(&m, "ReleaseFunding") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("release_funding of {} was requested", req.funding_id);
+        grpc_error(self.release_funding_helper(&req))
+    }
+
+    fn publish_funding(
+        &self,
+        m: grpc::RequestOptions,
+        req: PublishFundingRequest,
+    ) -> grpc::SingleResponse<PublishFundingResponse> {
+        let _span = match self.authorize(&m, "PublishFunding") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("publish_funding of {} was requested", req.funding_id);
+        grpc_error(self.publish_funding_helper(&req, caller(&m)))
+    }
+
+    fn unlock_coins(
+        &self,
+        m: grpc::RequestOptions,
+        req: UnlockCoinsRequest,
+    ) -> grpc::SingleResponse<UnlockCoinsResponse> {
+        let _span = match self.authorize(&m, "UnlockCoins") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("unlock_coins was requested");
+        let resp = self
+            .check_writable()
+            .and_then(|()| self.wallet(&req.wallet))
+            .map(|wallet| {
+                wallet
+                    .lock()
+                    .unwrap()
+                    .wallet_lib_mut()
+                    .unlock_coins(LockId::from(req.lock_id));
+                UnlockCoinsResponse::new()
+            });
+        grpc_error(resp)
+    }
+
+    fn freeze_utxo(
+        &self,
+        m: grpc::RequestOptions,
+        req: FreezeUtxoRequest,
+    ) -> grpc::SingleResponse<FreezeUtxoResponse> {
+        let _span = match self.authorize(&m, "FreezeUtxo") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("freeze_utxo was requested");
+        grpc_error(self.freeze_utxo_helper(&req))
+    }
+
+    fn set_address_label(
+        &self,
+        m: grpc::RequestOptions,
+        req: SetAddressLabelRequest,
+    ) -> grpc::SingleResponse<SetAddressLabelResponse> {
+        let _span = match self.authorize(&m, "SetAddressLabel") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("set_address_label was requested");
+        grpc_error(self.set_address_label_helper(&req))
+    }
+
+    fn unfreeze_utxo(
+        &self,
+        m: grpc::RequestOptions,
+        req: UnfreezeUtxoRequest,
+    ) -> grpc::SingleResponse<UnfreezeUtxoResponse> {
+        let _span = match self.authorize(&m, "UnfreezeUtxo") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("unfreeze_utxo was requested");
+        grpc_error(self.unfreeze_utxo_helper(&req))
+    }
+
+    fn export_backup(
+        &self,
+        m: grpc::RequestOptions,
+        req: ExportBackupRequest,
+    ) -> grpc::SingleResponse<ExportBackupResponse> {
+        let _span = match self.authorize(&m, "ExportBackup") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("backup export to {} was requested", req.path);
+        grpc_error(self.export_backup_helper(&req))
+    }
+
+    fn restore_backup(
+        &self,
+        m: grpc::RequestOptions,
+        req: RestoreBackupRequest,
+    ) -> grpc::SingleResponse<RestoreBackupResponse> {
+        let _span = match self.authorize(&m, "RestoreBackup") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("backup restore from {} was requested", req.path);
+        grpc_error(self.restore_backup_helper(req))
+    }
+
+    fn export_state_snapshot(
+        &self,
+        m: grpc::RequestOptions,
+        req: ExportStateSnapshotRequest,
+    ) -> grpc::SingleResponse<ExportStateSnapshotResponse> {
+        let _span = match self.authorize(&m, "ExportStateSnapshot") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("state snapshot export to {} was requested", req.path);
+        grpc_error(self.export_state_snapshot_helper(&req))
+    }
+
+    fn import_state_snapshot(
+        &self,
+        m: grpc::RequestOptions,
+        req: ImportStateSnapshotRequest,
+    ) -> grpc::SingleResponse<ImportStateSnapshotResponse> {
+        let _span = match self.authorize(&m, "ImportStateSnapshot") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("state snapshot import from {} was requested", req.path);
+        grpc_error(self.import_state_snapshot_helper(&req, caller(&m)))
+    }
+
+    fn check_wallet(
+        &self,
+        m: grpc::RequestOptions,
+        req: CheckWalletRequest,
+    ) -> grpc::SingleResponse<CheckWalletResponse> {
+        let _span = match self.authorize(&m, "CheckWallet") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("wallet check was requested, repair={}", req.repair);
+        grpc_error(self.check_wallet_helper(&req, caller(&m)))
+    }
+
+    fn create_wallet(
+        &self,
+        m: grpc::RequestOptions,
+        req: CreateWalletRequest,
+    ) -> grpc::SingleResponse<CreateWalletResponse> {
+        let _span = match self.authorize(&m, "CreateWallet") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("creation of wallet {} was requested", req.wallet);
+        grpc_error(self.create_wallet_helper(req))
+    }
+
+    fn validate_mnemonic(
+        &self,
+        m: grpc::RequestOptions,
+        req: ValidateMnemonicRequest,
+    ) -> grpc::SingleResponse<ValidateMnemonicResponse> {
+        let _span = match self.authorize(&m, "ValidateMnemonic") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("validation of a mnemonic was requested");
+        grpc::SingleResponse::completed(self.validate_mnemonic_helper(&req))
+    }
+
+    fn open_wallet(
+        &self,
+        m: grpc::RequestOptions,
+        req: OpenWalletRequest,
+    ) -> grpc::SingleResponse<OpenWalletResponse> {
+        let _span = match self.authorize(&m, "OpenWallet") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("opening of wallet {} was requested", req.wallet);
+        grpc_error(self.open_wallet_helper(req))
+    }
+
+    fn list_wallets(
+        &self,
+        m: grpc::RequestOptions,
+        _req: ListWalletsRequest,
+    ) -> grpc::SingleResponse<ListWalletsResponse> {
+        let _span = match self.authorize(&m, "ListWallets") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("wallet list was requested");
+        let mut names: Vec<String> = self.wallets.lock().unwrap().keys().cloned().collect();
+        names.sort();
+
+        let mut resp = ListWalletsResponse::new();
+        resp.set_wallets(RepeatedField::from_vec(names));
+        grpc::SingleResponse::completed(resp)
+    }
+
+    fn set_log_level(
+        &self,
+        m: grpc::RequestOptions,
+        req: SetLogLevelRequest,
+    ) -> grpc::SingleResponse<SetLogLevelResponse> {
+        let _span = match self.authorize(&m, "SetLogLevel") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("log filter {:?} was requested", req.filter);
+        grpc_error(self.set_log_level_helper(&req))
+    }
+
+    fn lock_wallet(
+        &self,
+        m: grpc::RequestOptions,
+        req: LockWalletRequest,
+    ) -> grpc::SingleResponse<LockWalletResponse> {
+        let _span = match self.authorize(&m, "LockWallet") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("wallet {} lock was requested", req.wallet);
+        grpc_error(self.lock_wallet_helper(&req))
+    }
+
+    fn unlock_wallet(
+        &self,
+        m: grpc::RequestOptions,
+        req: UnlockWalletRequest,
+    ) -> grpc::SingleResponse<UnlockWalletResponse> {
+        let _span = match self.authorize(&m, "UnlockWallet") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("wallet {} unlock was requested, timeout {}s", req.wallet, req.timeout);
+        grpc_error(self.unlock_wallet_helper(req))
+    }
+
+    fn subscribe_events(
+        &self,
+        m: grpc::RequestOptions,
+        req: SubscribeEventsRequest,
+    ) -> grpc::StreamingResponse<RpcWalletEvent> {
+        let _span = match self.authorize(&m, "SubscribeEvents") {
+            Ok(span) => span,
+            Err(e) => return grpc::StreamingResponse::err(e),
+        };
+        info!("subscribe_events was requested");
+
+        let events = match self.wallet(&req.wallet) {
+            Ok(wallet) => wallet.lock().unwrap().wallet_lib_mut().subscribe(),
+            Err(e) => return grpc::StreamingResponse::err(to_grpc(&*e)),
+        };
+        // the wallet sends events from the thread which processes transactions,
+        // pass them over to the event loop of the server
+        let (sender, receiver) = stream_mpsc::unbounded();
+        thread::spawn(move || {
+            for event in events {
+                if sender.unbounded_send(event.into()).is_err() {
+                    break;
+                }
+            }
+        });
+        grpc::StreamingResponse::no_metadata(
+            receiver.map_err(|()| grpc::Error::Other("event stream failed")),
+        )
+    }
+
+    fn shutdown(
+        &self,
+        m: grpc::RequestOptions,
+        _req: ShutdownRequest,
+    ) -> grpc::SingleResponse<ShutdownResponse> {
+        let _span = match self.authorize(&m, "Shutdown") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("shutdown was requested");
+
+        self.shutdown.lock().unwrap().send(ShutdownSignal).unwrap();
+
+        let resp = ShutdownResponse::new();
+        grpc::SingleResponse::completed(resp)
+    }
+
+    fn get_info(
+        &self,
+        m: grpc::RequestOptions,
+        _req: GetInfoRequest,
+    ) -> grpc::SingleResponse<GetInfoResponse> {
+        let _span = match self.authorize(&m, "GetInfo") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("info was requested");
+
+        let mut resp = GetInfoResponse::new();
+        resp.set_api_version(API_VERSION);
+        resp.set_version(env!("CARGO_PKG_VERSION").to_owned());
+        resp.set_read_only(self.read_only);
+        let stored = self.wallet(DEFAULT_WALLET_NAME).map(|wallet| {
+            let wallet = wallet.lock().unwrap();
+            (wallet.wallet_lib().network(), wallet.wallet_lib().backend())
+        });
+        if let Ok((network, backend)) = stored {
+            resp.set_network(match network {
+                Network::Bitcoin => RpcNetwork::BITCOIN,
+                Network::Testnet => RpcNetwork::TESTNET,
+                Network::Regtest => RpcNetwork::REGTEST,
+            });
+            if let Some(backend) = backend {
+                resp.set_backend(rpc_backend_kind(backend));
+            }
+        }
         grpc::SingleResponse::completed(resp)
     }
+
+    fn reconnect(
+        &self,
+        m: grpc::RequestOptions,
+        req: ReconnectRequest,
+    ) -> grpc::SingleResponse<ReconnectResponse> {
+        let _span = match self.authorize(&m, "Reconnect") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("reconnect of wallet {} to {:?} was requested", req.wallet, req.address);
+        grpc_error(self.reconnect_helper(req))
+    }
+
+    fn switch_backend(
+        &self,
+        m: grpc::RequestOptions,
+        req: SwitchBackendRequest,
+    ) -> grpc::SingleResponse<SwitchBackendResponse> {
+        let _span = match self.authorize(&m, "SwitchBackend") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("switch of wallet {} to {:?} was requested", req.wallet, req.address);
+        grpc_error(self.switch_backend_helper(req))
+    }
+
+    fn get_connection_status(
+        &self,
+        m: grpc::RequestOptions,
+        req: GetConnectionStatusRequest,
+    ) -> grpc::SingleResponse<GetConnectionStatusResponse> {
+        let _span = match self.authorize(&m, "GetConnectionStatus") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("connection status was requested");
+        grpc_error(self.get_connection_status_helper(&req))
+    }
+
+    fn get_transactions(
+        &self,
+        m: grpc::RequestOptions,
+        req: GetTransactionsRequest,
+    ) -> grpc::SingleResponse<GetTransactionsResponse> {
+        let _span = match self.authorize(&m, "GetTransactions") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("transaction history was requested");
+        grpc_error(self.get_transactions_helper(&req))
+    }
+
+    fn get_account_stats(
+        &self,
+        m: grpc::RequestOptions,
+        req: GetAccountStatsRequest,
+    ) -> grpc::SingleResponse<GetAccountStatsResponse> {
+        let _span = match self.authorize(&m, "GetAccountStats") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("account stats were requested");
+        grpc_error(self.get_account_stats_helper(&req))
+    }
+
+    fn export_history(
+        &self,
+        m: grpc::RequestOptions,
+        req: ExportHistoryRequest,
+    ) -> grpc::StreamingResponse<ExportHistoryChunk> {
+        let _span = match self.authorize(&m, "ExportHistory") {
+            Ok(span) => span,
+            Err(e) => return grpc::StreamingResponse::err(e),
+        };
+        info!("history export was requested");
+
+        let mut data = Vec::new();
+        let exported = self.wallet(&req.wallet).and_then(|wallet| {
+            let wallet = wallet.lock().unwrap();
+            wallet.wallet_lib().export_history(req.get_format().into(), &mut data)
+        });
+        if let Err(e) = exported {
+            return grpc::StreamingResponse::err(to_grpc(&*e));
+        }
+        let (sender, receiver) = stream_mpsc::unbounded();
+        for chunk in data.chunks(EXPORT_CHUNK_SIZE) {
+            let mut rpc_chunk = ExportHistoryChunk::new();
+            rpc_chunk.set_data(chunk.to_vec());
+            // the receiver is still here
+            sender.unbounded_send(rpc_chunk).unwrap();
+        }
+        // the stream ends along with the sender
+        drop(sender);
+        grpc::StreamingResponse::no_metadata(
+            receiver.map_err(|()| grpc::Error::Other("export stream failed")),
+        )
+    }
+
+    fn sweep_address(
+        &self,
+        m: grpc::RequestOptions,
+        req: SweepAddressRequest,
+    ) -> grpc::SingleResponse<SweepAddressResponse> {
+        let _span = match self.authorize(&m, "SweepAddress") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("sweep of {} to {} was requested", req.address, req.dest_addr);
+        grpc_error(self.sweep_address_helper(&req, caller(&m)))
+    }
+
+    fn new_timelocked_address(
+        &self,
+        m: grpc::RequestOptions,
+        req: NewTimelockedAddressRequest,
+    ) -> grpc::SingleResponse<NewTimelockedAddressResponse> {
+        let _span = match self.authorize(&m, "NewTimelockedAddress") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("timelocked address was requested, blocks {} height {}", req.blocks, req.height);
+        grpc_error(self.new_timelocked_address_helper(&req, caller(&m)))
+    }
+
+    fn set_vault(
+        &self,
+        m: grpc::RequestOptions,
+        req: SetVaultRequest,
+    ) -> grpc::SingleResponse<SetVaultResponse> {
+        let _span = match self.authorize(&m, "SetVault") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("vault with a delay of {} blocks was set", req.delay);
+        grpc_error(self.set_vault_helper(&req, caller(&m)))
+    }
+
+    fn new_vault_address(
+        &self,
+        m: grpc::RequestOptions,
+        req: NewVaultAddressRequest,
+    ) -> grpc::SingleResponse<NewVaultAddressResponse> {
+        let _span = match self.authorize(&m, "NewVaultAddress") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("vault address was requested");
+        grpc_error(self.new_vault_address_helper(&req, caller(&m)))
+    }
+
+    fn unvault(
+        &self,
+        m: grpc::RequestOptions,
+        req: UnvaultRequest,
+    ) -> grpc::SingleResponse<UnvaultResponse> {
+        let _span = match self.authorize(&m, "Unvault") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("unvault to {} was requested", req.dest_addr);
+        grpc_error(self.unvault_helper(&req, caller(&m)))
+    }
+
+    fn get_script_account_xpub(
+        &self,
+        m: grpc::RequestOptions,
+        req: GetScriptAccountXpubRequest,
+    ) -> grpc::SingleResponse<GetScriptAccountXpubResponse> {
+        let _span = match self.authorize(&m, "GetScriptAccountXpub") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("script account xpub was requested");
+        grpc_error(self.get_script_account_xpub_helper(&req))
+    }
+
+    fn register_policy(
+        &self,
+        m: grpc::RequestOptions,
+        req: RegisterPolicyRequest,
+    ) -> grpc::SingleResponse<RegisterPolicyResponse> {
+        let _span = match self.authorize(&m, "RegisterPolicy") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("policy {} was registered as {}", req.policy, req.name);
+        grpc_error(self.register_policy_helper(&req, caller(&m)))
+    }
+
+    fn new_script_address(
+        &self,
+        m: grpc::RequestOptions,
+        req: NewScriptAddressRequest,
+    ) -> grpc::SingleResponse<NewScriptAddressResponse> {
+        let _span = match self.authorize(&m, "NewScriptAddress") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("address of script account {} was requested", req.name);
+        grpc_error(self.new_script_address_helper(&req, caller(&m)))
+    }
+
+    fn spend_script_coins(
+        &self,
+        m: grpc::RequestOptions,
+        req: SpendScriptCoinsRequest,
+    ) -> grpc::SingleResponse<SpendScriptCoinsResponse> {
+        let _span = match self.authorize(&m, "SpendScriptCoins") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("spend of script account {} to {} was requested", req.name, req.dest_addr);
+        grpc_error(self.spend_script_coins_helper(&req, caller(&m)))
+    }
+
+    fn finalize_script_psbt(
+        &self,
+        m: grpc::RequestOptions,
+        req: FinalizeScriptPsbtRequest,
+    ) -> grpc::SingleResponse<FinalizeScriptPsbtResponse> {
+        let _span = match self.authorize(&m, "FinalizeScriptPsbt") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("script account psbt was finalized");
+        grpc_error(self.finalize_script_psbt_helper(&req, caller(&m)))
+    }
+
+    fn get_descriptors(
+        &self,
+        m: grpc::RequestOptions,
+        req: GetDescriptorsRequest,
+    ) -> grpc::SingleResponse<GetDescriptorsResponse> {
+        let _span = match self.authorize(&m, "GetDescriptors") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("descriptors were requested");
+        grpc_error(self.get_descriptors_helper(&req))
+    }
+
+    fn migrate_derivation(
+        &self,
+        m: grpc::RequestOptions,
+        req: MigrateDerivationRequest,
+    ) -> grpc::SingleResponse<MigrateDerivationResponse> {
+        let _span = match self.authorize(&m, "MigrateDerivation") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("migration to the standard derivation was requested");
+        grpc_error(self.migrate_derivation_helper(&req, caller(&m)))
+    }
+
+    fn export_electrum_wallet(
+        &self,
+        m: grpc::RequestOptions,
+        req: ExportElectrumWalletRequest,
+    ) -> grpc::SingleResponse<ExportElectrumWalletResponse> {
+        let _span = match self.authorize(&m, "ExportElectrumWallet") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("electrum wallet export to {} was requested", req.path);
+        grpc_error(self.export_electrum_wallet_helper(&req))
+    }
+
+    fn get_import_descriptors(
+        &self,
+        m: grpc::RequestOptions,
+        req: GetImportDescriptorsRequest,
+    ) -> grpc::SingleResponse<GetImportDescriptorsResponse> {
+        let _span = match self.authorize(&m, "GetImportDescriptors") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("descriptors to import into bitcoin core were requested");
+        grpc_error(self.get_import_descriptors_helper(&req))
+    }
+
+    fn create_unsigned_tx(
+        &self,
+        m: grpc::RequestOptions,
+        req: CreateUnsignedTxRequest,
+    ) -> grpc::SingleResponse<CreateUnsignedTxResponse> {
+        let _span = match self.authorize(&m, "CreateUnsignedTx") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("unsigned payment of {} to {} was requested", req.amt, req.dest_addr);
+        grpc_error(self.create_unsigned_tx_helper(req, caller(&m)))
+    }
+
+    fn list_unsigned_txs(
+        &self,
+        m: grpc::RequestOptions,
+        req: ListUnsignedTxsRequest,
+    ) -> grpc::SingleResponse<ListUnsignedTxsResponse> {
+        let _span = match self.authorize(&m, "ListUnsignedTxs") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("unsigned transactions were requested");
+        grpc_error(self.list_unsigned_txs_helper(&req))
+    }
+
+    fn broadcast_signed_psbt(
+        &self,
+        m: grpc::RequestOptions,
+        req: BroadcastSignedPsbtRequest,
+    ) -> grpc::SingleResponse<BroadcastSignedPsbtResponse> {
+        let _span = match self.authorize(&m, "BroadcastSignedPsbt") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("broadcast of the signed transaction {} was requested", req.id);
+        grpc_error(self.broadcast_signed_psbt_helper(&req, caller(&m)))
+    }
+
+    fn cancel_unsigned_tx(
+        &self,
+        m: grpc::RequestOptions,
+        req: CancelUnsignedTxRequest,
+    ) -> grpc::SingleResponse<CancelUnsignedTxResponse> {
+        let _span = match self.authorize(&m, "CancelUnsignedTx") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("cancel_unsigned_tx of {} was requested", req.id);
+        grpc_error(self.cancel_unsigned_tx_helper(&req))
+    }
+
+    fn encode_qr(
+        &self,
+        m: grpc::RequestOptions,
+        req: EncodeQrRequest,
+    ) -> grpc::SingleResponse<EncodeQrResponse> {
+        let _span = match self.authorize(&m, "EncodeQr") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("QR code parts were requested");
+        grpc_error(self.encode_qr_helper(&req))
+    }
+
+    fn decode_qr(
+        &self,
+        m: grpc::RequestOptions,
+        req: DecodeQrRequest,
+    ) -> grpc::SingleResponse<DecodeQrResponse> {
+        let _span = match self.authorize(&m, "DecodeQr") {
+            Ok(span) => span,
+            Err(e) => return grpc::SingleResponse::err(e),
+        };
+        info!("decoding of {} QR code parts was requested", req.parts.len());
+        grpc_error(self.decode_qr_helper(&req))
+    }
 }
 
-pub fn launch_server_new(wallet: Box<dyn WalletInterface + Send>, wallet_rpc_port: u16) {
+/// serve `wallet` as the default one with the options of `config`,
+/// more wallets can be created and opened with its `factory`,
+/// events of all of them are posted to `webhook` and published on the `zmq_endpoint`
+/// if those are configured, balances and transactions are valued in the currency of `fiat`.
+/// The processes of the `supervisor` are restarted when they exit,
+/// the wallets are told through their events and reconnected.
+/// With an `acl` every call needs a token which grants it, see `acl::Acl`.
+/// The open wallets follow the chain in the background as `auto_sync` says,
+/// with a `switcher` they can be moved to another backend while the daemon runs
+pub fn launch_server_new(
+    wallet: Box<dyn WalletInterface + Send>,
+    wallet_rpc_port: u16,
+    config: ServerConfig,
+) {
     let wallet = Arc::new(Mutex::new(wallet));
+    let publisher = config
+        .zmq_endpoint
+        .map(|endpoint| Publisher::bind(&endpoint).expect("zmq publisher"));
+    let read_only = config.read_only;
 
     let (shutdown_sender, shutdown_receiver) = mpsc::channel();
 
     let mut server: grpc::ServerBuilder<tls_api_native_tls::TlsAcceptor> =
         grpc::ServerBuilder::new();
     server.http.set_port(wallet_rpc_port);
-    let wallet_impl = WalletImpl::new(wallet, Mutex::new(shutdown_sender));
+    let wallet_impl = WalletImpl::new(
+        wallet,
+        Mutex::new(shutdown_sender),
+        WalletImplConfig {
+            factory: config.factory,
+            webhook: config.webhook.map(Webhook::start),
+            publisher,
+            fiat: config.fiat,
+            read_only,
+            acl: config.acl,
+            log_filter: config.log_filter,
+            switcher: config.switcher,
+        },
+    );
+    let events = config.auto_sync.map(|auto_sync| {
+        let wallets = Arc::clone(&wallet_impl.wallets);
+        autosync::start(auto_sync, move |event| chain_event(&wallets, event))
+            .expect("background sync")
+    });
+    let supervisor = config.supervisor.map(|mut supervisor| {
+        let wallets = Arc::clone(&wallet_impl.wallets);
+        let events = events.clone();
+        supervisor.start(move |event| backend_changed(&wallets, events.as_ref(), event));
+        supervisor
+    });
     server.add_service(WalletServer::new_service_def(wallet_impl));
+    server.add_service(ServerReflectionServer::new_service_def(ReflectionImpl));
     server.http.set_cpu_pool_threads(1);
     server
         .http
@@ -297,13 +3644,136 @@ pub fn launch_server_new(wallet: Box<dyn WalletInterface + Send>, wallet_rpc_por
     let _server = server.build().expect("server");
 
     info!(
-        "wallet server started on port {} {}",
-        wallet_rpc_port, "without tls"
+        "wallet server started on port {} {}{}",
+        wallet_rpc_port,
+        "without tls",
+        if read_only { ", read-only" } else { "" }
     );
 
     // wait for shutdown signal from grpc client
     shutdown_receiver.recv().unwrap();
+    if let Some(events) = events {
+        events.send(ChainEvent::Shutdown);
+    }
 
     // give some time to server gracefully shutdown
     thread::sleep(Duration::from_millis(SHUTDOWN_TIMEOUT_IN_MS));
+    // the managed processes are killed along with it
+    drop(supervisor);
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::network::constants::Network;
+    use wallet::{
+        default::WalletWithTrustedFullNode,
+        interface::Wallet as WalletInterface,
+        memory::MemoryChain,
+        walletlibrary::{WalletConfig, WalletLibraryMode, KeyGenConfig},
+    };
+
+    use std::{
+        error::Error,
+        sync::{mpsc, Arc, Mutex},
+        thread,
+        time::Duration,
+    };
+
+    use super::{WalletImpl, WalletImplConfig, SharedWallet, WalletFactory, DEFAULT_WALLET_NAME};
+    use crate::walletrpc::{
+        ErrorCode, ErrorDetails, NewAddressRequest, SendCoinsRequest, WalletBalanceRequest,
+    };
+
+    fn code<T>(result: Result<T, Box<dyn Error>>) -> ErrorCode {
+        match result {
+            Ok(_) => panic!("the call succeeded"),
+            Err(e) => e.downcast_ref::<ErrorDetails>().unwrap().code,
+        }
+    }
+
+    fn new_wallet() -> SharedWallet {
+        let (wallet, _) = WalletWithTrustedFullNode::new(
+            WalletConfig::in_memory(),
+            MemoryChain::new(Network::Regtest),
+            WalletLibraryMode::Create(KeyGenConfig::debug()),
+        )
+        .unwrap();
+        let wallet: Box<dyn WalletInterface + Send> = Box::new(wallet);
+        Arc::new(Mutex::new(wallet))
+    }
+
+    fn wallet_impl(read_only: bool) -> WalletImpl {
+        let (shutdown, _) = mpsc::channel();
+        // each wallet of the debug seed, in memory
+        let factory: WalletFactory = Box::new(|_, mode| {
+            let (wallet, mnemonic) = WalletWithTrustedFullNode::new(
+                WalletConfig::in_memory(),
+                MemoryChain::new(Network::Regtest),
+                mode,
+            )?;
+            let wallet: Box<dyn WalletInterface + Send> = Box::new(wallet);
+            Ok((wallet, mnemonic))
+        });
+        let config = WalletImplConfig {
+            factory: Some(factory),
+            read_only,
+            ..Default::default()
+        };
+        WalletImpl::new(new_wallet(), Mutex::new(shutdown), config)
+    }
+
+    #[test]
+    fn read_only() {
+        let read_only = wallet_impl(true);
+        let spend = read_only.send_coins_helper(SendCoinsRequest::new(), String::new());
+        assert_eq!(code(spend), ErrorCode::READ_ONLY);
+        let addr = read_only.new_address_helper(&NewAddressRequest::new(), String::new());
+        assert_eq!(code(addr), ErrorCode::READ_ONLY);
+
+        let writable = wallet_impl(false);
+        writable.new_address_helper(&NewAddressRequest::new(), String::new()).unwrap();
+    }
+
+    #[test]
+    fn named_wallets() {
+        let wallet_impl = wallet_impl(false);
+        let new_address = |name: &str| {
+            let mut req = NewAddressRequest::new();
+            req.set_wallet(name.to_owned());
+            wallet_impl.new_address_helper(&req, String::new()).map(|resp| resp.address)
+        };
+        let create = || WalletLibraryMode::Create(KeyGenConfig::debug());
+        wallet_impl.open_wallet("alice", create()).unwrap();
+        wallet_impl.open_wallet("bob", create()).unwrap();
+
+        // of the same seed, each wallet hands out its own first address
+        let alice = new_address("alice").unwrap();
+        assert_eq!(new_address("bob").unwrap(), alice);
+        assert_ne!(new_address("alice").unwrap(), alice);
+        assert_eq!(new_address(DEFAULT_WALLET_NAME).unwrap(), alice);
+        let (alice, bob) = (wallet_impl.wallet("alice"), wallet_impl.wallet("bob"));
+        assert!(!Arc::ptr_eq(&alice.unwrap(), &bob.unwrap()));
+
+        assert_eq!(code(new_address("carol")), ErrorCode::NOT_FOUND);
+        assert!(wallet_impl.open_wallet("bob", create()).is_err());
+    }
+
+    #[test]
+    fn balance_while_the_wallet_is_held() {
+        let wallet_impl = Arc::new(wallet_impl(false));
+        // e.g. by a sync
+        let wallet = wallet_impl.wallet(DEFAULT_WALLET_NAME).unwrap();
+        let _held = wallet.lock().unwrap();
+
+        let (sender, receiver) = mpsc::channel();
+        let reader = Arc::clone(&wallet_impl);
+        thread::spawn(move || {
+            let resp = reader.wallet_balance_helper(&WalletBalanceRequest::new());
+            sender.send(resp.map(|resp| resp.total_balance).map_err(|e| e.to_string())).unwrap();
+        });
+        let balance = receiver
+            .recv_timeout(Duration::from_secs(10))
+            .expect("the balance waits for the wallet");
+        assert_eq!(balance, Ok(0));
+    }
 }
@@ -26,19 +26,165 @@ pub trait Wallet {
 
     fn new_change_address(&self, o: ::grpc::RequestOptions, p: super::walletrpc::NewChangeAddressRequest) -> ::grpc::SingleResponse<super::walletrpc::NewChangeAddressResponse>;
 
+    fn get_unused_address(&self, o: ::grpc::RequestOptions, p: super::walletrpc::GetUnusedAddressRequest) -> ::grpc::SingleResponse<super::walletrpc::GetUnusedAddressResponse>;
+
+    fn list_addresses(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ListAddressesRequest) -> ::grpc::SingleResponse<super::walletrpc::ListAddressesResponse>;
+
     fn get_utxo_list(&self, o: ::grpc::RequestOptions, p: super::walletrpc::GetUtxoListRequest) -> ::grpc::SingleResponse<super::walletrpc::GetUtxoListResponse>;
 
+    fn watch_script(&self, o: ::grpc::RequestOptions, p: super::walletrpc::WatchScriptRequest) -> ::grpc::SingleResponse<super::walletrpc::WatchScriptResponse>;
+
+    fn list_watched_utxos(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ListWatchedUtxosRequest) -> ::grpc::SingleResponse<super::walletrpc::ListWatchedUtxosResponse>;
+
     fn wallet_balance(&self, o: ::grpc::RequestOptions, p: super::walletrpc::WalletBalanceRequest) -> ::grpc::SingleResponse<super::walletrpc::WalletBalanceResponse>;
 
     fn sync_with_tip(&self, o: ::grpc::RequestOptions, p: super::walletrpc::SyncWithTipRequest) -> ::grpc::SingleResponse<super::walletrpc::SyncWithTipResponse>;
 
+    fn sync_with_tip_stream(&self, o: ::grpc::RequestOptions, p: super::walletrpc::SyncWithTipRequest) -> ::grpc::StreamingResponse<super::walletrpc::SyncProgress>;
+
+    fn rescan_stream(&self, o: ::grpc::RequestOptions, p: super::walletrpc::RescanRequest) -> ::grpc::StreamingResponse<super::walletrpc::SyncProgress>;
+
+    fn cancel_operation(&self, o: ::grpc::RequestOptions, p: super::walletrpc::CancelOperationRequest) -> ::grpc::SingleResponse<super::walletrpc::CancelOperationResponse>;
+
     fn make_tx(&self, o: ::grpc::RequestOptions, p: super::walletrpc::MakeTxRequest) -> ::grpc::SingleResponse<super::walletrpc::MakeTxResponse>;
 
     fn send_coins(&self, o: ::grpc::RequestOptions, p: super::walletrpc::SendCoinsRequest) -> ::grpc::SingleResponse<super::walletrpc::SendCoinsResponse>;
 
+    fn prepare_transaction(&self, o: ::grpc::RequestOptions, p: super::walletrpc::PrepareTransactionRequest) -> ::grpc::SingleResponse<super::walletrpc::PrepareTransactionResponse>;
+
+    fn commit_transaction(&self, o: ::grpc::RequestOptions, p: super::walletrpc::CommitTransactionRequest) -> ::grpc::SingleResponse<super::walletrpc::CommitTransactionResponse>;
+
+    fn abandon_transaction(&self, o: ::grpc::RequestOptions, p: super::walletrpc::AbandonTransactionRequest) -> ::grpc::SingleResponse<super::walletrpc::AbandonTransactionResponse>;
+
+    fn accelerate_transaction(&self, o: ::grpc::RequestOptions, p: super::walletrpc::AccelerateTransactionRequest) -> ::grpc::SingleResponse<super::walletrpc::AccelerateTransactionResponse>;
+
+    fn cancel_transaction(&self, o: ::grpc::RequestOptions, p: super::walletrpc::CancelTransactionRequest) -> ::grpc::SingleResponse<super::walletrpc::CancelTransactionResponse>;
+
+    fn estimate_tx_size(&self, o: ::grpc::RequestOptions, p: super::walletrpc::EstimateTxSizeRequest) -> ::grpc::SingleResponse<super::walletrpc::EstimateTxSizeResponse>;
+
+    fn get_payment_code(&self, o: ::grpc::RequestOptions, p: super::walletrpc::GetPaymentCodeRequest) -> ::grpc::SingleResponse<super::walletrpc::GetPaymentCodeResponse>;
+
+    fn notify_payment_code(&self, o: ::grpc::RequestOptions, p: super::walletrpc::NotifyPaymentCodeRequest) -> ::grpc::SingleResponse<super::walletrpc::NotifyPaymentCodeResponse>;
+
+    fn payment_code_address(&self, o: ::grpc::RequestOptions, p: super::walletrpc::PaymentCodeAddressRequest) -> ::grpc::SingleResponse<super::walletrpc::PaymentCodeAddressResponse>;
+
+    fn sweep_payment_codes(&self, o: ::grpc::RequestOptions, p: super::walletrpc::SweepPaymentCodesRequest) -> ::grpc::SingleResponse<super::walletrpc::SweepPaymentCodesResponse>;
+
+    fn get_silent_payment_address(&self, o: ::grpc::RequestOptions, p: super::walletrpc::GetSilentPaymentAddressRequest) -> ::grpc::SingleResponse<super::walletrpc::GetSilentPaymentAddressResponse>;
+
+    fn list_silent_payment_utxos(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ListSilentPaymentUtxosRequest) -> ::grpc::SingleResponse<super::walletrpc::ListSilentPaymentUtxosResponse>;
+
+    fn sweep_silent_payments(&self, o: ::grpc::RequestOptions, p: super::walletrpc::SweepSilentPaymentsRequest) -> ::grpc::SingleResponse<super::walletrpc::SweepSilentPaymentsResponse>;
+
+    fn prove_reserves(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ProveReservesRequest) -> ::grpc::SingleResponse<super::walletrpc::ProveReservesResponse>;
+
+    fn get_spending_policy(&self, o: ::grpc::RequestOptions, p: super::walletrpc::GetSpendingPolicyRequest) -> ::grpc::SingleResponse<super::walletrpc::GetSpendingPolicyResponse>;
+
+    fn set_spending_policy(&self, o: ::grpc::RequestOptions, p: super::walletrpc::SetSpendingPolicyRequest) -> ::grpc::SingleResponse<super::walletrpc::SetSpendingPolicyResponse>;
+
+    fn list_pending_spends(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ListPendingSpendsRequest) -> ::grpc::SingleResponse<super::walletrpc::ListPendingSpendsResponse>;
+
+    fn approve_spend(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ApproveSpendRequest) -> ::grpc::SingleResponse<super::walletrpc::ApproveSpendResponse>;
+
+    fn reject_spend(&self, o: ::grpc::RequestOptions, p: super::walletrpc::RejectSpendRequest) -> ::grpc::SingleResponse<super::walletrpc::RejectSpendResponse>;
+
+    fn get_audit_log(&self, o: ::grpc::RequestOptions, p: super::walletrpc::GetAuditLogRequest) -> ::grpc::SingleResponse<super::walletrpc::GetAuditLogResponse>;
+
+    fn fund_channel(&self, o: ::grpc::RequestOptions, p: super::walletrpc::FundChannelRequest) -> ::grpc::SingleResponse<super::walletrpc::FundChannelResponse>;
+
+    fn release_funding(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ReleaseFundingRequest) -> ::grpc::SingleResponse<super::walletrpc::ReleaseFundingResponse>;
+
+    fn publish_funding(&self, o: ::grpc::RequestOptions, p: super::walletrpc::PublishFundingRequest) -> ::grpc::SingleResponse<super::walletrpc::PublishFundingResponse>;
+
     fn unlock_coins(&self, o: ::grpc::RequestOptions, p: super::walletrpc::UnlockCoinsRequest) -> ::grpc::SingleResponse<super::walletrpc::UnlockCoinsResponse>;
 
+    fn freeze_utxo(&self, o: ::grpc::RequestOptions, p: super::walletrpc::FreezeUtxoRequest) -> ::grpc::SingleResponse<super::walletrpc::FreezeUtxoResponse>;
+
+    fn unfreeze_utxo(&self, o: ::grpc::RequestOptions, p: super::walletrpc::UnfreezeUtxoRequest) -> ::grpc::SingleResponse<super::walletrpc::UnfreezeUtxoResponse>;
+
+    fn set_address_label(&self, o: ::grpc::RequestOptions, p: super::walletrpc::SetAddressLabelRequest) -> ::grpc::SingleResponse<super::walletrpc::SetAddressLabelResponse>;
+
+    fn export_backup(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ExportBackupRequest) -> ::grpc::SingleResponse<super::walletrpc::ExportBackupResponse>;
+
+    fn restore_backup(&self, o: ::grpc::RequestOptions, p: super::walletrpc::RestoreBackupRequest) -> ::grpc::SingleResponse<super::walletrpc::RestoreBackupResponse>;
+
+    fn export_state_snapshot(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ExportStateSnapshotRequest) -> ::grpc::SingleResponse<super::walletrpc::ExportStateSnapshotResponse>;
+
+    fn import_state_snapshot(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ImportStateSnapshotRequest) -> ::grpc::SingleResponse<super::walletrpc::ImportStateSnapshotResponse>;
+
+    fn check_wallet(&self, o: ::grpc::RequestOptions, p: super::walletrpc::CheckWalletRequest) -> ::grpc::SingleResponse<super::walletrpc::CheckWalletResponse>;
+
+    fn create_wallet(&self, o: ::grpc::RequestOptions, p: super::walletrpc::CreateWalletRequest) -> ::grpc::SingleResponse<super::walletrpc::CreateWalletResponse>;
+
+    fn validate_mnemonic(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ValidateMnemonicRequest) -> ::grpc::SingleResponse<super::walletrpc::ValidateMnemonicResponse>;
+
+    fn open_wallet(&self, o: ::grpc::RequestOptions, p: super::walletrpc::OpenWalletRequest) -> ::grpc::SingleResponse<super::walletrpc::OpenWalletResponse>;
+
+    fn list_wallets(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ListWalletsRequest) -> ::grpc::SingleResponse<super::walletrpc::ListWalletsResponse>;
+
+    fn lock_wallet(&self, o: ::grpc::RequestOptions, p: super::walletrpc::LockWalletRequest) -> ::grpc::SingleResponse<super::walletrpc::LockWalletResponse>;
+
+    fn unlock_wallet(&self, o: ::grpc::RequestOptions, p: super::walletrpc::UnlockWalletRequest) -> ::grpc::SingleResponse<super::walletrpc::UnlockWalletResponse>;
+
+    fn subscribe_events(&self, o: ::grpc::RequestOptions, p: super::walletrpc::SubscribeEventsRequest) -> ::grpc::StreamingResponse<super::walletrpc::WalletEvent>;
+
     fn shutdown(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ShutdownRequest) -> ::grpc::SingleResponse<super::walletrpc::ShutdownResponse>;
+
+    fn get_info(&self, o: ::grpc::RequestOptions, p: super::walletrpc::GetInfoRequest) -> ::grpc::SingleResponse<super::walletrpc::GetInfoResponse>;
+
+    fn set_log_level(&self, o: ::grpc::RequestOptions, p: super::walletrpc::SetLogLevelRequest) -> ::grpc::SingleResponse<super::walletrpc::SetLogLevelResponse>;
+
+    fn reconnect(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ReconnectRequest) -> ::grpc::SingleResponse<super::walletrpc::ReconnectResponse>;
+
+    fn switch_backend(&self, o: ::grpc::RequestOptions, p: super::walletrpc::SwitchBackendRequest) -> ::grpc::SingleResponse<super::walletrpc::SwitchBackendResponse>;
+
+    fn get_connection_status(&self, o: ::grpc::RequestOptions, p: super::walletrpc::GetConnectionStatusRequest) -> ::grpc::SingleResponse<super::walletrpc::GetConnectionStatusResponse>;
+
+    fn get_transactions(&self, o: ::grpc::RequestOptions, p: super::walletrpc::GetTransactionsRequest) -> ::grpc::SingleResponse<super::walletrpc::GetTransactionsResponse>;
+
+    fn get_account_stats(&self, o: ::grpc::RequestOptions, p: super::walletrpc::GetAccountStatsRequest) -> ::grpc::SingleResponse<super::walletrpc::GetAccountStatsResponse>;
+
+    fn export_history(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ExportHistoryRequest) -> ::grpc::StreamingResponse<super::walletrpc::ExportHistoryChunk>;
+
+    fn sweep_address(&self, o: ::grpc::RequestOptions, p: super::walletrpc::SweepAddressRequest) -> ::grpc::SingleResponse<super::walletrpc::SweepAddressResponse>;
+
+    fn new_timelocked_address(&self, o: ::grpc::RequestOptions, p: super::walletrpc::NewTimelockedAddressRequest) -> ::grpc::SingleResponse<super::walletrpc::NewTimelockedAddressResponse>;
+
+    fn set_vault(&self, o: ::grpc::RequestOptions, p: super::walletrpc::SetVaultRequest) -> ::grpc::SingleResponse<super::walletrpc::SetVaultResponse>;
+
+    fn new_vault_address(&self, o: ::grpc::RequestOptions, p: super::walletrpc::NewVaultAddressRequest) -> ::grpc::SingleResponse<super::walletrpc::NewVaultAddressResponse>;
+
+    fn unvault(&self, o: ::grpc::RequestOptions, p: super::walletrpc::UnvaultRequest) -> ::grpc::SingleResponse<super::walletrpc::UnvaultResponse>;
+
+    fn get_script_account_xpub(&self, o: ::grpc::RequestOptions, p: super::walletrpc::GetScriptAccountXpubRequest) -> ::grpc::SingleResponse<super::walletrpc::GetScriptAccountXpubResponse>;
+
+    fn register_policy(&self, o: ::grpc::RequestOptions, p: super::walletrpc::RegisterPolicyRequest) -> ::grpc::SingleResponse<super::walletrpc::RegisterPolicyResponse>;
+
+    fn new_script_address(&self, o: ::grpc::RequestOptions, p: super::walletrpc::NewScriptAddressRequest) -> ::grpc::SingleResponse<super::walletrpc::NewScriptAddressResponse>;
+
+    fn spend_script_coins(&self, o: ::grpc::RequestOptions, p: super::walletrpc::SpendScriptCoinsRequest) -> ::grpc::SingleResponse<super::walletrpc::SpendScriptCoinsResponse>;
+
+    fn finalize_script_psbt(&self, o: ::grpc::RequestOptions, p: super::walletrpc::FinalizeScriptPsbtRequest) -> ::grpc::SingleResponse<super::walletrpc::FinalizeScriptPsbtResponse>;
+
+    fn get_descriptors(&self, o: ::grpc::RequestOptions, p: super::walletrpc::GetDescriptorsRequest) -> ::grpc::SingleResponse<super::walletrpc::GetDescriptorsResponse>;
+
+    fn get_import_descriptors(&self, o: ::grpc::RequestOptions, p: super::walletrpc::GetImportDescriptorsRequest) -> ::grpc::SingleResponse<super::walletrpc::GetImportDescriptorsResponse>;
+
+    fn migrate_derivation(&self, o: ::grpc::RequestOptions, p: super::walletrpc::MigrateDerivationRequest) -> ::grpc::SingleResponse<super::walletrpc::MigrateDerivationResponse>;
+
+    fn export_electrum_wallet(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ExportElectrumWalletRequest) -> ::grpc::SingleResponse<super::walletrpc::ExportElectrumWalletResponse>;
+
+    fn create_unsigned_tx(&self, o: ::grpc::RequestOptions, p: super::walletrpc::CreateUnsignedTxRequest) -> ::grpc::SingleResponse<super::walletrpc::CreateUnsignedTxResponse>;
+
+    fn list_unsigned_txs(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ListUnsignedTxsRequest) -> ::grpc::SingleResponse<super::walletrpc::ListUnsignedTxsResponse>;
+
+    fn broadcast_signed_psbt(&self, o: ::grpc::RequestOptions, p: super::walletrpc::BroadcastSignedPsbtRequest) -> ::grpc::SingleResponse<super::walletrpc::BroadcastSignedPsbtResponse>;
+
+    fn cancel_unsigned_tx(&self, o: ::grpc::RequestOptions, p: super::walletrpc::CancelUnsignedTxRequest) -> ::grpc::SingleResponse<super::walletrpc::CancelUnsignedTxResponse>;
+
+    fn encode_qr(&self, o: ::grpc::RequestOptions, p: super::walletrpc::EncodeQrRequest) -> ::grpc::SingleResponse<super::walletrpc::EncodeQrResponse>;
+
+    fn decode_qr(&self, o: ::grpc::RequestOptions, p: super::walletrpc::DecodeQrRequest) -> ::grpc::SingleResponse<super::walletrpc::DecodeQrResponse>;
 }
 
 // client
@@ -47,13 +193,86 @@ pub struct WalletClient {
     grpc_client: ::std::sync::Arc<::grpc::Client>,
     method_NewAddress: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::NewAddressRequest, super::walletrpc::NewAddressResponse>>,
     method_NewChangeAddress: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::NewChangeAddressRequest, super::walletrpc::NewChangeAddressResponse>>,
+    method_GetUnusedAddress: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::GetUnusedAddressRequest, super::walletrpc::GetUnusedAddressResponse>>,
+    method_ListAddresses: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::ListAddressesRequest, super::walletrpc::ListAddressesResponse>>,
     method_GetUtxoList: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::GetUtxoListRequest, super::walletrpc::GetUtxoListResponse>>,
+    method_WatchScript: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::WatchScriptRequest, super::walletrpc::WatchScriptResponse>>,
+    method_ListWatchedUtxos: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::ListWatchedUtxosRequest, super::walletrpc::ListWatchedUtxosResponse>>,
     method_WalletBalance: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::WalletBalanceRequest, super::walletrpc::WalletBalanceResponse>>,
     method_SyncWithTip: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::SyncWithTipRequest, super::walletrpc::SyncWithTipResponse>>,
+    method_SyncWithTipStream: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::SyncWithTipRequest, super::walletrpc::SyncProgress>>,
+    method_RescanStream: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::RescanRequest, super::walletrpc::SyncProgress>>,
+    method_CancelOperation: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::CancelOperationRequest, super::walletrpc::CancelOperationResponse>>,
     method_MakeTx: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::MakeTxRequest, super::walletrpc::MakeTxResponse>>,
     method_SendCoins: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::SendCoinsRequest, super::walletrpc::SendCoinsResponse>>,
+    method_PrepareTransaction: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::PrepareTransactionRequest, super::walletrpc::PrepareTransactionResponse>>,
+    method_CommitTransaction: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::CommitTransactionRequest, super::walletrpc::CommitTransactionResponse>>,
+    method_AbandonTransaction: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::AbandonTransactionRequest, super::walletrpc::AbandonTransactionResponse>>,
+    method_AccelerateTransaction: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::AccelerateTransactionRequest, super::walletrpc::AccelerateTransactionResponse>>,
+    method_CancelTransaction: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::CancelTransactionRequest, super::walletrpc::CancelTransactionResponse>>,
+    method_EstimateTxSize: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::EstimateTxSizeRequest, super::walletrpc::EstimateTxSizeResponse>>,
+    method_GetPaymentCode: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::GetPaymentCodeRequest, super::walletrpc::GetPaymentCodeResponse>>,
+    method_NotifyPaymentCode: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::NotifyPaymentCodeRequest, super::walletrpc::NotifyPaymentCodeResponse>>,
+    method_PaymentCodeAddress: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::PaymentCodeAddressRequest, super::walletrpc::PaymentCodeAddressResponse>>,
+    method_SweepPaymentCodes: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::SweepPaymentCodesRequest, super::walletrpc::SweepPaymentCodesResponse>>,
+    method_GetSilentPaymentAddress: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::GetSilentPaymentAddressRequest, super::walletrpc::GetSilentPaymentAddressResponse>>,
+    method_ListSilentPaymentUtxos: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::ListSilentPaymentUtxosRequest, super::walletrpc::ListSilentPaymentUtxosResponse>>,
+    method_SweepSilentPayments: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::SweepSilentPaymentsRequest, super::walletrpc::SweepSilentPaymentsResponse>>,
+    method_ProveReserves: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::ProveReservesRequest, super::walletrpc::ProveReservesResponse>>,
+    method_GetSpendingPolicy: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::GetSpendingPolicyRequest, super::walletrpc::GetSpendingPolicyResponse>>,
+    method_SetSpendingPolicy: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::SetSpendingPolicyRequest, super::walletrpc::SetSpendingPolicyResponse>>,
+    method_ListPendingSpends: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::ListPendingSpendsRequest, super::walletrpc::ListPendingSpendsResponse>>,
+    method_ApproveSpend: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::ApproveSpendRequest, super::walletrpc::ApproveSpendResponse>>,
+    method_RejectSpend: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::RejectSpendRequest, super::walletrpc::RejectSpendResponse>>,
+    method_GetAuditLog: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::GetAuditLogRequest, super::walletrpc::GetAuditLogResponse>>,
+    method_FundChannel: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::FundChannelRequest, super::walletrpc::FundChannelResponse>>,
+    method_ReleaseFunding: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::ReleaseFundingRequest, super::walletrpc::ReleaseFundingResponse>>,
+    method_PublishFunding: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::PublishFundingRequest, super::walletrpc::PublishFundingResponse>>,
     method_UnlockCoins: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::UnlockCoinsRequest, super::walletrpc::UnlockCoinsResponse>>,
+    method_FreezeUtxo: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::FreezeUtxoRequest, super::walletrpc::FreezeUtxoResponse>>,
+    method_UnfreezeUtxo: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::UnfreezeUtxoRequest, super::walletrpc::UnfreezeUtxoResponse>>,
+    method_SetAddressLabel: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::SetAddressLabelRequest, super::walletrpc::SetAddressLabelResponse>>,
+    method_ExportBackup: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::ExportBackupRequest, super::walletrpc::ExportBackupResponse>>,
+    method_RestoreBackup: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::RestoreBackupRequest, super::walletrpc::RestoreBackupResponse>>,
+    method_ExportStateSnapshot: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::ExportStateSnapshotRequest, super::walletrpc::ExportStateSnapshotResponse>>,
+    method_ImportStateSnapshot: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::ImportStateSnapshotRequest, super::walletrpc::ImportStateSnapshotResponse>>,
+    method_CheckWallet: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::CheckWalletRequest, super::walletrpc::CheckWalletResponse>>,
+    method_CreateWallet: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::CreateWalletRequest, super::walletrpc::CreateWalletResponse>>,
+    method_ValidateMnemonic: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::ValidateMnemonicRequest, super::walletrpc::ValidateMnemonicResponse>>,
+    method_OpenWallet: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::OpenWalletRequest, super::walletrpc::OpenWalletResponse>>,
+    method_ListWallets: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::ListWalletsRequest, super::walletrpc::ListWalletsResponse>>,
+    method_LockWallet: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::LockWalletRequest, super::walletrpc::LockWalletResponse>>,
+    method_UnlockWallet: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::UnlockWalletRequest, super::walletrpc::UnlockWalletResponse>>,
+    method_SubscribeEvents: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::SubscribeEventsRequest, super::walletrpc::WalletEvent>>,
     method_Shutdown: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::ShutdownRequest, super::walletrpc::ShutdownResponse>>,
+    method_GetInfo: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::GetInfoRequest, super::walletrpc::GetInfoResponse>>,
+    method_SetLogLevel: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::SetLogLevelRequest, super::walletrpc::SetLogLevelResponse>>,
+    method_Reconnect: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::ReconnectRequest, super::walletrpc::ReconnectResponse>>,
+    method_SwitchBackend: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::SwitchBackendRequest, super::walletrpc::SwitchBackendResponse>>,
+    method_GetConnectionStatus: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::GetConnectionStatusRequest, super::walletrpc::GetConnectionStatusResponse>>,
+    method_GetTransactions: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::GetTransactionsRequest, super::walletrpc::GetTransactionsResponse>>,
+    method_GetAccountStats: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::GetAccountStatsRequest, super::walletrpc::GetAccountStatsResponse>>,
+    method_ExportHistory: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::ExportHistoryRequest, super::walletrpc::ExportHistoryChunk>>,
+    method_SweepAddress: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::SweepAddressRequest, super::walletrpc::SweepAddressResponse>>,
+    method_NewTimelockedAddress: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::NewTimelockedAddressRequest, super::walletrpc::NewTimelockedAddressResponse>>,
+    method_SetVault: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::SetVaultRequest, super::walletrpc::SetVaultResponse>>,
+    method_NewVaultAddress: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::NewVaultAddressRequest, super::walletrpc::NewVaultAddressResponse>>,
+    method_Unvault: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::UnvaultRequest, super::walletrpc::UnvaultResponse>>,
+    method_GetScriptAccountXpub: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::GetScriptAccountXpubRequest, super::walletrpc::GetScriptAccountXpubResponse>>,
+    method_RegisterPolicy: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::RegisterPolicyRequest, super::walletrpc::RegisterPolicyResponse>>,
+    method_NewScriptAddress: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::NewScriptAddressRequest, super::walletrpc::NewScriptAddressResponse>>,
+    method_SpendScriptCoins: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::SpendScriptCoinsRequest, super::walletrpc::SpendScriptCoinsResponse>>,
+    method_FinalizeScriptPsbt: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::FinalizeScriptPsbtRequest, super::walletrpc::FinalizeScriptPsbtResponse>>,
+    method_GetDescriptors: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::GetDescriptorsRequest, super::walletrpc::GetDescriptorsResponse>>,
+    method_GetImportDescriptors: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::GetImportDescriptorsRequest, super::walletrpc::GetImportDescriptorsResponse>>,
+    method_MigrateDerivation: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::MigrateDerivationRequest, super::walletrpc::MigrateDerivationResponse>>,
+    method_ExportElectrumWallet: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::ExportElectrumWalletRequest, super::walletrpc::ExportElectrumWalletResponse>>,
+    method_CreateUnsignedTx: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::CreateUnsignedTxRequest, super::walletrpc::CreateUnsignedTxResponse>>,
+    method_ListUnsignedTxs: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::ListUnsignedTxsRequest, super::walletrpc::ListUnsignedTxsResponse>>,
+    method_BroadcastSignedPsbt: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::BroadcastSignedPsbtRequest, super::walletrpc::BroadcastSignedPsbtResponse>>,
+    method_CancelUnsignedTx: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::CancelUnsignedTxRequest, super::walletrpc::CancelUnsignedTxResponse>>,
+    method_EncodeQr: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::EncodeQrRequest, super::walletrpc::EncodeQrResponse>>,
+    method_DecodeQr: ::std::sync::Arc<::grpc::rt::MethodDescriptor<super::walletrpc::DecodeQrRequest, super::walletrpc::DecodeQrResponse>>,
 }
 
 impl ::grpc::ClientStub for WalletClient {
@@ -72,12 +291,36 @@ impl ::grpc::ClientStub for WalletClient {
                 req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
                 resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
             }),
+            method_GetUnusedAddress: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/GetUnusedAddress".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_ListAddresses: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/ListAddresses".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
             method_GetUtxoList: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
                 name: "/walletrpc.Wallet/GetUtxoList".to_string(),
                 streaming: ::grpc::rt::GrpcStreaming::Unary,
                 req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
                 resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
             }),
+            method_WatchScript: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/WatchScript".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_ListWatchedUtxos: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/ListWatchedUtxos".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
             method_WalletBalance: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
                 name: "/walletrpc.Wallet/WalletBalance".to_string(),
                 streaming: ::grpc::rt::GrpcStreaming::Unary,
@@ -90,6 +333,24 @@ impl ::grpc::ClientStub for WalletClient {
                 req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
                 resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
             }),
+            method_SyncWithTipStream: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/SyncWithTipStream".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::ServerStreaming,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_RescanStream: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/RescanStream".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::ServerStreaming,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_CancelOperation: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/CancelOperation".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
             method_MakeTx: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
                 name: "/walletrpc.Wallet/MakeTx".to_string(),
                 streaming: ::grpc::rt::GrpcStreaming::Unary,
@@ -102,24 +363,420 @@ impl ::grpc::ClientStub for WalletClient {
                 req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
                 resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
             }),
+            method_PrepareTransaction: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/PrepareTransaction".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_CommitTransaction: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/CommitTransaction".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_AbandonTransaction: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/AbandonTransaction".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_AccelerateTransaction: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/AccelerateTransaction".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_CancelTransaction: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/CancelTransaction".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_EstimateTxSize: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/EstimateTxSize".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_GetPaymentCode: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/GetPaymentCode".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_NotifyPaymentCode: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/NotifyPaymentCode".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_PaymentCodeAddress: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/PaymentCodeAddress".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_SweepPaymentCodes: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/SweepPaymentCodes".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_GetSilentPaymentAddress: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/GetSilentPaymentAddress".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_ListSilentPaymentUtxos: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/ListSilentPaymentUtxos".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_SweepSilentPayments: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/SweepSilentPayments".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_ProveReserves: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/ProveReserves".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_GetSpendingPolicy: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/GetSpendingPolicy".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_SetSpendingPolicy: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/SetSpendingPolicy".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_ListPendingSpends: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/ListPendingSpends".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_ApproveSpend: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/ApproveSpend".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_RejectSpend: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/RejectSpend".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_GetAuditLog: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/GetAuditLog".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_FundChannel: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/FundChannel".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_ReleaseFunding: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/ReleaseFunding".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_PublishFunding: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/PublishFunding".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
             method_UnlockCoins: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
                 name: "/walletrpc.Wallet/UnlockCoins".to_string(),
                 streaming: ::grpc::rt::GrpcStreaming::Unary,
                 req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
                 resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
             }),
+            method_FreezeUtxo: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/FreezeUtxo".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_UnfreezeUtxo: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/UnfreezeUtxo".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_SetAddressLabel: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/SetAddressLabel".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_ExportBackup: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/ExportBackup".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_RestoreBackup: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/RestoreBackup".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_ExportStateSnapshot: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/ExportStateSnapshot".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_ImportStateSnapshot: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/ImportStateSnapshot".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_CheckWallet: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/CheckWallet".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_CreateWallet: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/CreateWallet".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_ValidateMnemonic: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/ValidateMnemonic".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_OpenWallet: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/OpenWallet".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_ListWallets: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/ListWallets".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_LockWallet: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/LockWallet".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_UnlockWallet: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/UnlockWallet".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_SubscribeEvents: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/SubscribeEvents".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::ServerStreaming,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
             method_Shutdown: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
                 name: "/walletrpc.Wallet/Shutdown".to_string(),
                 streaming: ::grpc::rt::GrpcStreaming::Unary,
                 req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
                 resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
             }),
-        }
-    }
-}
-
-impl Wallet for WalletClient {
-    fn new_address(&self, o: ::grpc::RequestOptions, p: super::walletrpc::NewAddressRequest) -> ::grpc::SingleResponse<super::walletrpc::NewAddressResponse> {
+            method_GetInfo: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/GetInfo".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_SetLogLevel: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/SetLogLevel".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_Reconnect: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/Reconnect".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_SwitchBackend: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/SwitchBackend".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_GetConnectionStatus: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/GetConnectionStatus".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_GetTransactions: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/GetTransactions".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_GetAccountStats: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/GetAccountStats".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_ExportHistory: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/ExportHistory".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::ServerStreaming,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_SweepAddress: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/SweepAddress".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_NewTimelockedAddress: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/NewTimelockedAddress".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_SetVault: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/SetVault".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_NewVaultAddress: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/NewVaultAddress".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_Unvault: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/Unvault".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_GetScriptAccountXpub: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/GetScriptAccountXpub".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_RegisterPolicy: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/RegisterPolicy".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_NewScriptAddress: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/NewScriptAddress".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_SpendScriptCoins: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/SpendScriptCoins".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_FinalizeScriptPsbt: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/FinalizeScriptPsbt".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_GetDescriptors: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/GetDescriptors".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_GetImportDescriptors: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/GetImportDescriptors".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_MigrateDerivation: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/MigrateDerivation".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_ExportElectrumWallet: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/ExportElectrumWallet".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_CreateUnsignedTx: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/CreateUnsignedTx".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_ListUnsignedTxs: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/ListUnsignedTxs".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_BroadcastSignedPsbt: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/BroadcastSignedPsbt".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_CancelUnsignedTx: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/CancelUnsignedTx".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_EncodeQr: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/EncodeQr".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+            method_DecodeQr: ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                name: "/walletrpc.Wallet/DecodeQr".to_string(),
+                streaming: ::grpc::rt::GrpcStreaming::Unary,
+                req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+            }),
+        }
+    }
+}
+
+impl Wallet for WalletClient {
+    fn new_address(&self, o: ::grpc::RequestOptions, p: super::walletrpc::NewAddressRequest) -> ::grpc::SingleResponse<super::walletrpc::NewAddressResponse> {
         self.grpc_client.call_unary(o, p, self.method_NewAddress.clone())
     }
 
@@ -127,10 +784,26 @@ impl Wallet for WalletClient {
         self.grpc_client.call_unary(o, p, self.method_NewChangeAddress.clone())
     }
 
+    fn get_unused_address(&self, o: ::grpc::RequestOptions, p: super::walletrpc::GetUnusedAddressRequest) -> ::grpc::SingleResponse<super::walletrpc::GetUnusedAddressResponse> {
+        self.grpc_client.call_unary(o, p, self.method_GetUnusedAddress.clone())
+    }
+
+    fn list_addresses(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ListAddressesRequest) -> ::grpc::SingleResponse<super::walletrpc::ListAddressesResponse> {
+        self.grpc_client.call_unary(o, p, self.method_ListAddresses.clone())
+    }
+
     fn get_utxo_list(&self, o: ::grpc::RequestOptions, p: super::walletrpc::GetUtxoListRequest) -> ::grpc::SingleResponse<super::walletrpc::GetUtxoListResponse> {
         self.grpc_client.call_unary(o, p, self.method_GetUtxoList.clone())
     }
 
+    fn watch_script(&self, o: ::grpc::RequestOptions, p: super::walletrpc::WatchScriptRequest) -> ::grpc::SingleResponse<super::walletrpc::WatchScriptResponse> {
+        self.grpc_client.call_unary(o, p, self.method_WatchScript.clone())
+    }
+
+    fn list_watched_utxos(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ListWatchedUtxosRequest) -> ::grpc::SingleResponse<super::walletrpc::ListWatchedUtxosResponse> {
+        self.grpc_client.call_unary(o, p, self.method_ListWatchedUtxos.clone())
+    }
+
     fn wallet_balance(&self, o: ::grpc::RequestOptions, p: super::walletrpc::WalletBalanceRequest) -> ::grpc::SingleResponse<super::walletrpc::WalletBalanceResponse> {
         self.grpc_client.call_unary(o, p, self.method_WalletBalance.clone())
     }
@@ -139,6 +812,18 @@ impl Wallet for WalletClient {
         self.grpc_client.call_unary(o, p, self.method_SyncWithTip.clone())
     }
 
+    fn sync_with_tip_stream(&self, o: ::grpc::RequestOptions, p: super::walletrpc::SyncWithTipRequest) -> ::grpc::StreamingResponse<super::walletrpc::SyncProgress> {
+        self.grpc_client.call_server_streaming(o, p, self.method_SyncWithTipStream.clone())
+    }
+
+    fn rescan_stream(&self, o: ::grpc::RequestOptions, p: super::walletrpc::RescanRequest) -> ::grpc::StreamingResponse<super::walletrpc::SyncProgress> {
+        self.grpc_client.call_server_streaming(o, p, self.method_RescanStream.clone())
+    }
+
+    fn cancel_operation(&self, o: ::grpc::RequestOptions, p: super::walletrpc::CancelOperationRequest) -> ::grpc::SingleResponse<super::walletrpc::CancelOperationResponse> {
+        self.grpc_client.call_unary(o, p, self.method_CancelOperation.clone())
+    }
+
     fn make_tx(&self, o: ::grpc::RequestOptions, p: super::walletrpc::MakeTxRequest) -> ::grpc::SingleResponse<super::walletrpc::MakeTxResponse> {
         self.grpc_client.call_unary(o, p, self.method_MakeTx.clone())
     }
@@ -147,131 +832,1271 @@ impl Wallet for WalletClient {
         self.grpc_client.call_unary(o, p, self.method_SendCoins.clone())
     }
 
+    fn prepare_transaction(&self, o: ::grpc::RequestOptions, p: super::walletrpc::PrepareTransactionRequest) -> ::grpc::SingleResponse<super::walletrpc::PrepareTransactionResponse> {
+        self.grpc_client.call_unary(o, p, self.method_PrepareTransaction.clone())
+    }
+
+    fn commit_transaction(&self, o: ::grpc::RequestOptions, p: super::walletrpc::CommitTransactionRequest) -> ::grpc::SingleResponse<super::walletrpc::CommitTransactionResponse> {
+        self.grpc_client.call_unary(o, p, self.method_CommitTransaction.clone())
+    }
+
+    fn abandon_transaction(&self, o: ::grpc::RequestOptions, p: super::walletrpc::AbandonTransactionRequest) -> ::grpc::SingleResponse<super::walletrpc::AbandonTransactionResponse> {
+        self.grpc_client.call_unary(o, p, self.method_AbandonTransaction.clone())
+    }
+
+    fn accelerate_transaction(&self, o: ::grpc::RequestOptions, p: super::walletrpc::AccelerateTransactionRequest) -> ::grpc::SingleResponse<super::walletrpc::AccelerateTransactionResponse> {
+        self.grpc_client.call_unary(o, p, self.method_AccelerateTransaction.clone())
+    }
+
+    fn cancel_transaction(&self, o: ::grpc::RequestOptions, p: super::walletrpc::CancelTransactionRequest) -> ::grpc::SingleResponse<super::walletrpc::CancelTransactionResponse> {
+        self.grpc_client.call_unary(o, p, self.method_CancelTransaction.clone())
+    }
+
+    fn estimate_tx_size(&self, o: ::grpc::RequestOptions, p: super::walletrpc::EstimateTxSizeRequest) -> ::grpc::SingleResponse<super::walletrpc::EstimateTxSizeResponse> {
+        self.grpc_client.call_unary(o, p, self.method_EstimateTxSize.clone())
+    }
+
+    fn get_payment_code(&self, o: ::grpc::RequestOptions, p: super::walletrpc::GetPaymentCodeRequest) -> ::grpc::SingleResponse<super::walletrpc::GetPaymentCodeResponse> {
+        self.grpc_client.call_unary(o, p, self.method_GetPaymentCode.clone())
+    }
+
+    fn notify_payment_code(&self, o: ::grpc::RequestOptions, p: super::walletrpc::NotifyPaymentCodeRequest) -> ::grpc::SingleResponse<super::walletrpc::NotifyPaymentCodeResponse> {
+        self.grpc_client.call_unary(o, p, self.method_NotifyPaymentCode.clone())
+    }
+
+    fn payment_code_address(&self, o: ::grpc::RequestOptions, p: super::walletrpc::PaymentCodeAddressRequest) -> ::grpc::SingleResponse<super::walletrpc::PaymentCodeAddressResponse> {
+        self.grpc_client.call_unary(o, p, self.method_PaymentCodeAddress.clone())
+    }
+
+    fn sweep_payment_codes(&self, o: ::grpc::RequestOptions, p: super::walletrpc::SweepPaymentCodesRequest) -> ::grpc::SingleResponse<super::walletrpc::SweepPaymentCodesResponse> {
+        self.grpc_client.call_unary(o, p, self.method_SweepPaymentCodes.clone())
+    }
+
+    fn get_silent_payment_address(&self, o: ::grpc::RequestOptions, p: super::walletrpc::GetSilentPaymentAddressRequest) -> ::grpc::SingleResponse<super::walletrpc::GetSilentPaymentAddressResponse> {
+        self.grpc_client.call_unary(o, p, self.method_GetSilentPaymentAddress.clone())
+    }
+
+    fn list_silent_payment_utxos(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ListSilentPaymentUtxosRequest) -> ::grpc::SingleResponse<super::walletrpc::ListSilentPaymentUtxosResponse> {
+        self.grpc_client.call_unary(o, p, self.method_ListSilentPaymentUtxos.clone())
+    }
+
+    fn sweep_silent_payments(&self, o: ::grpc::RequestOptions, p: super::walletrpc::SweepSilentPaymentsRequest) -> ::grpc::SingleResponse<super::walletrpc::SweepSilentPaymentsResponse> {
+        self.grpc_client.call_unary(o, p, self.method_SweepSilentPayments.clone())
+    }
+
+    fn prove_reserves(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ProveReservesRequest) -> ::grpc::SingleResponse<super::walletrpc::ProveReservesResponse> {
+        self.grpc_client.call_unary(o, p, self.method_ProveReserves.clone())
+    }
+
+    fn get_spending_policy(&self, o: ::grpc::RequestOptions, p: super::walletrpc::GetSpendingPolicyRequest) -> ::grpc::SingleResponse<super::walletrpc::GetSpendingPolicyResponse> {
+        self.grpc_client.call_unary(o, p, self.method_GetSpendingPolicy.clone())
+    }
+
+    fn set_spending_policy(&self, o: ::grpc::RequestOptions, p: super::walletrpc::SetSpendingPolicyRequest) -> ::grpc::SingleResponse<super::walletrpc::SetSpendingPolicyResponse> {
+        self.grpc_client.call_unary(o, p, self.method_SetSpendingPolicy.clone())
+    }
+
+    fn list_pending_spends(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ListPendingSpendsRequest) -> ::grpc::SingleResponse<super::walletrpc::ListPendingSpendsResponse> {
+        self.grpc_client.call_unary(o, p, self.method_ListPendingSpends.clone())
+    }
+
+    fn approve_spend(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ApproveSpendRequest) -> ::grpc::SingleResponse<super::walletrpc::ApproveSpendResponse> {
+        self.grpc_client.call_unary(o, p, self.method_ApproveSpend.clone())
+    }
+
+    fn reject_spend(&self, o: ::grpc::RequestOptions, p: super::walletrpc::RejectSpendRequest) -> ::grpc::SingleResponse<super::walletrpc::RejectSpendResponse> {
+        self.grpc_client.call_unary(o, p, self.method_RejectSpend.clone())
+    }
+
+    fn get_audit_log(&self, o: ::grpc::RequestOptions, p: super::walletrpc::GetAuditLogRequest) -> ::grpc::SingleResponse<super::walletrpc::GetAuditLogResponse> {
+        self.grpc_client.call_unary(o, p, self.method_GetAuditLog.clone())
+    }
+
+    fn fund_channel(&self, o: ::grpc::RequestOptions, p: super::walletrpc::FundChannelRequest) -> ::grpc::SingleResponse<super::walletrpc::FundChannelResponse> {
+        self.grpc_client.call_unary(o, p, self.method_FundChannel.clone())
+    }
+
+    fn release_funding(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ReleaseFundingRequest) -> ::grpc::SingleResponse<super::walletrpc::ReleaseFundingResponse> {
+        self.grpc_client.call_unary(o, p, self.method_ReleaseFunding.clone())
+    }
+
+    fn publish_funding(&self, o: ::grpc::RequestOptions, p: super::walletrpc::PublishFundingRequest) -> ::grpc::SingleResponse<super::walletrpc::PublishFundingResponse> {
+        self.grpc_client.call_unary(o, p, self.method_PublishFunding.clone())
+    }
+
     fn unlock_coins(&self, o: ::grpc::RequestOptions, p: super::walletrpc::UnlockCoinsRequest) -> ::grpc::SingleResponse<super::walletrpc::UnlockCoinsResponse> {
         self.grpc_client.call_unary(o, p, self.method_UnlockCoins.clone())
     }
 
+    fn freeze_utxo(&self, o: ::grpc::RequestOptions, p: super::walletrpc::FreezeUtxoRequest) -> ::grpc::SingleResponse<super::walletrpc::FreezeUtxoResponse> {
+        self.grpc_client.call_unary(o, p, self.method_FreezeUtxo.clone())
+    }
+
+    fn unfreeze_utxo(&self, o: ::grpc::RequestOptions, p: super::walletrpc::UnfreezeUtxoRequest) -> ::grpc::SingleResponse<super::walletrpc::UnfreezeUtxoResponse> {
+        self.grpc_client.call_unary(o, p, self.method_UnfreezeUtxo.clone())
+    }
+
+    fn set_address_label(&self, o: ::grpc::RequestOptions, p: super::walletrpc::SetAddressLabelRequest) -> ::grpc::SingleResponse<super::walletrpc::SetAddressLabelResponse> {
+        self.grpc_client.call_unary(o, p, self.method_SetAddressLabel.clone())
+    }
+
+    fn export_backup(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ExportBackupRequest) -> ::grpc::SingleResponse<super::walletrpc::ExportBackupResponse> {
+        self.grpc_client.call_unary(o, p, self.method_ExportBackup.clone())
+    }
+
+    fn restore_backup(&self, o: ::grpc::RequestOptions, p: super::walletrpc::RestoreBackupRequest) -> ::grpc::SingleResponse<super::walletrpc::RestoreBackupResponse> {
+        self.grpc_client.call_unary(o, p, self.method_RestoreBackup.clone())
+    }
+
+    fn export_state_snapshot(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ExportStateSnapshotRequest) -> ::grpc::SingleResponse<super::walletrpc::ExportStateSnapshotResponse> {
+        self.grpc_client.call_unary(o, p, self.method_ExportStateSnapshot.clone())
+    }
+
+    fn import_state_snapshot(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ImportStateSnapshotRequest) -> ::grpc::SingleResponse<super::walletrpc::ImportStateSnapshotResponse> {
+        self.grpc_client.call_unary(o, p, self.method_ImportStateSnapshot.clone())
+    }
+
+    fn check_wallet(&self, o: ::grpc::RequestOptions, p: super::walletrpc::CheckWalletRequest) -> ::grpc::SingleResponse<super::walletrpc::CheckWalletResponse> {
+        self.grpc_client.call_unary(o, p, self.method_CheckWallet.clone())
+    }
+
+    fn create_wallet(&self, o: ::grpc::RequestOptions, p: super::walletrpc::CreateWalletRequest) -> ::grpc::SingleResponse<super::walletrpc::CreateWalletResponse> {
+        self.grpc_client.call_unary(o, p, self.method_CreateWallet.clone())
+    }
+
+    fn validate_mnemonic(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ValidateMnemonicRequest) -> ::grpc::SingleResponse<super::walletrpc::ValidateMnemonicResponse> {
+        self.grpc_client.call_unary(o, p, self.method_ValidateMnemonic.clone())
+    }
+
+    fn open_wallet(&self, o: ::grpc::RequestOptions, p: super::walletrpc::OpenWalletRequest) -> ::grpc::SingleResponse<super::walletrpc::OpenWalletResponse> {
+        self.grpc_client.call_unary(o, p, self.method_OpenWallet.clone())
+    }
+
+    fn list_wallets(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ListWalletsRequest) -> ::grpc::SingleResponse<super::walletrpc::ListWalletsResponse> {
+        self.grpc_client.call_unary(o, p, self.method_ListWallets.clone())
+    }
+
+    fn lock_wallet(&self, o: ::grpc::RequestOptions, p: super::walletrpc::LockWalletRequest) -> ::grpc::SingleResponse<super::walletrpc::LockWalletResponse> {
+        self.grpc_client.call_unary(o, p, self.method_LockWallet.clone())
+    }
+
+    fn unlock_wallet(&self, o: ::grpc::RequestOptions, p: super::walletrpc::UnlockWalletRequest) -> ::grpc::SingleResponse<super::walletrpc::UnlockWalletResponse> {
+        self.grpc_client.call_unary(o, p, self.method_UnlockWallet.clone())
+    }
+
+    fn subscribe_events(&self, o: ::grpc::RequestOptions, p: super::walletrpc::SubscribeEventsRequest) -> ::grpc::StreamingResponse<super::walletrpc::WalletEvent> {
+        self.grpc_client.call_server_streaming(o, p, self.method_SubscribeEvents.clone())
+    }
+
     fn shutdown(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ShutdownRequest) -> ::grpc::SingleResponse<super::walletrpc::ShutdownResponse> {
         self.grpc_client.call_unary(o, p, self.method_Shutdown.clone())
     }
-}
 
-// server
+    fn get_info(&self, o: ::grpc::RequestOptions, p: super::walletrpc::GetInfoRequest) -> ::grpc::SingleResponse<super::walletrpc::GetInfoResponse> {
+        self.grpc_client.call_unary(o, p, self.method_GetInfo.clone())
+    }
 
-pub struct WalletServer;
+    fn set_log_level(&self, o: ::grpc::RequestOptions, p: super::walletrpc::SetLogLevelRequest) -> ::grpc::SingleResponse<super::walletrpc::SetLogLevelResponse> {
+        self.grpc_client.call_unary(o, p, self.method_SetLogLevel.clone())
+    }
 
+    fn reconnect(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ReconnectRequest) -> ::grpc::SingleResponse<super::walletrpc::ReconnectResponse> {
+        self.grpc_client.call_unary(o, p, self.method_Reconnect.clone())
+    }
 
-impl WalletServer {
-    pub fn new_service_def<H : Wallet + 'static + Sync + Send + 'static>(handler: H) -> ::grpc::rt::ServerServiceDefinition {
-        let handler_arc = ::std::sync::Arc::new(handler);
-        ::grpc::rt::ServerServiceDefinition::new("/walletrpc.Wallet",
-            vec![
+    fn switch_backend(&self, o: ::grpc::RequestOptions, p: super::walletrpc::SwitchBackendRequest) -> ::grpc::SingleResponse<super::walletrpc::SwitchBackendResponse> {
+        self.grpc_client.call_unary(o, p, self.method_SwitchBackend.clone())
+    }
+
+    fn get_connection_status(&self, o: ::grpc::RequestOptions, p: super::walletrpc::GetConnectionStatusRequest) -> ::grpc::SingleResponse<super::walletrpc::GetConnectionStatusResponse> {
+        self.grpc_client.call_unary(o, p, self.method_GetConnectionStatus.clone())
+    }
+
+    fn get_transactions(&self, o: ::grpc::RequestOptions, p: super::walletrpc::GetTransactionsRequest) -> ::grpc::SingleResponse<super::walletrpc::GetTransactionsResponse> {
+        self.grpc_client.call_unary(o, p, self.method_GetTransactions.clone())
+    }
+
+    fn get_account_stats(&self, o: ::grpc::RequestOptions, p: super::walletrpc::GetAccountStatsRequest) -> ::grpc::SingleResponse<super::walletrpc::GetAccountStatsResponse> {
+        self.grpc_client.call_unary(o, p, self.method_GetAccountStats.clone())
+    }
+
+    fn export_history(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ExportHistoryRequest) -> ::grpc::StreamingResponse<super::walletrpc::ExportHistoryChunk> {
+        self.grpc_client.call_server_streaming(o, p, self.method_ExportHistory.clone())
+    }
+
+    fn sweep_address(&self, o: ::grpc::RequestOptions, p: super::walletrpc::SweepAddressRequest) -> ::grpc::SingleResponse<super::walletrpc::SweepAddressResponse> {
+        self.grpc_client.call_unary(o, p, self.method_SweepAddress.clone())
+    }
+
+    fn new_timelocked_address(&self, o: ::grpc::RequestOptions, p: super::walletrpc::NewTimelockedAddressRequest) -> ::grpc::SingleResponse<super::walletrpc::NewTimelockedAddressResponse> {
+        self.grpc_client.call_unary(o, p, self.method_NewTimelockedAddress.clone())
+    }
+
+    fn set_vault(&self, o: ::grpc::RequestOptions, p: super::walletrpc::SetVaultRequest) -> ::grpc::SingleResponse<super::walletrpc::SetVaultResponse> {
+        self.grpc_client.call_unary(o, p, self.method_SetVault.clone())
+    }
+
+    fn new_vault_address(&self, o: ::grpc::RequestOptions, p: super::walletrpc::NewVaultAddressRequest) -> ::grpc::SingleResponse<super::walletrpc::NewVaultAddressResponse> {
+        self.grpc_client.call_unary(o, p, self.method_NewVaultAddress.clone())
+    }
+
+    fn unvault(&self, o: ::grpc::RequestOptions, p: super::walletrpc::UnvaultRequest) -> ::grpc::SingleResponse<super::walletrpc::UnvaultResponse> {
+        self.grpc_client.call_unary(o, p, self.method_Unvault.clone())
+    }
+
+    fn get_script_account_xpub(&self, o: ::grpc::RequestOptions, p: super::walletrpc::GetScriptAccountXpubRequest) -> ::grpc::SingleResponse<super::walletrpc::GetScriptAccountXpubResponse> {
+        self.grpc_client.call_unary(o, p, self.method_GetScriptAccountXpub.clone())
+    }
+
+    fn register_policy(&self, o: ::grpc::RequestOptions, p: super::walletrpc::RegisterPolicyRequest) -> ::grpc::SingleResponse<super::walletrpc::RegisterPolicyResponse> {
+        self.grpc_client.call_unary(o, p, self.method_RegisterPolicy.clone())
+    }
+
+    fn new_script_address(&self, o: ::grpc::RequestOptions, p: super::walletrpc::NewScriptAddressRequest) -> ::grpc::SingleResponse<super::walletrpc::NewScriptAddressResponse> {
+        self.grpc_client.call_unary(o, p, self.method_NewScriptAddress.clone())
+    }
+
+    fn spend_script_coins(&self, o: ::grpc::RequestOptions, p: super::walletrpc::SpendScriptCoinsRequest) -> ::grpc::SingleResponse<super::walletrpc::SpendScriptCoinsResponse> {
+        self.grpc_client.call_unary(o, p, self.method_SpendScriptCoins.clone())
+    }
+
+    fn finalize_script_psbt(&self, o: ::grpc::RequestOptions, p: super::walletrpc::FinalizeScriptPsbtRequest) -> ::grpc::SingleResponse<super::walletrpc::FinalizeScriptPsbtResponse> {
+        self.grpc_client.call_unary(o, p, self.method_FinalizeScriptPsbt.clone())
+    }
+
+    fn get_descriptors(&self, o: ::grpc::RequestOptions, p: super::walletrpc::GetDescriptorsRequest) -> ::grpc::SingleResponse<super::walletrpc::GetDescriptorsResponse> {
+        self.grpc_client.call_unary(o, p, self.method_GetDescriptors.clone())
+    }
+
+    fn get_import_descriptors(&self, o: ::grpc::RequestOptions, p: super::walletrpc::GetImportDescriptorsRequest) -> ::grpc::SingleResponse<super::walletrpc::GetImportDescriptorsResponse> {
+        self.grpc_client.call_unary(o, p, self.method_GetImportDescriptors.clone())
+    }
+
+    fn migrate_derivation(&self, o: ::grpc::RequestOptions, p: super::walletrpc::MigrateDerivationRequest) -> ::grpc::SingleResponse<super::walletrpc::MigrateDerivationResponse> {
+        self.grpc_client.call_unary(o, p, self.method_MigrateDerivation.clone())
+    }
+
+    fn export_electrum_wallet(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ExportElectrumWalletRequest) -> ::grpc::SingleResponse<super::walletrpc::ExportElectrumWalletResponse> {
+        self.grpc_client.call_unary(o, p, self.method_ExportElectrumWallet.clone())
+    }
+
+    fn create_unsigned_tx(&self, o: ::grpc::RequestOptions, p: super::walletrpc::CreateUnsignedTxRequest) -> ::grpc::SingleResponse<super::walletrpc::CreateUnsignedTxResponse> {
+        self.grpc_client.call_unary(o, p, self.method_CreateUnsignedTx.clone())
+    }
+
+    fn list_unsigned_txs(&self, o: ::grpc::RequestOptions, p: super::walletrpc::ListUnsignedTxsRequest) -> ::grpc::SingleResponse<super::walletrpc::ListUnsignedTxsResponse> {
+        self.grpc_client.call_unary(o, p, self.method_ListUnsignedTxs.clone())
+    }
+
+    fn broadcast_signed_psbt(&self, o: ::grpc::RequestOptions, p: super::walletrpc::BroadcastSignedPsbtRequest) -> ::grpc::SingleResponse<super::walletrpc::BroadcastSignedPsbtResponse> {
+        self.grpc_client.call_unary(o, p, self.method_BroadcastSignedPsbt.clone())
+    }
+
+    fn cancel_unsigned_tx(&self, o: ::grpc::RequestOptions, p: super::walletrpc::CancelUnsignedTxRequest) -> ::grpc::SingleResponse<super::walletrpc::CancelUnsignedTxResponse> {
+        self.grpc_client.call_unary(o, p, self.method_CancelUnsignedTx.clone())
+    }
+
+    fn encode_qr(&self, o: ::grpc::RequestOptions, p: super::walletrpc::EncodeQrRequest) -> ::grpc::SingleResponse<super::walletrpc::EncodeQrResponse> {
+        self.grpc_client.call_unary(o, p, self.method_EncodeQr.clone())
+    }
+
+    fn decode_qr(&self, o: ::grpc::RequestOptions, p: super::walletrpc::DecodeQrRequest) -> ::grpc::SingleResponse<super::walletrpc::DecodeQrResponse> {
+        self.grpc_client.call_unary(o, p, self.method_DecodeQr.clone())
+    }
+}
+
+// server
+
+pub struct WalletServer;
+
+
+impl WalletServer {
+    pub fn new_service_def<H : Wallet + 'static + Sync + Send + 'static>(handler: H) -> ::grpc::rt::ServerServiceDefinition {
+        let handler_arc = ::std::sync::Arc::new(handler);
+        ::grpc::rt::ServerServiceDefinition::new("/walletrpc.Wallet",
+            vec![
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/NewAddress".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.new_address(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/NewChangeAddress".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.new_change_address(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/GetUnusedAddress".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.get_unused_address(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/ListAddresses".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.list_addresses(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/GetUtxoList".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.get_utxo_list(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/WatchScript".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.watch_script(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/ListWatchedUtxos".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.list_watched_utxos(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/WalletBalance".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.wallet_balance(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/SyncWithTip".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.sync_with_tip(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/SyncWithTipStream".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::ServerStreaming,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerServerStreaming::new(move |o, p| handler_copy.sync_with_tip_stream(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/RescanStream".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::ServerStreaming,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerServerStreaming::new(move |o, p| handler_copy.rescan_stream(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/CancelOperation".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.cancel_operation(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/MakeTx".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.make_tx(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/SendCoins".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.send_coins(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/PrepareTransaction".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.prepare_transaction(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/CommitTransaction".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.commit_transaction(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/AbandonTransaction".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.abandon_transaction(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/AccelerateTransaction".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.accelerate_transaction(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/CancelTransaction".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.cancel_transaction(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/EstimateTxSize".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.estimate_tx_size(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/GetPaymentCode".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.get_payment_code(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/NotifyPaymentCode".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.notify_payment_code(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/PaymentCodeAddress".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.payment_code_address(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/SweepPaymentCodes".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.sweep_payment_codes(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/GetSilentPaymentAddress".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.get_silent_payment_address(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/ListSilentPaymentUtxos".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.list_silent_payment_utxos(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/SweepSilentPayments".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.sweep_silent_payments(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/ProveReserves".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.prove_reserves(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/GetSpendingPolicy".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.get_spending_policy(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/SetSpendingPolicy".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.set_spending_policy(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/ListPendingSpends".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.list_pending_spends(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/ApproveSpend".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.approve_spend(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/RejectSpend".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.reject_spend(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/GetAuditLog".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.get_audit_log(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/FundChannel".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.fund_channel(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/ReleaseFunding".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.release_funding(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/PublishFunding".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.publish_funding(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/UnlockCoins".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.unlock_coins(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/FreezeUtxo".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.freeze_utxo(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/UnfreezeUtxo".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.unfreeze_utxo(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/SetAddressLabel".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.set_address_label(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/ExportBackup".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.export_backup(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/RestoreBackup".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.restore_backup(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/ExportStateSnapshot".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.export_state_snapshot(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/ImportStateSnapshot".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.import_state_snapshot(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/CheckWallet".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.check_wallet(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/CreateWallet".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.create_wallet(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/ValidateMnemonic".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.validate_mnemonic(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/OpenWallet".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.open_wallet(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/ListWallets".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.list_wallets(o, p))
+                    },
+                ),
                 ::grpc::rt::ServerMethod::new(
                     ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
-                        name: "/walletrpc.Wallet/NewAddress".to_string(),
+                        name: "/walletrpc.Wallet/LockWallet".to_string(),
                         streaming: ::grpc::rt::GrpcStreaming::Unary,
                         req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
                         resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
                     }),
                     {
                         let handler_copy = handler_arc.clone();
-                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.new_address(o, p))
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.lock_wallet(o, p))
                     },
                 ),
                 ::grpc::rt::ServerMethod::new(
                     ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
-                        name: "/walletrpc.Wallet/NewChangeAddress".to_string(),
+                        name: "/walletrpc.Wallet/UnlockWallet".to_string(),
                         streaming: ::grpc::rt::GrpcStreaming::Unary,
                         req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
                         resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
                     }),
                     {
                         let handler_copy = handler_arc.clone();
-                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.new_change_address(o, p))
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.unlock_wallet(o, p))
                     },
                 ),
                 ::grpc::rt::ServerMethod::new(
                     ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
-                        name: "/walletrpc.Wallet/GetUtxoList".to_string(),
+                        name: "/walletrpc.Wallet/SubscribeEvents".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::ServerStreaming,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerServerStreaming::new(move |o, p| handler_copy.subscribe_events(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/Shutdown".to_string(),
                         streaming: ::grpc::rt::GrpcStreaming::Unary,
                         req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
                         resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
                     }),
                     {
                         let handler_copy = handler_arc.clone();
-                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.get_utxo_list(o, p))
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.shutdown(o, p))
                     },
                 ),
                 ::grpc::rt::ServerMethod::new(
                     ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
-                        name: "/walletrpc.Wallet/WalletBalance".to_string(),
+                        name: "/walletrpc.Wallet/GetInfo".to_string(),
                         streaming: ::grpc::rt::GrpcStreaming::Unary,
                         req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
                         resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
                     }),
                     {
                         let handler_copy = handler_arc.clone();
-                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.wallet_balance(o, p))
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.get_info(o, p))
                     },
                 ),
                 ::grpc::rt::ServerMethod::new(
                     ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
-                        name: "/walletrpc.Wallet/SyncWithTip".to_string(),
+                        name: "/walletrpc.Wallet/SetLogLevel".to_string(),
                         streaming: ::grpc::rt::GrpcStreaming::Unary,
                         req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
                         resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
                     }),
                     {
                         let handler_copy = handler_arc.clone();
-                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.sync_with_tip(o, p))
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.set_log_level(o, p))
                     },
                 ),
                 ::grpc::rt::ServerMethod::new(
                     ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
-                        name: "/walletrpc.Wallet/MakeTx".to_string(),
+                        name: "/walletrpc.Wallet/Reconnect".to_string(),
                         streaming: ::grpc::rt::GrpcStreaming::Unary,
                         req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
                         resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
                     }),
                     {
                         let handler_copy = handler_arc.clone();
-                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.make_tx(o, p))
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.reconnect(o, p))
                     },
                 ),
                 ::grpc::rt::ServerMethod::new(
                     ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
-                        name: "/walletrpc.Wallet/SendCoins".to_string(),
+                        name: "/walletrpc.Wallet/SwitchBackend".to_string(),
                         streaming: ::grpc::rt::GrpcStreaming::Unary,
                         req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
                         resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
                     }),
                     {
                         let handler_copy = handler_arc.clone();
-                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.send_coins(o, p))
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.switch_backend(o, p))
                     },
                 ),
                 ::grpc::rt::ServerMethod::new(
                     ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
-                        name: "/walletrpc.Wallet/UnlockCoins".to_string(),
+                        name: "/walletrpc.Wallet/GetConnectionStatus".to_string(),
                         streaming: ::grpc::rt::GrpcStreaming::Unary,
                         req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
                         resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
                     }),
                     {
                         let handler_copy = handler_arc.clone();
-                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.unlock_coins(o, p))
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.get_connection_status(o, p))
                     },
                 ),
                 ::grpc::rt::ServerMethod::new(
                     ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
-                        name: "/walletrpc.Wallet/Shutdown".to_string(),
+                        name: "/walletrpc.Wallet/GetTransactions".to_string(),
                         streaming: ::grpc::rt::GrpcStreaming::Unary,
                         req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
                         resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
                     }),
                     {
                         let handler_copy = handler_arc.clone();
-                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.shutdown(o, p))
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.get_transactions(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/GetAccountStats".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.get_account_stats(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/ExportHistory".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::ServerStreaming,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerServerStreaming::new(move |o, p| handler_copy.export_history(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/SweepAddress".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.sweep_address(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/NewTimelockedAddress".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.new_timelocked_address(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/SetVault".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.set_vault(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/NewVaultAddress".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.new_vault_address(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/Unvault".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.unvault(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/GetScriptAccountXpub".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.get_script_account_xpub(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/RegisterPolicy".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.register_policy(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/NewScriptAddress".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.new_script_address(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/SpendScriptCoins".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.spend_script_coins(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/FinalizeScriptPsbt".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.finalize_script_psbt(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/GetDescriptors".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.get_descriptors(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/GetImportDescriptors".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.get_import_descriptors(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/MigrateDerivation".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.migrate_derivation(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/ExportElectrumWallet".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.export_electrum_wallet(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/CreateUnsignedTx".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.create_unsigned_tx(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/ListUnsignedTxs".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.list_unsigned_txs(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/BroadcastSignedPsbt".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.broadcast_signed_psbt(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/CancelUnsignedTx".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.cancel_unsigned_tx(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/EncodeQr".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.encode_qr(o, p))
+                    },
+                ),
+                ::grpc::rt::ServerMethod::new(
+                    ::std::sync::Arc::new(::grpc::rt::MethodDescriptor {
+                        name: "/walletrpc.Wallet/DecodeQr".to_string(),
+                        streaming: ::grpc::rt::GrpcStreaming::Unary,
+                        req_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                        resp_marshaller: Box::new(::grpc::protobuf::MarshallerProtobuf),
+                    }),
+                    {
+                        let handler_copy = handler_arc.clone();
+                        ::grpc::rt::MethodHandlerUnary::new(move |o, p| handler_copy.decode_qr(o, p))
                     },
                 ),
             ],
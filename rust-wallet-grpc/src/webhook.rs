@@ -0,0 +1,251 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Webhook notifications
+//!
+//! Events of the wallets are posted as JSON to a URL, signed with HMAC-SHA256 in the
+//! `X-Signature` header, so a backend learns about deposits without holding a stream open
+//!
+use bitcoin_hashes::sha256d::Hash as Sha256dHash;
+use crypto::{hmac::Hmac, mac::Mac, sha2::Sha256};
+use serde_json::json;
+use wallet::events::WalletEvent;
+
+use log::{info, warn};
+
+use super::http;
+
+use std::{
+    thread,
+    error::Error,
+    time::Duration,
+    sync::{
+        Mutex,
+        mpsc::{self, Receiver, Sender},
+    },
+};
+
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+const MAX_ATTEMPTS: u32 = 10;
+
+#[derive(Clone, Debug)]
+pub struct WebhookConfig {
+    /// `http://` or `https://` URL the events are posted to
+    pub url: String,
+    /// key of the HMAC-SHA256 signature of the request body
+    pub secret: String,
+    /// number of confirmations a transaction is reported confirmed at
+    pub confirmations: usize,
+}
+
+/// posts events of the watched wallets one by one, in the order they happened
+pub struct Webhook {
+    config: WebhookConfig,
+    queue: Mutex<Sender<String>>,
+}
+
+impl Webhook {
+    pub fn start(config: WebhookConfig) -> Self {
+        let (queue, payloads) = mpsc::channel();
+        let delivery_config = config.clone();
+        thread::spawn(move || deliver(&delivery_config, payloads));
+        Webhook {
+            config,
+            queue: Mutex::new(queue),
+        }
+    }
+
+    /// forward the events of the wallet named `wallet` until the wallet goes away
+    pub fn watch(&self, wallet: &str, events: Receiver<WalletEvent>) {
+        let wallet = wallet.to_owned();
+        let confirmations = self.config.confirmations;
+        let queue = self.queue.lock().unwrap().clone();
+        thread::spawn(move || {
+            // confirmed transactions waiting for enough blocks on top of them
+            let mut pending: Vec<(Sha256dHash, usize, bool)> = Vec::new();
+            for event in events {
+                let payloads = match event {
+                    WalletEvent::Deposit { txid, value } => vec![json!({
+                        "event": "deposit",
+                        "wallet": wallet,
+                        "txid": txid.to_string(),
+                        "value": value,
+                    })],
+                    WalletEvent::Confirmed { txid, block_height, outgoing } => {
+                        pending.push((txid, block_height, outgoing));
+                        ripe(&wallet, &mut pending, block_height, confirmations)
+                    }
+                    WalletEvent::NewTip { height } => {
+                        ripe(&wallet, &mut pending, height, confirmations)
+                    }
+                    WalletEvent::WatchedFunded { label, out_point, value } => vec![json!({
+                        "event": "watched_funded",
+                        "wallet": wallet,
+                        "label": label,
+                        "txid": out_point.txid.to_string(),
+                        "vout": out_point.vout,
+                        "value": value,
+                    })],
+                    WalletEvent::WatchedSpent { label, out_point, txid } => vec![json!({
+                        "event": "watched_spent",
+                        "wallet": wallet,
+                        "label": label,
+                        "txid": out_point.txid.to_string(),
+                        "vout": out_point.vout,
+                        "spending_txid": txid.to_string(),
+                    })],
+                    WalletEvent::UnexpectedUnvault { out_point, txid } => vec![json!({
+                        "event": "unexpected_unvault",
+                        "wallet": wallet,
+                        "txid": out_point.txid.to_string(),
+                        "vout": out_point.vout,
+                        "spending_txid": txid.to_string(),
+                    })],
+                    WalletEvent::Conflict { .. } => Vec::new(),
+                    WalletEvent::BackendDown { name } => vec![json!({
+                        "event": "backend_down",
+                        "wallet": wallet,
+                        "backend": name,
+                    })],
+                    WalletEvent::BackendUp { name } => vec![json!({
+                        "event": "backend_up",
+                        "wallet": wallet,
+                        "backend": name,
+                    })],
+                    WalletEvent::BackendDivergence { source, height, tip, forked } => {
+                        vec![json!({
+                            "event": "backend_divergence",
+                            "wallet": wallet,
+                            "backend": source,
+                            "height": height,
+                            "tip": tip,
+                            "forked": forked,
+                        })]
+                    }
+                };
+                for payload in payloads {
+                    if queue.send(payload.to_string()).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+    }
+}
+
+// notifications of the pending transactions which have `confirmations` at `tip`
+fn ripe(
+    wallet: &str,
+    pending: &mut Vec<(Sha256dHash, usize, bool)>,
+    tip: usize,
+    confirmations: usize,
+) -> Vec<serde_json::Value> {
+    let mut payloads = Vec::new();
+    pending.retain(|&(txid, block_height, outgoing)| {
+        if tip + 1 < block_height + confirmations {
+            return true;
+        }
+        payloads.push(json!({
+            "event": "confirmed",
+            "wallet": wallet,
+            "txid": txid.to_string(),
+            "direction": if outgoing { "outgoing" } else { "incoming" },
+            "block_height": block_height,
+            "confirmations": tip + 1 - block_height,
+        }));
+        false
+    });
+    payloads
+}
+
+fn deliver(config: &WebhookConfig, payloads: Receiver<String>) {
+    for payload in payloads {
+        let signature = sign(&config.secret, &payload);
+        let mut delay = FIRST_RETRY_DELAY;
+        for attempt in 1..=MAX_ATTEMPTS {
+            match post(&config.url, &payload, &signature) {
+                Ok(()) => {
+                    info!("webhook delivered {}", payload);
+                    break;
+                }
+                Err(e) if attempt == MAX_ATTEMPTS => {
+                    warn!("webhook dropped {} after {} attempts: {}", payload, attempt, e);
+                }
+                Err(e) => {
+                    warn!("webhook attempt {} failed: {}, retry in {:?}", attempt, e, delay);
+                    thread::sleep(delay);
+                    delay = (delay * 2).min(MAX_RETRY_DELAY);
+                }
+            }
+        }
+    }
+}
+
+fn sign(secret: &str, payload: &str) -> String {
+    let mut mac = Hmac::new(Sha256::new(), secret.as_bytes());
+    mac.input(payload.as_bytes());
+    hex::encode(mac.result().code())
+}
+
+fn post(url: &str, payload: &str, signature: &str) -> Result<(), Box<dyn Error>> {
+    let url = http::Url::parse(url)?;
+    let request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nX-Signature: {}\r\nConnection: close\r\n\r\n{}",
+        url.path,
+        url.authority,
+        payload.len(),
+        signature,
+        payload,
+    );
+    let response = http::exchange(&url, &request)?;
+
+    let status = http::status(&response);
+    if !status.starts_with('2') {
+        return Err(From::from(format!("webhook responded with status {:?}", status)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin_hashes::Hash;
+
+    #[test]
+    fn confirmations() {
+        let txid = Sha256dHash::hash(b"tx");
+        let mut pending = vec![(txid, 100, false)];
+        assert!(ripe("default", &mut pending, 100, 3).is_empty());
+        assert!(ripe("default", &mut pending, 101, 3).is_empty());
+
+        let payloads = ripe("default", &mut pending, 102, 3);
+        assert_eq!(payloads.len(), 1);
+        assert_eq!(payloads[0]["confirmations"], 3);
+        assert_eq!(payloads[0]["direction"], "incoming");
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn signature() {
+        // RFC 4231 test case 2
+        let signature = sign("Jefe", "what do ya want for nothing?");
+        assert_eq!(
+            signature,
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+        );
+    }
+}
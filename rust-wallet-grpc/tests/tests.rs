@@ -1,31 +1,44 @@
 use bitcoin_rpc_client::Client;
-use std::{thread, time::Duration, process::Child};
+use std::{thread, time::Duration};
 use rust_wallet_grpc::client::WalletClientWrapper;
+use wallet::testkit::RegtestHarness;
 
 const LAUNCH_SERVER_DELAY_MS: u64 = 3_000;
 const SHUTDOWN_SERVER_DELAY_MS: u64 = 2_000;
+// Shutdown needs an admin token
+const TOKEN: &str = "test";
 
-fn run() -> (WalletClientWrapper, Client, Child) {
-    use wallet::{context::GlobalContext, walletlibrary::{WalletLibraryMode, KeyGenConfig}};
-    use rust_wallet_grpc::server;
+fn run() -> (WalletClientWrapper, Client, RegtestHarness) {
+    use wallet::walletlibrary::{WalletLibraryMode, KeyGenConfig};
+    use rust_wallet_grpc::{server, acl::Acl};
 
-    let context = GlobalContext::default();
-    let bitcoind_process = context.bitcoind("tcp://127.0.0.1:18501".to_owned(), "tcp://127.0.0.1:18502".to_owned()).unwrap();
+    let harness = RegtestHarness::new().unwrap();
 
     let mode = WalletLibraryMode::Create(KeyGenConfig::default());
 
-    let (wallet_context, _mnemonic) = context.default_context(mode).unwrap();
+    let (wallet_context, _mnemonic) = harness.default_context(mode).unwrap();
     let (wallet, bitcoin) = wallet_context.destruct();
-    let _ = thread::spawn(move || server::launch_server_new(wallet, server::DEFAULT_WALLET_RPC_PORT));
+    let acl = Acl::parse(&format!(r#"{{"tokens": {{"{}": {{"permission": "admin"}}}}}}"#, TOKEN));
+    let acl = Some(acl.unwrap());
+    let _ = thread::spawn(move || {
+        let port = server::DEFAULT_WALLET_RPC_PORT;
+        let config = server::ServerConfig {
+            acl,
+            ..Default::default()
+        };
+        server::launch_server_new(wallet, port, config)
+    });
     thread::sleep(Duration::from_millis(LAUNCH_SERVER_DELAY_MS));
-    let wallet = WalletClientWrapper::new(server::DEFAULT_WALLET_RPC_PORT);
+    let wallet =
+        WalletClientWrapper::new(server::DEFAULT_WALLET_RPC_PORT).with_token(TOKEN.to_owned());
 
-    (wallet, bitcoin, bitcoind_process)
+    (wallet, bitcoin, harness)
 }
 
-fn shutdown(client: WalletClientWrapper, mut bitcoin_process: Child) {
+// bitcoind is stopped along with the harness
+fn shutdown(client: WalletClientWrapper, harness: RegtestHarness) {
     client.shutdown();
-    bitcoin_process.kill().unwrap();
+    drop(harness);
     thread::sleep(Duration::from_millis(SHUTDOWN_SERVER_DELAY_MS));
 }
 
@@ -36,7 +49,7 @@ fn basic() {
     use bitcoin_rpc_client::RpcApi;
     use rust_wallet_grpc::walletrpc::AddressType;
 
-    let (wallet, bitcoin, bitcoin_process) = run();
+    let (wallet, bitcoin, harness) = run();
 
     let address = {
         let a = wallet.new_address(AddressType::P2WKH);
@@ -49,5 +62,5 @@ fn basic() {
     assert_eq!(balance, 50_0000_0000);
 
     let _ = bitcoin;
-    shutdown(wallet, bitcoin_process);
+    shutdown(wallet, harness);
 }
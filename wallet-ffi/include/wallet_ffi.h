@@ -0,0 +1,77 @@
+/*
+ * C ABI of rust-wallet, see wallet-ffi/src/lib.rs for the documentation of the functions.
+ *
+ * Every function but the free ones returns a WalletErrorCode, the message of the last
+ * failure of the calling thread is wallet_last_error_message(). Strings returned by the
+ * wallet are released with wallet_string_free().
+ */
+#ifndef WALLET_FFI_H
+#define WALLET_FFI_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define WALLET_NETWORK_BITCOIN 0
+#define WALLET_NETWORK_TESTNET 1
+#define WALLET_NETWORK_REGTEST 2
+
+#define WALLET_BACKEND_ELECTRUM 0
+#define WALLET_BACKEND_BITCOIND 1
+
+#define WALLET_ADDRESS_P2PKH 0
+#define WALLET_ADDRESS_P2SHWH 1
+#define WALLET_ADDRESS_P2WKH 2
+
+typedef enum WalletErrorCode {
+    WALLET_OK = 0,
+    WALLET_UNKNOWN = 1,
+    WALLET_INVALID_ARGUMENT = 2,
+    WALLET_NOT_FOUND = 3,
+    WALLET_ALREADY_EXISTS = 4,
+    WALLET_INSUFFICIENT_FUNDS = 5,
+    WALLET_POLICY_VIOLATION = 6,
+    WALLET_BACKEND_UNAVAILABLE = 7,
+    WALLET_BACKEND_REJECTED = 8,
+    WALLET_WATCH_ONLY = 9,
+    WALLET_PANIC = 10,
+} WalletErrorCode;
+
+typedef struct WalletFfiConfig {
+    const char *db_path;
+    uint32_t network;
+    uint32_t backend;
+    const char *backend_address;
+    const char *rpc_user;
+    const char *rpc_password;
+} WalletFfiConfig;
+
+typedef struct WalletHandle WalletHandle;
+
+WalletErrorCode wallet_create(const WalletFfiConfig *config, const char *passphrase,
+                              const char *salt, uint32_t mnemonic_words,
+                              WalletHandle **out_wallet, char **out_mnemonic);
+WalletErrorCode wallet_recover(const WalletFfiConfig *config, const char *mnemonic,
+                               const char *passphrase, const char *salt,
+                               WalletHandle **out_wallet);
+WalletErrorCode wallet_open(const WalletFfiConfig *config, const char *passphrase,
+                            const char *salt, WalletHandle **out_wallet);
+void wallet_free(WalletHandle *wallet);
+
+WalletErrorCode wallet_new_address(const WalletHandle *wallet, uint32_t address_type,
+                                   char **out_address);
+WalletErrorCode wallet_balance(const WalletHandle *wallet, uint64_t *out_balance);
+WalletErrorCode wallet_send(const WalletHandle *wallet, const char *address, uint64_t amount,
+                            char **out_txid);
+WalletErrorCode wallet_sync(const WalletHandle *wallet);
+
+char *wallet_last_error_message(void);
+void wallet_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,546 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # C ABI of the wallet
+//!
+//! iOS and Android apps embed the wallet through these functions instead of talking to the
+//! gRPC daemon, `include/wallet_ffi.h` declares them. A wallet is an opaque handle which may
+//! be used from any thread, calls on it are serialized. Every call returns a `WalletErrorCode`,
+//! the message of the last failure of the calling thread is `wallet_last_error_message`.
+//! Strings returned to the caller are released with `wallet_string_free`, which wipes them
+//! first since some of them are mnemonics
+//!
+use bitcoin::network::constants::Network;
+use wallet::{
+    account::AccountAddressType,
+    context::GlobalContext,
+    error::WalletError,
+    interface::Wallet,
+    keyfactory::MasterKeyEntropy,
+    mnemonic::Mnemonic,
+    walletlibrary::{DecryptConfig, KeyGenConfig, SendOptions, SendOutcome, WalletLibraryMode},
+};
+use zeroize::Zeroize;
+
+use std::{
+    cell::RefCell,
+    error::Error,
+    ffi::{CStr, CString},
+    fmt,
+    net::ToSocketAddrs,
+    os::raw::c_char,
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    ptr,
+    sync::Mutex,
+};
+
+pub const WALLET_NETWORK_BITCOIN: u32 = 0;
+pub const WALLET_NETWORK_TESTNET: u32 = 1;
+pub const WALLET_NETWORK_REGTEST: u32 = 2;
+
+pub const WALLET_BACKEND_ELECTRUM: u32 = 0;
+pub const WALLET_BACKEND_BITCOIND: u32 = 1;
+
+pub const WALLET_ADDRESS_P2PKH: u32 = 0;
+pub const WALLET_ADDRESS_P2SHWH: u32 = 1;
+pub const WALLET_ADDRESS_P2WKH: u32 = 2;
+
+/// outcome of a call, the numbers are stable across versions
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WalletErrorCode {
+    Ok = 0,
+    Unknown = 1,
+    InvalidArgument = 2,
+    /// no wallet in the database directory, or the handle is gone
+    NotFound = 3,
+    AlreadyExists = 4,
+    InsufficientFunds = 5,
+    /// the spending policy refuses the payment or wants it approved
+    PolicyViolation = 6,
+    /// the backend couldn't be reached, the call may succeed later
+    BackendUnavailable = 7,
+    BackendRejected = 8,
+    WatchOnly = 9,
+    /// a bug in the wallet, the handle should not be used any more
+    Panic = 10,
+}
+
+/// where a wallet keeps its data and the backend it syncs with
+#[repr(C)]
+pub struct WalletFfiConfig {
+    /// directory of the database of the wallet
+    pub db_path: *const c_char,
+    /// `WALLET_NETWORK_*`
+    pub network: u32,
+    /// `WALLET_BACKEND_*`
+    pub backend: u32,
+    /// host:port of the electrum server or of the rpc of bitcoind
+    pub backend_address: *const c_char,
+    /// rpc credentials of bitcoind, NULL for an electrum server
+    pub rpc_user: *const c_char,
+    pub rpc_password: *const c_char,
+}
+
+/// a wallet opened by `wallet_create`, `wallet_recover` or `wallet_open`
+pub struct WalletHandle {
+    wallet: Mutex<Box<dyn Wallet + Send>>,
+}
+
+// a failure of the arguments or the database directory rather than of the wallet
+#[derive(Debug)]
+struct FfiError {
+    code: WalletErrorCode,
+    message: String,
+}
+
+impl fmt::Display for FfiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for FfiError {}
+
+fn ffi_error(code: WalletErrorCode, message: &str) -> Box<dyn Error> {
+    Box::new(FfiError {
+        code,
+        message: message.to_owned(),
+    })
+}
+
+fn error_code(e: &(dyn Error + 'static)) -> WalletErrorCode {
+    if let Some(e) = e.downcast_ref::<FfiError>() {
+        return e.code;
+    }
+    match e.downcast_ref::<WalletError>() {
+        Some(&WalletError::IO(_)) | Some(&WalletError::BackendUnavailable(_)) => {
+            WalletErrorCode::BackendUnavailable
+        }
+        Some(&WalletError::BackendRejected(_)) => WalletErrorCode::BackendRejected,
+        Some(&WalletError::HasNoWalletInDatabase) => WalletErrorCode::NotFound,
+        Some(&WalletError::InsufficientFunds) => WalletErrorCode::InsufficientFunds,
+        Some(&WalletError::TransactionLimitExceeded(_))
+        | Some(&WalletError::DailyLimitExceeded(_))
+        | Some(&WalletError::DestinationNotAllowed(_))
+        | Some(&WalletError::ApprovalRequired(_)) => WalletErrorCode::PolicyViolation,
+        Some(&WalletError::WatchOnly) => WalletErrorCode::WatchOnly,
+        Some(&WalletError::UnknownMnemonicWord)
+        | Some(&WalletError::InvalidMnemonicLength)
+        | Some(&WalletError::InvalidMnemonicData)
+        | Some(&WalletError::MnemonicChecksumNotMatch)
+        | Some(&WalletError::InvalidPaymentCode)
+        | Some(&WalletError::InvalidWatchOnlyKeys)
+        | Some(&WalletError::InvalidQrPart)
+        | Some(&WalletError::WrongPassphrase)
+        | Some(&WalletError::DatabaseHoldsOtherWallet)
+        | Some(&WalletError::DatabaseNetworkMismatch(_))
+        | Some(&WalletError::InvalidAddress(_))
+        | Some(&WalletError::AddressNetworkMismatch(_)) => WalletErrorCode::InvalidArgument,
+        _ => WalletErrorCode::Unknown,
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None);
+}
+
+fn set_last_error(message: String) {
+    // a message can't hold a NUL for C, it is cut there
+    let message = message.split('\0').next().unwrap_or_default().to_owned();
+    LAST_ERROR.with(|last| *last.borrow_mut() = CString::new(message).ok());
+}
+
+// run `f` without letting a panic unwind into the caller, which is undefined behavior
+fn call<F>(f: F) -> WalletErrorCode
+where
+    F: FnOnce() -> Result<(), Box<dyn Error>>,
+{
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => {
+            LAST_ERROR.with(|last| *last.borrow_mut() = None);
+            WalletErrorCode::Ok
+        }
+        Ok(Err(e)) => {
+            set_last_error(e.to_string());
+            error_code(&*e)
+        }
+        Err(_) => {
+            set_last_error("the wallet panicked".to_owned());
+            WalletErrorCode::Panic
+        }
+    }
+}
+
+unsafe fn str_arg<'a>(arg: *const c_char, name: &str) -> Result<&'a str, Box<dyn Error>> {
+    opt_str_arg(arg, name)?
+        .ok_or_else(|| ffi_error(WalletErrorCode::InvalidArgument, &format!("{} is NULL", name)))
+}
+
+unsafe fn opt_str_arg<'a>(
+    arg: *const c_char,
+    name: &str,
+) -> Result<Option<&'a str>, Box<dyn Error>> {
+    if arg.is_null() {
+        return Ok(None);
+    }
+    CStr::from_ptr(arg).to_str().map(Some).map_err(|_| {
+        ffi_error(WalletErrorCode::InvalidArgument, &format!("{} is not UTF-8", name))
+    })
+}
+
+unsafe fn wallet_arg<'a>(wallet: *const WalletHandle) -> Result<&'a WalletHandle, Box<dyn Error>> {
+    wallet.as_ref().ok_or_else(|| ffi_error(WalletErrorCode::InvalidArgument, "wallet is NULL"))
+}
+
+// checked before anything is done that the caller couldn't get the result of
+fn out_arg<T>(out: *mut T, name: &str) -> Result<(), Box<dyn Error>> {
+    if out.is_null() {
+        return Err(ffi_error(WalletErrorCode::InvalidArgument, &format!("{} is NULL", name)));
+    }
+    Ok(())
+}
+
+unsafe fn set_out<T>(out: *mut T, value: T) -> Result<(), Box<dyn Error>> {
+    if out.is_null() {
+        return Err(ffi_error(WalletErrorCode::InvalidArgument, "output pointer is NULL"));
+    }
+    ptr::write(out, value);
+    Ok(())
+}
+
+unsafe fn set_out_string(out: *mut *mut c_char, value: String) -> Result<(), Box<dyn Error>> {
+    let value = CString::new(value)?;
+    set_out(out, value.into_raw())
+}
+
+// the wallet of `config`, `creates` tells whether the database directory has to be new
+unsafe fn open_wallet(
+    config: *const WalletFfiConfig,
+    mode: WalletLibraryMode,
+    creates: bool,
+) -> Result<(Box<WalletHandle>, Mnemonic), Box<dyn Error>> {
+    let config = config
+        .as_ref()
+        .ok_or_else(|| ffi_error(WalletErrorCode::InvalidArgument, "config is NULL"))?;
+    let db_path = str_arg(config.db_path, "db_path")?;
+    match (creates, Path::new(db_path).exists()) {
+        (true, true) => {
+            let message = format!("a wallet exists in {}", db_path);
+            return Err(ffi_error(WalletErrorCode::AlreadyExists, &message));
+        }
+        (false, false) => {
+            let message = format!("no wallet exists in {}", db_path);
+            return Err(ffi_error(WalletErrorCode::NotFound, &message));
+        }
+        _ => (),
+    }
+    let network = match config.network {
+        WALLET_NETWORK_BITCOIN => Network::Bitcoin,
+        WALLET_NETWORK_TESTNET => Network::Testnet,
+        WALLET_NETWORK_REGTEST => Network::Regtest,
+        network => {
+            let message = format!("unknown network {}", network);
+            return Err(ffi_error(WalletErrorCode::InvalidArgument, &message));
+        }
+    };
+    // apps are given host names of servers rather than addresses
+    let address = str_arg(config.backend_address, "backend_address")?
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| ffi_error(WalletErrorCode::InvalidArgument, "backend_address is unknown"))?;
+    let user = opt_str_arg(config.rpc_user, "rpc_user")?.unwrap_or_default();
+    let password = opt_str_arg(config.rpc_password, "rpc_password")?.unwrap_or_default();
+
+    let db_path = Some(db_path.to_owned());
+    let (user, password) = (user.to_owned(), password.to_owned());
+    let (wallet_context, mnemonic) = match config.backend {
+        WALLET_BACKEND_ELECTRUM => {
+            GlobalContext::new(network, user, password, db_path, None, Some(address))
+                .electrs_context(mode)?
+        }
+        WALLET_BACKEND_BITCOIND => {
+            GlobalContext::new(network, user, password, db_path, Some(address), None)
+                .default_context(mode)?
+        }
+        backend => {
+            let message = format!("unknown backend {}", backend);
+            return Err(ffi_error(WalletErrorCode::InvalidArgument, &message));
+        }
+    };
+    let (wallet, _) = wallet_context.destruct();
+    Ok((Box::new(WalletHandle { wallet: Mutex::new(wallet) }), mnemonic))
+}
+
+/// create a wallet of a new seed of `mnemonic_words` words in the empty `config.db_path`,
+/// `passphrase` encrypts the seed in the database, `salt` is its BIP39 passphrase.
+/// The mnemonic is returned for the user to write down
+///
+/// # Safety
+/// the strings are NUL terminated, the output pointers are valid
+#[no_mangle]
+pub unsafe extern "C" fn wallet_create(
+    config: *const WalletFfiConfig,
+    passphrase: *const c_char,
+    salt: *const c_char,
+    mnemonic_words: u32,
+    out_wallet: *mut *mut WalletHandle,
+    out_mnemonic: *mut *mut c_char,
+) -> WalletErrorCode {
+    call(|| {
+        // a wallet created without them would be lost, or its mnemonic left unwiped
+        out_arg(out_wallet, "out_wallet")?;
+        out_arg(out_mnemonic, "out_mnemonic")?;
+        let entropy = MasterKeyEntropy::from_word_count(mnemonic_words as usize).ok_or_else(|| {
+            ffi_error(WalletErrorCode::InvalidArgument, "mnemonic has 12, 18 or 24 words")
+        })?;
+        let passphrase = str_arg(passphrase, "passphrase")?.to_owned();
+        let salt = str_arg(salt, "salt")?.to_owned();
+        let mode = WalletLibraryMode::Create(KeyGenConfig::new(entropy, passphrase, salt));
+        let (wallet, mnemonic) = open_wallet(config, mode, true)?;
+        set_out_string(out_mnemonic, mnemonic.to_string())?;
+        set_out(out_wallet, Box::into_raw(wallet))
+    })
+}
+
+/// create a wallet of the seed of `mnemonic` in the empty `config.db_path`,
+/// the coins of the seed are found by the next `wallet_sync`
+///
+/// # Safety
+/// the strings are NUL terminated, the output pointer is valid
+#[no_mangle]
+pub unsafe extern "C" fn wallet_recover(
+    config: *const WalletFfiConfig,
+    mnemonic: *const c_char,
+    passphrase: *const c_char,
+    salt: *const c_char,
+    out_wallet: *mut *mut WalletHandle,
+) -> WalletErrorCode {
+    call(|| {
+        let mnemonic = Mnemonic::from(str_arg(mnemonic, "mnemonic")?.trim())?;
+        let passphrase = str_arg(passphrase, "passphrase")?.to_owned();
+        let salt = str_arg(salt, "salt")?.to_owned();
+        let mode =
+            WalletLibraryMode::RecoverFromMnemonic(mnemonic, DecryptConfig::new(passphrase, salt));
+        let (wallet, _) = open_wallet(config, mode, true)?;
+        set_out(out_wallet, Box::into_raw(wallet))
+    })
+}
+
+/// open the wallet in `config.db_path` created before
+///
+/// # Safety
+/// the strings are NUL terminated, the output pointer is valid
+#[no_mangle]
+pub unsafe extern "C" fn wallet_open(
+    config: *const WalletFfiConfig,
+    passphrase: *const c_char,
+    salt: *const c_char,
+    out_wallet: *mut *mut WalletHandle,
+) -> WalletErrorCode {
+    call(|| {
+        let passphrase = str_arg(passphrase, "passphrase")?.to_owned();
+        let salt = str_arg(salt, "salt")?.to_owned();
+        let mode = WalletLibraryMode::Decrypt(DecryptConfig::new(passphrase, salt));
+        let (wallet, _) = open_wallet(config, mode, false)?;
+        set_out(out_wallet, Box::into_raw(wallet))
+    })
+}
+
+/// close a wallet, the handle is not used afterwards
+///
+/// # Safety
+/// `wallet` is NULL or a handle which was not freed yet
+#[no_mangle]
+pub unsafe extern "C" fn wallet_free(wallet: *mut WalletHandle) {
+    if !wallet.is_null() {
+        drop(Box::from_raw(wallet));
+    }
+}
+
+/// a new receiving address of the account of `address_type`, a `WALLET_ADDRESS_*`
+///
+/// # Safety
+/// `wallet` is a handle of `wallet_open`, the output pointer is valid
+#[no_mangle]
+pub unsafe extern "C" fn wallet_new_address(
+    wallet: *const WalletHandle,
+    address_type: u32,
+    out_address: *mut *mut c_char,
+) -> WalletErrorCode {
+    call(|| {
+        let address_type = match address_type {
+            WALLET_ADDRESS_P2PKH => AccountAddressType::P2PKH,
+            WALLET_ADDRESS_P2SHWH => AccountAddressType::P2SHWH,
+            WALLET_ADDRESS_P2WKH => AccountAddressType::P2WKH,
+            _ => return Err(ffi_error(WalletErrorCode::InvalidArgument, "unknown address type")),
+        };
+        let mut wallet = wallet_arg(wallet)?.wallet.lock().unwrap();
+        let address = wallet.wallet_lib_mut().new_address(address_type)?;
+        set_out_string(out_address, address)
+    })
+}
+
+/// satoshis of the coins of the wallet
+///
+/// # Safety
+/// `wallet` is a handle of `wallet_open`, the output pointer is valid
+#[no_mangle]
+pub unsafe extern "C" fn wallet_balance(
+    wallet: *const WalletHandle,
+    out_balance: *mut u64,
+) -> WalletErrorCode {
+    call(|| {
+        let balance = wallet_arg(wallet)?.wallet.lock().unwrap().wallet_lib().wallet_balance();
+        set_out(out_balance, balance)
+    })
+}
+
+/// pay `amount` satoshis to `address` and broadcast the transaction,
+/// its id is returned as hex as displayed by block explorers. A payment above the approval
+/// threshold of the spending policy fails as a policy violation, it waits for approval
+///
+/// # Safety
+/// `wallet` is a handle of `wallet_open`, the string is NUL terminated,
+/// the output pointer is valid
+#[no_mangle]
+pub unsafe extern "C" fn wallet_send(
+    wallet: *const WalletHandle,
+    address: *const c_char,
+    amount: u64,
+    out_txid: *mut *mut c_char,
+) -> WalletErrorCode {
+    call(|| {
+        let address = str_arg(address, "address")?.to_owned();
+        let mut wallet = wallet_arg(wallet)?.wallet.lock().unwrap();
+        let outcome = wallet.send_coins(address, amount, true, SendOptions::default())?;
+        match outcome {
+            SendOutcome::Sent(sent) => set_out_string(out_txid, sent.txid().to_string()),
+            SendOutcome::ApprovalRequired(id) => Err(Box::new(WalletError::ApprovalRequired(id))),
+        }
+    })
+}
+
+/// process the blocks and transactions of the backend the wallet didn't see yet,
+/// it blocks until the wallet is at the tip
+///
+/// # Safety
+/// `wallet` is a handle of `wallet_open`
+#[no_mangle]
+pub unsafe extern "C" fn wallet_sync(wallet: *const WalletHandle) -> WalletErrorCode {
+    call(|| wallet_arg(wallet)?.wallet.lock().unwrap().sync_with_tip())
+}
+
+/// the message of the last call of this thread which failed, NULL if it succeeded
+#[no_mangle]
+pub extern "C" fn wallet_last_error_message() -> *mut c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow().as_ref().map_or(ptr::null_mut(), |message| message.clone().into_raw())
+    })
+}
+
+/// release a string returned by the wallet, its bytes are wiped
+///
+/// # Safety
+/// `s` is NULL or a string of the wallet which was not freed yet
+#[no_mangle]
+pub unsafe extern "C" fn wallet_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        let mut bytes = CString::from_raw(s).into_bytes();
+        bytes.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        env,
+        ffi::{CStr, CString},
+        ptr,
+    };
+
+    use super::{
+        wallet_balance, wallet_create, wallet_last_error_message, wallet_open, wallet_string_free,
+        WalletErrorCode, WalletFfiConfig, WALLET_BACKEND_ELECTRUM, WALLET_NETWORK_REGTEST,
+    };
+
+    fn last_error() -> String {
+        let message = wallet_last_error_message();
+        assert!(!message.is_null());
+        let text = unsafe { CStr::from_ptr(message) }.to_str().unwrap().to_owned();
+        unsafe { wallet_string_free(message) };
+        text
+    }
+
+    #[test]
+    fn errors() {
+        let secret = CString::new("").unwrap();
+        let mut wallet = ptr::null_mut();
+        let code =
+            unsafe { wallet_open(ptr::null(), secret.as_ptr(), secret.as_ptr(), &mut wallet) };
+        assert_eq!(code, WalletErrorCode::InvalidArgument);
+        assert_eq!(last_error(), "config is NULL");
+        assert!(wallet.is_null());
+
+        let db_path = CString::new("/nonexistent/wallet").unwrap();
+        let address = CString::new("127.0.0.1:60401").unwrap();
+        let config = WalletFfiConfig {
+            db_path: db_path.as_ptr(),
+            network: WALLET_NETWORK_REGTEST,
+            backend: WALLET_BACKEND_ELECTRUM,
+            backend_address: address.as_ptr(),
+            rpc_user: ptr::null(),
+            rpc_password: ptr::null(),
+        };
+        let code = unsafe { wallet_open(&config, secret.as_ptr(), secret.as_ptr(), &mut wallet) };
+        assert_eq!(code, WalletErrorCode::NotFound);
+
+        let mut balance = 0;
+        let code = unsafe { wallet_balance(ptr::null(), &mut balance) };
+        assert_eq!(code, WalletErrorCode::InvalidArgument);
+        assert_eq!(last_error(), "wallet is NULL");
+        unsafe { wallet_string_free(ptr::null_mut()) };
+    }
+
+    #[test]
+    fn create_without_outputs() {
+        let db_path = env::temp_dir().join("rust_wallet_ffi_create_without_outputs");
+        let c_db_path = CString::new(db_path.to_str().unwrap()).unwrap();
+        let address = CString::new("127.0.0.1:60401").unwrap();
+        let config = WalletFfiConfig {
+            db_path: c_db_path.as_ptr(),
+            network: WALLET_NETWORK_REGTEST,
+            backend: WALLET_BACKEND_ELECTRUM,
+            backend_address: address.as_ptr(),
+            rpc_user: ptr::null(),
+            rpc_password: ptr::null(),
+        };
+        let empty = CString::new("").unwrap();
+        let secret = empty.as_ptr();
+        let (mut wallet, mut mnemonic) = (ptr::null_mut(), ptr::null_mut());
+
+        let no_mnemonic = ptr::null_mut();
+        let code = unsafe { wallet_create(&config, secret, secret, 24, &mut wallet, no_mnemonic) };
+        assert_eq!(code, WalletErrorCode::InvalidArgument);
+        assert_eq!(last_error(), "out_mnemonic is NULL");
+        let no_wallet = ptr::null_mut();
+        let code = unsafe { wallet_create(&config, secret, secret, 24, no_wallet, &mut mnemonic) };
+        assert_eq!(code, WalletErrorCode::InvalidArgument);
+        assert_eq!(last_error(), "out_wallet is NULL");
+        assert!(wallet.is_null() && mnemonic.is_null());
+        assert!(!db_path.exists());
+    }
+}
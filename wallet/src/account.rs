@@ -19,8 +19,12 @@
 //!
 use bitcoin::{
     util::{
-        bip32::{ExtendedPubKey, ExtendedPrivKey, ChildNumber, Error as Bip32Error},
+        bip32::{
+            ExtendedPubKey, ExtendedPrivKey, ChildNumber, DerivationPath, Fingerprint,
+            Error as Bip32Error,
+        },
         address::Address,
+        base58,
     },
     blockdata::{
         script::Script,
@@ -34,12 +38,28 @@ use secp256k1::Secp256k1;
 use serde::{Serialize, Deserialize};
 
 use super::DB;
+use super::keyfactory::wipe_extended_private_key;
 
 use std::{
-    sync::{Arc, RwLock},
-    collections::HashMap,
+    sync::{Arc, Mutex, RwLock},
+    collections::{HashMap, BTreeMap},
+    error::Error,
+    fmt,
+    hash::Hash,
+    str::FromStr,
 };
 
+/// indexes from it on are hardened
+const HARDENED: u32 = 1 << 31;
+/// wallet file format of Electrum 3.3, later versions upgrade it when they open the file
+const ELECTRUM_SEED_VERSION: u32 = 18;
+/// keys Bitcoin Core derives ahead of the next index of a descriptor by default
+const CORE_KEYPOOL_SIZE: u32 = 1000;
+/// public keys an account keeps after deriving them
+const KEY_CACHE_SIZE: usize = 1024;
+/// scripts an account keeps the key paths of, the others are looked up in the database
+const SCRIPT_CACHE_SIZE: usize = 1024;
+
 /// Address type an account is using
 #[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
 pub enum AccountAddressType {
@@ -63,6 +83,17 @@ impl<'a> From<&'a str> for AccountAddressType {
     }
 }
 
+impl AccountAddressType {
+    /// the name `From<&str>` takes
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccountAddressType::P2PKH => "p2pkh",
+            AccountAddressType::P2SHWH => "p2shwh",
+            AccountAddressType::P2WKH => "p2wkh",
+        }
+    }
+}
+
 impl From<AccountAddressType> for usize {
     fn from(val: AccountAddressType) -> usize {
         match val {
@@ -84,7 +115,7 @@ impl Into<AccountAddressType> for usize {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum AddressChain {
     External,
     Internal,
@@ -99,7 +130,110 @@ impl Into<u32> for AddressChain {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// where the key of an account is derived from the master key,
+/// m/`purpose`'/`coin_type`'/`account`', the addresses follow at /chain/index
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DerivationScheme {
+    pub purpose: u32,
+    pub coin_type: u32,
+    pub account: u32,
+}
+
+impl DerivationScheme {
+    /// purposes of BIP44, BIP49, BIP84 and BIP86, the address type alone decides the scripts
+    pub fn new(purpose: u32, coin_type: u32, account: u32) -> Result<Self, Box<dyn Error>> {
+        if ![44, 49, 84, 86].contains(&purpose) {
+            return Err(From::from(format!("unsupported purpose {}", purpose)));
+        }
+        if coin_type >= HARDENED || account >= HARDENED {
+            return Err(From::from("coin type and account have to be below 2^31"));
+        }
+        Ok(DerivationScheme {
+            purpose,
+            coin_type,
+            account,
+        })
+    }
+
+    /// account 0 at the purpose of the address type, the scheme of wallets which didn't
+    /// configure another one
+    pub fn default_for(
+        addr_type: &AccountAddressType,
+        network: Network,
+        mode: DerivationMode,
+    ) -> Self {
+        let purpose = match addr_type {
+            AccountAddressType::P2PKH => 44,
+            AccountAddressType::P2SHWH => 49,
+            AccountAddressType::P2WKH => 84,
+        };
+        DerivationScheme {
+            purpose,
+            coin_type: coin_type(network, mode),
+            account: 0,
+        }
+    }
+
+    pub fn path(&self) -> Vec<ChildNumber> {
+        vec![
+            ChildNumber::Hardened { index: self.purpose },
+            ChildNumber::Hardened { index: self.coin_type },
+            ChildNumber::Hardened { index: self.account },
+        ]
+    }
+}
+
+/// how the coin type of the accounts is chosen
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DerivationMode {
+    /// SLIP44, every test network has coin type 1 like in Electrum and Bitcoin Core,
+    /// so a mnemonic restored in them finds the coins
+    Standard,
+    /// regtest has coin type 2, wallets created before the standard one keep it
+    Legacy,
+}
+
+impl Default for DerivationMode {
+    fn default() -> Self {
+        DerivationMode::Standard
+    }
+}
+
+/// coin type of the network
+pub fn coin_type(network: Network, mode: DerivationMode) -> u32 {
+    match (network, mode) {
+        (Network::Bitcoin, _) => 0,
+        (Network::Regtest, DerivationMode::Legacy) => 2,
+        (Network::Testnet, _) | (Network::Regtest, DerivationMode::Standard) => 1,
+    }
+}
+
+impl fmt::Display for DerivationScheme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "m/{}'/{}'/{}'", self.purpose, self.coin_type, self.account)
+    }
+}
+
+impl FromStr for DerivationScheme {
+    type Err = Box<dyn Error>;
+
+    /// `m/84'/0'/1'`, `h` may mark the hardened indexes as well
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut indexes = Vec::new();
+        for part in s.trim_start_matches("m/").split('/') {
+            if !part.ends_with('\'') && !part.ends_with('h') {
+                return Err(From::from(format!("{} is not a hardened index", part)));
+            }
+            indexes.push(part[..part.len() - 1].parse::<u32>()?);
+        }
+        match indexes[..] {
+            [purpose, coin_type, account] => DerivationScheme::new(purpose, coin_type, account),
+            _ => Err(From::from(format!("expected m/purpose'/coin_type'/account', got {}", s))),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct KeyPath {
     addr_chain: AddressChain,
     addr_index: u32,
@@ -112,6 +246,27 @@ impl KeyPath {
             addr_index,
         }
     }
+
+    pub fn addr_chain(&self) -> &AddressChain {
+        &self.addr_chain
+    }
+
+    pub fn addr_index(&self) -> u32 {
+        self.addr_index
+    }
+}
+
+/// an address of the wallet and whether it ever received funds
+#[derive(Debug, Clone)]
+pub struct AddressInfo {
+    pub address: String,
+    pub addr_type: AccountAddressType,
+    pub key_path: KeyPath,
+    /// fingerprint of the master key and the full path of the key of the address
+    pub origin: (Fingerprint, DerivationPath),
+    pub used: bool,
+    /// set by `WalletLibraryInterface::set_address_label`
+    pub label: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -144,24 +299,83 @@ impl Utxo {
     }
 }
 
-/// a TREZOR compatible account
+/// the entries used last, a coin or a change output is mostly looked up
+/// right after its key was derived
+struct LruCache<K, V> {
+    entries: HashMap<K, (V, u64)>,
+    // the keys of the entries by their last use, the first one is evicted
+    order: BTreeMap<u64, K>,
+    tick: u64,
+    capacity: usize,
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> LruCache<K, V> {
+    fn new(capacity: usize) -> Self {
+        LruCache {
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+            capacity,
+        }
+    }
+
+    fn get(&mut self, key: &K) -> Option<V> {
+        let entry = self.entries.get_mut(key)?;
+        self.tick += 1;
+        self.order.remove(&entry.1);
+        self.order.insert(self.tick, key.clone());
+        entry.1 = self.tick;
+        Some(entry.0.clone())
+    }
+
+    // the least recently used entry makes room for a new one
+    fn insert(&mut self, key: K, value: V) {
+        self.tick += 1;
+        match self.entries.insert(key.clone(), (value, self.tick)) {
+            Some((_, used)) => {
+                self.order.remove(&used);
+            }
+            None if self.entries.len() > self.capacity => {
+                let oldest = self.order.keys().next().cloned();
+                if let Some(oldest) = oldest.and_then(|used| self.order.remove(&used)) {
+                    self.entries.remove(&oldest);
+                }
+            }
+            None => (),
+        }
+        self.order.insert(self.tick, key);
+    }
+}
+
+/// a TREZOR compatible account, its keys are derived when they are needed,
+/// only the next index of each chain is kept along with the scripts of the derived keys
 pub struct Account {
-    account_key: ExtendedPrivKey,
+    /// `None` in a watch-only wallet
+    account_key: Option<ExtendedPrivKey>,
+    account_xpub: ExtendedPubKey,
+    /// keys of the external and the internal chain, /0 and /1 below `account_xpub`
+    chain_xpubs: [ExtendedPubKey; 2],
     pub address_type: AccountAddressType,
     network: Network,
+    /// the path of `account_xpub`
+    pub scheme: DerivationScheme,
+    master_fingerprint: Fingerprint,
 
     external_index: u32,
     internal_index: u32,
-    pub external_pk_list: Vec<PublicKey>,
-    pub internal_pk_list: Vec<PublicKey>,
-
-    pub btc_address_list: Vec<String>,
+    key_cache: Mutex<LruCache<KeyPath, PublicKey>>,
+    /// the scripts of all derived keys are in the script index of the database,
+    /// the ones used last are kept here
+    script_cache: Mutex<LruCache<Script, KeyPath>>,
 
     pub utxo_list: HashMap<OutPoint, Utxo>,
+
+    /// scripts which received funds, with the output that paid to them first
+    pub used_scripts: HashMap<Script, OutPoint>,
     db: Arc<RwLock<DB>>,
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
 pub struct SecretKeyHelper {
     pub addr_type: AccountAddressType,
     addr_chain: AddressChain,
@@ -183,25 +397,80 @@ impl Account {
         account_key: ExtendedPrivKey,
         address_type: AccountAddressType,
         network: Network,
+        scheme: DerivationScheme,
+        master_fingerprint: Fingerprint,
         db: Arc<RwLock<DB>>,
     ) -> Account {
+        let account_xpub = ExtendedPubKey::from_private(&Secp256k1::new(), &account_key);
+        let mut account = Account::watch_only(
+            account_xpub,
+            address_type,
+            network,
+            scheme,
+            master_fingerprint,
+            db,
+        );
+        account.account_key = Some(account_key);
+        account
+    }
+
+    /// an account which derives its public keys only, nothing can be signed with it
+    pub fn watch_only(
+        account_xpub: ExtendedPubKey,
+        address_type: AccountAddressType,
+        network: Network,
+        scheme: DerivationScheme,
+        master_fingerprint: Fingerprint,
+        db: Arc<RwLock<DB>>,
+    ) -> Account {
+        let ctx = Secp256k1::new();
+        let chain_xpub = |index| account_xpub.ckd_pub(&ctx, ChildNumber::Normal { index }).unwrap();
+        let chain_xpubs = [chain_xpub(0), chain_xpub(1)];
         Account {
-            account_key,
+            account_key: None,
+            account_xpub,
+            chain_xpubs,
             address_type,
             network,
+            scheme,
+            master_fingerprint,
 
             external_index: 0,
             internal_index: 0,
-            external_pk_list: Vec::new(),
-            internal_pk_list: Vec::new(),
-
-            btc_address_list: Vec::new(),
+            key_cache: Mutex::new(LruCache::new(KEY_CACHE_SIZE)),
+            script_cache: Mutex::new(LruCache::new(SCRIPT_CACHE_SIZE)),
 
             utxo_list: HashMap::new(),
+
+            used_scripts: HashMap::new(),
             db,
         }
     }
 
+    /// continue the derivation after the keys in the script index,
+    /// the database counts them as they are indexed
+    pub fn restore_indexes(&mut self) {
+        let db = self.db.read().unwrap();
+        let external = db.get_next_index(&self.address_type, &AddressChain::External);
+        let internal = db.get_next_index(&self.address_type, &AddressChain::Internal);
+        self.external_index = self.external_index.max(external);
+        self.internal_index = self.internal_index.max(internal);
+    }
+
+    /// remember that the script received funds in `out_point`,
+    /// false if it already received them in another output
+    pub fn mark_used(&mut self, script: &Script, out_point: OutPoint) -> bool {
+        if let Some(first_funding) = self.used_scripts.get(script) {
+            return *first_funding == out_point;
+        }
+        self.db
+            .write()
+            .unwrap()
+            .put_used_script(script, &self.address_type, &out_point);
+        self.used_scripts.insert(script.clone(), out_point);
+        true
+    }
+
     pub fn get_sk(&self, key_path: &KeyPath) -> PrivateKey {
         let path = &[
             ChildNumber::Normal {
@@ -211,11 +480,124 @@ impl Account {
                 index: key_path.addr_index,
             },
         ];
-        let extended_priv_key = self
+        // the signing paths of the wallet refuse a watch-only wallet before they get here
+        let mut extended_priv_key = self
             .account_key
+            .expect("a watch-only account has no private keys")
             .derive_priv(&Secp256k1::new(), path)
             .unwrap();
-        extended_priv_key.private_key
+        let sk = extended_priv_key.private_key;
+        wipe_extended_private_key(&mut extended_priv_key);
+        sk
+    }
+
+    pub fn get_pk(&self, key_path: &KeyPath) -> PublicKey {
+        let mut key_cache = self.key_cache.lock().unwrap();
+        if let Some(pk) = key_cache.get(key_path) {
+            return pk;
+        }
+        let chain: u32 = key_path.addr_chain.clone().into();
+        let index = ChildNumber::Normal {
+            index: key_path.addr_index,
+        };
+        let pk = self.chain_xpubs[chain as usize]
+            .ckd_pub(&Secp256k1::new(), index)
+            .unwrap()
+            .public_key;
+        key_cache.insert(key_path.clone(), pk);
+        pk
+    }
+
+    /// keys derived on the external chain, in the order of derivation
+    pub fn external_pks(&self) -> Vec<PublicKey> {
+        (0..self.external_index)
+            .map(|i| self.get_pk(&KeyPath::new(AddressChain::External, i)))
+            .collect()
+    }
+
+    pub fn is_watch_only(&self) -> bool {
+        self.account_key.is_none()
+    }
+
+    /// wipe the private key, the account derives public keys only until `unlock`
+    pub fn lock(&mut self) {
+        if let Some(ref mut account_key) = self.account_key {
+            wipe_extended_private_key(account_key);
+        }
+        self.account_key = None;
+    }
+
+    pub fn unlock(&mut self, account_key: ExtendedPrivKey) {
+        self.lock();
+        self.account_key = Some(account_key);
+    }
+
+    /// fingerprint of the master key and the full path of the key at `key_path`,
+    /// other signers of a PSBT find their keys by them
+    pub fn key_origin(&self, key_path: &KeyPath) -> (Fingerprint, DerivationPath) {
+        let mut path = self.scheme.path();
+        path.push(ChildNumber::Normal {
+            index: key_path.addr_chain.clone().into(),
+        });
+        path.push(ChildNumber::Normal {
+            index: key_path.addr_index,
+        });
+        (self.master_fingerprint, DerivationPath::from(path))
+    }
+
+    /// output descriptor of the addresses of `addr_chain` along with the origin of the account
+    /// key, e.g. `wpkh([d34db33f/84'/0'/0']xpub.../0/*)#checksum`
+    pub fn descriptor(&self, addr_chain: AddressChain) -> String {
+        let xpub = self.account_xpub;
+        let chain: u32 = addr_chain.into();
+        let origin = self.scheme.to_string().replacen("m", "", 1);
+        let fingerprint = hex::encode(&self.master_fingerprint[..]);
+        let key = format!("[{}{}]{}/{}/*", fingerprint, origin, xpub, chain);
+        let descriptor = match self.address_type {
+            AccountAddressType::P2PKH => format!("pkh({})", key),
+            AccountAddressType::P2SHWH => format!("sh(wpkh({}))", key),
+            AccountAddressType::P2WKH => format!("wpkh({})", key),
+        };
+        format!("{}#{}", descriptor, descriptor_checksum(&descriptor))
+    }
+
+    /// entry of Bitcoin Core's `importdescriptors` for the chain of the account, the range
+    /// covers the derived keys and a lookahead, a `birthday` of `None` rescans the whole chain
+    pub fn import_descriptor(
+        &self,
+        addr_chain: AddressChain,
+        birthday: Option<u64>,
+    ) -> serde_json::Value {
+        let next_index = match addr_chain {
+            AddressChain::External => self.external_index,
+            AddressChain::Internal => self.internal_index,
+        };
+        serde_json::json!({
+            "desc": self.descriptor(addr_chain.clone()),
+            "timestamp": birthday.unwrap_or(0),
+            "active": true,
+            "internal": addr_chain == AddressChain::Internal,
+            "range": [0, next_index + CORE_KEYPOOL_SIZE],
+            "next_index": next_index,
+        })
+    }
+
+    /// watch-only Electrum wallet file of the account, Electrum derives the addresses itself
+    /// and tells their type by the version of the extended key
+    pub fn electrum_wallet(&self) -> serde_json::Value {
+        let xpub = self.account_xpub;
+        serde_json::json!({
+            "keystore": {
+                "type": "bip32",
+                "xpub": slip132_encode(&xpub, &self.address_type),
+                "root_fingerprint": hex::encode(&self.master_fingerprint[..]),
+                "derivation": self.scheme.to_string(),
+                "pw_hash_version": 1,
+            },
+            "wallet_type": "standard",
+            "seed_version": ELECTRUM_SEED_VERSION,
+            "use_encryption": false,
+        })
     }
 
     pub fn grab_utxo(&mut self, utxo: Utxo) {
@@ -228,16 +610,10 @@ impl Account {
     }
 
     pub fn next_external_pk(&mut self) -> Result<PublicKey, Bip32Error> {
-        let path = &[
-            ChildNumber::Normal { index: 0 }, // TODO(evg): use addr chain enum instead?
-            ChildNumber::Normal {
-                index: self.external_index,
-            },
-        ];
-        let extended_priv_key = self.account_key.derive_priv(&Secp256k1::new(), path)?;
-
-        let extended_pub_key = ExtendedPubKey::from_private(&Secp256k1::new(), &extended_priv_key);
-        self.external_pk_list.push(extended_pub_key.public_key);
+        let index = ChildNumber::Normal {
+            index: self.external_index,
+        };
+        let extended_pub_key = self.chain_xpubs[0].ckd_pub(&Secp256k1::new(), index)?;
 
         // DB BEGIN
         let key = SecretKeyHelper::new(
@@ -251,22 +627,18 @@ impl Account {
             .put_external_public_key(&key, &extended_pub_key.public_key);
         // DB END
 
+        let key_path = KeyPath::new(AddressChain::External, self.external_index);
+        self.index_pk(&extended_pub_key.public_key, key_path);
+
         self.external_index += 1;
         Ok(extended_pub_key.public_key)
     }
 
     pub fn next_internal_pk(&mut self) -> Result<PublicKey, Bip32Error> {
-        let path = &[
-            ChildNumber::Normal { index: 1 },
-            ChildNumber::Normal {
-                index: self.internal_index,
-            },
-        ];
-        self.internal_index += 1;
-        let extended_priv_key = self.account_key.derive_priv(&Secp256k1::new(), path)?;
-
-        let extended_pub_key = ExtendedPubKey::from_private(&Secp256k1::new(), &extended_priv_key);
-        self.internal_pk_list.push(extended_pub_key.public_key);
+        let index = ChildNumber::Normal {
+            index: self.internal_index,
+        };
+        let extended_pub_key = self.chain_xpubs[1].ckd_pub(&Secp256k1::new(), index)?;
 
         // DB BEGIN
         let key = SecretKeyHelper::new(
@@ -280,9 +652,128 @@ impl Account {
             .put_internal_public_key(&key, &extended_pub_key.public_key);
         // DB END
 
+        let key_path = KeyPath::new(AddressChain::Internal, self.internal_index);
+        self.index_pk(&extended_pub_key.public_key, key_path);
+
+        self.internal_index += 1;
         Ok(extended_pub_key.public_key)
     }
 
+    fn index_pk(&mut self, pk: &PublicKey, key_path: KeyPath) {
+        let script = self.script_from_pk(pk);
+        self.db
+            .write()
+            .unwrap()
+            .put_script_index(&script, &self.address_type, &key_path);
+        self.key_cache.lock().unwrap().insert(key_path.clone(), *pk);
+        self.script_cache.lock().unwrap().insert(script, key_path);
+    }
+
+    /// the path of the key `script` pays to, `None` if the account didn't derive it
+    pub fn key_path(&self, script: &Script) -> Option<KeyPath> {
+        if let Some(key_path) = self.script_cache.lock().unwrap().get(script) {
+            return Some(key_path);
+        }
+        let (addr_type, key_path) = self.db.read().unwrap().get_script_index_entry(script)?;
+        if addr_type != self.address_type {
+            return None;
+        }
+        self.script_cache.lock().unwrap().insert(script.clone(), key_path.clone());
+        Some(key_path)
+    }
+
+    /// whether `script` pays to a key of the account
+    pub fn is_mine(&self, script: &Script) -> bool {
+        self.key_path(script).is_some()
+    }
+
+    /// the scripts of all derived keys with their paths, read from the database
+    pub fn scripts(&self) -> Vec<(Script, KeyPath)> {
+        self.db
+            .read()
+            .unwrap()
+            .get_script_index()
+            .into_iter()
+            .filter(|(_, (addr_type, _))| *addr_type == self.address_type)
+            .map(|(script, (_, key_path))| (script, key_path))
+            .collect()
+    }
+
+    /// index a public key stored before the script index was introduced,
+    /// change keys used to be stored with an index off by one, so the derivation is verified
+    pub fn reindex_pk(&mut self, key_helper: &SecretKeyHelper, pk: &PublicKey) {
+        let candidates = [key_helper.index, key_helper.index.saturating_sub(1)];
+        for &index in candidates.iter() {
+            let key_path = KeyPath::new(key_helper.addr_chain.clone(), index);
+            if self.get_pk(&key_path) == *pk {
+                self.index_pk(pk, key_path);
+                return;
+            }
+        }
+    }
+
+    /// paths of all keys the account should hold, up to the highest index derived,
+    /// stored or paid to on each chain
+    pub fn derived_paths(&self) -> Vec<KeyPath> {
+        let (mut external_count, mut internal_count) = (self.external_index, self.internal_index);
+        let db = self.db.read().unwrap();
+        let stored = db.get_external_public_key_list().into_iter();
+        let stored = stored.chain(db.get_internal_public_key_list()).map(|(key, _)| key);
+        let (mut external_stored, mut internal_stored) = (0, 0);
+        for key in stored.filter(|key| key.addr_type == self.address_type) {
+            match key.addr_chain {
+                AddressChain::External => external_stored += 1,
+                AddressChain::Internal => internal_stored += 1,
+            }
+        }
+        external_count = external_count.max(external_stored);
+        internal_count = internal_count.max(internal_stored);
+        for utxo in self.utxo_list.values() {
+            let next = utxo.key_path.addr_index + 1;
+            match utxo.key_path.addr_chain {
+                AddressChain::External => external_count = external_count.max(next),
+                AddressChain::Internal => internal_count = internal_count.max(next),
+            }
+        }
+        let external = (0..external_count).map(|i| KeyPath::new(AddressChain::External, i));
+        let internal = (0..internal_count).map(|i| KeyPath::new(AddressChain::Internal, i));
+        external.chain(internal).collect()
+    }
+
+    /// derive the key at `key_path` again and store whichever of the key, its script
+    /// and its address is missing
+    pub fn rederive(&mut self, key_path: &KeyPath) {
+        let pk = self.get_pk(key_path);
+        let key = SecretKeyHelper::new(
+            self.address_type.clone(),
+            key_path.addr_chain.clone(),
+            key_path.addr_index,
+        );
+        {
+            let mut db = self.db.write().unwrap();
+            match key_path.addr_chain {
+                AddressChain::External if db.get_external_public_key(&key) != Some(pk) => {
+                    db.put_external_public_key(&key, &pk)
+                }
+                AddressChain::Internal if db.get_internal_public_key(&key) != Some(pk) => {
+                    db.put_internal_public_key(&key, &pk)
+                }
+                _ => (),
+            }
+        }
+
+        let script = self.script_from_pk(&pk);
+        if self.key_path(&script).as_ref() != Some(key_path) {
+            self.index_pk(&pk, key_path.clone());
+        }
+
+        let addr = self.addr_from_pk(&pk);
+        let mut db = self.db.write().unwrap();
+        if !db.has_address(&self.address_type, &addr) {
+            db.put_address(self.address_type.clone(), addr);
+        }
+    }
+
     pub fn addr_from_pk(&self, pk: &PublicKey) -> String {
         fn p2pkh_addr_from_public_key(pk: &PublicKey, network: Network) -> String {
             let addr = Address::p2pkh(pk, network);
@@ -332,7 +823,6 @@ impl Account {
     pub fn new_address(&mut self) -> Result<String, Bip32Error> {
         let pk = self.next_external_pk()?;
         let addr = self.addr_from_pk(&pk);
-        self.btc_address_list.push(addr.clone());
         self.db
             .write()
             .unwrap()
@@ -340,10 +830,55 @@ impl Account {
         Ok(addr)
     }
 
+    /// the earliest external address which never received funds,
+    /// a new one is derived only if all of them did
+    pub fn unused_address(&mut self) -> Result<String, Bip32Error> {
+        // the script tells the address, no key has to be derived
+        let unused = self
+            .scripts()
+            .into_iter()
+            .filter(|(script, key_path)| {
+                key_path.addr_chain == AddressChain::External
+                    && !self.used_scripts.contains_key(script)
+            })
+            .min_by_key(|(_, key_path)| key_path.addr_index)
+            .and_then(|(script, _)| Address::from_script(&script, self.network));
+
+        match unused {
+            Some(addr) => Ok(addr.to_string()),
+            None => self.new_address(),
+        }
+    }
+
+    /// all derived addresses, external ones first, in the order of derivation
+    pub fn address_list(&self) -> Vec<AddressInfo> {
+        let scripts = self.scripts();
+        let mut address_list = Vec::with_capacity(scripts.len());
+        for (script, key_path) in scripts {
+            let address = match Address::from_script(&script, self.network) {
+                Some(address) => address.to_string(),
+                None => continue,
+            };
+            address_list.push(AddressInfo {
+                address,
+                addr_type: self.address_type.clone(),
+                origin: self.key_origin(&key_path),
+                key_path,
+                used: self.used_scripts.contains_key(&script),
+                label: None,
+            });
+        }
+
+        address_list.sort_by_key(|info| {
+            let addr_chain: u32 = info.key_path.addr_chain.clone().into();
+            (addr_chain, info.key_path.addr_index)
+        });
+        address_list
+    }
+
     pub fn new_change_address(&mut self) -> Result<String, Bip32Error> {
         let pk = self.next_internal_pk()?;
         let addr = self.addr_from_pk(&pk);
-        self.btc_address_list.push(addr.clone());
         self.db
             .write()
             .unwrap()
@@ -352,19 +887,101 @@ impl Account {
     }
 }
 
+impl Drop for Account {
+    fn drop(&mut self) {
+        if let Some(ref mut account_key) = self.account_key {
+            wipe_extended_private_key(account_key);
+        }
+    }
+}
+
+// the extended key with the SLIP132 version of the address type,
+// xpub/ypub/zpub on mainnet and tpub/upub/vpub on the test networks
+fn slip132_encode(xpub: &ExtendedPubKey, addr_type: &AccountAddressType) -> String {
+    let version: [u8; 4] = match (xpub.network, addr_type) {
+        (Network::Bitcoin, AccountAddressType::P2PKH) => [0x04, 0x88, 0xb2, 0x1e],
+        (Network::Bitcoin, AccountAddressType::P2SHWH) => [0x04, 0x9d, 0x7c, 0xb2],
+        (Network::Bitcoin, AccountAddressType::P2WKH) => [0x04, 0xb2, 0x47, 0x46],
+        (_, AccountAddressType::P2PKH) => [0x04, 0x35, 0x87, 0xcf],
+        (_, AccountAddressType::P2SHWH) => [0x04, 0x4a, 0x52, 0x62],
+        (_, AccountAddressType::P2WKH) => [0x04, 0x5f, 0x1c, 0xf6],
+    };
+    let mut data = base58::from_check(&xpub.to_string()).unwrap();
+    data[..4].copy_from_slice(&version);
+    base58::check_encode_slice(&data)
+}
+
+// BIP380 checksum of a descriptor made of the characters of INPUT_CHARSET
+fn descriptor_checksum(descriptor: &str) -> String {
+    const INPUT_CHARSET: &str = "0123456789()[],'/*abcdefgh@:$%{}\
+                                 IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~\
+                                 ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+    const CHECKSUM_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+    fn polymod(c: u64, value: u64) -> u64 {
+        let c0 = c >> 35;
+        let mut c = ((c & 0x7_ffff_ffff) << 5) ^ value;
+        for (bit, generator) in [
+            0xf5_dee5_1989,
+            0xa9_fdca_3312,
+            0x1b_ab10_e32d,
+            0x37_06b1_677a,
+            0x64_4d62_6ffd,
+        ]
+        .iter()
+        .enumerate()
+        {
+            if c0 >> bit & 1 != 0 {
+                c ^= generator;
+            }
+        }
+        c
+    }
+
+    let mut c = 1;
+    let mut groups = Vec::new();
+    for ch in descriptor.chars() {
+        let position = INPUT_CHARSET.find(ch).unwrap() as u64;
+        c = polymod(c, position & 31);
+        groups.push(position >> 5);
+        if groups.len() == 3 {
+            c = polymod(c, groups[0] * 9 + groups[1] * 3 + groups[2]);
+            groups.clear();
+        }
+    }
+    if groups.len() == 1 {
+        c = polymod(c, groups[0]);
+    } else if groups.len() == 2 {
+        c = polymod(c, groups[0] * 3 + groups[1]);
+    }
+    for _ in 0..8 {
+        c = polymod(c, 0);
+    }
+    c ^= 1;
+    (0..8).map(|j| CHECKSUM_CHARSET[(c >> (5 * (7 - j)) & 31) as usize] as char).collect()
+}
+
 #[cfg(test)]
 mod test {
     use bitcoin::{
         network::constants::Network,
-        Block, Transaction,
+        util::bip32::DerivationPath,
+        Block, Transaction, TxOut, Address, Script,
     };
     use bitcoin_hashes::sha256d::Hash as Sha256dHash;
-    use std::{fmt, error::Error};
+    use std::{fmt, error::Error, str::FromStr};
 
-    use crate::walletlibrary::{WalletConfigBuilder, WalletLibraryMode, KeyGenConfig};
+    use crate::walletlibrary::{
+        WalletConfig, WalletConfigBuilder, WalletLibraryMode, KeyGenConfig,
+    };
     use crate::default::WalletWithTrustedFullNode;
-    use crate::account::AccountAddressType;
-    use crate::interface::BlockChainIO;
+    use crate::memory::test::{new_wallet, payment};
+    use crate::account::{
+        AccountAddressType, DerivationMode, DerivationScheme, descriptor_checksum, LruCache,
+    };
+    use crate::interface::{Wallet, BlockChainIO};
+    use bitcoin::PublicKey;
+    use secp256k1::Secp256k1;
 
     struct FakeBlockChainIO;
 
@@ -396,10 +1013,24 @@ mod test {
             Err(FakeBlockChainIoError)
         }
 
+        fn get_raw_block(&self, header_hash: &Sha256dHash) -> Result<String, Self::Error> {
+            let _ = header_hash;
+            Err(FakeBlockChainIoError)
+        }
+
         fn send_raw_transaction(&self, tx: &Transaction) -> Result<Sha256dHash, Self::Error> {
             let _ = tx;
             Err(FakeBlockChainIoError)
         }
+
+        fn get_raw_mempool(&self) -> Result<Vec<Sha256dHash>, Self::Error> {
+            Err(FakeBlockChainIoError)
+        }
+
+        fn get_raw_transaction(&self, txid: &Sha256dHash) -> Result<Transaction, Self::Error> {
+            let _ = txid;
+            Err(FakeBlockChainIoError)
+        }
     }
 
     #[test]
@@ -433,7 +1064,7 @@ mod test {
             WalletLibraryMode::Create(KeyGenConfig::debug()),
         )
         .unwrap();
-        let account = af.wallet_lib.get_account_mut(AccountAddressType::P2PKH);
+        let mut account = af.wallet_lib.get_account_mut(AccountAddressType::P2PKH);
 
         for expected_pk in get_external_pk_vec() {
             let pk = account.next_external_pk().unwrap();
@@ -474,7 +1105,7 @@ mod test {
             WalletLibraryMode::Create(KeyGenConfig::debug()),
         )
         .unwrap();
-        let account = af.wallet_lib.get_account_mut(AccountAddressType::P2WKH);
+        let mut account = af.wallet_lib.get_account_mut(AccountAddressType::P2WKH);
 
         for expected_pk in external_pk_vec {
             let pk = account.next_external_pk().unwrap();
@@ -486,4 +1117,189 @@ mod test {
             assert_eq!(hex::encode(&pk.key.serialize()[..]), expected_pk);
         }
     }
+
+    #[test]
+    fn test_process_tx_uses_script_index() {
+        let wc = WalletConfigBuilder::new()
+            .db_path("/tmp/test_process_tx_uses_script_index".to_string())
+            .finalize();
+        let (mut af, _) = WalletWithTrustedFullNode::new(
+            wc,
+            FakeBlockChainIO,
+            WalletLibraryMode::Create(KeyGenConfig::debug()),
+        )
+        .unwrap();
+
+        let mut output = Vec::new();
+        for address_type in &[
+            AccountAddressType::P2PKH,
+            AccountAddressType::P2SHWH,
+            AccountAddressType::P2WKH,
+        ] {
+            let addr = af.wallet_lib.new_address(address_type.clone()).unwrap();
+            let change_addr = af.wallet_lib.new_change_address(address_type.clone()).unwrap();
+            for addr in &[addr, change_addr] {
+                output.push(TxOut {
+                    value: 100_000,
+                    script_pubkey: Address::from_str(addr).unwrap().script_pubkey(),
+                });
+            }
+        }
+        let tx = Transaction {
+            version: 0,
+            lock_time: 0,
+            input: Vec::new(),
+            output,
+        };
+
+        af.wallet_lib.process_tx(&tx);
+        assert_eq!(af.wallet_lib.wallet_balance(), 600_000);
+
+        let account = af.wallet_lib.get_account_mut(AccountAddressType::P2WKH);
+        assert_eq!(account.get_utxo_list().len(), 2);
+        for utxo in account.get_utxo_list().values() {
+            let sk = account.get_sk(&utxo.key_path);
+            let pk = PublicKey::from_private_key(&Secp256k1::new(), &sk);
+            assert_eq!(account.script_from_pk(&pk), utxo.pk_script);
+        }
+    }
+
+    #[test]
+    fn lru_cache() {
+        let mut cache = LruCache::new(2);
+        cache.insert(1, "a");
+        cache.insert(2, "b");
+        assert_eq!(cache.get(&1), Some("a"));
+        // 2 was used last before 1
+        cache.insert(3, "c");
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.get(&1), Some("a"));
+        cache.insert(3, "d");
+        cache.insert(4, "e");
+        assert_eq!(cache.get(&1), None);
+        assert_eq!(cache.get(&3), Some("d"));
+        assert_eq!(cache.entries.len(), cache.order.len());
+    }
+
+    #[test]
+    fn scripts_beyond_the_cache() {
+        let (mut wallet, _) = new_wallet(WalletConfig::in_memory());
+        let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        let script = Address::from_str(&addr).unwrap().script_pubkey();
+
+        let account = wallet.wallet_lib_mut().get_account_mut(AccountAddressType::P2WKH);
+        *account.script_cache.lock().unwrap() = LruCache::new(1);
+        let key_path = account.key_path(&script).unwrap();
+        assert_eq!(account.addr_from_pk(&account.get_pk(&key_path)), addr);
+        assert!(!account.is_mine(&Script::new()));
+    }
+
+    #[test]
+    fn derivation_scheme() {
+        let scheme = DerivationScheme::from_str("m/84'/0'/5'").unwrap();
+        assert_eq!(scheme, DerivationScheme::new(84, 0, 5).unwrap());
+        assert_eq!(DerivationScheme::from_str("m/84h/0h/5h").unwrap(), scheme);
+        assert_eq!(scheme.to_string(), "m/84'/0'/5'");
+        assert!(DerivationScheme::from_str("m/84'/0'/5").is_err());
+        assert!(DerivationScheme::from_str("m/84'/0'").is_err());
+        assert!(DerivationScheme::from_str("m/45'/0'/0'").is_err());
+        let p2shwh = AccountAddressType::P2SHWH;
+        let default = DerivationScheme::default_for(&p2shwh, Network::Testnet, Default::default());
+        assert_eq!(default.to_string(), "m/49'/1'/0'");
+        let regtest = DerivationScheme::default_for(&p2shwh, Network::Regtest, Default::default());
+        assert_eq!(regtest.to_string(), "m/49'/1'/0'");
+        let mode = DerivationMode::Legacy;
+        let legacy = DerivationScheme::default_for(&p2shwh, Network::Regtest, mode);
+        assert_eq!(legacy.to_string(), "m/49'/2'/0'");
+
+        assert_eq!(descriptor_checksum("raw(deadbeef)"), "89f8spxm");
+        let addr = "addr(mkmZxiEcEd8ZqjQWVZuC6so5dFMKEFpN2j)";
+        assert_eq!(descriptor_checksum(addr), "02wpgw69");
+    }
+
+    #[test]
+    fn custom_derivation_scheme() {
+        let (mut default, chain) = new_wallet(WalletConfig::in_memory());
+        let mut key_gen_cfg = KeyGenConfig::debug();
+        let scheme = DerivationScheme::new(84, 2, 5).unwrap();
+        key_gen_cfg.set_derivation_scheme(AccountAddressType::P2WKH, scheme);
+        let (mut wallet, _) = WalletWithTrustedFullNode::new(
+            WalletConfig::in_memory(),
+            chain.clone(),
+            WalletLibraryMode::Create(key_gen_cfg),
+        )
+        .unwrap();
+
+        // the same seed, another account
+        let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        let default_addr = default.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        assert_ne!(addr, default_addr);
+        let p2pkh_addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2PKH).unwrap();
+        let default_p2pkh_addr =
+            default.wallet_lib_mut().new_address(AccountAddressType::P2PKH).unwrap();
+        assert_eq!(p2pkh_addr, default_p2pkh_addr);
+
+        let descriptors = wallet.wallet_lib().descriptors();
+        assert_eq!(descriptors.len(), 6);
+        assert!(descriptors[0].starts_with("pkh(["));
+        assert!(descriptors[4].starts_with("wpkh(["));
+        assert!(descriptors[4].contains("/84'/2'/5']"));
+        assert!(descriptors[5].contains("/1/*)#"));
+        assert!(default.wallet_lib().descriptors()[4].contains("/84'/1'/0']"));
+
+        // cosigners find the key of the input by its origin
+        chain.push_block(vec![payment(&addr, 100_000_000)]);
+        wallet.sync_with_tip().unwrap();
+        let (_, funding) = wallet
+            .wallet_lib_mut()
+            .fund_psbt_for_channel(&Script::from(vec![0x51]), 10_000_000, 10)
+            .unwrap();
+        let origins: Vec<_> = funding.psbt.inputs[0].hd_keypaths.values().cloned().collect();
+        assert_eq!(origins.len(), 1);
+        assert_eq!(origins[0].1, DerivationPath::from_str("m/84'/2'/5'/0/0").unwrap());
+        // and tell the change from the payment
+        assert!(funding.psbt.outputs[0].hd_keypaths.is_empty());
+        let origins: Vec<_> = funding.psbt.outputs[1].hd_keypaths.values().cloned().collect();
+        assert_eq!(origins[0].1, DerivationPath::from_str("m/84'/2'/5'/1/0").unwrap());
+
+        let addrs = wallet.wallet_lib().get_address_list();
+        let info = addrs.iter().find(|info| info.address == addr).unwrap();
+        assert_eq!(info.origin.1, DerivationPath::from_str("m/84'/2'/5'/0/0").unwrap());
+    }
+
+    #[test]
+    fn migrate_derivation() {
+        let (mut standard, chain) = new_wallet(WalletConfig::in_memory());
+        assert_eq!(standard.wallet_lib().get_derivation_mode(), DerivationMode::Standard);
+        assert!(standard.migrate_derivation().is_err());
+        let mut key_gen_cfg = KeyGenConfig::debug();
+        key_gen_cfg.set_derivation_mode(DerivationMode::Legacy);
+        let (mut wallet, _) = WalletWithTrustedFullNode::new(
+            WalletConfig::in_memory(),
+            chain.clone(),
+            WalletLibraryMode::Create(key_gen_cfg),
+        )
+        .unwrap();
+        assert!(wallet.wallet_lib().descriptors()[4].contains("/84'/2'/0']"));
+
+        // the coins of the regtest coin type move to the standard one of the same seed
+        let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        let standard_addr =
+            standard.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        assert_ne!(addr, standard_addr);
+        chain.push_block(vec![payment(&addr, 100_000_000)]);
+        wallet.sync_with_tip().unwrap();
+        let tx = wallet.migrate_derivation().unwrap();
+        let swept_to = Address::from_str(&standard_addr).unwrap().script_pubkey();
+        assert_eq!(tx.output[0].script_pubkey, swept_to);
+        assert_eq!(wallet.wallet_lib().get_derivation_mode(), DerivationMode::Legacy);
+
+        chain.mine();
+        wallet.sync_with_tip().unwrap();
+        assert_eq!(wallet.wallet_lib().get_derivation_mode(), DerivationMode::Standard);
+        assert_eq!(wallet.wallet_lib().wallet_balance(), tx.output[0].value);
+        assert_eq!(wallet.wallet_lib().descriptors(), standard.wallet_lib().descriptors());
+        // the history is of the same wallet
+        assert_eq!(wallet.wallet_lib().get_tx_history().len(), 2);
+    }
 }
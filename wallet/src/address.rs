@@ -0,0 +1,316 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Destination addresses
+//!
+//! The address a payment goes to is checked before a transaction is built: its checksum
+//! and the network it belongs to. The wallet pays to P2PKH and P2SH (base58), P2WPKH and
+//! P2WSH (bech32, BIP173) addresses, and to the ones of witness version 1, P2TR, and later
+//! (bech32m, BIP350) though it owns none of them. The outputs of later versions are standard,
+//! so the wallet doesn't need to change once a soft fork gives them a meaning.
+//!
+use bitcoin::{
+    blockdata::script::Script,
+    network::constants::Network,
+    util::{address::Address, base58},
+};
+
+use super::bip352::{CHARSET, BECH32M_CONST, polymod, to_base32, bech32m_encode};
+use super::error::WalletError;
+
+// base58 version bytes
+const P2PKH_MAIN: u8 = 0;
+const P2SH_MAIN: u8 = 5;
+const P2PKH_TEST: u8 = 111;
+const P2SH_TEST: u8 = 196;
+
+/// the output script of a payment to `addr`, which has to be an address of `network`
+pub fn script_pubkey(addr: &str, network: Network) -> Result<Script, WalletError> {
+    let invalid = || WalletError::InvalidAddress(addr.to_owned());
+    let wrong_network = || WalletError::AddressNetworkMismatch(addr.to_owned());
+
+    let lower = addr.to_lowercase();
+    if let Some(separator) = lower.rfind('1') {
+        let hrp = &lower[..separator];
+        if hrp == "bc" || hrp == "tb" || hrp == "bcrt" {
+            // bech32 is either lower or upper case
+            if addr != lower && addr != addr.to_uppercase() {
+                return Err(invalid());
+            }
+            let (version, program) = decode_segwit(&lower, separator).ok_or_else(invalid)?;
+            if hrp != segwit_hrp(network) {
+                return Err(wrong_network());
+            }
+            return Ok(witness_script(version, &program));
+        }
+    }
+
+    let data = base58::from_check(addr).map_err(|_| invalid())?;
+    if data.len() != 21 {
+        return Err(invalid());
+    }
+    let test = network != Network::Bitcoin;
+    let hash = &data[1..];
+    match data[0] {
+        P2PKH_MAIN | P2SH_MAIN if test => Err(wrong_network()),
+        P2PKH_TEST | P2SH_TEST if !test => Err(wrong_network()),
+        P2PKH_MAIN | P2PKH_TEST => {
+            let mut script = vec![0x76, 0xa9, 0x14];
+            script.extend_from_slice(hash);
+            script.extend_from_slice(&[0x88, 0xac]);
+            Ok(Script::from(script))
+        }
+        P2SH_MAIN | P2SH_TEST => {
+            let mut script = vec![0xa9, 0x14];
+            script.extend_from_slice(hash);
+            script.push(0x87);
+            Ok(Script::from(script))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+// human readable part of the segwit addresses of the network
+fn segwit_hrp(network: Network) -> &'static str {
+    match network {
+        Network::Bitcoin => "bc",
+        Network::Testnet => "tb",
+        Network::Regtest => "bcrt",
+    }
+}
+
+// the witness version and program of a lower case segwit address, `None` if the checksum,
+// its variant or the lengths are wrong
+fn decode_segwit(addr: &str, separator: usize) -> Option<(u8, Vec<u8>)> {
+    if addr.len() > 90 || addr.len() < separator + 8 {
+        return None;
+    }
+    let hrp = &addr[..separator];
+    let mut values = Vec::new();
+    for c in addr[separator + 1..].bytes() {
+        values.push(CHARSET.iter().position(|&d| d == c)? as u8);
+    }
+
+    let mut expanded: Vec<u8> = hrp.bytes().map(|c| c >> 5).collect();
+    expanded.push(0);
+    expanded.extend(hrp.bytes().map(|c| c & 31));
+    expanded.extend_from_slice(&values);
+    let bech32m = match polymod(&expanded) {
+        1 => false,
+        BECH32M_CONST => true,
+        _ => return None,
+    };
+
+    let data = &values[..values.len() - 6];
+    let version = data[0];
+    // version 0 is checksummed by bech32, the later ones by bech32m
+    if version > 16 || bech32m != (version > 0) {
+        return None;
+    }
+    let program = from_base32(&data[1..])?;
+    if program.len() < 2 || program.len() > 40 {
+        return None;
+    }
+    if version == 0 && program.len() != 20 && program.len() != 32 {
+        return None;
+    }
+    Some((version, program))
+}
+
+// regroup 5 bit values into bytes, the padding has to be less than 5 zero bits
+fn from_base32(values: &[u8]) -> Option<Vec<u8>> {
+    let mut data = Vec::new();
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    for value in values {
+        acc = (acc << 5) | *value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            data.push((acc >> bits) as u8);
+        }
+    }
+    if bits >= 5 || acc & ((1 << bits) - 1) != 0 {
+        return None;
+    }
+    Some(data)
+}
+
+// OP_0 or OP_1 to OP_16 and the push of the program
+fn witness_script(version: u8, program: &[u8]) -> Script {
+    let opcode = if version == 0 { 0x00 } else { 0x50 + version };
+    let mut script = vec![opcode, program.len() as u8];
+    script.extend_from_slice(program);
+    Script::from(script)
+}
+
+// the witness version and program of an output script of the form `witness_script` makes
+fn witness_program(script: &[u8]) -> Option<(u8, &[u8])> {
+    if script.len() < 4 || script.len() > 42 || script[1] as usize != script.len() - 2 {
+        return None;
+    }
+    match script[0] {
+        0x00 => Some((0, &script[2..])),
+        opcode @ 0x51..=0x60 => Some((opcode - 0x50, &script[2..])),
+        _ => None,
+    }
+}
+
+/// the address `script` pays to on `network`, `None` for scripts without an address form.
+/// Unlike `Address::from_script` it encodes version 1 and later programs as bech32m.
+pub fn from_script(script: &Script, network: Network) -> Option<String> {
+    match witness_program(script.as_bytes()) {
+        Some((version, program)) if version > 0 => {
+            let mut data = vec![version];
+            data.extend(to_base32(program));
+            Some(bech32m_encode(segwit_hrp(network), &data))
+        }
+        _ => Address::from_script(script, network).map(|addr| addr.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::{Address, network::constants::Network};
+
+    use std::str::FromStr;
+
+    use super::{script_pubkey, from_script};
+    use crate::error::WalletError;
+    use crate::walletlibrary::{WalletConfigBuilder, WalletLibraryMode, KeyGenConfig};
+    use crate::default::WalletWithTrustedFullNode;
+    use crate::memory::{MemoryChain, test::payment};
+    use crate::history::ExportFormat;
+    use crate::account::AccountAddressType;
+    use crate::interface::Wallet;
+
+    #[test]
+    fn destinations() {
+        for (addr, network) in &[
+            ("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2", Network::Bitcoin),
+            ("3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy", Network::Bitcoin),
+            ("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq", Network::Bitcoin),
+            ("mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn", Network::Testnet),
+            ("2MzQwSSnBHWHqSAqtTVQ6v47XtaisrJa1Vc", Network::Regtest),
+            ("bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49", Network::Regtest),
+            (
+                "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7",
+                Network::Testnet,
+            ),
+        ] {
+            let expected = Address::from_str(addr).unwrap().script_pubkey();
+            assert_eq!(script_pubkey(addr, *network).unwrap(), expected);
+        }
+        // upper case bech32
+        let upper = "BC1QAR0SRRR7XFKVY5L643LYDNW9RE59GTZZWF5MDQ";
+        assert!(script_pubkey(upper, Network::Bitcoin).is_ok());
+
+        // P2TR, BIP350
+        let p2tr = "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0";
+        let script = script_pubkey(p2tr, Network::Bitcoin).unwrap();
+        assert_eq!(
+            hex::encode(script.as_bytes()),
+            "512079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+        );
+        assert_eq!(from_script(&script, Network::Bitcoin).unwrap(), p2tr);
+
+        // versions without a meaning yet
+        for (addr, network, hex) in &[
+            ("BC1SW50QGDZ25J", Network::Bitcoin, "6002751e"),
+            (
+                "bc1zw508d6qejxtdg4y5r3zarvaryvaxxpcs",
+                Network::Bitcoin,
+                "5210751e76e8199196d454941c45d1b3a323",
+            ),
+            (
+                "tb1pqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesf3hn0c",
+                Network::Testnet,
+                "5120000000c4a5cad46221b2a187905e5266362b99d5e91c6ce24d165dab93e86433",
+            ),
+        ] {
+            let script = script_pubkey(addr, *network).unwrap();
+            assert_eq!(hex::encode(script.as_bytes()), *hex);
+            assert_eq!(from_script(&script, *network).unwrap(), addr.to_lowercase());
+        }
+    }
+
+    #[test]
+    fn refused_destinations() {
+        let error = |addr: &str, network| match script_pubkey(addr, network).unwrap_err() {
+            WalletError::InvalidAddress(_) => "invalid",
+            WalletError::AddressNetworkMismatch(_) => "network",
+            e => panic!("unexpected error {}", e),
+        };
+        let mainnet = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+        assert_eq!(error(mainnet, Network::Testnet), "network");
+        assert_eq!(error(mainnet, Network::Regtest), "network");
+        assert_eq!(error("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2", Network::Regtest), "network");
+        assert_eq!(error("mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn", Network::Bitcoin), "network");
+        let regtest = "bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49";
+        assert_eq!(error(regtest, Network::Testnet), "network");
+
+        // a changed character
+        let typo = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdr";
+        assert_eq!(error(typo, Network::Bitcoin), "invalid");
+        assert_eq!(error("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN3", Network::Bitcoin), "invalid");
+        let mixed_case = "bc1qAR0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+        assert_eq!(error(mixed_case, Network::Bitcoin), "invalid");
+        // version 1 checksummed by bech32 instead of bech32m
+        let p2tr_bech32 = "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqh2y7hd";
+        assert_eq!(error(p2tr_bech32, Network::Bitcoin), "invalid");
+        // version 1 with a program longer than 40 bytes
+        let too_long =
+            "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7v8n0nx0muaewav253zgeav";
+        assert_eq!(error(too_long, Network::Bitcoin), "invalid");
+        assert_eq!(error("an address", Network::Bitcoin), "invalid");
+    }
+
+    #[test]
+    fn pay_witness_programs() {
+        for &(network, dest_addr) in &[
+            (Network::Bitcoin, "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0"),
+            (Network::Testnet, "tb1pqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesf3hn0c"),
+            // version 2, no soft fork gave it a meaning yet
+            (Network::Bitcoin, "bc1zw508d6qejxtdg4y5r3zarvaryvaxxpcs"),
+        ] {
+            let chain = MemoryChain::new(network);
+            let config = WalletConfigBuilder::new().network(network).in_memory(true).finalize();
+            let (mut wallet, _) = WalletWithTrustedFullNode::new(
+                config,
+                chain.clone(),
+                WalletLibraryMode::Create(KeyGenConfig::debug()),
+            )
+            .unwrap();
+            let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+            chain.push_block(Vec::new());
+            chain.push_block(vec![payment(&addr, 100_000_000)]);
+            wallet.sync_with_tip().unwrap();
+
+            let ops = vec![wallet.wallet_lib().get_utxo_list()[0].out_point];
+            let tx = wallet.make_tx(ops, dest_addr.to_owned(), 50_000_000, true, None).unwrap();
+            let script = script_pubkey(dest_addr, network).unwrap();
+            assert_eq!(tx.output[0].script_pubkey, script);
+            chain.mine();
+            wallet.sync_with_tip().unwrap();
+
+            // the history shows the bech32m address paid to
+            let mut csv = Vec::new();
+            wallet.wallet_lib().export_history(ExportFormat::Csv, &mut csv).unwrap();
+            let csv = String::from_utf8(csv).unwrap();
+            let outgoing = csv.lines().find(|line| line.contains("outgoing")).unwrap();
+            assert_eq!(outgoing.split(',').nth(6), Some(dest_addr));
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Audit log
+//!
+//! Append-only record of the operations deriving addresses, signing and broadcasting.
+//! Every entry commits to the hash of the previous one, so an entry can't be changed
+//! or dropped without breaking the chain of all entries after it.
+//!
+use bitcoin_hashes::{sha256d::Hash as Sha256dHash, Hash};
+use serde::{Serialize, Deserialize};
+use serde_json;
+
+use std::error::Error;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AuditEntry {
+    /// position in the log, the first entry is 0
+    pub seq: u64,
+    /// unix time
+    pub timestamp: u64,
+    /// name of the operation, e.g. `send_coins`
+    pub operation: String,
+    /// space separated `name=value` pairs
+    pub params: String,
+    pub txid: Option<Sha256dHash>,
+    /// who requested the operation, empty if the wallet wasn't called over gRPC
+    pub caller: String,
+    /// hash of the previous entry, all zeroes for the first one
+    pub prev_hash: Sha256dHash,
+}
+
+impl AuditEntry {
+    /// hash the next entry commits to
+    pub fn hash(&self) -> Sha256dHash {
+        Sha256dHash::hash(&serde_json::to_vec(self).unwrap())
+    }
+}
+
+/// check that `entries` follow each other in the log, the first one commits to `prev_hash`
+pub fn verify(entries: &[AuditEntry], prev_hash: Sha256dHash) -> Result<(), Box<dyn Error>> {
+    let mut prev_hash = prev_hash;
+    let mut seq = match entries.first() {
+        Some(entry) => entry.seq,
+        None => return Ok(()),
+    };
+    for entry in entries {
+        if entry.seq != seq {
+            return Err(From::from(format!("entry {} is missing", seq)));
+        }
+        if entry.prev_hash != prev_hash {
+            return Err(From::from(format!("entry {} breaks the hash chain", entry.seq)));
+        }
+        prev_hash = entry.hash();
+        seq += 1;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin_hashes::{sha256d::Hash as Sha256dHash, Hash};
+    use super::AuditEntry;
+
+    #[test]
+    fn hash_chain() {
+        let mut entries: Vec<AuditEntry> = Vec::new();
+        for seq in 0..3 {
+            let prev_hash = entries.last().map_or(Default::default(), AuditEntry::hash);
+            entries.push(AuditEntry {
+                seq,
+                timestamp: 1_560_000_000 + seq,
+                operation: "send_coins".to_owned(),
+                params: format!("amt={}", seq),
+                txid: Some(Sha256dHash::hash(&[seq as u8])),
+                caller: "treasury".to_owned(),
+                prev_hash,
+            });
+        }
+        super::verify(&entries, Default::default()).unwrap();
+        // a page in the middle is checked against the hash of the entry before it
+        super::verify(&entries[1..], entries[0].hash()).unwrap();
+
+        let mut changed = entries.clone();
+        changed[1].params = "amt=100".to_owned();
+        assert!(super::verify(&changed, Default::default()).is_err());
+
+        let mut dropped = entries.clone();
+        dropped.remove(1);
+        assert!(super::verify(&dropped, Default::default()).is_err());
+    }
+}
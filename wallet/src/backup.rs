@@ -0,0 +1,674 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Wallet backup
+//!
+//! Encrypted snapshot of the wallet database which can be restored on another machine
+//!
+//! file layout: magic, version, PBKDF2 salt, AES256-CTR iv, ciphertext, HMAC-SHA256 tag
+//!
+use bitcoin::{OutPoint, Script, Transaction};
+use bitcoin::util::key::PublicKey;
+use bitcoin_hashes::sha256d::Hash as Sha256dHash;
+use crypto::aes;
+use crypto::hmac::Hmac;
+use crypto::mac::{Mac, MacResult};
+use crypto::pbkdf2;
+use crypto::sha2::{Sha256, Sha512};
+use crypto::symmetriccipher::SynchronousStreamCipher;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Serialize, Deserialize};
+use zeroize::{Zeroize, Zeroizing};
+
+use std::{fs, path::Path};
+
+use super::error::WalletError;
+use super::account::{
+    Utxo, SecretKeyHelper, AccountAddressType, KeyPath, DerivationMode, DerivationScheme,
+};
+use super::walletlibrary::{
+    ImportedUtxo, WatchedUtxo, SilentPaymentUtxo, SpendingPolicy, TimelockedUtxo,
+    ScriptAccount, ScriptUtxo, VaultUtxo,
+};
+use super::timelock::Timelock;
+use super::vault::Vault;
+use super::history::{TxRecord, AddressStats};
+use super::DB;
+
+const MAGIC: &[u8] = b"RWBK";
+const VERSION: u8 = 2;
+// backups with the tables of the database in fields of their own
+const LEGACY_VERSION: u8 = 1;
+const SALT_LENGTH: usize = 16;
+const IV_LENGTH: usize = 16;
+const TAG_LENGTH: usize = 32;
+const HEADER_LENGTH: usize = 4 + 1 + SALT_LENGTH + IV_LENGTH;
+const PBKDF2_ITERATIONS: u32 = 20_000;
+
+/// everything needed to bring a wallet back, the seed randomness stays encrypted
+/// with the wallet passphrase inside the backup
+#[derive(Serialize, Deserialize)]
+pub struct Backup {
+    bip39_randomness: Vec<u8>,
+    /// master public key, tells whether the randomness is decrypted with the right secrets
+    master_public: String,
+    /// the rest of the database as `DB::dump` gives it
+    data: serde_json::Value,
+}
+
+impl Drop for Backup {
+    fn drop(&mut self) {
+        self.bip39_randomness.zeroize();
+    }
+}
+
+impl Backup {
+    /// take a snapshot of the database
+    pub(crate) fn from_db(db: &DB, master_public: String) -> Result<Self, WalletError> {
+        let bip39_randomness = db
+            .get_bip39_randomness()
+            .ok_or(WalletError::HasNoWalletInDatabase)?;
+        Ok(Backup {
+            bip39_randomness,
+            master_public,
+            data: db.dump(),
+        })
+    }
+
+    /// replace the content of the database with the snapshot
+    pub(crate) fn to_db(&self, db: &mut DB) -> Result<(), WalletError> {
+        db.restore_dump(&self.data, &self.bip39_randomness)
+    }
+
+    pub fn bip39_randomness(&self) -> &[u8] {
+        &self.bip39_randomness
+    }
+
+    pub fn master_public(&self) -> &str {
+        &self.master_public
+    }
+
+    /// serialize and encrypt the backup with a key derived from `passphrase`
+    pub fn encrypt(&self, passphrase: &str) -> Result<Vec<u8>, WalletError> {
+        let plain = serde_json::to_vec(self).map_err(|_| WalletError::InvalidBackup)?;
+        seal(&Zeroizing::new(plain), VERSION, passphrase)
+    }
+
+    /// verify and decrypt a backup produced by `Backup::encrypt`,
+    /// or by a wallet of the version before
+    pub fn decrypt(data: &[u8], passphrase: &str) -> Result<Self, WalletError> {
+        if data.len() < HEADER_LENGTH + TAG_LENGTH || &data[..MAGIC.len()] != MAGIC {
+            return Err(WalletError::InvalidBackup);
+        }
+        let version = data[MAGIC.len()];
+        if version != VERSION && version != LEGACY_VERSION {
+            return Err(WalletError::InvalidBackup);
+        }
+        let salt = &data[MAGIC.len() + 1..MAGIC.len() + 1 + SALT_LENGTH];
+        let iv = &data[MAGIC.len() + 1 + SALT_LENGTH..HEADER_LENGTH];
+        let (authenticated, tag) = data.split_at(data.len() - TAG_LENGTH);
+        let (enc_key, mac_key) = derive_keys(passphrase, salt);
+
+        // `MacResult` compares in constant time
+        if authenticate(&*mac_key, authenticated) != MacResult::new(tag) {
+            return Err(WalletError::BackupAuthenticationFailed);
+        }
+
+        let encrypted = &authenticated[HEADER_LENGTH..];
+        let mut plain = Zeroizing::new(vec![0u8; encrypted.len()]);
+        aes::ctr(aes::KeySize::KeySize256, &*enc_key, iv).process(encrypted, &mut plain);
+        if version == LEGACY_VERSION {
+            let legacy: LegacyBackup =
+                serde_json::from_slice(&plain).map_err(|_| WalletError::InvalidBackup)?;
+            return legacy.into_backup();
+        }
+        serde_json::from_slice(&plain).map_err(|_| WalletError::InvalidBackup)
+    }
+
+    /// encrypt the backup into a file
+    pub fn write(&self, path: &Path, passphrase: &str) -> Result<(), WalletError> {
+        let data = self.encrypt(passphrase)?;
+        fs::write(path, data)?;
+        Ok(())
+    }
+
+    /// read an encrypted backup file
+    pub fn read(path: &Path, passphrase: &str) -> Result<Self, WalletError> {
+        let data = fs::read(path)?;
+        Backup::decrypt(&data, passphrase)
+    }
+}
+
+// a backup of version 1, each table of the database in a field,
+// those written since hold all of them in a dump of the database
+#[derive(Serialize, Deserialize)]
+struct LegacyBackup {
+    bip39_randomness: Vec<u8>,
+    /// master public key, tells whether the randomness is decrypted with the right secrets
+    master_public: String,
+    last_seen_block_height: usize,
+    external_public_key_list: Vec<(SecretKeyHelper, PublicKey)>,
+    internal_public_key_list: Vec<(SecretKeyHelper, PublicKey)>,
+    address_list: Vec<(AccountAddressType, String)>,
+    utxo_list: Vec<(OutPoint, Utxo)>,
+    script_index: Vec<(Script, AccountAddressType, KeyPath)>,
+    #[serde(default)]
+    imported_key_list: Vec<(Script, PublicKey, AccountAddressType)>,
+    #[serde(default)]
+    imported_utxo_list: Vec<ImportedUtxo>,
+    #[serde(default)]
+    used_script_list: Vec<(Script, AccountAddressType, OutPoint)>,
+    #[serde(default)]
+    frozen_utxo_list: Vec<OutPoint>,
+    #[serde(default)]
+    unconfirmed_tx_list: Vec<Transaction>,
+    #[serde(default)]
+    tx_history: Vec<TxRecord>,
+    #[serde(default)]
+    watched_script_list: Vec<(Script, String)>,
+    #[serde(default)]
+    watched_utxo_list: Vec<WatchedUtxo>,
+    #[serde(default)]
+    sent_payment_code_list: Vec<(String, u32)>,
+    #[serde(default)]
+    received_payment_code_list: Vec<(String, u32)>,
+    #[serde(default)]
+    silent_payment_utxo_list: Vec<SilentPaymentUtxo>,
+    #[serde(default)]
+    spending_policy: SpendingPolicy,
+    /// a wallet restored without them derives its accounts at the default paths
+    #[serde(default)]
+    derivation_schemes: Vec<(AccountAddressType, DerivationScheme)>,
+    /// backups made before the standard derivation are of legacy wallets
+    #[serde(default = "legacy_derivation")]
+    derivation_mode: DerivationMode,
+    #[serde(default)]
+    birthday: Option<u64>,
+    #[serde(default)]
+    timelock_script_list: Vec<(Script, u32, Timelock)>,
+    #[serde(default)]
+    timelocked_utxo_list: Vec<TimelockedUtxo>,
+    #[serde(default)]
+    script_account_list: Vec<ScriptAccount>,
+    #[serde(default)]
+    script_utxo_list: Vec<ScriptUtxo>,
+    #[serde(default)]
+    vault: Option<Vault>,
+    #[serde(default)]
+    vault_script_list: Vec<(Script, u32, Vault)>,
+    #[serde(default)]
+    vault_utxo_list: Vec<VaultUtxo>,
+    /// the unvaults the wallet signed, any other spend of a deposit is an alert
+    #[serde(default)]
+    unvault_txid_list: Vec<Sha256dHash>,
+    /// a wallet restored without them counts its history again
+    #[serde(default)]
+    address_stats: Vec<(Script, AddressStats)>,
+    #[serde(default)]
+    address_labels: Vec<(Script, String)>,
+}
+
+fn legacy_derivation() -> DerivationMode {
+    DerivationMode::Legacy
+}
+
+impl Drop for LegacyBackup {
+    fn drop(&mut self) {
+        self.bip39_randomness.zeroize();
+    }
+}
+
+impl LegacyBackup {
+    fn to_db(&self, db: &mut DB) {
+        db.clear();
+        db.put_bip39_randomness(&self.bip39_randomness);
+        db.put_last_seen_block_height(self.last_seen_block_height as u32);
+        for (key_helper, pk) in &self.external_public_key_list {
+            db.put_external_public_key(key_helper, pk);
+        }
+        for (key_helper, pk) in &self.internal_public_key_list {
+            db.put_internal_public_key(key_helper, pk);
+        }
+        for (addr_type, address) in &self.address_list {
+            db.put_address(addr_type.clone(), address.clone());
+        }
+        for (op, utxo) in &self.utxo_list {
+            db.put_utxo(op, utxo);
+        }
+        for (script, addr_type, key_path) in &self.script_index {
+            db.put_script_index(script, addr_type, key_path);
+        }
+        for (script, pk, addr_type) in &self.imported_key_list {
+            db.put_imported_key(script, pk, addr_type);
+        }
+        for utxo in &self.imported_utxo_list {
+            db.put_imported_utxo(&utxo.out_point, utxo);
+        }
+        for (script, addr_type, first_funding) in &self.used_script_list {
+            db.put_used_script(script, addr_type, first_funding);
+        }
+        for op in &self.frozen_utxo_list {
+            db.put_frozen_utxo(op);
+        }
+        for tx in &self.unconfirmed_tx_list {
+            db.put_unconfirmed_tx(tx);
+        }
+        for record in &self.tx_history {
+            db.put_tx_record(record);
+        }
+        for (script, label) in &self.watched_script_list {
+            db.put_watched_script(script, label);
+        }
+        for utxo in &self.watched_utxo_list {
+            db.put_watched_utxo(utxo);
+        }
+        for (code, next_index) in &self.sent_payment_code_list {
+            db.put_sent_payment_code(code, *next_index);
+        }
+        for (code, watched_count) in &self.received_payment_code_list {
+            db.put_received_payment_code(code, *watched_count);
+        }
+        for utxo in &self.silent_payment_utxo_list {
+            db.put_silent_payment_utxo(utxo);
+        }
+        db.put_spending_policy(&self.spending_policy);
+        db.put_derivation_schemes(&self.derivation_schemes);
+        db.put_derivation_mode(self.derivation_mode);
+        if let Some(birthday) = self.birthday {
+            db.put_birthday(birthday);
+        }
+        for (script, key_index, timelock) in &self.timelock_script_list {
+            db.put_timelock_script(script, *key_index, timelock);
+        }
+        for utxo in &self.timelocked_utxo_list {
+            db.put_timelocked_utxo(utxo);
+        }
+        for account in &self.script_account_list {
+            db.put_script_account(account);
+        }
+        for utxo in &self.script_utxo_list {
+            db.put_script_utxo(utxo);
+        }
+        if let Some(ref vault) = self.vault {
+            db.put_vault(vault);
+        }
+        for (script, key_index, vault) in &self.vault_script_list {
+            db.put_vault_script(script, *key_index, vault);
+        }
+        for utxo in &self.vault_utxo_list {
+            db.put_vault_utxo(utxo);
+        }
+        for txid in &self.unvault_txid_list {
+            db.put_unvault_txid(txid);
+        }
+        for (script, stats) in &self.address_stats {
+            db.put_address_stats(script, stats);
+        }
+        for (script, label) in &self.address_labels {
+            db.put_address_label(script, label);
+        }
+    }
+
+    // the tables are written to a database of their own, the dump of which is the new backup
+    fn into_backup(self) -> Result<Backup, WalletError> {
+        let mut db = DB::in_memory()?;
+        self.to_db(&mut db);
+        Ok(Backup {
+            bip39_randomness: self.bip39_randomness.clone(),
+            master_public: self.master_public.clone(),
+            data: db.dump(),
+        })
+    }
+}
+
+// the file of a backup with the serialized `plain` of `version`
+fn seal(plain: &[u8], version: u8, passphrase: &str) -> Result<Vec<u8>, WalletError> {
+    let mut rng = OsRng::new().map_err(|_| WalletError::CannotObtainRandomSource)?;
+    let mut salt = [0u8; SALT_LENGTH];
+    let mut iv = [0u8; IV_LENGTH];
+    rng.fill_bytes(&mut salt);
+    rng.fill_bytes(&mut iv);
+    let (enc_key, mac_key) = derive_keys(passphrase, &salt);
+
+    let mut data = Vec::with_capacity(HEADER_LENGTH + plain.len() + TAG_LENGTH);
+    data.extend_from_slice(MAGIC);
+    data.push(version);
+    data.extend_from_slice(&salt);
+    data.extend_from_slice(&iv);
+
+    let mut encrypted = vec![0u8; plain.len()];
+    aes::ctr(aes::KeySize::KeySize256, &*enc_key, &iv).process(plain, &mut encrypted);
+    data.extend_from_slice(&encrypted);
+
+    let tag = authenticate(&*mac_key, &data);
+    data.extend_from_slice(tag.code());
+    Ok(data)
+}
+
+// PBKDF2-HMAC-SHA512 output split into an encryption and an authentication key
+fn derive_keys(passphrase: &str, salt: &[u8]) -> (Zeroizing<[u8; 32]>, Zeroizing<[u8; 32]>) {
+    let mut output = Zeroizing::new([0u8; 64]);
+    let mut mac = Hmac::new(Sha512::new(), passphrase.as_bytes());
+    pbkdf2::pbkdf2(&mut mac, salt, PBKDF2_ITERATIONS, &mut *output);
+
+    let mut enc_key = Zeroizing::new([0u8; 32]);
+    let mut mac_key = Zeroizing::new([0u8; 32]);
+    enc_key.copy_from_slice(&output[..32]);
+    mac_key.copy_from_slice(&output[32..]);
+    (enc_key, mac_key)
+}
+
+fn authenticate(key: &[u8], data: &[u8]) -> MacResult {
+    let mut mac = Hmac::new(Sha256::new(), key);
+    mac.input(data);
+    mac.result()
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::util::key::PublicKey;
+    use crate::walletlibrary::{
+        SpendingPolicy, WalletConfig, DecryptConfig, SendOptions, SendOutcome, InputFilter,
+        IdempotentSend,
+    };
+    use crate::memory::{MemoryChain, test::{new_wallet, payment}};
+    use crate::default::WalletWithTrustedFullNode;
+    use crate::account::{DerivationMode, AccountAddressType};
+    use crate::timelock::Timelock;
+    use crate::vault::Vault;
+    use crate::interface::{Wallet, WalletLibraryInterface};
+    use crate::DB;
+    use super::{Backup, LegacyBackup, LEGACY_VERSION, seal};
+
+    fn backup() -> Backup {
+        let mut db = DB::in_memory().unwrap();
+        db.put_bip39_randomness(&[0x5a; 32]);
+        db.put_last_seen_block_height(42);
+        Backup::from_db(&db, "xpub".to_owned()).unwrap()
+    }
+
+    fn legacy_backup() -> LegacyBackup {
+        LegacyBackup {
+            bip39_randomness: vec![0x5a; 32],
+            master_public: "xpub".to_owned(),
+            last_seen_block_height: 42,
+            external_public_key_list: Vec::new(),
+            internal_public_key_list: Vec::new(),
+            address_list: Vec::new(),
+            utxo_list: Vec::new(),
+            script_index: Vec::new(),
+            imported_key_list: Vec::new(),
+            imported_utxo_list: Vec::new(),
+            used_script_list: Vec::new(),
+            frozen_utxo_list: Vec::new(),
+            unconfirmed_tx_list: Vec::new(),
+            tx_history: Vec::new(),
+            watched_script_list: Vec::new(),
+            watched_utxo_list: Vec::new(),
+            sent_payment_code_list: Vec::new(),
+            received_payment_code_list: Vec::new(),
+            silent_payment_utxo_list: Vec::new(),
+            spending_policy: SpendingPolicy::default(),
+            derivation_schemes: Vec::new(),
+            derivation_mode: DerivationMode::Standard,
+            birthday: None,
+            timelock_script_list: Vec::new(),
+            timelocked_utxo_list: Vec::new(),
+            script_account_list: Vec::new(),
+            script_utxo_list: Vec::new(),
+            vault: None,
+            vault_script_list: Vec::new(),
+            vault_utxo_list: Vec::new(),
+            unvault_txid_list: Vec::new(),
+            address_stats: Vec::new(),
+            address_labels: Vec::new(),
+        }
+    }
+
+    // a wallet of the debug seed restored from a backup of `wallet`
+    fn restore_backup_of(
+        wallet: &WalletWithTrustedFullNode<MemoryChain>,
+        name: &str,
+    ) -> WalletWithTrustedFullNode<MemoryChain> {
+        let path = std::env::temp_dir().join(name);
+        wallet.wallet_lib().export_backup(&path, "backup passphrase").unwrap();
+        let (mut restored, _) = new_wallet(WalletConfig::in_memory());
+        restored
+            .wallet_lib_mut()
+            .import_backup(&path, "backup passphrase", DecryptConfig::default())
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        restored
+    }
+
+    // a wallet with a confirmed coin of 1 BTC
+    fn funded_wallet() -> WalletWithTrustedFullNode<MemoryChain> {
+        let (mut wallet, chain) = new_wallet(WalletConfig::in_memory());
+        let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        chain.push_block(Vec::new());
+        chain.push_block(vec![payment(&addr, 100_000_000)]);
+        wallet.sync_with_tip().unwrap();
+        wallet
+    }
+
+    const DEST_ADDR: &str = "bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49";
+
+    #[test]
+    fn encrypt_decrypt() {
+        let data = backup().encrypt("backup passphrase").unwrap();
+        let restored = Backup::decrypt(&data, "backup passphrase").unwrap();
+        assert_eq!(restored.bip39_randomness(), &[0x5a; 32][..]);
+        assert_eq!(restored.master_public(), "xpub");
+        let mut db = DB::in_memory().unwrap();
+        restored.to_db(&mut db).unwrap();
+        assert_eq!(db.get_last_seen_block_height(), 42);
+        assert_eq!(db.get_bip39_randomness(), Some(vec![0x5a; 32]));
+    }
+
+    #[test]
+    fn legacy_version() {
+        let plain = serde_json::to_vec(&legacy_backup()).unwrap();
+        let data = seal(&plain, LEGACY_VERSION, "backup passphrase").unwrap();
+        let restored = Backup::decrypt(&data, "backup passphrase").unwrap();
+        assert_eq!(restored.master_public(), "xpub");
+        let mut db = DB::in_memory().unwrap();
+        restored.to_db(&mut db).unwrap();
+        assert_eq!(db.get_last_seen_block_height(), 42);
+        assert_eq!(db.get_bip39_randomness(), Some(vec![0x5a; 32]));
+    }
+
+    #[test]
+    fn invalid_dump() {
+        let mut backup = backup();
+        backup.data = serde_json::json!({
+            "schema_version": 0,
+            "entries": [["unknown", [["00", "00"]]]],
+        });
+        let mut db = DB::in_memory().unwrap();
+        db.put_last_seen_block_height(7);
+        assert!(backup.to_db(&mut db).is_err());
+        // nothing is cleared for a dump which can't be restored
+        assert_eq!(db.get_last_seen_block_height(), 7);
+    }
+
+    #[test]
+    fn wrong_passphrase() {
+        let data = backup().encrypt("backup passphrase").unwrap();
+        assert!(Backup::decrypt(&data, "other passphrase").is_err());
+    }
+
+    #[test]
+    fn tampered() {
+        let mut data = backup().encrypt("backup passphrase").unwrap();
+        let middle = data.len() / 2;
+        data[middle] ^= 1;
+        assert!(Backup::decrypt(&data, "backup passphrase").is_err());
+        assert!(Backup::decrypt(&data[..10], "backup passphrase").is_err());
+    }
+
+    #[test]
+    fn restore() {
+        let (mut wallet, chain) = new_wallet(WalletConfig::in_memory());
+        let timelock = Timelock::Blocks(3);
+        let savings = wallet.wallet_lib_mut().new_timelocked_address(timelock).unwrap();
+        let xpub = wallet.wallet_lib().script_account_xpub().unwrap();
+        // the generator point
+        let cosigner = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+        let policy = format!("or(pk({}),and(pk({}),older(2)))", xpub, cosigner);
+        wallet.wallet_lib_mut().register_policy("shared", &policy).unwrap();
+        let shared = wallet.wallet_lib_mut().new_script_address("shared").unwrap();
+        let recovery_pk = PublicKey::from_slice(&hex::decode(cosigner).unwrap()).unwrap();
+        wallet.wallet_lib_mut().set_vault(Vault { recovery_pk, delay: 2 }).unwrap();
+        let vault = wallet.wallet_lib_mut().new_vault_address().unwrap();
+        let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        wallet.wallet_lib_mut().set_address_label(&addr, "salary").unwrap();
+        chain.push_block(Vec::new());
+        chain.push_block(vec![
+            payment(&addr, 40_000_000),
+            payment(&savings, 60_000_000),
+            payment(&shared, 20_000_000),
+            payment(&vault, 30_000_000),
+        ]);
+        wallet.sync_with_tip().unwrap();
+        let path = std::env::temp_dir().join("rust_wallet_backup_restore");
+        wallet.wallet_lib().export_backup(&path, "backup passphrase").unwrap();
+
+        let (mut restored, _) = new_wallet(WalletConfig::in_memory());
+        restored
+            .wallet_lib_mut()
+            .import_backup(&path, "backup passphrase", DecryptConfig::default())
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(restored.wallet_lib().wallet_balance(), 40_000_000);
+        assert_eq!(restored.wallet_lib().address_stats(), wallet.wallet_lib().address_stats());
+        let info = restored
+            .wallet_lib()
+            .get_address_list()
+            .into_iter()
+            .find(|info| info.address == addr)
+            .unwrap();
+        assert_eq!(info.label, Some("salary".to_owned()));
+        let locked = restored.wallet_lib().get_timelocked_utxo_list();
+        assert_eq!(locked.len(), 1);
+        assert_eq!(locked[0].value, 60_000_000);
+        assert_eq!(locked[0].timelock, timelock);
+        assert_eq!(locked[0].height, Some(2));
+        let coins = restored.wallet_lib().get_script_utxo_list();
+        assert_eq!(coins.len(), 1);
+        assert_eq!((coins[0].value, coins[0].index), (20_000_000, 0));
+        assert_eq!(coins[0].account, "shared");
+        // the account goes on at the next index
+        assert!(restored.wallet_lib_mut().register_policy("shared", &policy).is_err());
+        assert_ne!(restored.wallet_lib_mut().new_script_address("shared").unwrap(), shared);
+        assert_eq!(restored.wallet_lib().get_vault().map(|vault| vault.delay), Some(2));
+        let deposits = restored.wallet_lib().get_vault_utxo_list();
+        assert_eq!(deposits.len(), 1);
+        assert_eq!(deposits[0].value, 30_000_000);
+        // the deposit matures with the delay of the restored vault
+        chain.push_block(Vec::new());
+        restored.sync_with_tip().unwrap();
+        let dest_addr = "bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49".to_owned();
+        let tx = restored.unvault(dest_addr, 10).unwrap();
+        assert_eq!(tx.input[0].previous_output, deposits[0].out_point);
+        assert_eq!(tx.input[0].sequence, 2);
+
+        // the restored wallet still recognizes payments to the timelock script
+        let mut deposit = payment(&savings, 10_000_000);
+        deposit.input[0].previous_output.vout = 1;
+        chain.push_block(vec![deposit]);
+        restored.sync_with_tip().unwrap();
+        assert_eq!(restored.wallet_lib().get_timelocked_utxo_list().len(), 2);
+    }
+
+    #[test]
+    fn restore_policy_spends() {
+        let mut wallet = funded_wallet();
+        wallet.send_coins(DEST_ADDR.to_owned(), 10_000_000, true, SendOptions::default()).unwrap();
+        assert_eq!(wallet.wallet_lib().spent_in_window(), 10_000_000);
+
+        // the payments made before count against the daily limit of the restored wallet
+        let restored = restore_backup_of(&wallet, "rust_wallet_backup_policy_spends");
+        assert_eq!(restored.wallet_lib().spent_in_window(), 10_000_000);
+    }
+
+    #[test]
+    fn restore_pending_spends() {
+        let mut wallet = funded_wallet();
+        let policy = SpendingPolicy {
+            approval_threshold: Some(5_000_000),
+            ..SpendingPolicy::default()
+        };
+        wallet.wallet_lib_mut().set_spending_policy(policy).unwrap();
+        fn send(wallet: &mut WalletWithTrustedFullNode<MemoryChain>) -> u64 {
+            let options = SendOptions::default();
+            match wallet.send_coins(DEST_ADDR.to_owned(), 10_000_000, true, options) {
+                Ok(SendOutcome::ApprovalRequired(id)) => id,
+                _ => panic!("the payment doesn't wait for approval"),
+            }
+        }
+        let id = send(&mut wallet);
+
+        let mut restored = restore_backup_of(&wallet, "rust_wallet_backup_pending_spends");
+        let pending = restored.wallet_lib().get_pending_spends();
+        assert_eq!(pending.len(), 1);
+        assert_eq!((pending[0].id, pending[0].amt), (id, 10_000_000));
+        // the ids go on after the ones given out already
+        assert_eq!(send(&mut restored), id + 1);
+    }
+
+    #[test]
+    fn restore_unsigned_txs() {
+        let mut wallet = funded_wallet();
+        let (unsigned, _) = wallet
+            .wallet_lib_mut()
+            .create_unsigned_tx(DEST_ADDR.to_owned(), 10_000_000, InputFilter::Any, None, None)
+            .unwrap();
+
+        let mut restored = restore_backup_of(&wallet, "rust_wallet_backup_unsigned_txs");
+        let queued = restored.wallet_lib().get_unsigned_txs();
+        assert_eq!(queued.len(), 1);
+        assert_eq!((queued[0].id, &queued[0].tx), (unsigned.id, &unsigned.tx));
+        let psbt = restored.wallet_lib().unsigned_psbt(unsigned.id).unwrap();
+        assert_eq!(psbt.global.unsigned_tx, unsigned.tx);
+
+        // the coin of the queued payment stays locked, the next payment gets the next id
+        let lib = restored.wallet_lib_mut();
+        let create = |lib: &mut Box<dyn WalletLibraryInterface + Send>| {
+            lib.create_unsigned_tx(DEST_ADDR.to_owned(), 10_000_000, InputFilter::Any, None, None)
+        };
+        assert!(create(lib).is_err());
+        lib.cancel_unsigned_tx(unsigned.id).unwrap();
+        assert_eq!(create(lib).unwrap().0.id, unsigned.id + 1);
+    }
+
+    #[test]
+    fn restore_idempotent_sends() {
+        let mut wallet = funded_wallet();
+        let outcome = wallet
+            .send_coins(DEST_ADDR.to_owned(), 10_000_000, true, SendOptions::default())
+            .unwrap();
+        let txid = outcome.clone().sent().unwrap().txid();
+        let key = "retry".to_owned();
+        let send = IdempotentSend::new(key, DEST_ADDR.to_owned(), 10_000_000, outcome);
+        wallet.wallet_lib_mut().put_idempotent_send(send);
+
+        // a retry of the call on the restored wallet doesn't pay a second time
+        let restored = restore_backup_of(&wallet, "rust_wallet_backup_idempotent_sends");
+        let send = restored.wallet_lib().get_idempotent_send("retry").unwrap();
+        assert_eq!((send.dest_addr.as_str(), send.amt), (DEST_ADDR, 10_000_000));
+        assert_eq!(send.outcome.sent().map(|sent| sent.txid()), Some(txid));
+        assert!(restored.wallet_lib().get_idempotent_send("other").is_none());
+    }
+}
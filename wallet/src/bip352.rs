@@ -0,0 +1,515 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Silent payments
+//!
+//! BIP352 addresses and the detection of the taproot outputs paying to them,
+//! such an output is spent by the spend key tweaked with the secret it shares with the sender
+//!
+use bitcoin::{
+    util::key::{PublicKey, PrivateKey},
+    blockdata::transaction::{OutPoint, Transaction, TxIn, TxOut},
+    network::constants::Network,
+    consensus::encode::serialize,
+};
+use secp256k1::Secp256k1;
+
+use std::{fmt, error::Error, collections::HashMap};
+
+use super::taproot::{self, tagged_hash};
+
+pub(crate) const CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+pub(crate) const BECH32M_CONST: u32 = 0x2bc830a3;
+/// x coordinate of H, the point of BIP341 nobody knows the key of. A script path spend
+/// with it as the internal key has no key to share a secret with
+const NUMS_H: [u8; 32] = [
+    0x50, 0x92, 0x9b, 0x74, 0xc1, 0xa0, 0x49, 0x54, 0xb7, 0x8b, 0x4b, 0x60, 0x35, 0xe9, 0x7a, 0x5e,
+    0x07, 0x8a, 0x5a, 0x0f, 0x28, 0xec, 0x96, 0xd5, 0x47, 0xbf, 0xee, 0x9a, 0xce, 0x80, 0x3a, 0xc0,
+];
+
+/// scan and spend public keys published as an `sp1...` (`tsp1...` on test networks) address
+#[derive(Clone, Debug, PartialEq)]
+pub struct SilentPaymentAddress {
+    pub scan: PublicKey,
+    pub spend: PublicKey,
+    pub network: Network,
+}
+
+impl fmt::Display for SilentPaymentAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let hrp = if self.network == Network::Bitcoin { "sp" } else { "tsp" };
+        let mut keys = self.scan.key.serialize().to_vec();
+        keys.extend_from_slice(&self.spend.key.serialize());
+        // version 0
+        let mut data = vec![0];
+        data.extend(to_base32(&keys));
+        f.write_str(&bech32m_encode(hrp, &data))
+    }
+}
+
+/// the outputs spent by the taproot inputs of `tx`, whose keys `scan` takes from them.
+/// None are needed unless `tx` pays to a taproot output
+pub fn spent_taproot_outputs(tx: &Transaction) -> Vec<OutPoint> {
+    if !tx.output.iter().any(|output| taproot::output_key(&output.script_pubkey).is_some()) {
+        return Vec::new();
+    }
+    tx.input
+        .iter()
+        .filter(|input| match input_key(input) {
+            InputKey::InSpentOutput => true,
+            _ => false,
+        })
+        .map(|input| input.previous_output)
+        .collect()
+}
+
+/// outputs of the transaction paying to the address of the keys,
+/// by output index along with the tweak of the spend key.
+/// `spent` has the outputs the taproot inputs spend, see `spent_taproot_outputs`
+pub fn scan(
+    tx: &Transaction,
+    spent: &HashMap<OutPoint, TxOut>,
+    scan_sk: &PrivateKey,
+    spend_pk: &PublicKey,
+) -> Result<Vec<(u32, [u8; 32])>, Box<dyn Error>> {
+    let output_keys: Vec<(u32, &[u8])> = tx
+        .output
+        .iter()
+        .enumerate()
+        .filter_map(|(i, output)| taproot::output_key(&output.script_pubkey).map(|x| (i as u32, x)))
+        .collect();
+    if output_keys.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut input_keys = Vec::new();
+    for input in &tx.input {
+        match input_key(input) {
+            InputKey::Eligible(pk) => input_keys.push(pk),
+            InputKey::Ineligible => {}
+            InputKey::InSpentOutput => {
+                // the sum of the keys is unknown without it, no payment can be found
+                let op = input.previous_output;
+                let output = spent.get(&op).ok_or_else(|| {
+                    format!("the output spent by {}:{} is unknown", op.txid, op.vout)
+                })?;
+                // the witness of another output type may look like a taproot one
+                if let Some(x) = taproot::output_key(&output.script_pubkey) {
+                    // the key with the even y coordinate
+                    let mut key = vec![0x02];
+                    key.extend_from_slice(x);
+                    if let Ok(pk) = PublicKey::from_slice(&key) {
+                        input_keys.push(pk);
+                    }
+                }
+            }
+        }
+    }
+    let (first, rest) = match input_keys.split_first() {
+        Some(keys) => keys,
+        None => return Ok(Vec::new()),
+    };
+    let mut sum = first.key;
+    for pk in rest {
+        sum = sum.combine(&pk.key)?;
+    }
+
+    let ctx = Secp256k1::new();
+    let smallest_outpoint = tx
+        .input
+        .iter()
+        .map(|input| serialize(&input.previous_output))
+        .min()
+        .unwrap();
+    let input_hash = tagged_hash("BIP0352/Inputs", &[&smallest_outpoint, &sum.serialize()]);
+    let mut shared_secret = sum;
+    shared_secret.mul_assign(&ctx, &input_hash)?;
+    shared_secret.mul_assign(&ctx, &scan_sk.key[..])?;
+
+    // the k-th output paying to the address has the tweak of index k, they are looked up in turn
+    let mut found = Vec::new();
+    for k in 0u32.. {
+        let tweak = tagged_hash(
+            "BIP0352/SharedSecret",
+            &[&shared_secret.serialize(), &k.to_be_bytes()],
+        );
+        let mut pk = spend_pk.key;
+        pk.add_exp_assign(&ctx, &tweak)?;
+        let x = &pk.serialize()[1..];
+        match output_keys.iter().find(|(_, output_key)| *output_key == x) {
+            Some((vout, _)) => found.push((*vout, tweak)),
+            None => break,
+        }
+    }
+    Ok(found)
+}
+
+/// key spending an output found by `scan`
+pub fn spend_key(spend_sk: &PrivateKey, tweak: &[u8; 32]) -> Result<PrivateKey, Box<dyn Error>> {
+    let mut sk = *spend_sk;
+    sk.key.add_assign(tweak)?;
+    Ok(sk)
+}
+
+enum InputKey {
+    Eligible(PublicKey),
+    Ineligible,
+    // a taproot spend, its key is in the spent output which isn't part of the transaction
+    InSpentOutput,
+}
+
+fn input_key(input: &TxIn) -> InputKey {
+    let script_sig = input.script_sig.as_bytes();
+    let witness = &input.witness;
+
+    // P2WPKH and P2SH-P2WPKH, the witness is a signature and a compressed key
+    let nested_p2wpkh = script_sig.len() == 23 && script_sig[..3] == [0x16, 0x00, 0x14];
+    if witness.len() == 2 && witness[1].len() == 33 && (script_sig.is_empty() || nested_p2wpkh) {
+        return compressed_key(&witness[1]);
+    }
+
+    // P2PKH, the key is pushed last
+    if witness.is_empty() {
+        let len = script_sig.len();
+        if len > 34 && script_sig[len - 34] == 33 {
+            return compressed_key(&script_sig[len - 33..]);
+        }
+        return InputKey::Ineligible;
+    }
+
+    if !script_sig.is_empty() {
+        return InputKey::Ineligible;
+    }
+    let mut witness = &witness[..];
+    if witness.len() > 1 && witness[witness.len() - 1].first() == Some(&0x50) {
+        // annex
+        witness = &witness[..witness.len() - 1];
+    }
+    let last = &witness[witness.len() - 1];
+    let key_path = witness.len() == 1 && (last.len() == 64 || last.len() == 65);
+    let control_block = witness.len() > 1
+        && last.len() >= 33
+        && (last.len() - 33) % 32 == 0
+        && last[0] & 0xfe == 0xc0;
+    if control_block && last[1..33] == NUMS_H {
+        InputKey::Ineligible
+    } else if key_path || control_block {
+        InputKey::InSpentOutput
+    } else {
+        InputKey::Ineligible
+    }
+}
+
+fn compressed_key(data: &[u8]) -> InputKey {
+    match data.first() {
+        Some(0x02) | Some(0x03) => PublicKey::from_slice(data)
+            .map(InputKey::Eligible)
+            .unwrap_or(InputKey::Ineligible),
+        _ => InputKey::Ineligible,
+    }
+}
+
+// regroup bytes into 5 bit values, the last one padded with zeroes
+pub(crate) fn to_base32(data: &[u8]) -> Vec<u8> {
+    let mut values = Vec::new();
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    for byte in data {
+        acc = (acc << 8) | *byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            values.push(((acc >> bits) & 31) as u8);
+        }
+    }
+    if bits > 0 {
+        values.push(((acc << (5 - bits)) & 31) as u8);
+    }
+    values
+}
+
+pub(crate) fn polymod(values: &[u8]) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    let mut chk: u32 = 1;
+    for value in values {
+        let top = chk >> 25;
+        chk = ((chk & 0x1ffffff) << 5) ^ *value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= generator;
+            }
+        }
+    }
+    chk
+}
+
+// BIP350, silent payment addresses exceed the 90 characters limit of segwit addresses
+pub(crate) fn bech32m_encode(hrp: &str, data: &[u8]) -> String {
+    let mut values: Vec<u8> = hrp.bytes().map(|c| c >> 5).collect();
+    values.push(0);
+    values.extend(hrp.bytes().map(|c| c & 31));
+    values.extend_from_slice(data);
+    values.extend_from_slice(&[0; 6]);
+    let checksum = polymod(&values) ^ BECH32M_CONST;
+
+    let mut encoded = hrp.to_owned();
+    encoded.push('1');
+    let checksum_values = (0..6).map(|i| ((checksum >> (5 * (5 - i))) & 31) as u8);
+    for value in data.iter().cloned().chain(checksum_values) {
+        encoded.push(CHARSET[value as usize] as char);
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::{
+        util::key::{PublicKey, PrivateKey},
+        blockdata::transaction::{OutPoint, Transaction, TxIn, TxOut},
+        blockdata::script::Script,
+        network::constants::Network,
+        consensus::encode::serialize,
+    };
+    use bitcoin_hashes::{sha256d::Hash as Sha256dHash, hex::FromHex};
+    use secp256k1::{Secp256k1, SecretKey};
+    use std::collections::HashMap;
+    use crate::taproot::{self, tagged_hash};
+
+    fn key(byte: u8) -> PrivateKey {
+        PrivateKey {
+            compressed: true,
+            network: Network::Regtest,
+            key: SecretKey::from_slice(&[byte; 32]).unwrap(),
+        }
+    }
+
+    fn public(sk: &PrivateKey) -> PublicKey {
+        PublicKey::from_private_key(&Secp256k1::new(), sk)
+    }
+
+    #[test]
+    fn bech32m() {
+        assert_eq!(super::bech32m_encode("a", &[]), "a1lqfn3a");
+        let data: Vec<u8> = (0..32).rev().collect();
+        assert_eq!(
+            super::bech32m_encode("abcdef", &data),
+            "abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx"
+        );
+    }
+
+    #[test]
+    fn scan_finds_payment() {
+        let ctx = Secp256k1::new();
+        let (scan_sk, spend_sk) = (key(1), key(2));
+        let address = super::SilentPaymentAddress {
+            scan: public(&scan_sk),
+            spend: public(&spend_sk),
+            network: Network::Regtest,
+        };
+        assert!(address.to_string().starts_with("tsp1q"));
+
+        // the sender spends a P2WPKH coin and derives the output from the scan key
+        let input_sk = key(3);
+        let out_point = OutPoint {
+            txid: Default::default(),
+            vout: 1,
+        };
+        let input_hash = tagged_hash(
+            "BIP0352/Inputs",
+            &[&serialize(&out_point), &public(&input_sk).key.serialize()],
+        );
+        let mut shared_secret = address.scan.key;
+        shared_secret.mul_assign(&ctx, &input_hash).unwrap();
+        shared_secret.mul_assign(&ctx, &input_sk.key[..]).unwrap();
+        let tweak = tagged_hash(
+            "BIP0352/SharedSecret",
+            &[&shared_secret.serialize(), &0u32.to_be_bytes()],
+        );
+        let mut output_key = address.spend;
+        output_key.key.add_exp_assign(&ctx, &tweak).unwrap();
+
+        let tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output: out_point,
+                script_sig: Script::new(),
+                sequence: 0xFFFFFFFF,
+                witness: vec![vec![0x30; 71], public(&input_sk).key.serialize().to_vec()],
+            }],
+            output: vec![
+                TxOut {
+                    value: 1000,
+                    script_pubkey: Script::from(vec![0x51]),
+                },
+                TxOut {
+                    value: 50_000,
+                    script_pubkey: taproot::output_script(&output_key),
+                },
+            ],
+        };
+        let no_spent = HashMap::new();
+        let found = super::scan(&tx, &no_spent, &scan_sk, &address.spend).unwrap();
+        assert_eq!(found, vec![(1, tweak)]);
+
+        // the tweaked spend key controls the output
+        let sk = super::spend_key(&spend_sk, &tweak).unwrap();
+        assert_eq!(taproot::output_script(&public(&sk)), tx.output[1].script_pubkey);
+
+        // other scan keys find nothing
+        assert!(super::scan(&tx, &no_spent, &key(4), &address.spend).unwrap().is_empty());
+    }
+
+    // keys shared by the test vectors of BIP352
+    fn vector_keys() -> (PrivateKey, PublicKey) {
+        let scan_sk = hex_key("0f694e068028a717f8af6b9411f9a133dd3565258714cc226594b34db90c1f2c");
+        let spend_sk = hex_key("9d6ad855ce3417ef84e836892e5a56392bfba05fa5d97ccea30e266f540e08b3");
+        let spend_pk = public(&spend_sk);
+        assert_eq!(
+            hex::encode(&public(&scan_sk).key.serialize()[..]),
+            "0220bcfac5b99e04ad1a06ddfb016ee13582609d60b6291e98d01a9bc9a16c96d4"
+        );
+        assert_eq!(
+            hex::encode(&spend_pk.key.serialize()[..]),
+            "025cc9856d6f8375350e123978daac200c260cb5b5ae83106cab90484dcd8fcf36"
+        );
+        (scan_sk, spend_pk)
+    }
+
+    fn hex_key(sk: &str) -> PrivateKey {
+        PrivateKey {
+            compressed: true,
+            network: Network::Bitcoin,
+            key: SecretKey::from_slice(&hex::decode(sk).unwrap()).unwrap(),
+        }
+    }
+
+    fn out_point(txid: &str) -> OutPoint {
+        OutPoint {
+            txid: Sha256dHash::from_hex(txid).unwrap(),
+            vout: 0,
+        }
+    }
+
+    fn vector_tx(input: Vec<TxIn>, output_key: &str) -> Transaction {
+        let mut script = vec![0x51, 0x20];
+        script.extend(hex::decode(output_key).unwrap());
+        Transaction {
+            version: 2,
+            lock_time: 0,
+            input,
+            output: vec![TxOut {
+                value: 50_000,
+                script_pubkey: Script::from(script),
+            }],
+        }
+    }
+
+    // the signatures of the vectors aren't part of the scan, placeholders of their size stand in
+    #[test]
+    fn vector_p2pkh_inputs() {
+        let (scan_sk, spend_pk) = vector_keys();
+        let input = |txid: &str, sk: &str| {
+            let mut script_sig = vec![71];
+            script_sig.extend(vec![0x30; 71]);
+            script_sig.push(33);
+            script_sig.extend(&public(&hex_key(sk)).key.serialize()[..]);
+            TxIn {
+                previous_output: out_point(txid),
+                script_sig: Script::from(script_sig),
+                sequence: 0xFFFFFFFF,
+                witness: Vec::new(),
+            }
+        };
+        let tx = vector_tx(
+            vec![
+                input(
+                    "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16",
+                    "eadc78165ff1f8ea94ad7cfdc54990738a4c53f6e0507b42154201b8e5dff3b1",
+                ),
+                input(
+                    "a1075db55d416d3ca199f55b6084e2115b9345e16c5cf302fc80e9d5fbf5d48d",
+                    "93f5ed907ad5b2bdbbdcb5d9116ebc0a4e1f92f910d5260237fa45a9408aad16",
+                ),
+            ],
+            "3e9fce73d4e77a4809908e3c3a2e54ee147b9312dc5044a193d1fc85de46e3c1",
+        );
+        assert!(super::spent_taproot_outputs(&tx).is_empty());
+        let found = super::scan(&tx, &HashMap::new(), &scan_sk, &spend_pk).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, 0);
+        assert_eq!(
+            hex::encode(&found[0].1[..]),
+            "f438b40179a3c4262de12986c0e6cce0634007cdc79c1dcd3e20b9ebc2e7eef6"
+        );
+    }
+
+    #[test]
+    fn vector_taproot_inputs() {
+        let (scan_sk, spend_pk) = vector_keys();
+        let inputs = [
+            (
+                "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16",
+                "51205a1e61f898173040e20616d43e9f496fba90338a39faa1ed98fcbaeee4dd9be5",
+            ),
+            (
+                "a1075db55d416d3ca199f55b6084e2115b9345e16c5cf302fc80e9d5fbf5d48d",
+                "5120782eeb913431ca6e9b8c2fd80a5f72ed2024ef72a3c6fb10263c379937323338",
+            ),
+        ];
+        let tx = vector_tx(
+            inputs
+                .iter()
+                .map(|(txid, _)| TxIn {
+                    previous_output: out_point(txid),
+                    script_sig: Script::new(),
+                    sequence: 0xFFFFFFFF,
+                    witness: vec![vec![0x01; 64]],
+                })
+                .collect(),
+            "de88bea8e7ffc9ce1af30d1132f910323c505185aec8eae361670421e749a1fb",
+        );
+        let spent: HashMap<OutPoint, TxOut> = inputs
+            .iter()
+            .map(|(txid, script)| {
+                let output = TxOut {
+                    value: 100_000,
+                    script_pubkey: Script::from(hex::decode(script).unwrap()),
+                };
+                (out_point(txid), output)
+            })
+            .collect();
+        let expected: Vec<OutPoint> = inputs.iter().map(|(txid, _)| out_point(txid)).collect();
+        assert_eq!(super::spent_taproot_outputs(&tx), expected);
+
+        // without the spent outputs the keys are unknown
+        assert!(super::scan(&tx, &HashMap::new(), &scan_sk, &spend_pk).is_err());
+        let found = super::scan(&tx, &spent, &scan_sk, &spend_pk).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, 0);
+        assert_eq!(
+            hex::encode(&found[0].1[..]),
+            "3fb9ce5ce1746ced103c8ed254e81f6690764637ddbc876ec1f9b3ddab776b03"
+        );
+
+        // a script path spend with the internal key of nobody shares no secret
+        let mut nums_tx = tx.clone();
+        let mut control_block = vec![0xc0];
+        control_block.extend_from_slice(&super::NUMS_H);
+        nums_tx.input[1].witness = vec![vec![0x01; 64], vec![0x51], control_block];
+        let found = super::scan(&nums_tx, &spent, &scan_sk, &spend_pk).unwrap();
+        assert!(found.is_empty());
+    }
+}
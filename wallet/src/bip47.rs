@@ -0,0 +1,352 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Reusable payment codes
+//!
+//! BIP47 version 1 payment codes, the addresses two payment codes derive for each other
+//! and the blinding of a payment code in a notification transaction
+//!
+use bitcoin::{
+    util::{
+        base58,
+        bip32::{ExtendedPubKey, ChildNumber, ChainCode},
+        key::{PublicKey, PrivateKey},
+    },
+    blockdata::transaction::{OutPoint, Transaction, TxIn},
+    blockdata::script::{Script, Builder},
+    blockdata::opcodes,
+    network::constants::Network,
+    consensus::encode::serialize,
+};
+use crypto::digest::Digest;
+use crypto::hmac::Hmac;
+use crypto::mac::Mac;
+use crypto::sha2::{Sha256, Sha512};
+use secp256k1::Secp256k1;
+use zeroize::Zeroizing;
+
+use std::{fmt, error::Error, str::FromStr};
+
+use super::error::WalletError;
+
+/// base58check version byte of a payment code, makes its string start with "PM8T"
+const VERSION_BYTE: u8 = 0x47;
+const PAYLOAD_LEN: usize = 80;
+// the key x coordinate and the chain code, the blinded part of the payload
+const BLINDED_RANGE: std::ops::Range<usize> = 3..67;
+
+/// public key and chain code of the extended key m/47'/coin'/account'
+#[derive(Clone, Debug, PartialEq)]
+pub struct PaymentCode {
+    public_key: PublicKey,
+    chain_code: ChainCode,
+}
+
+impl PaymentCode {
+    pub fn from_xpub(xpub: &ExtendedPubKey) -> Self {
+        PaymentCode {
+            public_key: xpub.public_key,
+            chain_code: xpub.chain_code,
+        }
+    }
+
+    /// version 1 without features: version, features, compressed key, chain code, reserved
+    pub fn to_payload(&self) -> [u8; PAYLOAD_LEN] {
+        let mut payload = [0u8; PAYLOAD_LEN];
+        payload[0] = 0x01;
+        payload[2..35].copy_from_slice(&self.public_key.key.serialize());
+        payload[35..67].copy_from_slice(&self.chain_code[..]);
+        payload
+    }
+
+    pub fn from_payload(payload: &[u8]) -> Result<Self, WalletError> {
+        if payload.len() != PAYLOAD_LEN || payload[0] != 0x01 {
+            return Err(WalletError::InvalidPaymentCode);
+        }
+        // the 33 bytes parse as a compressed key only
+        let public_key =
+            PublicKey::from_slice(&payload[2..35]).map_err(|_| WalletError::InvalidPaymentCode)?;
+        Ok(PaymentCode {
+            public_key,
+            chain_code: ChainCode::from(&payload[35..67]),
+        })
+    }
+
+    /// public key of the child `index`, the child 0 is the notification key
+    pub fn child_key(&self, index: u32) -> Result<PublicKey, WalletError> {
+        let xpub = ExtendedPubKey {
+            network: Network::Bitcoin,
+            depth: 3,
+            parent_fingerprint: Default::default(),
+            child_number: ChildNumber::Hardened { index: 0 },
+            public_key: self.public_key,
+            chain_code: self.chain_code,
+        };
+        xpub.ckd_pub(&Secp256k1::new(), ChildNumber::Normal { index })
+            .map(|child| child.public_key)
+            .map_err(WalletError::KeyDerivation)
+    }
+}
+
+impl fmt::Display for PaymentCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut data = vec![VERSION_BYTE];
+        data.extend_from_slice(&self.to_payload());
+        f.write_str(&base58::check_encode_slice(&data))
+    }
+}
+
+impl FromStr for PaymentCode {
+    type Err = WalletError;
+
+    fn from_str(s: &str) -> Result<Self, WalletError> {
+        let data = base58::from_check(s).map_err(|_| WalletError::InvalidPaymentCode)?;
+        if data.first() != Some(&VERSION_BYTE) {
+            return Err(WalletError::InvalidPaymentCode);
+        }
+        PaymentCode::from_payload(&data[1..])
+    }
+}
+
+/// key of the `index`-th address paying to `recipient`,
+/// `sk` is the notification key of the sender (its child 0)
+pub fn send_key(
+    sk: &PrivateKey,
+    recipient: &PaymentCode,
+    index: u32,
+) -> Result<PublicKey, Box<dyn Error>> {
+    let mut pk = recipient.child_key(index)?;
+    let secret = shared_secret(sk, &pk)?;
+    pk.key.add_exp_assign(&Secp256k1::new(), &secret[..])?;
+    Ok(pk)
+}
+
+/// key of the address `sender` pays to,
+/// `sk` is the child of the recipient with the index of the address
+pub fn receive_key(sk: &PrivateKey, sender: &PaymentCode) -> Result<PrivateKey, Box<dyn Error>> {
+    let secret = shared_secret(sk, &sender.child_key(0)?)?;
+    let mut sk = *sk;
+    sk.key.add_assign(&secret[..])?;
+    Ok(sk)
+}
+
+// SHA256 of the x coordinate of the point the keys share (ECDH)
+fn shared_secret(sk: &PrivateKey, pk: &PublicKey) -> Result<Zeroizing<[u8; 32]>, Box<dyn Error>> {
+    let mut point = pk.key;
+    point.mul_assign(&Secp256k1::new(), &sk.key[..])?;
+    let mut secret = Zeroizing::new([0u8; 32]);
+    let mut sha = Sha256::new();
+    sha.input(&point.serialize()[1..]);
+    sha.result(&mut *secret);
+    Ok(secret)
+}
+
+/// payload of `code` blinded for the notification key `pk` of the recipient,
+/// `sk` is the key of the designated input spending `out_point`
+pub fn blind(
+    code: &PaymentCode,
+    sk: &PrivateKey,
+    pk: &PublicKey,
+    out_point: &OutPoint,
+) -> Result<[u8; PAYLOAD_LEN], Box<dyn Error>> {
+    let mut payload = code.to_payload();
+    xor_blinding_factor(&mut payload, sk, pk, out_point)?;
+    Ok(payload)
+}
+
+/// payment code from a blinded payload, `sk` is the notification key of the recipient
+/// and `pk` is the key of the designated input spending `out_point`
+pub fn unblind(
+    payload: &[u8],
+    sk: &PrivateKey,
+    pk: &PublicKey,
+    out_point: &OutPoint,
+) -> Result<PaymentCode, Box<dyn Error>> {
+    if payload.len() != PAYLOAD_LEN {
+        return Err(From::from(WalletError::InvalidPaymentCode));
+    }
+    let mut unblinded = [0u8; PAYLOAD_LEN];
+    unblinded.copy_from_slice(payload);
+    xor_blinding_factor(&mut unblinded, sk, pk, out_point)?;
+    Ok(PaymentCode::from_payload(&unblinded)?)
+}
+
+// HMAC-SHA512 of the x coordinate of the shared point keyed by the outpoint,
+// xoring with it twice gives back the payload
+fn xor_blinding_factor(
+    payload: &mut [u8; PAYLOAD_LEN],
+    sk: &PrivateKey,
+    pk: &PublicKey,
+    out_point: &OutPoint,
+) -> Result<(), Box<dyn Error>> {
+    let mut point = pk.key;
+    point.mul_assign(&Secp256k1::new(), &sk.key[..])?;
+
+    let mut mac = Hmac::new(Sha512::new(), &serialize(out_point));
+    mac.input(&point.serialize()[1..]);
+    let mut factor = Zeroizing::new([0u8; 64]);
+    mac.raw_result(&mut *factor);
+
+    for (byte, mask) in payload[BLINDED_RANGE].iter_mut().zip(factor.iter()) {
+        *byte ^= mask;
+    }
+    Ok(())
+}
+
+/// OP_RETURN output script carrying a blinded payment code
+pub fn notification_script(payload: &[u8; PAYLOAD_LEN]) -> Script {
+    Builder::new()
+        .push_opcode(opcodes::all::OP_RETURN)
+        .push_slice(payload)
+        .into_script()
+}
+
+/// blinded payment code carried by the transaction, if it is a notification transaction
+pub fn find_payload(tx: &Transaction) -> Option<&[u8]> {
+    // OP_RETURN OP_PUSHDATA1 80 <payload>
+    tx.output
+        .iter()
+        .map(|output| output.script_pubkey.as_bytes())
+        .find(|script| {
+            script.len() == 3 + PAYLOAD_LEN
+                && script[0] == 0x6a
+                && script[1] == 0x4c
+                && script[2] as usize == PAYLOAD_LEN
+        })
+        .map(|script| &script[3..])
+}
+
+/// the first input exposing its public key, its key blinds the payment code
+pub fn designated_input(tx: &Transaction) -> Option<(OutPoint, PublicKey)> {
+    tx.input
+        .iter()
+        .filter_map(|input| input_key(input).map(|pk| (input.previous_output, pk)))
+        .next()
+}
+
+// the key is the last item of the witness of P2WPKH and P2SH-P2WPKH,
+// and the last push of the script sig of P2PKH
+fn input_key(input: &TxIn) -> Option<PublicKey> {
+    if input.witness.len() == 2 {
+        return PublicKey::from_slice(&input.witness[1]).ok();
+    }
+    let script = input.script_sig.as_bytes();
+    [33, 65]
+        .iter()
+        .filter(|&&len| script.len() > len && script[script.len() - len - 1] as usize == len)
+        .filter_map(|&len| PublicKey::from_slice(&script[script.len() - len..]).ok())
+        .next()
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::network::constants::Network;
+    use bitcoin::util::address::Address;
+    use bitcoin::util::key::PublicKey;
+    use bitcoin::OutPoint;
+    use bitcoin::util::bip32::{ChildNumber, ExtendedPrivKey};
+    use secp256k1::Secp256k1;
+    use std::str::FromStr;
+    use crate::keyfactory::{KeyFactory, Seed};
+    use crate::mnemonic::Mnemonic;
+    use super::PaymentCode;
+
+    const ALICE_CODE: &str = "PM8TJTLJbPRGxSbc8EJi42Wrr6QbNSaSSVJ5Y3E4pbCYiTHUskHg13935Ubb7q8tx9GVbh2UuRnBc3WSyJHhUrw8KhprKnn9eDznYGieTzFcwQRya4GA";
+    const BOB_CODE: &str = "PM8TJS2JxQ5ztXUpBBRnpTbcUXbUHy2T1abfrb3KkAAtMEGNbey4oumH7Hc578WgQJhPjBxteQ5GHHToTYHE3A1w6p7tU6KSoFmWBVbFGjKPisZDbP97";
+
+    // m/47'/0'/0' of the test vector mnemonic
+    fn payment_code_key(phrase: &str) -> ExtendedPrivKey {
+        let seed = Seed::new(&Mnemonic::from(phrase).unwrap(), "");
+        let master = KeyFactory::master_private_key(Network::Bitcoin, &seed).unwrap();
+        let path = [
+            ChildNumber::Hardened { index: 47 },
+            ChildNumber::Hardened { index: 0 },
+            ChildNumber::Hardened { index: 0 },
+        ];
+        master.derive_priv(&Secp256k1::new(), &path).unwrap()
+    }
+
+    #[test]
+    fn bip47_test_vectors() {
+        let alice = payment_code_key(
+            "response seminar brave tip suit recall often sound stick owner lottery motion",
+        );
+        let bob = payment_code_key(
+            "reward upper indicate eight swift arch injury crystal super wrestle already dentist",
+        );
+        let alice_code =
+            PaymentCode::from_xpub(&KeyFactory::extended_public_from_private(&alice));
+        let bob_code = PaymentCode::from_xpub(&KeyFactory::extended_public_from_private(&bob));
+        assert_eq!(alice_code.to_string(), ALICE_CODE);
+        assert_eq!(bob_code.to_string(), BOB_CODE);
+        assert_eq!(PaymentCode::from_str(BOB_CODE).unwrap(), bob_code);
+
+        let notification = Address::p2pkh(&bob_code.child_key(0).unwrap(), Network::Bitcoin);
+        assert_eq!(notification.to_string(), "1ChvUUvht2hUQufHBXF8NgLhW8SwE2ecGV");
+
+        let alice_sk = KeyFactory::private_child(&alice, ChildNumber::Normal { index: 0 })
+            .unwrap()
+            .private_key;
+        let expected = ["141fi7TY3h936vRUKh1qfUZr8rSBuYbVBK", "12u3Uued2fuko2nY4SoSFGCoGLCBUGPkk6"];
+        for (index, expected) in expected.iter().enumerate() {
+            let pk = super::send_key(&alice_sk, &bob_code, index as u32).unwrap();
+            assert_eq!(Address::p2pkh(&pk, Network::Bitcoin).to_string(), *expected);
+
+            // Bob gets the private key of the same address
+            let child = ChildNumber::Normal { index: index as u32 };
+            let bob_sk = KeyFactory::private_child(&bob, child).unwrap().private_key;
+            let sk = super::receive_key(&bob_sk, &alice_code).unwrap();
+            assert_eq!(PublicKey::from_private_key(&Secp256k1::new(), &sk), pk);
+        }
+    }
+
+    #[test]
+    fn blinding() {
+        let alice = payment_code_key(
+            "response seminar brave tip suit recall often sound stick owner lottery motion",
+        );
+        let bob = payment_code_key(
+            "reward upper indicate eight swift arch injury crystal super wrestle already dentist",
+        );
+        let alice_code =
+            PaymentCode::from_xpub(&KeyFactory::extended_public_from_private(&alice));
+        let bob_notification = KeyFactory::private_child(&bob, ChildNumber::Normal { index: 0 })
+            .unwrap()
+            .private_key;
+
+        // any key of Alice may spend the designated input
+        let input_sk = KeyFactory::private_child(&alice, ChildNumber::Normal { index: 7 })
+            .unwrap()
+            .private_key;
+        let out_point = OutPoint::null();
+        let payload = super::blind(
+            &alice_code,
+            &input_sk,
+            &PublicKey::from_private_key(&Secp256k1::new(), &bob_notification),
+            &out_point,
+        )
+        .unwrap();
+        assert_ne!(&payload[..], &alice_code.to_payload()[..]);
+
+        let unblinded = super::unblind(
+            &payload,
+            &bob_notification,
+            &PublicKey::from_private_key(&Secp256k1::new(), &input_sk),
+            &out_point,
+        )
+        .unwrap();
+        assert_eq!(unblinded, alice_code);
+    }
+}
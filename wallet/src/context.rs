@@ -1,30 +1,137 @@
 use super::{
-    interface::Wallet,
+    interface::{Wallet, WalletLibraryInterface, BlockChainIO, BackendConfig, BackendKind},
     default::WalletWithTrustedFullNode,
-    electrumx::ElectrumxWallet,
+    electrumx::{self, ElectrumxWallet},
+    headers,
     walletlibrary::WalletConfig,
     walletlibrary::WalletLibraryMode,
     mnemonic::Mnemonic,
+    account::AccountAddressType,
+    retry::RetryPolicy,
+    quorum::Quorum,
 };
 use bitcoin_rpc_client::{Client, Auth, Error as BitcoinError};
-use std::{process::{Child, Command}, error::Error, io, net::SocketAddr};
+use bitcoin_rpc_client::jsonrpc::{
+    Client as JsonRpcClient, Error as JsonRpcError, simple_http::SimpleHttpTransport,
+};
+use std::{
+    process::{self, Child, Command},
+    error::Error,
+    io,
+    fs,
+    env,
+    net::{SocketAddr, TcpListener},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 use bitcoin::network::constants::Network;
 
+// the transport has one timeout for connecting and reading, a call of a node which
+// can't be reached fails after the read timeout as well
+fn bitcoind_client(
+    address: SocketAddr,
+    auth: Auth,
+    timeout: Duration,
+) -> Result<Client, BitcoinError> {
+    bitcoind_client_at(&format!("http://{}", address), auth, timeout)
+}
+
+// the credentials of `config`, `auth` if it has none
+fn config_auth(auth: &Auth, config: &BackendConfig) -> Auth {
+    match config.user {
+        Some(ref user) => {
+            let password = config.password.clone().unwrap_or_default();
+            Auth::UserPass(user.clone(), password)
+        }
+        None => auth.clone(),
+    }
+}
+
+// client of the endpoint `url` of bitcoind, e.g. the one of a wallet of the node
+fn bitcoind_client_at(url: &str, auth: Auth, timeout: Duration) -> Result<Client, BitcoinError> {
+    let (user, password) = auth.get_user_pass()?;
+    let mut transport = SimpleHttpTransport::builder()
+        .url(url)
+        .map_err(JsonRpcError::from)?
+        .timeout(timeout);
+    if let Some(user) = user {
+        transport = transport.auth(user, password);
+    }
+    Ok(Client::from_jsonrpc(JsonRpcClient::with_transport(transport.build())))
+}
+
+// a port nobody listens on at the moment, another process may take it before it is used
+fn free_port() -> io::Result<u16> {
+    Ok(TcpListener::bind("127.0.0.1:0")?.local_addr()?.port())
+}
+
+// directory in the system temp dir which no other context of any process uses
+fn unique_temp_dir() -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let name = format!(
+        "test_{}_{}_{}",
+        now.as_secs(),
+        process::id(),
+        NEXT.fetch_add(1, Ordering::SeqCst)
+    );
+    env::temp_dir().join(name)
+}
+
+fn zmq_endpoint(port: u16) -> String {
+    format!("tcp://127.0.0.1:{}", port)
+}
+
+/// takes over a wallet which left its backend by `Wallet::into_wallet_lib`
+pub type Attach =
+    Box<dyn FnOnce(Box<dyn WalletLibraryInterface + Send>) -> Box<dyn Wallet + Send> + Send>;
+
+#[derive(Clone)]
 pub struct GlobalContext {
     network: Network,
     bitcoin_auth: Auth,
     bitcoin_socket_address: SocketAddr,
     electrum_auth: String,
     electrum_socket_address: Option<SocketAddr>,
+    zmq_pub_raw_block: String,
+    zmq_pub_raw_tx: String,
+    /// directory bitcoind keeps its data in and the p2p port it listens on,
+    /// the defaults of bitcoind if `None`
+    bitcoind_data_dir: Option<PathBuf>,
+    bitcoind_p2p_port: Option<u16>,
     db_path: String,
     wallet_config: WalletConfig,
+    /// descriptor wallet of bitcoind the default wallets sync through, they scan the blocks
+    /// themselves if `None`
+    core_wallet: Option<String>,
+    /// other nodes the default wallets check their node against, see `quorum::Quorum`
+    witnesses: Vec<BackendConfig>,
+    max_lag: usize,
 }
 
 impl Default for GlobalContext {
+    /// regtest context of its own free ports and temp directory, tests using it
+    /// may run in parallel with each other and with a node on the default ports
     fn default() -> Self {
         let user = "devuser".to_owned();
         let password = "devpass".to_owned();
-        GlobalContext::new(Network::Regtest, user, password, None, None, None)
+        let dir = unique_temp_dir();
+        let port = || free_port().unwrap();
+        let mut context = GlobalContext::new(
+            Network::Regtest,
+            user,
+            password,
+            Some(dir.join("wallet").to_string_lossy().into_owned()),
+            Some(SocketAddr::from(([127, 0, 0, 1], port()))),
+            Some(SocketAddr::from(([127, 0, 0, 1], port()))),
+        );
+        context.zmq_pub_raw_block = zmq_endpoint(port());
+        context.zmq_pub_raw_tx = zmq_endpoint(port());
+        context.bitcoind_data_dir = Some(dir.join("bitcoind"));
+        context.bitcoind_p2p_port = Some(port());
+        context
     }
 }
 
@@ -38,13 +145,11 @@ impl GlobalContext {
         electrum_socket_address: Option<SocketAddr>,
     ) -> Self {
         use super::walletlibrary::WalletConfigBuilder;
-        use std::time::{SystemTime, UNIX_EPOCH};
 
         let bitcoin_socket_address = bitcoin_socket_address.unwrap_or("127.0.0.1:18443".parse().unwrap());
         let auth = Auth::UserPass(user.clone(), password.clone());
 
-        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
-        let db_path = db_path.unwrap_or(format!("/tmp/test_{:?}", now.as_secs()));
+        let db_path = db_path.unwrap_or(unique_temp_dir().to_string_lossy().into_owned());
         let config = WalletConfigBuilder::new()
             .network(network.clone())
             .db_path(db_path.clone())
@@ -56,15 +161,135 @@ impl GlobalContext {
             bitcoin_socket_address: bitcoin_socket_address,
             electrum_auth: format!("{}:{}", user, password),
             electrum_socket_address: electrum_socket_address,
+            zmq_pub_raw_block: zmq_endpoint(18501),
+            zmq_pub_raw_tx: zmq_endpoint(18502),
+            bitcoind_data_dir: None,
+            bitcoind_p2p_port: None,
             db_path: db_path,
             wallet_config: config,
+            core_wallet: None,
+            witnesses: Vec::new(),
+            max_lag: 0,
         }
     }
 
-    pub fn bitcoind(&self, zmqpubrawblock: String, zmqpubrawtx: String) -> Result<Child, io::Error> {
-        use std::{thread, time::Duration};
-        use bitcoin_rpc_client::RpcApi;
+    /// account the change of the wallets made with this context goes to by default
+    pub fn set_change_addr_type(&mut self, change_addr_type: AccountAddressType) {
+        self.wallet_config.set_change_addr_type(change_addr_type);
+    }
+
+    /// the trusted full node wallets made with this context fetch blocks as raw hex
+    pub fn set_raw_blocks(&mut self, raw_blocks: bool) {
+        self.wallet_config.set_raw_blocks(raw_blocks);
+    }
+
+    /// the trusted full node wallets made with this context scan the blocks for silent payments
+    pub fn set_silent_payments(&mut self, silent_payments: bool) {
+        self.wallet_config.set_silent_payments(silent_payments);
+    }
+
+    /// the electrumx wallets made with this context check merkle proofs of their transactions
+    pub fn set_verify_proofs(&mut self, verify_proofs: bool) {
+        self.wallet_config.set_verify_proofs(verify_proofs);
+    }
+
+    /// the wallets made with this context drop their spent coins after so many confirmations
+    pub fn set_prune_spent_after(&mut self, confirmations: Option<usize>) {
+        self.wallet_config.set_prune_spent_after(confirmations);
+    }
+
+    /// the wallets made with this context spend the change of their own transactions
+    /// before it is confirmed
+    pub fn set_spend_unconfirmed_change(&mut self, spend: bool) {
+        self.wallet_config.set_spend_unconfirmed_change(spend);
+    }
+
+    /// the wallets made with this context select coins worth less than the fee of their input
+    pub fn set_spend_uneconomical(&mut self, spend: bool) {
+        self.wallet_config.set_spend_uneconomical(spend);
+    }
+
+    /// the wallets made with this context spend coins of one account per payment
+    pub fn set_privacy(&mut self, privacy: bool) {
+        self.wallet_config.set_privacy(privacy);
+    }
+
+    /// the wallets made with this context spend the coins of a reused address all together
+    pub fn set_avoid_reuse(&mut self, avoid_reuse: bool) {
+        self.wallet_config.set_avoid_reuse(avoid_reuse);
+    }
+
+    /// the wallets made with this context leave change below `tolerance` to the fee
+    /// and look for coins paying a payment exactly first
+    pub fn set_change_tolerance(&mut self, tolerance: Option<u64>) {
+        self.wallet_config.set_change_tolerance(tolerance);
+    }
+
+    /// the wallets made with this context pay `fee_rate` satoshi per virtual byte
+    pub fn set_fee_rate(&mut self, fee_rate: u64) {
+        self.wallet_config.set_fee_rate(fee_rate);
+    }
+
+    /// a wallet recovered with this context into a database of another seed replaces it
+    pub fn set_force_overwrite(&mut self, force_overwrite: bool) {
+        self.wallet_config.set_force_overwrite(force_overwrite);
+    }
+
+    /// the trusted full node wallets made with this context retry failed calls to bitcoind by it
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.wallet_config.set_retry_policy(retry_policy);
+    }
+
+    /// the clients of bitcoind and electrs made with this context give up on a backend
+    /// which doesn't accept the connection or answer a call in time
+    pub fn set_timeouts(&mut self, connect_timeout: Duration, read_timeout: Duration) {
+        self.wallet_config.set_timeouts(connect_timeout, read_timeout);
+    }
+
+    /// the default wallets made with this context sync through the watch-only descriptor
+    /// wallet `name` of bitcoind, see `WalletWithTrustedFullNode::set_core_wallet`
+    pub fn set_core_wallet(&mut self, name: Option<String>) {
+        self.core_wallet = name;
+    }
+
+    /// the default wallets made with this context publish their transactions through the
+    /// nodes `witnesses` as well and report the ones more than `max_lag` blocks away from
+    /// their node or on another chain. A witness without credentials takes the ones of the node
+    pub fn set_quorum(&mut self, witnesses: Vec<BackendConfig>, max_lag: usize) {
+        self.witnesses = witnesses;
+        self.max_lag = max_lag;
+    }
+
+    pub fn bitcoin_socket_address(&self) -> SocketAddr {
+        self.bitcoin_socket_address
+    }
+
+    /// `None` for the default port of the network
+    pub fn electrum_socket_address(&self) -> Option<SocketAddr> {
+        self.electrum_socket_address
+    }
+
+    /// endpoints bitcoind started by `spawn_bitcoind` publishes blocks and transactions on
+    pub fn zmq_endpoints(&self) -> (&str, &str) {
+        (&self.zmq_pub_raw_block, &self.zmq_pub_raw_tx)
+    }
+
+    pub fn set_zmq_endpoints(&mut self, zmq_pub_raw_block: String, zmq_pub_raw_tx: String) {
+        self.zmq_pub_raw_block = zmq_pub_raw_block;
+        self.zmq_pub_raw_tx = zmq_pub_raw_tx;
+    }
 
+    pub fn bitcoind_data_dir(&self) -> Option<&Path> {
+        self.bitcoind_data_dir.as_ref().map(PathBuf::as_path)
+    }
+
+    /// database of the wallets, electrs keeps its index there too
+    pub fn db_path(&self) -> &str {
+        &self.db_path
+    }
+
+    // command line of a bitcoind serving the wallets of this context
+    fn bitcoind_command(&self, zmqpubrawblock: &str, zmqpubrawtx: &str) -> Command {
         assert!(self.bitcoin_socket_address.ip().is_loopback());
 
         let auth_args = match &self.bitcoin_auth {
@@ -76,15 +301,38 @@ impl GlobalContext {
             ],
         };
 
-        let r = Command::new("bitcoind")
+        let mut command = Command::new("bitcoind");
+        command
             .args(&["-deprecatedrpc=generate"])
             .args(auth_args)
             .arg(format!("-{}", self.network.clone()))
             .arg(format!("-txindex"))
             .arg(format!("-rpcport={}", self.bitcoin_socket_address.port()))
             .arg(format!("-zmqpubrawblock={}", zmqpubrawblock))
-            .arg(format!("-zmqpubrawtx={}", zmqpubrawtx))
-            .spawn()?;
+            .arg(format!("-zmqpubrawtx={}", zmqpubrawtx));
+        if let Some(ref data_dir) = self.bitcoind_data_dir {
+            command.arg(format!("-datadir={}", data_dir.display()));
+        }
+        if let Some(port) = self.bitcoind_p2p_port {
+            command.arg(format!("-port={}", port));
+        }
+        command
+    }
+
+    /// bitcoind on the ports and in the data directory of this context,
+    /// unlike `bitcoind` it doesn't wait for the node to start
+    pub fn spawn_bitcoind(&self) -> Result<Child, io::Error> {
+        if let Some(ref data_dir) = self.bitcoind_data_dir {
+            fs::create_dir_all(data_dir)?;
+        }
+        self.bitcoind_command(&self.zmq_pub_raw_block, &self.zmq_pub_raw_tx).spawn()
+    }
+
+    pub fn bitcoind(&self, zmqpubrawblock: String, zmqpubrawtx: String) -> Result<Child, io::Error> {
+        use std::{thread, time::Duration};
+        use bitcoin_rpc_client::RpcApi;
+
+        let r = self.bitcoind_command(&zmqpubrawblock, &zmqpubrawtx).spawn()?;
         thread::sleep(Duration::from_millis(2_000));
 
         let _ = self.client().unwrap().generate(1, None).unwrap();
@@ -92,41 +340,142 @@ impl GlobalContext {
         Ok(r)
     }
 
-    pub fn electrs(&self) -> Result<Child, io::Error> {
-        use std::{thread, time::Duration};
-
-        const LAUNCH_ELECTRUMX_SERVER_DELAY_MS: u64 = 500;
-
+    // command line of an electrs indexing the bitcoind of this context
+    fn electrs_command(&self) -> Command {
         if let Some(ref address) = self.electrum_socket_address {
             assert!(address.ip().is_loopback());
         }
 
-        let electrs_process = Command::new("electrs")
+        let mut command = Command::new("electrs");
+        command
             .arg("--jsonrpc-import")
             .arg(format!("--cookie={}", self.electrum_auth))
             .arg(format!("--daemon-rpc-addr={}", self.bitcoin_socket_address))
             .arg(format!("--network={}", self.network))
             .arg(format!("--db-dir={}", self.db_path))
-            .args(self.electrum_socket_address.iter().map(|&address| format!("--electrum-rpc-addr={}", address)))
-            .spawn();
+            .args(
+                self.electrum_socket_address
+                    .iter()
+                    .map(|&address| format!("--electrum-rpc-addr={}", address)),
+            );
+        if let Some(ref data_dir) = self.bitcoind_data_dir {
+            command.arg(format!("--daemon-dir={}", data_dir.display()));
+        }
+        command
+    }
+
+    /// electrs indexing the bitcoind of `spawn_bitcoind`, doesn't wait for it to start either
+    pub fn spawn_electrs(&self) -> Result<Child, io::Error> {
+        self.electrs_command().spawn()
+    }
+
+    pub fn electrs(&self) -> Result<Child, io::Error> {
+        use std::{thread, time::Duration};
+
+        const LAUNCH_ELECTRUMX_SERVER_DELAY_MS: u64 = 500;
+
+        let electrs_process = self.electrs_command().spawn();
         thread::sleep(Duration::from_millis(LAUNCH_ELECTRUMX_SERVER_DELAY_MS));
         electrs_process
     }
 
-    fn client(&self) -> Result<Client, BitcoinError> {
-        let url = format!("http://{}", self.bitcoin_socket_address);
-        Client::new(url, self.bitcoin_auth.clone())
+    pub(crate) fn client(&self) -> Result<Client, BitcoinError> {
+        let timeout = self.wallet_config.read_timeout();
+        bitcoind_client(self.bitcoin_socket_address, self.bitcoin_auth.clone(), timeout)
+    }
+
+    fn quorum(&self) -> Result<Option<Quorum<Client>>, BitcoinError> {
+        if self.witnesses.is_empty() {
+            return Ok(None);
+        }
+        let timeout = self.wallet_config.read_timeout();
+        let mut witnesses = Vec::new();
+        for config in &self.witnesses {
+            let auth = config_auth(&self.bitcoin_auth, config);
+            let client = bitcoind_client(config.address, auth, timeout)?;
+            witnesses.push((config.address.to_string(), client));
+        }
+        Ok(Some(Quorum::new(witnesses, self.max_lag)))
+    }
+
+    // client of the watch-only descriptor wallet `name` of the node, it is created unless
+    // the node has it and loaded unless it is
+    fn core_wallet_client(&self, name: &str) -> Result<Client, Box<dyn Error>> {
+        use bitcoin_rpc_client::RpcApi;
+
+        let node = self.client()?;
+        let loaded: Vec<String> = node.call("listwallets", &[])?;
+        if !loaded.iter().any(|loaded| loaded == name) {
+            if node.call::<serde_json::Value>("loadwallet", &[name.into()]).is_err() {
+                // no private keys, blank, no passphrase, no avoid_reuse, descriptors
+                let args =
+                    [name.into(), true.into(), true.into(), "".into(), false.into(), true.into()];
+                node.call::<serde_json::Value>("createwallet", &args)?;
+            }
+        }
+        let url = format!("http://{}/wallet/{}", self.bitcoin_socket_address, name);
+        let timeout = self.wallet_config.read_timeout();
+        Ok(bitcoind_client_at(&url, self.bitcoin_auth.clone(), timeout)?)
     }
 
     pub fn default_context(&self, mode: WalletLibraryMode) -> Result<(WalletContext, Mnemonic), Box<dyn Error>> {
         let cfg = self.wallet_config.clone();
-        let (wallet, mnemonic) = WalletWithTrustedFullNode::new(cfg, self.client()?, mode)?;
+        let (mut wallet, mnemonic) = WalletWithTrustedFullNode::new(cfg, self.client()?, mode)?;
+        if let Some(ref name) = self.core_wallet {
+            wallet.set_core_wallet(Box::new(self.core_wallet_client(name)?))?;
+        }
+        if let Some(quorum) = self.quorum()? {
+            wallet.set_quorum(quorum);
+        }
+        let auth = self.bitcoin_auth.clone();
+        let timeout = self.wallet_config.read_timeout();
+        wallet.set_connector(self.bitcoin_socket_address.to_string(), move |config| {
+            Ok(bitcoind_client(config.address, config_auth(&auth, config), timeout)?)
+        });
         Ok((WalletContext::Default {
             wallet: Box::new(wallet),
             bitcoin: self.client()?,
         }, mnemonic))
     }
 
+    /// connect to the backend of `kind` at `config` for a running wallet to move to, the
+    /// backend has to follow the chain of the network of the context. The wallet goes on
+    /// syncing from the height it reached, a full node without the descriptor wallet
+    pub fn switch_backend(
+        &self,
+        kind: BackendKind,
+        config: &BackendConfig,
+    ) -> Result<Attach, Box<dyn Error>> {
+        let wc = self.wallet_config.clone();
+        let address = config.address;
+        match kind {
+            BackendKind::FullNode => {
+                let auth = config_auth(&self.bitcoin_auth, config);
+                let timeout = wc.read_timeout();
+                let client = bitcoind_client(address, auth.clone(), timeout)?;
+                headers::check_genesis(self.network, BlockChainIO::get_block_hash(&client, 0)?)?;
+                let quorum = self.quorum()?;
+                Ok(Box::new(move |wallet_lib| {
+                    let mut wallet = WalletWithTrustedFullNode::attach(wallet_lib, client, &wc);
+                    if let Some(quorum) = quorum {
+                        wallet.set_quorum(quorum);
+                    }
+                    wallet.set_connector(address.to_string(), move |config| {
+                        Ok(bitcoind_client(config.address, config_auth(&auth, config), timeout)?)
+                    });
+                    Box::new(wallet) as Box<dyn Wallet + Send>
+                }))
+            }
+            BackendKind::Electrum => {
+                let client = electrumx::connect_to_chain(address, &wc, self.network)?;
+                Ok(Box::new(move |wallet_lib| {
+                    let wallet = ElectrumxWallet::attach(wallet_lib, address, client, &wc);
+                    Box::new(wallet) as Box<dyn Wallet + Send>
+                }))
+            }
+        }
+    }
+
     pub fn electrs_context(&self, mode: WalletLibraryMode) -> Result<(WalletContext, Mnemonic), Box<dyn Error>> {
         let cfg = self.wallet_config.clone();
 
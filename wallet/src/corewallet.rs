@@ -0,0 +1,185 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Descriptor wallet of bitcoind
+//!
+//! Scanning every block for the scripts of the wallet takes the CPU of the wallet, bitcoind
+//! does the same work anyway for the wallets it holds. In the hybrid mode the descriptors of
+//! the accounts are imported into a watch-only descriptor wallet of the node, and a sync asks
+//! it with `listsinceblock` for the transactions since the last one. The transactions are
+//! processed by the wallet as if it had found them in the blocks, the node only tells which.
+//! Rescans after an import are the node's job as well.
+//!
+//! Keys, coin selection and signing stay with the wallet, the node never sees a private key.
+//!
+use bitcoin::{BlockHeader, Transaction};
+use bitcoin_hashes::{hex::FromHex, sha256d::Hash as Sha256dHash};
+use serde_json::Value;
+
+use std::error::Error;
+
+#[cfg(feature = "bitcoind")]
+use bitcoin::consensus::encode::deserialize;
+#[cfg(feature = "bitcoind")]
+use bitcoin_rpc_client::{Client as BitcoinClient, RpcApi};
+
+/// what `listsinceblock` reported
+#[derive(Clone, Debug, PartialEq)]
+pub struct SinceBlock {
+    /// transactions of the wallet with the height of their block, `None` for the ones
+    /// in the mempool. A transaction paying to several scripts is listed once
+    pub txs: Vec<(Sha256dHash, Option<usize>)>,
+    /// tip of the node, the next sync lists the transactions since it
+    pub last_block: Sha256dHash,
+}
+
+impl SinceBlock {
+    /// parse the answer of `listsinceblock`, the transactions conflicted by ones in the chain
+    /// are left out
+    pub fn parse(answer: &Value) -> Result<SinceBlock, Box<dyn Error>> {
+        let last_block = answer["lastblock"].as_str().ok_or("no lastblock in the answer")?;
+        let entries = answer["transactions"].as_array().ok_or("no transactions in the answer")?;
+        let mut txs = Vec::new();
+        for entry in entries {
+            let txid = entry["txid"].as_str().ok_or("a transaction without a txid")?;
+            let txid = Sha256dHash::from_hex(txid)?;
+            let confirmations = entry["confirmations"].as_i64().unwrap_or(0);
+            if confirmations < 0 {
+                continue;
+            }
+            let height = entry["blockheight"].as_u64().map(|height| height as usize);
+            if !txs.contains(&(txid, height)) {
+                txs.push((txid, height));
+            }
+        }
+        Ok(SinceBlock {
+            txs,
+            last_block: Sha256dHash::from_hex(last_block)?,
+        })
+    }
+}
+
+/// a watch-only descriptor wallet of bitcoind the wallet syncs through,
+/// see `WalletWithTrustedFullNode::set_core_wallet`
+pub trait CoreWalletIO {
+    /// `importdescriptors` with the requests of `WalletLibraryInterface::import_descriptors`,
+    /// fails unless each of them succeeded
+    fn import_descriptors(&self, requests: &Value) -> Result<(), Box<dyn Error>>;
+    /// the transactions in the blocks after `since`, of the whole chain if it is `None`,
+    /// and the ones in the mempool
+    fn list_since_block(&self, since: Option<&Sha256dHash>) -> Result<SinceBlock, Box<dyn Error>>;
+    fn get_transaction(&self, txid: &Sha256dHash) -> Result<Transaction, Box<dyn Error>>;
+    /// height and header of the block `hash`
+    fn get_block_header(&self, hash: &Sha256dHash) -> Result<(usize, BlockHeader), Box<dyn Error>>;
+}
+
+/// the client has to call the endpoint of the wallet, `http://<node>/wallet/<name>`
+#[cfg(feature = "bitcoind")]
+impl CoreWalletIO for BitcoinClient {
+    fn import_descriptors(&self, requests: &Value) -> Result<(), Box<dyn Error>> {
+        let results: Vec<Value> = self.call("importdescriptors", &[requests.clone()])?;
+        for (request, result) in requests.as_array().into_iter().flatten().zip(&results) {
+            if result["success"].as_bool() != Some(true) {
+                let message = result["error"]["message"].as_str().unwrap_or("unknown error");
+                let desc = request["desc"].as_str().unwrap_or_default();
+                return Err(From::from(format!("failed to import {}: {}", desc, message)));
+            }
+        }
+        Ok(())
+    }
+
+    fn list_since_block(&self, since: Option<&Sha256dHash>) -> Result<SinceBlock, Box<dyn Error>> {
+        let since = match since {
+            Some(hash) => serde_json::to_value(hash)?,
+            None => Value::Null,
+        };
+        // target confirmations, watch-only and removed transactions
+        let args = [since, 1.into(), true.into(), false.into()];
+        let answer: Value = self.call("listsinceblock", &args)?;
+        SinceBlock::parse(&answer)
+    }
+
+    fn get_transaction(&self, txid: &Sha256dHash) -> Result<Transaction, Box<dyn Error>> {
+        let args = [serde_json::to_value(txid)?, true.into()];
+        let answer: Value = self.call("gettransaction", &args)?;
+        let raw = answer["hex"].as_str().ok_or("no hex in the answer")?;
+        Ok(deserialize(&hex::decode(raw)?)?)
+    }
+
+    fn get_block_header(&self, hash: &Sha256dHash) -> Result<(usize, BlockHeader), Box<dyn Error>> {
+        let hash = serde_json::to_value(hash)?;
+        let verbose: Value = self.call("getblockheader", &[hash.clone(), true.into()])?;
+        let height = verbose["height"].as_u64().ok_or("no height in the answer")?;
+        let raw: String = self.call("getblockheader", &[hash, false.into()])?;
+        Ok((height as usize, deserialize(&hex::decode(raw)?)?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin_hashes::{hex::FromHex, sha256d::Hash as Sha256dHash};
+
+    use crate::walletlibrary::WalletConfig;
+    use crate::memory::test::new_wallet;
+    use crate::account::AccountAddressType;
+    use crate::interface::Wallet;
+    use super::SinceBlock;
+
+    #[test]
+    fn since_block() {
+        let txid = "bd2e3c04b3e1c2e2a4d0f6f5d0f57a4c3b3d1f6c12c3c2c5f6e2a1b0c9d8e7f6";
+        let replaced = "0b2e3c04b3e1c2e2a4d0f6f5d0f57a4c3b3d1f6c12c3c2c5f6e2a1b0c9d8e7f6";
+        let pending = "1b2e3c04b3e1c2e2a4d0f6f5d0f57a4c3b3d1f6c12c3c2c5f6e2a1b0c9d8e7f6";
+        let tip = "0f9188f13cb7b2c71f2a335e3a4fc328bf5beb436012afca590b1a11466e2206";
+        let answer = serde_json::json!({
+            "transactions": [
+                // paying to two addresses of the wallet
+                {"txid": txid, "category": "receive", "confirmations": 3, "blockheight": 101},
+                {"txid": txid, "category": "receive", "confirmations": 3, "blockheight": 101},
+                {"txid": replaced, "category": "send", "confirmations": -3},
+                {"txid": pending, "category": "receive", "confirmations": 0},
+            ],
+            "removed": [],
+            "lastblock": tip,
+        });
+        let since = SinceBlock::parse(&answer).unwrap();
+        let txid = Sha256dHash::from_hex(txid).unwrap();
+        let pending = Sha256dHash::from_hex(pending).unwrap();
+        assert_eq!(since.txs, vec![(txid, Some(101)), (pending, None)]);
+        assert_eq!(since.last_block, Sha256dHash::from_hex(tip).unwrap());
+
+        assert!(SinceBlock::parse(&serde_json::json!({"transactions": []})).is_err());
+    }
+
+    #[test]
+    fn import_descriptors() {
+        let (mut wallet, _) = new_wallet(WalletConfig::in_memory());
+        wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        let requests = wallet.wallet_lib().import_descriptors();
+        let requests = requests.as_array().unwrap();
+        let descriptors = wallet.wallet_lib().descriptors();
+        assert_eq!(requests.len(), descriptors.len());
+        for (request, descriptor) in requests.iter().zip(&descriptors) {
+            assert_eq!(request["desc"], descriptor.as_str());
+            assert!(request["timestamp"].as_u64().unwrap() > 0);
+            assert_eq!(request["active"], true);
+        }
+        assert_eq!(requests[4]["internal"], false);
+        assert_eq!(requests[4]["next_index"], 1);
+        assert_eq!(requests[4]["range"], serde_json::json!([0, 1001]));
+        assert_eq!(requests[5]["internal"], true);
+        assert_eq!(requests[5]["next_index"], 0);
+    }
+}
@@ -12,19 +12,84 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
-use bitcoin::OutPoint;
+use bitcoin::{OutPoint, Script, Transaction};
+use bitcoin_hashes::sha256d::Hash as Sha256dHash;
 use bitcoin::util::key::PublicKey;
-use rocksdb::{DB as RocksDB, ColumnFamilyDescriptor, Options, IteratorMode};
+use bitcoin::network::constants::Network;
+use rocksdb::{
+    DB as RocksDB, ColumnFamilyDescriptor, Options, IteratorMode, Direction, Error as RocksError,
+};
 use byteorder::{ByteOrder, BigEndian};
+use serde::{Serialize, Deserialize};
 use serde_json;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, BTreeMap};
+use std::ops::Bound;
+use std::sync::Mutex;
+use std::str::FromStr;
 
-use super::account::{Utxo, SecretKeyHelper, AccountAddressType};
-use super::walletlibrary::{LockId, LockGroup};
+use super::account::{
+    Utxo, SecretKeyHelper, AccountAddressType, AddressChain, KeyPath, DerivationMode,
+    DerivationScheme,
+};
+use super::walletlibrary::{
+    LockId, LockGroup, PendingRotation, ImportedUtxo, WatchedUtxo, SilentPaymentUtxo,
+    SpendingPolicy, PendingSpend, SpentUtxo, TimelockedUtxo, ScriptAccount, ScriptUtxo, UnsignedTx,
+    IdempotentSend, VaultUtxo,
+};
+use super::timelock::Timelock;
+use super::vault::Vault;
+use super::history::{TxRecord, AddressStats};
+use super::audit::AuditEntry;
+use super::headers::HeaderRecord;
+use super::signer::WatchOnlyKeys;
+use super::error::WalletError;
+use super::interface::BackendKind;
+
+/// version of the layout of the data, raised along with a new migration
+/// whenever the data written by older versions has to be changed
+pub const SCHEMA_VERSION: u32 = 4;
+
+// `MIGRATIONS[i]` upgrades the data of version `i` to version `i + 1`,
+// a new database is upgraded from version 0 too, so the migrations have to work on empty data
+static MIGRATIONS: &[fn(&mut DB)] = &[
+    // databases written before the version was stored need no changes,
+    // everything added until then came in new keys and column families
+    |_| (),
+    // wallets created before the standard derivation keep the coin type of their accounts
+    |db| {
+        if db.get_bip39_randomness().is_some() {
+            db.put_derivation_mode(DerivationMode::Legacy);
+        }
+    },
+    // the totals of the addresses didn't count the transactions paying to them,
+    // they are counted again from the history when the wallet is loaded
+    |db| db.clear_address_stats(),
+    // the next index of each chain is stored instead of being looked up in the script index
+    |db| {
+        for (_, (addr_type, key_path)) in db.get_script_index() {
+            db.advance_next_index(&addr_type, &key_path);
+        }
+    },
+];
 
 static BIP39_RANDOMNESS: &'static [u8] = b"bip39_randomness";
 static LAST_SEEN_BLOCK_HEIGHT: &'static [u8] = b"lsbh";
+static PENDING_ROTATION: &'static [u8] = b"pending_rotation";
+static SPENDING_POLICY: &'static [u8] = b"spending_policy";
+static POLICY_SPENDS: &'static [u8] = b"policy_spends";
+static NEXT_SPEND_ID: &'static [u8] = b"next_spend_id";
+static DERIVATION_SCHEMES: &'static [u8] = b"derivation_schemes";
+static DERIVATION_MODE: &'static [u8] = b"derivation_mode";
+static BIRTHDAY: &'static [u8] = b"birthday";
+static WATCH_ONLY_KEYS: &'static [u8] = b"watch_only_keys";
+static NEXT_UNSIGNED_ID: &'static [u8] = b"next_unsigned_id";
+static SCHEMA_VERSION_KEY: &'static [u8] = b"schema_version";
+static SCRIPT_STATUS_TIP: &'static [u8] = b"script_status_tip";
+static VAULT: &'static [u8] = b"vault";
+static MASTER_FINGERPRINT: &'static [u8] = b"master_fingerprint";
+static NETWORK: &'static [u8] = b"network";
+static BACKEND: &'static [u8] = b"backend";
 static UTXO_MAP_CF: &'static str = "utxo_map";
 static EXTERNAL_PUBLIC_KEY_CF: &'static str = "epkcf";
 static INTERNAL_PUBLIC_KEY_CF: &'static str = "ipkcf";
@@ -32,11 +97,162 @@ static P2PKH_ADDRESS_CF: &'static str = "p2pkh";
 static P2SHWH_ADDRESS_CF: &'static str = "p2shwh";
 static P2WKH_ADDRESS_CF: &'static str = "p2wkh";
 static LOCK_GROUP_MAP_CF: &'static str = "lgm";
+static SCRIPT_INDEX_CF: &'static str = "script_index";
+static IMPORTED_KEY_CF: &'static str = "imported_keys";
+static IMPORTED_UTXO_CF: &'static str = "imported_utxo";
+static USED_SCRIPT_CF: &'static str = "used_script";
+static FROZEN_UTXO_CF: &'static str = "frozen_utxo";
+static UNCONFIRMED_TX_CF: &'static str = "unconfirmed_tx";
+static TX_HISTORY_CF: &'static str = "tx_history";
+static WATCHED_SCRIPT_CF: &'static str = "watched_script";
+static WATCHED_UTXO_CF: &'static str = "watched_utxo";
+static SENT_PAYMENT_CODE_CF: &'static str = "sent_payment_code";
+static RECEIVED_PAYMENT_CODE_CF: &'static str = "received_payment_code";
+static SILENT_PAYMENT_UTXO_CF: &'static str = "silent_payment_utxo";
+static PENDING_SPEND_CF: &'static str = "pending_spend";
+static AUDIT_LOG_CF: &'static str = "audit_log";
+static BLOCK_HEADER_CF: &'static str = "block_header";
+static SPENT_UTXO_CF: &'static str = "spent_utxo";
+static FIAT_PRICE_CF: &'static str = "fiat_price";
+static TIMELOCK_SCRIPT_CF: &'static str = "timelock_script";
+static TIMELOCKED_UTXO_CF: &'static str = "timelocked_utxo";
+static SCRIPT_ACCOUNT_CF: &'static str = "script_account";
+static SCRIPT_UTXO_CF: &'static str = "script_utxo";
+static UNSIGNED_TX_CF: &'static str = "unsigned_tx";
+static IDEMPOTENT_SEND_CF: &'static str = "idempotent_send";
+static ADDRESS_STATS_CF: &'static str = "address_stats";
+static ADDRESS_LABEL_CF: &'static str = "address_label";
+static SCRIPT_STATUS_CF: &'static str = "script_status";
+static VAULT_SCRIPT_CF: &'static str = "vault_script";
+static VAULT_UTXO_CF: &'static str = "vault_utxo";
+static UNVAULT_TX_CF: &'static str = "unvault_tx";
+static NEXT_INDEX_CF: &'static str = "next_index";
+
+// keys outside of the column families
+static DEFAULT_CF: &'static str = "default";
+
+type MemoryMaps = HashMap<String, BTreeMap<Box<[u8]>, Box<[u8]>>>;
+type Entries<'a> = Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a>;
+
+// the subset of RocksDB the wallet uses, an ephemeral wallet keeps
+// each column family in a sorted map instead
+enum Store {
+    Rocks(RocksDB),
+    Memory(Mutex<MemoryMaps>),
+}
+
+impl Store {
+    fn cf_handle<'a>(&self, name: &'a str) -> Option<&'a str> {
+        Some(name)
+    }
+
+    fn get<K: AsRef<[u8]>>(&self, key: K) -> Result<Option<Vec<u8>>, RocksError> {
+        self.get_cf(DEFAULT_CF, key)
+    }
+
+    fn put<K: AsRef<[u8]>, V: AsRef<[u8]>>(&self, key: K, val: V) -> Result<(), RocksError> {
+        self.put_cf(DEFAULT_CF, key, val)
+    }
+
+    fn delete<K: AsRef<[u8]>>(&self, key: K) -> Result<(), RocksError> {
+        self.delete_cf(DEFAULT_CF, key)
+    }
+
+    fn get_cf<K: AsRef<[u8]>>(
+        &self,
+        cf: &str,
+        key: K,
+    ) -> Result<Option<Vec<u8>>, RocksError> {
+        match self {
+            Store::Rocks(db) if cf == DEFAULT_CF => Ok(db.get(key)?.map(|val| val.to_vec())),
+            Store::Rocks(db) => {
+                let cf = db.cf_handle(cf).unwrap();
+                Ok(db.get_cf(cf, key)?.map(|val| val.to_vec()))
+            }
+            Store::Memory(maps) => Ok(maps
+                .lock()
+                .unwrap()
+                .get(cf)
+                .and_then(|map| map.get(key.as_ref()))
+                .map(|val| val.to_vec())),
+        }
+    }
+
+    fn put_cf<K: AsRef<[u8]>, V: AsRef<[u8]>>(
+        &self,
+        cf: &str,
+        key: K,
+        val: V,
+    ) -> Result<(), RocksError> {
+        match self {
+            Store::Rocks(db) if cf == DEFAULT_CF => db.put(key, val),
+            Store::Rocks(db) => db.put_cf(db.cf_handle(cf).unwrap(), key, val),
+            Store::Memory(maps) => {
+                maps.lock()
+                    .unwrap()
+                    .entry(cf.to_owned())
+                    .or_insert_with(BTreeMap::new)
+                    .insert(key.as_ref().into(), val.as_ref().into());
+                Ok(())
+            }
+        }
+    }
+
+    fn delete_cf<K: AsRef<[u8]>>(&self, cf: &str, key: K) -> Result<(), RocksError> {
+        match self {
+            Store::Rocks(db) if cf == DEFAULT_CF => db.delete(key),
+            Store::Rocks(db) => db.delete_cf(db.cf_handle(cf).unwrap(), key),
+            Store::Memory(maps) => {
+                if let Some(map) = maps.lock().unwrap().get_mut(cf) {
+                    map.remove(key.as_ref());
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn iterator_cf(&self, cf: &str, mode: IteratorMode) -> Result<Entries, RocksError> {
+        let maps = match self {
+            Store::Rocks(db) => {
+                return Ok(Box::new(db.iterator_cf(db.cf_handle(cf).unwrap(), mode)?));
+            }
+            Store::Memory(maps) => maps.lock().unwrap(),
+        };
+        let map = match maps.get(cf) {
+            Some(map) => map,
+            None => return Ok(Box::new(Vec::new().into_iter())),
+        };
+        let entry = |(key, val): (&Box<[u8]>, &Box<[u8]>)| (key.clone(), val.clone());
+        // the map is locked only while it is copied, so the wallet may write while iterating
+        let entries: Vec<_> = match mode {
+            IteratorMode::Start => map.iter().map(entry).collect(),
+            IteratorMode::End => map.iter().rev().map(entry).collect(),
+            IteratorMode::From(key, Direction::Forward) => map
+                .range::<[u8], _>((Bound::Included(key), Bound::Unbounded))
+                .map(entry)
+                .collect(),
+            IteratorMode::From(key, Direction::Reverse) => map
+                .range::<[u8], _>((Bound::Unbounded, Bound::Included(key)))
+                .rev()
+                .map(entry)
+                .collect(),
+        };
+        Ok(Box::new(entries.into_iter()))
+    }
+}
 
-pub struct DB(RocksDB);
+pub struct DB(Store);
 
 impl DB {
-    pub fn new(db_path: String) -> Self {
+    /// database of an ephemeral wallet, its data is lost when it is dropped
+    pub fn in_memory() -> Result<Self, WalletError> {
+        let mut db = DB(Store::Memory(Mutex::new(HashMap::new())));
+        db.migrate()?;
+        Ok(db)
+    }
+
+    /// open the database and upgrade its data, a database of a newer wallet is refused
+    pub fn new(db_path: String) -> Result<Self, WalletError> {
         let utxo_map_cf = ColumnFamilyDescriptor::new(UTXO_MAP_CF, Options::default());
         let public_key_cf = ColumnFamilyDescriptor::new(EXTERNAL_PUBLIC_KEY_CF, Options::default());
         let internal_public_key_cf =
@@ -45,6 +261,42 @@ impl DB {
         let p2shwh_address_cf = ColumnFamilyDescriptor::new(P2SHWH_ADDRESS_CF, Options::default());
         let p2wkh_address_cf = ColumnFamilyDescriptor::new(P2WKH_ADDRESS_CF, Options::default());
         let lock_group_map_cf = ColumnFamilyDescriptor::new(LOCK_GROUP_MAP_CF, Options::default());
+        let script_index_cf = ColumnFamilyDescriptor::new(SCRIPT_INDEX_CF, Options::default());
+        let imported_key_cf = ColumnFamilyDescriptor::new(IMPORTED_KEY_CF, Options::default());
+        let imported_utxo_cf = ColumnFamilyDescriptor::new(IMPORTED_UTXO_CF, Options::default());
+        let used_script_cf = ColumnFamilyDescriptor::new(USED_SCRIPT_CF, Options::default());
+        let frozen_utxo_cf = ColumnFamilyDescriptor::new(FROZEN_UTXO_CF, Options::default());
+        let unconfirmed_tx_cf = ColumnFamilyDescriptor::new(UNCONFIRMED_TX_CF, Options::default());
+        let tx_history_cf = ColumnFamilyDescriptor::new(TX_HISTORY_CF, Options::default());
+        let watched_script_cf = ColumnFamilyDescriptor::new(WATCHED_SCRIPT_CF, Options::default());
+        let watched_utxo_cf = ColumnFamilyDescriptor::new(WATCHED_UTXO_CF, Options::default());
+        let sent_payment_code_cf =
+            ColumnFamilyDescriptor::new(SENT_PAYMENT_CODE_CF, Options::default());
+        let received_payment_code_cf =
+            ColumnFamilyDescriptor::new(RECEIVED_PAYMENT_CODE_CF, Options::default());
+        let silent_payment_utxo_cf =
+            ColumnFamilyDescriptor::new(SILENT_PAYMENT_UTXO_CF, Options::default());
+        let pending_spend_cf = ColumnFamilyDescriptor::new(PENDING_SPEND_CF, Options::default());
+        let audit_log_cf = ColumnFamilyDescriptor::new(AUDIT_LOG_CF, Options::default());
+        let block_header_cf = ColumnFamilyDescriptor::new(BLOCK_HEADER_CF, Options::default());
+        let spent_utxo_cf = ColumnFamilyDescriptor::new(SPENT_UTXO_CF, Options::default());
+        let fiat_price_cf = ColumnFamilyDescriptor::new(FIAT_PRICE_CF, Options::default());
+        let timelock_script_cf =
+            ColumnFamilyDescriptor::new(TIMELOCK_SCRIPT_CF, Options::default());
+        let timelocked_utxo_cf =
+            ColumnFamilyDescriptor::new(TIMELOCKED_UTXO_CF, Options::default());
+        let script_account_cf = ColumnFamilyDescriptor::new(SCRIPT_ACCOUNT_CF, Options::default());
+        let script_utxo_cf = ColumnFamilyDescriptor::new(SCRIPT_UTXO_CF, Options::default());
+        let unsigned_tx_cf = ColumnFamilyDescriptor::new(UNSIGNED_TX_CF, Options::default());
+        let idempotent_send_cf =
+            ColumnFamilyDescriptor::new(IDEMPOTENT_SEND_CF, Options::default());
+        let address_stats_cf = ColumnFamilyDescriptor::new(ADDRESS_STATS_CF, Options::default());
+        let address_label_cf = ColumnFamilyDescriptor::new(ADDRESS_LABEL_CF, Options::default());
+        let script_status_cf = ColumnFamilyDescriptor::new(SCRIPT_STATUS_CF, Options::default());
+        let vault_script_cf = ColumnFamilyDescriptor::new(VAULT_SCRIPT_CF, Options::default());
+        let vault_utxo_cf = ColumnFamilyDescriptor::new(VAULT_UTXO_CF, Options::default());
+        let unvault_tx_cf = ColumnFamilyDescriptor::new(UNVAULT_TX_CF, Options::default());
+        let next_index_cf = ColumnFamilyDescriptor::new(NEXT_INDEX_CF, Options::default());
 
         let mut db_opts = Options::default();
         db_opts.create_missing_column_families(true);
@@ -60,15 +312,156 @@ impl DB {
                 p2pkh_address_cf,
                 p2shwh_address_cf,
                 p2wkh_address_cf,
+                script_index_cf,
+                imported_key_cf,
+                imported_utxo_cf,
+                used_script_cf,
+                frozen_utxo_cf,
+                unconfirmed_tx_cf,
+                tx_history_cf,
+                watched_script_cf,
+                watched_utxo_cf,
+                sent_payment_code_cf,
+                received_payment_code_cf,
+                silent_payment_utxo_cf,
+                pending_spend_cf,
+                audit_log_cf,
+                block_header_cf,
+                spent_utxo_cf,
+                fiat_price_cf,
+                timelock_script_cf,
+                timelocked_utxo_cf,
+                script_account_cf,
+                script_utxo_cf,
+                unsigned_tx_cf,
+                idempotent_send_cf,
+                address_stats_cf,
+                address_label_cf,
+                script_status_cf,
+                vault_script_cf,
+                vault_utxo_cf,
+                unvault_tx_cf,
+                next_index_cf,
             ],
         )
         .unwrap();
-        DB(db)
+        let mut db = DB(Store::Rocks(db));
+        db.migrate()?;
+        Ok(db)
+    }
+
+    fn migrate(&mut self) -> Result<(), WalletError> {
+        let version = self.get_schema_version();
+        if version > SCHEMA_VERSION {
+            return Err(WalletError::UnsupportedSchemaVersion(version));
+        }
+        for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+            migration(self);
+            self.put_schema_version(from as u32 + 1);
+        }
+        Ok(())
+    }
+
+    fn get_schema_version(&self) -> u32 {
+        self.0
+            .get(SCHEMA_VERSION_KEY)
+            .unwrap()
+            .map_or(0, |val| BigEndian::read_u32(&*val))
+    }
+
+    fn put_schema_version(&mut self, version: u32) {
+        let mut buff = [0u8; 4];
+        BigEndian::write_u32(&mut buff, version);
+        self.0.put(SCHEMA_VERSION_KEY, &buff).unwrap();
+    }
+
+    /// remove all data of the wallet but the audit log, which is append-only,
+    /// the schema version, the network and the backend
+    pub fn clear(&mut self) {
+        self.0.delete(BIP39_RANDOMNESS).unwrap();
+        for key in wallet_keys() {
+            self.0.delete(key).unwrap();
+        }
+        for name in wallet_cfs() {
+            let cf = self.0.cf_handle(name).unwrap();
+            let keys: Vec<Box<[u8]>> = self
+                .0
+                .iterator_cf(cf, IteratorMode::Start)
+                .unwrap()
+                .map(|(key, _)| key)
+                .collect();
+            for key in keys {
+                self.0.delete_cf(cf, &key).unwrap();
+            }
+        }
+    }
+
+    /// everything `clear` removes but the seed randomness, which a backup keeps apart
+    pub fn dump(&self) -> serde_json::Value {
+        let mut entries = Vec::new();
+        let keys = wallet_keys().into_iter().filter_map(|key| {
+            let val = self.0.get(key).unwrap()?;
+            Some((hex::encode(key), hex::encode(&val)))
+        });
+        entries.push((DEFAULT_CF.to_owned(), keys.collect()));
+        for name in wallet_cfs() {
+            let cf = self.0.cf_handle(name).unwrap();
+            let cf_entries = self
+                .0
+                .iterator_cf(cf, IteratorMode::Start)
+                .unwrap()
+                .map(|(key, val)| (hex::encode(&key), hex::encode(&val)));
+            entries.push((name.to_owned(), cf_entries.collect()));
+        }
+        let dump = Dump {
+            schema_version: self.get_schema_version(),
+            entries,
+        };
+        serde_json::to_value(dump).unwrap()
+    }
+
+    /// replace the data of the wallet with a `dump` and the seed randomness,
+    /// the dump of an older wallet is upgraded
+    pub fn restore_dump(
+        &mut self,
+        dump: &serde_json::Value,
+        bip39_randomness: &[u8],
+    ) -> Result<(), WalletError> {
+        let dump: Dump =
+            serde_json::from_value(dump.clone()).map_err(|_| WalletError::InvalidBackup)?;
+        if dump.schema_version > SCHEMA_VERSION {
+            return Err(WalletError::UnsupportedSchemaVersion(dump.schema_version));
+        }
+        // the whole dump is checked before the wallet is cleared
+        let (keys, cfs) = (wallet_keys(), wallet_cfs());
+        let mut puts = Vec::new();
+        for (name, entries) in &dump.entries {
+            let name = match cfs.iter().find(|cf| **cf == name.as_str()) {
+                Some(cf) => *cf,
+                None if name == DEFAULT_CF => DEFAULT_CF,
+                None => return Err(WalletError::InvalidBackup),
+            };
+            for (key, val) in entries {
+                let key = hex::decode(key).map_err(|_| WalletError::InvalidBackup)?;
+                let val = hex::decode(val).map_err(|_| WalletError::InvalidBackup)?;
+                if name == DEFAULT_CF && !keys.iter().any(|known| *known == &key[..]) {
+                    return Err(WalletError::InvalidBackup);
+                }
+                puts.push((name, key, val));
+            }
+        }
+        self.clear();
+        self.put_bip39_randomness(bip39_randomness);
+        for (name, key, val) in puts {
+            let cf = self.0.cf_handle(name).unwrap();
+            self.0.put_cf(cf, key, val).unwrap();
+        }
+        self.put_schema_version(dump.schema_version);
+        self.migrate()
     }
 
     pub fn get_bip39_randomness(&self) -> Option<Vec<u8>> {
         self.0.get(BIP39_RANDOMNESS).unwrap()
-            .map(|v| v.to_vec())
     }
 
     pub fn put_bip39_randomness(&mut self, randomness: &[u8]) {
@@ -89,6 +482,424 @@ impl DB {
         self.0.put(LAST_SEEN_BLOCK_HEIGHT, &buff).unwrap();
     }
 
+    pub fn get_pending_rotation(&self) -> Option<PendingRotation> {
+        self.0
+            .get(PENDING_ROTATION)
+            .unwrap()
+            .map(|val| serde_json::from_slice(&*val).unwrap())
+    }
+
+    pub fn put_pending_rotation(&mut self, rotation: &PendingRotation) {
+        let val = serde_json::to_vec(rotation).unwrap();
+        self.0.put(PENDING_ROTATION, val.as_slice()).unwrap();
+    }
+
+    pub fn get_spending_policy(&self) -> SpendingPolicy {
+        self.0
+            .get(SPENDING_POLICY)
+            .unwrap()
+            .map(|val| serde_json::from_slice(&*val).unwrap())
+            .unwrap_or_default()
+    }
+
+    pub fn put_spending_policy(&mut self, policy: &SpendingPolicy) {
+        let val = serde_json::to_vec(policy).unwrap();
+        self.0.put(SPENDING_POLICY, val.as_slice()).unwrap();
+    }
+
+    /// schemes of the accounts which aren't derived at the default path of their address type
+    pub fn get_derivation_schemes(&self) -> Vec<(AccountAddressType, DerivationScheme)> {
+        self.0
+            .get(DERIVATION_SCHEMES)
+            .unwrap()
+            .map(|val| serde_json::from_slice(&*val).unwrap())
+            .unwrap_or_default()
+    }
+
+    pub fn put_derivation_schemes(&mut self, schemes: &[(AccountAddressType, DerivationScheme)]) {
+        let val = serde_json::to_vec(schemes).unwrap();
+        self.0.put(DERIVATION_SCHEMES, val.as_slice()).unwrap();
+    }
+
+    pub fn get_derivation_mode(&self) -> DerivationMode {
+        self.0
+            .get(DERIVATION_MODE)
+            .unwrap()
+            .map(|val| serde_json::from_slice(&*val).unwrap())
+            .unwrap_or_default()
+    }
+
+    pub fn put_derivation_mode(&mut self, mode: DerivationMode) {
+        let val = serde_json::to_vec(&mode).unwrap();
+        self.0.put(DERIVATION_MODE, val.as_slice()).unwrap();
+    }
+
+    /// unix time the seed was created at, unknown for restored wallets
+    pub fn get_birthday(&self) -> Option<u64> {
+        self.0
+            .get(BIRTHDAY)
+            .unwrap()
+            .map(|val| serde_json::from_slice(&*val).unwrap())
+    }
+
+    pub fn put_birthday(&mut self, birthday: u64) {
+        let val = serde_json::to_vec(&birthday).unwrap();
+        self.0.put(BIRTHDAY, val.as_slice()).unwrap();
+    }
+
+    /// keys of the offline signer, `None` unless the wallet is watch-only
+    /// fingerprint of the master key of the wallet, written whenever the wallet is loaded
+    pub fn get_master_fingerprint(&self) -> Option<Vec<u8>> {
+        self.0.get(MASTER_FINGERPRINT).unwrap()
+    }
+
+    pub fn put_master_fingerprint(&mut self, fingerprint: &[u8]) {
+        self.0.put(MASTER_FINGERPRINT, fingerprint).unwrap();
+    }
+
+    pub fn get_watch_only_keys(&self) -> Option<WatchOnlyKeys> {
+        self.0
+            .get(WATCH_ONLY_KEYS)
+            .unwrap()
+            .map(|val| serde_json::from_slice(&*val).unwrap())
+    }
+
+    pub fn put_watch_only_keys(&mut self, keys: &WatchOnlyKeys) {
+        let val = serde_json::to_vec(keys).unwrap();
+        self.0.put(WATCH_ONLY_KEYS, val.as_slice()).unwrap();
+    }
+
+    /// network the wallet was created on, `None` until a wallet is opened in the database
+    pub fn get_network(&self) -> Option<Network> {
+        self.0
+            .get(NETWORK)
+            .unwrap()
+            .map(|val| Network::from_str(&String::from_utf8_lossy(&*val)).unwrap())
+    }
+
+    pub fn put_network(&mut self, network: Network) {
+        self.0.put(NETWORK, network.to_string().as_bytes()).unwrap();
+    }
+
+    /// kind of backend the wallet was first opened with
+    pub fn get_backend(&self) -> Option<BackendKind> {
+        self.0
+            .get(BACKEND)
+            .unwrap()
+            .map(|val| serde_json::from_slice(&*val).unwrap())
+    }
+
+    pub fn put_backend(&mut self, backend: BackendKind) {
+        let val = serde_json::to_vec(&backend).unwrap();
+        self.0.put(BACKEND, val.as_slice()).unwrap();
+    }
+
+    pub fn get_policy_spends(&self) -> Vec<(u64, u64)> {
+        self.0
+            .get(POLICY_SPENDS)
+            .unwrap()
+            .map(|val| serde_json::from_slice(&*val).unwrap())
+            .unwrap_or_default()
+    }
+
+    pub fn put_policy_spends(&mut self, spends: &[(u64, u64)]) {
+        let val = serde_json::to_vec(spends).unwrap();
+        self.0.put(POLICY_SPENDS, val.as_slice()).unwrap();
+    }
+
+    /// ids of the pending spends start at 1
+    pub fn get_next_spend_id(&self) -> u64 {
+        self.0
+            .get(NEXT_SPEND_ID)
+            .unwrap()
+            .map(|val| serde_json::from_slice(&*val).unwrap())
+            .unwrap_or(1)
+    }
+
+    pub fn put_next_spend_id(&mut self, id: u64) {
+        let val = serde_json::to_vec(&id).unwrap();
+        self.0.put(NEXT_SPEND_ID, val.as_slice()).unwrap();
+    }
+
+    pub fn get_pending_spends(&self) -> HashMap<u64, PendingSpend> {
+        let cf = self.0.cf_handle(PENDING_SPEND_CF).unwrap();
+        let db_iterator = self.0.iterator_cf(cf, IteratorMode::Start).unwrap();
+
+        let mut pending_spends = HashMap::new();
+        for (key, val) in db_iterator {
+            let id: u64 = serde_json::from_slice(&key).unwrap();
+            let spend: PendingSpend = serde_json::from_slice(&val).unwrap();
+            pending_spends.insert(id, spend);
+        }
+        pending_spends
+    }
+
+    pub fn put_pending_spend(&mut self, spend: &PendingSpend) {
+        let key = serde_json::to_vec(&spend.id).unwrap();
+        let val = serde_json::to_vec(spend).unwrap();
+        let cf = self.0.cf_handle(PENDING_SPEND_CF).unwrap();
+        self.0.put_cf(cf, key.as_slice(), val.as_slice()).unwrap();
+    }
+
+    pub fn delete_pending_spend(&mut self, id: u64) {
+        let key = serde_json::to_vec(&id).unwrap();
+        let cf = self.0.cf_handle(PENDING_SPEND_CF).unwrap();
+        self.0.delete_cf(cf, key.as_slice()).unwrap();
+    }
+
+    /// ids of the unsigned transactions start at 1
+    pub fn get_next_unsigned_id(&self) -> u64 {
+        self.0
+            .get(NEXT_UNSIGNED_ID)
+            .unwrap()
+            .map(|val| serde_json::from_slice(&*val).unwrap())
+            .unwrap_or(1)
+    }
+
+    pub fn put_next_unsigned_id(&mut self, id: u64) {
+        let val = serde_json::to_vec(&id).unwrap();
+        self.0.put(NEXT_UNSIGNED_ID, val.as_slice()).unwrap();
+    }
+
+    pub fn get_unsigned_txs(&self) -> HashMap<u64, UnsignedTx> {
+        let cf = self.0.cf_handle(UNSIGNED_TX_CF).unwrap();
+        let db_iterator = self.0.iterator_cf(cf, IteratorMode::Start).unwrap();
+
+        let mut unsigned_txs = HashMap::new();
+        for (key, val) in db_iterator {
+            let id: u64 = serde_json::from_slice(&key).unwrap();
+            let unsigned: UnsignedTx = serde_json::from_slice(&val).unwrap();
+            unsigned_txs.insert(id, unsigned);
+        }
+        unsigned_txs
+    }
+
+    pub fn put_unsigned_tx(&mut self, unsigned: &UnsignedTx) {
+        let key = serde_json::to_vec(&unsigned.id).unwrap();
+        let val = serde_json::to_vec(unsigned).unwrap();
+        let cf = self.0.cf_handle(UNSIGNED_TX_CF).unwrap();
+        self.0.put_cf(cf, key.as_slice(), val.as_slice()).unwrap();
+    }
+
+    pub fn delete_unsigned_tx(&mut self, id: u64) {
+        let key = serde_json::to_vec(&id).unwrap();
+        let cf = self.0.cf_handle(UNSIGNED_TX_CF).unwrap();
+        self.0.delete_cf(cf, key.as_slice()).unwrap();
+    }
+
+    pub fn get_idempotent_sends(&self) -> HashMap<String, IdempotentSend> {
+        let cf = self.0.cf_handle(IDEMPOTENT_SEND_CF).unwrap();
+        let db_iterator = self.0.iterator_cf(cf, IteratorMode::Start).unwrap();
+
+        let mut sends = HashMap::new();
+        for (_, val) in db_iterator {
+            let send: IdempotentSend = serde_json::from_slice(&val).unwrap();
+            sends.insert(send.key.clone(), send);
+        }
+        sends
+    }
+
+    pub fn put_idempotent_send(&mut self, send: &IdempotentSend) {
+        let val = serde_json::to_vec(send).unwrap();
+        let cf = self.0.cf_handle(IDEMPOTENT_SEND_CF).unwrap();
+        self.0.put_cf(cf, send.key.as_bytes(), val.as_slice()).unwrap();
+    }
+
+    pub fn delete_idempotent_send(&mut self, key: &str) {
+        let cf = self.0.cf_handle(IDEMPOTENT_SEND_CF).unwrap();
+        self.0.delete_cf(cf, key.as_bytes()).unwrap();
+    }
+
+    pub fn get_address_stats(&self) -> HashMap<Script, AddressStats> {
+        let cf = self.0.cf_handle(ADDRESS_STATS_CF).unwrap();
+        let db_iterator = self.0.iterator_cf(cf, IteratorMode::Start).unwrap();
+
+        let mut address_stats = HashMap::new();
+        for (key, val) in db_iterator {
+            let script = Script::from(key.to_vec());
+            let stats: AddressStats = serde_json::from_slice(&val).unwrap();
+            address_stats.insert(script, stats);
+        }
+        address_stats
+    }
+
+    pub fn put_address_stats(&mut self, script: &Script, stats: &AddressStats) {
+        let val = serde_json::to_vec(stats).unwrap();
+        let cf = self.0.cf_handle(ADDRESS_STATS_CF).unwrap();
+        self.0.put_cf(cf, script.as_bytes(), val.as_slice()).unwrap();
+    }
+
+    fn clear_address_stats(&mut self) {
+        let cf = self.0.cf_handle(ADDRESS_STATS_CF).unwrap();
+        let keys: Vec<Box<[u8]>> = self
+            .0
+            .iterator_cf(cf, IteratorMode::Start)
+            .unwrap()
+            .map(|(key, _)| key)
+            .collect();
+        for key in keys {
+            self.0.delete_cf(cf, &key).unwrap();
+        }
+    }
+
+    pub fn get_address_labels(&self) -> HashMap<Script, String> {
+        let cf = self.0.cf_handle(ADDRESS_LABEL_CF).unwrap();
+        let db_iterator = self.0.iterator_cf(cf, IteratorMode::Start).unwrap();
+
+        let mut address_labels = HashMap::new();
+        for (key, val) in db_iterator {
+            let script = Script::from(key.to_vec());
+            let label: String = serde_json::from_slice(&val).unwrap();
+            address_labels.insert(script, label);
+        }
+        address_labels
+    }
+
+    pub fn put_address_label(&mut self, script: &Script, label: &str) {
+        let val = serde_json::to_vec(label).unwrap();
+        let cf = self.0.cf_handle(ADDRESS_LABEL_CF).unwrap();
+        self.0.put_cf(cf, script.as_bytes(), val.as_slice()).unwrap();
+    }
+
+    pub fn delete_address_label(&mut self, script: &Script) {
+        let cf = self.0.cf_handle(ADDRESS_LABEL_CF).unwrap();
+        self.0.delete_cf(cf, script.as_bytes()).unwrap();
+    }
+
+    /// electrum status of each script, keyed by its electrum script hash
+    pub fn get_script_statuses(&self) -> HashMap<String, String> {
+        let cf = self.0.cf_handle(SCRIPT_STATUS_CF).unwrap();
+        self.0
+            .iterator_cf(cf, IteratorMode::Start)
+            .unwrap()
+            .map(|(key, val)| {
+                let script_hash = String::from_utf8(key.to_vec()).unwrap();
+                (script_hash, serde_json::from_slice(&val).unwrap())
+            })
+            .collect()
+    }
+
+    pub fn put_script_status(&mut self, script_hash: &str, status: &str) {
+        let val = serde_json::to_vec(status).unwrap();
+        let cf = self.0.cf_handle(SCRIPT_STATUS_CF).unwrap();
+        self.0.put_cf(cf, script_hash.as_bytes(), val.as_slice()).unwrap();
+    }
+
+    pub fn get_script_status_tip(&self) -> Option<(usize, Sha256dHash)> {
+        self.0
+            .get(SCRIPT_STATUS_TIP)
+            .unwrap()
+            .map(|val| serde_json::from_slice(&*val).unwrap())
+    }
+
+    pub fn put_script_status_tip(&mut self, height: usize, hash: &Sha256dHash) {
+        let val = serde_json::to_vec(&(height, hash)).unwrap();
+        self.0.put(SCRIPT_STATUS_TIP, val.as_slice()).unwrap();
+    }
+
+    pub fn clear_script_statuses(&mut self) {
+        self.0.delete(SCRIPT_STATUS_TIP).unwrap();
+        let cf = self.0.cf_handle(SCRIPT_STATUS_CF).unwrap();
+        let keys: Vec<Box<[u8]>> = self
+            .0
+            .iterator_cf(cf, IteratorMode::Start)
+            .unwrap()
+            .map(|(key, _)| key)
+            .collect();
+        for key in keys {
+            self.0.delete_cf(cf, &key).unwrap();
+        }
+    }
+
+    pub fn get_last_audit_entry(&self) -> Option<AuditEntry> {
+        let cf = self.0.cf_handle(AUDIT_LOG_CF).unwrap();
+        let mut db_iterator = self.0.iterator_cf(cf, IteratorMode::End).unwrap();
+        db_iterator.next().map(|(_, val)| serde_json::from_slice(&val).unwrap())
+    }
+
+    /// entries are keyed by their big endian sequence number, so they are iterated in order
+    pub fn get_audit_log(&self, start: u64, limit: usize) -> Vec<AuditEntry> {
+        let mut key = [0u8; 8];
+        BigEndian::write_u64(&mut key, start);
+        let cf = self.0.cf_handle(AUDIT_LOG_CF).unwrap();
+        let db_iterator = self
+            .0
+            .iterator_cf(cf, IteratorMode::From(&key, Direction::Forward))
+            .unwrap();
+
+        db_iterator
+            .take(limit)
+            .map(|(_, val)| serde_json::from_slice(&val).unwrap())
+            .collect()
+    }
+
+    pub fn put_audit_entry(&mut self, entry: &AuditEntry) {
+        let mut key = [0u8; 8];
+        BigEndian::write_u64(&mut key, entry.seq);
+        let val = serde_json::to_vec(entry).unwrap();
+        let cf = self.0.cf_handle(AUDIT_LOG_CF).unwrap();
+        self.0.put_cf(cf, &key, val.as_slice()).unwrap();
+    }
+
+    pub fn get_block_header(&self, height: usize) -> Option<HeaderRecord> {
+        let mut key = [0u8; 8];
+        BigEndian::write_u64(&mut key, height as u64);
+        let cf = self.0.cf_handle(BLOCK_HEADER_CF).unwrap();
+        self.0
+            .get_cf(cf, &key)
+            .unwrap()
+            .map(|val| serde_json::from_slice(&*val).unwrap())
+    }
+
+    /// headers are keyed by their big endian height, the last one is the tip
+    pub fn get_last_block_header(&self) -> Option<HeaderRecord> {
+        let cf = self.0.cf_handle(BLOCK_HEADER_CF).unwrap();
+        let mut db_iterator = self.0.iterator_cf(cf, IteratorMode::End).unwrap();
+        db_iterator.next().map(|(_, val)| serde_json::from_slice(&val).unwrap())
+    }
+
+    pub fn put_block_header(&mut self, header: &HeaderRecord) {
+        let mut key = [0u8; 8];
+        BigEndian::write_u64(&mut key, header.height as u64);
+        let val = serde_json::to_vec(header).unwrap();
+        let cf = self.0.cf_handle(BLOCK_HEADER_CF).unwrap();
+        self.0.put_cf(cf, &key, val.as_slice()).unwrap();
+    }
+
+    /// remove the headers above `height`
+    pub fn delete_block_headers_above(&mut self, height: usize) {
+        let mut key = [0u8; 8];
+        BigEndian::write_u64(&mut key, height as u64 + 1);
+        let cf = self.0.cf_handle(BLOCK_HEADER_CF).unwrap();
+        let keys: Vec<Box<[u8]>> = self
+            .0
+            .iterator_cf(cf, IteratorMode::From(&key, Direction::Forward))
+            .unwrap()
+            .map(|(key, _)| key)
+            .collect();
+        for key in keys {
+            self.0.delete_cf(cf, &key).unwrap();
+        }
+    }
+
+    /// price of a bitcoin in `currency` at unix time `time`, as stored by `put_fiat_price`
+    pub fn get_fiat_price(&self, currency: &str, time: u32) -> Option<f64> {
+        let key = format!("{}:{}", currency, time);
+        let cf = self.0.cf_handle(FIAT_PRICE_CF).unwrap();
+        self.0
+            .get_cf(cf, key.as_bytes())
+            .unwrap()
+            .map(|val| BigEndian::read_f64(&*val))
+    }
+
+    pub fn put_fiat_price(&mut self, currency: &str, time: u32, price: f64) {
+        let key = format!("{}:{}", currency, time);
+        let mut buff = [0u8; 8];
+        BigEndian::write_f64(&mut buff, price);
+        let cf = self.0.cf_handle(FIAT_PRICE_CF).unwrap();
+        self.0.put_cf(cf, key.as_bytes(), &buff).unwrap();
+    }
+
     pub fn get_utxo_map(&self) -> HashMap<OutPoint, Utxo> {
         let cf = self.0.cf_handle(UTXO_MAP_CF).unwrap();
         let db_iterator = self.0.iterator_cf(cf, IteratorMode::Start).unwrap();
@@ -145,6 +956,35 @@ impl DB {
         vec
     }
 
+    pub fn get_external_public_key(&self, key_helper: &SecretKeyHelper) -> Option<PublicKey> {
+        self.get_public_key(EXTERNAL_PUBLIC_KEY_CF, key_helper)
+    }
+
+    pub fn get_internal_public_key(&self, key_helper: &SecretKeyHelper) -> Option<PublicKey> {
+        self.get_public_key(INTERNAL_PUBLIC_KEY_CF, key_helper)
+    }
+
+    fn get_public_key(&self, name: &str, key_helper: &SecretKeyHelper) -> Option<PublicKey> {
+        let key = serde_json::to_vec(key_helper).unwrap();
+        let cf = self.0.cf_handle(name).unwrap();
+        self.0.get_cf(cf, &key).unwrap().map(|val| {
+            let pk: String = serde_json::from_slice(&*val).unwrap();
+            PublicKey::from_slice(&hex::decode(&pk).unwrap()).unwrap()
+        })
+    }
+
+    /// whether `address` was stored by `put_address`, without listing the addresses
+    pub fn has_address(&self, addr_type: &AccountAddressType, address: &str) -> bool {
+        let name = match *addr_type {
+            AccountAddressType::P2PKH => P2PKH_ADDRESS_CF,
+            AccountAddressType::P2SHWH => P2SHWH_ADDRESS_CF,
+            AccountAddressType::P2WKH => P2WKH_ADDRESS_CF,
+        };
+        let key = serde_json::to_vec(address).unwrap();
+        let cf = self.0.cf_handle(name).unwrap();
+        self.0.get_cf(cf, &key).unwrap().is_some()
+    }
+
     pub fn get_full_address_list(&self) -> Vec<String> {
         let p2pkh = self.get_account_address_list(AccountAddressType::P2PKH);
         let p2shwh = self.get_account_address_list(AccountAddressType::P2SHWH);
@@ -206,4 +1046,739 @@ impl DB {
         let cf = self.0.cf_handle(LOCK_GROUP_MAP_CF).unwrap();
         self.0.put_cf(cf, &key, &value).unwrap();
     }
+
+    pub fn delete_lock_group(&mut self, lock_id: &LockId) {
+        let key = serde_json::to_vec(lock_id).unwrap();
+        let cf = self.0.cf_handle(LOCK_GROUP_MAP_CF).unwrap();
+        self.0.delete_cf(cf, &key).unwrap();
+    }
+
+    pub fn get_script_index(&self) -> HashMap<Script, (AccountAddressType, KeyPath)> {
+        let cf = self.0.cf_handle(SCRIPT_INDEX_CF).unwrap();
+        let db_iterator = self.0.iterator_cf(cf, IteratorMode::Start).unwrap();
+
+        let mut script_index = HashMap::new();
+        for (key, val) in db_iterator {
+            let script = Script::from(key.to_vec());
+            let entry: (AccountAddressType, KeyPath) = serde_json::from_slice(&val).unwrap();
+            script_index.insert(script, entry);
+        }
+        script_index
+    }
+
+    /// whether any key was indexed, without reading the index
+    pub fn has_script_index(&self) -> bool {
+        let cf = self.0.cf_handle(SCRIPT_INDEX_CF).unwrap();
+        self.0.iterator_cf(cf, IteratorMode::Start).unwrap().next().is_some()
+    }
+
+    /// the entry of `script` in the script index, without reading the others
+    pub fn get_script_index_entry(&self, script: &Script) -> Option<(AccountAddressType, KeyPath)> {
+        let cf = self.0.cf_handle(SCRIPT_INDEX_CF).unwrap();
+        self.0
+            .get_cf(cf, script.as_bytes())
+            .unwrap()
+            .map(|val| serde_json::from_slice(&*val).unwrap())
+    }
+
+    pub fn put_script_index(
+        &mut self,
+        script: &Script,
+        addr_type: &AccountAddressType,
+        key_path: &KeyPath,
+    ) {
+        let val = serde_json::to_vec(&(addr_type, key_path)).unwrap();
+        let cf = self.0.cf_handle(SCRIPT_INDEX_CF).unwrap();
+        self.0.put_cf(cf, script.as_bytes(), val.as_slice()).unwrap();
+        self.advance_next_index(addr_type, key_path);
+    }
+
+    /// the index after the last key indexed on `addr_chain` of the account, 0 if there is none
+    pub fn get_next_index(&self, addr_type: &AccountAddressType, addr_chain: &AddressChain) -> u32 {
+        let cf = self.0.cf_handle(NEXT_INDEX_CF).unwrap();
+        self.0
+            .get_cf(cf, next_index_key(addr_type, addr_chain))
+            .unwrap()
+            .map_or(0, |val| BigEndian::read_u32(&*val))
+    }
+
+    fn advance_next_index(&mut self, addr_type: &AccountAddressType, key_path: &KeyPath) {
+        let next = key_path.addr_index() + 1;
+        if next <= self.get_next_index(addr_type, key_path.addr_chain()) {
+            return;
+        }
+        let mut buff = [0u8; 4];
+        BigEndian::write_u32(&mut buff, next);
+        let cf = self.0.cf_handle(NEXT_INDEX_CF).unwrap();
+        self.0
+            .put_cf(cf, next_index_key(addr_type, key_path.addr_chain()), &buff)
+            .unwrap();
+    }
+
+    pub fn get_imported_keys(&self) -> HashMap<Script, (PublicKey, AccountAddressType)> {
+        let cf = self.0.cf_handle(IMPORTED_KEY_CF).unwrap();
+        let db_iterator = self.0.iterator_cf(cf, IteratorMode::Start).unwrap();
+
+        let mut imported_keys = HashMap::new();
+        for (key, val) in db_iterator {
+            let script = Script::from(key.to_vec());
+            let entry: (PublicKey, AccountAddressType) = serde_json::from_slice(&val).unwrap();
+            imported_keys.insert(script, entry);
+        }
+        imported_keys
+    }
+
+    pub fn put_imported_key(
+        &mut self,
+        script: &Script,
+        pk: &PublicKey,
+        addr_type: &AccountAddressType,
+    ) {
+        let val = serde_json::to_vec(&(pk, addr_type)).unwrap();
+        let cf = self.0.cf_handle(IMPORTED_KEY_CF).unwrap();
+        self.0.put_cf(cf, script.as_bytes(), val.as_slice()).unwrap();
+    }
+
+    pub fn get_imported_utxo_map(&self) -> HashMap<OutPoint, ImportedUtxo> {
+        let cf = self.0.cf_handle(IMPORTED_UTXO_CF).unwrap();
+        let db_iterator = self.0.iterator_cf(cf, IteratorMode::Start).unwrap();
+
+        let mut utxo_map = HashMap::new();
+        for (key, val) in db_iterator {
+            let out_point: OutPoint = serde_json::from_slice(&key).unwrap();
+            let utxo: ImportedUtxo = serde_json::from_slice(&val).unwrap();
+            utxo_map.insert(out_point, utxo);
+        }
+        utxo_map
+    }
+
+    pub fn put_imported_utxo(&mut self, op: &OutPoint, utxo: &ImportedUtxo) {
+        let key = serde_json::to_vec(op).unwrap();
+        let val = serde_json::to_vec(utxo).unwrap();
+        let cf = self.0.cf_handle(IMPORTED_UTXO_CF).unwrap();
+        self.0.put_cf(cf, key.as_slice(), val.as_slice()).unwrap();
+    }
+
+    pub fn delete_imported_utxo(&mut self, op: &OutPoint) {
+        let key = serde_json::to_vec(op).unwrap();
+        let cf = self.0.cf_handle(IMPORTED_UTXO_CF).unwrap();
+        self.0.delete_cf(cf, key.as_slice()).unwrap();
+    }
+
+    pub fn get_watched_scripts(&self) -> HashMap<Script, String> {
+        let cf = self.0.cf_handle(WATCHED_SCRIPT_CF).unwrap();
+        let db_iterator = self.0.iterator_cf(cf, IteratorMode::Start).unwrap();
+
+        let mut watched_scripts = HashMap::new();
+        for (key, val) in db_iterator {
+            let script = Script::from(key.to_vec());
+            let label: String = serde_json::from_slice(&val).unwrap();
+            watched_scripts.insert(script, label);
+        }
+        watched_scripts
+    }
+
+    pub fn put_watched_script(&mut self, script: &Script, label: &str) {
+        let val = serde_json::to_vec(label).unwrap();
+        let cf = self.0.cf_handle(WATCHED_SCRIPT_CF).unwrap();
+        self.0.put_cf(cf, script.as_bytes(), val.as_slice()).unwrap();
+    }
+
+    pub fn get_watched_utxo_map(&self) -> HashMap<OutPoint, WatchedUtxo> {
+        let cf = self.0.cf_handle(WATCHED_UTXO_CF).unwrap();
+        let db_iterator = self.0.iterator_cf(cf, IteratorMode::Start).unwrap();
+
+        let mut utxo_map = HashMap::new();
+        for (key, val) in db_iterator {
+            let out_point: OutPoint = serde_json::from_slice(&key).unwrap();
+            let utxo: WatchedUtxo = serde_json::from_slice(&val).unwrap();
+            utxo_map.insert(out_point, utxo);
+        }
+        utxo_map
+    }
+
+    pub fn put_watched_utxo(&mut self, utxo: &WatchedUtxo) {
+        let key = serde_json::to_vec(&utxo.out_point).unwrap();
+        let val = serde_json::to_vec(utxo).unwrap();
+        let cf = self.0.cf_handle(WATCHED_UTXO_CF).unwrap();
+        self.0.put_cf(cf, key.as_slice(), val.as_slice()).unwrap();
+    }
+
+    pub fn delete_watched_utxo(&mut self, op: &OutPoint) {
+        let key = serde_json::to_vec(op).unwrap();
+        let cf = self.0.cf_handle(WATCHED_UTXO_CF).unwrap();
+        self.0.delete_cf(cf, key.as_slice()).unwrap();
+    }
+
+    pub fn get_sent_payment_codes(&self) -> HashMap<String, u32> {
+        self.get_payment_codes(SENT_PAYMENT_CODE_CF)
+    }
+
+    pub fn put_sent_payment_code(&mut self, code: &str, next_index: u32) {
+        self.put_payment_code(SENT_PAYMENT_CODE_CF, code, next_index);
+    }
+
+    pub fn get_received_payment_codes(&self) -> HashMap<String, u32> {
+        self.get_payment_codes(RECEIVED_PAYMENT_CODE_CF)
+    }
+
+    pub fn put_received_payment_code(&mut self, code: &str, watched_count: u32) {
+        self.put_payment_code(RECEIVED_PAYMENT_CODE_CF, code, watched_count);
+    }
+
+    fn get_payment_codes(&self, cf_name: &str) -> HashMap<String, u32> {
+        let cf = self.0.cf_handle(cf_name).unwrap();
+        let db_iterator = self.0.iterator_cf(cf, IteratorMode::Start).unwrap();
+
+        let mut payment_codes = HashMap::new();
+        for (key, val) in db_iterator {
+            let code: String = serde_json::from_slice(&key).unwrap();
+            let index: u32 = serde_json::from_slice(&val).unwrap();
+            payment_codes.insert(code, index);
+        }
+        payment_codes
+    }
+
+    fn put_payment_code(&mut self, cf_name: &str, code: &str, index: u32) {
+        let key = serde_json::to_vec(code).unwrap();
+        let val = serde_json::to_vec(&index).unwrap();
+        let cf = self.0.cf_handle(cf_name).unwrap();
+        self.0.put_cf(cf, key.as_slice(), val.as_slice()).unwrap();
+    }
+
+    pub fn get_silent_payment_utxo_map(&self) -> HashMap<OutPoint, SilentPaymentUtxo> {
+        let cf = self.0.cf_handle(SILENT_PAYMENT_UTXO_CF).unwrap();
+        let db_iterator = self.0.iterator_cf(cf, IteratorMode::Start).unwrap();
+
+        let mut utxo_map = HashMap::new();
+        for (key, val) in db_iterator {
+            let out_point: OutPoint = serde_json::from_slice(&key).unwrap();
+            let utxo: SilentPaymentUtxo = serde_json::from_slice(&val).unwrap();
+            utxo_map.insert(out_point, utxo);
+        }
+        utxo_map
+    }
+
+    pub fn put_silent_payment_utxo(&mut self, utxo: &SilentPaymentUtxo) {
+        let key = serde_json::to_vec(&utxo.out_point).unwrap();
+        let val = serde_json::to_vec(utxo).unwrap();
+        let cf = self.0.cf_handle(SILENT_PAYMENT_UTXO_CF).unwrap();
+        self.0.put_cf(cf, key.as_slice(), val.as_slice()).unwrap();
+    }
+
+    pub fn delete_silent_payment_utxo(&mut self, op: &OutPoint) {
+        let key = serde_json::to_vec(op).unwrap();
+        let cf = self.0.cf_handle(SILENT_PAYMENT_UTXO_CF).unwrap();
+        self.0.delete_cf(cf, key.as_slice()).unwrap();
+    }
+
+    pub fn get_timelock_scripts(&self) -> HashMap<Script, (u32, Timelock)> {
+        let cf = self.0.cf_handle(TIMELOCK_SCRIPT_CF).unwrap();
+        let db_iterator = self.0.iterator_cf(cf, IteratorMode::Start).unwrap();
+
+        let mut timelock_scripts = HashMap::new();
+        for (key, val) in db_iterator {
+            let script = Script::from(key.to_vec());
+            let lock: (u32, Timelock) = serde_json::from_slice(&val).unwrap();
+            timelock_scripts.insert(script, lock);
+        }
+        timelock_scripts
+    }
+
+    pub fn put_timelock_script(&mut self, script: &Script, key_index: u32, timelock: &Timelock) {
+        let val = serde_json::to_vec(&(key_index, timelock)).unwrap();
+        let cf = self.0.cf_handle(TIMELOCK_SCRIPT_CF).unwrap();
+        self.0.put_cf(cf, script.as_bytes(), val.as_slice()).unwrap();
+    }
+
+    pub fn get_timelocked_utxo_map(&self) -> HashMap<OutPoint, TimelockedUtxo> {
+        let cf = self.0.cf_handle(TIMELOCKED_UTXO_CF).unwrap();
+        let db_iterator = self.0.iterator_cf(cf, IteratorMode::Start).unwrap();
+
+        let mut utxo_map = HashMap::new();
+        for (key, val) in db_iterator {
+            let out_point: OutPoint = serde_json::from_slice(&key).unwrap();
+            let utxo: TimelockedUtxo = serde_json::from_slice(&val).unwrap();
+            utxo_map.insert(out_point, utxo);
+        }
+        utxo_map
+    }
+
+    pub fn put_timelocked_utxo(&mut self, utxo: &TimelockedUtxo) {
+        let key = serde_json::to_vec(&utxo.out_point).unwrap();
+        let val = serde_json::to_vec(utxo).unwrap();
+        let cf = self.0.cf_handle(TIMELOCKED_UTXO_CF).unwrap();
+        self.0.put_cf(cf, key.as_slice(), val.as_slice()).unwrap();
+    }
+
+    pub fn delete_timelocked_utxo(&mut self, op: &OutPoint) {
+        let key = serde_json::to_vec(op).unwrap();
+        let cf = self.0.cf_handle(TIMELOCKED_UTXO_CF).unwrap();
+        self.0.delete_cf(cf, key.as_slice()).unwrap();
+    }
+
+    pub fn get_vault(&self) -> Option<Vault> {
+        self.0
+            .get(VAULT)
+            .unwrap()
+            .map(|val| serde_json::from_slice(&*val).unwrap())
+    }
+
+    pub fn put_vault(&mut self, vault: &Vault) {
+        let val = serde_json::to_vec(vault).unwrap();
+        self.0.put(VAULT, val.as_slice()).unwrap();
+    }
+
+    pub fn get_vault_scripts(&self) -> HashMap<Script, (u32, Vault)> {
+        let cf = self.0.cf_handle(VAULT_SCRIPT_CF).unwrap();
+        let db_iterator = self.0.iterator_cf(cf, IteratorMode::Start).unwrap();
+
+        let mut vault_scripts = HashMap::new();
+        for (key, val) in db_iterator {
+            let script = Script::from(key.to_vec());
+            let entry: (u32, Vault) = serde_json::from_slice(&val).unwrap();
+            vault_scripts.insert(script, entry);
+        }
+        vault_scripts
+    }
+
+    pub fn put_vault_script(&mut self, script: &Script, key_index: u32, vault: &Vault) {
+        let val = serde_json::to_vec(&(key_index, vault)).unwrap();
+        let cf = self.0.cf_handle(VAULT_SCRIPT_CF).unwrap();
+        self.0.put_cf(cf, script.as_bytes(), val.as_slice()).unwrap();
+    }
+
+    pub fn get_vault_utxo_map(&self) -> HashMap<OutPoint, VaultUtxo> {
+        let cf = self.0.cf_handle(VAULT_UTXO_CF).unwrap();
+        let db_iterator = self.0.iterator_cf(cf, IteratorMode::Start).unwrap();
+
+        let mut utxo_map = HashMap::new();
+        for (key, val) in db_iterator {
+            let out_point: OutPoint = serde_json::from_slice(&key).unwrap();
+            let utxo: VaultUtxo = serde_json::from_slice(&val).unwrap();
+            utxo_map.insert(out_point, utxo);
+        }
+        utxo_map
+    }
+
+    pub fn put_vault_utxo(&mut self, utxo: &VaultUtxo) {
+        let key = serde_json::to_vec(&utxo.out_point).unwrap();
+        let val = serde_json::to_vec(utxo).unwrap();
+        let cf = self.0.cf_handle(VAULT_UTXO_CF).unwrap();
+        self.0.put_cf(cf, key.as_slice(), val.as_slice()).unwrap();
+    }
+
+    pub fn delete_vault_utxo(&mut self, op: &OutPoint) {
+        let key = serde_json::to_vec(op).unwrap();
+        let cf = self.0.cf_handle(VAULT_UTXO_CF).unwrap();
+        self.0.delete_cf(cf, key.as_slice()).unwrap();
+    }
+
+    /// the unvaults the wallet signed
+    pub fn get_unvault_txids(&self) -> HashSet<Sha256dHash> {
+        let cf = self.0.cf_handle(UNVAULT_TX_CF).unwrap();
+        self.0
+            .iterator_cf(cf, IteratorMode::Start)
+            .unwrap()
+            .map(|(key, _)| serde_json::from_slice(&key).unwrap())
+            .collect()
+    }
+
+    pub fn put_unvault_txid(&mut self, txid: &Sha256dHash) {
+        let key = serde_json::to_vec(txid).unwrap();
+        let cf = self.0.cf_handle(UNVAULT_TX_CF).unwrap();
+        self.0.put_cf(cf, key.as_slice(), &[]).unwrap();
+    }
+
+    pub fn get_script_accounts(&self) -> HashMap<String, ScriptAccount> {
+        let cf = self.0.cf_handle(SCRIPT_ACCOUNT_CF).unwrap();
+        let db_iterator = self.0.iterator_cf(cf, IteratorMode::Start).unwrap();
+
+        let mut accounts = HashMap::new();
+        for (_, val) in db_iterator {
+            let account: ScriptAccount = serde_json::from_slice(&val).unwrap();
+            accounts.insert(account.name.clone(), account);
+        }
+        accounts
+    }
+
+    pub fn put_script_account(&mut self, account: &ScriptAccount) {
+        let val = serde_json::to_vec(account).unwrap();
+        let cf = self.0.cf_handle(SCRIPT_ACCOUNT_CF).unwrap();
+        self.0.put_cf(cf, account.name.as_bytes(), val.as_slice()).unwrap();
+    }
+
+    pub fn get_script_utxo_map(&self) -> HashMap<OutPoint, ScriptUtxo> {
+        let cf = self.0.cf_handle(SCRIPT_UTXO_CF).unwrap();
+        let db_iterator = self.0.iterator_cf(cf, IteratorMode::Start).unwrap();
+
+        let mut utxo_map = HashMap::new();
+        for (key, val) in db_iterator {
+            let out_point: OutPoint = serde_json::from_slice(&key).unwrap();
+            let utxo: ScriptUtxo = serde_json::from_slice(&val).unwrap();
+            utxo_map.insert(out_point, utxo);
+        }
+        utxo_map
+    }
+
+    pub fn put_script_utxo(&mut self, utxo: &ScriptUtxo) {
+        let key = serde_json::to_vec(&utxo.out_point).unwrap();
+        let val = serde_json::to_vec(utxo).unwrap();
+        let cf = self.0.cf_handle(SCRIPT_UTXO_CF).unwrap();
+        self.0.put_cf(cf, key.as_slice(), val.as_slice()).unwrap();
+    }
+
+    pub fn delete_script_utxo(&mut self, op: &OutPoint) {
+        let key = serde_json::to_vec(op).unwrap();
+        let cf = self.0.cf_handle(SCRIPT_UTXO_CF).unwrap();
+        self.0.delete_cf(cf, key.as_slice()).unwrap();
+    }
+
+    pub fn get_spent_utxo_map(&self) -> HashMap<OutPoint, SpentUtxo> {
+        let cf = self.0.cf_handle(SPENT_UTXO_CF).unwrap();
+        let db_iterator = self.0.iterator_cf(cf, IteratorMode::Start).unwrap();
+
+        let mut utxo_map = HashMap::new();
+        for (key, val) in db_iterator {
+            let out_point: OutPoint = serde_json::from_slice(&key).unwrap();
+            let utxo: SpentUtxo = serde_json::from_slice(&val).unwrap();
+            utxo_map.insert(out_point, utxo);
+        }
+        utxo_map
+    }
+
+    pub fn put_spent_utxo(&mut self, utxo: &SpentUtxo) {
+        let key = serde_json::to_vec(&utxo.utxo.out_point).unwrap();
+        let val = serde_json::to_vec(utxo).unwrap();
+        let cf = self.0.cf_handle(SPENT_UTXO_CF).unwrap();
+        self.0.put_cf(cf, key.as_slice(), val.as_slice()).unwrap();
+    }
+
+    pub fn delete_spent_utxo(&mut self, op: &OutPoint) {
+        let key = serde_json::to_vec(op).unwrap();
+        let cf = self.0.cf_handle(SPENT_UTXO_CF).unwrap();
+        self.0.delete_cf(cf, key.as_slice()).unwrap();
+    }
+
+    pub fn get_used_scripts(&self) -> HashMap<Script, (AccountAddressType, OutPoint)> {
+        let cf = self.0.cf_handle(USED_SCRIPT_CF).unwrap();
+        let db_iterator = self.0.iterator_cf(cf, IteratorMode::Start).unwrap();
+
+        let mut used_scripts = HashMap::new();
+        for (key, val) in db_iterator {
+            let script = Script::from(key.to_vec());
+            let entry: (AccountAddressType, OutPoint) = serde_json::from_slice(&val).unwrap();
+            used_scripts.insert(script, entry);
+        }
+        used_scripts
+    }
+
+    pub fn put_used_script(
+        &mut self,
+        script: &Script,
+        addr_type: &AccountAddressType,
+        first_funding: &OutPoint,
+    ) {
+        let val = serde_json::to_vec(&(addr_type, first_funding)).unwrap();
+        let cf = self.0.cf_handle(USED_SCRIPT_CF).unwrap();
+        self.0.put_cf(cf, script.as_bytes(), val.as_slice()).unwrap();
+    }
+
+    pub fn get_frozen_utxo_set(&self) -> HashSet<OutPoint> {
+        let cf = self.0.cf_handle(FROZEN_UTXO_CF).unwrap();
+        let db_iterator = self.0.iterator_cf(cf, IteratorMode::Start).unwrap();
+
+        let mut frozen = HashSet::new();
+        for (key, _) in db_iterator {
+            let out_point: OutPoint = serde_json::from_slice(&key).unwrap();
+            frozen.insert(out_point);
+        }
+        frozen
+    }
+
+    pub fn put_frozen_utxo(&mut self, op: &OutPoint) {
+        let key = serde_json::to_vec(op).unwrap();
+        let cf = self.0.cf_handle(FROZEN_UTXO_CF).unwrap();
+        self.0.put_cf(cf, key.as_slice(), &[]).unwrap();
+    }
+
+    pub fn delete_frozen_utxo(&mut self, op: &OutPoint) {
+        let key = serde_json::to_vec(op).unwrap();
+        let cf = self.0.cf_handle(FROZEN_UTXO_CF).unwrap();
+        self.0.delete_cf(cf, key.as_slice()).unwrap();
+    }
+
+    pub fn get_unconfirmed_tx_map(&self) -> HashMap<Sha256dHash, Transaction> {
+        let cf = self.0.cf_handle(UNCONFIRMED_TX_CF).unwrap();
+        let db_iterator = self.0.iterator_cf(cf, IteratorMode::Start).unwrap();
+
+        let mut tx_map = HashMap::new();
+        for (key, val) in db_iterator {
+            let txid: Sha256dHash = serde_json::from_slice(&key).unwrap();
+            let tx: Transaction = serde_json::from_slice(&val).unwrap();
+            tx_map.insert(txid, tx);
+        }
+        tx_map
+    }
+
+    pub fn put_unconfirmed_tx(&mut self, tx: &Transaction) {
+        let key = serde_json::to_vec(&tx.txid()).unwrap();
+        let val = serde_json::to_vec(tx).unwrap();
+        let cf = self.0.cf_handle(UNCONFIRMED_TX_CF).unwrap();
+        self.0.put_cf(cf, key.as_slice(), val.as_slice()).unwrap();
+    }
+
+    pub fn delete_unconfirmed_tx(&mut self, txid: &Sha256dHash) {
+        let key = serde_json::to_vec(txid).unwrap();
+        let cf = self.0.cf_handle(UNCONFIRMED_TX_CF).unwrap();
+        self.0.delete_cf(cf, key.as_slice()).unwrap();
+    }
+
+    pub fn get_tx_history(&self) -> HashMap<Sha256dHash, TxRecord> {
+        let cf = self.0.cf_handle(TX_HISTORY_CF).unwrap();
+        let db_iterator = self.0.iterator_cf(cf, IteratorMode::Start).unwrap();
+
+        let mut tx_history = HashMap::new();
+        for (key, val) in db_iterator {
+            let txid: Sha256dHash = serde_json::from_slice(&key).unwrap();
+            let record: TxRecord = serde_json::from_slice(&val).unwrap();
+            tx_history.insert(txid, record);
+        }
+        tx_history
+    }
+
+    pub fn put_tx_record(&mut self, record: &TxRecord) {
+        let key = serde_json::to_vec(&record.txid()).unwrap();
+        let val = serde_json::to_vec(record).unwrap();
+        let cf = self.0.cf_handle(TX_HISTORY_CF).unwrap();
+        self.0.put_cf(cf, key.as_slice(), val.as_slice()).unwrap();
+    }
+}
+
+// the keys of a wallet outside of the column families but the seed randomness,
+// removed by `clear` and held by a dump
+fn wallet_keys() -> Vec<&'static [u8]> {
+    vec![
+        LAST_SEEN_BLOCK_HEIGHT,
+        PENDING_ROTATION,
+        SPENDING_POLICY,
+        POLICY_SPENDS,
+        NEXT_SPEND_ID,
+        DERIVATION_SCHEMES,
+        DERIVATION_MODE,
+        BIRTHDAY,
+        WATCH_ONLY_KEYS,
+        NEXT_UNSIGNED_ID,
+        SCRIPT_STATUS_TIP,
+        VAULT,
+        MASTER_FINGERPRINT,
+    ]
+}
+
+// the column families of a wallet, all but the audit log
+fn wallet_cfs() -> Vec<&'static str> {
+    vec![
+        UTXO_MAP_CF,
+        EXTERNAL_PUBLIC_KEY_CF,
+        INTERNAL_PUBLIC_KEY_CF,
+        P2PKH_ADDRESS_CF,
+        P2SHWH_ADDRESS_CF,
+        P2WKH_ADDRESS_CF,
+        LOCK_GROUP_MAP_CF,
+        SCRIPT_INDEX_CF,
+        IMPORTED_KEY_CF,
+        IMPORTED_UTXO_CF,
+        USED_SCRIPT_CF,
+        FROZEN_UTXO_CF,
+        UNCONFIRMED_TX_CF,
+        TX_HISTORY_CF,
+        WATCHED_SCRIPT_CF,
+        WATCHED_UTXO_CF,
+        SENT_PAYMENT_CODE_CF,
+        RECEIVED_PAYMENT_CODE_CF,
+        SILENT_PAYMENT_UTXO_CF,
+        PENDING_SPEND_CF,
+        BLOCK_HEADER_CF,
+        SPENT_UTXO_CF,
+        FIAT_PRICE_CF,
+        TIMELOCK_SCRIPT_CF,
+        TIMELOCKED_UTXO_CF,
+        SCRIPT_ACCOUNT_CF,
+        SCRIPT_UTXO_CF,
+        UNSIGNED_TX_CF,
+        IDEMPOTENT_SEND_CF,
+        ADDRESS_STATS_CF,
+        ADDRESS_LABEL_CF,
+        SCRIPT_STATUS_CF,
+        VAULT_SCRIPT_CF,
+        VAULT_UTXO_CF,
+        UNVAULT_TX_CF,
+        NEXT_INDEX_CF,
+    ]
+}
+
+/// the data of a wallet as `DB::dump` gives it, keys and values are hex encoded
+#[derive(Serialize, Deserialize)]
+struct Dump {
+    schema_version: u32,
+    /// by column family, the keys outside of them are under the default one
+    entries: Vec<(String, Vec<(String, String)>)>,
+}
+
+fn next_index_key(addr_type: &AccountAddressType, addr_chain: &AddressChain) -> String {
+    let chain: u32 = addr_chain.clone().into();
+    format!("{}:{}", addr_type.as_str(), chain)
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::Script;
+    use rocksdb::IteratorMode;
+    use super::{DB, SCHEMA_VERSION, MIGRATIONS, NEXT_INDEX_CF, DEFAULT_CF, wallet_keys, wallet_cfs};
+    use crate::backup::Backup;
+    use crate::account::{DerivationMode, AccountAddressType, AddressChain, KeyPath};
+    use crate::error::WalletError;
+    use crate::headers::HeaderRecord;
+
+    #[test]
+    fn schema_version() {
+        assert_eq!(MIGRATIONS.len(), SCHEMA_VERSION as usize);
+
+        let db_path = "/tmp/test_schema_version".to_owned();
+        let _ = std::fs::remove_dir_all(&db_path);
+        {
+            let mut db = DB::new(db_path.clone()).unwrap();
+            assert_eq!(db.get_schema_version(), SCHEMA_VERSION);
+            // the version is kept when the wallet is wiped
+            db.clear();
+            assert_eq!(db.get_schema_version(), SCHEMA_VERSION);
+            db.put_schema_version(SCHEMA_VERSION + 1);
+        }
+        match DB::new(db_path) {
+            Err(WalletError::UnsupportedSchemaVersion(version)) => {
+                assert_eq!(version, SCHEMA_VERSION + 1)
+            }
+            _ => panic!("a database of a newer wallet is opened"),
+        }
+    }
+
+    #[test]
+    fn memory_store() {
+        let mut db = DB::in_memory().unwrap();
+        assert_eq!(db.get_schema_version(), SCHEMA_VERSION);
+        assert_eq!(db.get_last_block_header(), None);
+
+        for height in 1..4 {
+            db.put_block_header(&HeaderRecord {
+                height,
+                hash: Default::default(),
+                prev_hash: Default::default(),
+                merkle_root: Default::default(),
+                time: 0,
+            });
+        }
+        assert_eq!(db.get_last_block_header().unwrap().height, 3);
+        db.delete_block_headers_above(1);
+        assert_eq!(db.get_last_block_header().unwrap().height, 1);
+        assert!(db.get_block_header(2).is_none());
+
+        db.put_script_status("00", "01");
+        db.put_script_status_tip(3, &Default::default());
+        assert_eq!(db.get_script_statuses().get("00").map(String::as_str), Some("01"));
+        db.clear_script_statuses();
+        assert!(db.get_script_statuses().is_empty());
+        assert_eq!(db.get_script_status_tip(), None);
+
+        db.put_last_seen_block_height(10);
+        db.clear();
+        assert_eq!(db.get_last_seen_block_height(), 1);
+        assert_eq!(db.get_last_block_header(), None);
+    }
+
+    #[test]
+    fn legacy_derivation() {
+        let db_path = "/tmp/test_legacy_derivation".to_owned();
+        let _ = std::fs::remove_dir_all(&db_path);
+        {
+            let mut db = DB::new(db_path.clone()).unwrap();
+            assert_eq!(db.get_derivation_mode(), DerivationMode::Standard);
+            db.put_bip39_randomness(&[0u8; 32]);
+            // as if written before the standard derivation
+            db.put_schema_version(1);
+        }
+        let db = DB::new(db_path).unwrap();
+        assert_eq!(db.get_derivation_mode(), DerivationMode::Legacy);
+        assert_eq!(db.get_schema_version(), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn next_index() {
+        let mut db = DB::in_memory().unwrap();
+        let p2wkh = AccountAddressType::P2WKH;
+        assert!(!db.has_script_index());
+        assert_eq!(db.get_next_index(&p2wkh, &AddressChain::External), 0);
+
+        let key_path = |index| KeyPath::new(AddressChain::External, index);
+        db.put_script_index(&Script::from(vec![4]), &p2wkh, &key_path(4));
+        db.put_script_index(&Script::from(vec![2]), &p2wkh, &key_path(2));
+        assert!(db.has_script_index());
+        assert_eq!(db.get_next_index(&p2wkh, &AddressChain::External), 5);
+        assert_eq!(db.get_next_index(&p2wkh, &AddressChain::Internal), 0);
+        assert_eq!(db.get_next_index(&AccountAddressType::P2PKH, &AddressChain::External), 0);
+
+        // as if written before the indexes were counted
+        let cf = db.0.cf_handle(NEXT_INDEX_CF).unwrap();
+        let keys: Vec<_> = db.0.iterator_cf(cf, IteratorMode::Start).unwrap().collect();
+        for (key, _) in keys {
+            db.0.delete_cf(cf, &key).unwrap();
+        }
+        db.put_schema_version(SCHEMA_VERSION - 1);
+        db.migrate().unwrap();
+        assert_eq!(db.get_next_index(&p2wkh, &AddressChain::External), 5);
+
+        db.clear();
+        assert_eq!(db.get_next_index(&p2wkh, &AddressChain::External), 0);
+    }
+
+    // the value of each key and the entries of each column family of the wallet
+    fn entries(db: &DB) -> Vec<(&'static str, Vec<(Box<[u8]>, Box<[u8]>)>)> {
+        let keys = wallet_keys().into_iter().filter_map(|key| {
+            let val = db.0.get(key).unwrap()?;
+            Some((Box::from(key), val.into_boxed_slice()))
+        });
+        let mut entries = vec![(DEFAULT_CF, keys.collect())];
+        for name in wallet_cfs() {
+            let cf = db.0.cf_handle(name).unwrap();
+            entries.push((name, db.0.iterator_cf(cf, IteratorMode::Start).unwrap().collect()));
+        }
+        entries
+    }
+
+    #[test]
+    fn backup_every_column_family() {
+        let db_path = "/tmp/test_backup_every_column_family".to_owned();
+        let _ = std::fs::remove_dir_all(&db_path);
+        let mut db = DB::new(db_path).unwrap();
+        db.put_bip39_randomness(&[0x5a; 32]);
+        for (index, key) in wallet_keys().into_iter().enumerate() {
+            db.0.put(key, &[index as u8]).unwrap();
+        }
+        for (index, name) in wallet_cfs().into_iter().enumerate() {
+            let cf = db.0.cf_handle(name).unwrap();
+            db.0.put_cf(cf, &[index as u8], name.as_bytes()).unwrap();
+        }
+        let written = entries(&db);
+        assert!(written.iter().all(|(_, entries)| !entries.is_empty()));
+
+        let backup = Backup::from_db(&db, "xpub".to_owned()).unwrap();
+        let data = backup.encrypt("backup passphrase").unwrap();
+        db.clear();
+        assert!(entries(&db).iter().all(|(_, entries)| entries.is_empty()));
+        assert_eq!(db.get_bip39_randomness(), None);
+
+        let backup = Backup::decrypt(&data, "backup passphrase").unwrap();
+        backup.to_db(&mut db).unwrap();
+        assert_eq!(entries(&db), written);
+        assert_eq!(db.get_bip39_randomness(), Some(vec![0x5a; 32]));
+    }
 }
@@ -12,14 +12,39 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
-use bitcoin::{Block, Transaction, OutPoint};
+use bitcoin::{
+    Block, BitcoinHash, Transaction, TxOut, OutPoint, PrivateKey, PublicKey,
+    consensus::encode::deserialize,
+    util::psbt::PartiallySignedTransaction,
+};
+use bitcoin_hashes::sha256d::Hash as Sha256dHash;
+use secp256k1::Secp256k1;
 
-use std::error::Error;
+use std::{error::Error, sync::Arc, collections::{HashSet, HashMap}};
 
-use super::walletlibrary::{WalletLibrary, WalletConfig, LockId, WalletLibraryMode};
-use super::interface::{BlockChainIO, WalletLibraryInterface, Wallet};
+use super::walletlibrary::{
+    WalletLibrary, WalletConfig, LockId, SendOptions, WalletLibraryMode, KeyGenConfig, SendOutcome,
+    InputSpec, ScriptSpend,
+};
+use super::interface::{
+    BlockChainIO, WalletLibraryInterface, Wallet, SyncProgress, BackendConfig, BackendKind,
+    ConnectionStatus, FetchedBlocks, BlockFetch,
+};
+use super::corewallet::CoreWalletIO;
+use super::quorum::Quorum;
+use super::events::WalletEvent;
+use super::retry::Retrying;
 use super::error::WalletError;
 use super::mnemonic::Mnemonic;
+use super::account::AccountAddressType;
+use super::keyfactory::wipe_private_key;
+use super::bip352;
+
+// makes the `BlockChainIO` of another backend for `Wallet::reconnect`
+type Connector<IO> = Box<dyn Fn(&BackendConfig) -> Result<IO, Box<dyn Error>> + Send>;
+
+// bitcoind takes this long around the timestamp of an import, from `TIMESTAMP_WINDOW`
+const CORE_TIMESTAMP_WINDOW: u64 = 2 * 60 * 60;
 
 // a factory for TREZOR (BIP44) compatible accounts
 pub struct WalletWithTrustedFullNode<IO>
@@ -27,12 +52,26 @@ where
     IO: BlockChainIO,
 {
     pub wallet_lib: Box<dyn WalletLibraryInterface + Send>,
-    bio: IO,
+    // shared with the downloads of `block_fetch`
+    bio: Arc<Retrying<IO>>,
+    raw_blocks: bool,
+    // the outputs spent by the blocks are fetched along with them, see `WalletConfig`
+    silent_payments: bool,
+    endpoint: String,
+    connector: Option<Connector<IO>>,
+    // the blocks are not scanned if it is set, see `set_core_wallet`
+    core_wallet: Option<Box<dyn CoreWalletIO + Send>>,
+    // ranges of the descriptors imported into it, they are imported again once they grow
+    imported_ranges: Vec<serde_json::Value>,
+    // coins known before a sync done in batches started, the ones found are counted against it
+    sync_known: Option<HashSet<OutPoint>>,
+    // other nodes checked against `bio`, see `set_quorum`
+    quorum: Option<Quorum<IO>>,
 }
 
 impl<IO> Wallet for WalletWithTrustedFullNode<IO>
 where
-    IO: BlockChainIO,
+    IO: BlockChainIO + Send + Sync + 'static,
 {
     fn wallet_lib(&self) -> &Box<dyn WalletLibraryInterface + Send> {
         &self.wallet_lib
@@ -42,23 +81,55 @@ where
         &mut self.wallet_lib
     }
 
-    fn reconnect(&mut self) {}
+    // the node is asked over a new connection every time, only check it answers
+    fn reconnect(&mut self, config: Option<BackendConfig>) -> Result<(), Box<dyn Error>> {
+        let config = match config {
+            Some(config) => config,
+            None => {
+                self.bio.get_block_count()?;
+                return Ok(());
+            }
+        };
+        let connector = self
+            .connector
+            .as_ref()
+            .ok_or("the backend of this wallet can't be replaced")?;
+        // a download still running goes on with the previous connection
+        let bio = Retrying::new(connector(&config)?, self.bio.policy().clone());
+        bio.get_block_count()?;
+        self.bio = Arc::new(bio);
+        self.endpoint = config.address.to_string();
+        Ok(())
+    }
+
+    fn connection_status(&self) -> ConnectionStatus {
+        ConnectionStatus {
+            backend: BackendKind::FullNode,
+            endpoint: self.endpoint.clone(),
+            last_success: self.bio.last_success(),
+        }
+    }
+
+    fn into_wallet_lib(self: Box<Self>) -> Box<dyn WalletLibraryInterface + Send> {
+        self.wallet_lib
+    }
 
     fn send_coins(
         &mut self,
         addr_str: String,
         amt: u64,
-        lock_coins: bool,
-        witness_only: bool,
         submit: bool,
-    ) -> Result<(Transaction, LockId), Box<dyn Error>> {
-        let (tx, lock_id) = self
-            .wallet_lib
-            .send_coins(addr_str, amt, lock_coins, witness_only)?;
-        if submit {
-            self.bio.send_raw_transaction(&tx)?;
+        options: SendOptions,
+    ) -> Result<SendOutcome, Box<dyn Error>> {
+        let params = format!("dest_addr={} amt={} submit={}", addr_str, amt, submit);
+        let outcome = self.wallet_lib.send_coins(addr_str, amt, options)?;
+        if let SendOutcome::Sent(ref sent) = outcome {
+            self.wallet_lib.audit("send_coins", params, Some(sent.txid()));
+            if submit {
+                self.publish_tx(&sent.tx)?;
+            }
         }
-        Ok((tx, lock_id))
+        Ok(outcome)
     }
 
     fn make_tx(
@@ -67,53 +138,569 @@ where
         addr_str: String,
         amt: u64,
         submit: bool,
+        change_addr_type: Option<AccountAddressType>,
     ) -> Result<Transaction, Box<dyn Error>> {
-        let tx = self.wallet_lib.make_tx(ops, addr_str, amt).unwrap();
+        let params = format!("dest_addr={} amt={} submit={}", addr_str, amt, submit);
+        let tx = self.wallet_lib.make_tx(ops, addr_str, amt, change_addr_type).unwrap();
+        self.wallet_lib.audit("make_tx", params, Some(tx.txid()));
         if submit {
-            self.bio.send_raw_transaction(&tx)?;
+            self.publish_tx(&tx)?;
+        }
+        Ok(tx)
+    }
+
+    fn make_tx_advanced(
+        &mut self,
+        inputs: Vec<InputSpec>,
+        addr_str: String,
+        amt: u64,
+        submit: bool,
+        change_addr_type: Option<AccountAddressType>,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let params = format!("dest_addr={} amt={} submit={}", addr_str, amt, submit);
+        let tx = self.wallet_lib.make_tx_advanced(inputs, addr_str, amt, change_addr_type)?;
+        self.wallet_lib.audit("make_tx_advanced", params, Some(tx.txid()));
+        if submit {
+            self.publish_tx(&tx)?;
         }
         Ok(tx)
     }
 
     fn publish_tx(&mut self, tx: &Transaction) -> Result<(), Box<dyn Error>> {
-        self.bio.send_raw_transaction(tx)?;
+        // a node refusing it may be lying, the witnesses get it all the same
+        let published = self.bio.send_raw_transaction(tx);
+        let accepted = self.quorum.as_ref().map_or(0, |quorum| quorum.broadcast(tx));
+        if let Err(err) = published {
+            if accepted == 0 {
+                return Err(err.into());
+            }
+            log::warn!("the node refused {}, {} witnesses took it: {}", tx.txid(), accepted, err);
+        }
+        self.wallet_lib.add_unconfirmed_tx(tx);
+        self.wallet_lib.audit("publish_tx", String::new(), Some(tx.txid()));
         Ok(())
     }
 
-    fn sync_with_tip(&mut self) -> Result<(), Box<dyn Error>> {
+    fn commit_tx(&mut self, prepared_id: u64) -> Result<Transaction, Box<dyn Error>> {
+        let tx = self.wallet_lib.sign_prepared_tx(prepared_id)?;
+        self.publish_tx(&tx)?;
+        Ok(tx)
+    }
+
+    fn publish_funding(&mut self, funding_id: LockId) -> Result<Transaction, Box<dyn Error>> {
+        let tx = self.wallet_lib.sign_funding(funding_id)?;
+        self.publish_tx(&tx)?;
+        Ok(tx)
+    }
+
+    fn cancel_tx(
+        &mut self,
+        txid: &Sha256dHash,
+        fee_rate: u64,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let tx = self.wallet_lib.cancel_tx(txid, fee_rate)?;
+        self.publish_tx(&tx)?;
+        Ok(tx)
+    }
+
+    fn accelerate(
+        &mut self,
+        txid: &Sha256dHash,
+        fee_rate: u64,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let tx = self.wallet_lib.accelerate(txid, fee_rate)?;
+        self.publish_tx(&tx)?;
+        Ok(tx)
+    }
+
+    fn notify_payment_code(&mut self, recipient: &str) -> Result<Transaction, Box<dyn Error>> {
+        let tx = self.wallet_lib.notify_payment_code(recipient)?;
+        self.publish_tx(&tx)?;
+        Ok(tx)
+    }
+
+    fn sweep_payment_codes(
+        &mut self,
+        dest_addr_type: AccountAddressType,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let tx = self.wallet_lib.sweep_payment_codes(dest_addr_type)?;
+        self.publish_tx(&tx)?;
+        Ok(tx)
+    }
+
+    fn approve_spend(&mut self, id: u64) -> Result<Transaction, Box<dyn Error>> {
+        let tx = self.wallet_lib.approve_spend(id)?;
+        self.publish_tx(&tx)?;
+        Ok(tx)
+    }
+
+    fn broadcast_signed_psbt(
+        &mut self,
+        id: u64,
+        signed: &PartiallySignedTransaction,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let tx = self.wallet_lib.finalize_unsigned_tx(id, signed)?;
+        self.wallet_lib.audit("broadcast_signed_psbt", format!("id={}", id), Some(tx.txid()));
+        self.publish_tx(&tx)?;
+        Ok(tx)
+    }
+
+    fn sweep_silent_payments(
+        &mut self,
+        dest_addr_type: AccountAddressType,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let tx = self.wallet_lib.sweep_silent_payments(dest_addr_type)?;
+        self.publish_tx(&tx)?;
+        Ok(tx)
+    }
+
+    fn sweep_address(
+        &mut self,
+        addr_str: &str,
+        dest_addr: String,
+        fee_rate: u64,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let params = format!("address={} dest_addr={} fee_rate={}", addr_str, dest_addr, fee_rate);
+        let tx = self.wallet_lib.sweep_address(addr_str, dest_addr, fee_rate)?;
+        self.wallet_lib.audit("sweep_address", params, Some(tx.txid()));
+        self.publish_tx(&tx)?;
+        Ok(tx)
+    }
+
+    fn unvault(&mut self, dest_addr: String, fee_rate: u64) -> Result<Transaction, Box<dyn Error>> {
+        let params = format!("dest_addr={} fee_rate={}", dest_addr, fee_rate);
+        let tx = self.wallet_lib.unvault(dest_addr, fee_rate)?;
+        self.wallet_lib.audit("unvault", params, Some(tx.txid()));
+        self.publish_tx(&tx)?;
+        Ok(tx)
+    }
+
+    fn spend_script_coins(
+        &mut self,
+        name: &str,
+        dest_addr: String,
+        fee_rate: u64,
+    ) -> Result<ScriptSpend, Box<dyn Error>> {
+        let params = format!("name={} dest_addr={} fee_rate={}", name, dest_addr, fee_rate);
+        let spend = self.wallet_lib.spend_script_coins(name, dest_addr, fee_rate)?;
+        if let Some(tx) = &spend.tx {
+            self.wallet_lib.audit("spend_script_coins", params, Some(tx.txid()));
+            self.publish_tx(tx)?;
+        }
+        Ok(spend)
+    }
+
+    fn finalize_script_psbt(
+        &mut self,
+        psbt: PartiallySignedTransaction,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let tx = self.wallet_lib.finalize_script_psbt(psbt)?;
+        self.wallet_lib.audit("finalize_script_psbt", String::new(), Some(tx.txid()));
+        self.publish_tx(&tx)?;
+        Ok(tx)
+    }
+
+    fn rotate_seed(
+        &mut self,
+        key_gen_cfg: KeyGenConfig,
+    ) -> Result<(Transaction, Mnemonic), Box<dyn Error>> {
+        let (tx, mnemonic) = self.wallet_lib.rotate_seed(key_gen_cfg)?;
+        self.publish_tx(&tx)?;
+        Ok((tx, mnemonic))
+    }
+
+    fn migrate_derivation(&mut self) -> Result<Transaction, Box<dyn Error>> {
+        let tx = self.wallet_lib.migrate_derivation()?;
+        self.publish_tx(&tx)?;
+        Ok(tx)
+    }
+
+    fn sweep_wif(
+        &mut self,
+        wif: &str,
+        dest_addr_type: AccountAddressType,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let mut sk = PrivateKey::from_wif(wif)?;
+        let pk = PublicKey::from_private_key(&Secp256k1::new(), &sk);
+        self.wallet_lib.import_key(&pk);
+
+        // the key may have received coins at any height
         let block_height = self.bio.get_block_count()?;
+        let tx = self
+            .process_block_range(1, block_height as usize)
+            .and_then(|()| self.wallet_lib.sweep_imported(&sk, dest_addr_type));
+        wipe_private_key(&mut sk);
 
-        let start_from = self.wallet_lib.get_last_seen_block_height_from_memory() + 1;
-        self.process_block_range(start_from, block_height as usize)?;
+        let tx = tx?;
+        self.publish_tx(&tx)?;
+        Ok(tx)
+    }
 
+    fn sync_with_tip_progress(
+        &mut self,
+        progress: &mut dyn FnMut(SyncProgress),
+    ) -> Result<(), Box<dyn Error>> {
+        while !self.sync_with_tip_batch(usize::max_value(), progress)? {}
         Ok(())
     }
+
+    // the headers and the height go back, the coins and the history stay and the transactions
+    // of the blocks scanned again are processed as if they were new
+    fn rescan_from(&mut self, height: usize) -> Result<(), Box<dyn Error>> {
+        let next = self.wallet_lib.get_last_seen_block_height_from_memory() + 1;
+        if height == 0 || height > next {
+            return Err(From::from(format!("a rescan starts between block 1 and {}", next)));
+        }
+        self.wallet_lib.rewind_headers(height - 1);
+        self.wallet_lib.update_last_seen_block_height_in_memory(height - 1);
+        self.wallet_lib.update_last_seen_block_height_in_db(height - 1);
+        Ok(())
+    }
+
+    fn sync_with_tip_batch(
+        &mut self,
+        max_blocks: usize,
+        progress: &mut dyn FnMut(SyncProgress),
+    ) -> Result<bool, Box<dyn Error>> {
+        if self.core_wallet.is_some() {
+            self.sync_with_core_wallet(progress)?;
+            self.rebroadcast();
+            self.check_quorum();
+            return Ok(true);
+        }
+
+        // a block at a time, a sync to the tip doesn't hold the blocks of the whole chain.
+        // The batch goes up to the tip it started with, the next one asks for it again
+        let tip = match self.bio.get_block_count() {
+            Ok(tip) => tip as usize,
+            // the sync ends as it would with a failed download
+            Err(err) => return self.sync_fetched(Err(err.into()), progress),
+        };
+        for _ in 0..max_blocks {
+            let next = self.wallet_lib.get_last_seen_block_height_from_memory() + 1;
+            let fetched =
+                fetch_range(&*self.bio, self.raw_blocks, self.silent_payments, next, tip, 1);
+            if self.sync_fetched(fetched, progress)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn block_fetch(&mut self, max_blocks: usize) -> Option<BlockFetch> {
+        if self.core_wallet.is_some() {
+            return None;
+        }
+        let next = self.wallet_lib.get_last_seen_block_height_from_memory() + 1;
+        let bio = Arc::clone(&self.bio);
+        Some(fetch_blocks(bio, self.raw_blocks, self.silent_payments, next, max_blocks))
+    }
+
+    fn sync_fetched(
+        &mut self,
+        fetched: Result<FetchedBlocks, Box<dyn Error>>,
+        progress: &mut dyn FnMut(SyncProgress),
+    ) -> Result<bool, Box<dyn Error>> {
+        let result = fetched.and_then(|fetched| self.process_fetched(fetched, progress));
+        // a failed batch ends the sync as well, the next one counts the coins found from its start
+        if result.as_ref().map_or(true, |&done| done) {
+            self.sync_known = None;
+        }
+        if let Ok(true) = result {
+            self.rebroadcast();
+            self.check_quorum();
+        }
+        result
+    }
 }
 
 impl<IO> WalletWithTrustedFullNode<IO>
 where
     IO: BlockChainIO,
 {
-    /// initialize with new random master key
+    /// wallet syncing with `bio`, any `BlockChainIO` will do, e.g. a `wallet::memory::MemoryChain`
+    /// along with `WalletConfig::in_memory()` for tests which need neither a node nor a disk,
+    /// failed calls of `bio` are made again by `WalletConfig::retry_policy`
     pub fn new(
         wc: WalletConfig,
         bio: IO,
         mode: WalletLibraryMode,
     ) -> Result<(Self, Mnemonic), WalletError> {
-        let (wallet_lib, mnemonic) = WalletLibrary::new(wc, mode).unwrap();
+        let (mut wallet_lib, mnemonic) = WalletLibrary::new(wc.clone(), mode)?;
+        wallet_lib.record_backend(BackendKind::FullNode);
+        Ok((WalletWithTrustedFullNode::attach(Box::new(wallet_lib), bio, &wc), mnemonic))
+    }
+
+    /// the wallet of another backend syncing with `bio` from the height it reached,
+    /// see `Wallet::into_wallet_lib`
+    pub fn attach(
+        wallet_lib: Box<dyn WalletLibraryInterface + Send>,
+        bio: IO,
+        wc: &WalletConfig,
+    ) -> Self {
+        let mut wallet = WalletWithTrustedFullNode {
+            wallet_lib,
+            bio: Arc::new(Retrying::new(bio, wc.retry_policy().clone())),
+            raw_blocks: wc.raw_blocks(),
+            silent_payments: wc.silent_payments(),
+            endpoint: String::new(),
+            connector: None,
+            core_wallet: None,
+            imported_ranges: Vec::new(),
+            sync_known: None,
+            quorum: None,
+        };
+        // the wallet works without it, pending transactions show up once they are confirmed
+        if let Err(err) = wallet.process_mempool() {
+            log::warn!("failed to scan the mempool of the node: {}", err);
+        }
+        wallet
+    }
+
+    /// the backend is at `endpoint`, `connector` makes the `BlockChainIO` of another one
+    /// when `Wallet::reconnect` is given a new config, the wallet can't switch without it
+    pub fn set_connector<F>(&mut self, endpoint: String, connector: F)
+    where
+        F: Fn(&BackendConfig) -> Result<IO, Box<dyn Error>> + Send + 'static,
+    {
+        self.endpoint = endpoint;
+        self.connector = Some(Box::new(connector));
+    }
+
+    /// publish the transactions of the wallet through the witnesses of `quorum` as well,
+    /// and compare their tips with the one of the node after every sync. A witness which
+    /// diverges is reported by a `WalletEvent::BackendDivergence`
+    pub fn set_quorum(&mut self, quorum: Quorum<IO>) {
+        self.quorum = Some(quorum);
+    }
+
+    /// sync through `core_wallet`, a watch-only descriptor wallet of the node, instead of
+    /// scanning the blocks. The descriptors of the accounts are imported into it, bitcoind
+    /// rescans the chain from the birthday of the wallet, or from the last synced block
+    pub fn set_core_wallet(
+        &mut self,
+        core_wallet: Box<dyn CoreWalletIO + Send>,
+    ) -> Result<(), Box<dyn Error>> {
+        let requests = self.descriptor_requests();
+        core_wallet.import_descriptors(&requests)?;
+        self.imported_ranges = descriptor_ranges(&requests);
+        self.core_wallet = Some(core_wallet);
+        Ok(())
+    }
+
+    // `importdescriptors` of the accounts, the node needs to rescan the blocks after the last
+    // one synced only, the wallet knows the transactions below it
+    fn descriptor_requests(&self) -> serde_json::Value {
+        let mut requests = self.wallet_lib.import_descriptors();
+        if let Some(tip) = self.wallet_lib.get_header_tip() {
+            let timestamp = (tip.time as u64).saturating_sub(CORE_TIMESTAMP_WINDOW);
+            for request in requests.as_array_mut().into_iter().flatten() {
+                request["timestamp"] = timestamp.into();
+            }
+        }
+        requests
+    }
+
+    fn sync_with_core_wallet(
+        &mut self,
+        progress: &mut dyn FnMut(SyncProgress),
+    ) -> Result<(), Box<dyn Error>> {
+        // addresses were derived past the range the node watches
+        let requests = self.descriptor_requests();
+        let ranges = descriptor_ranges(&requests);
+        let core_wallet = self.core_wallet.as_ref().unwrap();
+        if ranges != self.imported_ranges {
+            core_wallet.import_descriptors(&requests)?;
+            self.imported_ranges = ranges;
+        }
+
+        let since = self.wallet_lib.get_header_tip().map(|header| header.hash);
+        let listed = core_wallet.list_since_block(since.as_ref())?;
+        let (tip_height, tip_header) = core_wallet.get_block_header(&listed.last_block)?;
+        let _span = tracing::info_span!("sync", tip = tip_height).entered();
+
+        let known: HashSet<OutPoint> =
+            self.wallet_lib.get_utxo_list().iter().map(|utxo| utxo.out_point).collect();
+        let mut txs = listed.txs;
+        // oldest first, the ones in the mempool last
+        txs.sort_by_key(|(_, height)| height.unwrap_or(usize::max_value()));
+        for (txid, height) in txs {
+            let tx = core_wallet.get_transaction(&txid)?;
+            match height {
+                Some(height) => self.wallet_lib.process_confirmed_tx(&tx, height),
+                None => self.wallet_lib.process_tx(&tx),
+            }
+        }
+
+        // the headers are the points synced to rather than a chain, the ones above the tip
+        // are of blocks a reorg replaced and the next sync would start from them
+        self.wallet_lib.rewind_headers(tip_height.saturating_sub(1));
+        match self.wallet_lib.add_block_header(tip_height, &tip_header) {
+            Err(WalletError::HeaderNotConnected(_)) => {
+                self.wallet_lib.rewind_headers(tip_height.saturating_sub(2));
+                self.wallet_lib.add_block_header(tip_height, &tip_header)?;
+            }
+            result => result?,
+        }
+        self.wallet_lib.update_last_seen_block_height_in_memory(tip_height);
+        self.wallet_lib.update_last_seen_block_height_in_db(tip_height);
+
+        let utxos_found = self
+            .wallet_lib
+            .get_utxo_list()
+            .iter()
+            .filter(|utxo| !known.contains(&utxo.out_point))
+            .count();
+        progress(SyncProgress {
+            height_scanned: tip_height,
+            tip_height,
+            utxos_found,
+        });
+        Ok(())
+    }
+
+    // the node may have dropped them from its mempool or restarted since
+    fn rebroadcast(&self) {
+        for tx in self.wallet_lib.get_unconfirmed_txs() {
+            if let Err(err) = self.bio.send_raw_transaction(&tx) {
+                log::warn!("rebroadcast of {} failed: {}", tx.txid(), err);
+            }
+            if let Some(ref quorum) = self.quorum {
+                quorum.broadcast(&tx);
+            }
+        }
+    }
 
-        Ok((
-            WalletWithTrustedFullNode {
-                wallet_lib: Box::new(wallet_lib),
-                bio,
+    // reported after every sync as long as it lasts, the sync itself stands
+    fn check_quorum(&mut self) {
+        let divergences = match self.quorum {
+            Some(ref quorum) => match quorum.check(&*self.bio) {
+                Ok(divergences) => divergences,
+                Err(err) => {
+                    log::warn!("failed to check the quorum: {}", err);
+                    return;
+                }
             },
-            mnemonic,
-        ))
+            None => return,
+        };
+        for divergence in divergences {
+            log::warn!(
+                "witness {} is at block {}, the node at {}{}",
+                divergence.source,
+                divergence.height,
+                divergence.tip,
+                if divergence.forked { " on another chain" } else { "" }
+            );
+            self.wallet_lib.emit(WalletEvent::BackendDivergence {
+                source: divergence.source,
+                height: divergence.height,
+                tip: divergence.tip,
+                forked: divergence.forked,
+            });
+        }
+    }
+
+    // pick up unconfirmed transactions sent or received while the wallet was down
+    fn process_mempool(&mut self) -> Result<(), WalletError> {
+        let mut txs = HashMap::new();
+        for txid in self.bio.get_raw_mempool()? {
+            // it may be mined or evicted since the mempool was listed
+            if let Ok(tx) = self.bio.get_raw_transaction(&txid) {
+                txs.insert(txid, tx);
+            }
+        }
+
+        if self.silent_payments {
+            // the spends of coins of the mempool are found among the transactions listed
+            let listed: Vec<Transaction> = txs.values().cloned().collect();
+            let spent_outputs = fetch_spent_taproot_outputs(&*self.bio, &listed);
+            self.wallet_lib.set_spent_outputs(spent_outputs);
+        }
+
+        // parents go first, so the coins their children spend are known
+        while !txs.is_empty() {
+            let ready: Vec<Sha256dHash> = txs
+                .iter()
+                .filter(|(_, tx)| {
+                    tx.input
+                        .iter()
+                        .all(|input| !txs.contains_key(&input.previous_output.txid))
+                })
+                .map(|(txid, _)| *txid)
+                .collect();
+            for txid in ready {
+                let tx = txs.remove(&txid).unwrap();
+                self.wallet_lib.process_tx(&tx);
+            }
+        }
+
+        Ok(())
     }
 
-    fn process_block(&mut self, block_height: usize, block: &Block) {
+    // the blocks of `fetched` from the one after the last processed on, true if they reach
+    // the tip. Another sync or a rescan may have moved the wallet since they were fetched,
+    // the blocks it doesn't go on with are fetched again
+    fn process_fetched(
+        &mut self,
+        fetched: FetchedBlocks,
+        progress: &mut dyn FnMut(SyncProgress),
+    ) -> Result<bool, Box<dyn Error>> {
+        let _span = tracing::info_span!("sync", tip = fetched.tip).entered();
+        if self.sync_known.is_none() {
+            let utxos = self.wallet_lib.get_utxo_list();
+            self.sync_known = Some(utxos.iter().map(|utxo| utxo.out_point).collect());
+        }
+        let mut next = self.wallet_lib.get_last_seen_block_height_from_memory() + 1;
+        for (height, block, spent_outputs) in fetched.blocks {
+            if height < next {
+                continue;
+            }
+            if height > next {
+                return Ok(false);
+            }
+            match self.wallet_lib.add_block_header(height, &block.header) {
+                Err(WalletError::HeaderNotConnected(_)) => {
+                    // transactions of the blocks replaced are not rolled back,
+                    // the ones mined again get the height of their new block
+                    let fork_height = self.find_fork(height - 1)?;
+                    log::warn!("blocks above {} were replaced by a reorg", fork_height);
+                    self.wallet_lib.rewind_headers(fork_height);
+                    self.wallet_lib.update_last_seen_block_height_in_memory(fork_height);
+                    self.wallet_lib.update_last_seen_block_height_in_db(fork_height);
+                    return Ok(false);
+                }
+                result => result?,
+            }
+            self.process_block(height, &block, spent_outputs);
+
+            let known = self.sync_known.as_ref().unwrap();
+            let utxos_found = self
+                .wallet_lib
+                .get_utxo_list()
+                .iter()
+                .filter(|utxo| !known.contains(&utxo.out_point))
+                .count();
+            progress(SyncProgress {
+                height_scanned: height,
+                tip_height: fetched.tip,
+                utxos_found,
+            });
+            next += 1;
+        }
+        Ok(next > fetched.tip)
+    }
+
+    fn process_block(
+        &mut self,
+        block_height: usize,
+        block: &Block,
+        spent_outputs: HashMap<OutPoint, TxOut>,
+    ) {
+        let txs = block.txdata.len();
+        let _span = tracing::debug_span!("block", height = block_height, txs).entered();
+        self.wallet_lib.set_spent_outputs(spent_outputs);
         for tx in &block.txdata {
-            self.wallet_lib.process_tx(&tx);
+            self.wallet_lib.process_confirmed_tx(&tx, block_height);
         }
         // TODO(evg): if block_height > self.last_seen_block_height?
         self.wallet_lib
@@ -123,13 +710,613 @@ where
             .update_last_seen_block_height_in_db(block_height);
     }
 
-    fn process_block_range(&mut self, left: usize, right: usize) -> Result<(), IO::Error> {
+    fn process_block_range(&mut self, left: usize, right: usize) -> Result<(), Box<dyn Error>> {
         for i in left..right + 1 {
-            let block_hash = self.bio.get_block_hash(i as u32)?;
-            let block = self.bio.get_block(&block_hash)?;
-            self.process_block(i, &block);
+            let block = fetch_block(&*self.bio, self.raw_blocks, i)?;
+            let spent_outputs = fetch_spent_outputs(&*self.bio, self.silent_payments, &block);
+            self.process_block(i, &block, spent_outputs);
         }
 
         Ok(())
     }
+
+    // the highest block of the header chain which is still in the chain of the node
+    fn find_fork(&self, height: usize) -> Result<usize, Box<dyn Error>> {
+        let mut height = height;
+        while let Some(header) = self.wallet_lib.get_block_header(height) {
+            if header.hash == self.bio.get_block_hash(height as u32)? {
+                break;
+            }
+            height -= 1;
+        }
+        Ok(height)
+    }
+}
+
+// downloads at most `max_blocks` blocks from the height `next` on, along with the outputs
+// their taproot inputs spend if `silent_payments` is set
+fn fetch_blocks<IO>(
+    bio: Arc<Retrying<IO>>,
+    raw_blocks: bool,
+    silent_payments: bool,
+    next: usize,
+    max_blocks: usize,
+) -> BlockFetch
+where
+    IO: BlockChainIO + Send + Sync + 'static,
+{
+    Box::new(move || {
+        let tip = bio.get_block_count()? as usize;
+        fetch_range(&*bio, raw_blocks, silent_payments, next, tip, max_blocks)
+    })
+}
+
+// the blocks of `fetch_blocks` up to a tip read before
+fn fetch_range<B>(
+    bio: &B,
+    raw_blocks: bool,
+    silent_payments: bool,
+    next: usize,
+    tip: usize,
+    max_blocks: usize,
+) -> Result<FetchedBlocks, Box<dyn Error>>
+where
+    B: BlockChainIO,
+{
+    let mut blocks = Vec::new();
+    for height in (next..=tip).take(max_blocks) {
+        let block = fetch_block(bio, raw_blocks, height)?;
+        let spent_outputs = fetch_spent_outputs(bio, silent_payments, &block);
+        blocks.push((height, block, spent_outputs));
+    }
+    Ok(FetchedBlocks { tip, blocks })
+}
+
+fn fetch_block<B>(bio: &B, raw_blocks: bool, block_height: usize) -> Result<Block, Box<dyn Error>>
+where
+    B: BlockChainIO,
+{
+    let block_hash = bio.get_block_hash(block_height as u32)?;
+    if raw_blocks {
+        let raw = hex::decode(bio.get_raw_block(&block_hash)?)?;
+        Ok(deserialize(&raw)?)
+    } else {
+        Ok(bio.get_block(&block_hash)?)
+    }
+}
+
+// the outputs of `fetch_spent_taproot_outputs` for the transactions of `block`, none unless
+// `silent_payments` is set. A node telling the prevouts along with the block is asked
+// for them, with a single call
+fn fetch_spent_outputs<B>(
+    bio: &B,
+    silent_payments: bool,
+    block: &Block,
+) -> HashMap<OutPoint, TxOut>
+where
+    B: BlockChainIO,
+{
+    if !silent_payments {
+        return HashMap::new();
+    }
+    match bio.get_block_prevouts(&block.bitcoin_hash()) {
+        Ok(Some(mut prevouts)) => {
+            let spent: HashSet<OutPoint> =
+                block.txdata.iter().flat_map(bip352::spent_taproot_outputs).collect();
+            prevouts.retain(|op, _| spent.contains(op));
+            return prevouts;
+        }
+        Ok(None) => (),
+        Err(e) => {
+            log::warn!("failed to fetch the prevouts of {}: {}", block.bitcoin_hash(), e);
+        }
+    }
+    fetch_spent_taproot_outputs(bio, &block.txdata)
+}
+
+// outputs spent by the taproot inputs of `txs` paying to taproot outputs, their keys
+// are part of the BIP352 scan. Outputs of other blocks need the transaction index
+// of the node, the scan of a transaction with an output missing fails
+fn fetch_spent_taproot_outputs<B>(bio: &B, txs: &[Transaction]) -> HashMap<OutPoint, TxOut>
+where
+    B: BlockChainIO,
+{
+    let mut fetched: HashMap<Sha256dHash, Transaction> =
+        txs.iter().map(|tx| (tx.txid(), tx.clone())).collect();
+    let mut outputs = HashMap::new();
+    for tx in txs {
+        for op in bip352::spent_taproot_outputs(tx) {
+            if !fetched.contains_key(&op.txid) {
+                match bio.get_raw_transaction(&op.txid) {
+                    Ok(prev_tx) => {
+                        fetched.insert(op.txid, prev_tx);
+                    }
+                    Err(e) => {
+                        log::warn!("failed to fetch {} spent by {}: {}", op.txid, tx.txid(), e);
+                        continue;
+                    }
+                }
+            }
+            if let Some(output) = fetched[&op.txid].output.get(op.vout as usize) {
+                outputs.insert(op, output.clone());
+            }
+        }
+    }
+    outputs
+}
+
+// the range of each descriptor of `importdescriptors` requests
+fn descriptor_ranges(requests: &serde_json::Value) -> Vec<serde_json::Value> {
+    requests
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|request| request["range"].clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::{
+        Block, Transaction, TxIn, TxOut, OutPoint, Script, Address, SigHashType,
+        network::constants::Network,
+    };
+    use bitcoin_hashes::sha256d::Hash as Sha256dHash;
+
+    use std::{
+        io,
+        collections::HashMap,
+        str::FromStr,
+        sync::{Arc, Mutex, atomic::{AtomicUsize, Ordering}},
+        thread,
+    };
+
+    use crate::walletlibrary::{WalletConfig, InputSpec, WalletLibraryMode, KeyGenConfig};
+    use crate::memory::{MemoryChain, test::{new_wallet, payment}};
+    use crate::account::AccountAddressType;
+    use crate::error::WalletError;
+    use crate::sighash::verify_input;
+    use crate::interface::{
+        Wallet, BlockChainIO, BackendConfig, BackendKind, CancelToken, SyncProgress,
+    };
+    use super::WalletWithTrustedFullNode;
+
+    // a `MemoryChain` counting the tips asked for, its blocks are served while `gate`
+    // isn't held
+    #[derive(Clone)]
+    struct GatedChain {
+        chain: MemoryChain,
+        tips: Arc<AtomicUsize>,
+        downloads: Arc<AtomicUsize>,
+        gate: Arc<Mutex<()>>,
+    }
+
+    impl BlockChainIO for GatedChain {
+        type Error = io::Error;
+
+        fn get_block_count(&self) -> Result<u32, Self::Error> {
+            self.tips.fetch_add(1, Ordering::SeqCst);
+            self.chain.get_block_count()
+        }
+
+        fn get_block_hash(&self, height: u32) -> Result<Sha256dHash, Self::Error> {
+            self.chain.get_block_hash(height)
+        }
+
+        fn get_block(&self, header_hash: &Sha256dHash) -> Result<Block, Self::Error> {
+            self.downloads.fetch_add(1, Ordering::SeqCst);
+            let _gate = self.gate.lock().unwrap();
+            self.chain.get_block(header_hash)
+        }
+
+        fn get_raw_block(&self, header_hash: &Sha256dHash) -> Result<String, Self::Error> {
+            self.downloads.fetch_add(1, Ordering::SeqCst);
+            let _gate = self.gate.lock().unwrap();
+            self.chain.get_raw_block(header_hash)
+        }
+
+        fn send_raw_transaction(&self, tx: &Transaction) -> Result<Sha256dHash, Self::Error> {
+            self.chain.send_raw_transaction(tx)
+        }
+
+        fn get_raw_mempool(&self) -> Result<Vec<Sha256dHash>, Self::Error> {
+            self.chain.get_raw_mempool()
+        }
+
+        fn get_raw_transaction(&self, txid: &Sha256dHash) -> Result<Transaction, Self::Error> {
+            self.chain.get_raw_transaction(txid)
+        }
+    }
+
+    fn gated_wallet() -> (WalletWithTrustedFullNode<GatedChain>, GatedChain) {
+        let io = GatedChain {
+            chain: MemoryChain::new(Network::Regtest),
+            tips: Arc::new(AtomicUsize::new(0)),
+            downloads: Arc::new(AtomicUsize::new(0)),
+            gate: Arc::new(Mutex::new(())),
+        };
+        let (wallet, _) = WalletWithTrustedFullNode::new(
+            WalletConfig::in_memory(),
+            io.clone(),
+            WalletLibraryMode::Create(KeyGenConfig::debug()),
+        )
+        .unwrap();
+        (wallet, io)
+    }
+
+    #[test]
+    fn sweep_address() {
+        let (mut wallet, chain) = new_wallet(WalletConfig::in_memory());
+        let exposed = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        let other = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        chain.push_block(Vec::new());
+        chain.push_block(vec![
+            payment(&exposed, 30_000_000),
+            payment(&exposed, 20_000_000),
+            payment(&other, 40_000_000),
+        ]);
+        wallet.sync_with_tip().unwrap();
+
+        let dest_addr = "bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49".to_owned();
+        let tx = wallet.sweep_address(&exposed, dest_addr.clone(), 10).unwrap();
+        assert_eq!(tx.input.len(), 2);
+        assert_eq!(tx.output.len(), 1);
+        assert_eq!(
+            tx.output[0].script_pubkey,
+            Address::from_str(&dest_addr).unwrap().script_pubkey()
+        );
+        let fee = 50_000_000 - tx.output[0].value;
+        assert!(fee >= 10 * 100 && fee < 10 * 300);
+        assert_eq!(chain.get_raw_mempool().unwrap(), vec![tx.txid()]);
+
+        chain.mine();
+        wallet.sync_with_tip().unwrap();
+        let utxos = wallet.wallet_lib().get_utxo_list();
+        assert_eq!(utxos.len(), 1);
+        assert_eq!(utxos[0].value, 40_000_000);
+        // nothing left to sweep
+        assert!(wallet.sweep_address(&exposed, dest_addr.clone(), 10).is_err());
+        // only addresses of the wallet are swept
+        assert!(wallet.sweep_address(&dest_addr, exposed, 10).is_err());
+    }
+
+    #[test]
+    fn make_tx_advanced() {
+        let (mut wallet, chain) = new_wallet(WalletConfig::in_memory());
+        let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        let legacy = wallet.wallet_lib_mut().new_address(AccountAddressType::P2PKH).unwrap();
+        chain.push_block(Vec::new());
+        chain.push_block(vec![payment(&addr, 30_000_000), payment(&legacy, 40_000_000)]);
+        wallet.sync_with_tip().unwrap();
+
+        let utxos = wallet.wallet_lib().get_utxo_list();
+        let anchor = InputSpec {
+            out_point: utxos[0].out_point,
+            sequence: 144,
+            sighash_type: SigHashType::SinglePlusAnyoneCanPay,
+        };
+        let inputs = vec![anchor, InputSpec::new(utxos[1].out_point)];
+        let dest_addr = "bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49".to_owned();
+        let tx = wallet
+            .make_tx_advanced(inputs, dest_addr.clone(), 50_000_000, true, None)
+            .unwrap();
+        assert_eq!(tx.input[0].sequence, 144);
+        for (i, utxo) in utxos.iter().enumerate() {
+            let spent = TxOut {
+                value: utxo.value,
+                script_pubkey: utxo.pk_script.clone(),
+            };
+            verify_input(&tx, i, &spent).unwrap();
+        }
+        assert_eq!(chain.get_raw_mempool().unwrap(), vec![tx.txid()]);
+
+        // not a coin of the wallet
+        let foreign = InputSpec::new(OutPoint {
+            txid: tx.txid(),
+            vout: 0,
+        });
+        assert!(wallet.make_tx_advanced(vec![foreign], dest_addr, 1_000, false, None).is_err());
+    }
+
+    #[test]
+    fn switch_backend() {
+        let (mut wallet, chain) = new_wallet(WalletConfig::in_memory());
+        let config = BackendConfig {
+            address: "127.0.0.1:18443".parse().unwrap(),
+            user: None,
+            password: None,
+        };
+        assert!(wallet.reconnect(Some(config.clone())).is_err());
+        assert_eq!(wallet.connection_status().endpoint, "");
+
+        // a longer chain, as if the other node were synced further
+        let other = MemoryChain::new(Network::Regtest);
+        other.push_block(Vec::new());
+        other.push_block(Vec::new());
+        let connected = other.clone();
+        wallet.set_connector("memory".to_owned(), move |_| Ok(connected.clone()));
+        wallet.reconnect(Some(config)).unwrap();
+
+        let status = wallet.connection_status();
+        assert_eq!(status.backend, BackendKind::FullNode);
+        assert_eq!(status.endpoint, "127.0.0.1:18443");
+        assert!(status.last_success.is_some());
+        wallet.sync_with_tip().unwrap();
+        assert_eq!(wallet.wallet_lib().get_last_seen_block_height_from_memory(), 2);
+    }
+
+    #[test]
+    fn sync_in_batches() {
+        let (mut wallet, chain) = new_wallet(WalletConfig::in_memory());
+        let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        for _ in 0..20 {
+            chain.push_block(Vec::new());
+        }
+        chain.push_block(vec![payment(&addr, 100_000_000)]);
+
+        let mut heights = Vec::new();
+        let done = wallet
+            .sync_with_tip_batch(5, &mut |progress| heights.push(progress.height_scanned))
+            .unwrap();
+        assert!(!done);
+        assert_eq!(heights.len(), 5);
+        assert_eq!(wallet.wallet_lib().wallet_balance(), 0);
+
+        // addresses are derived while another thread syncs, the wallet is unlocked while
+        // the blocks download
+        let wallet = Arc::new(Mutex::new(wallet));
+        let syncing = wallet.clone();
+        let sync = thread::spawn(move || {
+            let mut utxos_found = 0;
+            loop {
+                let fetch = syncing.lock().unwrap().block_fetch(2).unwrap();
+                let fetched = fetch();
+                let mut wallet = syncing.lock().unwrap();
+                let report = &mut |progress: SyncProgress| utxos_found = progress.utxos_found;
+                if wallet.sync_fetched(fetched, report).unwrap() {
+                    break;
+                }
+            }
+            utxos_found
+        });
+        let mut addresses = Vec::new();
+        for _ in 0..10 {
+            let mut wallet = wallet.lock().unwrap();
+            let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+            addresses.push(addr);
+        }
+        assert_eq!(sync.join().unwrap(), 1);
+
+        let wallet = wallet.lock().unwrap();
+        addresses.sort();
+        addresses.dedup();
+        assert_eq!(addresses.len(), 10);
+        assert_eq!(wallet.wallet_lib().wallet_balance(), 100_000_000);
+        assert_eq!(wallet.wallet_lib().get_last_seen_block_height_from_memory(), chain.height());
+        assert_eq!(wallet.wallet_lib().verify_state(), Vec::new());
+    }
+
+    #[test]
+    fn tip_of_a_batch() {
+        let (mut wallet, io) = gated_wallet();
+        for _ in 0..10 {
+            io.chain.push_block(Vec::new());
+        }
+        io.tips.store(0, Ordering::SeqCst);
+        assert!(!wallet.sync_with_tip_batch(4, &mut |_| ()).unwrap());
+        assert_eq!(io.tips.swap(0, Ordering::SeqCst), 1);
+
+        // the wallet starts above the first block
+        let mut tips = Vec::new();
+        let report = &mut |progress: SyncProgress| tips.push(progress.tip_height);
+        assert!(wallet.sync_with_tip_batch(10, report).unwrap());
+        assert_eq!(tips, vec![10; 5]);
+        assert_eq!(io.tips.swap(0, Ordering::SeqCst), 1);
+        assert_eq!(wallet.wallet_lib().get_last_seen_block_height_from_memory(), 10);
+    }
+
+    // the key material, the coins and the history of a wallet are behind its one lock,
+    // requests are served while the blocks download, not while they are processed
+    #[test]
+    fn served_during_download() {
+        let (mut wallet, io) = gated_wallet();
+        let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        io.chain.push_block(Vec::new());
+        io.chain.push_block(vec![payment(&addr, 100_000_000)]);
+        let wallet = Arc::new(Mutex::new(wallet));
+
+        let gate = io.gate.lock().unwrap();
+        let syncing = wallet.clone();
+        let sync = thread::spawn(move || {
+            let fetch = syncing.lock().unwrap().block_fetch(10).unwrap();
+            let fetched = fetch();
+            syncing.lock().unwrap().sync_fetched(fetched, &mut |_| ()).unwrap()
+        });
+        while io.downloads.load(Ordering::SeqCst) == 0 {
+            thread::yield_now();
+        }
+        // the download waits for the gate
+        {
+            let mut wallet = wallet.lock().unwrap();
+            wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+            assert_eq!(wallet.wallet_lib().wallet_balance(), 0);
+        }
+        drop(gate);
+        assert!(sync.join().unwrap());
+        assert_eq!(wallet.lock().unwrap().wallet_lib().wallet_balance(), 100_000_000);
+    }
+
+    #[test]
+    fn failed_batch() {
+        let (mut wallet, chain) = new_wallet(WalletConfig::in_memory());
+        let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        chain.push_block(Vec::new());
+        chain.push_block(vec![payment(&addr, 100_000_000)]);
+        chain.push_block(Vec::new());
+        chain.push_block(vec![payment(&addr, 50_000_000)]);
+        assert!(!wallet.sync_with_tip_batch(2, &mut |_| ()).unwrap());
+
+        // the sync ends with the download, the coin found before isn't counted by the next one
+        assert!(wallet.sync_fetched(Err(From::from("lost")), &mut |_| ()).is_err());
+        let fetch = wallet.block_fetch(10).unwrap();
+        let mut utxos_found = 0;
+        wallet.sync_with_tip_progress(&mut |progress| utxos_found = progress.utxos_found).unwrap();
+        assert_eq!(utxos_found, 1);
+        assert_eq!(wallet.wallet_lib().wallet_balance(), 150_000_000);
+
+        // blocks fetched before the wallet synced them are left out
+        assert!(wallet.sync_fetched(fetch(), &mut |_| panic!("synced again")).unwrap());
+        assert_eq!(wallet.wallet_lib().wallet_balance(), 150_000_000);
+        assert_eq!(wallet.wallet_lib().get_last_seen_block_height_from_memory(), 4);
+    }
+
+    #[test]
+    fn cancel_rescan() {
+        let (mut wallet, chain) = new_wallet(WalletConfig::in_memory());
+        let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        chain.push_block(Vec::new());
+        chain.push_block(vec![payment(&addr, 100_000_000)]);
+        for _ in 0..8 {
+            chain.push_block(Vec::new());
+        }
+
+        // the sync stops after the block it was scanning when it was cancelled
+        let cancel = CancelToken::new();
+        let e = wallet
+            .sync_cancellable(&cancel, &mut |progress| {
+                if progress.height_scanned == 4 {
+                    cancel.cancel();
+                }
+            })
+            .unwrap_err();
+        match e.downcast_ref::<WalletError>() {
+            Some(&WalletError::Cancelled) => (),
+            _ => panic!("unexpected error {}", e),
+        }
+        assert_eq!(wallet.wallet_lib().get_last_seen_block_height_from_memory(), 4);
+        assert_eq!(wallet.wallet_lib().wallet_balance(), 100_000_000);
+
+        // a rescan goes back, the coins found stay
+        assert!(wallet.rescan_from(0).is_err());
+        assert!(wallet.rescan_from(6).is_err());
+        wallet.rescan_from(2).unwrap();
+        assert_eq!(wallet.wallet_lib().get_last_seen_block_height_from_memory(), 1);
+        assert_eq!(wallet.wallet_lib().wallet_balance(), 100_000_000);
+        wallet.sync_cancellable(&CancelToken::new(), &mut |_| ()).unwrap();
+        assert_eq!(wallet.wallet_lib().get_last_seen_block_height_from_memory(), chain.height());
+        assert_eq!(wallet.wallet_lib().wallet_balance(), 100_000_000);
+        assert_eq!(wallet.wallet_lib().get_tx_history().len(), 1);
+    }
+
+    // a `MemoryChain` counting the transactions asked for, it tells the prevouts
+    // of a block if `prevouts` is set, as bitcoind 23.0 does
+    #[derive(Clone)]
+    struct PrevoutChain {
+        chain: MemoryChain,
+        prevouts: bool,
+        lookups: Arc<AtomicUsize>,
+    }
+
+    impl BlockChainIO for PrevoutChain {
+        type Error = io::Error;
+
+        fn get_block_count(&self) -> Result<u32, Self::Error> {
+            self.chain.get_block_count()
+        }
+
+        fn get_block_hash(&self, height: u32) -> Result<Sha256dHash, Self::Error> {
+            self.chain.get_block_hash(height)
+        }
+
+        fn get_block(&self, header_hash: &Sha256dHash) -> Result<Block, Self::Error> {
+            self.chain.get_block(header_hash)
+        }
+
+        fn get_raw_block(&self, header_hash: &Sha256dHash) -> Result<String, Self::Error> {
+            self.chain.get_raw_block(header_hash)
+        }
+
+        fn send_raw_transaction(&self, tx: &Transaction) -> Result<Sha256dHash, Self::Error> {
+            self.chain.send_raw_transaction(tx)
+        }
+
+        fn get_raw_mempool(&self) -> Result<Vec<Sha256dHash>, Self::Error> {
+            self.chain.get_raw_mempool()
+        }
+
+        fn get_raw_transaction(&self, txid: &Sha256dHash) -> Result<Transaction, Self::Error> {
+            self.lookups.fetch_add(1, Ordering::SeqCst);
+            self.chain.get_raw_transaction(txid)
+        }
+
+        fn get_block_prevouts(
+            &self,
+            header_hash: &Sha256dHash,
+        ) -> Result<Option<HashMap<OutPoint, TxOut>>, Self::Error> {
+            if !self.prevouts {
+                return Ok(None);
+            }
+            let block = self.chain.get_block(header_hash)?;
+            let mut prevouts = HashMap::new();
+            for input in block.txdata.iter().flat_map(|tx| tx.input.iter()) {
+                let op = input.previous_output;
+                if let Ok(prev_tx) = self.chain.get_raw_transaction(&op.txid) {
+                    prevouts.insert(op, prev_tx.output[op.vout as usize].clone());
+                }
+            }
+            Ok(Some(prevouts))
+        }
+    }
+
+    #[test]
+    fn silent_payment_prevouts() {
+        let taproot = Script::from([&[0x51, 0x20][..], &[7; 32][..]].concat());
+        let mut funding = payment("bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49", 50_000_000);
+        funding.output[0].script_pubkey = taproot.clone();
+        // a key path spend paying to a taproot output, the key of its input is
+        // in the output of another block
+        let spend = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output: OutPoint {
+                    txid: funding.txid(),
+                    vout: 0,
+                },
+                script_sig: Script::new(),
+                sequence: 0xFFFFFFFF,
+                witness: vec![vec![1; 64]],
+            }],
+            output: vec![TxOut {
+                value: 49_990_000,
+                script_pubkey: taproot,
+            }],
+        };
+
+        // disabled, with the prevouts of a block, and fetched by transaction
+        for &(silent_payments, prevouts, lookups) in
+            &[(false, false, 0), (true, true, 0), (true, false, 1)]
+        {
+            let io = PrevoutChain {
+                chain: MemoryChain::new(Network::Regtest),
+                prevouts,
+                lookups: Arc::new(AtomicUsize::new(0)),
+            };
+            let mut config = WalletConfig::in_memory();
+            config.set_silent_payments(silent_payments);
+            let (mut wallet, _) = WalletWithTrustedFullNode::new(
+                config,
+                io.clone(),
+                WalletLibraryMode::Create(KeyGenConfig::debug()),
+            )
+            .unwrap();
+            io.chain.push_block(Vec::new());
+            io.chain.push_block(vec![funding.clone()]);
+            io.chain.push_block(vec![spend.clone()]);
+            wallet.sync_with_tip().unwrap();
+            assert_eq!(io.lookups.load(Ordering::SeqCst), lookups);
+            let addr = wallet.wallet_lib().silent_payment_address();
+            assert_eq!(addr.is_ok(), silent_payments);
+        }
+    }
 }
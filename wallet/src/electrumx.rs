@@ -13,30 +13,90 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 use bitcoin::{
-    Transaction, OutPoint,
+    BlockHeader, BitcoinHash, Transaction, OutPoint, PrivateKey, PublicKey,
+    network::constants::Network,
     consensus::encode::{serialize_hex, deserialize},
+    util::psbt::PartiallySignedTransaction,
 };
+use bitcoin_hashes::{hex::FromHex, sha256, sha256d::Hash as Sha256dHash, Hash};
 use hex;
+use secp256k1::Secp256k1;
+use serde_json::{json, Value};
 
 use std::{
     error::Error,
-    collections::HashMap,
-    net::SocketAddr
+    collections::HashSet,
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpStream},
+    time::Duration,
 };
 
 use electrumx_client::{
     electrumx_client::ElectrumxClient,
     interface::Electrumx,
 };
-use super::walletlibrary::{WalletLibrary, WalletConfig, LockId, WalletLibraryMode};
-use super::interface::{WalletLibraryInterface, Wallet};
+use super::walletlibrary::{
+    WalletLibrary, WalletConfig, LockId, SendOptions, WalletLibraryMode, KeyGenConfig, now,
+    SendOutcome, InputSpec, ScriptSpend,
+};
+use super::interface::{
+    WalletLibraryInterface, Wallet, SyncProgress, BackendConfig, BackendKind, ConnectionStatus,
+};
 use super::error::WalletError;
 use super::mnemonic::Mnemonic;
+use super::account::AccountAddressType;
+use super::keyfactory::wipe_private_key;
+use super::headers::{self, HeaderRecord};
+use super::address;
+
+// requests in flight on the connection of a batch, the server reads the next ones
+// while it answers these
+const PIPELINE_DEPTH: usize = 100;
 
 pub struct ElectrumxWallet {
     pub wallet_lib: Box<dyn WalletLibraryInterface + Send>,
     electrumx_address: SocketAddr,
     electrumx_client: ElectrumxClient<SocketAddr>,
+    verify_proofs: bool,
+    connect_timeout: Duration,
+    read_timeout: Duration,
+    // unix time of the last connection or call which succeeded
+    last_success: Option<u64>,
+}
+
+// electrum indexes the histories by the sha256 of the script, byte-reversed
+fn script_hash(addr: &str, network: Network) -> Result<String, Box<dyn Error>> {
+    let script = address::script_pubkey(addr, network)?;
+    let mut hash = sha256::Hash::hash(script.as_bytes()).into_inner();
+    hash.reverse();
+    Ok(hex::encode(hash))
+}
+
+// the server is probed first, the client would wait for as long as the OS does
+// to connect to a host which is down
+fn connect(
+    address: SocketAddr,
+    connect_timeout: Duration,
+    read_timeout: Duration,
+) -> Result<ElectrumxClient<SocketAddr>, Box<dyn Error>> {
+    TcpStream::connect_timeout(&address, connect_timeout)?;
+    let client = ElectrumxClient::new(address)?;
+    client.set_read_timeout(Some(read_timeout))?;
+    Ok(client)
+}
+
+/// connection to the electrum server at `address` for `ElectrumxWallet::attach`,
+/// refused unless the server follows the chain of `network`
+pub fn connect_to_chain(
+    address: SocketAddr,
+    wc: &WalletConfig,
+    network: Network,
+) -> Result<ElectrumxClient<SocketAddr>, Box<dyn Error>> {
+    let mut client = connect(address, wc.connect_timeout(), wc.read_timeout())?;
+    let raw = hex::decode(client.get_block_header(0)?)?;
+    let genesis: BlockHeader = deserialize(&raw)?;
+    headers::check_genesis(network, genesis.bitcoin_hash())?;
+    Ok(client)
 }
 
 impl Wallet for ElectrumxWallet {
@@ -48,25 +108,42 @@ impl Wallet for ElectrumxWallet {
         &mut self.wallet_lib
     }
 
-    fn reconnect(&mut self) {
-        self.electrumx_client = ElectrumxClient::new(self.electrumx_address).unwrap();
+    fn reconnect(&mut self, config: Option<BackendConfig>) -> Result<(), Box<dyn Error>> {
+        let address = config.map_or(self.electrumx_address, |config| config.address);
+        self.electrumx_client = connect(address, self.connect_timeout, self.read_timeout)?;
+        self.electrumx_address = address;
+        self.last_success = Some(now());
+        Ok(())
+    }
+
+    fn connection_status(&self) -> ConnectionStatus {
+        ConnectionStatus {
+            backend: BackendKind::Electrum,
+            endpoint: self.electrumx_address.to_string(),
+            last_success: self.last_success,
+        }
+    }
+
+    fn into_wallet_lib(self: Box<Self>) -> Box<dyn WalletLibraryInterface + Send> {
+        self.wallet_lib
     }
 
     fn send_coins(
         &mut self,
         addr_str: String,
         amt: u64,
-        lock_coins: bool,
-        witness_only: bool,
         submit: bool,
-    ) -> Result<(Transaction, LockId), Box<dyn Error>> {
-        let (tx, lock_id) = self
-            .wallet_lib
-            .send_coins(addr_str, amt, lock_coins, witness_only)?;
-        if submit {
-            self.publish_tx(&tx)?;
+        options: SendOptions,
+    ) -> Result<SendOutcome, Box<dyn Error>> {
+        let params = format!("dest_addr={} amt={} submit={}", addr_str, amt, submit);
+        let outcome = self.wallet_lib.send_coins(addr_str, amt, options)?;
+        if let SendOutcome::Sent(ref sent) = outcome {
+            self.wallet_lib.audit("send_coins", params, Some(sent.txid()));
+            if submit {
+                self.publish_tx(&sent.tx)?;
+            }
         }
-        Ok((tx, lock_id))
+        Ok(outcome)
     }
 
     fn make_tx(
@@ -75,8 +152,28 @@ impl Wallet for ElectrumxWallet {
         addr_str: String,
         amt: u64,
         submit: bool,
+        change_addr_type: Option<AccountAddressType>,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let params = format!("dest_addr={} amt={} submit={}", addr_str, amt, submit);
+        let tx = self.wallet_lib.make_tx(ops, addr_str, amt, change_addr_type).unwrap();
+        self.wallet_lib.audit("make_tx", params, Some(tx.txid()));
+        if submit {
+            self.publish_tx(&tx)?;
+        }
+        Ok(tx)
+    }
+
+    fn make_tx_advanced(
+        &mut self,
+        inputs: Vec<InputSpec>,
+        addr_str: String,
+        amt: u64,
+        submit: bool,
+        change_addr_type: Option<AccountAddressType>,
     ) -> Result<Transaction, Box<dyn Error>> {
-        let tx = self.wallet_lib.make_tx(ops, addr_str, amt).unwrap();
+        let params = format!("dest_addr={} amt={} submit={}", addr_str, amt, submit);
+        let tx = self.wallet_lib.make_tx_advanced(inputs, addr_str, amt, change_addr_type)?;
+        self.wallet_lib.audit("make_tx_advanced", params, Some(tx.txid()));
         if submit {
             self.publish_tx(&tx)?;
         }
@@ -84,54 +181,297 @@ impl Wallet for ElectrumxWallet {
     }
 
     fn publish_tx(&mut self, tx: &Transaction) -> Result<(), Box<dyn Error>> {
-        let tx = serialize_hex(tx);
-        self.electrumx_client.broadcast_transaction(tx)?;
+        self.electrumx_client.broadcast_transaction(serialize_hex(tx))?;
+        self.last_success = Some(now());
+        self.wallet_lib.add_unconfirmed_tx(tx);
+        self.wallet_lib.audit("publish_tx", String::new(), Some(tx.txid()));
         Ok(())
     }
 
+    fn commit_tx(&mut self, prepared_id: u64) -> Result<Transaction, Box<dyn Error>> {
+        let tx = self.wallet_lib.sign_prepared_tx(prepared_id)?;
+        self.publish_tx(&tx)?;
+        Ok(tx)
+    }
+
+    fn publish_funding(&mut self, funding_id: LockId) -> Result<Transaction, Box<dyn Error>> {
+        let tx = self.wallet_lib.sign_funding(funding_id)?;
+        self.publish_tx(&tx)?;
+        Ok(tx)
+    }
+
+    fn cancel_tx(
+        &mut self,
+        txid: &Sha256dHash,
+        fee_rate: u64,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let tx = self.wallet_lib.cancel_tx(txid, fee_rate)?;
+        self.publish_tx(&tx)?;
+        Ok(tx)
+    }
+
+    fn accelerate(
+        &mut self,
+        txid: &Sha256dHash,
+        fee_rate: u64,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let tx = self.wallet_lib.accelerate(txid, fee_rate)?;
+        self.publish_tx(&tx)?;
+        Ok(tx)
+    }
+
+    fn notify_payment_code(&mut self, recipient: &str) -> Result<Transaction, Box<dyn Error>> {
+        let tx = self.wallet_lib.notify_payment_code(recipient)?;
+        self.publish_tx(&tx)?;
+        Ok(tx)
+    }
+
+    fn sweep_payment_codes(
+        &mut self,
+        dest_addr_type: AccountAddressType,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let tx = self.wallet_lib.sweep_payment_codes(dest_addr_type)?;
+        self.publish_tx(&tx)?;
+        Ok(tx)
+    }
+
+    fn approve_spend(&mut self, id: u64) -> Result<Transaction, Box<dyn Error>> {
+        let tx = self.wallet_lib.approve_spend(id)?;
+        self.publish_tx(&tx)?;
+        Ok(tx)
+    }
+
+    fn broadcast_signed_psbt(
+        &mut self,
+        id: u64,
+        signed: &PartiallySignedTransaction,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let tx = self.wallet_lib.finalize_unsigned_tx(id, signed)?;
+        self.wallet_lib.audit("broadcast_signed_psbt", format!("id={}", id), Some(tx.txid()));
+        self.publish_tx(&tx)?;
+        Ok(tx)
+    }
+
+    fn sweep_silent_payments(
+        &mut self,
+        dest_addr_type: AccountAddressType,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let tx = self.wallet_lib.sweep_silent_payments(dest_addr_type)?;
+        self.publish_tx(&tx)?;
+        Ok(tx)
+    }
+
+    fn sweep_address(
+        &mut self,
+        addr_str: &str,
+        dest_addr: String,
+        fee_rate: u64,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let params = format!("address={} dest_addr={} fee_rate={}", addr_str, dest_addr, fee_rate);
+        let tx = self.wallet_lib.sweep_address(addr_str, dest_addr, fee_rate)?;
+        self.wallet_lib.audit("sweep_address", params, Some(tx.txid()));
+        self.publish_tx(&tx)?;
+        Ok(tx)
+    }
+
+    fn unvault(&mut self, dest_addr: String, fee_rate: u64) -> Result<Transaction, Box<dyn Error>> {
+        let params = format!("dest_addr={} fee_rate={}", dest_addr, fee_rate);
+        let tx = self.wallet_lib.unvault(dest_addr, fee_rate)?;
+        self.wallet_lib.audit("unvault", params, Some(tx.txid()));
+        self.publish_tx(&tx)?;
+        Ok(tx)
+    }
+
+    fn spend_script_coins(
+        &mut self,
+        name: &str,
+        dest_addr: String,
+        fee_rate: u64,
+    ) -> Result<ScriptSpend, Box<dyn Error>> {
+        let params = format!("name={} dest_addr={} fee_rate={}", name, dest_addr, fee_rate);
+        let spend = self.wallet_lib.spend_script_coins(name, dest_addr, fee_rate)?;
+        if let Some(tx) = &spend.tx {
+            self.wallet_lib.audit("spend_script_coins", params, Some(tx.txid()));
+            self.publish_tx(tx)?;
+        }
+        Ok(spend)
+    }
+
+    fn finalize_script_psbt(
+        &mut self,
+        psbt: PartiallySignedTransaction,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let tx = self.wallet_lib.finalize_script_psbt(psbt)?;
+        self.wallet_lib.audit("finalize_script_psbt", String::new(), Some(tx.txid()));
+        self.publish_tx(&tx)?;
+        Ok(tx)
+    }
+
+    fn rotate_seed(
+        &mut self,
+        key_gen_cfg: KeyGenConfig,
+    ) -> Result<(Transaction, Mnemonic), Box<dyn Error>> {
+        let (tx, mnemonic) = self.wallet_lib.rotate_seed(key_gen_cfg)?;
+        self.publish_tx(&tx)?;
+        Ok((tx, mnemonic))
+    }
+
+    fn migrate_derivation(&mut self) -> Result<Transaction, Box<dyn Error>> {
+        let tx = self.wallet_lib.migrate_derivation()?;
+        self.publish_tx(&tx)?;
+        Ok(tx)
+    }
+
+    fn sweep_wif(
+        &mut self,
+        wif: &str,
+        dest_addr_type: AccountAddressType,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let mut sk = PrivateKey::from_wif(wif)?;
+        let pk = PublicKey::from_private_key(&Secp256k1::new(), &sk);
+        self.wallet_lib.import_key(&pk);
+
+        // addresses of the imported key are requested along with the wallet ones
+        let tx = self
+            .sync_with_tip()
+            .and_then(|()| self.wallet_lib.sweep_imported(&sk, dest_addr_type));
+        wipe_private_key(&mut sk);
+
+        let tx = tx?;
+        self.publish_tx(&tx)?;
+        Ok(tx)
+    }
+
     // TODO(evg): something better?
-    fn sync_with_tip(&mut self) -> Result<(), Box<dyn Error>> {
+    fn sync_with_tip_progress(
+        &mut self,
+        progress: &mut dyn FnMut(SyncProgress),
+    ) -> Result<(), Box<dyn Error>> {
         println!("******** SYNC_WITH_TIP_BEGIN ********");
+        let _span = tracing::info_span!("sync").entered();
+        let known: HashSet<OutPoint> =
+            self.wallet_lib.get_utxo_list().iter().map(|utxo| utxo.out_point).collect();
+        let (tip_height, tip_hash) = self.server_tip()?;
+        self.last_success = Some(now());
+        self.check_status_tip(tip_height)?;
+
+        // only the scripts whose status changed since the last sync have new transactions
+        let network = self.wallet_lib.network();
+        let script_hashes = self
+            .wallet_lib
+            .get_full_address_list()
+            .iter()
+            .map(|addr| script_hash(addr, network))
+            .collect::<Result<Vec<_>, _>>()?;
+        let requests = script_hashes.iter().map(|hash| json!([hash])).collect();
+        let statuses = self.call_pipelined("blockchain.scripthash.subscribe", requests)?;
+        let known_statuses = self.wallet_lib.script_statuses();
+        let changed: Vec<(String, String)> = script_hashes
+            .into_iter()
+            .zip(statuses)
+            .filter_map(|(hash, status)| {
+                // scripts without history have no status
+                let status = status.as_str()?.to_owned();
+                if known_statuses.get(&hash) == Some(&status) {
+                    None
+                } else {
+                    Some((hash, status))
+                }
+            })
+            .collect();
+        log::info!("{} of the scripts changed since the last sync", changed.len());
+
+        let requests = changed.iter().map(|(hash, _)| json!([hash])).collect();
+        let histories = self.call_pipelined("blockchain.scripthash.get_history", requests)?;
         let mut all_wallet_related_txs = Vec::new();
-        let btc_address_list = self.wallet_lib.get_full_address_list();
-        for btc_address in btc_address_list {
-            let history = self.electrumx_client.get_history(&btc_address)?;
-            for resp in history {
-                all_wallet_related_txs.push((resp.height, resp.tx_hash))
+        for history in histories {
+            for entry in history.as_array().ok_or("get_history answered no list")? {
+                let height = entry["height"].as_i64().ok_or("a history entry without height")?;
+                let tx_hash = entry["tx_hash"].as_str().ok_or("a history entry without tx_hash")?;
+                all_wallet_related_txs.push((height, tx_hash.to_owned()));
             }
         }
 
         // sort txs by height
-        // every time sync_with_tip is called we request all wallet related tx and process them
-        // in properly order
+        // every time sync_with_tip is called we request the txs of the changed scripts
+        // and process them in properly order
         // it seems we don't have to clear utxos from memory and database
         // through nature of key-value db, means it's not a problem try to add
         // one utxo several time it will be accept only once
         all_wallet_related_txs.sort();
 
-        let mut to_skip = HashMap::new();
-        for wallet_related_tx in all_wallet_related_txs {
-            // we don't want to process same tx twice so we skip already processed tx
-            if to_skip.contains_key(&wallet_related_tx.1) {
-                continue;
-            }
+        // we don't want to process same tx twice so we skip already processed tx,
+        // a transaction paying to several addresses is in each of their histories
+        let mut seen = HashSet::new();
+        all_wallet_related_txs.retain(|(_, tx_hash)| seen.insert(tx_hash.clone()));
+        let requests = all_wallet_related_txs
+            .iter()
+            .map(|(_, tx_hash)| json!([tx_hash, false]))
+            .collect();
+        let raw_txs = self.call_pipelined("blockchain.transaction.get", requests)?;
 
+        for (wallet_related_tx, tx_hex) in all_wallet_related_txs.into_iter().zip(raw_txs) {
             let tx_hash = wallet_related_tx.1;
-            let tx_hex = self
-                .electrumx_client
-                .get_transaction(tx_hash.clone(), false, false)?;
-            let tx = hex::decode(tx_hex).unwrap();
+            let tx_hex = tx_hex.as_str().ok_or("transaction.get answered no hex")?;
+            let tx: Transaction = deserialize(&hex::decode(tx_hex)?)?;
+            if self.verify_proofs {
+                if tx.txid().to_string() != tx_hash {
+                    let err = format!("server sent another transaction for {}", tx_hash);
+                    return Err(From::from(err));
+                }
+                if wallet_related_tx.0 > 0 {
+                    self.verify_merkle_proof(&tx, wallet_related_tx.0 as usize)?;
+                }
+            }
+            // mempool transactions have no height
+            if wallet_related_tx.0 > 0 {
+                self.wallet_lib
+                    .process_confirmed_tx(&tx, wallet_related_tx.0 as usize);
+            } else {
+                self.wallet_lib.process_tx(&tx);
+            }
+
+            let utxos_found = self
+                .wallet_lib
+                .get_utxo_list()
+                .iter()
+                .filter(|utxo| !known.contains(&utxo.out_point))
+                .count();
+            progress(SyncProgress {
+                height_scanned: wallet_related_tx.0.max(0) as usize,
+                tip_height,
+                utxos_found,
+            });
+        }
 
-            let tx: Transaction = deserialize(&tx).unwrap();
-            self.wallet_lib.process_tx(&tx);
+        // stored once their transactions are processed, a sync which failed halfway
+        // fetches the same histories again
+        for (hash, status) in &changed {
+            self.wallet_lib.put_script_status(hash, status);
+        }
+        self.wallet_lib.put_script_status_tip(tip_height, &tip_hash);
 
-            // mark tx as processed
-            to_skip.insert(tx_hash, ());
+        // the server may have dropped them from its mempool or restarted since
+        for tx in self.wallet_lib.get_unconfirmed_txs() {
+            if let Err(err) = self.electrumx_client.broadcast_transaction(serialize_hex(&tx)) {
+                log::warn!("rebroadcast of {} failed: {}", tx.txid(), err);
+            }
         }
         println!("******** SYNC_WITH_TIP_END ********\n\n\n");
 
         Ok(())
     }
+
+    // the server is asked for the histories of the scripts, not for blocks, so there is no
+    // block to stop the sync at
+    fn sync_with_tip_batch(
+        &mut self,
+        _max_blocks: usize,
+        progress: &mut dyn FnMut(SyncProgress),
+    ) -> Result<bool, Box<dyn Error>> {
+        self.sync_with_tip_progress(progress)?;
+        Ok(true)
+    }
 }
 
 impl ElectrumxWallet {
@@ -140,16 +480,343 @@ impl ElectrumxWallet {
         wc: WalletConfig,
         mode: WalletLibraryMode,
     ) -> Result<(ElectrumxWallet, Mnemonic), WalletError> {
-        let (wallet_lib, mnemonic) = WalletLibrary::new(wc, mode)?;
-        let electrumx_client = ElectrumxClient::new(electrumx_address).unwrap();
-
-        Ok((
-            ElectrumxWallet {
-                wallet_lib: Box::new(wallet_lib),
-                electrumx_address,
-                electrumx_client,
-            },
-            mnemonic,
-        ))
+        let (mut wallet_lib, mnemonic) = WalletLibrary::new(wc.clone(), mode)?;
+        wallet_lib.record_backend(BackendKind::Electrum);
+        let electrumx_client = connect(electrumx_address, wc.connect_timeout(), wc.read_timeout())
+            .map_err(|err| WalletError::BackendUnavailable(err.to_string()))?;
+        let wallet =
+            ElectrumxWallet::attach(Box::new(wallet_lib), electrumx_address, electrumx_client, &wc);
+        Ok((wallet, mnemonic))
+    }
+
+    /// the wallet of another backend syncing with the electrum server at `electrumx_address`
+    /// from the height it reached, see `Wallet::into_wallet_lib` and `connect_to_chain`
+    pub fn attach(
+        wallet_lib: Box<dyn WalletLibraryInterface + Send>,
+        electrumx_address: SocketAddr,
+        electrumx_client: ElectrumxClient<SocketAddr>,
+        wc: &WalletConfig,
+    ) -> Self {
+        ElectrumxWallet {
+            wallet_lib,
+            electrumx_address,
+            electrumx_client,
+            verify_proofs: wc.verify_proofs(),
+            connect_timeout: wc.connect_timeout(),
+            read_timeout: wc.read_timeout(),
+            last_success: Some(now()),
+        }
+    }
+
+    // the requests of a sync go out on a connection of their own without waiting for the
+    // answers, one round-trip per PIPELINE_DEPTH requests instead of one per request.
+    // Answers come back in any order, they are matched to the requests by id
+    fn call_pipelined(
+        &self,
+        method: &str,
+        params: Vec<Value>,
+    ) -> Result<Vec<Value>, Box<dyn Error>> {
+        let mut writer =
+            TcpStream::connect_timeout(&self.electrumx_address, self.connect_timeout)?;
+        writer.set_read_timeout(Some(self.read_timeout))?;
+        let mut reader = BufReader::new(writer.try_clone()?);
+        let mut results = vec![Value::Null; params.len()];
+        for (chunk_no, chunk) in params.chunks(PIPELINE_DEPTH).enumerate() {
+            let first = chunk_no * PIPELINE_DEPTH;
+            let mut requests = String::new();
+            for (i, params) in chunk.iter().enumerate() {
+                let request = json!({
+                    "jsonrpc": "2.0",
+                    "id": first + i,
+                    "method": method,
+                    "params": params,
+                });
+                requests.push_str(&request.to_string());
+                requests.push('\n');
+            }
+            writer.write_all(requests.as_bytes())?;
+
+            let mut answered = 0;
+            while answered < chunk.len() {
+                let mut line = String::new();
+                if reader.read_line(&mut line)? == 0 {
+                    let err = format!("the server closed the connection on {}", method);
+                    return Err(From::from(err));
+                }
+                let mut answer: Value = serde_json::from_str(&line)?;
+                // notifications of the scripts subscribed to by the requests before
+                if answer["id"].is_null() && answer["method"].is_string() {
+                    continue;
+                }
+                answered += 1;
+                let id = answer["id"].as_u64().map(|id| id as usize);
+                let id = match id {
+                    Some(id) if id >= first && id < first + chunk.len() => id,
+                    _ => {
+                        let err = format!("unexpected answer to {}: {}", method, line.trim_end());
+                        return Err(From::from(err));
+                    }
+                };
+                if !answer["error"].is_null() {
+                    return Err(From::from(format!("{} failed: {}", method, answer["error"])));
+                }
+                results[id] = answer["result"].take();
+            }
+        }
+        Ok(results)
+    }
+
+    fn server_tip(&self) -> Result<(usize, Sha256dHash), Box<dyn Error>> {
+        let mut answer = self.call_pipelined("blockchain.headers.subscribe", vec![json!([])])?;
+        let tip = answer.pop().ok_or("no answer to headers.subscribe")?;
+        let height = tip["height"].as_u64().ok_or("no height of the tip")?;
+        let raw = tip["hex"].as_str().ok_or("no header of the tip")?;
+        let header: BlockHeader = deserialize(&hex::decode(raw)?)?;
+        Ok((height as usize, header.bitcoin_hash()))
+    }
+
+    // the statuses describe the histories on the chain they were seen on, a reorg
+    // may have moved transactions which the wallet then still has in the old blocks
+    fn check_status_tip(&mut self, tip_height: usize) -> Result<(), Box<dyn Error>> {
+        let (height, hash) = match self.wallet_lib.script_status_tip() {
+            Some(status_tip) => status_tip,
+            None => return Ok(()),
+        };
+        let reorged = if height > tip_height {
+            true
+        } else {
+            let raw = hex::decode(self.electrumx_client.get_block_header(height)?)?;
+            let header: BlockHeader = deserialize(&raw)?;
+            header.bitcoin_hash() != hash
+        };
+        if reorged {
+            log::info!("block {} of the last sync was replaced, fetching all histories", height);
+            self.wallet_lib.clear_script_statuses();
+        }
+        Ok(())
+    }
+
+    // a server making up a transaction would have to mine the block it claims the transaction is in
+    fn verify_merkle_proof(
+        &mut self,
+        tx: &Transaction,
+        height: usize,
+    ) -> Result<(), Box<dyn Error>> {
+        let header = match self.wallet_lib.get_block_header(height) {
+            Some(header) => header,
+            None => {
+                let raw = hex::decode(self.electrumx_client.get_block_header(height)?)?;
+                let header: BlockHeader = deserialize(&raw)?;
+                header.spv_validate(&header.target())?;
+                self.wallet_lib.add_block_header(height, &header)?;
+                HeaderRecord::new(height, &header)
+            }
+        };
+
+        let txid = tx.txid();
+        let proof = self
+            .electrumx_client
+            .get_merkle_transaction(txid.to_string(), height)?;
+        let branch = proof
+            .merkle
+            .iter()
+            .map(|hash| Sha256dHash::from_hex(hash))
+            .collect::<Result<Vec<_>, _>>()?;
+        if headers::merkle_root(txid, &branch, proof.pos) != header.merkle_root {
+            return Err(From::from(format!("{} is not in the block at height {}", txid, height)));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::{
+        Block, Transaction, network::constants::Network, consensus::encode::serialize_hex,
+    };
+    use bitcoin_hashes::{sha256, sha256d::Hash as Sha256dHash, Hash};
+    use serde_json::{json, Value};
+
+    use std::{
+        thread,
+        io::{BufRead, BufReader, Write},
+        net::{SocketAddr, TcpListener},
+        sync::{Arc, atomic::{AtomicUsize, Ordering}},
+        time::{Duration, Instant},
+    };
+
+    use crate::walletlibrary::{WalletConfig, WalletLibraryMode, KeyGenConfig};
+    use crate::memory::{MemoryChain, test::payment};
+    use crate::interface::{Wallet, BlockChainIO};
+    use crate::account::AccountAddressType;
+    use crate::address;
+    use super::{script_hash, connect_to_chain, ElectrumxWallet};
+
+    fn block(chain: &MemoryChain, height: usize) -> Block {
+        chain.get_block(&chain.get_block_hash(height as u32).unwrap()).unwrap()
+    }
+
+    // the transactions paying to the script of `hash` with their heights
+    fn history(chain: &MemoryChain, hash: &str) -> Vec<(usize, Sha256dHash)> {
+        let pays = |tx: &Transaction| {
+            tx.output.iter().any(|output| {
+                address::from_script(&output.script_pubkey, Network::Regtest)
+                    .map_or(false, |addr| script_hash(&addr, Network::Regtest).unwrap() == hash)
+            })
+        };
+        let mut history = Vec::new();
+        for height in 0..=chain.height() {
+            for tx in block(chain, height).txdata.iter().filter(|tx| pays(tx)) {
+                history.push((height, tx.txid()));
+            }
+        }
+        history
+    }
+
+    fn answer(chain: &MemoryChain, method: &str, params: &Value) -> Value {
+        match method {
+            "blockchain.headers.subscribe" => {
+                let header = block(chain, chain.height()).header;
+                json!({"height": chain.height(), "hex": serialize_hex(&header)})
+            }
+            "blockchain.block.header" => {
+                let height = params[0].as_u64().unwrap() as usize;
+                json!(serialize_hex(&block(chain, height).header))
+            }
+            "blockchain.scripthash.subscribe" => {
+                let history = history(chain, params[0].as_str().unwrap());
+                if history.is_empty() {
+                    return Value::Null;
+                }
+                let status: String = history
+                    .iter()
+                    .map(|(height, txid)| format!("{}:{}:", txid, height))
+                    .collect();
+                json!(hex::encode(sha256::Hash::hash(status.as_bytes()).into_inner()))
+            }
+            "blockchain.scripthash.get_history" => {
+                let history = history(chain, params[0].as_str().unwrap());
+                let entries: Vec<Value> = history
+                    .iter()
+                    .map(|(height, txid)| json!({"height": height, "tx_hash": txid.to_string()}))
+                    .collect();
+                json!(entries)
+            }
+            "blockchain.transaction.get" => {
+                let txid = params[0].as_str().unwrap();
+                (0..=chain.height())
+                    .flat_map(|height| block(chain, height).txdata)
+                    .find(|tx| tx.txid().to_string() == txid)
+                    .map_or(Value::Null, |tx| json!(serialize_hex(&tx)))
+            }
+            _ => Value::Null,
+        }
+    }
+
+    // an electrum server of `chain`, counting the histories it is asked for
+    fn serve(chain: MemoryChain) -> (SocketAddr, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let histories = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&histories);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let (chain, counter) = (chain.clone(), Arc::clone(&counter));
+                let stream = stream.unwrap();
+                thread::spawn(move || {
+                    let mut writer = stream.try_clone().unwrap();
+                    for line in BufReader::new(stream).lines() {
+                        let request: Value = match line {
+                            Ok(line) => serde_json::from_str(&line).unwrap(),
+                            Err(_) => return,
+                        };
+                        let method = request["method"].as_str().unwrap();
+                        if method == "blockchain.scripthash.get_history" {
+                            counter.fetch_add(1, Ordering::SeqCst);
+                        }
+                        let answer = json!({
+                            "jsonrpc": "2.0",
+                            "id": request["id"],
+                            "result": answer(&chain, method, &request["params"]),
+                        });
+                        if writeln!(writer, "{}", answer).is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+        (address, histories)
+    }
+
+    #[test]
+    fn electrum_script_hash() {
+        // the example of the electrum protocol docs
+        let hash = script_hash("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa", Network::Bitcoin).unwrap();
+        assert_eq!(hash, "8b01df4e368ea28f8dc0423bcf7a4923e3a12d307c875e47a0cfbf90b5c39161");
+    }
+
+    #[test]
+    fn silent_server() {
+        // the OS accepts the connection, nobody ever answers on it
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut wc = WalletConfig::in_memory();
+        wc.set_timeouts(Duration::from_secs(1), Duration::from_millis(200));
+
+        let started = Instant::now();
+        let address = listener.local_addr().unwrap();
+        assert!(connect_to_chain(address, &wc, Network::Regtest).is_err());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn script_status_cache() {
+        let chain = MemoryChain::new(Network::Regtest);
+        let (address, histories) = serve(chain.clone());
+        let (mut wallet, _) = ElectrumxWallet::new(
+            address,
+            WalletConfig::in_memory(),
+            WalletLibraryMode::Create(KeyGenConfig::debug()),
+        )
+        .unwrap();
+        let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        // the histories a sync asked for
+        let sync = |wallet: &mut ElectrumxWallet| {
+            wallet.sync_with_tip().unwrap();
+            histories.swap(0, Ordering::SeqCst)
+        };
+
+        chain.push_block(vec![payment(&addr, 100_000_000)]);
+        assert_eq!(sync(&mut wallet), 1);
+        assert_eq!(wallet.wallet_lib().wallet_balance(), 100_000_000);
+
+        // the status of the address stays the same
+        chain.push_block(Vec::new());
+        assert_eq!(sync(&mut wallet), 0);
+
+        let mut second = payment(&addr, 50_000_000);
+        second.input[0].previous_output.vout = 1;
+        chain.push_block(vec![second.clone()]);
+        assert_eq!(sync(&mut wallet), 1);
+        assert_eq!(wallet.wallet_lib().wallet_balance(), 150_000_000);
+
+        // the block of the last sync is replaced by another one with the same payment,
+        // the statuses of the old chain are dropped
+        let height = chain.height();
+        chain.truncate(height - 1);
+        let unrelated = Transaction {
+            version: 2,
+            lock_time: 1,
+            input: Vec::new(),
+            output: Vec::new(),
+        };
+        chain.push_block(vec![unrelated, second]);
+        assert_eq!(sync(&mut wallet), 1);
+        assert_eq!(sync(&mut wallet), 0);
+
+        // and so are they along with the headers
+        wallet.wallet_lib_mut().rewind_headers(0);
+        assert_eq!(sync(&mut wallet), 1);
+        assert_eq!(wallet.wallet_lib().wallet_balance(), 150_000_000);
     }
 }
@@ -1,8 +1,12 @@
 use wallet::context::GlobalContext;
+use bitcoin::network::constants::Network;
 use std::io::Result;
 
 fn main() -> Result<()> {
-    let context = GlobalContext::default();
+    // the default ports, `GlobalContext::default()` would pick free ones
+    let user = "devuser".to_owned();
+    let password = "devpass".to_owned();
+    let context = GlobalContext::new(Network::Regtest, user, password, None, None, None);
     let mut bitcoind = context.bitcoind("tcp://127.0.0.1:18501".to_owned(), "tcp://127.0.0.1:18502".to_owned())?;
     let mut electrs = context.electrs()?;
 
@@ -35,6 +35,10 @@ pub enum WalletError {
     SymmetricCipherError(symmetriccipher::SymmetricCipherError),
     /// has no key in db
     HasNoWalletInDatabase,
+    /// Database to recover into holds the state of a wallet of another seed
+    DatabaseHoldsOtherWallet,
+    /// Database holds a wallet of this other network
+    DatabaseNetworkMismatch(String),
     /// Mnemonic contains an unknown word
     UnknownMnemonicWord,
     /// Mnemonic must have a word count divisible by 3
@@ -45,6 +49,64 @@ pub enum WalletError {
     MnemonicChecksumNotMatch,
     /// Cannot obtain random source
     CannotObtainRandomSource,
+    /// SLIP-39 share is malformed or its checksum does not match
+    InvalidShare,
+    /// SLIP-39 shares do not belong together or are not enough
+    InvalidShareSet,
+    /// SLIP-39 threshold, share count or secret length is out of range
+    InvalidShareParameters,
+    /// SLIP-39 shares recover a secret with a wrong digest
+    ShareDigestNotMatch,
+    /// Backup file is malformed or has an unsupported version
+    InvalidBackup,
+    /// Backup file is tampered or the passphrase is wrong
+    BackupAuthenticationFailed,
+    /// Backup belongs to a wallet with other passphrase or salt
+    BackupKeyNotMatch,
+    /// State snapshot is malformed or has an unsupported version
+    InvalidSnapshot,
+    /// State snapshot is corrupted, its checksum does not match
+    SnapshotChecksumNotMatch,
+    /// State snapshot was taken of a wallet with another seed
+    SnapshotWalletNotMatch,
+    /// BIP47 payment code is malformed or has an unsupported version
+    InvalidPaymentCode,
+    /// Coins of the wallet can't pay the amount and the fee
+    InsufficientFunds,
+    /// Payment is above the limit of a single transaction in the spending policy
+    TransactionLimitExceeded(u64),
+    /// Payment is above what is left of the daily limit in the spending policy
+    DailyLimitExceeded(u64),
+    /// Destination is blacklisted or not whitelisted by the spending policy
+    DestinationNotAllowed(String),
+    /// Payment is above the approval threshold, it waits for approval under this id
+    ApprovalRequired(u64),
+    /// Block at this height is not the checkpoint of the network
+    CheckpointNotMatch(usize),
+    /// Block at this height does not follow the header stored below it
+    HeaderNotConnected(usize),
+    /// Database was written by a newer version of the wallet with this schema version
+    UnsupportedSchemaVersion(u32),
+    /// Blockchain backend couldn't be reached, the call may succeed later
+    BackendUnavailable(String),
+    /// Blockchain backend refused the request, making it again won't help
+    BackendRejected(String),
+    /// Wallet has the public keys only, the private ones stay with an offline signer
+    WatchOnly,
+    /// Keys of a watch-only wallet are malformed or lack an account
+    InvalidWatchOnlyKeys,
+    /// Part of an animated QR code is malformed or belongs to another payload
+    InvalidQrPart,
+    /// Master key is wiped from memory until the wallet is unlocked with its passphrase
+    Locked,
+    /// Passphrase and salt do not decrypt the seed of the wallet
+    WrongPassphrase,
+    /// Destination address is malformed or its checksum does not match
+    InvalidAddress(String),
+    /// Destination address belongs to another network than the wallet
+    AddressNetworkMismatch(String),
+    /// Operation was cancelled, the blocks processed before are kept
+    Cancelled,
 }
 
 impl Error for WalletError {
@@ -73,6 +135,12 @@ impl fmt::Display for WalletError {
                 }
             ),
             &WalletError::HasNoWalletInDatabase => write!(f, "has no wallet in database"),
+            &WalletError::DatabaseHoldsOtherWallet => {
+                write!(f, "database holds a wallet of another seed, it has to be overwritten")
+            },
+            &WalletError::DatabaseNetworkMismatch(ref network) => {
+                write!(f, "database holds a wallet of network {}", network)
+            },
             &WalletError::UnknownMnemonicWord => write!(f, "mnemonic contains an unknown word"),
             &WalletError::InvalidMnemonicLength => {
                 write!(f, "mnemonic must have a word count divisible by 3")
@@ -82,6 +150,77 @@ impl fmt::Display for WalletError {
             },
             &WalletError::MnemonicChecksumNotMatch => write!(f, "mnemonic checking bits not match"),
             &WalletError::CannotObtainRandomSource => write!(f, "cannot obtain random source"),
+            &WalletError::InvalidShare => write!(f, "share is malformed or has a wrong checksum"),
+            &WalletError::InvalidShareSet => {
+                write!(f, "shares do not belong together or are not enough")
+            },
+            &WalletError::InvalidShareParameters => {
+                write!(f, "threshold, share count or secret length is out of range")
+            },
+            &WalletError::ShareDigestNotMatch => write!(f, "shares digest not match"),
+            &WalletError::InvalidBackup => write!(f, "backup is malformed or has unknown version"),
+            &WalletError::BackupAuthenticationFailed => {
+                write!(f, "backup is tampered or the passphrase is wrong")
+            },
+            &WalletError::BackupKeyNotMatch => {
+                write!(f, "backup does not match the wallet passphrase and salt")
+            },
+            &WalletError::InvalidSnapshot => {
+                write!(f, "state snapshot is malformed or has unknown version")
+            },
+            &WalletError::SnapshotChecksumNotMatch => {
+                write!(f, "state snapshot is corrupted, checksum not match")
+            },
+            &WalletError::SnapshotWalletNotMatch => {
+                write!(f, "state snapshot was taken of a wallet with another seed")
+            },
+            &WalletError::InvalidPaymentCode => {
+                write!(f, "payment code is malformed or has unknown version")
+            },
+            &WalletError::InsufficientFunds => {
+                write!(f, "not enough coins to pay the amount and the fee")
+            },
+            &WalletError::TransactionLimitExceeded(limit) => {
+                write!(f, "payment exceeds the limit of {} satoshi per transaction", limit)
+            },
+            &WalletError::DailyLimitExceeded(left) => {
+                write!(f, "payment exceeds the daily limit, {} satoshi left", left)
+            },
+            &WalletError::DestinationNotAllowed(ref addr) => {
+                write!(f, "spending policy does not allow paying to {}", addr)
+            },
+            &WalletError::ApprovalRequired(id) => {
+                write!(f, "payment waits for approval, its id is {}", id)
+            },
+            &WalletError::CheckpointNotMatch(height) => {
+                write!(f, "block at height {} does not match the checkpoint", height)
+            },
+            &WalletError::HeaderNotConnected(height) => {
+                write!(f, "block at height {} does not follow the stored header chain", height)
+            },
+            &WalletError::UnsupportedSchemaVersion(version) => {
+                write!(f, "database has schema version {} of a newer wallet", version)
+            },
+            &WalletError::BackendUnavailable(ref err) => write!(f, "backend unavailable: {}", err),
+            &WalletError::BackendRejected(ref err) => write!(f, "backend rejected: {}", err),
+            &WalletError::WatchOnly => {
+                write!(f, "watch-only wallet has no private keys, sign with the offline signer")
+            },
+            &WalletError::InvalidWatchOnlyKeys => {
+                write!(f, "watch-only keys are malformed or lack an account")
+            },
+            &WalletError::InvalidQrPart => {
+                write!(f, "QR code part is malformed or belongs to another payload")
+            },
+            &WalletError::Locked => write!(f, "wallet is locked, unlock it to sign"),
+            &WalletError::WrongPassphrase => {
+                write!(f, "passphrase or salt does not decrypt the wallet seed")
+            },
+            &WalletError::InvalidAddress(ref addr) => write!(f, "{} is not a valid address", addr),
+            &WalletError::AddressNetworkMismatch(ref addr) => {
+                write!(f, "{} is an address of another network", addr)
+            },
+            &WalletError::Cancelled => write!(f, "operation was cancelled"),
         }
     }
 }
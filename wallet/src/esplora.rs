@@ -0,0 +1,194 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Esplora backend
+//!
+//! A page can't open the sockets of an electrum server or of bitcoind, the wallet compiled to
+//! wasm32-unknown-unknown syncs with the HTTP API of an Esplora server instead, such as
+//! https://blockstream.info/api. The requests are made with `fetch`, so syncing and publishing
+//! are futures the page awaits, `EsploraWallet` doesn't implement the blocking `Wallet`.
+//!
+use bitcoin::{
+    Transaction,
+    consensus::encode::{serialize_hex, deserialize},
+};
+use serde::Deserialize;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Request, RequestInit, RequestMode, Response};
+
+use std::{collections::HashSet, error::Error};
+
+use super::walletlibrary::{
+    WalletLibrary, WalletConfig, WalletLibraryMode, SendOptions, SendOutcome,
+};
+use super::interface::WalletLibraryInterface;
+use super::error::WalletError;
+use super::mnemonic::Mnemonic;
+
+// confirmed transactions of an address are listed this many at a time
+const CHAIN_PAGE_LEN: usize = 25;
+
+#[derive(Deserialize)]
+struct TxStatus {
+    confirmed: bool,
+    block_height: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct AddressTx {
+    txid: String,
+    status: TxStatus,
+}
+
+pub struct EsploraWallet {
+    pub wallet_lib: Box<dyn WalletLibraryInterface + Send>,
+    // e.g. https://blockstream.info/testnet/api, without the trailing slash
+    base_url: String,
+}
+
+fn js_error(err: JsValue) -> WalletError {
+    WalletError::BackendUnavailable(format!("{:?}", err))
+}
+
+impl EsploraWallet {
+    pub fn new(
+        base_url: &str,
+        wc: WalletConfig,
+        mode: WalletLibraryMode,
+    ) -> Result<(EsploraWallet, Mnemonic), WalletError> {
+        let (wallet_lib, mnemonic) = WalletLibrary::new(wc, mode)?;
+        Ok((
+            EsploraWallet {
+                wallet_lib: Box::new(wallet_lib),
+                base_url: base_url.trim_end_matches('/').to_owned(),
+            },
+            mnemonic,
+        ))
+    }
+
+    pub fn wallet_lib(&self) -> &Box<dyn WalletLibraryInterface + Send> {
+        &self.wallet_lib
+    }
+
+    pub fn wallet_lib_mut(&mut self) -> &mut Box<dyn WalletLibraryInterface + Send> {
+        &mut self.wallet_lib
+    }
+
+    // body of the answer to `path`, the server refusing the request is `BackendRejected`
+    async fn fetch(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<String>,
+    ) -> Result<String, WalletError> {
+        let mut init = RequestInit::new();
+        init.method(method);
+        init.mode(RequestMode::Cors);
+        if let Some(ref body) = body {
+            init.body(Some(&JsValue::from_str(body)));
+        }
+        let url = format!("{}{}", self.base_url, path);
+        let request = Request::new_with_str_and_init(&url, &init).map_err(js_error)?;
+        let window = web_sys::window()
+            .ok_or_else(|| WalletError::BackendUnavailable("no window to fetch from".to_owned()))?;
+        let response: Response = JsFuture::from(window.fetch_with_request(&request))
+            .await
+            .map_err(js_error)?
+            .dyn_into()
+            .map_err(js_error)?;
+        let text = JsFuture::from(response.text().map_err(js_error)?)
+            .await
+            .map_err(js_error)?
+            .as_string()
+            .unwrap_or_default();
+        if !response.ok() {
+            return Err(WalletError::BackendRejected(format!("{} {}", response.status(), text)));
+        }
+        Ok(text)
+    }
+
+    async fn address_txs(&self, address: &str) -> Result<Vec<AddressTx>, Box<dyn Error>> {
+        // the first page has the transactions in the mempool too
+        let first = self.fetch("GET", &format!("/address/{}/txs", address), None).await?;
+        let mut txs: Vec<AddressTx> = serde_json::from_str(&first)?;
+        let mut page_len = txs.iter().filter(|tx| tx.status.confirmed).count();
+        while page_len == CHAIN_PAGE_LEN {
+            let last = &txs.last().unwrap().txid;
+            let path = format!("/address/{}/txs/chain/{}", address, last);
+            let page: Vec<AddressTx> =
+                serde_json::from_str(&self.fetch("GET", &path, None).await?)?;
+            page_len = page.len();
+            txs.extend(page);
+        }
+        Ok(txs)
+    }
+
+    /// process the transactions of the addresses of the wallet, oldest first
+    pub async fn sync_with_tip(&mut self) -> Result<(), Box<dyn Error>> {
+        let mut related = Vec::new();
+        for address in self.wallet_lib.get_full_address_list() {
+            for tx in self.address_txs(&address).await? {
+                // mempool transactions are processed after the confirmed ones
+                let height = tx.status.block_height.filter(|_| tx.status.confirmed);
+                related.push((height.unwrap_or(usize::max_value()), tx.txid));
+            }
+        }
+        related.sort();
+
+        let mut processed = HashSet::new();
+        for (height, txid) in related {
+            if !processed.insert(txid.clone()) {
+                continue;
+            }
+            let raw = hex::decode(self.fetch("GET", &format!("/tx/{}/hex", txid), None).await?)?;
+            let tx: Transaction = deserialize(&raw)?;
+            if tx.txid().to_string() != txid {
+                return Err(From::from(format!("server sent another transaction for {}", txid)));
+            }
+            if height == usize::max_value() {
+                self.wallet_lib.process_tx(&tx);
+            } else {
+                self.wallet_lib.process_confirmed_tx(&tx, height);
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn publish_tx(&mut self, tx: &Transaction) -> Result<(), Box<dyn Error>> {
+        self.fetch("POST", "/tx", Some(serialize_hex(tx))).await?;
+        self.wallet_lib.add_unconfirmed_tx(tx);
+        self.wallet_lib.audit("publish_tx", String::new(), Some(tx.txid()));
+        Ok(())
+    }
+
+    pub async fn send_coins(
+        &mut self,
+        addr_str: String,
+        amt: u64,
+        submit: bool,
+        options: SendOptions,
+    ) -> Result<SendOutcome, Box<dyn Error>> {
+        let params = format!("dest_addr={} amt={} submit={}", addr_str, amt, submit);
+        let outcome = self.wallet_lib.send_coins(addr_str, amt, options)?;
+        if let SendOutcome::Sent(ref sent) = outcome {
+            self.wallet_lib.audit("send_coins", params, Some(sent.txid()));
+            if submit {
+                self.publish_tx(&sent.tx).await?;
+            }
+        }
+        Ok(outcome)
+    }
+}
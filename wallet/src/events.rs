@@ -0,0 +1,85 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Wallet events
+//!
+//! Notifications about what the wallet noticed while processing transactions
+//!
+use bitcoin::OutPoint;
+use bitcoin_hashes::sha256d::Hash as Sha256dHash;
+
+use std::sync::mpsc::{self, Receiver, Sender};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum WalletEvent {
+    /// a transaction paying to the wallet, it may be unconfirmed
+    Deposit { txid: Sha256dHash, value: u64 },
+    /// a wallet transaction got into a block, `outgoing` if it spends coins of the wallet
+    Confirmed {
+        txid: Sha256dHash,
+        block_height: usize,
+        outgoing: bool,
+    },
+    /// an output paying to a script watched under `label` appeared
+    WatchedFunded {
+        label: String,
+        out_point: OutPoint,
+        value: u64,
+    },
+    /// `txid` spends an output of a script watched under `label`
+    WatchedSpent {
+        label: String,
+        out_point: OutPoint,
+        txid: Sha256dHash,
+    },
+    /// `txid` spends the vault deposit `out_point` but isn't an unvault of the wallet,
+    /// the recovery key should move the deposits left before their delay passes
+    UnexpectedUnvault { out_point: OutPoint, txid: Sha256dHash },
+    /// the wallet processed the block at `height`
+    NewTip { height: usize },
+    /// `txid` can not be confirmed anymore, `conflicting_txid` spends some of the same coins
+    Conflict {
+        txid: Sha256dHash,
+        conflicting_txid: Sha256dHash,
+    },
+    /// the process `name` serving the wallet, bitcoind or electrs, exited
+    BackendDown { name: String },
+    /// the process `name` was restarted and the wallet reconnected to its backend
+    BackendUp { name: String },
+    /// the witness `source` of the quorum has its tip at `height` while the backend the wallet
+    /// synced with is at `tip`, `forked` if they have different blocks, see `quorum::Quorum`
+    BackendDivergence {
+        source: String,
+        height: usize,
+        tip: usize,
+        forked: bool,
+    },
+}
+
+/// subscribers of wallet events, the ones which went away are dropped on the next event
+#[derive(Default)]
+pub struct EventSink(Vec<Sender<WalletEvent>>);
+
+impl EventSink {
+    pub fn subscribe(&mut self) -> Receiver<WalletEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.0.push(sender);
+        receiver
+    }
+
+    pub fn emit(&mut self, event: WalletEvent) {
+        self.0.retain(|sender| sender.send(event.clone()).is_ok());
+    }
+}
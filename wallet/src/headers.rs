@@ -0,0 +1,200 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Header chain
+//!
+//! Headers of the blocks the wallet synced with, each one has to follow the one below it.
+//! On the main and test networks the chain is pinned to the blocks of a few checkpoints,
+//! a backend serving another chain is caught once the sync passes one of them.
+//! Transactions reported by an electrum server are checked by their merkle proofs
+//! against the headers.
+//!
+use bitcoin::{
+    BlockHeader, BitcoinHash,
+    blockdata::constants::genesis_block,
+    network::constants::Network,
+};
+use bitcoin_hashes::{hex::FromHex, sha256d::Hash as Sha256dHash, Hash};
+use serde::{Serialize, Deserialize};
+
+use super::error::WalletError;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HeaderRecord {
+    pub height: usize,
+    pub hash: Sha256dHash,
+    pub prev_hash: Sha256dHash,
+    /// merkle proofs of the transactions in the block are checked against it
+    pub merkle_root: Sha256dHash,
+    /// unix time the block was mined at, 0 for headers stored before it was kept
+    #[serde(default)]
+    pub time: u32,
+}
+
+impl HeaderRecord {
+    pub fn new(height: usize, header: &BlockHeader) -> Self {
+        HeaderRecord {
+            height,
+            hash: header.bitcoin_hash(),
+            prev_hash: header.prev_blockhash,
+            merkle_root: header.merkle_root,
+            time: header.time,
+        }
+    }
+}
+
+// from the checkpoints of Bitcoin Core
+const BITCOIN_CHECKPOINTS: &[(usize, &str)] = &[
+    (11111, "0000000069e244f73d78e8fd29ba2fd2ed618bd6fa2ee92559f542fdb26e7c1d"),
+    (33333, "000000002dd5588a74784eaa7ab0507a18ad16a236e7b1ce69f00d7ddfb5d0a6"),
+    (105000, "00000000000291ce28027faea320c8d2b054b2e0fe44a773f3eefb151d6bdc97"),
+    (210000, "000000000000048b95347e83192f69cf0366076336c639f9b7228e9ba171342e"),
+    (250000, "000000000000003887df1f29024b06fc2200b55f8af8f35453d7be294df2d214"),
+    (295000, "00000000000000004d9b4ef50f0f9d686fd69db2e03af35a100370c64632a983"),
+];
+
+const TESTNET_CHECKPOINTS: &[(usize, &str)] = &[
+    (546, "000000002a936ca763904c3c35fce2f3556c559c0214345d31b1bcebf76acb70"),
+];
+
+/// hash of the block at `height` if the network has a checkpoint there
+pub fn checkpoint(network: Network, height: usize) -> Option<Sha256dHash> {
+    let checkpoints: &[(usize, &str)] = match network {
+        Network::Bitcoin => BITCOIN_CHECKPOINTS,
+        Network::Testnet => TESTNET_CHECKPOINTS,
+        Network::Regtest => &[],
+    };
+    checkpoints
+        .iter()
+        .find(|(checkpoint_height, _)| *checkpoint_height == height)
+        .map(|(_, hash)| Sha256dHash::from_hex(hash).unwrap())
+}
+
+/// check that `hash` of the block at height 0 of a backend is the genesis block of `network`,
+/// a wallet isn't attached to a backend of another chain
+pub fn check_genesis(network: Network, hash: Sha256dHash) -> Result<(), WalletError> {
+    if hash != genesis_block(network).bitcoin_hash() {
+        return Err(WalletError::CheckpointNotMatch(0));
+    }
+    Ok(())
+}
+
+/// check that `header` may go on top of `prev`, the header below it in the chain if it is known
+pub fn check(
+    network: Network,
+    header: &HeaderRecord,
+    prev: Option<&HeaderRecord>,
+) -> Result<(), WalletError> {
+    if let Some(hash) = checkpoint(network, header.height) {
+        if header.hash != hash {
+            return Err(WalletError::CheckpointNotMatch(header.height));
+        }
+    }
+    match prev {
+        Some(prev) if prev.hash != header.prev_hash => {
+            Err(WalletError::HeaderNotConnected(header.height))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// merkle root `txid` at the position `pos` of its block leads to by the `branch` of hashes
+pub fn merkle_root(txid: Sha256dHash, branch: &[Sha256dHash], pos: usize) -> Sha256dHash {
+    let mut hash = txid;
+    for (level, sibling) in branch.iter().enumerate() {
+        let mut data = Vec::with_capacity(64);
+        if (pos >> level) & 1 == 1 {
+            data.extend_from_slice(&sibling[..]);
+            data.extend_from_slice(&hash[..]);
+        } else {
+            data.extend_from_slice(&hash[..]);
+            data.extend_from_slice(&sibling[..]);
+        }
+        hash = Sha256dHash::hash(&data);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::{BlockHeader, BitcoinHash, network::constants::Network};
+    use bitcoin::util::hash::bitcoin_merkle_root;
+    use bitcoin::blockdata::constants::genesis_block;
+    use bitcoin_hashes::{hex::FromHex, sha256d::Hash as Sha256dHash, Hash};
+    use super::HeaderRecord;
+
+    fn header(prev_blockhash: Sha256dHash, nonce: u32) -> BlockHeader {
+        BlockHeader {
+            version: 1,
+            prev_blockhash,
+            merkle_root: Default::default(),
+            time: 1_296_688_602,
+            bits: 0x207fffff,
+            nonce,
+        }
+    }
+
+    #[test]
+    fn header_chain() {
+        let first = header(Default::default(), 0);
+        let second = header(first.bitcoin_hash(), 1);
+        let first = HeaderRecord::new(1, &first);
+        let second = HeaderRecord::new(2, &second);
+        super::check(Network::Regtest, &first, None).unwrap();
+        super::check(Network::Regtest, &second, Some(&first)).unwrap();
+
+        // a header of another chain doesn't follow
+        let fork = HeaderRecord::new(2, &header(Default::default(), 2));
+        assert!(super::check(Network::Regtest, &fork, Some(&first)).is_err());
+
+        // nor does a block of the right height which isn't the checkpoint
+        let mut off_checkpoint = second.clone();
+        off_checkpoint.height = 11111;
+        assert!(super::check(Network::Bitcoin, &off_checkpoint, None).is_err());
+        assert!(super::check(Network::Regtest, &off_checkpoint, None).is_ok());
+    }
+
+    #[test]
+    fn checkpoint() {
+        let hash = "000000002a936ca763904c3c35fce2f3556c559c0214345d31b1bcebf76acb70";
+        let hash = Sha256dHash::from_hex(hash).unwrap();
+        assert_eq!(super::checkpoint(Network::Testnet, 546), Some(hash));
+        assert_eq!(super::checkpoint(Network::Testnet, 547), None);
+        assert_eq!(super::checkpoint(Network::Regtest, 546), None);
+    }
+
+    #[test]
+    fn genesis() {
+        let regtest = genesis_block(Network::Regtest).bitcoin_hash();
+        super::check_genesis(Network::Regtest, regtest).unwrap();
+        assert!(super::check_genesis(Network::Bitcoin, regtest).is_err());
+    }
+
+    #[test]
+    fn merkle_proof() {
+        let txids: Vec<Sha256dHash> = (0..3u8).map(|i| Sha256dHash::hash(&[i])).collect();
+        let root = bitcoin_merkle_root(txids.clone());
+        let pair = |left: &Sha256dHash, right: &Sha256dHash| {
+            Sha256dHash::hash(&[&left[..], &right[..]].concat())
+        };
+
+        let branch = [txids[1], pair(&txids[2], &txids[2])];
+        assert_eq!(super::merkle_root(txids[0], &branch, 0), root);
+        assert_ne!(super::merkle_root(txids[0], &branch, 1), root);
+        // the last transaction of an odd level is paired with itself
+        let branch = [txids[2], pair(&txids[0], &txids[1])];
+        assert_eq!(super::merkle_root(txids[2], &branch, 2), root);
+    }
+}
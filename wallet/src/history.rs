@@ -0,0 +1,440 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Transaction history
+//!
+//! Transactions of the wallet and what became of them,
+//! exported for accounting as CSV or JSON by `WalletLibraryInterface::export_history`
+//!
+use bitcoin::{Script, Transaction};
+use bitcoin_hashes::sha256d::Hash as Sha256dHash;
+use serde::{Serialize, Deserialize};
+
+use std::{collections::HashMap, error::Error, io::Write, str::FromStr};
+
+use super::account::Utxo;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum TxStatus {
+    Unconfirmed,
+    /// height of the block the transaction is in
+    Confirmed(usize),
+    /// the transaction with this id spends some of the same coins and won
+    Conflicted(Sha256dHash),
+    /// the wallet cancelled the transaction by the one with this id, see `cancel_tx`
+    Replaced(Sha256dHash),
+}
+
+/// a transaction spending coins of the wallet or paying to it
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TxRecord {
+    pub tx: Transaction,
+    pub status: TxStatus,
+    /// coins of the wallet the transaction spends, they are given back if it is conflicted
+    pub spent: Vec<Utxo>,
+    /// fee the wallet paid, `None` unless all inputs are coins of the wallet
+    #[serde(default)]
+    pub fee: Option<u64>,
+}
+
+impl TxRecord {
+    pub fn txid(&self) -> Sha256dHash {
+        self.tx.txid()
+    }
+
+    /// the transaction can't be confirmed anymore, unless the block decides otherwise
+    pub fn is_conflicted(&self) -> bool {
+        match self.status {
+            TxStatus::Conflicted(_) | TxStatus::Replaced(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// fees the wallet paid, see `WalletLibraryInterface::fee_stats`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FeeStats {
+    /// fees of the confirmed transactions
+    pub confirmed: u64,
+    /// fees of the transactions waiting for a block
+    pub unconfirmed: u64,
+    /// number of transactions counted, ones funded along with others are not
+    pub tx_count: usize,
+}
+
+impl FeeStats {
+    /// add up the fees of `records`, the conflicted ones paid nothing
+    pub fn of<'a, I>(records: I) -> Self
+    where
+        I: IntoIterator<Item = &'a TxRecord>,
+    {
+        let mut stats = FeeStats::default();
+        for record in records {
+            let fee = match record.fee {
+                Some(fee) => fee,
+                None => continue,
+            };
+            match record.status {
+                TxStatus::Confirmed(_) => stats.confirmed += fee,
+                TxStatus::Unconfirmed => stats.unconfirmed += fee,
+                TxStatus::Conflicted(_) | TxStatus::Replaced(_) => continue,
+            }
+            stats.tx_count += 1;
+        }
+        stats
+    }
+}
+
+/// what an address of the wallet, or all of an account, received and sent over its life,
+/// see `WalletLibraryInterface::address_stats`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct AddressStats {
+    /// value of the outputs paying to the address
+    pub received: u64,
+    /// value of the coins of the address spent, change included
+    pub sent: u64,
+    /// transactions paying to the address, more than one means it was reused
+    #[serde(default)]
+    pub receipts: u32,
+}
+
+impl AddressStats {
+    /// what `record` adds to the totals of the scripts it pays to or spends from,
+    /// `is_ours` tells the scripts of the wallet apart
+    pub fn of_tx<F>(record: &TxRecord, is_ours: F) -> HashMap<Script, AddressStats>
+    where
+        F: Fn(&Script) -> bool,
+    {
+        let mut stats: HashMap<Script, AddressStats> = HashMap::new();
+        for output in record.tx.output.iter().filter(|output| is_ours(&output.script_pubkey)) {
+            stats.entry(output.script_pubkey.clone()).or_default().received += output.value;
+        }
+        for script_stats in stats.values_mut() {
+            script_stats.receipts = 1;
+        }
+        for utxo in &record.spent {
+            stats.entry(utxo.pk_script.clone()).or_default().sent += utxo.value;
+        }
+        stats
+    }
+
+    pub fn add(&mut self, other: &AddressStats) {
+        self.received += other.received;
+        self.sent += other.sent;
+        self.receipts += other.receipts;
+    }
+
+    pub fn subtract(&mut self, other: &AddressStats) {
+        self.received = self.received.saturating_sub(other.received);
+        self.sent = self.sent.saturating_sub(other.sent);
+        self.receipts = self.receipts.saturating_sub(other.receipts);
+    }
+}
+
+/// fee of `tx` given the values of the inputs which are coins of the wallet,
+/// known only if every input is one
+pub fn fee_paid(tx: &Transaction, input_values: &[u64]) -> Option<u64> {
+    if input_values.is_empty() || input_values.len() != tx.input.len() {
+        return None;
+    }
+    let input_value: u64 = input_values.iter().sum();
+    let output_value: u64 = tx.output.iter().map(|output| output.value).sum();
+    Some(input_value.saturating_sub(output_value))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    /// a header line and a line per transaction
+    Csv,
+    /// an array of objects with the fields of `HistoryEntry`
+    Json,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            _ => Err(format!("unknown export format {}", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Incoming,
+    Outgoing,
+}
+
+impl Direction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Direction::Incoming => "incoming",
+            Direction::Outgoing => "outgoing",
+        }
+    }
+}
+
+/// a transaction as an accountant sees it
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    /// UTC time of the block, e.g. 2019-06-01T12:00:00Z, empty while unconfirmed
+    pub date: String,
+    pub txid: String,
+    pub direction: Direction,
+    /// satoshi received, or paid to others, the fee not included
+    pub amount: u64,
+    /// satoshi, 0 unless the wallet paid it alone
+    pub fee: u64,
+    /// label of a watched script the transaction pays to
+    pub label: String,
+    /// the address paid, the first one if there are more
+    pub address: String,
+}
+
+/// write `entries` in `format`
+pub fn write_history(
+    entries: &[HistoryEntry],
+    format: ExportFormat,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    match format {
+        ExportFormat::Json => serde_json::to_writer_pretty(&mut *out, entries)?,
+        ExportFormat::Csv => {
+            writeln!(out, "date,txid,direction,amount,fee,label,address")?;
+            for entry in entries {
+                writeln!(
+                    out,
+                    "{},{},{},{},{},{},{}",
+                    entry.date,
+                    entry.txid,
+                    entry.direction.as_str(),
+                    entry.amount,
+                    entry.fee,
+                    csv_field(&entry.label),
+                    entry.address,
+                )?;
+            }
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+// labels are chosen by the user, they may contain anything
+fn csv_field(field: &str) -> String {
+    if field.contains(|c: char| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// ISO 8601 date of the unix time `time` in UTC
+pub fn format_date(time: u64) -> String {
+    let days = time / 86400;
+    let seconds = time % 86400;
+    // civil from days, http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::{OutPoint, network::constants::Network};
+
+    use crate::walletlibrary::{WalletConfig, SendOptions};
+    use crate::memory::test::{new_wallet, payment};
+    use crate::account::AccountAddressType;
+    use crate::address;
+    use crate::interface::Wallet;
+    use super::{
+        HistoryEntry, Direction, ExportFormat, FeeStats, AddressStats, write_history, format_date,
+    };
+
+    #[test]
+    fn date() {
+        assert_eq!(format_date(0), "1970-01-01T00:00:00Z");
+        // the genesis block
+        assert_eq!(format_date(1_231_006_505), "2009-01-03T18:15:05Z");
+        assert_eq!(format_date(951_782_400), "2000-02-29T00:00:00Z");
+    }
+
+    #[test]
+    fn export() {
+        let entries = vec![HistoryEntry {
+            date: format_date(1_231_006_505),
+            txid: "ab".to_owned(),
+            direction: Direction::Outgoing,
+            amount: 50_000_000,
+            fee: 1_000,
+            label: "rent, \"june\"".to_owned(),
+            address: "bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49".to_owned(),
+        }];
+
+        let mut csv = Vec::new();
+        write_history(&entries, ExportFormat::Csv, &mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "date,txid,direction,amount,fee,label,address\n\
+             2009-01-03T18:15:05Z,ab,outgoing,50000000,1000,\"rent, \"\"june\"\"\",\
+             bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49\n",
+        );
+
+        let mut json = Vec::new();
+        write_history(&entries, ExportFormat::Json, &mut json).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(json[0]["direction"], "outgoing");
+        assert_eq!(json[0]["fee"], 1_000);
+    }
+
+    #[test]
+    fn fee_accounting() {
+        let (mut wallet, chain) = new_wallet(WalletConfig::in_memory());
+        let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        chain.push_block(Vec::new());
+        chain.push_block(vec![payment(&addr, 100_000_000)]);
+        wallet.sync_with_tip().unwrap();
+        // received coins cost the wallet nothing
+        assert_eq!(wallet.wallet_lib().fee_stats(), FeeStats::default());
+
+        let dest_addr = "bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49".to_owned();
+        let sent = wallet
+            .send_coins(dest_addr, 50_000_000, true, SendOptions::default())
+            .unwrap()
+            .sent()
+            .unwrap();
+        let tx = sent.tx.clone();
+        let output_value: u64 = tx.output.iter().map(|output| output.value).sum();
+        let fee = 100_000_000 - output_value;
+        assert!(fee > 0);
+        assert_eq!(sent.fee, fee);
+        let inputs: Vec<OutPoint> = tx.input.iter().map(|input| input.previous_output).collect();
+        assert_eq!(sent.selected_inputs, inputs);
+        let stats = wallet.wallet_lib().fee_stats();
+        assert_eq!((stats.confirmed, stats.unconfirmed, stats.tx_count), (0, fee, 1));
+
+        chain.mine();
+        wallet.sync_with_tip().unwrap();
+        let stats = wallet.wallet_lib().fee_stats();
+        assert_eq!((stats.confirmed, stats.unconfirmed, stats.tx_count), (fee, 0, 1));
+        let record = wallet
+            .wallet_lib()
+            .get_tx_history()
+            .into_iter()
+            .find(|record| record.txid() == tx.txid())
+            .unwrap();
+        assert_eq!(record.fee, Some(fee));
+        // the change is a coin of the wallet
+        let change = sent.change_outpoint.unwrap();
+        let utxos = wallet.wallet_lib().get_utxo_list();
+        let change_utxo = utxos.iter().find(|utxo| utxo.out_point == change).unwrap();
+        assert_eq!(change_utxo.value, 50_000_000 - fee);
+    }
+
+    #[test]
+    fn export_history() {
+        let (mut wallet, chain) = new_wallet(WalletConfig::in_memory());
+        let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        chain.push_block(Vec::new());
+        chain.push_block(vec![payment(&addr, 100_000_000)]);
+        wallet.sync_with_tip().unwrap();
+        let dest_addr = "bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49".to_owned();
+        let tx = wallet
+            .send_coins(dest_addr.clone(), 50_000_000, true, SendOptions::default())
+            .unwrap()
+            .sent()
+            .unwrap()
+            .tx;
+
+        let mut csv = Vec::new();
+        wallet.wallet_lib().export_history(ExportFormat::Csv, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<Vec<&str>> = csv.lines().map(|line| line.split(',').collect()).collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1][2..4], ["incoming", "100000000"]);
+        assert_eq!(lines[1][6], addr);
+        // unconfirmed, so it has no date yet
+        let txid = tx.txid().to_string();
+        assert_eq!(lines[2][..4], ["", txid.as_str(), "outgoing", "50000000"]);
+        assert_eq!(lines[2][6], dest_addr);
+        assert!(lines[1][0].ends_with('Z'));
+    }
+
+    #[test]
+    fn address_totals() {
+        let (mut wallet, chain) = new_wallet(WalletConfig::in_memory());
+        let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        chain.push_block(Vec::new());
+        chain.push_block(vec![payment(&addr, 100_000_000)]);
+        wallet.sync_with_tip().unwrap();
+        let received = AddressStats {
+            received: 100_000_000,
+            sent: 0,
+            receipts: 1,
+        };
+        let stats = wallet.wallet_lib().address_stats();
+        assert_eq!(stats, vec![(addr.clone(), AccountAddressType::P2WKH, received)]);
+
+        let dest_addr = "bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49".to_owned();
+        let dest_script = address::script_pubkey(&dest_addr, Network::Regtest).unwrap();
+        let tx = wallet
+            .send_coins(dest_addr, 50_000_000, true, SendOptions::default())
+            .unwrap()
+            .sent()
+            .unwrap()
+            .tx;
+        let change: u64 = tx
+            .output
+            .iter()
+            .filter(|output| output.script_pubkey != dest_script)
+            .map(|output| output.value)
+            .sum();
+
+        let stats = wallet.wallet_lib().address_stats();
+        let funded = stats.iter().find(|(a, _, _)| *a == addr).unwrap();
+        assert_eq!(funded.2.sent, 100_000_000);
+        // the change may go to another account
+        let mut total = AddressStats::default();
+        for addr_type in &[
+            AccountAddressType::P2PKH,
+            AccountAddressType::P2SHWH,
+            AccountAddressType::P2WKH,
+        ] {
+            total.add(&wallet.wallet_lib().account_stats(addr_type.clone()));
+        }
+        assert_eq!(total.received, 100_000_000 + change);
+        assert_eq!(total.sent, 100_000_000);
+    }
+}
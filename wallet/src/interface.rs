@@ -13,36 +13,276 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 use bitcoin::{
-    Block, Transaction, OutPoint,
+    Block, BlockHeader, Transaction, TxOut, OutPoint, PublicKey, PrivateKey,
+    network::constants::Network,
+    util::{bip32::ExtendedPubKey, psbt::PartiallySignedTransaction},
 };
 use bitcoin_hashes::sha256d::Hash as Sha256dHash;
-use super::account::{Account, AccountAddressType, Utxo};
-use super::walletlibrary::LockId;
+use super::account::{AccountAddressType, AddressInfo, DerivationMode, Utxo};
+use super::walletlibrary::{
+    LockId, InputFilter, DecryptConfig, KeyGenConfig, ImportedUtxo, PreparedTx, ChannelFunding,
+    WatchedUtxo, SilentPaymentUtxo, SpendingPolicy, PendingSpend, SpentUtxo, SendOutcome,
+    InputSpec, TimelockedUtxo, ScriptUtxo, ScriptSpend, UnsignedTx, IdempotentSend, VaultUtxo,
+    StateIssue, SendOptions, AccountGuard, WalletState,
+};
+use super::timelock::Timelock;
+use super::vault::Vault;
+use bitcoin::Script;
+use super::mnemonic::Mnemonic;
+use super::history::{TxRecord, FeeStats, ExportFormat, AddressStats};
+use super::price::PriceSource;
+use super::audit::AuditEntry;
+use super::headers::HeaderRecord;
+use super::error::WalletError;
+use super::events::WalletEvent;
+use serde::{Serialize, Deserialize};
+#[cfg(feature = "bitcoind")]
 use bitcoin_rpc_client::{Client as BitcoinClient, RpcApi, Error as BitcoinClientError};
+#[cfg(feature = "bitcoind")]
+use bitcoin_rpc_client::jsonrpc::Error as JsonRpcError;
+#[cfg(feature = "bitcoind")]
+use super::retry::is_transient_io;
+
+use std::{
+    collections::HashMap, error::Error, io::Write, net::SocketAddr, path::Path,
+    sync::{mpsc::Receiver, Arc, atomic::{AtomicBool, Ordering}},
+};
+
+/// reported by `Wallet::sync_with_tip_progress` as the blocks or transactions are processed
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyncProgress {
+    /// height of the last block processed
+    pub height_scanned: usize,
+    /// height the sync goes up to
+    pub tip_height: usize,
+    /// coins received since the sync started
+    pub utxos_found: usize,
+}
+
+/// blocks downloaded for `Wallet::sync_fetched`
+#[derive(Clone, Debug)]
+pub struct FetchedBlocks {
+    /// height of the tip when they were fetched
+    pub tip: usize,
+    /// each block with its height and the outputs its taproot inputs spend, see `bip352`
+    pub blocks: Vec<(usize, Block, HashMap<OutPoint, TxOut>)>,
+}
+
+/// downloads a batch of blocks without the wallet, see `Wallet::block_fetch`
+pub type BlockFetch = Box<dyn FnOnce() -> Result<FetchedBlocks, Box<dyn Error>> + Send>;
+
+/// stops a sync or a rescan after the block it is processing, see `Wallet::sync_cancellable`.
+/// The clones of a token share it, any of them cancels the operation
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// kind of backend a wallet syncs with
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum BackendKind {
+    /// bitcoind, or another `BlockChainIO` the wallet takes the blocks of on trust
+    FullNode,
+    Electrum,
+}
 
-use std::error::Error;
+/// reported by `Wallet::connection_status`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConnectionStatus {
+    pub backend: BackendKind,
+    /// address of the backend, empty if it has none, e.g. a `wallet::memory::MemoryChain`
+    pub endpoint: String,
+    /// unix time of the last call the backend answered, `None` if none did yet
+    pub last_success: Option<u64>,
+}
+
+/// backend `Wallet::reconnect` switches to
+#[derive(Clone, Debug, PartialEq)]
+pub struct BackendConfig {
+    pub address: SocketAddr,
+    /// rpc credentials of bitcoind, the ones of the current backend are kept if `None`,
+    /// electrum servers take none
+    pub user: Option<String>,
+    pub password: Option<String>,
+}
 
 pub trait Wallet {
     fn wallet_lib(&self) -> &Box<dyn WalletLibraryInterface + Send>;
     fn wallet_lib_mut(&mut self) -> &mut Box<dyn WalletLibraryInterface + Send>;
-    fn reconnect(&mut self);
+    /// connect to the backend again, e.g. after it was restarted, or to the one of `config`
+    /// from now on. Fails while it is unreachable, the wallet keeps the backend it had then
+    fn reconnect(&mut self, config: Option<BackendConfig>) -> Result<(), Box<dyn Error>>;
+    /// the backend the wallet syncs with and when it answered last
+    fn connection_status(&self) -> ConnectionStatus;
+    /// leave the backend for one of another kind, see `context::GlobalContext::switch_backend`
+    fn into_wallet_lib(self: Box<Self>) -> Box<dyn WalletLibraryInterface + Send>;
     fn send_coins(
         &mut self,
         addr_str: String,
         amt: u64,
         submit: bool,
-        lock_coins: bool,
-        witness_only: bool,
-    ) -> Result<(Transaction, LockId), Box<dyn Error>>;
+        options: SendOptions,
+    ) -> Result<SendOutcome, Box<dyn Error>>;
     fn make_tx(
         &mut self,
         ops: Vec<OutPoint>,
         addr_str: String,
         amt: u64,
         submit: bool,
+        change_addr_type: Option<AccountAddressType>,
     ) -> Result<Transaction, Box<dyn Error>>;
+    /// `make_tx` with the sequences and sighash types of `WalletLibraryInterface::make_tx_advanced`
+    fn make_tx_advanced(
+        &mut self,
+        inputs: Vec<InputSpec>,
+        addr_str: String,
+        amt: u64,
+        submit: bool,
+        change_addr_type: Option<AccountAddressType>,
+    ) -> Result<Transaction, Box<dyn Error>>;
+    /// broadcast the transaction, it is rebroadcast on every sync until it is confirmed
     fn publish_tx(&mut self, tx: &Transaction) -> Result<(), Box<dyn Error>>;
-    fn sync_with_tip(&mut self) -> Result<(), Box<dyn Error>>;
+    /// sign and publish a transaction made by `prepare_tx`
+    fn commit_tx(&mut self, prepared_id: u64) -> Result<Transaction, Box<dyn Error>>;
+    /// sign and publish a channel funding made by `fund_psbt_for_channel`
+    fn publish_funding(&mut self, funding_id: LockId) -> Result<Transaction, Box<dyn Error>>;
+    /// cancel the unconfirmed transaction `txid` by publishing a replacement
+    /// paying its coins back to the wallet
+    fn cancel_tx(
+        &mut self,
+        txid: &Sha256dHash,
+        fee_rate: u64,
+    ) -> Result<Transaction, Box<dyn Error>>;
+    /// sign and publish a child pulling the unconfirmed transaction `txid` into a block
+    fn accelerate(
+        &mut self,
+        txid: &Sha256dHash,
+        fee_rate: u64,
+    ) -> Result<Transaction, Box<dyn Error>>;
+    /// sign and publish a notification transaction made by `notify_payment_code`
+    fn notify_payment_code(&mut self, recipient: &str) -> Result<Transaction, Box<dyn Error>>;
+    /// sign and publish a transaction made by `sweep_payment_codes`
+    fn sweep_payment_codes(
+        &mut self,
+        dest_addr_type: AccountAddressType,
+    ) -> Result<Transaction, Box<dyn Error>>;
+    /// sign and publish a payment waiting for approval, see `WalletLibraryInterface::approve_spend`
+    fn approve_spend(&mut self, id: u64) -> Result<Transaction, Box<dyn Error>>;
+    /// finalize the queued payment `id` with the signatures of `signed` and publish it,
+    /// see `WalletLibraryInterface::finalize_unsigned_tx`
+    fn broadcast_signed_psbt(
+        &mut self,
+        id: u64,
+        signed: &PartiallySignedTransaction,
+    ) -> Result<Transaction, Box<dyn Error>>;
+    /// sign and publish a transaction made by `sweep_silent_payments`
+    fn sweep_silent_payments(
+        &mut self,
+        dest_addr_type: AccountAddressType,
+    ) -> Result<Transaction, Box<dyn Error>>;
+    /// publish a transaction made by `WalletLibraryInterface::sweep_address`
+    fn sweep_address(
+        &mut self,
+        addr_str: &str,
+        dest_addr: String,
+        fee_rate: u64,
+    ) -> Result<Transaction, Box<dyn Error>>;
+    /// publish a transaction made by `WalletLibraryInterface::unvault`
+    fn unvault(&mut self, dest_addr: String, fee_rate: u64) -> Result<Transaction, Box<dyn Error>>;
+    /// see `WalletLibraryInterface::spend_script_coins`, the transaction is published
+    /// if the wallet alone satisfied the policy
+    fn spend_script_coins(
+        &mut self,
+        name: &str,
+        dest_addr: String,
+        fee_rate: u64,
+    ) -> Result<ScriptSpend, Box<dyn Error>>;
+    /// publish the transaction of a PSBT the cosigners signed,
+    /// see `WalletLibraryInterface::finalize_script_psbt`
+    fn finalize_script_psbt(
+        &mut self,
+        psbt: PartiallySignedTransaction,
+    ) -> Result<Transaction, Box<dyn Error>>;
+    fn sync_with_tip(&mut self) -> Result<(), Box<dyn Error>> {
+        self.sync_with_tip_progress(&mut |_| ())
+    }
+    fn sync_with_tip_progress(
+        &mut self,
+        progress: &mut dyn FnMut(SyncProgress),
+    ) -> Result<(), Box<dyn Error>>;
+    /// sync at most `max_blocks` blocks, true once the wallet reached the tip. A wallet shared
+    /// between threads is unlocked between the calls, so other operations don't wait for
+    /// a long sync
+    fn sync_with_tip_batch(
+        &mut self,
+        max_blocks: usize,
+        progress: &mut dyn FnMut(SyncProgress),
+    ) -> Result<bool, Box<dyn Error>>;
+    /// the download of the next `max_blocks` blocks, it doesn't need the wallet: a wallet
+    /// shared between threads stays unlocked while they download, then `sync_fetched`
+    /// processes them. `None` if the backend doesn't scan blocks, `sync_with_tip_batch`
+    /// syncs it
+    fn block_fetch(&mut self, max_blocks: usize) -> Option<BlockFetch> {
+        let _ = max_blocks;
+        None
+    }
+    /// process the blocks of a `block_fetch`, true once the wallet reached the tip. A failed
+    /// download ends the sync, the next one starts over from the last block processed
+    fn sync_fetched(
+        &mut self,
+        fetched: Result<FetchedBlocks, Box<dyn Error>>,
+        progress: &mut dyn FnMut(SyncProgress),
+    ) -> Result<bool, Box<dyn Error>> {
+        let _ = (fetched, progress);
+        Err(From::from("this backend doesn't scan blocks"))
+    }
+    /// sync block by block until the tip or until `cancel` is cancelled, which fails with
+    /// `WalletError::Cancelled`. The height reached is stored after every block,
+    /// the next sync goes on from it
+    fn sync_cancellable(
+        &mut self,
+        cancel: &CancelToken,
+        progress: &mut dyn FnMut(SyncProgress),
+    ) -> Result<(), Box<dyn Error>> {
+        while !cancel.is_cancelled() {
+            if self.sync_with_tip_batch(1, progress)? {
+                return Ok(());
+            }
+        }
+        Err(WalletError::Cancelled.into())
+    }
+    /// forget the blocks from `height` on, the next sync scans them again.
+    /// Backends finding the transactions of the wallet by its scripts can't rescan
+    fn rescan_from(&mut self, height: usize) -> Result<(), Box<dyn Error>> {
+        let _ = height;
+        Err(From::from("this backend can't rescan blocks"))
+    }
+    /// sweep all coins to a freshly generated seed and publish the sweep transaction
+    fn rotate_seed(
+        &mut self,
+        key_gen_cfg: KeyGenConfig,
+    ) -> Result<(Transaction, Mnemonic), Box<dyn Error>>;
+    /// publish the sweep of a legacy wallet to its accounts at the standard paths
+    fn migrate_derivation(&mut self) -> Result<Transaction, Box<dyn Error>>;
+    /// import a WIF encoded key, look for its coins and publish a transaction
+    /// moving them to a new address of `dest_addr_type`
+    fn sweep_wif(
+        &mut self,
+        wif: &str,
+        dest_addr_type: AccountAddressType,
+    ) -> Result<Transaction, Box<dyn Error>>;
 }
 
 pub trait WalletLibraryInterface {
@@ -53,26 +293,365 @@ pub trait WalletLibraryInterface {
     ) -> Result<String, Box<dyn Error>>;
     fn get_utxo_list(&self) -> Vec<Utxo>;
     fn wallet_balance(&self) -> u64;
+    /// handle on the keys, the coins and the history, it reads them without waiting
+    /// for the wallet, e.g. while it processes the blocks of a sync
+    fn state(&self) -> WalletState;
     fn unlock_coins(&mut self, lock_id: LockId);
+    /// exclude the coin from coin selection until it is unfrozen,
+    /// it still can be spent by passing it to `make_tx` explicitly
+    fn freeze_utxo(&mut self, op: OutPoint) -> Result<(), Box<dyn Error>>;
+    fn unfreeze_utxo(&mut self, op: OutPoint) -> Result<(), Box<dyn Error>>;
+    fn get_frozen_list(&self) -> Vec<OutPoint>;
+    /// pay `amt` to `addr_str` the way `options` tells, see `SendOptions`.
+    /// A payment above the approval threshold isn't signed, it waits for `approve_spend`
     fn send_coins(
         &mut self,
         addr_str: String,
         amt: u64,
-        lock_coins: bool,
-        witness_only: bool,
-    ) -> Result<(Transaction, LockId), Box<dyn Error>>;
+        options: SendOptions,
+    ) -> Result<SendOutcome, Box<dyn Error>>;
+    /// the change goes to a new address of `change_addr_type`,
+    /// of the account set in `WalletConfig` if not specified
     fn make_tx(
         &mut self,
         ops: Vec<OutPoint>,
         addr_str: String,
         amt: u64,
+        change_addr_type: Option<AccountAddressType>,
     ) -> Result<Transaction, Box<dyn Error>>;
-    fn get_account_mut(&mut self, address_type: AccountAddressType) -> &mut Account;
+    /// `make_tx` setting the sequence of each input and signing it with the sighash type
+    /// given along with its coin, e.g. SIGHASH_SINGLE|ANYONECANPAY for an input others may
+    /// add to. Every signature is checked before the transaction is returned
+    fn make_tx_advanced(
+        &mut self,
+        inputs: Vec<InputSpec>,
+        addr_str: String,
+        amt: u64,
+        change_addr_type: Option<AccountAddressType>,
+    ) -> Result<Transaction, Box<dyn Error>>;
+    /// select coins and build a transaction like `send_coins` without signing it,
+    /// returns an id to sign it with by `sign_prepared_tx`
+    fn prepare_tx(
+        &mut self,
+        addr_str: String,
+        amt: u64,
+        input_filter: InputFilter,
+        change_addr_type: Option<AccountAddressType>,
+        privacy: Option<bool>,
+    ) -> Result<(u64, PreparedTx), Box<dyn Error>>;
+    fn sign_prepared_tx(&mut self, prepared_id: u64) -> Result<Transaction, Box<dyn Error>>;
+    /// build a transaction paying `amt` to the P2WSH of `witness_script`, e.g. the 2-of-2 script
+    /// of a Lightning channel, with the fee of `fee_rate` satoshi per virtual byte,
+    /// its coins are locked until `release_funding` or `sign_funding`
+    fn fund_psbt_for_channel(
+        &mut self,
+        witness_script: &Script,
+        amt: u64,
+        fee_rate: u64,
+    ) -> Result<(LockId, ChannelFunding), Box<dyn Error>>;
+    /// forget the channel funding and unlock its coins
+    fn release_funding(&mut self, funding_id: LockId) -> Result<(), Box<dyn Error>>;
+    fn sign_funding(&mut self, funding_id: LockId) -> Result<Transaction, Box<dyn Error>>;
+    /// sign a transaction spending the coins of the unconfirmed transaction `txid` back to
+    /// the wallet with `fee_rate` satoshi per virtual byte, the original is marked as replaced
+    fn cancel_tx(
+        &mut self,
+        txid: &Sha256dHash,
+        fee_rate: u64,
+    ) -> Result<Transaction, Box<dyn Error>>;
+    /// sign a child spending the outputs of the wallet in the unconfirmed transaction `txid`
+    /// (child pays for parent), so that both together pay `fee_rate` satoshi per virtual byte
+    fn accelerate(
+        &mut self,
+        txid: &Sha256dHash,
+        fee_rate: u64,
+    ) -> Result<Transaction, Box<dyn Error>>;
+    fn get_account_mut(&mut self, address_type: AccountAddressType) -> AccountGuard;
+    /// output descriptors of the external and internal addresses of the accounts,
+    /// with the fingerprint of the master key and the path of each account key
+    fn descriptors(&self) -> Vec<String>;
+    /// request of Bitcoin Core's `importdescriptors` mirroring the accounts as a watch-only
+    /// wallet, rescanning from the creation of the seed
+    fn import_descriptors(&self) -> serde_json::Value;
+    /// keep a published transaction to rebroadcast it until it is confirmed,
+    /// a payment it makes counts against the daily limit from now on
+    fn add_unconfirmed_tx(&mut self, tx: &Transaction);
+    fn get_unconfirmed_txs(&self) -> Vec<Transaction>;
+    /// process a transaction found in a block,
+    /// the wallet transactions double spending it are marked as conflicted
+    fn process_confirmed_tx(&mut self, tx: &Transaction, block_height: usize);
+    fn get_tx_history(&self) -> Vec<TxRecord>;
+    /// whether `txid` is in the history, confirmed or not
+    fn knows_tx(&self, txid: &Sha256dHash) -> bool;
+    /// kind of backend the wallet was first opened with, `None` for a bare `WalletLibrary`
+    fn backend(&self) -> Option<BackendKind>;
+    /// stored by the `Wallet` opening the wallet, the first one is kept
+    fn record_backend(&mut self, backend: BackendKind);
+    /// coins of the wallet spent by transactions of the history,
+    /// the ones spent deep enough in the chain are pruned if the wallet is configured so
+    fn get_spent_utxos(&self) -> Vec<SpentUtxo>;
+    /// fees paid by the transactions of the history
+    fn fee_stats(&self) -> FeeStats;
+    /// what the addresses of the accounts received and sent, conflicted transactions left out,
+    /// by account and address; addresses which never saw a coin are not listed
+    fn address_stats(&self) -> Vec<(String, AccountAddressType, AddressStats)>;
+    /// what the addresses of an account received and sent together
+    fn account_stats(&self, addr_type: AccountAddressType) -> AddressStats;
+    /// whether the address of `script` was paid by more than one transaction, its coins are
+    /// spent together if the wallet avoids reuse
+    fn is_reused(&self, script: &Script) -> bool;
+    /// write the history to `out` for accounting, oldest first and the unconfirmed
+    /// transactions last, conflicted ones are left out as they never happened
+    fn export_history(
+        &self,
+        format: ExportFormat,
+        out: &mut dyn Write,
+    ) -> Result<(), Box<dyn Error>>;
+    /// what the transaction changed the balance by, negative if it spent more than it received
+    fn tx_value(&self, record: &TxRecord) -> i64;
+    /// price of a bitcoin in `currency` at the time of the block at `height`,
+    /// `source` is asked once and the price kept in the database,
+    /// `None` unless the wallet has the header of the block
+    fn confirmation_price(
+        &mut self,
+        source: &dyn PriceSource,
+        currency: &str,
+        height: usize,
+    ) -> Result<Option<f64>, Box<dyn Error>>;
+    /// receive the events of the wallet until the receiver is dropped
+    fn subscribe(&mut self) -> Receiver<WalletEvent>;
+    /// pass an event noticed outside of the wallet to its subscribers, e.g. of its backend
+    fn emit(&mut self, event: WalletEvent);
+    /// stop rebroadcasting the transaction
+    fn abandon_tx(&mut self, txid: &Sha256dHash) -> Result<(), Box<dyn Error>>;
     fn get_last_seen_block_height_from_memory(&self) -> usize;
     fn update_last_seen_block_height_in_memory(&mut self, block_height: usize);
     fn update_last_seen_block_height_in_db(&mut self, block_height: usize);
+    /// put the header of the block at `height` on top of the header chain,
+    /// fails if it doesn't follow the header below it or isn't the checkpoint at `height`
+    fn add_block_header(&mut self, height: usize, header: &BlockHeader) -> Result<(), WalletError>;
+    fn get_block_header(&self, height: usize) -> Option<HeaderRecord>;
+    /// the highest header of the header chain
+    fn get_header_tip(&self) -> Option<HeaderRecord>;
+    /// drop the headers above `height`, the blocks were replaced by a reorg
+    fn rewind_headers(&mut self, height: usize);
+    /// electrum status of each script hash as of the last sync of `ElectrumxWallet`,
+    /// the scripts whose status is unchanged have no new transactions
+    fn script_statuses(&self) -> HashMap<String, String>;
+    fn put_script_status(&mut self, script_hash: &str, status: &str);
+    /// height and hash of the tip of the server when the statuses were stored
+    fn script_status_tip(&self) -> Option<(usize, Sha256dHash)>;
+    fn put_script_status_tip(&mut self, height: usize, hash: &Sha256dHash);
+    /// forget the statuses, the next sync fetches the history of every script
+    fn clear_script_statuses(&mut self);
     fn get_full_address_list(&self) -> Vec<String>;
+    /// the earliest external address of the account which never received funds
+    fn get_unused_address(
+        &mut self,
+        address_type: AccountAddressType,
+    ) -> Result<String, Box<dyn Error>>;
+    fn get_address_list(&self) -> Vec<AddressInfo>;
+    /// label an address of the accounts, its coins are then selected by `InputFilter::Label`;
+    /// an empty label removes it
+    fn set_address_label(&mut self, addr: &str, label: &str) -> Result<(), Box<dyn Error>>;
+    /// process an unconfirmed transaction,
+    /// it is marked as conflicted if a wallet transaction already spends the same coins
     fn process_tx(&mut self, tx: &Transaction);
+    /// generate a new seed and sign a transaction sweeping all spendable coins to it,
+    /// the wallet switches to the new seed once the transaction is confirmed
+    fn rotate_seed(
+        &mut self,
+        key_gen_cfg: KeyGenConfig,
+    ) -> Result<(Transaction, Mnemonic), Box<dyn Error>>;
+    fn get_derivation_mode(&self) -> DerivationMode;
+    fn network(&self) -> Network;
+    /// sign a transaction sweeping the coins of a wallet of the legacy derivation to
+    /// the accounts of the same seed at the standard paths, where other wallets look for them.
+    /// The wallet derives by the standard once the transaction is confirmed
+    fn migrate_derivation(&mut self) -> Result<Transaction, Box<dyn Error>>;
+    /// watch the addresses of a key which doesn't belong to the wallet
+    fn import_key(&mut self, pk: &PublicKey);
+    fn get_imported_utxo_list(&self) -> Vec<ImportedUtxo>;
+    /// track the outputs of a script which doesn't belong to the wallet, e.g. a channel
+    /// funding output, they are reported by events and counted in `watched_balance` only
+    fn watch_script(&mut self, script_pubkey: Script, label: String);
+    fn get_watched_utxo_list(&self) -> Vec<WatchedUtxo>;
+    fn watched_balance(&self) -> u64;
+    /// BIP47 payment code of the wallet, it can be published instead of an address
+    fn payment_code(&self) -> String;
+    /// sign a notification transaction telling the payment code of the wallet to `recipient`,
+    /// it has to be published before paying to the addresses of `recipient`
+    fn notify_payment_code(&mut self, recipient: &str) -> Result<Transaction, Box<dyn Error>>;
+    /// next address paying to a notified payment code, the addresses are never reused
+    fn payment_code_address(&mut self, recipient: &str) -> Result<String, Box<dyn Error>>;
+    /// sign a transaction moving the coins received through payment codes to a new address
+    fn sweep_payment_codes(
+        &mut self,
+        dest_addr_type: AccountAddressType,
+    ) -> Result<Transaction, Box<dyn Error>>;
+    /// BIP352 silent payment address of the wallet, senders derive a new output from it
+    /// for every payment, the blocks are scanned for them. Fails unless the wallet was
+    /// configured to scan for silent payments
+    fn silent_payment_address(&self) -> Result<String, Box<dyn Error>>;
+    fn get_silent_payment_utxo_list(&self) -> Vec<SilentPaymentUtxo>;
+    /// outputs spent by the transactions processed next, a taproot input has its key
+    /// in the output it spends, see `bip352::spent_taproot_outputs`
+    fn set_spent_outputs(&mut self, outputs: HashMap<OutPoint, TxOut>);
+    /// sign a transaction moving the coins received by silent payments to a new address
+    fn sweep_silent_payments(
+        &mut self,
+        dest_addr_type: AccountAddressType,
+    ) -> Result<Transaction, Box<dyn Error>>;
+    /// P2WSH address of a new key of the timelock account, locked by `timelock`,
+    /// e.g. savings which can't be spent for 1000 blocks
+    fn new_timelocked_address(&mut self, timelock: Timelock) -> Result<String, Box<dyn Error>>;
+    /// outputs paid to the timelocked addresses, see `TimelockedUtxo::is_mature`
+    fn get_timelocked_utxo_list(&self) -> Vec<TimelockedUtxo>;
+    /// lock the deposits to the vault addresses created from now on by `vault`,
+    /// the addresses of the vault before keep theirs
+    fn set_vault(&mut self, vault: Vault) -> Result<(), Box<dyn Error>>;
+    fn get_vault(&self) -> Option<Vault>;
+    /// P2WSH address of a new key of the vault account, see `vault::Vault`
+    fn new_vault_address(&mut self) -> Result<String, Box<dyn Error>>;
+    /// deposits to the vault addresses, they aren't part of the balance
+    fn get_vault_utxo_list(&self) -> Vec<VaultUtxo>;
+    /// unvault the deposits confirmed for the delay of their vault to `dest_addr`
+    /// with the fee of `fee_rate` satoshi per virtual byte. The wallet remembers the
+    /// transaction, any other spend of a deposit emits `WalletEvent::UnexpectedUnvault`
+    fn unvault(&mut self, dest_addr: String, fee_rate: u64) -> Result<Transaction, Box<dyn Error>>;
+    /// extended key of the wallet for policies, the address of index i of a script account
+    /// has its child i, a watch-only wallet has none
+    fn script_account_xpub(&self) -> Result<ExtendedPubKey, Box<dyn Error>>;
+    /// `script_account_xpub` along with its origin, `[fingerprint/84'/coin'/2'/0]xpub...`,
+    /// the policies of cosigners carry it
+    fn script_account_key(&self) -> Result<String, Box<dyn Error>>;
+    /// add the script account `name` whose coins `policy` spends, see `policy::Policy`
+    fn register_policy(&mut self, name: &str, policy: &str) -> Result<(), Box<dyn Error>>;
+    /// P2WSH address of the next index of the script account `name`
+    fn new_script_address(&mut self, name: &str) -> Result<String, Box<dyn Error>>;
+    /// outputs paid to the addresses of the script accounts, they aren't part of the balance
+    fn get_script_utxo_list(&self) -> Vec<ScriptUtxo>;
+    /// move the coins of the script account `name` to `dest_addr` with the fee of `fee_rate`
+    /// satoshi per virtual byte, by the branches of its policy needing the fewest signatures
+    /// of others. The PSBT carries the signatures of the wallet for the cosigners,
+    /// coins whose branch is still timelocked stay
+    fn spend_script_coins(
+        &mut self,
+        name: &str,
+        dest_addr: String,
+        fee_rate: u64,
+    ) -> Result<ScriptSpend, Box<dyn Error>>;
+    /// a transaction spending script account coins with the partial signatures of `psbt`
+    fn finalize_script_psbt(
+        &self,
+        psbt: PartiallySignedTransaction,
+    ) -> Result<Transaction, Box<dyn Error>>;
+    /// sign a transaction moving all coins of the wallet address `addr_str` to `dest_addr`
+    /// with the fee of `fee_rate` satoshi per virtual byte, e.g. once the address was exposed.
+    /// Locked and frozen coins stay, a payment to another wallet is subject to the spending policy
+    fn sweep_address(
+        &mut self,
+        addr_str: &str,
+        dest_addr: String,
+        fee_rate: u64,
+    ) -> Result<Transaction, Box<dyn Error>>;
+    fn get_spending_policy(&self) -> SpendingPolicy;
+    /// replace the limits `send_coins` and `sign_prepared_tx` enforce,
+    /// payments signed already keep counting against the daily limit
+    fn set_spending_policy(&mut self, policy: SpendingPolicy) -> Result<(), Box<dyn Error>>;
+    /// satoshi paid within the last 24 hours, as counted by the daily limit
+    fn spent_in_window(&self) -> u64;
+    /// payments above the approval threshold which are neither approved, rejected nor expired,
+    /// `send_coins` returns `SendOutcome::ApprovalRequired` for them
+    fn get_pending_spends(&self) -> Vec<PendingSpend>;
+    /// select the coins of a pending payment and sign it, the spending policy is checked again
+    fn approve_spend(&mut self, id: u64) -> Result<Transaction, Box<dyn Error>>;
+    fn reject_spend(&mut self, id: u64) -> Result<(), Box<dyn Error>>;
+    /// name recorded in the audit log as the caller of the following operations
+    fn set_audit_caller(&mut self, caller: String);
+    /// append an entry to the audit log, `params` are space separated `name=value` pairs
+    fn audit(&mut self, operation: &str, params: String, txid: Option<Sha256dHash>);
+    /// up to `limit` entries of the audit log from the one at `start` on
+    fn get_audit_log(&self, start: u64, limit: usize) -> Vec<AuditEntry>;
+    /// BIP127 proof of reserves committing to `message`, it spends all confirmed coins
+    /// of the wallet and is checked by `reserves::verify` as of the last seen block
+    fn prove_reserves(&self, message: &str) -> Result<Transaction, Box<dyn Error>>;
+    /// sign a transaction moving all known coins of an imported key to a new address
+    fn sweep_imported(
+        &mut self,
+        sk: &PrivateKey,
+        dest_addr_type: AccountAddressType,
+    ) -> Result<Transaction, Box<dyn Error>>;
+    /// write an encrypted backup of the wallet to `path`
+    fn export_backup(&self, path: &Path, passphrase: &str) -> Result<(), Box<dyn Error>>;
+    /// write the coins, derived keys, history and tip of the wallet to `path`,
+    /// see `snapshot::StateSnapshot`
+    fn export_state_snapshot(&self, path: &Path) -> Result<(), Box<dyn Error>>;
+    /// replace what the syncs found with the snapshot at `path`, taken of a wallet of the
+    /// same seed, e.g. on another machine. The next sync goes on from the tip of the snapshot
+    fn import_state_snapshot(&mut self, path: &Path) -> Result<(), Box<dyn Error>>;
+    /// cross-check the stored state: the coins of the accounts pay to their keys, the keys,
+    /// scripts and addresses match the derivation and the lock groups hold coins
+    fn verify_state(&self) -> Vec<StateIssue>;
+    /// derive the missing keys, scripts and addresses again and drop the orphaned lock
+    /// groups, returns the issues `verify_state` finds afterwards
+    fn repair_state(&mut self) -> Result<Vec<StateIssue>, Box<dyn Error>>;
+    /// write a watch-only Electrum wallet file of each account into the directory `path`,
+    /// named by the address type, e.g. `p2wkh`, to cross-check the balance in Electrum
+    fn export_electrum_wallet(&self, path: &Path) -> Result<(), Box<dyn Error>>;
+    /// replace the wallet with the backup at `path`,
+    /// `decrypt_cfg` holds the secrets of the wallet the backup was made of
+    fn import_backup(
+        &mut self,
+        path: &Path,
+        passphrase: &str,
+        decrypt_cfg: DecryptConfig,
+    ) -> Result<(), Box<dyn Error>>;
+    /// whether the wallet follows the public keys of an offline signer,
+    /// everything needing a private key fails with `WalletError::WatchOnly`
+    fn is_watch_only(&self) -> bool;
+    /// wipe the master key and the keys of the accounts from memory, everything needing
+    /// a private key fails with `WalletError::Locked` until `unlock`
+    fn lock(&mut self) -> Result<(), Box<dyn Error>>;
+    /// decrypt the master key with the secrets the wallet was opened with,
+    /// it locks again `timeout` seconds later unless that is `None`
+    fn unlock(
+        &mut self,
+        decrypt_cfg: &DecryptConfig,
+        timeout: Option<u64>,
+    ) -> Result<(), Box<dyn Error>>;
+    /// whether the wallet waits for `unlock` to sign, its timeout passed counts as locked
+    fn is_locked(&self) -> bool;
+    /// wipe the keys of a wallet whose `unlock` timed out, a no-op otherwise
+    fn relock_expired(&mut self);
+    /// select coins and build a payment like `send_coins` for the offline signer to sign,
+    /// it waits in a queue with its coins locked. The PSBT names the key of each input
+    /// by the fingerprint of the master key and its path
+    fn create_unsigned_tx(
+        &mut self,
+        addr_str: String,
+        amt: u64,
+        input_filter: InputFilter,
+        change_addr_type: Option<AccountAddressType>,
+        privacy: Option<bool>,
+    ) -> Result<(UnsignedTx, PartiallySignedTransaction), Box<dyn Error>>;
+    fn get_unsigned_txs(&self) -> Vec<UnsignedTx>;
+    /// the payment made under the idempotency `key` less than `IDEMPOTENCY_TTL` seconds ago
+    fn get_idempotent_send(&self, key: &str) -> Option<IdempotentSend>;
+    /// remember a payment made under an idempotency key, the expired ones are forgotten
+    fn put_idempotent_send(&mut self, send: IdempotentSend);
+    /// the PSBT of the queued payment `id`, as `create_unsigned_tx` returned it
+    fn unsigned_psbt(&self, id: u64) -> Result<PartiallySignedTransaction, Box<dyn Error>>;
+    /// the queued payment `id` with the signatures of `signed`, which must be a PSBT
+    /// of the same transaction. The payment leaves the queue and its coins stay locked
+    /// until the transaction is processed
+    fn finalize_unsigned_tx(
+        &mut self,
+        id: u64,
+        signed: &PartiallySignedTransaction,
+    ) -> Result<Transaction, Box<dyn Error>>;
+    /// drop the queued payment `id` and unlock its coins
+    fn cancel_unsigned_tx(&mut self, id: u64) -> Result<(), Box<dyn Error>>;
 }
 
 pub trait BlockChainIO {
@@ -81,9 +660,28 @@ pub trait BlockChainIO {
     fn get_block_count(&self) -> Result<u32, Self::Error>;
     fn get_block_hash(&self, height: u32) -> Result<Sha256dHash, Self::Error>;
     fn get_block(&self, header_hash: &Sha256dHash) -> Result<Block, Self::Error>;
+    /// hex of the serialized block, it is cheaper for the node than the decoded one
+    fn get_raw_block(&self, header_hash: &Sha256dHash) -> Result<String, Self::Error>;
     fn send_raw_transaction(&self, tx: &Transaction) -> Result<Sha256dHash, Self::Error>;
+    fn get_raw_mempool(&self) -> Result<Vec<Sha256dHash>, Self::Error>;
+    fn get_raw_transaction(&self, txid: &Sha256dHash) -> Result<Transaction, Self::Error>;
+    /// outputs spent by the inputs of a block, None if the backend can't tell them
+    /// along with the block, they are fetched by transaction then
+    fn get_block_prevouts(
+        &self,
+        _header_hash: &Sha256dHash,
+    ) -> Result<Option<HashMap<OutPoint, TxOut>>, Self::Error> {
+        Ok(None)
+    }
+
+    /// whether the call failing with `err` may succeed if it is made again,
+    /// see `wallet::retry::Retrying`, errors are taken as permanent unless told otherwise
+    fn is_transient(_err: &Self::Error) -> bool {
+        false
+    }
 }
 
+#[cfg(feature = "bitcoind")]
 impl BlockChainIO for BitcoinClient {
     type Error = BitcoinClientError;
 
@@ -99,7 +697,111 @@ impl BlockChainIO for BitcoinClient {
         RpcApi::get_block(self, header_hash)
     }
 
+    fn get_raw_block(&self, header_hash: &Sha256dHash) -> Result<String, Self::Error> {
+        // verbosity 0
+        self.call("getblock", &[serde_json::to_value(header_hash)?, 0.into()])
+    }
+
     fn send_raw_transaction(&self, tx: &Transaction) -> Result<Sha256dHash, Self::Error> {
         RpcApi::send_raw_transaction(self, tx)
     }
+
+    fn get_raw_mempool(&self) -> Result<Vec<Sha256dHash>, Self::Error> {
+        RpcApi::get_raw_mempool(self)
+    }
+
+    fn get_raw_transaction(&self, txid: &Sha256dHash) -> Result<Transaction, Self::Error> {
+        RpcApi::get_raw_transaction(self, txid, None)
+    }
+
+    fn get_block_prevouts(
+        &self,
+        header_hash: &Sha256dHash,
+    ) -> Result<Option<HashMap<OutPoint, TxOut>>, Self::Error> {
+        // verbosity 3, since bitcoind 23.0, older nodes answer as for verbosity 2
+        let block: serde_json::Value =
+            self.call("getblock", &[serde_json::to_value(header_hash)?, 3.into()])?;
+        Ok(block_prevouts(&block))
+    }
+
+    // an error answer of the node is final, failing to get an answer is not
+    fn is_transient(err: &Self::Error) -> bool {
+        match err {
+            BitcoinClientError::JsonRpc(JsonRpcError::Rpc(_)) => false,
+            BitcoinClientError::JsonRpc(_) => true,
+            BitcoinClientError::Io(err) => is_transient_io(err),
+            _ => false,
+        }
+    }
+}
+
+// the prevouts of the inputs in a `getblock` answer of verbosity 3, None if an input
+// other than a coinbase has none
+#[cfg(feature = "bitcoind")]
+fn block_prevouts(block: &serde_json::Value) -> Option<HashMap<OutPoint, TxOut>> {
+    use bitcoin_hashes::hex::FromHex;
+
+    let mut prevouts = HashMap::new();
+    for tx in block["tx"].as_array()? {
+        for input in tx["vin"].as_array()? {
+            if input.get("coinbase").is_some() {
+                continue;
+            }
+            let out_point = OutPoint {
+                txid: Sha256dHash::from_hex(input["txid"].as_str()?).ok()?,
+                vout: input["vout"].as_u64()? as u32,
+            };
+            let prevout = &input["prevout"];
+            // in bitcoins
+            let value = (prevout["value"].as_f64()? * 100_000_000.0).round() as u64;
+            let script = hex::decode(prevout["scriptPubKey"]["hex"].as_str()?).ok()?;
+            let output = TxOut {
+                value,
+                script_pubkey: Script::from(script),
+            };
+            prevouts.insert(out_point, output);
+        }
+    }
+    Some(prevouts)
+}
+
+#[cfg(all(test, feature = "bitcoind"))]
+mod test {
+    use bitcoin::{TxOut, OutPoint, Script};
+    use bitcoin_hashes::{hex::FromHex, sha256d::Hash as Sha256dHash};
+
+    use super::block_prevouts;
+
+    #[test]
+    fn prevouts_of_block() {
+        let txid = "bd2e3c04b3e1c2e2a4d0f6f5d0f57a4c3b3d1f6c12c3c2c5f6e2a1b0c9d8e7f6";
+        let script = "5120a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c";
+        let answer = serde_json::json!({
+            "tx": [
+                {"vin": [{"coinbase": "0101", "sequence": 4294967295u64}]},
+                {"vin": [{
+                    "txid": txid,
+                    "vout": 1,
+                    "prevout": {"value": 0.29999, "scriptPubKey": {"hex": script}},
+                }]},
+            ],
+        });
+        let prevouts = block_prevouts(&answer).unwrap();
+        let out_point = OutPoint {
+            txid: Sha256dHash::from_hex(txid).unwrap(),
+            vout: 1,
+        };
+        let output = TxOut {
+            value: 29_999_000,
+            script_pubkey: Script::from(hex::decode(script).unwrap()),
+        };
+        assert_eq!(prevouts.len(), 1);
+        assert_eq!(prevouts[&out_point], output);
+
+        // a node older than 23.0 answers without the prevouts
+        let answer = serde_json::json!({
+            "tx": [{"vin": [{"txid": txid, "vout": 1}]}],
+        });
+        assert!(block_prevouts(&answer).is_none());
+    }
 }
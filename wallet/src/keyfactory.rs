@@ -20,8 +20,12 @@
 
 use bitcoin::network::constants::Network;
 use bitcoin::util::bip32::{ExtendedPubKey, ExtendedPrivKey, ChildNumber};
-use secp256k1::Secp256k1;
+use bitcoin::PrivateKey;
+use secp256k1::{Secp256k1, SecretKey};
 use rand::{rngs::OsRng, RngCore};
+use zeroize::{Zeroize, Zeroizing};
+
+use std::slice;
 
 use super::error::WalletError;
 use super::mnemonic::Mnemonic;
@@ -38,12 +42,20 @@ impl KeyFactory {
         salt: &str,
         debug: bool,
     ) -> Result<(ExtendedPrivKey, Mnemonic, Vec<u8>), WalletError> {
-        let mut encrypted = vec![0u8; entropy as usize];
+        let mut random = Zeroizing::new(vec![0u8; entropy as usize]);
         if let Ok(mut rng) = OsRng::new() {
             if !debug {
-                rng.fill_bytes(encrypted.as_mut_slice());
+                rng.fill_bytes(random.as_mut_slice());
             }
-            let mnemonic = Mnemonic::new(&encrypted, passphrase)?;
+            // random data filling whole AES blocks is taken as encrypted entropy,
+            // otherwise it is the entropy itself and gets encrypted
+            let (mnemonic, encrypted) = if random.len() % 16 == 0 {
+                (Mnemonic::new(&random, passphrase)?, random.to_vec())
+            } else {
+                let mnemonic = Mnemonic::from_entropy(&random)?;
+                let encrypted = mnemonic.restore(passphrase)?;
+                (mnemonic, encrypted)
+            };
             let seed = Seed::new(&mnemonic, salt);
             let key = KeyFactory::master_private_key(network, &seed)?;
             return Ok((key, mnemonic, encrypted));
@@ -105,15 +117,40 @@ impl KeyFactory {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum MasterKeyEntropy {
     Low = 16,
+    Medium = 24,
     Recommended = 32,
     Paranoid = 64,
 }
 
+impl MasterKeyEntropy {
+    /// entropy of a BIP39 mnemonic with the given number of words, `Paranoid` makes
+    /// 48 words no other wallet restores, so it is left out
+    pub fn from_word_count(word_count: usize) -> Option<Self> {
+        match word_count {
+            12 => Some(MasterKeyEntropy::Low),
+            18 => Some(MasterKeyEntropy::Medium),
+            24 => Some(MasterKeyEntropy::Recommended),
+            _ => None,
+        }
+    }
+
+    /// number of words in a mnemonic for this entropy
+    pub fn word_count(&self) -> usize {
+        *self as usize * 3 / 4
+    }
+}
+
 pub struct Seed(Vec<u8>);
 
+impl Drop for Seed {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
 #[cfg(test)]
 impl Seed {
     // return a copy of the seed data
@@ -129,14 +166,36 @@ impl Seed {
         use crypto::hmac::Hmac;
         use crypto::sha2::Sha512;
 
-        let mut mac = Hmac::new(Sha512::new(), mnemonic.to_string().as_bytes());
-        let mut output = [0u8; 64];
-        let msalt = "mnemonic".to_owned() + salt;
-        pbkdf2::pbkdf2(&mut mac, msalt.as_bytes(), 2048, &mut output);
+        let phrase = Zeroizing::new(mnemonic.to_string());
+        let mut mac = Hmac::new(Sha512::new(), phrase.as_bytes());
+        let mut output = Zeroizing::new([0u8; 64]);
+        let msalt = Zeroizing::new("mnemonic".to_owned() + salt);
+        pbkdf2::pbkdf2(&mut mac, msalt.as_bytes(), 2048, &mut *output);
         Seed(output.to_vec())
     }
 }
 
+/// overwrite the secret key and the chain code of an extended private key with zeroes,
+/// the key is left holding the secret key 1
+pub fn wipe_extended_private_key(key: &mut ExtendedPrivKey) {
+    wipe_private_key(&mut key.private_key);
+    let len = key.chain_code.len();
+    // any 32 bytes are a chain code
+    unsafe { slice::from_raw_parts_mut(key.chain_code.as_mut_ptr(), len) }.zeroize();
+}
+
+/// overwrite the bytes of a private key with zeroes, the key is left holding the secret key 1
+pub fn wipe_private_key(key: &mut PrivateKey) {
+    let len = key.key.len();
+    // only the byte array inside the key is zeroed, the other fields and the types around it
+    // keep their values
+    unsafe { slice::from_raw_parts_mut(key.key.as_mut_ptr(), len) }.zeroize();
+    // zero is no secret key, nothing may use the wiped key as one
+    let mut one = [0u8; 32];
+    one[31] = 1;
+    key.key = SecretKey::from_slice(&one).unwrap();
+}
+
 #[cfg(test)]
 mod test {
     use std::fs::File;
@@ -144,7 +203,7 @@ mod test {
     use std::io::Read;
     use bitcoin::network::constants::Network;
     use bitcoin::util::bip32::ChildNumber;
-    use crate::keyfactory::Seed;
+    use crate::keyfactory::{Seed, MasterKeyEntropy};
     use rustc_serialize::json::Json;
 
     #[test]
@@ -192,4 +251,44 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn mnemonic_word_count() {
+        for &word_count in &[12, 18, 24] {
+            let entropy = MasterKeyEntropy::from_word_count(word_count).unwrap();
+            assert_eq!(entropy.word_count(), word_count);
+
+            let (key, mnemonic, encrypted) = super::KeyFactory::new_master_private_key(
+                entropy,
+                Network::Bitcoin,
+                "passphrase",
+                "salt",
+                false,
+            )
+            .unwrap();
+            assert_eq!(mnemonic.to_string().split(' ').count(), word_count);
+
+            let (decrypted_key, decrypted_mnemonic) =
+                super::KeyFactory::decrypt(&encrypted, Network::Bitcoin, "passphrase", "salt")
+                    .unwrap();
+            assert_eq!(decrypted_mnemonic.to_string(), mnemonic.to_string());
+            assert_eq!(decrypted_key, key);
+        }
+        assert!(MasterKeyEntropy::from_word_count(15).is_none());
+        assert!(MasterKeyEntropy::from_word_count(48).is_none());
+    }
+
+    #[test]
+    fn wipe() {
+        let seed = Seed(vec![0x42; 32]);
+        let mut key = super::KeyFactory::master_private_key(Network::Testnet, &seed).unwrap();
+        super::wipe_extended_private_key(&mut key);
+        let mut one = [0u8; 32];
+        one[31] = 1;
+        assert_eq!(&key.private_key.key[..], &one[..]);
+        assert_eq!(&key.chain_code[..], &[0u8; 32][..]);
+        // the other fields keep valid values
+        assert_eq!(key.network, Network::Testnet);
+        assert_eq!(key.child_number, ChildNumber::Normal { index: 0 });
+    }
 }
@@ -15,14 +15,45 @@
 //
 
 pub mod mnemonic;
+pub mod slip39;
+pub mod backup;
+pub mod snapshot;
 pub mod error;
 pub mod keyfactory;
+pub mod bip47;
+pub mod taproot;
+pub mod sighash;
+pub mod timelock;
+pub mod vault;
+pub mod policy;
+pub mod signer;
+pub mod qr;
+pub mod bip352;
+pub mod address;
+pub mod reserves;
+pub mod audit;
+pub mod headers;
 pub mod walletlibrary;
 pub mod default;
+pub mod corewallet;
+#[cfg(feature = "electrum")]
 pub mod electrumx;
+#[cfg(all(feature = "esplora", target_arch = "wasm32"))]
+pub mod esplora;
 pub mod account;
+pub mod history;
+pub mod price;
+pub mod events;
 pub mod interface;
+pub mod retry;
+pub mod quorum;
+pub mod memory;
+#[cfg(all(feature = "bitcoind", feature = "electrum"))]
 pub mod context;
+#[cfg(feature = "process")]
+pub mod supervisor;
+#[cfg(feature = "testkit")]
+pub mod testkit;
 
 #[cfg(not(target_arch = "wasm32"))]
 mod db;
@@ -0,0 +1,251 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # In-memory blockchain
+//!
+//! A `BlockChainIO` without a node behind it, for unit tests and simulations.
+//! Blocks are made up on demand and nothing is validated, combined with
+//! `WalletConfig::in_memory()` the wallet runs without touching the disk
+//!
+//! ```ignore
+//! let chain = MemoryChain::new(Network::Regtest);
+//! let (mut wallet, _) = WalletWithTrustedFullNode::new(
+//!     WalletConfig::in_memory(),
+//!     chain.clone(),
+//!     WalletLibraryMode::Create(KeyGenConfig::debug()),
+//! )?;
+//! chain.push_block(vec![tx_paying_the_wallet]);
+//! wallet.sync_with_tip()?;
+//! ```
+//!
+use bitcoin::{Block, BlockHeader, BitcoinHash, Transaction, network::constants::Network};
+use bitcoin::blockdata::constants::genesis_block;
+use bitcoin::consensus::encode::serialize;
+use bitcoin::util::hash::bitcoin_merkle_root;
+use bitcoin_hashes::sha256d::Hash as Sha256dHash;
+
+use std::{io, sync::{Arc, Mutex}};
+
+use super::interface::BlockChainIO;
+use super::retry::is_transient_io;
+
+struct Chain {
+    blocks: Vec<Block>,
+    mempool: Vec<Transaction>,
+    refusing: bool,
+}
+
+/// chain starting at the genesis block of a network, clones share the same chain,
+/// so a test keeps one to mine blocks while the wallet owns another
+#[derive(Clone)]
+pub struct MemoryChain(Arc<Mutex<Chain>>);
+
+impl MemoryChain {
+    pub fn new(network: Network) -> Self {
+        MemoryChain(Arc::new(Mutex::new(Chain {
+            blocks: vec![genesis_block(network)],
+            mempool: Vec::new(),
+            refusing: false,
+        })))
+    }
+
+    /// height of the tip, 0 is the genesis block
+    pub fn height(&self) -> usize {
+        self.0.lock().unwrap().blocks.len() - 1
+    }
+
+    /// add a block of `txdata` on top of the tip, the transactions are not checked,
+    /// ones waiting in the mempool are removed from it
+    pub fn push_block(&self, txdata: Vec<Transaction>) -> Sha256dHash {
+        let mut chain = self.0.lock().unwrap();
+        let prev = chain.blocks.last().unwrap().header;
+        let txids: Vec<Sha256dHash> = txdata.iter().map(Transaction::txid).collect();
+        let header = BlockHeader {
+            version: 1,
+            prev_blockhash: prev.bitcoin_hash(),
+            merkle_root: if txids.is_empty() {
+                Default::default()
+            } else {
+                bitcoin_merkle_root(txids.clone())
+            },
+            time: prev.time + 600,
+            bits: prev.bits,
+            nonce: 0,
+        };
+        chain.mempool.retain(|tx| !txids.contains(&tx.txid()));
+        chain.blocks.push(Block { header, txdata });
+        header.bitcoin_hash()
+    }
+
+    /// mine the transactions of the mempool
+    pub fn mine(&self) -> Sha256dHash {
+        let txdata = self.0.lock().unwrap().mempool.clone();
+        self.push_block(txdata)
+    }
+
+    /// refuse every transaction sent from now on, like a node lying to the wallet
+    pub fn refuse_transactions(&self, refuse: bool) {
+        self.0.lock().unwrap().refusing = refuse;
+    }
+
+    /// drop the blocks above `height`, blocks pushed afterwards replace them as in a reorg
+    pub fn truncate(&self, height: usize) {
+        self.0.lock().unwrap().blocks.truncate(height + 1);
+    }
+
+    fn find_block(&self, header_hash: &Sha256dHash) -> Result<Block, io::Error> {
+        self.0
+            .lock()
+            .unwrap()
+            .blocks
+            .iter()
+            .find(|block| block.bitcoin_hash() == *header_hash)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such block"))
+    }
+}
+
+impl BlockChainIO for MemoryChain {
+    type Error = io::Error;
+
+    fn get_block_count(&self) -> Result<u32, Self::Error> {
+        Ok(self.height() as u32)
+    }
+
+    fn get_block_hash(&self, height: u32) -> Result<Sha256dHash, Self::Error> {
+        self.0
+            .lock()
+            .unwrap()
+            .blocks
+            .get(height as usize)
+            .map(Block::bitcoin_hash)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "height above the tip"))
+    }
+
+    fn get_block(&self, header_hash: &Sha256dHash) -> Result<Block, Self::Error> {
+        self.find_block(header_hash)
+    }
+
+    fn get_raw_block(&self, header_hash: &Sha256dHash) -> Result<String, Self::Error> {
+        self.find_block(header_hash).map(|block| hex::encode(serialize(&block)))
+    }
+
+    fn send_raw_transaction(&self, tx: &Transaction) -> Result<Sha256dHash, Self::Error> {
+        let mut chain = self.0.lock().unwrap();
+        if chain.refusing {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "transaction refused"));
+        }
+        let txid = tx.txid();
+        if !chain.mempool.iter().any(|pending| pending.txid() == txid) {
+            chain.mempool.push(tx.clone());
+        }
+        Ok(txid)
+    }
+
+    fn get_raw_mempool(&self) -> Result<Vec<Sha256dHash>, Self::Error> {
+        Ok(self.0.lock().unwrap().mempool.iter().map(Transaction::txid).collect())
+    }
+
+    fn get_raw_transaction(&self, txid: &Sha256dHash) -> Result<Transaction, Self::Error> {
+        let chain = self.0.lock().unwrap();
+        chain
+            .mempool
+            .iter()
+            .chain(chain.blocks.iter().flat_map(|block| block.txdata.iter()))
+            .find(|tx| tx.txid() == *txid)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such transaction"))
+    }
+
+    fn is_transient(err: &Self::Error) -> bool {
+        is_transient_io(err)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use bitcoin::{Transaction, TxIn, TxOut, OutPoint, Script, Address, network::constants::Network};
+    use bitcoin_hashes::{sha256d::Hash as Sha256dHash, Hash};
+
+    use std::str::FromStr;
+
+    use crate::walletlibrary::{WalletConfig, WalletLibraryMode, KeyGenConfig};
+    use crate::default::WalletWithTrustedFullNode;
+    use crate::account::AccountAddressType;
+    use crate::interface::{Wallet, BlockChainIO};
+    use super::MemoryChain;
+
+    /// a payment of `value` to `addr`, spending a coin no wallet has
+    pub fn payment(addr: &str, value: u64) -> Transaction {
+        Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output: OutPoint {
+                    txid: Sha256dHash::hash(addr.as_bytes()),
+                    vout: 0,
+                },
+                script_sig: Script::new(),
+                sequence: 0xFFFFFFFF,
+                witness: Vec::new(),
+            }],
+            output: vec![TxOut {
+                value,
+                script_pubkey: Address::from_str(addr).unwrap().script_pubkey(),
+            }],
+        }
+    }
+
+    /// a wallet of the debug seed and the empty regtest chain it syncs with
+    pub fn new_wallet(
+        config: WalletConfig,
+    ) -> (WalletWithTrustedFullNode<MemoryChain>, MemoryChain) {
+        let chain = MemoryChain::new(Network::Regtest);
+        let (wallet, _) = WalletWithTrustedFullNode::new(
+            config,
+            chain.clone(),
+            WalletLibraryMode::Create(KeyGenConfig::debug()),
+        )
+        .unwrap();
+        (wallet, chain)
+    }
+
+    #[test]
+    fn ephemeral_wallet() {
+        let (mut wallet, chain) = new_wallet(WalletConfig::in_memory());
+        let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+
+        // the wallet starts scanning above the first block
+        chain.push_block(Vec::new());
+        chain.send_raw_transaction(&payment(&addr, 100_000_000)).unwrap();
+        chain.mine();
+        assert_eq!(chain.get_raw_mempool().unwrap().len(), 0);
+
+        wallet.sync_with_tip().unwrap();
+        assert_eq!(wallet.wallet_lib().wallet_balance(), 100_000_000);
+
+        // another ephemeral wallet of the same seed starts from scratch
+        let (mut other, _) = WalletWithTrustedFullNode::new(
+            WalletConfig::in_memory(),
+            chain.clone(),
+            WalletLibraryMode::Create(KeyGenConfig::debug()),
+        )
+        .unwrap();
+        assert_eq!(other.wallet_lib().wallet_balance(), 0);
+        other.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        other.sync_with_tip().unwrap();
+        assert_eq!(other.wallet_lib().wallet_balance(), 100_000_000);
+    }
+}
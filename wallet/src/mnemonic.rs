@@ -18,14 +18,63 @@
 //! TREZOR compatible mnemonic in english
 //!
 use super::error::WalletError;
+use super::slip39;
 use crypto::sha2::Sha256;
 use crypto::digest::Digest;
 use crypto::aes;
 use crypto::blockmodes;
 use crypto::buffer;
+use zeroize::Zeroizing;
+
+use std::{error::Error, fmt, ptr, sync::atomic};
+
+/// what is wrong with the words of a mnemonic, see `Mnemonic::validate`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MnemonicError {
+    /// the number of words, it has to be a multiple of 3
+    InvalidLength(usize),
+    /// position of the first word not in the wordlist, counted from 0
+    UnknownWord(usize),
+    /// the words are known but the last one doesn't carry the checksum of the others
+    ChecksumNotMatch,
+}
+
+impl Error for MnemonicError {}
+
+impl fmt::Display for MnemonicError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &MnemonicError::InvalidLength(count) => {
+                write!(f, "mnemonic of {} words, the count must be divisible by 3", count)
+            }
+            &MnemonicError::UnknownWord(i) => write!(f, "word {} is not in the wordlist", i + 1),
+            &MnemonicError::ChecksumNotMatch => write!(f, "mnemonic checking bits not match"),
+        }
+    }
+}
+
+impl From<MnemonicError> for WalletError {
+    fn from(err: MnemonicError) -> WalletError {
+        match err {
+            MnemonicError::InvalidLength(_) => WalletError::InvalidMnemonicLength,
+            MnemonicError::UnknownWord(_) => WalletError::UnknownMnemonicWord,
+            MnemonicError::ChecksumNotMatch => WalletError::MnemonicChecksumNotMatch,
+        }
+    }
+}
 
 pub struct Mnemonic(Vec<&'static str>);
 
+impl Drop for Mnemonic {
+    fn drop(&mut self) {
+        // the choice of words encodes the entropy, don't leave it behind
+        for word in self.0.iter_mut() {
+            unsafe { ptr::write_volatile(word, "") };
+        }
+        atomic::compiler_fence(atomic::Ordering::SeqCst);
+    }
+}
+
 impl ToString for Mnemonic {
     fn to_string(&self) -> String {
         self.0.as_slice().join(" ")
@@ -34,23 +83,86 @@ impl ToString for Mnemonic {
 
 impl Mnemonic {
     /// create a mnemonic for encrypted data
-    /// decryption algorithm: AES256(Sha256(passphrase), ECB, NoPadding)
+    /// decryption algorithm: AES256(Sha256(passphrase), ECB, NoPadding),
+    /// data not filling whole blocks was padded by `Mnemonic::restore`
     pub fn new(encrypted: &[u8], passphrase: &str) -> Result<Self, WalletError> {
-        let mut key = [0u8; 32];
-        let mut decrypted = vec![0u8; encrypted.len()];
+        let mut key = Zeroizing::new([0u8; 32]);
+        let mut decrypted = Zeroizing::new(vec![0u8; encrypted.len()]);
         let mut sha2 = Sha256::new();
         sha2.input(passphrase.as_bytes());
-        sha2.result(&mut key);
+        sha2.result(&mut *key);
         let mut decryptor =
-            aes::ecb_decryptor(aes::KeySize::KeySize256, &key, blockmodes::NoPadding {});
+            aes::ecb_decryptor(aes::KeySize::KeySize256, &*key, blockmodes::NoPadding {});
         decryptor.decrypt(
             &mut buffer::RefReadBuffer::new(encrypted),
             &mut buffer::RefWriteBuffer::new(decrypted.as_mut_slice()),
             true,
         )?;
+        unpad(&mut decrypted);
         Mnemonic::mnemonic(decrypted.as_slice())
     }
 
+    /// create a mnemonic for plain entropy
+    pub fn from_entropy(data: &[u8]) -> Result<Self, WalletError> {
+        Mnemonic::mnemonic(data)
+    }
+
+    /// split the entropy into SLIP-39 shares, any `threshold` of `count` shares restore the mnemonic
+    pub fn to_shares(&self, threshold: u8, count: u8) -> Result<Vec<String>, WalletError> {
+        let data = Zeroizing::new(self.data()?);
+        slip39::split(&data, "", threshold, count)
+    }
+
+    /// restore a mnemonic from SLIP-39 shares
+    pub fn from_shares(shares: &[String]) -> Result<Self, WalletError> {
+        let data = slip39::combine(shares, "")?;
+        Mnemonic::mnemonic(&data)
+    }
+
+    /// a mnemonic of no words, a watch-only wallet has no seed to show
+    pub fn empty() -> Self {
+        Mnemonic(Vec::new())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn words(&self) -> &[&'static str] {
+        &self.0
+    }
+
+    /// fails unless the last word carries the checksum of the others,
+    /// `from` only checks the words are in the wordlist
+    pub fn check(&self) -> Result<(), WalletError> {
+        Zeroizing::new(self.data()?);
+        Ok(())
+    }
+
+    /// the words of the wordlist `prefix` may stand for, a word stands for itself
+    /// even if longer ones start with it. The first four letters tell the words apart
+    pub fn complete(prefix: &str) -> Vec<&'static str> {
+        if let Ok(idx) = WORDS.binary_search(&prefix) {
+            return vec![WORDS[idx]];
+        }
+        WORDS.iter().cloned().filter(|word| word.starts_with(prefix)).collect()
+    }
+
+    /// check the words of a mnemonic as `from` and `check` do, but tell which word
+    /// is wrong, a recovery form points at it
+    pub fn validate(s: &str) -> Result<(), MnemonicError> {
+        let words: Vec<_> = s.split(' ').collect();
+        if words.len() < 3 || words.len() % 3 != 0 {
+            return Err(MnemonicError::InvalidLength(words.len()));
+        }
+        if let Some(i) = words.iter().position(|word| WORDS.binary_search(word).is_err()) {
+            return Err(MnemonicError::UnknownWord(i));
+        }
+        Mnemonic::from(s)
+            .and_then(|mnemonic| mnemonic.check())
+            .map_err(|_| MnemonicError::ChecksumNotMatch)
+    }
+
     pub fn from(s: &str) -> Result<Self, WalletError> {
         let words: Vec<_> = s.split(' ').collect();
         if words.len() < 3 || words.len() % 3 != 0 {
@@ -68,16 +180,17 @@ impl Mnemonic {
     }
 
     pub fn restore(&self, new_passphrase: &str) -> Result<Vec<u8>, WalletError> {
-        let decrypted = self.data()?;
+        let mut decrypted = Zeroizing::new(self.data()?);
+        pad(&mut decrypted)?;
 
-        let mut key = [0u8; 32];
+        let mut key = Zeroizing::new([0u8; 32]);
         let mut sha2 = Sha256::new();
         sha2.input(new_passphrase.as_bytes());
-        sha2.result(&mut key);
+        sha2.result(&mut *key);
 
         let mut encrypted = vec![0u8; decrypted.len()];
         let mut encryptor =
-            aes::ecb_encryptor(aes::KeySize::KeySize256, &key, blockmodes::NoPadding {});
+            aes::ecb_encryptor(aes::KeySize::KeySize256, &*key, blockmodes::NoPadding {});
         encryptor.encrypt(
             &mut buffer::RefReadBuffer::new(decrypted.as_slice()),
             &mut buffer::RefWriteBuffer::new(encrypted.as_mut_slice()),
@@ -97,7 +210,7 @@ impl Mnemonic {
         sha2.input(data);
         sha2.result(&mut check);
 
-        let mut bits = vec![false; data.len() * 8 + data.len() / 4];
+        let mut bits = Zeroizing::new(vec![false; data.len() * 8 + data.len() / 4]);
         for i in 0..data.len() {
             for j in 0..8 {
                 bits[i * 8 + j] = (data[i] & (1 << (7 - j))) > 0;
@@ -129,7 +242,7 @@ impl Mnemonic {
         assert_eq!(full_length % 3, 0);
 
         // convert words into bits
-        let mut bits = Vec::with_capacity(full_length);
+        let mut bits = Zeroizing::new(Vec::with_capacity(full_length));
         for word in self.0.iter() {
             let index = WORDS
                 .binary_search(word)
@@ -140,7 +253,7 @@ impl Mnemonic {
         }
 
         // convert bits into bytes
-        let data = bits
+        let data = Zeroizing::new(bits
             .as_slice()
             .chunks(8)
             .map(|bits| {
@@ -150,7 +263,7 @@ impl Mnemonic {
                         accumulator + (if bit { 1 } else { 0 } << (7 - index))
                     })
             })
-            .collect::<Vec<_>>();
+            .collect::<Vec<_>>());
 
         // split
         let (data, check) = data.split_at(data_length / 8);
@@ -179,6 +292,36 @@ impl Mnemonic {
     }
 }
 
+// AES works on whole blocks, so entropy of 18 and 15 word mnemonics is padded PKCS#7 style,
+// the 4 bytes padding of 21 words would be too likely to appear at the end of random entropy
+const PADDING_LENGTHS: [usize; 2] = [8, 12];
+
+fn pad(data: &mut Vec<u8>) -> Result<(), WalletError> {
+    let padding = (16 - data.len() % 16) % 16;
+    if padding == 0 {
+        return Ok(());
+    }
+    if !PADDING_LENGTHS.contains(&padding) {
+        return Err(WalletError::InvalidMnemonicLength);
+    }
+    data.resize(data.len() + padding, padding as u8);
+    Ok(())
+}
+
+fn unpad(data: &mut Vec<u8>) {
+    let padding = match data.last() {
+        Some(&last) => last as usize,
+        None => return,
+    };
+    if PADDING_LENGTHS.contains(&padding)
+        && data.len() > padding
+        && data[data.len() - padding..].iter().all(|&b| b as usize == padding)
+    {
+        let len = data.len() - padding;
+        data.truncate(len);
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -235,6 +378,51 @@ mod test {
         )
         .is_err());
     }
+
+    #[test]
+    fn test_complete() {
+        assert_eq!(Mnemonic::complete("aban"), vec!["abandon"]);
+        assert_eq!(Mnemonic::complete("act"), vec!["act"]);
+        assert_eq!(Mnemonic::complete("abs"), vec!["absent", "absorb", "abstract", "absurd"]);
+        assert!(Mnemonic::complete("xyz").is_empty());
+
+        let words = "abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+                     abandon abandon";
+        let valid = Mnemonic::from(&format!("{} about", words)).unwrap();
+        assert!(valid.check().is_ok());
+        assert_eq!(valid.words()[11], "about");
+        let invalid = Mnemonic::from(&format!("{} abandon", words)).unwrap();
+        assert!(invalid.check().is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let words = "abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+                     abandon abandon";
+        assert_eq!(Mnemonic::validate(&format!("{} about", words)), Ok(()));
+        assert_eq!(
+            Mnemonic::validate(&format!("{} abandon", words)),
+            Err(MnemonicError::ChecksumNotMatch)
+        );
+        assert_eq!(
+            Mnemonic::validate(&format!("{} abuot", words)),
+            Err(MnemonicError::UnknownWord(11))
+        );
+        assert_eq!(Mnemonic::validate(words), Err(MnemonicError::InvalidLength(11)));
+        assert_eq!(MnemonicError::UnknownWord(11).to_string(), "word 12 is not in the wordlist");
+    }
+
+    #[test]
+    fn test_restore_padded() {
+        let data = hex::decode("7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f").unwrap();
+        let mnemonic = Mnemonic::from_entropy(&data).unwrap();
+        assert_eq!(mnemonic.to_string().split(' ').count(), 18);
+
+        let encrypted = mnemonic.restore("passphrase").unwrap();
+        assert_eq!(encrypted.len(), 32);
+        let decrypted = Mnemonic::new(&encrypted, "passphrase").unwrap();
+        assert_eq!(decrypted.to_string(), mnemonic.to_string());
+    }
 }
 
 const WORDS: [&str; 2048] = [
@@ -0,0 +1,707 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Spending policies
+//!
+//! A policy like `or(pk(A),and(pk(B),older(1000)))` states which keys and timelocks spend
+//! the coins of a script account. It compiles to the witness script of a P2WSH address
+//! the way miniscript compiles its `and_v` and `or_i` fragments: `and` runs its left part
+//! ending in a VERIFY before its right one, `or` picks its branch by an OP_IF.
+//! Keys are fixed public keys or extended ones, the address of index i has the child i
+//! of every extended key. An extended key may be given with its origin,
+//! `[d34db33f/84'/0'/2'/0]xpub...`, the PSBTs of the account carry the origins of its children
+//! for their signers.
+//!
+//! `pk(KEY)`, `multi(k,KEY,...)`, `older(blocks)`, `after(height)`, `and(X,Y)`, `or(X,Y)`
+//!
+use bitcoin::{
+    blockdata::{
+        opcodes,
+        script::{Builder, Script},
+    },
+    util::{
+        bip32::{self, ChildNumber, DerivationPath, ExtendedPubKey, Fingerprint},
+        key::PublicKey,
+    },
+};
+use secp256k1::Secp256k1;
+
+use std::{collections::HashMap, error::Error, fmt, str::FromStr};
+
+/// nLockTime below it is a block height, a unix time otherwise
+const LOCKTIME_THRESHOLD: u32 = 500_000_000;
+/// nSequence without a relative lock (BIP68)
+const SEQUENCE_DISABLE_FLAG: u32 = 1 << 31;
+/// nSequence locking for units of 512 seconds rather than blocks
+const SEQUENCE_TYPE_FLAG: u32 = 1 << 22;
+/// keys OP_CHECKMULTISIG takes at most
+const MAX_MULTI_KEYS: usize = 20;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PolicyKey {
+    Fixed(PublicKey),
+    /// the fingerprint of its master key and its path are known if it was given with them
+    Extended(ExtendedPubKey, Option<(Fingerprint, Vec<ChildNumber>)>),
+}
+
+impl PolicyKey {
+    /// the key of the address `index`, an extended key has no child at a few indexes
+    pub fn derive(&self, index: u32) -> Result<PublicKey, bip32::Error> {
+        match self {
+            PolicyKey::Fixed(pk) => Ok(pk.clone()),
+            PolicyKey::Extended(xpub, _) => {
+                let child = xpub.ckd_pub(&Secp256k1::new(), ChildNumber::Normal { index })?;
+                Ok(child.public_key)
+            }
+        }
+    }
+
+    /// fingerprint of the master key and full path of the key of the address `index`
+    pub fn origin(&self, index: u32) -> Option<(Fingerprint, DerivationPath)> {
+        match self {
+            PolicyKey::Extended(_, Some((fingerprint, path))) => {
+                let mut path = path.clone();
+                path.push(ChildNumber::Normal { index });
+                Some((*fingerprint, DerivationPath::from(path)))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for PolicyKey {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(bytes) = hex::decode(s) {
+            return Ok(PolicyKey::Fixed(PublicKey::from_slice(&bytes)?));
+        }
+        let (origin, key) = if s.starts_with('[') {
+            let end = s.find(']').ok_or_else(|| format!("unterminated key origin in {}", s))?;
+            (Some(parse_origin(&s[1..end])?), &s[end + 1..])
+        } else {
+            (None, s)
+        };
+        let xpub = ExtendedPubKey::from_str(key)
+            .map_err(|_| format!("{} is neither a public key nor an extended one", s))?;
+        Ok(PolicyKey::Extended(xpub, origin))
+    }
+}
+
+// `d34db33f/84'/0'/2'/0`, `h` may mark the hardened indexes as well
+fn parse_origin(s: &str) -> Result<(Fingerprint, Vec<ChildNumber>), Box<dyn Error>> {
+    let mut parts = s.split('/');
+    let bytes = hex::decode(parts.next().unwrap())?;
+    if bytes.len() != 4 {
+        return Err(From::from("a fingerprint has 4 bytes"));
+    }
+    let mut path = Vec::new();
+    for part in parts {
+        path.push(if part.ends_with('\'') || part.ends_with('h') {
+            ChildNumber::Hardened { index: part[..part.len() - 1].parse()? }
+        } else {
+            ChildNumber::Normal { index: part.parse()? }
+        });
+    }
+    Ok((Fingerprint::from(&bytes[..]), path))
+}
+
+impl fmt::Display for PolicyKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PolicyKey::Fixed(pk) => write!(f, "{}", hex::encode(&pk.key.serialize()[..])),
+            PolicyKey::Extended(xpub, None) => write!(f, "{}", xpub),
+            PolicyKey::Extended(xpub, Some((fingerprint, path))) => {
+                write!(f, "[{}", hex::encode(&fingerprint[..]))?;
+                for child in path {
+                    match child {
+                        ChildNumber::Hardened { index } => write!(f, "/{}'", index)?,
+                        ChildNumber::Normal { index } => write!(f, "/{}", index)?,
+                    }
+                }
+                write!(f, "]{}", xpub)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Policy {
+    /// a signature of the key
+    Key(PolicyKey),
+    /// signatures of `k` of the keys
+    Multi(usize, Vec<PolicyKey>),
+    /// OP_CHECKSEQUENCEVERIFY, the spent output is confirmed for this many blocks
+    Older(u32),
+    /// OP_CHECKLOCKTIMEVERIFY, the spend is in a block after this height
+    After(u32),
+    And(Box<Policy>, Box<Policy>),
+    Or(Box<Policy>, Box<Policy>),
+}
+
+/// what the cheapest satisfaction of a policy takes, see `Policy::plan`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Plan {
+    /// signatures of keys the signer doesn't hold
+    pub foreign: usize,
+    pub signatures: usize,
+    /// nSequence of the spending input, 0 without a relative lock
+    pub older: u32,
+    /// nLockTime of the spending transaction, 0 without an absolute lock
+    pub after: u32,
+}
+
+impl Plan {
+    fn and(self, other: Plan) -> Plan {
+        Plan {
+            foreign: self.foreign + other.foreign,
+            signatures: self.signatures + other.signatures,
+            older: self.older.max(other.older),
+            after: self.after.max(other.after),
+        }
+    }
+
+    // signatures of others weigh most, a shorter wait least
+    fn cost(&self) -> (usize, usize, u32, u32) {
+        (self.foreign, self.signatures, self.older, self.after)
+    }
+}
+
+impl Policy {
+    /// the witness script of the address `index`
+    pub fn witness_script(&self, index: u32) -> Result<Script, bip32::Error> {
+        Ok(self.compile(Builder::new(), index, false)?.into_script())
+    }
+
+    // `verify` ends the fragment in a VERIFY rather than leaving its result on the stack
+    fn compile(&self, builder: Builder, index: u32, verify: bool) -> Result<Builder, bip32::Error> {
+        let builder = match self {
+            Policy::Key(key) => {
+                let builder = builder.push_slice(&key.derive(index)?.key.serialize());
+                return Ok(if verify {
+                    builder.push_opcode(opcodes::all::OP_CHECKSIGVERIFY)
+                } else {
+                    builder.push_opcode(opcodes::all::OP_CHECKSIG)
+                });
+            }
+            Policy::Multi(k, keys) => {
+                let mut builder = builder.push_int(*k as i64);
+                for key in keys {
+                    builder = builder.push_slice(&key.derive(index)?.key.serialize());
+                }
+                let builder = builder.push_int(keys.len() as i64);
+                return Ok(if verify {
+                    builder.push_opcode(opcodes::all::OP_CHECKMULTISIGVERIFY)
+                } else {
+                    builder.push_opcode(opcodes::all::OP_CHECKMULTISIG)
+                });
+            }
+            Policy::Older(blocks) => {
+                builder.push_int(*blocks as i64).push_opcode(opcodes::all::OP_CSV)
+            }
+            Policy::After(height) => {
+                builder.push_int(*height as i64).push_opcode(opcodes::all::OP_CLTV)
+            }
+            Policy::And(left, right) => {
+                let builder = left.compile(builder, index, true)?;
+                return right.compile(builder, index, verify);
+            }
+            Policy::Or(left, right) => {
+                let builder =
+                    left.compile(builder.push_opcode(opcodes::all::OP_IF), index, false)?;
+                let builder =
+                    right.compile(builder.push_opcode(opcodes::all::OP_ELSE), index, false)?;
+                builder.push_opcode(opcodes::all::OP_ENDIF)
+            }
+        };
+        Ok(if verify {
+            builder.push_opcode(opcodes::all::OP_VERIFY)
+        } else {
+            builder
+        })
+    }
+
+    /// keys of the address `index`, in the order of the policy
+    pub fn keys(&self, index: u32) -> Result<Vec<PublicKey>, bip32::Error> {
+        match self {
+            Policy::Key(key) => Ok(vec![key.derive(index)?]),
+            Policy::Multi(_, keys) => keys.iter().map(|key| key.derive(index)).collect(),
+            Policy::Older(_) | Policy::After(_) => Ok(Vec::new()),
+            Policy::And(left, right) | Policy::Or(left, right) => {
+                let mut keys = left.keys(index)?;
+                keys.extend(right.keys(index)?);
+                Ok(keys)
+            }
+        }
+    }
+
+    /// origins of the keys of the address `index` given with one
+    pub fn key_origins(
+        &self,
+        index: u32,
+    ) -> Result<Vec<(PublicKey, (Fingerprint, DerivationPath))>, bip32::Error> {
+        let mut origins = Vec::new();
+        for key in self.policy_keys() {
+            if let Some(origin) = key.origin(index) {
+                origins.push((key.derive(index)?, origin));
+            }
+        }
+        Ok(origins)
+    }
+
+    // the keys as given, in the order of the policy
+    fn policy_keys(&self) -> Vec<&PolicyKey> {
+        match self {
+            Policy::Key(key) => vec![key],
+            Policy::Multi(_, keys) => keys.iter().collect(),
+            Policy::Older(_) | Policy::After(_) => Vec::new(),
+            Policy::And(left, right) | Policy::Or(left, right) => {
+                let mut keys = left.policy_keys();
+                keys.extend(right.policy_keys());
+                keys
+            }
+        }
+    }
+
+    /// the branch of the address `index` needing the fewest signatures of keys `own` doesn't
+    /// hold, among those unlocked for an output with `confirmations` spent in a block after
+    /// `height`. It may still need signatures of others, `None` if every branch is locked.
+    /// A key without a child at `index` is held by nobody
+    pub fn plan(
+        &self,
+        index: u32,
+        own: &dyn Fn(&PublicKey) -> bool,
+        confirmations: u32,
+        height: u32,
+    ) -> Option<Plan> {
+        match self {
+            Policy::Key(key) => Some(Plan {
+                foreign: if key.derive(index).map_or(false, |pk| own(&pk)) { 0 } else { 1 },
+                signatures: 1,
+                ..Plan::default()
+            }),
+            Policy::Multi(k, keys) => {
+                let owned = keys
+                    .iter()
+                    .filter(|key| key.derive(index).map_or(false, |pk| own(&pk)))
+                    .count();
+                Some(Plan {
+                    foreign: k.saturating_sub(owned),
+                    signatures: *k,
+                    ..Plan::default()
+                })
+            }
+            Policy::Older(blocks) if *blocks <= confirmations => Some(Plan {
+                older: *blocks,
+                ..Plan::default()
+            }),
+            Policy::After(lock_height) if *lock_height <= height => Some(Plan {
+                after: *lock_height,
+                ..Plan::default()
+            }),
+            Policy::Older(_) | Policy::After(_) => None,
+            Policy::And(left, right) => {
+                let left = left.plan(index, own, confirmations, height)?;
+                Some(left.and(right.plan(index, own, confirmations, height)?))
+            }
+            Policy::Or(left, right) => {
+                let left = left.plan(index, own, confirmations, height);
+                let right = right.plan(index, own, confirmations, height);
+                match (left, right) {
+                    (Some(left), Some(right)) => {
+                        Some(if right.cost() < left.cost() { right } else { left })
+                    }
+                    (left, right) => left.or(right),
+                }
+            }
+        }
+    }
+
+    /// witness stack satisfying the policy of the address `index` with `sigs`, the witness
+    /// script goes on top of it. `sequence` and `lock_time` are those of the spending input
+    /// and transaction, `None` if no branch is satisfied. A key without a child at `index`
+    /// has no signature
+    pub fn satisfy(
+        &self,
+        index: u32,
+        sigs: &HashMap<PublicKey, Vec<u8>>,
+        sequence: u32,
+        lock_time: u32,
+    ) -> Option<Vec<Vec<u8>>> {
+        match self {
+            Policy::Key(key) => {
+                let sig = key.derive(index).ok().and_then(|pk| sigs.get(&pk));
+                sig.map(|sig| vec![sig.clone()])
+            }
+            Policy::Multi(k, keys) => {
+                // OP_CHECKMULTISIG pops an extra element, the signatures follow the order
+                // of their keys
+                let mut stack = vec![Vec::new()];
+                stack.extend(
+                    keys.iter()
+                        .filter_map(|key| sigs.get(&key.derive(index).ok()?).cloned())
+                        .take(*k),
+                );
+                if stack.len() == k + 1 {
+                    Some(stack)
+                } else {
+                    None
+                }
+            }
+            Policy::Older(blocks) => {
+                let relative = sequence & (SEQUENCE_DISABLE_FLAG | SEQUENCE_TYPE_FLAG) == 0;
+                if relative && sequence & 0xFFFF >= *blocks {
+                    Some(Vec::new())
+                } else {
+                    None
+                }
+            }
+            Policy::After(height) => {
+                // a final input disables the lock time
+                if lock_time < LOCKTIME_THRESHOLD && lock_time >= *height && sequence != 0xFFFFFFFF
+                {
+                    Some(Vec::new())
+                } else {
+                    None
+                }
+            }
+            Policy::And(left, right) => {
+                // the left part runs first, its witness is on top
+                let mut stack = right.satisfy(index, sigs, sequence, lock_time)?;
+                stack.extend(left.satisfy(index, sigs, sequence, lock_time)?);
+                Some(stack)
+            }
+            Policy::Or(left, right) => {
+                // OP_IF takes the left branch on 1 and the right one on an empty element
+                let left = left.satisfy(index, sigs, sequence, lock_time).map(|mut stack| {
+                    stack.push(vec![1]);
+                    stack
+                });
+                let right = right.satisfy(index, sigs, sequence, lock_time).map(|mut stack| {
+                    stack.push(Vec::new());
+                    stack
+                });
+                match (left, right) {
+                    (Some(left), Some(right)) => {
+                        Some(if stack_size(&right) < stack_size(&left) { right } else { left })
+                    }
+                    (left, right) => left.or(right),
+                }
+            }
+        }
+    }
+}
+
+fn stack_size(stack: &[Vec<u8>]) -> usize {
+    stack.iter().map(|item| item.len() + 1).sum()
+}
+
+impl FromStr for Policy {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+        let (policy, rest) = parse(&s)?;
+        if !rest.is_empty() {
+            return Err(From::from(format!("unexpected {} after the policy", rest)));
+        }
+        Ok(policy)
+    }
+}
+
+// the fragment `s` starts with, along with what follows it
+fn parse(s: &str) -> Result<(Policy, &str), Box<dyn Error>> {
+    let open = s.find('(').ok_or_else(|| format!("expected a fragment at {}", s))?;
+    let (name, rest) = (&s[..open], &s[open + 1..]);
+    let (policy, rest) = match name {
+        "pk" => {
+            let (key, rest) = argument(rest)?;
+            (Policy::Key(key.parse()?), rest)
+        }
+        "multi" => {
+            let (k, mut rest) = argument(rest)?;
+            let k: usize = k.parse()?;
+            let mut keys = Vec::new();
+            while rest.starts_with(',') {
+                let (key, tail) = argument(&rest[1..])?;
+                keys.push(key.parse()?);
+                rest = tail;
+            }
+            if k == 0 || k > keys.len() || keys.len() > MAX_MULTI_KEYS {
+                let err = format!(
+                    "multi takes 1 to {} keys and at most as many signatures",
+                    MAX_MULTI_KEYS
+                );
+                return Err(From::from(err));
+            }
+            (Policy::Multi(k, keys), rest)
+        }
+        "older" => {
+            let (blocks, rest) = argument(rest)?;
+            let blocks: u32 = blocks.parse()?;
+            if blocks == 0 || blocks > 0xFFFF {
+                return Err(From::from(format!("older({}) is no relative lock of blocks", blocks)));
+            }
+            (Policy::Older(blocks), rest)
+        }
+        "after" => {
+            let (height, rest) = argument(rest)?;
+            let height: u32 = height.parse()?;
+            if height == 0 || height >= LOCKTIME_THRESHOLD {
+                return Err(From::from(format!("after({}) is no block height", height)));
+            }
+            (Policy::After(height), rest)
+        }
+        "and" | "or" => {
+            let (left, rest) = parse(rest)?;
+            let (right, rest) = parse(expect(rest, ',')?)?;
+            let (left, right) = (Box::new(left), Box::new(right));
+            let policy = if name == "and" {
+                Policy::And(left, right)
+            } else {
+                Policy::Or(left, right)
+            };
+            (policy, rest)
+        }
+        _ => return Err(From::from(format!("unknown fragment {}", name))),
+    };
+    Ok((policy, expect(rest, ')')?))
+}
+
+// the argument up to the next ',' or ')'
+fn argument(s: &str) -> Result<(&str, &str), Box<dyn Error>> {
+    let end = s.find(|c| c == ',' || c == ')').ok_or("unterminated fragment")?;
+    Ok((&s[..end], &s[end..]))
+}
+
+fn expect(s: &str, c: char) -> Result<&str, Box<dyn Error>> {
+    if s.starts_with(c) {
+        Ok(&s[1..])
+    } else {
+        Err(From::from(format!("expected {} at {}", c, s)))
+    }
+}
+
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Policy::Key(key) => write!(f, "pk({})", key),
+            Policy::Multi(k, keys) => {
+                write!(f, "multi({}", k)?;
+                for key in keys {
+                    write!(f, ",{}", key)?;
+                }
+                write!(f, ")")
+            }
+            Policy::Older(blocks) => write!(f, "older({})", blocks),
+            Policy::After(height) => write!(f, "after({})", height),
+            Policy::And(left, right) => write!(f, "and({},{})", left, right),
+            Policy::Or(left, right) => write!(f, "or({},{})", left, right),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::{
+        blockdata::{opcodes, script::Builder},
+        util::{
+            bip32::{DerivationPath, ExtendedPrivKey, ExtendedPubKey},
+            key::{PrivateKey, PublicKey},
+        },
+        network::constants::Network,
+        SigHashType,
+    };
+    use secp256k1::{Secp256k1, SecretKey, Message};
+    use std::{collections::HashMap, str::FromStr};
+    use crate::sighash::signature_hash;
+    use crate::walletlibrary::WalletConfig;
+    use crate::memory::test::{new_wallet, payment};
+    use crate::interface::Wallet;
+    use super::{Policy, Plan};
+
+    fn key(byte: u8) -> PublicKey {
+        let sk = PrivateKey {
+            compressed: true,
+            network: Network::Regtest,
+            key: SecretKey::from_slice(&[byte; 32]).unwrap(),
+        };
+        PublicKey::from_private_key(&Secp256k1::new(), &sk)
+    }
+
+    fn hex(pk: &PublicKey) -> String {
+        hex::encode(&pk.key.serialize()[..])
+    }
+
+    #[test]
+    fn parse() {
+        let (a, b) = (key(1), key(2));
+        let s = format!("or(pk({}), and(pk({}), older(1000)))", hex(&a), hex(&b));
+        let policy: Policy = s.parse().unwrap();
+        assert_eq!(policy.to_string(), s.replace(' ', ""));
+        assert_eq!(policy.keys(0).unwrap(), vec![a.clone(), b.clone()]);
+
+        let multi: Policy = format!("multi(2,{},{})", hex(&a), hex(&b)).parse().unwrap();
+        assert_eq!(multi.keys(7).unwrap().len(), 2);
+
+        for bad in &[
+            format!("pk({}", hex(&a)),
+            format!("pk({}))", hex(&a)),
+            format!("multi(3,{},{})", hex(&a), hex(&b)),
+            "older(0)".to_string(),
+            "after(500000000)".to_string(),
+            "thresh(1,older(1))".to_string(),
+            "pk(00)".to_string(),
+        ] {
+            assert!(bad.parse::<Policy>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn key_origin() {
+        let ctx = Secp256k1::new();
+        let master = ExtendedPrivKey::new_master(Network::Regtest, &[7; 32]).unwrap();
+        let xpub = ExtendedPubKey::from_private(&ctx, &master);
+        let s = format!("multi(1,[d34db33f/84'/1'/2h/0]{},{},{})", xpub, xpub, hex(&key(1)));
+        let policy: Policy = s.parse().unwrap();
+        assert_eq!(policy.to_string(), s.replace("2h", "2'"));
+
+        let origins = policy.key_origins(5).unwrap();
+        assert_eq!(origins.len(), 1);
+        assert_eq!(origins[0].0, policy.keys(5).unwrap()[0]);
+        assert_eq!(hex::encode(&(origins[0].1).0[..]), "d34db33f");
+        assert_eq!((origins[0].1).1, DerivationPath::from_str("m/84'/1'/2'/0/5").unwrap());
+
+        assert!(format!("pk([d34db33f/84'{})", xpub).parse::<Policy>().is_err());
+        assert!(format!("pk([d34d/84']{})", xpub).parse::<Policy>().is_err());
+    }
+
+    #[test]
+    fn compile() {
+        let (a, b) = (key(1), key(2));
+        let policy: Policy =
+            format!("or(pk({}),and(pk({}),older(1000)))", hex(&a), hex(&b)).parse().unwrap();
+        let expected = Builder::new()
+            .push_opcode(opcodes::all::OP_IF)
+            .push_slice(&a.key.serialize())
+            .push_opcode(opcodes::all::OP_CHECKSIG)
+            .push_opcode(opcodes::all::OP_ELSE)
+            .push_slice(&b.key.serialize())
+            .push_opcode(opcodes::all::OP_CHECKSIGVERIFY)
+            .push_int(1000)
+            .push_opcode(opcodes::all::OP_CSV)
+            .push_opcode(opcodes::all::OP_ENDIF)
+            .into_script();
+        assert_eq!(policy.witness_script(0).unwrap(), expected);
+    }
+
+    #[test]
+    fn plan_and_satisfy() {
+        let (a, b, c) = (key(1), key(2), key(3));
+        let policy: Policy = format!(
+            "or(multi(2,{},{},{}),and(pk({}),older(144)))",
+            hex(&a),
+            hex(&b),
+            hex(&c),
+            hex(&b)
+        )
+        .parse()
+        .unwrap();
+
+        // alone the holder of b waits for the lock
+        let own_b = |pk: &PublicKey| *pk == b;
+        assert_eq!(policy.plan(0, &own_b, 143, 0).unwrap().foreign, 1);
+        let plan = policy.plan(0, &own_b, 144, 0).unwrap();
+        assert_eq!(
+            plan,
+            Plan {
+                foreign: 0,
+                signatures: 1,
+                older: 144,
+                after: 0,
+            }
+        );
+        let mut sigs = HashMap::new();
+        sigs.insert(b.clone(), vec![0xbb]);
+        assert_eq!(policy.satisfy(0, &sigs, 143, 0), None);
+        assert_eq!(policy.satisfy(0, &sigs, 144, 0), Some(vec![vec![0xbb], vec![]]));
+        assert_eq!(policy.satisfy(0, &sigs, 0xFFFFFFFD, 0), None);
+
+        // along with a cosigner it doesn't
+        let plan = policy.plan(0, &|pk| *pk == b || *pk == c, 144, 0).unwrap();
+        assert_eq!((plan.foreign, plan.older), (0, 0));
+        sigs.insert(c.clone(), vec![0xcc]);
+        let stack = policy.satisfy(0, &sigs, 0xFFFFFFFD, 0).unwrap();
+        assert_eq!(stack, vec![vec![], vec![0xbb], vec![0xcc], vec![1]]);
+
+        let after: Policy = format!("and(pk({}),after(100))", hex(&a)).parse().unwrap();
+        assert_eq!(after.plan(0, &|_| true, 0, 99), None);
+        assert_eq!(after.plan(0, &|_| true, 0, 100).unwrap().after, 100);
+    }
+
+    #[test]
+    fn script_account() {
+        let (mut wallet, chain) = new_wallet(WalletConfig::in_memory());
+        let ctx = Secp256k1::new();
+        let cosigner_sk = SecretKey::from_slice(&[0x33; 32]).unwrap();
+        let cosigner = PublicKey {
+            compressed: true,
+            key: secp256k1::PublicKey::from_secret_key(&ctx, &cosigner_sk),
+        };
+        let cosigner_hex = hex::encode(&cosigner.key.serialize()[..]);
+        let xpub = wallet.wallet_lib().script_account_xpub().unwrap();
+        let policy = format!("or(multi(2,{},{}),and(pk({}),older(2)))", xpub, cosigner_hex, xpub);
+        wallet.wallet_lib_mut().register_policy("vault", &policy).unwrap();
+        assert!(wallet.wallet_lib_mut().register_policy("vault", &policy).is_err());
+        let addr = wallet.wallet_lib_mut().new_script_address("vault").unwrap();
+        assert_ne!(addr, wallet.wallet_lib_mut().new_script_address("vault").unwrap());
+        chain.push_block(Vec::new());
+        chain.push_block(vec![payment(&addr, 50_000_000)]);
+        wallet.sync_with_tip().unwrap();
+        let coins = wallet.wallet_lib().get_script_utxo_list();
+        assert_eq!(coins.len(), 1);
+        assert_eq!((coins[0].value, coins[0].index), (50_000_000, 0));
+        assert_eq!(wallet.wallet_lib().wallet_balance(), 0);
+
+        // before the lock expires the cosigner has to sign
+        let dest_addr = "bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49".to_owned();
+        let spend = wallet.spend_script_coins("vault", dest_addr.clone(), 10).unwrap();
+        assert!(spend.tx.is_none());
+        let mut psbt = spend.psbt;
+        assert_eq!(psbt.inputs[0].partial_sigs.len(), 1);
+        assert!(wallet.finalize_script_psbt(psbt.clone()).is_err());
+        let tx = &psbt.global.unsigned_tx;
+        let witness_script = psbt.inputs[0].witness_script.clone().unwrap();
+        let hash =
+            signature_hash(tx, 0, &witness_script, 50_000_000, SigHashType::All, true).unwrap();
+        let sig = ctx.sign(&Message::from_slice(&hash[..]).unwrap(), &cosigner_sk);
+        let mut serialized_sig = sig.serialize_der().to_vec();
+        serialized_sig.push(SigHashType::All.as_u32() as u8);
+        psbt.inputs[0].partial_sigs.insert(cosigner, serialized_sig);
+        let tx = wallet.finalize_script_psbt(psbt).unwrap();
+        assert_eq!(tx.input[0].witness.len(), 5);
+        assert_eq!(tx.input[0].witness[3], vec![1]);
+
+        // the next block it is the wallet's alone
+        chain.push_block(Vec::new());
+        wallet.sync_with_tip().unwrap();
+        let spend = wallet.spend_script_coins("vault", dest_addr, 10).unwrap();
+        let tx = spend.tx.unwrap();
+        assert_eq!(tx.input[0].sequence, 2);
+        assert_eq!(tx.input[0].witness.len(), 3);
+        assert!(tx.input[0].witness[1].is_empty());
+        assert!(tx.output[0].value < 50_000_000);
+    }
+}
@@ -0,0 +1,162 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Fiat valuation
+//!
+//! Prices of a bitcoin in fiat currencies come from a `PriceSource`, such as an exchange API.
+//! The current price is asked again once it is older than the time to live of
+//! `CachedPriceSource`, the price at the time of a block never changes, so
+//! `WalletLibraryInterface::confirmation_price` keeps it in the wallet database.
+//!
+use std::{
+    collections::HashMap,
+    error::Error,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+const SATOSHIS_PER_BITCOIN: f64 = 100_000_000.0;
+
+/// where the prices of a bitcoin come from, `currency` is a code such as "usd"
+pub trait PriceSource: Send + Sync {
+    fn current_price(&self, currency: &str) -> Result<f64, Box<dyn Error>>;
+    /// price at unix time `time`
+    fn historical_price(&self, currency: &str, time: u64) -> Result<f64, Box<dyn Error>>;
+}
+
+/// value of `amount` satoshi at `price` per bitcoin
+pub fn fiat_value(amount: i64, price: f64) -> f64 {
+    amount as f64 / SATOSHIS_PER_BITCOIN * price
+}
+
+/// `PriceSource` asking `source` for the current price at most once per `ttl` and currency
+pub struct CachedPriceSource<S>
+where
+    S: PriceSource,
+{
+    source: S,
+    ttl: Duration,
+    current: Mutex<HashMap<String, (Instant, f64)>>,
+}
+
+impl<S> CachedPriceSource<S>
+where
+    S: PriceSource,
+{
+    pub fn new(source: S, ttl: Duration) -> Self {
+        CachedPriceSource {
+            source,
+            ttl,
+            current: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<S> PriceSource for CachedPriceSource<S>
+where
+    S: PriceSource,
+{
+    fn current_price(&self, currency: &str) -> Result<f64, Box<dyn Error>> {
+        if let Some(&(fetched, price)) = self.current.lock().unwrap().get(currency) {
+            if fetched.elapsed() < self.ttl {
+                return Ok(price);
+            }
+        }
+        // not held while asking, a slow source doesn't block the other currencies
+        let price = self.source.current_price(currency)?;
+        self.current
+            .lock()
+            .unwrap()
+            .insert(currency.to_owned(), (Instant::now(), price));
+        Ok(price)
+    }
+
+    fn historical_price(&self, currency: &str, time: u64) -> Result<f64, Box<dyn Error>> {
+        self.source.historical_price(currency, time)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::{error::Error, sync::atomic::{AtomicUsize, Ordering}, time::Duration};
+    use crate::walletlibrary::WalletConfig;
+    use crate::memory::test::{new_wallet, payment};
+    use crate::account::AccountAddressType;
+    use crate::interface::{Wallet, BlockChainIO};
+    use super::{PriceSource, CachedPriceSource, fiat_value};
+
+    /// a bitcoin is worth its unix time in any currency, or 10000 now
+    #[derive(Default)]
+    pub struct FakePriceSource {
+        pub calls: AtomicUsize,
+    }
+
+    impl PriceSource for FakePriceSource {
+        fn current_price(&self, _currency: &str) -> Result<f64, Box<dyn Error>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(10_000.0)
+        }
+
+        fn historical_price(&self, _currency: &str, time: u64) -> Result<f64, Box<dyn Error>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(time as f64)
+        }
+    }
+
+    #[test]
+    fn cached_current_price() {
+        let prices = CachedPriceSource::new(FakePriceSource::default(), Duration::from_secs(60));
+        assert_eq!(prices.current_price("usd").unwrap(), 10_000.0);
+        assert_eq!(prices.current_price("usd").unwrap(), 10_000.0);
+        assert_eq!(prices.source.calls.load(Ordering::SeqCst), 1);
+        prices.current_price("eur").unwrap();
+        assert_eq!(prices.source.calls.load(Ordering::SeqCst), 2);
+
+        // expired right away
+        let prices = CachedPriceSource::new(FakePriceSource::default(), Duration::from_secs(0));
+        prices.current_price("usd").unwrap();
+        prices.current_price("usd").unwrap();
+        assert_eq!(prices.source.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn value() {
+        assert_eq!(fiat_value(50_000_000, 10_000.0), 5_000.0);
+        assert_eq!(fiat_value(-25_000_000, 10_000.0), -2_500.0);
+    }
+
+    #[test]
+    fn fiat_valuation() {
+        let (mut wallet, chain) = new_wallet(WalletConfig::in_memory());
+        let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        chain.push_block(Vec::new());
+        let block_hash = chain.push_block(vec![payment(&addr, 100_000_000)]);
+        wallet.sync_with_tip().unwrap();
+
+        let record = wallet.wallet_lib().get_tx_history().pop().unwrap();
+        assert_eq!(wallet.wallet_lib().tx_value(&record), 100_000_000);
+
+        // the fake source prices a bitcoin at the unix time
+        let block_time = chain.get_block(&block_hash).unwrap().header.time;
+        let source = FakePriceSource::default();
+        for _ in 0..2 {
+            let price = wallet.wallet_lib_mut().confirmation_price(&source, "usd", 2).unwrap();
+            assert_eq!(price, Some(block_time as f64));
+        }
+        assert_eq!(source.calls.load(Ordering::SeqCst), 1);
+        // the wallet has no header above its tip
+        assert_eq!(wallet.wallet_lib_mut().confirmation_price(&source, "usd", 3).unwrap(), None);
+    }
+}
@@ -0,0 +1,960 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Animated QR codes
+//!
+//! PSBTs, descriptors and addresses move to and from air-gapped signers such as SeedSigner
+//! or Keystone as QR codes. A payload too large for one code is split into the parts of an
+//! animated QR code, as BC-UR (BCR-2020-005) with fountain codes or as BBQr
+//!
+use bitcoin::{
+    blockdata::{opcodes, script::Builder},
+    network::constants::Network,
+    util::address::Address,
+};
+use bitcoin_hashes::{sha256, Hash};
+
+use std::{error::Error, str::FromStr};
+
+use super::error::WalletError;
+
+/// payload bytes of a part at most, animated QR codes of this size scan at arm's length
+pub const DEFAULT_MAX_FRAGMENT_LEN: usize = 200;
+/// UR fragments are not made shorter
+pub const MIN_FRAGMENT_LEN: usize = 10;
+
+const BBQR_BASE32: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+const BBQR_BASE36: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+// two base36 digits number the parts
+const BBQR_MAX_PARTS: usize = 36 * 36 - 1;
+
+// BCR-2020-012, a byte is the first and last letter of its word in URs
+const BYTEWORDS: [&str; 256] = [
+    "able", "acid", "also", "apex", "aqua", "arch", "atom", "aunt", "away", "axis", "back", "bald",
+    "barn", "belt", "beta", "bias", "blue", "body", "brag", "brew", "bulb", "buzz", "calm", "cash",
+    "cats", "chef", "city", "claw", "code", "cola", "cook", "cost", "crux", "curl", "cusp", "cyan",
+    "dark", "data", "days", "deli", "dice", "diet", "door", "down", "draw", "drop", "drum", "dull",
+    "duty", "each", "easy", "echo", "edge", "epic", "even", "exam", "exit", "eyes", "fact", "fair",
+    "fern", "figs", "film", "fish", "fizz", "flap", "flew", "flux", "foxy", "free", "frog", "fuel",
+    "fund", "gala", "game", "gear", "gems", "gift", "girl", "glow", "good", "gray", "grim", "guru",
+    "gush", "gyro", "half", "hang", "hard", "hawk", "heat", "help", "high", "hill", "holy", "hope",
+    "horn", "huts", "iced", "idea", "idle", "inch", "inky", "into", "iris", "iron", "item", "jade",
+    "jazz", "join", "jolt", "jowl", "judo", "jugs", "jump", "junk", "jury", "keep", "keno", "kept",
+    "keys", "kick", "kiln", "king", "kite", "kiwi", "knob", "lamb", "lava", "lazy", "leaf", "legs",
+    "liar", "limp", "lion", "list", "logo", "loud", "love", "luau", "luck", "lung", "main", "many",
+    "math", "maze", "memo", "menu", "meow", "mild", "mint", "miss", "monk", "nail", "navy", "need",
+    "news", "next", "noon", "note", "numb", "obey", "oboe", "omit", "onyx", "open", "oval", "owls",
+    "paid", "part", "peck", "play", "plus", "poem", "pool", "pose", "puff", "puma", "purr", "quad",
+    "quiz", "race", "ramp", "real", "redo", "rich", "road", "rock", "roof", "ruby", "ruin", "runs",
+    "rust", "safe", "saga", "scar", "sets", "silk", "skew", "slot", "soap", "solo", "song", "stub",
+    "surf", "swan", "taco", "task", "taxi", "tent", "tied", "time", "tiny", "toil", "tomb", "toys",
+    "trip", "tuna", "twin", "ugly", "undo", "unit", "urge", "user", "vast", "very", "veto", "vial",
+    "vibe", "view", "visa", "void", "vows", "wall", "wand", "warm", "wasp", "wave", "waxy", "webs",
+    "what", "when", "whiz", "wolf", "work", "yank", "yawn", "yell", "yoga", "yurt", "zaps", "zero",
+    "zest", "zinc", "zone", "zoom",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QrFormat {
+    /// `ur:crypto-psbt/...`, `ur:output-descriptor/...` and `ur:crypto-address/...`
+    Ur,
+    /// `B$2P...` for PSBTs and `B$2U...` for descriptors and addresses
+    Bbqr,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum QrPayload {
+    /// BIP174 serialized
+    Psbt(Vec<u8>),
+    Descriptor(String),
+    /// P2PKH, P2SH or P2WPKH
+    Address(Address),
+}
+
+/// the parts of an animated QR code of `payload`, a single part if it fits `max_fragment_len`.
+/// A UR of more parts is followed by `extra_parts` fountain codes, they let a scanner which
+/// missed some parts complete without waiting for those to come round again
+pub fn encode(
+    payload: &QrPayload,
+    format: QrFormat,
+    max_fragment_len: usize,
+    extra_parts: usize,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    match format {
+        QrFormat::Ur => {
+            let (ur_type, cbor) = match payload {
+                QrPayload::Psbt(psbt) => {
+                    let mut cbor = Vec::new();
+                    cbor_bytes(psbt, &mut cbor);
+                    ("crypto-psbt", cbor)
+                }
+                // BCR-2023-010, the source is key 1 of the map
+                QrPayload::Descriptor(descriptor) => {
+                    let mut cbor = Vec::new();
+                    cbor_head(5, 1, &mut cbor);
+                    cbor_head(0, 1, &mut cbor);
+                    cbor_head(3, descriptor.len() as u64, &mut cbor);
+                    cbor.extend_from_slice(descriptor.as_bytes());
+                    ("output-descriptor", cbor)
+                }
+                QrPayload::Address(addr) => ("crypto-address", crypto_address(addr)?),
+            };
+            let mut encoder = UrEncoder::new(ur_type, &cbor, max_fragment_len)?;
+            let count = if encoder.seq_len() == 1 { 1 } else { encoder.seq_len() + extra_parts };
+            Ok((0..count).map(|_| encoder.next_part()).collect())
+        }
+        QrFormat::Bbqr => match payload {
+            QrPayload::Psbt(psbt) => bbqr_encode('P', psbt, max_fragment_len),
+            QrPayload::Descriptor(descriptor) => {
+                bbqr_encode('U', descriptor.as_bytes(), max_fragment_len)
+            }
+            QrPayload::Address(addr) => {
+                bbqr_encode('U', addr.to_string().as_bytes(), max_fragment_len)
+            }
+        },
+    }
+}
+
+/// the payload of the scanned parts of an animated QR code, in any order and with repeats.
+/// Addresses of test networks are decoded for `network`
+pub fn decode(parts: &[String], network: Network) -> Result<QrPayload, Box<dyn Error>> {
+    let first = parts.first().ok_or(WalletError::InvalidQrPart)?;
+    if first.starts_with("B$") {
+        let mut decoder = BbqrDecoder::new();
+        for part in parts {
+            decoder.receive(part)?;
+        }
+        let (file_type, data) = decoder.result().ok_or(WalletError::InvalidQrPart)?;
+        return match file_type {
+            'P' => Ok(QrPayload::Psbt(data)),
+            'U' => {
+                let text = String::from_utf8(data).map_err(|_| WalletError::InvalidQrPart)?;
+                let text = text.trim();
+                // an address has no parentheses, a descriptor has some
+                if text.contains('(') {
+                    Ok(QrPayload::Descriptor(text.to_owned()))
+                } else {
+                    Ok(QrPayload::Address(Address::from_str(text)?))
+                }
+            }
+            _ => Err(From::from(format!("BBQr file type {} is not supported", file_type))),
+        };
+    }
+
+    let mut decoder = UrDecoder::new();
+    for part in parts {
+        decoder.receive(part)?;
+    }
+    let (ur_type, cbor) = decoder.result().ok_or(WalletError::InvalidQrPart)?;
+    let mut reader = CborReader { data: cbor, pos: 0 };
+    match ur_type {
+        "crypto-psbt" | "psbt" => Ok(QrPayload::Psbt(reader.bytes(2)?.to_vec())),
+        "output-descriptor" => {
+            reader.skip_tag()?;
+            for _ in 0..reader.head_of(5)? {
+                let key = reader.head_of(0)?;
+                if key == 1 {
+                    let source = reader.bytes(3)?;
+                    let source = String::from_utf8(source.to_vec())
+                        .map_err(|_| WalletError::InvalidQrPart)?;
+                    return Ok(QrPayload::Descriptor(source));
+                }
+                reader.skip()?;
+            }
+            Err(From::from(WalletError::InvalidQrPart))
+        }
+        "crypto-address" | "address" => {
+            Ok(QrPayload::Address(address_of_crypto_address(&mut reader, network)?))
+        }
+        _ => Err(From::from(format!("UR type {} is not supported", ur_type))),
+    }
+}
+
+/// splits a UR into the parts of an animated QR code. The first `seq_len` parts carry
+/// a fragment each, the following ones mix random fragments, so a decoder which missed
+/// some parts completes with any few of the following ones
+pub struct UrEncoder {
+    ur_type: String,
+    message_len: usize,
+    checksum: u32,
+    fragments: Vec<Vec<u8>>,
+    seq_num: u32,
+}
+
+impl UrEncoder {
+    /// `cbor` is the payload of a UR of `ur_type`
+    pub fn new(
+        ur_type: &str,
+        cbor: &[u8],
+        max_fragment_len: usize,
+    ) -> Result<UrEncoder, WalletError> {
+        if cbor.is_empty() || max_fragment_len < MIN_FRAGMENT_LEN {
+            return Err(WalletError::InvalidQrPart);
+        }
+        let fragment_len = if cbor.len() <= max_fragment_len {
+            cbor.len()
+        } else {
+            // the fewest fragments of equal length fitting the limit
+            let max_count = cbor.len() / MIN_FRAGMENT_LEN;
+            (1..=max_count)
+                .map(|count| (cbor.len() + count - 1) / count)
+                .find(|len| *len <= max_fragment_len)
+                .unwrap_or(MIN_FRAGMENT_LEN)
+        };
+        let fragments = cbor
+            .chunks(fragment_len)
+            .map(|chunk| {
+                let mut fragment = chunk.to_vec();
+                fragment.resize(fragment_len, 0);
+                fragment
+            })
+            .collect();
+        Ok(UrEncoder {
+            ur_type: ur_type.to_owned(),
+            message_len: cbor.len(),
+            checksum: crc32(cbor),
+            fragments,
+            seq_num: 0,
+        })
+    }
+
+    /// number of fragments, the decoder needs as many parts at least
+    pub fn seq_len(&self) -> usize {
+        self.fragments.len()
+    }
+
+    pub fn next_part(&mut self) -> String {
+        if self.seq_len() == 1 {
+            let mut message = self.fragments[0].clone();
+            message.truncate(self.message_len);
+            return format!("ur:{}/{}", self.ur_type, bytewords_encode(&message));
+        }
+        self.seq_num += 1;
+        let mut data = vec![0; self.fragments[0].len()];
+        for index in choose_fragments(self.seq_num, self.seq_len(), self.checksum) {
+            xor_into(&mut data, &self.fragments[index]);
+        }
+        let mut part = Vec::new();
+        cbor_head(4, 5, &mut part);
+        cbor_head(0, self.seq_num as u64, &mut part);
+        cbor_head(0, self.seq_len() as u64, &mut part);
+        cbor_head(0, self.message_len as u64, &mut part);
+        cbor_head(0, self.checksum as u64, &mut part);
+        cbor_bytes(&data, &mut part);
+        format!(
+            "ur:{}/{}-{}/{}",
+            self.ur_type,
+            self.seq_num,
+            self.seq_len(),
+            bytewords_encode(&part)
+        )
+    }
+}
+
+/// joins the scanned parts of a UR, the single part one or the parts of an `UrEncoder`
+#[derive(Default)]
+pub struct UrDecoder {
+    ur_type: String,
+    seq_len: usize,
+    message_len: usize,
+    checksum: u32,
+    fragments: Vec<Option<Vec<u8>>>,
+    // parts mixing fragments not known yet, by the indexes of these fragments
+    mixed: Vec<(Vec<usize>, Vec<u8>)>,
+    message: Option<Vec<u8>>,
+}
+
+impl UrDecoder {
+    pub fn new() -> UrDecoder {
+        UrDecoder::default()
+    }
+
+    /// take a scanned part, parts of another UR are refused
+    pub fn receive(&mut self, part: &str) -> Result<(), WalletError> {
+        // QR codes in alphanumeric mode carry URs in upper case
+        let part = part.trim().to_lowercase();
+        if !part.starts_with("ur:") {
+            return Err(WalletError::InvalidQrPart);
+        }
+        let components: Vec<&str> = part[3..].split('/').collect();
+        if components.len() == 2 {
+            self.set_type(components[0])?;
+            self.message = Some(bytewords_decode(components[1])?);
+            return Ok(());
+        }
+        if components.len() != 3 {
+            return Err(WalletError::InvalidQrPart);
+        }
+        self.set_type(components[0])?;
+        let data = bytewords_decode(components[2])?;
+        let mut reader = CborReader { data: &data, pos: 0 };
+        if reader.head_of(4)? != 5 {
+            return Err(WalletError::InvalidQrPart);
+        }
+        let seq_num = reader.head_of(0)?;
+        let seq_len = reader.head_of(0)? as usize;
+        let message_len = reader.head_of(0)? as usize;
+        let checksum = reader.head_of(0)?;
+        let fragment = reader.bytes(2)?.to_vec();
+        if components[1] != format!("{}-{}", seq_num, seq_len)
+            || seq_num == 0
+            || seq_num > u32::max_value() as u64
+            || checksum > u32::max_value() as u64
+            || seq_len == 0
+            || fragment.len() * seq_len < message_len
+        {
+            return Err(WalletError::InvalidQrPart);
+        }
+        let checksum = checksum as u32;
+        if self.fragments.is_empty() {
+            self.seq_len = seq_len;
+            self.message_len = message_len;
+            self.checksum = checksum;
+            self.fragments = vec![None; seq_len];
+        } else if seq_len != self.seq_len
+            || message_len != self.message_len
+            || checksum != self.checksum
+            || self.fragments.iter().flatten().any(|known| known.len() != fragment.len())
+        {
+            return Err(WalletError::InvalidQrPart);
+        }
+        if self.message.is_some() {
+            return Ok(());
+        }
+        self.add_part(choose_fragments(seq_num as u32, seq_len, checksum), fragment);
+
+        if self.fragments.iter().all(|fragment| fragment.is_some()) {
+            let mut message: Vec<u8> = self.fragments.iter().flatten().flatten().cloned().collect();
+            message.truncate(self.message_len);
+            if crc32(&message) != self.checksum {
+                *self = UrDecoder::new();
+                return Err(WalletError::InvalidQrPart);
+            }
+            self.message = Some(message);
+        }
+        Ok(())
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.message.is_some()
+    }
+
+    /// fraction of the fragments known, to show while scanning
+    pub fn progress(&self) -> f64 {
+        if self.message.is_some() {
+            return 1.0;
+        }
+        if self.fragments.is_empty() {
+            return 0.0;
+        }
+        self.fragments.iter().flatten().count() as f64 / self.fragments.len() as f64
+    }
+
+    /// type and CBOR payload of the complete UR
+    pub fn result(&self) -> Option<(&str, &[u8])> {
+        self.message.as_ref().map(|message| (self.ur_type.as_str(), message.as_slice()))
+    }
+
+    fn set_type(&mut self, ur_type: &str) -> Result<(), WalletError> {
+        let valid = !ur_type.is_empty()
+            && ur_type.bytes().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == b'-');
+        if !valid || (!self.ur_type.is_empty() && self.ur_type != ur_type) {
+            return Err(WalletError::InvalidQrPart);
+        }
+        self.ur_type = ur_type.to_owned();
+        Ok(())
+    }
+
+    // peel the known fragments off the part, a part left with a single fragment
+    // may peel the parts kept before
+    fn add_part(&mut self, indexes: Vec<usize>, data: Vec<u8>) {
+        let mut queue = vec![(indexes, data)];
+        while let Some((mut indexes, mut data)) = queue.pop() {
+            let fragments = &self.fragments;
+            indexes.retain(|index| match fragments[*index] {
+                Some(ref fragment) => {
+                    xor_into(&mut data, fragment);
+                    false
+                }
+                None => true,
+            });
+            match indexes.len() {
+                0 => {}
+                1 => {
+                    self.fragments[indexes[0]] = Some(data);
+                    queue.append(&mut self.mixed);
+                }
+                _ => {
+                    if !self.mixed.iter().any(|(known, _)| *known == indexes) {
+                        self.mixed.push((indexes, data));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// joins the scanned parts of a BBQr, hex or base32 encoded ones.
+/// Zlib compressed parts are not supported
+#[derive(Default)]
+pub struct BbqrDecoder {
+    encoding: u8,
+    file_type: u8,
+    parts: Vec<Option<Vec<u8>>>,
+}
+
+impl BbqrDecoder {
+    pub fn new() -> BbqrDecoder {
+        BbqrDecoder::default()
+    }
+
+    pub fn receive(&mut self, part: &str) -> Result<(), Box<dyn Error>> {
+        let part = part.trim().as_bytes();
+        if part.len() < 8 || &part[..2] != b"B$" {
+            return Err(From::from(WalletError::InvalidQrPart));
+        }
+        let (encoding, file_type) = (part[2], part[3]);
+        if encoding == b'Z' {
+            return Err(From::from("zlib compressed BBQr is not supported, use encoding 2"));
+        }
+        let count = base36(&part[4..6])?;
+        let index = base36(&part[6..8])?;
+        if count == 0 || index >= count {
+            return Err(From::from(WalletError::InvalidQrPart));
+        }
+        if self.parts.is_empty() {
+            self.encoding = encoding;
+            self.file_type = file_type;
+            self.parts = vec![None; count];
+        } else if (encoding, file_type, count) != (self.encoding, self.file_type, self.parts.len())
+        {
+            return Err(From::from(WalletError::InvalidQrPart));
+        }
+        let data = match encoding {
+            b'H' => hex::decode(&part[8..]).map_err(|_| WalletError::InvalidQrPart)?,
+            b'2' => base32_decode(&part[8..])?,
+            _ => return Err(From::from(WalletError::InvalidQrPart)),
+        };
+        self.parts[index] = Some(data);
+        Ok(())
+    }
+
+    pub fn is_complete(&self) -> bool {
+        !self.parts.is_empty() && self.parts.iter().all(|part| part.is_some())
+    }
+
+    /// file type, e.g. `P` for a PSBT, and the joined data
+    pub fn result(&self) -> Option<(char, Vec<u8>)> {
+        if !self.is_complete() {
+            return None;
+        }
+        Some((self.file_type as char, self.parts.iter().flatten().flatten().cloned().collect()))
+    }
+}
+
+fn bbqr_encode(
+    file_type: char,
+    data: &[u8],
+    max_fragment_len: usize,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    // base32 encodes 5 bytes in 8 characters, so a part but the last has a multiple of 5 bytes
+    let chunk_len = max_fragment_len / 5 * 5;
+    if chunk_len == 0 || data.is_empty() {
+        return Err(From::from(WalletError::InvalidQrPart));
+    }
+    let count = (data.len() + chunk_len - 1) / chunk_len;
+    if count > BBQR_MAX_PARTS {
+        return Err(From::from(format!("BBQr has {} parts at most", BBQR_MAX_PARTS)));
+    }
+    let digits = |n: usize| {
+        let digit = |d: usize| BBQR_BASE36[d] as char;
+        format!("{}{}", digit(n / 36), digit(n % 36))
+    };
+    Ok(data
+        .chunks(chunk_len)
+        .enumerate()
+        .map(|(index, chunk)| {
+            format!("B$2{}{}{}{}", file_type, digits(count), digits(index), base32_encode(chunk))
+        })
+        .collect())
+}
+
+fn base36(digits: &[u8]) -> Result<usize, WalletError> {
+    let mut n = 0;
+    for c in digits {
+        let d = BBQR_BASE36.iter().position(|d| d == c).ok_or(WalletError::InvalidQrPart)?;
+        n = n * 36 + d;
+    }
+    Ok(n)
+}
+
+fn base32_encode(data: &[u8]) -> String {
+    let mut encoded = String::new();
+    let (mut acc, mut bits) = (0u32, 0);
+    for byte in data {
+        acc = acc << 8 | *byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BBQR_BASE32[(acc >> bits) as usize & 31] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(BBQR_BASE32[(acc << (5 - bits)) as usize & 31] as char);
+    }
+    encoded
+}
+
+fn base32_decode(encoded: &[u8]) -> Result<Vec<u8>, WalletError> {
+    let mut data = Vec::new();
+    let (mut acc, mut bits) = (0u32, 0);
+    for c in encoded {
+        let d = BBQR_BASE32.iter().position(|d| d == c).ok_or(WalletError::InvalidQrPart)?;
+        acc = (acc << 5 | d as u32) & 0xfff;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            data.push((acc >> bits) as u8);
+        }
+    }
+    Ok(data)
+}
+
+// BCR-2020-009, the hash or witness program of the address with its type and network
+fn crypto_address(addr: &Address) -> Result<Vec<u8>, Box<dyn Error>> {
+    let script = addr.script_pubkey();
+    let script = script.as_bytes();
+    let (addr_type, hash) = if script.len() == 25 && script[0] == opcodes::all::OP_DUP.into_u8() {
+        (0, &script[3..23])
+    } else if script.len() == 23 && script[0] == opcodes::all::OP_HASH160.into_u8() {
+        (1, &script[2..22])
+    } else if script.len() == 22 && script[0] == 0 {
+        (2, &script[2..22])
+    } else {
+        return Err(From::from("only P2PKH, P2SH and P2WPKH addresses have a UR"));
+    };
+    let mut cbor = Vec::new();
+    cbor_head(5, 3, &mut cbor);
+    // crypto-coininfo of bitcoin, tagged 305
+    cbor_head(0, 1, &mut cbor);
+    cbor_head(6, 305, &mut cbor);
+    cbor_head(5, 2, &mut cbor);
+    cbor_head(0, 1, &mut cbor);
+    cbor_head(0, 0, &mut cbor);
+    cbor_head(0, 2, &mut cbor);
+    cbor_head(0, if addr.network == Network::Bitcoin { 0 } else { 1 }, &mut cbor);
+    cbor_head(0, 2, &mut cbor);
+    cbor_head(0, addr_type, &mut cbor);
+    cbor_head(0, 3, &mut cbor);
+    cbor_bytes(hash, &mut cbor);
+    Ok(cbor)
+}
+
+fn address_of_crypto_address(
+    reader: &mut CborReader,
+    network: Network,
+) -> Result<Address, WalletError> {
+    let (mut testnet, mut addr_type, mut hash) = (false, None, None);
+    reader.skip_tag()?;
+    for _ in 0..reader.head_of(5)? {
+        match reader.head_of(0)? {
+            1 => {
+                reader.skip_tag()?;
+                for _ in 0..reader.head_of(5)? {
+                    let key = reader.head_of(0)?;
+                    let value = reader.head_of(0)?;
+                    if key == 2 {
+                        testnet = value == 1;
+                    }
+                }
+            }
+            2 => addr_type = Some(reader.head_of(0)?),
+            3 => hash = Some(reader.bytes(2)?.to_vec()),
+            _ => reader.skip()?,
+        }
+    }
+    let hash = hash.ok_or(WalletError::InvalidQrPart)?;
+    if hash.len() != 20 {
+        return Err(WalletError::InvalidQrPart);
+    }
+    let script = match addr_type {
+        Some(0) => Builder::new()
+            .push_opcode(opcodes::all::OP_DUP)
+            .push_opcode(opcodes::all::OP_HASH160)
+            .push_slice(&hash)
+            .push_opcode(opcodes::all::OP_EQUALVERIFY)
+            .push_opcode(opcodes::all::OP_CHECKSIG),
+        Some(1) => Builder::new()
+            .push_opcode(opcodes::all::OP_HASH160)
+            .push_slice(&hash)
+            .push_opcode(opcodes::all::OP_EQUAL),
+        // a 20 byte hash without a type is a key hash of segwit
+        Some(2) | None => Builder::new().push_int(0).push_slice(&hash),
+        _ => return Err(WalletError::InvalidQrPart),
+    }
+    .into_script();
+    let network = match (testnet, network) {
+        (false, _) => Network::Bitcoin,
+        (true, Network::Bitcoin) => Network::Testnet,
+        (true, network) => network,
+    };
+    Address::from_script(&script, network).ok_or(WalletError::InvalidQrPart)
+}
+
+// indexes of the fragments a part mixes, the first `seq_len` parts carry one each
+fn choose_fragments(seq_num: u32, seq_len: usize, checksum: u32) -> Vec<usize> {
+    if seq_num as usize <= seq_len {
+        return vec![seq_num as usize - 1];
+    }
+    let mut seed = seq_num.to_be_bytes().to_vec();
+    seed.extend_from_slice(&checksum.to_be_bytes());
+    let mut rng = Xoshiro256::new(&seed);
+    // a part mixes d fragments with a probability proportional to 1/d
+    let degree_probs: Vec<f64> = (1..=seq_len).map(|d| 1.0 / d as f64).collect();
+    let degree = sample(&degree_probs, &mut rng) + 1;
+    let mut remaining: Vec<usize> = (0..seq_len).collect();
+    let mut shuffled = Vec::with_capacity(seq_len);
+    while !remaining.is_empty() {
+        let index = rng.next_int(0, remaining.len() as u64 - 1) as usize;
+        shuffled.push(remaining.remove(index));
+    }
+    shuffled.truncate(degree);
+    shuffled.sort();
+    shuffled
+}
+
+// Vose's alias method, the tables are built as in the reference implementation of BC-UR
+// to make the same choices
+fn sample(probs: &[f64], rng: &mut Xoshiro256) -> usize {
+    let n = probs.len();
+    let sum: f64 = probs.iter().sum();
+    let mut scaled: Vec<f64> = probs.iter().map(|p| p * n as f64 / sum).collect();
+    let (mut small, mut large) = (Vec::new(), Vec::new());
+    for i in (0..n).rev() {
+        if scaled[i] < 1.0 {
+            small.push(i);
+        } else {
+            large.push(i);
+        }
+    }
+    let mut alias_probs = vec![0.0; n];
+    let mut aliases = vec![0; n];
+    while !small.is_empty() && !large.is_empty() {
+        let a = small.pop().unwrap();
+        let g = large.pop().unwrap();
+        alias_probs[a] = scaled[a];
+        aliases[a] = g;
+        scaled[g] += scaled[a] - 1.0;
+        if scaled[g] < 1.0 {
+            small.push(g);
+        } else {
+            large.push(g);
+        }
+    }
+    for i in large.into_iter().chain(small) {
+        alias_probs[i] = 1.0;
+    }
+
+    let r1 = rng.next_double();
+    let r2 = rng.next_double();
+    let i = (n as f64 * r1) as usize;
+    if r2 < alias_probs[i] {
+        i
+    } else {
+        aliases[i]
+    }
+}
+
+// xoshiro256**, seeded by the SHA256 of the seed
+struct Xoshiro256 {
+    s: [u64; 4],
+}
+
+impl Xoshiro256 {
+    fn new(seed: &[u8]) -> Xoshiro256 {
+        let hash = sha256::Hash::hash(seed).into_inner();
+        let mut s = [0u64; 4];
+        for (i, word) in s.iter_mut().enumerate() {
+            *word = hash[i * 8..i * 8 + 8].iter().fold(0, |acc, b| acc << 8 | *b as u64);
+        }
+        Xoshiro256 { s }
+    }
+
+    fn next(&mut self) -> u64 {
+        let result = self.s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = self.s[1] << 17;
+        self.s[2] ^= self.s[0];
+        self.s[3] ^= self.s[1];
+        self.s[1] ^= self.s[2];
+        self.s[0] ^= self.s[3];
+        self.s[2] ^= t;
+        self.s[3] = self.s[3].rotate_left(45);
+        result
+    }
+
+    fn next_double(&mut self) -> f64 {
+        self.next() as f64 / (u64::max_value() as f64 + 1.0)
+    }
+
+    fn next_int(&mut self, low: u64, high: u64) -> u64 {
+        (self.next_double() * (high - low + 1) as f64) as u64 + low
+    }
+}
+
+fn xor_into(data: &mut [u8], other: &[u8]) {
+    for (a, b) in data.iter_mut().zip(other) {
+        *a ^= b;
+    }
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { crc >> 1 ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+// minimal bytewords with the CRC32 of the data appended
+fn bytewords_encode(data: &[u8]) -> String {
+    let checksum = crc32(data).to_be_bytes();
+    data.iter()
+        .chain(checksum.iter())
+        .map(|byte| {
+            let word = BYTEWORDS[*byte as usize].as_bytes();
+            format!("{}{}", word[0] as char, word[3] as char)
+        })
+        .collect()
+}
+
+fn bytewords_decode(encoded: &str) -> Result<Vec<u8>, WalletError> {
+    let encoded = encoded.as_bytes();
+    if encoded.len() % 2 != 0 || encoded.len() < 10 {
+        return Err(WalletError::InvalidQrPart);
+    }
+    let mut data = Vec::with_capacity(encoded.len() / 2);
+    for pair in encoded.chunks(2) {
+        let byte = BYTEWORDS
+            .iter()
+            .position(|word| word.as_bytes()[0] == pair[0] && word.as_bytes()[3] == pair[1])
+            .ok_or(WalletError::InvalidQrPart)?;
+        data.push(byte as u8);
+    }
+    let checksum = data.split_off(data.len() - 4);
+    if checksum != crc32(&data).to_be_bytes() {
+        return Err(WalletError::InvalidQrPart);
+    }
+    Ok(data)
+}
+
+fn cbor_head(major: u8, value: u64, out: &mut Vec<u8>) {
+    let major = major << 5;
+    if value < 24 {
+        out.push(major | value as u8);
+    } else if value <= 0xff {
+        out.push(major | 24);
+        out.push(value as u8);
+    } else if value <= 0xffff {
+        out.push(major | 25);
+        out.extend_from_slice(&(value as u16).to_be_bytes());
+    } else if value <= 0xffff_ffff {
+        out.push(major | 26);
+        out.extend_from_slice(&(value as u32).to_be_bytes());
+    } else {
+        out.push(major | 27);
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+fn cbor_bytes(data: &[u8], out: &mut Vec<u8>) {
+    cbor_head(2, data.len() as u64, out);
+    out.extend_from_slice(data);
+}
+
+// reads the definite length items of the CBOR of URs
+struct CborReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> CborReader<'a> {
+    fn head(&mut self) -> Result<(u8, u64), WalletError> {
+        let initial = *self.data.get(self.pos).ok_or(WalletError::InvalidQrPart)?;
+        self.pos += 1;
+        let len = match initial & 0x1f {
+            info if info < 24 => return Ok((initial >> 5, info as u64)),
+            24 => 1,
+            25 => 2,
+            26 => 4,
+            27 => 8,
+            _ => return Err(WalletError::InvalidQrPart),
+        };
+        let bytes = self.data.get(self.pos..self.pos + len).ok_or(WalletError::InvalidQrPart)?;
+        self.pos += len;
+        Ok((initial >> 5, bytes.iter().fold(0, |acc, b| acc << 8 | *b as u64)))
+    }
+
+    // the argument of an item of major type `major`, the count of an array or map
+    fn head_of(&mut self, major: u8) -> Result<u64, WalletError> {
+        match self.head()? {
+            (found, value) if found == major => Ok(value),
+            _ => Err(WalletError::InvalidQrPart),
+        }
+    }
+
+    // a byte or text string
+    fn bytes(&mut self, major: u8) -> Result<&'a [u8], WalletError> {
+        let len = self.head_of(major)? as usize;
+        let end = self.pos.checked_add(len).ok_or(WalletError::InvalidQrPart)?;
+        let bytes = self.data.get(self.pos..end).ok_or(WalletError::InvalidQrPart)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn skip_tag(&mut self) -> Result<(), WalletError> {
+        if self.data.get(self.pos).map_or(false, |initial| initial >> 5 == 6) {
+            self.head()?;
+        }
+        Ok(())
+    }
+
+    fn skip(&mut self) -> Result<(), WalletError> {
+        let start = self.pos;
+        match self.head()? {
+            (0, _) | (1, _) | (7, _) => {}
+            (major, _) if major == 2 || major == 3 => {
+                self.pos = start;
+                self.bytes(major)?;
+            }
+            (4, count) => {
+                for _ in 0..count {
+                    self.skip()?;
+                }
+            }
+            (5, count) => {
+                for _ in 0..count * 2 {
+                    self.skip()?;
+                }
+            }
+            _ => self.skip()?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::{network::constants::Network, util::address::Address};
+
+    use std::str::FromStr;
+
+    use super::{
+        bytewords_encode, choose_fragments, crc32, decode, encode, QrFormat, QrPayload,
+        UrDecoder, UrEncoder, Xoshiro256,
+    };
+
+    // the test message of the reference implementation
+    fn message(len: usize) -> Vec<u8> {
+        let mut rng = Xoshiro256::new(b"Wolf");
+        (0..len).map(|_| rng.next_int(0, 255) as u8).collect()
+    }
+
+    #[test]
+    fn reference_vectors() {
+        assert_eq!(bytewords_encode(&[0, 1, 2, 128, 255]), "aeadaolazmjendeoti");
+
+        let mut rng = Xoshiro256::new(b"Wolf");
+        let numbers: Vec<u64> = (0..8).map(|_| rng.next() % 100).collect();
+        assert_eq!(numbers, vec![42, 81, 85, 8, 82, 84, 76, 73]);
+
+        let checksum = crc32(&message(1024));
+        let chosen: Vec<Vec<usize>> =
+            (11..=15).map(|seq_num| choose_fragments(seq_num, 11, checksum)).collect();
+        assert_eq!(
+            chosen,
+            vec![vec![10], vec![9], vec![2, 5, 6, 8, 9, 10], vec![8], vec![1, 5]]
+        );
+
+        let mut cbor = vec![0x59, 0x01, 0x00];
+        cbor.extend(message(256));
+        let mut encoder = UrEncoder::new("bytes", &cbor, 30).unwrap();
+        assert_eq!(encoder.seq_len(), 9);
+        assert_eq!(
+            encoder.next_part(),
+            "ur:bytes/1-9/lpadascfadaxcywenbpljkhdcahkadaemejtswhhylkepmykhhtsytsnoyoyaxaedsuttyd\
+             mmhhpktpmsrjtdkgslpgh"
+        );
+    }
+
+    #[test]
+    fn fountain_parts() {
+        let psbt = message(1000);
+        let parts = encode(&QrPayload::Psbt(psbt.clone()), QrFormat::Ur, 100, 5).unwrap();
+        assert_eq!(parts.len(), 16);
+        assert_eq!(decode(&parts, Network::Bitcoin).unwrap(), QrPayload::Psbt(psbt.clone()));
+
+        // a scanner which missed the first parts completes with the mixed ones
+        let mut cbor = vec![0x59, 0x03, 0xe8];
+        cbor.extend(&psbt);
+        let mut encoder = UrEncoder::new("crypto-psbt", &cbor, 100).unwrap();
+        let mut decoder = UrDecoder::new();
+        let mut scanned = 0;
+        while !decoder.is_complete() {
+            let part = encoder.next_part();
+            scanned += 1;
+            if scanned > 3 {
+                decoder.receive(&part.to_uppercase()).unwrap();
+            }
+            assert!(scanned < 100);
+        }
+        assert_eq!(decoder.result().unwrap(), ("crypto-psbt", &cbor[..]));
+
+        assert!(decoder.receive("ur:crypto-output/aeadaolazmjendeoti").is_err());
+        assert!(UrDecoder::new().receive("ur:bytes/aeadaolazmjendeotu").is_err());
+    }
+
+    #[test]
+    fn payloads() {
+        let descriptor = "wpkh([d34db33f/84'/0'/0']xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1\
+            LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL/0/*)";
+        for format in &[QrFormat::Ur, QrFormat::Bbqr] {
+            let payload = QrPayload::Descriptor(descriptor.to_owned());
+            let parts = encode(&payload, *format, 50, 0).unwrap();
+            assert!(parts.len() > 1);
+            let mut reversed = parts.clone();
+            reversed.reverse();
+            assert_eq!(decode(&reversed, Network::Bitcoin).unwrap(), payload);
+
+            for addr in &[
+                "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2",
+                "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy",
+                "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080",
+            ] {
+                let addr = Address::from_str(addr).unwrap();
+                let parts = encode(&QrPayload::Address(addr.clone()), *format, 200, 3).unwrap();
+                assert_eq!(parts.len(), 1);
+                let decoded = decode(&parts, addr.network).unwrap();
+                assert_eq!(decoded, QrPayload::Address(addr));
+            }
+        }
+
+        let parts = encode(&QrPayload::Psbt(vec![0x70; 12]), QrFormat::Bbqr, 7, 0).unwrap();
+        assert_eq!(parts, vec!["B$2P0300OBYHA4DQ", "B$2P0301OBYHA4DQ", "B$2P0302OBYA"]);
+        assert!(decode(&parts[..2].to_vec(), Network::Bitcoin).is_err());
+    }
+}
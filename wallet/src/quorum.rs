@@ -0,0 +1,240 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Redundant backends
+//!
+//! A wallet syncing with a single node believes whatever chain the node shows it, and a node
+//! stuck behind the network looks just like a quiet chain. The witnesses of a quorum are other
+//! nodes the wallet doesn't sync with: its transactions are published through all of them, and
+//! after each sync their tips are compared with the one of the node the wallet synced with.
+//! A transaction fails to publish only if the node and every witness refuse it.
+//!
+//! The tips may be a few blocks apart while a block propagates, `max_lag` says how many.
+//! A witness further away, or on another block below those, diverges.
+//!
+use bitcoin::Transaction;
+
+use std::{cmp, error::Error};
+
+use super::interface::BlockChainIO;
+
+/// a witness disagreeing with the backend the wallet syncs with
+#[derive(Clone, Debug, PartialEq)]
+pub struct Divergence {
+    /// name the witness was given
+    pub source: String,
+    /// tip of the witness
+    pub height: usize,
+    /// tip of the backend the wallet syncs with
+    pub tip: usize,
+    /// the witness has another block `max_lag` below the lower of both tips
+    pub forked: bool,
+}
+
+/// other backends of the same chain, see `WalletWithTrustedFullNode::set_quorum`
+pub struct Quorum<IO> {
+    witnesses: Vec<(String, IO)>,
+    max_lag: usize,
+}
+
+impl<IO> Quorum<IO>
+where
+    IO: BlockChainIO,
+{
+    /// `witnesses` with the names they are reported by
+    pub fn new(witnesses: Vec<(String, IO)>, max_lag: usize) -> Self {
+        Quorum { witnesses, max_lag }
+    }
+
+    /// publish `tx` through every witness, one refusing it doesn't keep it from the others,
+    /// returns how many took it
+    pub fn broadcast(&self, tx: &Transaction) -> usize {
+        let mut accepted = 0;
+        for (name, witness) in &self.witnesses {
+            // the nodes relay it to each other, a witness often knows it already
+            match witness.send_raw_transaction(tx) {
+                Ok(_) => accepted += 1,
+                Err(err) => log::debug!("witness {} refused {}: {}", name, tx.txid(), err),
+            }
+        }
+        accepted
+    }
+
+    /// the witnesses diverging from `primary`. A witness which doesn't answer is left out,
+    /// it can't tell anything about the chain
+    pub fn check<P>(&self, primary: &P) -> Result<Vec<Divergence>, Box<dyn Error>>
+    where
+        P: BlockChainIO,
+    {
+        let tip = primary.get_block_count()? as usize;
+        let mut divergences = Vec::new();
+        for (name, witness) in &self.witnesses {
+            match self.compare(primary, tip, witness) {
+                Ok((height, forked)) => {
+                    let lag = cmp::max(height, tip) - cmp::min(height, tip);
+                    if forked || lag > self.max_lag {
+                        divergences.push(Divergence {
+                            source: name.clone(),
+                            height,
+                            tip,
+                            forked,
+                        });
+                    }
+                }
+                Err(err) => log::warn!("failed to compare with witness {}: {}", name, err),
+            }
+        }
+        Ok(divergences)
+    }
+
+    // tip of `witness` and whether it has another block than `primary` below the lag
+    fn compare<P>(
+        &self,
+        primary: &P,
+        tip: usize,
+        witness: &IO,
+    ) -> Result<(usize, bool), Box<dyn Error>>
+    where
+        P: BlockChainIO,
+    {
+        let height = witness.get_block_count()? as usize;
+        let settled = cmp::min(height, tip).saturating_sub(self.max_lag) as u32;
+        let forked = witness.get_block_hash(settled)? != primary.get_block_hash(settled)?;
+        Ok((height, forked))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::{Transaction, network::constants::Network};
+
+    use std::sync::mpsc;
+
+    use crate::walletlibrary::{WalletConfig, SendOptions};
+    use crate::memory::{MemoryChain, test::{new_wallet, payment}};
+    use crate::events::WalletEvent;
+    use crate::account::AccountAddressType;
+    use crate::interface::{Wallet, BlockChainIO};
+    use super::{Quorum, Divergence};
+
+    #[test]
+    fn divergences() {
+        let primary = MemoryChain::new(Network::Regtest);
+        let behind = MemoryChain::new(Network::Regtest);
+        let forked = MemoryChain::new(Network::Regtest);
+        let witnesses = vec![
+            ("behind".to_owned(), behind.clone()),
+            ("forked".to_owned(), forked.clone()),
+        ];
+        let quorum = Quorum::new(witnesses, 1);
+        assert_eq!(quorum.check(&primary).unwrap(), Vec::new());
+
+        // the same blocks, the last one still on its way to a witness
+        for _ in 0..5 {
+            primary.mine();
+            forked.mine();
+        }
+        for _ in 0..4 {
+            behind.mine();
+        }
+        assert_eq!(quorum.check(&primary).unwrap(), Vec::new());
+
+        behind.truncate(2);
+        forked.truncate(2);
+        let other = Transaction {
+            version: 2,
+            lock_time: 1,
+            input: Vec::new(),
+            output: Vec::new(),
+        };
+        forked.push_block(vec![other]);
+        forked.mine();
+        forked.mine();
+        let divergence = |source: &str, height, forked| Divergence {
+            source: source.to_owned(),
+            height,
+            tip: 5,
+            forked,
+        };
+        let expected = vec![divergence("behind", 2, false), divergence("forked", 5, true)];
+        assert_eq!(quorum.check(&primary).unwrap(), expected);
+    }
+
+    #[test]
+    fn quorum_of_nodes() {
+        let (mut wallet, chain) = new_wallet(WalletConfig::in_memory());
+        let witness = MemoryChain::new(Network::Regtest);
+        wallet.set_quorum(Quorum::new(vec![("witness".to_owned(), witness.clone())], 1));
+        let events = wallet.wallet_lib_mut().subscribe();
+        let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        chain.push_block(Vec::new());
+        chain.push_block(vec![payment(&addr, 100_000_000)]);
+        witness.push_block(Vec::new());
+        witness.push_block(vec![payment(&addr, 100_000_000)]);
+        wallet.sync_with_tip().unwrap();
+        let divergences = |events: &mpsc::Receiver<WalletEvent>| {
+            events
+                .try_iter()
+                .filter(|event| match event {
+                    WalletEvent::BackendDivergence { .. } => true,
+                    _ => false,
+                })
+                .collect::<Vec<_>>()
+        };
+        assert!(divergences(&events).is_empty());
+
+        // published through both nodes
+        let dest_addr = "bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49".to_owned();
+        let sent = wallet
+            .send_coins(dest_addr, 50_000_000, true, SendOptions::default())
+            .unwrap()
+            .sent()
+            .unwrap();
+        assert_eq!(chain.get_raw_mempool().unwrap(), vec![sent.tx.txid()]);
+        assert_eq!(witness.get_raw_mempool().unwrap(), vec![sent.tx.txid()]);
+
+        // the node goes on while the witness is stuck
+        chain.mine();
+        chain.push_block(Vec::new());
+        wallet.sync_with_tip().unwrap();
+        let expected = WalletEvent::BackendDivergence {
+            source: "witness".to_owned(),
+            height: 2,
+            tip: 4,
+            forked: false,
+        };
+        assert_eq!(divergences(&events), vec![expected]);
+
+        // a node refusing the transactions of the wallet doesn't keep them from the witness
+        witness.mine();
+        witness.mine();
+        wallet.sync_with_tip().unwrap();
+        let dest_addr = "bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49".to_owned();
+        let tx = wallet
+            .send_coins(dest_addr, 10_000_000, false, SendOptions::default())
+            .unwrap()
+            .sent()
+            .unwrap()
+            .tx;
+        chain.refuse_transactions(true);
+        wallet.publish_tx(&tx).unwrap();
+        assert!(chain.get_raw_mempool().unwrap().is_empty());
+        assert_eq!(witness.get_raw_mempool().unwrap(), vec![tx.txid()]);
+
+        // it fails once all of them refuse it
+        witness.refuse_transactions(true);
+        assert!(wallet.publish_tx(&tx).is_err());
+    }
+}
@@ -0,0 +1,171 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Proof of reserves
+//!
+//! BIP127 proofs, a transaction spending the coins of the wallet along with a first input
+//! committing to a message. The input doesn't exist, so the transaction can never be mined,
+//! yet its signatures show the coins are controlled by whoever made it.
+//!
+use bitcoin::{
+    blockdata::transaction::{OutPoint, Transaction, TxIn, TxOut},
+    blockdata::script::Script,
+};
+use bitcoin_hashes::{sha256, sha256d::Hash as Sha256dHash, Hash};
+use secp256k1::{Secp256k1, Message, Signature};
+
+use std::error::Error;
+
+use super::sighash::signed_hash;
+
+/// output spent by the first input of a proof, its txid is the SHA256 of the message
+pub fn commitment(message: &str) -> OutPoint {
+    let hash = sha256::Hash::hash(format!("Proof-of-Reserves: {}", message).as_bytes());
+    OutPoint {
+        txid: Sha256dHash::from_slice(&hash[..]).unwrap(),
+        vout: 0,
+    }
+}
+
+/// proof spending `coins` given with their value, the inputs after the first one
+/// are left for the owner of the coins to sign with SIGHASH_ALL
+pub fn unsigned_proof(message: &str, coins: &[(OutPoint, u64)]) -> Transaction {
+    let mut input = vec![TxIn {
+        previous_output: commitment(message),
+        script_sig: Script::new(),
+        sequence: 0xFFFFFFFF,
+        witness: Vec::new(),
+    }];
+    for (out_point, _) in coins {
+        input.push(TxIn {
+            previous_output: *out_point,
+            script_sig: Script::new(),
+            sequence: 0xFFFFFFFF,
+            witness: Vec::new(),
+        });
+    }
+    Transaction {
+        version: 1,
+        lock_time: 0,
+        input,
+        output: vec![TxOut {
+            value: coins.iter().map(|(_, value)| value).sum(),
+            // OP_TRUE
+            script_pubkey: Script::from(vec![0x51]),
+        }],
+    }
+}
+
+/// check a proof for `message`, returns the amount it proves,
+/// `spent_output` looks up the unspent outputs at the height the proof is checked for
+pub fn verify<F>(proof: &Transaction, message: &str, spent_output: F) -> Result<u64, Box<dyn Error>>
+where
+    F: Fn(&OutPoint) -> Option<TxOut>,
+{
+    match proof.input.first() {
+        Some(input) if input.previous_output == commitment(message) => {}
+        _ => return Err(From::from("the proof doesn't commit to the message")),
+    }
+    if proof.input.len() < 2 {
+        return Err(From::from("the proof spends no coins"));
+    }
+    if proof.output.len() != 1 {
+        return Err(From::from("the proof has to have a single output"));
+    }
+
+    let ctx = Secp256k1::new();
+    let mut total = 0;
+    for (i, input) in proof.input.iter().enumerate().skip(1) {
+        let op = input.previous_output;
+        let spent = spent_output(&op)
+            .ok_or_else(|| format!("{}:{} is not an unspent output", op.txid, op.vout))?;
+        let (sig, pk, hash) = signed_hash(proof, i, &spent)
+            .ok_or_else(|| format!("{}:{} is not signed by a known template", op.txid, op.vout))?;
+        // SIGHASH_ALL, nothing else commits to the message
+        let (sighash_type, sig) = sig.split_last().ok_or("empty signature")?;
+        if *sighash_type != 0x01 {
+            return Err(From::from("the proof has to be signed with SIGHASH_ALL"));
+        }
+        let sig = Signature::from_der(sig)?;
+        ctx.verify(&Message::from_slice(&hash[..])?, &sig, &pk.key)
+            .map_err(|_| format!("invalid signature of {}:{}", op.txid, op.vout))?;
+        total += spent.value;
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::{
+        util::{address::Address, key::{PublicKey, PrivateKey}},
+        blockdata::transaction::{OutPoint, TxOut},
+        network::constants::Network,
+    };
+    use bitcoin_hashes::{sha256d::Hash as Sha256dHash, Hash};
+    use secp256k1::{Secp256k1, SecretKey};
+    use crate::account::AccountAddressType;
+    use crate::walletlibrary::sign_input;
+
+    #[test]
+    fn prove_and_verify() {
+        let sk = PrivateKey {
+            compressed: true,
+            network: Network::Regtest,
+            key: SecretKey::from_slice(&[0x11; 32]).unwrap(),
+        };
+        let pk = PublicKey::from_private_key(&Secp256k1::new(), &sk);
+        let coins = [
+            (AccountAddressType::P2PKH, Address::p2pkh(&pk, Network::Regtest), 10_000),
+            (AccountAddressType::P2SHWH, Address::p2shwpkh(&pk, Network::Regtest), 20_000),
+            (AccountAddressType::P2WKH, Address::p2wpkh(&pk, Network::Regtest), 30_000),
+        ];
+        let spent: Vec<(OutPoint, TxOut)> = coins
+            .iter()
+            .enumerate()
+            .map(|(vout, (_, addr, value))| {
+                let out_point = OutPoint {
+                    txid: Sha256dHash::hash(b"funding"),
+                    vout: vout as u32,
+                };
+                let output = TxOut {
+                    value: *value,
+                    script_pubkey: addr.script_pubkey(),
+                };
+                (out_point, output)
+            })
+            .collect();
+        let lookup = |op: &OutPoint| {
+            spent.iter().find(|(out_point, _)| out_point == op).map(|(_, output)| output.clone())
+        };
+
+        let values: Vec<(OutPoint, u64)> =
+            spent.iter().map(|(op, output)| (*op, output.value)).collect();
+        let mut proof = super::unsigned_proof("audit 2019", &values);
+        for (i, (addr_type, _, value)) in coins.iter().enumerate() {
+            sign_input(&mut proof, i + 1, &sk, addr_type, *value);
+        }
+        assert_eq!(proof.output[0].value, 60_000);
+        assert_eq!(super::verify(&proof, "audit 2019", lookup).unwrap(), 60_000);
+
+        // the proof is bound to the message and to the coins being unspent
+        assert!(super::verify(&proof, "audit 2020", lookup).is_err());
+        assert!(super::verify(&proof, "audit 2019", |_| None).is_err());
+
+        // other inputs can't be slipped into the proof without invalidating the signatures
+        let mut forged = proof.clone();
+        forged.input.swap(1, 2);
+        assert!(super::verify(&forged, "audit 2019", lookup).is_err());
+    }
+}
@@ -0,0 +1,367 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Retries of backend calls
+//!
+//! `Retrying` makes the calls of a `BlockChainIO` again when they fail with an error
+//! the backend tells transient by `BlockChainIO::is_transient`, waiting longer
+//! after every failure in a row. What fails for good is a `WalletError::BackendRejected`,
+//! what still fails after the last attempt a `WalletError::BackendUnavailable`.
+//!
+use bitcoin::{Block, Transaction, TxOut, OutPoint};
+use bitcoin_hashes::sha256d::Hash as Sha256dHash;
+
+use std::{
+    cmp, io, mem, thread,
+    collections::HashMap,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use super::interface::BlockChainIO;
+use super::error::WalletError;
+use super::walletlibrary::now;
+
+/// how often and how patiently a failed call is made again
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// calls made at most, including the first one
+    pub max_attempts: u32,
+    /// delay after the first failure, it doubles with every further one
+    pub initial_backoff: Duration,
+    /// the delay doesn't grow beyond this
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// make every call once
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// delay after the failure of attempt `attempt`, counting from 1
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1 << attempt.saturating_sub(1).min(16);
+        let backoff = self.initial_backoff.checked_mul(factor).unwrap_or(self.max_backoff);
+        cmp::min(backoff, self.max_backoff)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(4),
+        }
+    }
+}
+
+/// whether an IO error is about the connection rather than the request
+pub fn is_transient_io(err: &io::Error) -> bool {
+    match err.kind() {
+        io::ErrorKind::ConnectionRefused
+        | io::ErrorKind::ConnectionReset
+        | io::ErrorKind::ConnectionAborted
+        | io::ErrorKind::NotConnected
+        | io::ErrorKind::BrokenPipe
+        | io::ErrorKind::TimedOut
+        | io::ErrorKind::Interrupted
+        | io::ErrorKind::WouldBlock
+        | io::ErrorKind::UnexpectedEof => true,
+        _ => false,
+    }
+}
+
+/// `BlockChainIO` making the calls of `inner` again by `policy`
+pub struct Retrying<IO>
+where
+    IO: BlockChainIO,
+{
+    inner: IO,
+    policy: RetryPolicy,
+    // unix time, 0 until the backend answers a call
+    last_success: AtomicU64,
+}
+
+impl<IO> Retrying<IO>
+where
+    IO: BlockChainIO,
+{
+    pub fn new(inner: IO, policy: RetryPolicy) -> Self {
+        Retrying {
+            inner,
+            policy,
+            last_success: AtomicU64::new(0),
+        }
+    }
+
+    pub fn inner(&self) -> &IO {
+        &self.inner
+    }
+
+    /// make the calls of `inner` from now on, returns the one replaced
+    pub fn replace(&mut self, inner: IO) -> IO {
+        mem::replace(&mut self.inner, inner)
+    }
+
+    /// unix time of the last call the backend answered, with an error or not
+    pub fn last_success(&self) -> Option<u64> {
+        Some(self.last_success.load(Ordering::Relaxed)).filter(|&time| time != 0)
+    }
+
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    // `recover` is asked once a call made more than once fails for good,
+    // whether an earlier attempt went through even though its answer got lost
+    fn call<T, F, R>(&self, what: &str, call: F, recover: R) -> Result<T, WalletError>
+    where
+        F: Fn(&IO) -> Result<T, IO::Error>,
+        R: Fn(&IO) -> Option<T>,
+    {
+        let mut attempt = 1;
+        loop {
+            let err = match call(&self.inner) {
+                Ok(value) => {
+                    self.last_success.store(now(), Ordering::Relaxed);
+                    return Ok(value);
+                }
+                Err(err) => err,
+            };
+            if !IO::is_transient(&err) {
+                // refused, but the backend is up
+                self.last_success.store(now(), Ordering::Relaxed);
+                if attempt > 1 {
+                    if let Some(value) = recover(&self.inner) {
+                        return Ok(value);
+                    }
+                }
+                return Err(WalletError::BackendRejected(format!("{}: {}", what, err)));
+            }
+            if attempt >= self.policy.max_attempts {
+                let err = format!("{} failed {} times: {}", what, attempt, err);
+                return Err(WalletError::BackendUnavailable(err));
+            }
+            let max_attempts = self.policy.max_attempts;
+            log::warn!("{} failed, attempt {} of {}: {}", what, attempt, max_attempts, err);
+            thread::sleep(self.policy.backoff(attempt));
+            attempt += 1;
+        }
+    }
+}
+
+impl<IO> BlockChainIO for Retrying<IO>
+where
+    IO: BlockChainIO,
+{
+    type Error = WalletError;
+
+    fn get_block_count(&self) -> Result<u32, Self::Error> {
+        self.call("getblockcount", |io| io.get_block_count(), |_| None)
+    }
+
+    fn get_block_hash(&self, height: u32) -> Result<Sha256dHash, Self::Error> {
+        self.call("getblockhash", |io| io.get_block_hash(height), |_| None)
+    }
+
+    fn get_block(&self, header_hash: &Sha256dHash) -> Result<Block, Self::Error> {
+        self.call("getblock", |io| io.get_block(header_hash), |_| None)
+    }
+
+    fn get_raw_block(&self, header_hash: &Sha256dHash) -> Result<String, Self::Error> {
+        self.call("getblock", |io| io.get_raw_block(header_hash), |_| None)
+    }
+
+    // an attempt whose answer got lost may have reached the node, then the next one
+    // is refused as a duplicate, the transaction being known to the node means success
+    fn send_raw_transaction(&self, tx: &Transaction) -> Result<Sha256dHash, Self::Error> {
+        let txid = tx.txid();
+        self.call(
+            "sendrawtransaction",
+            |io| io.send_raw_transaction(tx),
+            |io| io.get_raw_transaction(&txid).ok().map(|_| txid),
+        )
+    }
+
+    fn get_raw_mempool(&self) -> Result<Vec<Sha256dHash>, Self::Error> {
+        self.call("getrawmempool", |io| io.get_raw_mempool(), |_| None)
+    }
+
+    fn get_raw_transaction(&self, txid: &Sha256dHash) -> Result<Transaction, Self::Error> {
+        self.call("getrawtransaction", |io| io.get_raw_transaction(txid), |_| None)
+    }
+
+    fn get_block_prevouts(
+        &self,
+        header_hash: &Sha256dHash,
+    ) -> Result<Option<HashMap<OutPoint, TxOut>>, Self::Error> {
+        self.call("getblock", |io| io.get_block_prevouts(header_hash), |_| None)
+    }
+
+    fn is_transient(err: &Self::Error) -> bool {
+        match err {
+            WalletError::BackendUnavailable(_) => true,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::{Block, Transaction, network::constants::Network};
+    use bitcoin::blockdata::constants::genesis_block;
+    use bitcoin_hashes::sha256d::Hash as Sha256dHash;
+
+    use std::{cell::Cell, io, time::Duration};
+
+    use crate::interface::BlockChainIO;
+    use crate::memory::MemoryChain;
+    use crate::error::WalletError;
+    use super::{Retrying, RetryPolicy, is_transient_io};
+
+    // chain failing the next `failures` calls as if the connection dropped,
+    // a sent transaction reaches the chain even if its call fails
+    struct Flaky {
+        chain: MemoryChain,
+        failures: Cell<u32>,
+        calls: Cell<u32>,
+    }
+
+    impl Flaky {
+        fn new(failures: u32) -> Self {
+            Flaky {
+                chain: MemoryChain::new(Network::Regtest),
+                failures: Cell::new(failures),
+                calls: Cell::new(0),
+            }
+        }
+
+        fn answer<T>(&self, result: Result<T, io::Error>) -> Result<T, io::Error> {
+            self.calls.set(self.calls.get() + 1);
+            if self.failures.get() > 0 {
+                self.failures.set(self.failures.get() - 1);
+                return Err(io::Error::new(io::ErrorKind::ConnectionReset, "connection reset"));
+            }
+            result
+        }
+    }
+
+    impl BlockChainIO for Flaky {
+        type Error = io::Error;
+
+        fn get_block_count(&self) -> Result<u32, Self::Error> {
+            self.answer(self.chain.get_block_count())
+        }
+
+        fn get_block_hash(&self, height: u32) -> Result<Sha256dHash, Self::Error> {
+            self.answer(self.chain.get_block_hash(height))
+        }
+
+        fn get_block(&self, header_hash: &Sha256dHash) -> Result<Block, Self::Error> {
+            self.answer(self.chain.get_block(header_hash))
+        }
+
+        fn get_raw_block(&self, header_hash: &Sha256dHash) -> Result<String, Self::Error> {
+            self.answer(self.chain.get_raw_block(header_hash))
+        }
+
+        // like bitcoind a transaction already in the mempool is refused
+        fn send_raw_transaction(&self, tx: &Transaction) -> Result<Sha256dHash, Self::Error> {
+            if self.chain.get_raw_transaction(&tx.txid()).is_ok() {
+                let err = io::Error::new(io::ErrorKind::InvalidInput, "txn-already-in-mempool");
+                return self.answer(Err(err));
+            }
+            let txid = self.chain.send_raw_transaction(tx)?;
+            self.answer(Ok(txid))
+        }
+
+        fn get_raw_mempool(&self) -> Result<Vec<Sha256dHash>, Self::Error> {
+            self.answer(self.chain.get_raw_mempool())
+        }
+
+        fn get_raw_transaction(&self, txid: &Sha256dHash) -> Result<Transaction, Self::Error> {
+            self.answer(self.chain.get_raw_transaction(txid))
+        }
+
+        fn is_transient(err: &Self::Error) -> bool {
+            is_transient_io(err)
+        }
+    }
+
+    fn policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            initial_backoff: Duration::from_millis(0),
+            max_backoff: Duration::from_millis(0),
+        }
+    }
+
+    #[test]
+    fn transient_errors() {
+        let bio = Retrying::new(Flaky::new(2), policy(3));
+        assert_eq!(bio.get_block_count().unwrap(), 0);
+        assert_eq!(bio.inner().calls.get(), 3);
+        assert!(bio.last_success().is_some());
+
+        let bio = Retrying::new(Flaky::new(3), policy(3));
+        match bio.get_block_count() {
+            Err(WalletError::BackendUnavailable(_)) => (),
+            result => panic!("unexpected {:?}", result),
+        }
+        assert_eq!(bio.inner().calls.get(), 3);
+        assert_eq!(bio.last_success(), None);
+    }
+
+    #[test]
+    fn permanent_errors() {
+        let bio = Retrying::new(Flaky::new(0), policy(3));
+        match bio.get_raw_transaction(&Default::default()) {
+            Err(WalletError::BackendRejected(_)) => (),
+            result => panic!("unexpected {:?}", result),
+        }
+        assert_eq!(bio.inner().calls.get(), 1);
+    }
+
+    #[test]
+    fn send_once() {
+        // any transaction not in the chain yet will do, nothing is validated
+        let mut tx = genesis_block(Network::Regtest).txdata[0].clone();
+        tx.lock_time = 1;
+        // the first send reaches the chain but its answer is lost
+        let bio = Retrying::new(Flaky::new(1), policy(3));
+        assert_eq!(bio.send_raw_transaction(&tx).unwrap(), tx.txid());
+        assert_eq!(bio.inner().chain.get_raw_mempool().unwrap(), vec![tx.txid()]);
+
+        // sent before, so refused right away
+        match bio.send_raw_transaction(&tx) {
+            Err(WalletError::BackendRejected(_)) => (),
+            result => panic!("unexpected {:?}", result),
+        }
+    }
+
+    #[test]
+    fn backoff() {
+        let policy = RetryPolicy::default();
+        let delays: Vec<u64> = (1..7)
+            .map(|attempt| policy.backoff(attempt).as_millis() as u64)
+            .collect();
+        assert_eq!(delays, vec![250, 500, 1000, 2000, 4000, 4000]);
+    }
+}
@@ -0,0 +1,300 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Signature hashes
+//!
+//! Legacy and BIP143 signature hashes of any sighash type, `bip143::SighashComponents`
+//! only knows SIGHASH_ALL. Protocols built on the wallet sign some inputs with
+//! SIGHASH_SINGLE|ANYONECANPAY and the like, `verify_input` checks such signatures
+//! of the single key inputs of all account types.
+//!
+use bitcoin::{
+    util::{address::Address, key::PublicKey},
+    blockdata::transaction::{SigHashType, Transaction, TxOut},
+    blockdata::script::Script,
+    network::constants::Network,
+    consensus::encode::serialize,
+};
+use bitcoin_hashes::{sha256d::Hash as Sha256dHash, Hash};
+use secp256k1::{Secp256k1, Message, Signature};
+
+use std::error::Error;
+
+const SIGHASH_NONE: u32 = 0x02;
+const SIGHASH_SINGLE: u32 = 0x03;
+const SIGHASH_ANYONECANPAY: u32 = 0x80;
+
+/// hash the input `i` signs with `sighash_type`, `script_code` is the script of the spent
+/// output of a legacy input and the P2PKH script of the key of a P2WPKH one.
+/// SIGHASH_SINGLE without an output at the index of the input is refused,
+/// such a signature commits to nothing but the constant 1
+pub fn signature_hash(
+    tx: &Transaction,
+    i: usize,
+    script_code: &Script,
+    value: u64,
+    sighash_type: SigHashType,
+    segwit: bool,
+) -> Result<Sha256dHash, Box<dyn Error>> {
+    any_hash(tx, i, script_code, value, sighash_type.as_u32(), segwit)
+}
+
+// `signature_hash` of any sighash byte, even one of no `SigHashType`
+fn any_hash(
+    tx: &Transaction,
+    i: usize,
+    script_code: &Script,
+    value: u64,
+    sighash_type: u32,
+    segwit: bool,
+) -> Result<Sha256dHash, Box<dyn Error>> {
+    if sighash_type & 0x1f == SIGHASH_SINGLE && i >= tx.output.len() {
+        let err = format!("input {} is signed with SIGHASH_SINGLE but has no output", i);
+        return Err(From::from(err));
+    }
+    if segwit {
+        Ok(bip143_hash(tx, i, script_code, value, sighash_type))
+    } else {
+        Ok(tx.signature_hash(i, script_code, sighash_type))
+    }
+}
+
+fn bip143_hash(
+    tx: &Transaction,
+    i: usize,
+    script_code: &Script,
+    value: u64,
+    sighash_type: u32,
+) -> Sha256dHash {
+    let anyone_can_pay = sighash_type & SIGHASH_ANYONECANPAY != 0;
+    let base_type = sighash_type & 0x1f;
+    let zero = [0u8; 32];
+
+    let hash_prevouts = if anyone_can_pay {
+        zero
+    } else {
+        let prevouts: Vec<u8> =
+            tx.input.iter().flat_map(|input| serialize(&input.previous_output)).collect();
+        Sha256dHash::hash(&prevouts).into_inner()
+    };
+    let all_sequences =
+        !anyone_can_pay && base_type != SIGHASH_NONE && base_type != SIGHASH_SINGLE;
+    let hash_sequence = if all_sequences {
+        let sequences: Vec<u8> =
+            tx.input.iter().flat_map(|input| input.sequence.to_le_bytes().to_vec()).collect();
+        Sha256dHash::hash(&sequences).into_inner()
+    } else {
+        zero
+    };
+    let hash_outputs = match base_type {
+        SIGHASH_SINGLE if i < tx.output.len() => {
+            Sha256dHash::hash(&serialize(&tx.output[i])).into_inner()
+        }
+        SIGHASH_SINGLE | SIGHASH_NONE => zero,
+        _ => {
+            let outputs: Vec<u8> = tx.output.iter().flat_map(serialize).collect();
+            Sha256dHash::hash(&outputs).into_inner()
+        }
+    };
+
+    let input = &tx.input[i];
+    let mut data = Vec::new();
+    data.extend_from_slice(&tx.version.to_le_bytes());
+    data.extend_from_slice(&hash_prevouts);
+    data.extend_from_slice(&hash_sequence);
+    data.extend(serialize(&input.previous_output));
+    data.extend(serialize(script_code));
+    data.extend_from_slice(&value.to_le_bytes());
+    data.extend_from_slice(&input.sequence.to_le_bytes());
+    data.extend_from_slice(&hash_outputs);
+    data.extend_from_slice(&tx.lock_time.to_le_bytes());
+    data.extend_from_slice(&sighash_type.to_le_bytes());
+    Sha256dHash::hash(&data)
+}
+
+/// check the signature of the input `i` spending `spent`, by the sighash type it was made with
+pub fn verify_input(tx: &Transaction, i: usize, spent: &TxOut) -> Result<(), Box<dyn Error>> {
+    let (sig, pk, hash) = signed_hash(tx, i, spent)
+        .ok_or_else(|| format!("input {} is not signed by a known template", i))?;
+    // the sighash byte follows the DER signature
+    let sig = Signature::from_der(&sig[..sig.len() - 1])?;
+    Secp256k1::new()
+        .verify(&Message::from_slice(&hash[..])?, &sig, &pk.key)
+        .map_err(|_| format!("invalid signature of input {}", i))?;
+    Ok(())
+}
+
+// signature of the input `i` along with its sighash byte, the key and the hash they sign,
+// the network of the addresses is irrelevant since only their scripts are compared
+pub(crate) fn signed_hash<'a>(
+    tx: &'a Transaction,
+    i: usize,
+    spent: &TxOut,
+) -> Option<(&'a [u8], PublicKey, Sha256dHash)> {
+    let input = &tx.input[i];
+    if input.witness.is_empty() {
+        let pushes = pushes(input.script_sig.as_bytes())?;
+        if pushes.len() != 2 {
+            return None;
+        }
+        let pk = PublicKey::from_slice(pushes[1]).ok()?;
+        if Address::p2pkh(&pk, Network::Bitcoin).script_pubkey() != spent.script_pubkey {
+            return None;
+        }
+        let sighash_type = *pushes[0].last()? as u32;
+        let hash = any_hash(tx, i, &spent.script_pubkey, spent.value, sighash_type, false).ok()?;
+        return Some((pushes[0], pk, hash));
+    }
+
+    if input.witness.len() != 2 || input.witness[0].is_empty() {
+        return None;
+    }
+    let pk = PublicKey::from_slice(&input.witness[1]).ok()?;
+    let p2wpkh = Address::p2wpkh(&pk, Network::Bitcoin).script_pubkey();
+    let script_pubkey = if input.script_sig.is_empty() {
+        p2wpkh
+    } else if pushes(input.script_sig.as_bytes())? == [p2wpkh.as_bytes()] {
+        Address::p2shwpkh(&pk, Network::Bitcoin).script_pubkey()
+    } else {
+        return None;
+    };
+    if script_pubkey != spent.script_pubkey {
+        return None;
+    }
+    let script_code = Address::p2pkh(&pk, Network::Bitcoin).script_pubkey();
+    let sighash_type = *input.witness[0].last()? as u32;
+    let hash = any_hash(tx, i, &script_code, spent.value, sighash_type, true).ok()?;
+    Some((&input.witness[0], pk, hash))
+}
+
+// data of a script made of direct pushes only
+fn pushes(script: &[u8]) -> Option<Vec<&[u8]>> {
+    let mut data = Vec::new();
+    let mut rest = script;
+    while let Some((len, tail)) = rest.split_first() {
+        let len = *len as usize;
+        if len == 0 || len > 75 || tail.len() < len {
+            return None;
+        }
+        data.push(&tail[..len]);
+        rest = &tail[len..];
+    }
+    Some(data)
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::{
+        util::{bip143, address::Address, key::{PublicKey, PrivateKey}},
+        blockdata::transaction::{OutPoint, SigHashType, Transaction, TxIn, TxOut},
+        blockdata::script::Script,
+        network::constants::Network,
+    };
+    use bitcoin_hashes::{sha256d::Hash as Sha256dHash, Hash};
+    use secp256k1::{Secp256k1, SecretKey};
+    use crate::account::AccountAddressType;
+    use crate::walletlibrary::sign_input_with;
+    use super::{signature_hash, verify_input};
+
+    fn unsigned(inputs: u32) -> Transaction {
+        Transaction {
+            version: 2,
+            lock_time: 0,
+            input: (0..inputs)
+                .map(|vout| TxIn {
+                    previous_output: OutPoint {
+                        txid: Sha256dHash::hash(b"funding"),
+                        vout,
+                    },
+                    script_sig: Script::new(),
+                    sequence: 0xFFFFFFFD - vout,
+                    witness: Vec::new(),
+                })
+                .collect(),
+            output: vec![
+                TxOut {
+                    value: 10_000,
+                    script_pubkey: Script::from(vec![0x51]),
+                },
+                TxOut {
+                    value: 20_000,
+                    script_pubkey: Script::from(vec![0x52]),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn bip143_all() {
+        let tx = unsigned(2);
+        let script_code = Script::from(vec![0x51]);
+        for i in 0..2 {
+            let expected = bip143::SighashComponents::new(&tx).sighash_all(
+                &tx.input[i],
+                &script_code,
+                50_000,
+            );
+            let hash = signature_hash(&tx, i, &script_code, 50_000, SigHashType::All, true);
+            assert_eq!(hash.unwrap(), expected);
+        }
+
+        // the third input has no output to sign
+        let tx = unsigned(3);
+        assert!(signature_hash(&tx, 2, &script_code, 50_000, SigHashType::Single, true).is_err());
+        assert!(signature_hash(&tx, 2, &script_code, 50_000, SigHashType::Single, false).is_err());
+    }
+
+    #[test]
+    fn sign_and_verify() {
+        let sk = PrivateKey {
+            compressed: true,
+            network: Network::Regtest,
+            key: SecretKey::from_slice(&[0x11; 32]).unwrap(),
+        };
+        let pk = PublicKey::from_private_key(&Secp256k1::new(), &sk);
+        let coins = [
+            (AccountAddressType::P2PKH, Address::p2pkh(&pk, Network::Regtest)),
+            (AccountAddressType::P2SHWH, Address::p2shwpkh(&pk, Network::Regtest)),
+            (AccountAddressType::P2WKH, Address::p2wpkh(&pk, Network::Regtest)),
+        ];
+        let sighash_types = [
+            SigHashType::All,
+            SigHashType::None,
+            SigHashType::AllPlusAnyoneCanPay,
+            SigHashType::SinglePlusAnyoneCanPay,
+        ];
+        for (addr_type, addr) in coins.iter() {
+            let spent = TxOut {
+                value: 50_000,
+                script_pubkey: addr.script_pubkey(),
+            };
+            for sighash_type in sighash_types.iter() {
+                let mut tx = unsigned(2);
+                sign_input_with(&mut tx, 0, &sk, addr_type, 50_000, *sighash_type).unwrap();
+                verify_input(&tx, 0, &spent).unwrap();
+            }
+
+            // others may add inputs and outputs, the output at the index of the input is fixed
+            let mut tx = unsigned(1);
+            let sighash_type = SigHashType::SinglePlusAnyoneCanPay;
+            sign_input_with(&mut tx, 0, &sk, addr_type, 50_000, sighash_type).unwrap();
+            tx.input.push(unsigned(2).input[1].clone());
+            tx.output[1].value = 15_000;
+            verify_input(&tx, 0, &spent).unwrap();
+            tx.output[0].value = 5_000;
+            assert!(verify_input(&tx, 0, &spent).is_err());
+        }
+    }
+}
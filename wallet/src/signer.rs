@@ -0,0 +1,317 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Offline signing
+//!
+//! A watch-only wallet knows the public keys of its accounts only, its payments wait in a
+//! queue as unsigned PSBTs (BIP174). A signer holding the seed on an air-gapped machine
+//! signs them with `sign_psbt`, the wallet finalizes the signed PSBTs and publishes them.
+//!
+use bitcoin::{
+    blockdata::{
+        script::{Builder, Script},
+        transaction::{SigHashType, Transaction, TxOut},
+    },
+    network::constants::Network,
+    util::{
+        address::Address,
+        bip32::{ExtendedPrivKey, ExtendedPubKey, Fingerprint},
+        key::{PrivateKey, PublicKey},
+        psbt::{self, PartiallySignedTransaction},
+    },
+};
+use secp256k1::{Secp256k1, Message};
+use serde::{Serialize, Deserialize};
+
+use std::{error::Error, str::FromStr};
+
+use super::account::{AccountAddressType, DerivationScheme};
+use super::bip47::PaymentCode;
+use super::keyfactory::{wipe_extended_private_key, wipe_private_key};
+use super::sighash;
+
+/// the public side of a seed, enough to follow its accounts and find the payments to it,
+/// the offline signer exports it to create a watch-only wallet with
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WatchOnlyKeys {
+    /// hex encoded, PSBTs name the keys of the signer by it
+    pub master_fingerprint: String,
+    pub accounts: Vec<WatchOnlyAccount>,
+    /// BIP47 payment code of the seed
+    pub payment_code: String,
+    /// BIP352 scan key in WIF, it finds the silent payments but can't spend them
+    pub silent_payment_scan_key: String,
+    /// hex encoded BIP352 spend key
+    pub silent_payment_spend_pk: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WatchOnlyAccount {
+    pub addr_type: AccountAddressType,
+    pub scheme: DerivationScheme,
+    /// extended public key at the path of `scheme`
+    pub xpub: String,
+}
+
+impl WatchOnlyKeys {
+    pub fn master_fingerprint(&self) -> Result<Fingerprint, Box<dyn Error>> {
+        let bytes = hex::decode(&self.master_fingerprint)?;
+        if bytes.len() != 4 {
+            return Err(From::from("a fingerprint has 4 bytes"));
+        }
+        Ok(Fingerprint::from(&bytes[..]))
+    }
+
+    pub fn silent_payment_scan_sk(&self) -> Result<PrivateKey, Box<dyn Error>> {
+        Ok(PrivateKey::from_wif(&self.silent_payment_scan_key)?)
+    }
+
+    pub fn silent_payment_spend_pk(&self) -> Result<PublicKey, Box<dyn Error>> {
+        Ok(PublicKey::from_slice(&hex::decode(&self.silent_payment_spend_pk)?)?)
+    }
+
+    /// refuse keys which are malformed or lack an account
+    pub fn check(&self) -> Result<(), Box<dyn Error>> {
+        self.master_fingerprint()?;
+        for addr_type in &[
+            AccountAddressType::P2PKH,
+            AccountAddressType::P2SHWH,
+            AccountAddressType::P2WKH,
+        ] {
+            self.account(addr_type)?;
+        }
+        PaymentCode::from_str(&self.payment_code)?;
+        let mut scan_sk = self.silent_payment_scan_sk()?;
+        wipe_private_key(&mut scan_sk);
+        self.silent_payment_spend_pk()?;
+        Ok(())
+    }
+
+    /// derivation scheme and extended public key of the account of `addr_type`
+    pub fn account(
+        &self,
+        addr_type: &AccountAddressType,
+    ) -> Result<(DerivationScheme, ExtendedPubKey), Box<dyn Error>> {
+        let account = self
+            .accounts
+            .iter()
+            .find(|account| account.addr_type == *addr_type)
+            .ok_or_else(|| format!("the keys lack the {} account", addr_type.as_str()))?;
+        Ok((account.scheme, ExtendedPubKey::from_str(&account.xpub)?))
+    }
+}
+
+// the single key templates of the accounts
+#[derive(PartialEq)]
+enum SpendType {
+    P2pkh,
+    P2shP2wpkh,
+    P2wpkh,
+}
+
+impl SpendType {
+    // the template of `script_pubkey` if it pays to `pk`
+    fn of(script_pubkey: &Script, pk: &PublicKey) -> Option<SpendType> {
+        // the network of the addresses is irrelevant since only their scripts are compared
+        if *script_pubkey == Address::p2pkh(pk, Network::Bitcoin).script_pubkey() {
+            Some(SpendType::P2pkh)
+        } else if *script_pubkey == Address::p2shwpkh(pk, Network::Bitcoin).script_pubkey() {
+            Some(SpendType::P2shP2wpkh)
+        } else if *script_pubkey == Address::p2wpkh(pk, Network::Bitcoin).script_pubkey() {
+            Some(SpendType::P2wpkh)
+        } else {
+            None
+        }
+    }
+}
+
+/// the output the input `i` spends, by its `witness_utxo` or the whole transaction
+/// in `non_witness_utxo`
+pub fn spent_output(
+    tx: &Transaction,
+    i: usize,
+    input: &psbt::Input,
+) -> Result<TxOut, Box<dyn Error>> {
+    if let Some(ref output) = input.witness_utxo {
+        return Ok(output.clone());
+    }
+    let prev_out = tx.input[i].previous_output;
+    match input.non_witness_utxo {
+        Some(ref prev_tx) if prev_tx.txid() == prev_out.txid => prev_tx
+            .output
+            .get(prev_out.vout as usize)
+            .cloned()
+            .ok_or_else(|| From::from(format!("input {} spends an unknown output", i))),
+        _ => Err(From::from(format!("input {} lacks the output it spends", i))),
+    }
+}
+
+/// sign the inputs of `psbt` paying to keys of `master_key`, which are found by the BIP32
+/// derivations of the inputs, returns the number of signatures made.
+/// Inputs of other templates and keys of other signers are left alone
+pub fn sign_psbt(
+    master_key: &ExtendedPrivKey,
+    psbt: &mut PartiallySignedTransaction,
+) -> Result<usize, Box<dyn Error>> {
+    let ctx = Secp256k1::new();
+    let fingerprint = master_key.fingerprint(&ctx);
+    let tx = psbt.global.unsigned_tx.clone();
+    let mut signed = 0;
+    for (i, input) in psbt.inputs.iter_mut().enumerate() {
+        let spent = spent_output(&tx, i, input)?;
+        let own_keys: Vec<_> = input
+            .hd_keypaths
+            .iter()
+            .filter(|(_, (key_fingerprint, _))| *key_fingerprint == fingerprint)
+            .map(|(pk, (_, path))| (*pk, path.clone()))
+            .collect();
+        for (pk, path) in own_keys {
+            let spend_type = match SpendType::of(&spent.script_pubkey, &pk) {
+                Some(spend_type) => spend_type,
+                None => continue,
+            };
+            let mut key = master_key.derive_priv(&ctx, &path)?;
+            if PublicKey::from_private_key(&ctx, &key.private_key) != pk {
+                wipe_extended_private_key(&mut key);
+                return Err(From::from(format!("the key of input {} is not at its path", i)));
+            }
+            let sighash_type = input.sighash_type.unwrap_or(SigHashType::All);
+            // the script of P2PKH is the script code of P2WPKH too
+            let script_code = Address::p2pkh(&pk, Network::Bitcoin).script_pubkey();
+            let segwit = spend_type != SpendType::P2pkh;
+            let hash =
+                sighash::signature_hash(&tx, i, &script_code, spent.value, sighash_type, segwit);
+            let signature = hash.and_then(|hash| {
+                let msg = Message::from_slice(&hash[..])?;
+                Ok(ctx.sign(&msg, &key.private_key.key))
+            });
+            wipe_extended_private_key(&mut key);
+
+            let mut serialized_sig = signature?.serialize_der().to_vec();
+            serialized_sig.push(sighash_type.as_u32() as u8);
+            input.partial_sigs.insert(pk, serialized_sig);
+            signed += 1;
+        }
+    }
+    Ok(signed)
+}
+
+/// the transaction of `psbt` with the signature of every input in its script_sig or witness,
+/// all inputs have to spend single key outputs and the signatures are verified
+pub fn finalize_psbt(psbt: &PartiallySignedTransaction) -> Result<Transaction, Box<dyn Error>> {
+    let mut tx = psbt.global.unsigned_tx.clone();
+    for (i, input) in psbt.inputs.iter().enumerate() {
+        let spent = spent_output(&tx, i, input)?;
+        let (pk, sig) = input
+            .partial_sigs
+            .iter()
+            .find(|(pk, _)| SpendType::of(&spent.script_pubkey, pk).is_some())
+            .ok_or_else(|| format!("input {} is not signed", i))?;
+        let pk_bytes = pk.key.serialize().to_vec();
+        match SpendType::of(&spent.script_pubkey, pk).unwrap() {
+            SpendType::P2pkh => {
+                tx.input[i].script_sig = Builder::new()
+                    .push_slice(sig)
+                    .push_slice(&pk_bytes)
+                    .into_script();
+            }
+            SpendType::P2shP2wpkh => {
+                let redeem_script = Address::p2wpkh(pk, Network::Bitcoin).script_pubkey();
+                tx.input[i].script_sig =
+                    Builder::new().push_slice(redeem_script.as_bytes()).into_script();
+                tx.input[i].witness = vec![sig.clone(), pk_bytes];
+            }
+            SpendType::P2wpkh => tx.input[i].witness = vec![sig.clone(), pk_bytes],
+        }
+        sighash::verify_input(&tx, i, &spent)?;
+    }
+    Ok(tx)
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::network::constants::Network;
+
+    use crate::walletlibrary::{
+        WalletLibrary, WalletConfig, WalletLibraryMode, KeyGenConfig, InputFilter,
+    };
+    use crate::default::WalletWithTrustedFullNode;
+    use crate::memory::{MemoryChain, test::payment};
+    use crate::account::{AccountAddressType, DerivationMode};
+    use crate::error::WalletError;
+    use crate::interface::{Wallet, WalletLibraryInterface};
+    use super::sign_psbt;
+
+    #[test]
+    fn watch_only_signing() {
+        let (signer_lib, _) = WalletLibrary::new(
+            WalletConfig::in_memory(),
+            WalletLibraryMode::Create(KeyGenConfig::debug()),
+        )
+        .unwrap();
+        let master_key = signer_lib.master_private().unwrap();
+        let keys = WalletLibrary::watch_only_keys(
+            &master_key,
+            Network::Regtest,
+            DerivationMode::Standard,
+            &[],
+        )
+        .unwrap();
+        let chain = MemoryChain::new(Network::Regtest);
+        let (mut wallet, mnemonic) = WalletWithTrustedFullNode::new(
+            WalletConfig::in_memory(),
+            chain.clone(),
+            WalletLibraryMode::WatchOnly(keys),
+        )
+        .unwrap();
+        assert!(wallet.wallet_lib().is_watch_only());
+        assert_eq!(mnemonic.to_string(), "");
+        assert_eq!(wallet.wallet_lib().descriptors(), signer_lib.descriptors());
+
+        let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        let legacy_addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2PKH).unwrap();
+        chain.push_block(Vec::new());
+        chain.push_block(vec![payment(&addr, 60_000_000), payment(&legacy_addr, 60_000_000)]);
+        wallet.sync_with_tip().unwrap();
+        assert_eq!(wallet.wallet_lib().wallet_balance(), 120_000_000);
+
+        let dest_addr = "bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49".to_owned();
+        let e = wallet
+            .send_coins(dest_addr.clone(), 10_000_000, true, SendOptions::default())
+            .unwrap_err();
+        match e.downcast_ref::<WalletError>() {
+            Some(&WalletError::WatchOnly) => (),
+            _ => panic!("unexpected error {}", e),
+        }
+
+        let (unsigned, psbt) = wallet
+            .wallet_lib_mut()
+            .create_unsigned_tx(dest_addr, 100_000_000, InputFilter::Any, None, None)
+            .unwrap();
+        assert_eq!(psbt.inputs.len(), 2);
+        assert_eq!(wallet.wallet_lib().get_unsigned_txs().len(), 1);
+        // nothing is signed yet
+        assert!(wallet.broadcast_signed_psbt(unsigned.id, &psbt).is_err());
+
+        let mut signed = psbt.clone();
+        assert_eq!(sign_psbt(&master_key, &mut signed).unwrap(), 2);
+        let tx = wallet.broadcast_signed_psbt(unsigned.id, &signed).unwrap();
+        assert_eq!(tx.txid(), unsigned.tx.txid());
+        assert!(wallet.wallet_lib().get_unsigned_txs().is_empty());
+        chain.mine();
+        wallet.sync_with_tip().unwrap();
+        assert_eq!(wallet.wallet_lib().wallet_balance(), 20_000_000 - unsigned.fee);
+    }
+}
@@ -0,0 +1,1634 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # SLIP-0039 Shamir backup
+//!
+//! Split a master secret into mnemonic shares, any `threshold` of them recover it
+//!
+use super::error::WalletError;
+use crypto::pbkdf2;
+use crypto::hmac::Hmac;
+use crypto::mac::Mac;
+use crypto::sha2::Sha256;
+use rand::{rngs::OsRng, RngCore};
+use zeroize::Zeroizing;
+
+const RADIX_BITS: usize = 10;
+const CHECKSUM_LENGTH_WORDS: usize = 3;
+// identifier with iteration exponent and group parameters take two words each
+const HEADER_LENGTH_WORDS: usize = 4;
+const MIN_SECRET_LENGTH_BYTES: usize = 16;
+const MAX_SHARE_COUNT: u8 = 16;
+const DIGEST_LENGTH_BYTES: usize = 4;
+const DIGEST_INDEX: u8 = 254;
+const SECRET_INDEX: u8 = 255;
+const BASE_ITERATION_COUNT: u32 = 10_000;
+const ROUND_COUNT: u8 = 4;
+const CUSTOMIZATION_STRING: &[u8] = b"shamir";
+const CUSTOMIZATION_STRING_EXTENDABLE: &[u8] = b"shamir_extendable";
+
+/// iteration exponent of the key derivation in the encryption of the master secret
+pub const DEFAULT_ITERATION_EXPONENT: u8 = 1;
+
+/// split a master secret into `count` mnemonic shares, any `threshold` of them recover it
+/// the secret is encrypted with `passphrase`, it is needed again for recovery
+pub fn split(
+    master_secret: &[u8],
+    passphrase: &str,
+    threshold: u8,
+    count: u8,
+) -> Result<Vec<String>, WalletError> {
+    if master_secret.len() < MIN_SECRET_LENGTH_BYTES || master_secret.len() % 2 != 0 {
+        return Err(WalletError::InvalidShareParameters);
+    }
+    if threshold == 0 || threshold > count || count > MAX_SHARE_COUNT {
+        return Err(WalletError::InvalidShareParameters);
+    }
+    // a single share would be the encrypted secret itself, handing out copies of it is pointless
+    if threshold == 1 && count > 1 {
+        return Err(WalletError::InvalidShareParameters);
+    }
+
+    let mut rng = OsRng::new().map_err(|_| WalletError::CannotObtainRandomSource)?;
+    let identifier = (rng.next_u32() & 0x7fff) as u16;
+
+    let encrypted = encrypt(
+        master_secret,
+        passphrase,
+        DEFAULT_ITERATION_EXPONENT,
+        identifier,
+        false,
+    );
+    let shares = split_secret(&mut rng, threshold, count, &encrypted)?;
+
+    Ok(shares
+        .into_iter()
+        .map(|(member_index, value)| {
+            Share {
+                identifier,
+                extendable: false,
+                iteration_exponent: DEFAULT_ITERATION_EXPONENT,
+                group_index: 0,
+                group_threshold: 1,
+                group_count: 1,
+                member_index,
+                member_threshold: threshold,
+                value,
+            }
+            .to_mnemonic()
+        })
+        .collect())
+}
+
+/// recover the master secret from mnemonic shares, groups are supported as well
+pub fn combine(mnemonics: &[String], passphrase: &str) -> Result<Zeroizing<Vec<u8>>, WalletError> {
+    let mut shares = Vec::new();
+    for mnemonic in mnemonics {
+        shares.push(Share::from_mnemonic(mnemonic)?);
+    }
+    let first = match shares.first() {
+        Some(first) => first,
+        None => return Err(WalletError::InvalidShareSet),
+    };
+
+    for share in &shares {
+        if share.identifier != first.identifier
+            || share.extendable != first.extendable
+            || share.iteration_exponent != first.iteration_exponent
+            || share.group_threshold != first.group_threshold
+            || share.group_count != first.group_count
+            || share.value.len() != first.value.len()
+        {
+            return Err(WalletError::InvalidShareSet);
+        }
+    }
+
+    // recover a share of every group first, then the encrypted secret from the group shares
+    let mut group_shares = Vec::new();
+    for group_index in 0..first.group_count {
+        let members: Vec<&Share> = shares
+            .iter()
+            .filter(|share| share.group_index == group_index)
+            .collect();
+        let member_threshold = match members.first() {
+            Some(member) => member.member_threshold,
+            None => continue,
+        };
+        if members
+            .iter()
+            .any(|member| member.member_threshold != member_threshold)
+        {
+            return Err(WalletError::InvalidShareSet);
+        }
+        // not enough members to recover the group share, other groups may be enough
+        if members.len() < member_threshold as usize {
+            continue;
+        }
+        let member_shares: Vec<(u8, &[u8])> = members
+            .iter()
+            .map(|member| (member.member_index, member.value.as_slice()))
+            .collect();
+        let group_share = recover_secret(member_threshold, &member_shares)?;
+        group_shares.push((group_index, group_share));
+    }
+
+    let group_shares: Vec<(u8, &[u8])> = group_shares
+        .iter()
+        .map(|(group_index, value)| (*group_index, value.as_slice()))
+        .collect();
+    let encrypted = recover_secret(first.group_threshold, &group_shares)?;
+
+    Ok(decrypt(
+        &encrypted,
+        passphrase,
+        first.iteration_exponent,
+        first.identifier,
+        first.extendable,
+    ))
+}
+
+// x coordinate and value of a point of the sharing polynomial
+type IndexedShare = (u8, Zeroizing<Vec<u8>>);
+
+struct Share {
+    identifier: u16,
+    extendable: bool,
+    iteration_exponent: u8,
+    group_index: u8,
+    group_threshold: u8,
+    group_count: u8,
+    member_index: u8,
+    member_threshold: u8,
+    value: Zeroizing<Vec<u8>>,
+}
+
+impl Share {
+    fn from_mnemonic(mnemonic: &str) -> Result<Self, WalletError> {
+        let mut indices = Vec::new();
+        for word in mnemonic.split_whitespace() {
+            let index = WORDS
+                .binary_search(&word)
+                .map_err(|_| WalletError::UnknownMnemonicWord)?;
+            indices.push(index as u16);
+        }
+        let value_length_words = indices
+            .len()
+            .checked_sub(HEADER_LENGTH_WORDS + CHECKSUM_LENGTH_WORDS)
+            .ok_or(WalletError::InvalidShare)?;
+        if value_length_words * RADIX_BITS < MIN_SECRET_LENGTH_BYTES * 8 {
+            return Err(WalletError::InvalidShare);
+        }
+
+        let id_exp = (u32::from(indices[0]) << RADIX_BITS) | u32::from(indices[1]);
+        let identifier = (id_exp >> 5) as u16;
+        let extendable = (id_exp >> 4) & 1 == 1;
+        let iteration_exponent = (id_exp & 0xf) as u8;
+
+        if rs1024_polymod(customization(extendable), &indices) != 1 {
+            return Err(WalletError::InvalidShare);
+        }
+
+        let params = (u32::from(indices[2]) << RADIX_BITS) | u32::from(indices[3]);
+        let param = |i: u32| ((params >> (16 - 4 * i)) & 0xf) as u8;
+        let group_index = param(0);
+        let group_threshold = param(1) + 1;
+        let group_count = param(2) + 1;
+        let member_index = param(3);
+        let member_threshold = param(4) + 1;
+        if group_threshold > group_count {
+            return Err(WalletError::InvalidShare);
+        }
+
+        let value_words = &indices[HEADER_LENGTH_WORDS..indices.len() - CHECKSUM_LENGTH_WORDS];
+        let value = words_to_bytes(value_words)?;
+
+        Ok(Share {
+            identifier,
+            extendable,
+            iteration_exponent,
+            group_index,
+            group_threshold,
+            group_count,
+            member_index,
+            member_threshold,
+            value,
+        })
+    }
+
+    fn to_mnemonic(&self) -> String {
+        let id_exp = (u32::from(self.identifier) << 5)
+            | (if self.extendable { 1 << 4 } else { 0 })
+            | u32::from(self.iteration_exponent);
+        let params = [
+            self.group_index,
+            self.group_threshold - 1,
+            self.group_count - 1,
+            self.member_index,
+            self.member_threshold - 1,
+        ]
+        .iter()
+        .fold(0u32, |acc, &param| (acc << 4) | u32::from(param));
+
+        let mut indices = vec![
+            (id_exp >> RADIX_BITS) as u16,
+            (id_exp & 0x3ff) as u16,
+            (params >> RADIX_BITS) as u16,
+            (params & 0x3ff) as u16,
+        ];
+        indices.extend(bytes_to_words(&self.value));
+
+        let mut values = indices.clone();
+        values.extend_from_slice(&[0; CHECKSUM_LENGTH_WORDS]);
+        let checksum = rs1024_polymod(customization(self.extendable), &values) ^ 1;
+        for i in (0..CHECKSUM_LENGTH_WORDS).rev() {
+            indices.push(((checksum >> (RADIX_BITS * i)) & 0x3ff) as u16);
+        }
+
+        indices
+            .iter()
+            .map(|&index| WORDS[index as usize])
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+fn customization(extendable: bool) -> &'static [u8] {
+    if extendable {
+        CUSTOMIZATION_STRING_EXTENDABLE
+    } else {
+        CUSTOMIZATION_STRING
+    }
+}
+
+// the value is prefixed with zero bits to fill whole words
+fn bytes_to_words(data: &[u8]) -> Vec<u16> {
+    let word_count = (data.len() * 8 + RADIX_BITS - 1) / RADIX_BITS;
+    let mut words = Vec::with_capacity(word_count);
+    let mut accumulator = 0u32;
+    let mut bits = word_count * RADIX_BITS - data.len() * 8;
+    for &byte in data {
+        accumulator = (accumulator << 8) | u32::from(byte);
+        bits += 8;
+        if bits >= RADIX_BITS {
+            bits -= RADIX_BITS;
+            words.push((accumulator >> bits) as u16 & 0x3ff);
+            accumulator &= (1 << bits) - 1;
+        }
+    }
+    words
+}
+
+fn words_to_bytes(words: &[u16]) -> Result<Zeroizing<Vec<u8>>, WalletError> {
+    let padding = words.len() * RADIX_BITS % 16;
+    if padding > 8 {
+        return Err(WalletError::InvalidShare);
+    }
+    let mut data = Zeroizing::new(Vec::with_capacity((words.len() * RADIX_BITS - padding) / 8));
+    let mut accumulator = 0u32;
+    let mut bits = 0;
+    let mut skip = padding;
+    for &word in words {
+        accumulator = (accumulator << RADIX_BITS) | u32::from(word);
+        bits += RADIX_BITS;
+        if skip > 0 {
+            if accumulator >> (bits - skip) != 0 {
+                return Err(WalletError::InvalidShare);
+            }
+            bits -= skip;
+            accumulator &= (1 << bits) - 1;
+            skip = 0;
+        }
+        while bits >= 8 {
+            bits -= 8;
+            data.push((accumulator >> bits) as u8);
+            accumulator &= (1 << bits) - 1;
+        }
+    }
+    Ok(data)
+}
+
+fn rs1024_polymod(customization: &[u8], values: &[u16]) -> u32 {
+    const GEN: [u32; 10] = [
+        0x00e0_e040,
+        0x01c1_c080,
+        0x0383_8100,
+        0x0707_0200,
+        0x0e0e_0009,
+        0x1c0c_2412,
+        0x3808_6c24,
+        0x3090_fc48,
+        0x21b1_f890,
+        0x03f3_f120,
+    ];
+    let mut checksum = 1u32;
+    let values = customization
+        .iter()
+        .map(|&b| u32::from(b))
+        .chain(values.iter().map(|&v| u32::from(v)));
+    for value in values {
+        let top = checksum >> 20;
+        checksum = ((checksum & 0xfffff) << RADIX_BITS) ^ value;
+        for (i, gen) in GEN.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= gen;
+            }
+        }
+    }
+    checksum
+}
+
+// four round Feistel network, the round function is PBKDF2 keyed by the passphrase
+fn feistel(
+    data: &[u8],
+    passphrase: &str,
+    iteration_exponent: u8,
+    identifier: u16,
+    extendable: bool,
+    rounds: &[u8],
+) -> Zeroizing<Vec<u8>> {
+    let half = data.len() / 2;
+    let mut left = Zeroizing::new(data[..half].to_vec());
+    let mut right = Zeroizing::new(data[half..].to_vec());
+
+    let mut salt = Vec::new();
+    if !extendable {
+        salt.extend_from_slice(CUSTOMIZATION_STRING);
+        salt.extend_from_slice(&[(identifier >> 8) as u8, identifier as u8]);
+    }
+    let iterations = (BASE_ITERATION_COUNT << iteration_exponent) / u32::from(ROUND_COUNT);
+
+    for &round in rounds {
+        let mut password = Zeroizing::new(vec![round]);
+        password.extend_from_slice(passphrase.as_bytes());
+        let mut round_salt = Zeroizing::new(salt.clone());
+        round_salt.extend_from_slice(&right);
+
+        let mut mac = Hmac::new(Sha256::new(), &password);
+        let mut f = Zeroizing::new(vec![0u8; half]);
+        pbkdf2::pbkdf2(&mut mac, &round_salt, iterations, &mut f);
+
+        for (l, f) in left.iter_mut().zip(f.iter()) {
+            *l ^= f;
+        }
+        std::mem::swap(&mut left, &mut right);
+    }
+
+    let mut result = Zeroizing::new(right.to_vec());
+    result.extend_from_slice(&left);
+    result
+}
+
+fn encrypt(
+    master_secret: &[u8],
+    passphrase: &str,
+    iteration_exponent: u8,
+    identifier: u16,
+    extendable: bool,
+) -> Zeroizing<Vec<u8>> {
+    let rounds: Vec<u8> = (0..ROUND_COUNT).collect();
+    feistel(master_secret, passphrase, iteration_exponent, identifier, extendable, &rounds)
+}
+
+fn decrypt(
+    encrypted: &[u8],
+    passphrase: &str,
+    iteration_exponent: u8,
+    identifier: u16,
+    extendable: bool,
+) -> Zeroizing<Vec<u8>> {
+    let rounds: Vec<u8> = (0..ROUND_COUNT).rev().collect();
+    feistel(encrypted, passphrase, iteration_exponent, identifier, extendable, &rounds)
+}
+
+fn split_secret(
+    rng: &mut OsRng,
+    threshold: u8,
+    count: u8,
+    secret: &[u8],
+) -> Result<Vec<IndexedShare>, WalletError> {
+    if threshold == 1 {
+        return Ok((0..count)
+            .map(|index| (index, Zeroizing::new(secret.to_vec())))
+            .collect());
+    }
+
+    // `threshold - 2` random shares, the digest and the secret determine the polynomial
+    let mut shares = Vec::new();
+    for index in 0..threshold - 2 {
+        let mut value = Zeroizing::new(vec![0u8; secret.len()]);
+        rng.fill_bytes(&mut value);
+        shares.push((index, value));
+    }
+
+    let mut random_part = Zeroizing::new(vec![0u8; secret.len() - DIGEST_LENGTH_BYTES]);
+    rng.fill_bytes(&mut random_part);
+    let mut digest = Zeroizing::new(share_digest(&random_part, secret));
+    digest.extend_from_slice(&random_part);
+
+    let mut base: Vec<(u8, &[u8])> = shares
+        .iter()
+        .map(|(index, value)| (*index, value.as_slice()))
+        .collect();
+    base.push((DIGEST_INDEX, digest.as_slice()));
+    base.push((SECRET_INDEX, secret));
+
+    let mut result = Vec::new();
+    for index in threshold - 2..count {
+        result.push((index, interpolate(&base, index)?));
+    }
+    shares.extend(result);
+    Ok(shares)
+}
+
+fn recover_secret(
+    threshold: u8,
+    shares: &[(u8, &[u8])],
+) -> Result<Zeroizing<Vec<u8>>, WalletError> {
+    let mut indices: Vec<u8> = shares.iter().map(|(index, _)| *index).collect();
+    indices.sort();
+    indices.dedup();
+    if indices.len() != shares.len() || shares.len() < threshold as usize {
+        return Err(WalletError::InvalidShareSet);
+    }
+    let shares = &shares[..threshold as usize];
+
+    if threshold == 1 {
+        return Ok(Zeroizing::new(shares[0].1.to_vec()));
+    }
+
+    let secret = interpolate(shares, SECRET_INDEX)?;
+    let digest = interpolate(shares, DIGEST_INDEX)?;
+    let (digest, random_part) = digest.split_at(DIGEST_LENGTH_BYTES);
+    if share_digest(random_part, &secret) != digest {
+        return Err(WalletError::ShareDigestNotMatch);
+    }
+    Ok(secret)
+}
+
+fn share_digest(random_part: &[u8], secret: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::new(Sha256::new(), random_part);
+    mac.input(secret);
+    mac.result().code()[..DIGEST_LENGTH_BYTES].to_vec()
+}
+
+// Lagrange interpolation in GF(256) with the Rijndael polynomial
+fn interpolate(shares: &[(u8, &[u8])], x: u8) -> Result<Zeroizing<Vec<u8>>, WalletError> {
+    if let Some((_, value)) = shares.iter().find(|(index, _)| *index == x) {
+        return Ok(Zeroizing::new(value.to_vec()));
+    }
+
+    let (exp, log) = gf256_tables();
+    let length = shares[0].1.len();
+    if shares.iter().any(|(_, value)| value.len() != length) {
+        return Err(WalletError::InvalidShareSet);
+    }
+
+    let log_product: usize = shares
+        .iter()
+        .map(|(index, _)| log[(index ^ x) as usize] as usize)
+        .sum();
+
+    let mut result = Zeroizing::new(vec![0u8; length]);
+    for (index, value) in shares {
+        let log_denominator: usize = shares
+            .iter()
+            .map(|(other, _)| log[(index ^ other) as usize] as usize)
+            .sum();
+        let log_basis = (log_product + 255 * shares.len()
+            - log[(index ^ x) as usize] as usize
+            - log_denominator)
+            % 255;
+        for (r, &v) in result.iter_mut().zip(value.iter()) {
+            if v != 0 {
+                *r ^= exp[(log[v as usize] as usize + log_basis) % 255];
+            }
+        }
+    }
+    Ok(result)
+}
+
+fn gf256_tables() -> ([u8; 255], [u8; 256]) {
+    let mut exp = [0u8; 255];
+    let mut log = [0u8; 256];
+    let mut poly = 1u16;
+    for (i, e) in exp.iter_mut().enumerate() {
+        *e = poly as u8;
+        log[poly as usize] = i as u8;
+        // multiply by the generator x + 1
+        poly = (poly << 1) ^ poly;
+        if poly & 0x100 != 0 {
+            poly ^= 0x11b;
+        }
+    }
+    (exp, log)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn shares(mnemonics: &[&str]) -> Vec<String> {
+        mnemonics.iter().map(|&m| m.to_owned()).collect()
+    }
+
+    #[test]
+    fn test_vectors() {
+        // SLIP-0039 test vectors, all of them use the passphrase "TREZOR"
+        let single = shares(&[
+            "duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision keyboard",
+        ]);
+        assert_eq!(
+            hex::encode(&*combine(&single, "TREZOR").unwrap()),
+            "bb54aac4b89dc868ba37d9cc21b2cece"
+        );
+
+        let two_of_three = shares(&[
+            "shadow pistol academic always adequate wildlife fancy gross oasis cylinder mustang wrist rescue view short owner flip making coding armed",
+            "shadow pistol academic acid actress prayer class unknown daughter sweater depict flip twice unkind craft early superior advocate guest smoking",
+        ]);
+        assert_eq!(
+            hex::encode(&*combine(&two_of_three, "TREZOR").unwrap()),
+            "b43ceb7e57a0ea8766221624d01b0864"
+        );
+        assert!(combine(&two_of_three[..1], "TREZOR").is_err());
+
+        let invalid_checksum = shares(&[
+            "duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision kidney",
+        ]);
+        assert!(combine(&invalid_checksum, "TREZOR").is_err());
+    }
+
+    #[test]
+    fn test_split_and_combine() {
+        for secret in &[
+            hex::decode("bb54aac4b89dc868ba37d9cc21b2cece").unwrap(),
+            hex::decode("7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f").unwrap(),
+            hex::decode("8080808080808080808080808080808080808080808080808080808080808080").unwrap(),
+        ] {
+            let mnemonics = split(secret, "passphrase", 2, 3).unwrap();
+            assert_eq!(mnemonics.len(), 3);
+            for i in 0..3 {
+                for j in 0..3 {
+                    if i != j {
+                        let pair = [mnemonics[i].clone(), mnemonics[j].clone()];
+                        assert_eq!(*combine(&pair, "passphrase").unwrap(), *secret);
+                    }
+                }
+            }
+
+            let mnemonics = split(secret, "", 3, 5).unwrap();
+            assert_eq!(*combine(&mnemonics[2..], "").unwrap(), *secret);
+            assert!(combine(&mnemonics[3..], "").is_err());
+        }
+
+        let secret = hex::decode("bb54aac4b89dc868ba37d9cc21b2cece").unwrap();
+        assert!(split(&secret, "", 4, 3).is_err());
+        assert!(split(&secret, "", 1, 3).is_err());
+        assert!(split(&secret[1..], "", 2, 3).is_err());
+
+        // shares of different splits don't belong together
+        let first = split(&secret, "", 2, 3).unwrap();
+        let second = split(&secret, "", 2, 3).unwrap();
+        let mixed = [first[0].clone(), second[1].clone()];
+        assert!(combine(&mixed, "").is_err());
+    }
+}
+
+const WORDS: [&str; 1024] = [
+    "academic",
+    "acid",
+    "acne",
+    "acquire",
+    "acrobat",
+    "activity",
+    "actress",
+    "adapt",
+    "adequate",
+    "adjust",
+    "admit",
+    "adorn",
+    "adult",
+    "advance",
+    "advocate",
+    "afraid",
+    "again",
+    "agency",
+    "agree",
+    "aide",
+    "aircraft",
+    "airline",
+    "airport",
+    "ajar",
+    "alarm",
+    "album",
+    "alcohol",
+    "alien",
+    "alive",
+    "alpha",
+    "already",
+    "alto",
+    "aluminum",
+    "always",
+    "amazing",
+    "ambition",
+    "amount",
+    "amuse",
+    "analysis",
+    "anatomy",
+    "ancestor",
+    "ancient",
+    "angel",
+    "angry",
+    "animal",
+    "answer",
+    "antenna",
+    "anxiety",
+    "apart",
+    "aquatic",
+    "arcade",
+    "arena",
+    "argue",
+    "armed",
+    "artist",
+    "artwork",
+    "aspect",
+    "auction",
+    "august",
+    "aunt",
+    "average",
+    "aviation",
+    "avoid",
+    "award",
+    "away",
+    "axis",
+    "axle",
+    "beam",
+    "beard",
+    "beaver",
+    "become",
+    "bedroom",
+    "behavior",
+    "being",
+    "believe",
+    "belong",
+    "benefit",
+    "best",
+    "beyond",
+    "bike",
+    "biology",
+    "birthday",
+    "bishop",
+    "black",
+    "blanket",
+    "blessing",
+    "blimp",
+    "blind",
+    "blue",
+    "body",
+    "bolt",
+    "boring",
+    "born",
+    "both",
+    "boundary",
+    "bracelet",
+    "branch",
+    "brave",
+    "breathe",
+    "briefing",
+    "broken",
+    "brother",
+    "browser",
+    "bucket",
+    "budget",
+    "building",
+    "bulb",
+    "bulge",
+    "bumpy",
+    "bundle",
+    "burden",
+    "burning",
+    "busy",
+    "buyer",
+    "cage",
+    "calcium",
+    "camera",
+    "campus",
+    "canyon",
+    "capacity",
+    "capital",
+    "capture",
+    "carbon",
+    "cards",
+    "careful",
+    "cargo",
+    "carpet",
+    "carve",
+    "category",
+    "cause",
+    "ceiling",
+    "center",
+    "ceramic",
+    "champion",
+    "change",
+    "charity",
+    "check",
+    "chemical",
+    "chest",
+    "chew",
+    "chubby",
+    "cinema",
+    "civil",
+    "class",
+    "clay",
+    "cleanup",
+    "client",
+    "climate",
+    "clinic",
+    "clock",
+    "clogs",
+    "closet",
+    "clothes",
+    "club",
+    "cluster",
+    "coal",
+    "coastal",
+    "coding",
+    "column",
+    "company",
+    "corner",
+    "costume",
+    "counter",
+    "course",
+    "cover",
+    "cowboy",
+    "cradle",
+    "craft",
+    "crazy",
+    "credit",
+    "cricket",
+    "criminal",
+    "crisis",
+    "critical",
+    "crowd",
+    "crucial",
+    "crunch",
+    "crush",
+    "crystal",
+    "cubic",
+    "cultural",
+    "curious",
+    "curly",
+    "custody",
+    "cylinder",
+    "daisy",
+    "damage",
+    "dance",
+    "darkness",
+    "database",
+    "daughter",
+    "deadline",
+    "deal",
+    "debris",
+    "debut",
+    "decent",
+    "decision",
+    "declare",
+    "decorate",
+    "decrease",
+    "deliver",
+    "demand",
+    "density",
+    "deny",
+    "depart",
+    "depend",
+    "depict",
+    "deploy",
+    "describe",
+    "desert",
+    "desire",
+    "desktop",
+    "destroy",
+    "detailed",
+    "detect",
+    "device",
+    "devote",
+    "diagnose",
+    "dictate",
+    "diet",
+    "dilemma",
+    "diminish",
+    "dining",
+    "diploma",
+    "disaster",
+    "discuss",
+    "disease",
+    "dish",
+    "dismiss",
+    "display",
+    "distance",
+    "dive",
+    "divorce",
+    "document",
+    "domain",
+    "domestic",
+    "dominant",
+    "dough",
+    "downtown",
+    "dragon",
+    "dramatic",
+    "dream",
+    "dress",
+    "drift",
+    "drink",
+    "drove",
+    "drug",
+    "dryer",
+    "duckling",
+    "duke",
+    "duration",
+    "dwarf",
+    "dynamic",
+    "early",
+    "earth",
+    "easel",
+    "easy",
+    "echo",
+    "eclipse",
+    "ecology",
+    "edge",
+    "editor",
+    "educate",
+    "either",
+    "elbow",
+    "elder",
+    "election",
+    "elegant",
+    "element",
+    "elephant",
+    "elevator",
+    "elite",
+    "else",
+    "email",
+    "emerald",
+    "emission",
+    "emperor",
+    "emphasis",
+    "employer",
+    "empty",
+    "ending",
+    "endless",
+    "endorse",
+    "enemy",
+    "energy",
+    "enforce",
+    "engage",
+    "enjoy",
+    "enlarge",
+    "entrance",
+    "envelope",
+    "envy",
+    "epidemic",
+    "episode",
+    "equation",
+    "equip",
+    "eraser",
+    "erode",
+    "escape",
+    "estate",
+    "estimate",
+    "evaluate",
+    "evening",
+    "evidence",
+    "evil",
+    "evoke",
+    "exact",
+    "example",
+    "exceed",
+    "exchange",
+    "exclude",
+    "excuse",
+    "execute",
+    "exercise",
+    "exhaust",
+    "exotic",
+    "expand",
+    "expect",
+    "explain",
+    "express",
+    "extend",
+    "extra",
+    "eyebrow",
+    "facility",
+    "fact",
+    "failure",
+    "faint",
+    "fake",
+    "false",
+    "family",
+    "famous",
+    "fancy",
+    "fangs",
+    "fantasy",
+    "fatal",
+    "fatigue",
+    "favorite",
+    "fawn",
+    "fiber",
+    "fiction",
+    "filter",
+    "finance",
+    "findings",
+    "finger",
+    "firefly",
+    "firm",
+    "fiscal",
+    "fishing",
+    "fitness",
+    "flame",
+    "flash",
+    "flavor",
+    "flea",
+    "flexible",
+    "flip",
+    "float",
+    "floral",
+    "fluff",
+    "focus",
+    "forbid",
+    "force",
+    "forecast",
+    "forget",
+    "formal",
+    "fortune",
+    "forward",
+    "founder",
+    "fraction",
+    "fragment",
+    "frequent",
+    "freshman",
+    "friar",
+    "fridge",
+    "friendly",
+    "frost",
+    "froth",
+    "frozen",
+    "fumes",
+    "funding",
+    "furl",
+    "fused",
+    "galaxy",
+    "game",
+    "garbage",
+    "garden",
+    "garlic",
+    "gasoline",
+    "gather",
+    "general",
+    "genius",
+    "genre",
+    "genuine",
+    "geology",
+    "gesture",
+    "glad",
+    "glance",
+    "glasses",
+    "glen",
+    "glimpse",
+    "goat",
+    "golden",
+    "graduate",
+    "grant",
+    "grasp",
+    "gravity",
+    "gray",
+    "greatest",
+    "grief",
+    "grill",
+    "grin",
+    "grocery",
+    "gross",
+    "group",
+    "grownup",
+    "grumpy",
+    "guard",
+    "guest",
+    "guilt",
+    "guitar",
+    "gums",
+    "hairy",
+    "hamster",
+    "hand",
+    "hanger",
+    "harvest",
+    "have",
+    "havoc",
+    "hawk",
+    "hazard",
+    "headset",
+    "health",
+    "hearing",
+    "heat",
+    "helpful",
+    "herald",
+    "herd",
+    "hesitate",
+    "hobo",
+    "holiday",
+    "holy",
+    "home",
+    "hormone",
+    "hospital",
+    "hour",
+    "huge",
+    "human",
+    "humidity",
+    "hunting",
+    "husband",
+    "hush",
+    "husky",
+    "hybrid",
+    "idea",
+    "identify",
+    "idle",
+    "image",
+    "impact",
+    "imply",
+    "improve",
+    "impulse",
+    "include",
+    "income",
+    "increase",
+    "index",
+    "indicate",
+    "industry",
+    "infant",
+    "inform",
+    "inherit",
+    "injury",
+    "inmate",
+    "insect",
+    "inside",
+    "install",
+    "intend",
+    "intimate",
+    "invasion",
+    "involve",
+    "iris",
+    "island",
+    "isolate",
+    "item",
+    "ivory",
+    "jacket",
+    "jerky",
+    "jewelry",
+    "join",
+    "judicial",
+    "juice",
+    "jump",
+    "junction",
+    "junior",
+    "junk",
+    "jury",
+    "justice",
+    "kernel",
+    "keyboard",
+    "kidney",
+    "kind",
+    "kitchen",
+    "knife",
+    "knit",
+    "laden",
+    "ladle",
+    "ladybug",
+    "lair",
+    "lamp",
+    "language",
+    "large",
+    "laser",
+    "laundry",
+    "lawsuit",
+    "leader",
+    "leaf",
+    "learn",
+    "leaves",
+    "lecture",
+    "legal",
+    "legend",
+    "legs",
+    "lend",
+    "length",
+    "level",
+    "liberty",
+    "library",
+    "license",
+    "lift",
+    "likely",
+    "lilac",
+    "lily",
+    "lips",
+    "liquid",
+    "listen",
+    "literary",
+    "living",
+    "lizard",
+    "loan",
+    "lobe",
+    "location",
+    "losing",
+    "loud",
+    "loyalty",
+    "luck",
+    "lunar",
+    "lunch",
+    "lungs",
+    "luxury",
+    "lying",
+    "lyrics",
+    "machine",
+    "magazine",
+    "maiden",
+    "mailman",
+    "main",
+    "makeup",
+    "making",
+    "mama",
+    "manager",
+    "mandate",
+    "mansion",
+    "manual",
+    "marathon",
+    "march",
+    "market",
+    "marvel",
+    "mason",
+    "material",
+    "math",
+    "maximum",
+    "mayor",
+    "meaning",
+    "medal",
+    "medical",
+    "member",
+    "memory",
+    "mental",
+    "merchant",
+    "merit",
+    "method",
+    "metric",
+    "midst",
+    "mild",
+    "military",
+    "mineral",
+    "minister",
+    "miracle",
+    "mixed",
+    "mixture",
+    "mobile",
+    "modern",
+    "modify",
+    "moisture",
+    "moment",
+    "morning",
+    "mortgage",
+    "mother",
+    "mountain",
+    "mouse",
+    "move",
+    "much",
+    "mule",
+    "multiple",
+    "muscle",
+    "museum",
+    "music",
+    "mustang",
+    "nail",
+    "national",
+    "necklace",
+    "negative",
+    "nervous",
+    "network",
+    "news",
+    "nuclear",
+    "numb",
+    "numerous",
+    "nylon",
+    "oasis",
+    "obesity",
+    "object",
+    "observe",
+    "obtain",
+    "ocean",
+    "often",
+    "olympic",
+    "omit",
+    "oral",
+    "orange",
+    "orbit",
+    "order",
+    "ordinary",
+    "organize",
+    "ounce",
+    "oven",
+    "overall",
+    "owner",
+    "paces",
+    "pacific",
+    "package",
+    "paid",
+    "painting",
+    "pajamas",
+    "pancake",
+    "pants",
+    "papa",
+    "paper",
+    "parcel",
+    "parking",
+    "party",
+    "patent",
+    "patrol",
+    "payment",
+    "payroll",
+    "peaceful",
+    "peanut",
+    "peasant",
+    "pecan",
+    "penalty",
+    "pencil",
+    "percent",
+    "perfect",
+    "permit",
+    "petition",
+    "phantom",
+    "pharmacy",
+    "photo",
+    "phrase",
+    "physics",
+    "pickup",
+    "picture",
+    "piece",
+    "pile",
+    "pink",
+    "pipeline",
+    "pistol",
+    "pitch",
+    "plains",
+    "plan",
+    "plastic",
+    "platform",
+    "playoff",
+    "pleasure",
+    "plot",
+    "plunge",
+    "practice",
+    "prayer",
+    "preach",
+    "predator",
+    "pregnant",
+    "premium",
+    "prepare",
+    "presence",
+    "prevent",
+    "priest",
+    "primary",
+    "priority",
+    "prisoner",
+    "privacy",
+    "prize",
+    "problem",
+    "process",
+    "profile",
+    "program",
+    "promise",
+    "prospect",
+    "provide",
+    "prune",
+    "public",
+    "pulse",
+    "pumps",
+    "punish",
+    "puny",
+    "pupal",
+    "purchase",
+    "purple",
+    "python",
+    "quantity",
+    "quarter",
+    "quick",
+    "quiet",
+    "race",
+    "racism",
+    "radar",
+    "railroad",
+    "rainbow",
+    "raisin",
+    "random",
+    "ranked",
+    "rapids",
+    "raspy",
+    "reaction",
+    "realize",
+    "rebound",
+    "rebuild",
+    "recall",
+    "receiver",
+    "recover",
+    "regret",
+    "regular",
+    "reject",
+    "relate",
+    "remember",
+    "remind",
+    "remove",
+    "render",
+    "repair",
+    "repeat",
+    "replace",
+    "require",
+    "rescue",
+    "research",
+    "resident",
+    "response",
+    "result",
+    "retailer",
+    "retreat",
+    "reunion",
+    "revenue",
+    "review",
+    "reward",
+    "rhyme",
+    "rhythm",
+    "rich",
+    "rival",
+    "river",
+    "robin",
+    "rocky",
+    "romantic",
+    "romp",
+    "roster",
+    "round",
+    "royal",
+    "ruin",
+    "ruler",
+    "rumor",
+    "sack",
+    "safari",
+    "salary",
+    "salon",
+    "salt",
+    "satisfy",
+    "satoshi",
+    "saver",
+    "says",
+    "scandal",
+    "scared",
+    "scatter",
+    "scene",
+    "scholar",
+    "science",
+    "scout",
+    "scramble",
+    "screw",
+    "script",
+    "scroll",
+    "seafood",
+    "season",
+    "secret",
+    "security",
+    "segment",
+    "senior",
+    "shadow",
+    "shaft",
+    "shame",
+    "shaped",
+    "sharp",
+    "shelter",
+    "sheriff",
+    "short",
+    "should",
+    "shrimp",
+    "sidewalk",
+    "silent",
+    "silver",
+    "similar",
+    "simple",
+    "single",
+    "sister",
+    "skin",
+    "skunk",
+    "slap",
+    "slavery",
+    "sled",
+    "slice",
+    "slim",
+    "slow",
+    "slush",
+    "smart",
+    "smear",
+    "smell",
+    "smirk",
+    "smith",
+    "smoking",
+    "smug",
+    "snake",
+    "snapshot",
+    "sniff",
+    "society",
+    "software",
+    "soldier",
+    "solution",
+    "soul",
+    "source",
+    "space",
+    "spark",
+    "speak",
+    "species",
+    "spelling",
+    "spend",
+    "spew",
+    "spider",
+    "spill",
+    "spine",
+    "spirit",
+    "spit",
+    "spray",
+    "sprinkle",
+    "square",
+    "squeeze",
+    "stadium",
+    "staff",
+    "standard",
+    "starting",
+    "station",
+    "stay",
+    "steady",
+    "step",
+    "stick",
+    "stilt",
+    "story",
+    "strategy",
+    "strike",
+    "style",
+    "subject",
+    "submit",
+    "sugar",
+    "suitable",
+    "sunlight",
+    "superior",
+    "surface",
+    "surprise",
+    "survive",
+    "sweater",
+    "swimming",
+    "swing",
+    "switch",
+    "symbolic",
+    "sympathy",
+    "syndrome",
+    "system",
+    "tackle",
+    "tactics",
+    "tadpole",
+    "talent",
+    "task",
+    "taste",
+    "taught",
+    "taxi",
+    "teacher",
+    "teammate",
+    "teaspoon",
+    "temple",
+    "tenant",
+    "tendency",
+    "tension",
+    "terminal",
+    "testify",
+    "texture",
+    "thank",
+    "that",
+    "theater",
+    "theory",
+    "therapy",
+    "thorn",
+    "threaten",
+    "thumb",
+    "thunder",
+    "ticket",
+    "tidy",
+    "timber",
+    "timely",
+    "ting",
+    "tofu",
+    "together",
+    "tolerate",
+    "total",
+    "toxic",
+    "tracks",
+    "traffic",
+    "training",
+    "transfer",
+    "trash",
+    "traveler",
+    "treat",
+    "trend",
+    "trial",
+    "tricycle",
+    "trip",
+    "triumph",
+    "trouble",
+    "true",
+    "trust",
+    "twice",
+    "twin",
+    "type",
+    "typical",
+    "ugly",
+    "ultimate",
+    "umbrella",
+    "uncover",
+    "undergo",
+    "unfair",
+    "unfold",
+    "unhappy",
+    "union",
+    "universe",
+    "unkind",
+    "unknown",
+    "unusual",
+    "unwrap",
+    "upgrade",
+    "upstairs",
+    "username",
+    "usher",
+    "usual",
+    "valid",
+    "valuable",
+    "vampire",
+    "vanish",
+    "various",
+    "vegan",
+    "velvet",
+    "venture",
+    "verdict",
+    "verify",
+    "very",
+    "veteran",
+    "vexed",
+    "victim",
+    "video",
+    "view",
+    "vintage",
+    "violence",
+    "viral",
+    "visitor",
+    "visual",
+    "vitamins",
+    "vocal",
+    "voice",
+    "volume",
+    "voter",
+    "voting",
+    "walnut",
+    "warmth",
+    "warn",
+    "watch",
+    "wavy",
+    "wealthy",
+    "weapon",
+    "webcam",
+    "welcome",
+    "welfare",
+    "western",
+    "width",
+    "wildlife",
+    "window",
+    "wine",
+    "wireless",
+    "wisdom",
+    "withdraw",
+    "wits",
+    "wolf",
+    "woman",
+    "work",
+    "worthy",
+    "wrap",
+    "wrist",
+    "writing",
+    "wrote",
+    "year",
+    "yelp",
+    "yield",
+    "yoga",
+    "zero",
+];
@@ -0,0 +1,417 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # State snapshot
+//!
+//! What the syncs of a wallet found: its coins, the keys derived so far, the history and
+//! the tip. A wallet of the same seed on another machine imports it and syncs on from the
+//! tip instead of scanning the chain again. There is no private key in it, but it tells
+//! every address and coin of the wallet.
+//!
+//! file layout: magic, version, JSON of the snapshot, SHA256 of the bytes before
+//!
+use bitcoin::{OutPoint, Script, Transaction};
+use bitcoin::util::key::PublicKey;
+use bitcoin_hashes::{sha256, sha256d::Hash as Sha256dHash, Hash};
+use serde::{Serialize, Deserialize};
+
+use std::{fs, path::Path};
+
+use super::error::WalletError;
+use super::account::{Utxo, SecretKeyHelper, AccountAddressType, KeyPath};
+use super::walletlibrary::{
+    ImportedUtxo, WatchedUtxo, SilentPaymentUtxo, SpentUtxo, TimelockedUtxo, ScriptAccount,
+    ScriptUtxo, VaultUtxo,
+};
+use super::timelock::Timelock;
+use super::vault::Vault;
+use super::history::{TxRecord, AddressStats};
+use super::headers::HeaderRecord;
+use super::DB;
+
+const MAGIC: &[u8] = b"RWSS";
+const VERSION: u8 = 1;
+const CHECKSUM_LENGTH: usize = 32;
+
+/// the chain state of a wallet, the seed and the settings stay with the wallet importing it
+#[derive(Serialize, Deserialize)]
+pub struct StateSnapshot {
+    /// hex encoded fingerprint of the master key, a snapshot is imported by the same seed only
+    master_fingerprint: String,
+    last_seen_block_height: usize,
+    /// header of the last block, the next sync checks the block after it connects
+    tip: Option<HeaderRecord>,
+    external_public_key_list: Vec<(SecretKeyHelper, PublicKey)>,
+    internal_public_key_list: Vec<(SecretKeyHelper, PublicKey)>,
+    address_list: Vec<(AccountAddressType, String)>,
+    script_index: Vec<(Script, AccountAddressType, KeyPath)>,
+    used_script_list: Vec<(Script, AccountAddressType, OutPoint)>,
+    utxo_list: Vec<(OutPoint, Utxo)>,
+    spent_utxo_list: Vec<SpentUtxo>,
+    frozen_utxo_list: Vec<OutPoint>,
+    unconfirmed_tx_list: Vec<Transaction>,
+    tx_history: Vec<TxRecord>,
+    address_stats: Vec<(Script, AddressStats)>,
+    imported_key_list: Vec<(Script, PublicKey, AccountAddressType)>,
+    imported_utxo_list: Vec<ImportedUtxo>,
+    watched_script_list: Vec<(Script, String)>,
+    watched_utxo_list: Vec<WatchedUtxo>,
+    sent_payment_code_list: Vec<(String, u32)>,
+    received_payment_code_list: Vec<(String, u32)>,
+    silent_payment_utxo_list: Vec<SilentPaymentUtxo>,
+    timelock_script_list: Vec<(Script, u32, Timelock)>,
+    timelocked_utxo_list: Vec<TimelockedUtxo>,
+    script_account_list: Vec<ScriptAccount>,
+    script_utxo_list: Vec<ScriptUtxo>,
+    vault: Option<Vault>,
+    vault_script_list: Vec<(Script, u32, Vault)>,
+    vault_utxo_list: Vec<VaultUtxo>,
+    unvault_txid_list: Vec<Sha256dHash>,
+}
+
+impl StateSnapshot {
+    /// take a snapshot of the database of the wallet with the master key of `master_fingerprint`
+    pub(crate) fn from_db(db: &DB, master_fingerprint: String) -> Self {
+        let mut address_list = Vec::new();
+        for addr_type in &[
+            AccountAddressType::P2PKH,
+            AccountAddressType::P2SHWH,
+            AccountAddressType::P2WKH,
+        ] {
+            for address in db.get_account_address_list(addr_type.clone()) {
+                address_list.push((addr_type.clone(), address));
+            }
+        }
+
+        StateSnapshot {
+            master_fingerprint,
+            last_seen_block_height: db.get_last_seen_block_height(),
+            tip: db.get_last_block_header(),
+            external_public_key_list: db.get_external_public_key_list(),
+            internal_public_key_list: db.get_internal_public_key_list(),
+            address_list,
+            script_index: db
+                .get_script_index()
+                .into_iter()
+                .map(|(script, (addr_type, key_path))| (script, addr_type, key_path))
+                .collect(),
+            used_script_list: db
+                .get_used_scripts()
+                .into_iter()
+                .map(|(script, (addr_type, first_funding))| (script, addr_type, first_funding))
+                .collect(),
+            utxo_list: db.get_utxo_map().into_iter().collect(),
+            spent_utxo_list: db.get_spent_utxo_map().values().cloned().collect(),
+            frozen_utxo_list: db.get_frozen_utxo_set().into_iter().collect(),
+            unconfirmed_tx_list: db.get_unconfirmed_tx_map().values().cloned().collect(),
+            tx_history: db.get_tx_history().values().cloned().collect(),
+            address_stats: db.get_address_stats().into_iter().collect(),
+            imported_key_list: db
+                .get_imported_keys()
+                .into_iter()
+                .map(|(script, (pk, addr_type))| (script, pk, addr_type))
+                .collect(),
+            imported_utxo_list: db.get_imported_utxo_map().values().cloned().collect(),
+            watched_script_list: db.get_watched_scripts().into_iter().collect(),
+            watched_utxo_list: db.get_watched_utxo_map().values().cloned().collect(),
+            sent_payment_code_list: db.get_sent_payment_codes().into_iter().collect(),
+            received_payment_code_list: db.get_received_payment_codes().into_iter().collect(),
+            silent_payment_utxo_list: db.get_silent_payment_utxo_map().values().cloned().collect(),
+            timelock_script_list: db
+                .get_timelock_scripts()
+                .into_iter()
+                .map(|(script, (key_index, timelock))| (script, key_index, timelock))
+                .collect(),
+            timelocked_utxo_list: db.get_timelocked_utxo_map().values().cloned().collect(),
+            script_account_list: db.get_script_accounts().values().cloned().collect(),
+            script_utxo_list: db.get_script_utxo_map().values().cloned().collect(),
+            vault: db.get_vault(),
+            vault_script_list: db
+                .get_vault_scripts()
+                .into_iter()
+                .map(|(script, (key_index, vault))| (script, key_index, vault))
+                .collect(),
+            vault_utxo_list: db.get_vault_utxo_map().values().cloned().collect(),
+            unvault_txid_list: db.get_unvault_txids().into_iter().collect(),
+        }
+    }
+
+    /// replace the chain state in the database with the snapshot. The seed, the keys of
+    /// a watch-only wallet, the derivation, the spending policy and the address labels stay,
+    /// anything else is dropped
+    pub(crate) fn to_db(&self, db: &mut DB) {
+        let bip39_randomness = db.get_bip39_randomness();
+        let watch_only_keys = db.get_watch_only_keys();
+        let derivation_schemes = db.get_derivation_schemes();
+        let derivation_mode = db.get_derivation_mode();
+        let birthday = db.get_birthday();
+        let spending_policy = db.get_spending_policy();
+        let policy_spends = db.get_policy_spends();
+        let address_labels = db.get_address_labels();
+        db.clear();
+        if let Some(randomness) = bip39_randomness {
+            db.put_bip39_randomness(&randomness);
+        }
+        if let Some(keys) = watch_only_keys {
+            db.put_watch_only_keys(&keys);
+        }
+        db.put_derivation_schemes(&derivation_schemes);
+        db.put_derivation_mode(derivation_mode);
+        if let Some(birthday) = birthday {
+            db.put_birthday(birthday);
+        }
+        db.put_spending_policy(&spending_policy);
+        db.put_policy_spends(&policy_spends);
+        for (script, label) in &address_labels {
+            db.put_address_label(script, label);
+        }
+
+        db.put_last_seen_block_height(self.last_seen_block_height as u32);
+        if let Some(ref tip) = self.tip {
+            db.put_block_header(tip);
+        }
+        for (key_helper, pk) in &self.external_public_key_list {
+            db.put_external_public_key(key_helper, pk);
+        }
+        for (key_helper, pk) in &self.internal_public_key_list {
+            db.put_internal_public_key(key_helper, pk);
+        }
+        for (addr_type, address) in &self.address_list {
+            db.put_address(addr_type.clone(), address.clone());
+        }
+        for (script, addr_type, key_path) in &self.script_index {
+            db.put_script_index(script, addr_type, key_path);
+        }
+        for (script, addr_type, first_funding) in &self.used_script_list {
+            db.put_used_script(script, addr_type, first_funding);
+        }
+        for (op, utxo) in &self.utxo_list {
+            db.put_utxo(op, utxo);
+        }
+        for utxo in &self.spent_utxo_list {
+            db.put_spent_utxo(utxo);
+        }
+        for op in &self.frozen_utxo_list {
+            db.put_frozen_utxo(op);
+        }
+        for tx in &self.unconfirmed_tx_list {
+            db.put_unconfirmed_tx(tx);
+        }
+        for record in &self.tx_history {
+            db.put_tx_record(record);
+        }
+        for (script, stats) in &self.address_stats {
+            db.put_address_stats(script, stats);
+        }
+        for (script, pk, addr_type) in &self.imported_key_list {
+            db.put_imported_key(script, pk, addr_type);
+        }
+        for utxo in &self.imported_utxo_list {
+            db.put_imported_utxo(&utxo.out_point, utxo);
+        }
+        for (script, label) in &self.watched_script_list {
+            db.put_watched_script(script, label);
+        }
+        for utxo in &self.watched_utxo_list {
+            db.put_watched_utxo(utxo);
+        }
+        for (code, next_index) in &self.sent_payment_code_list {
+            db.put_sent_payment_code(code, *next_index);
+        }
+        for (code, watched_count) in &self.received_payment_code_list {
+            db.put_received_payment_code(code, *watched_count);
+        }
+        for utxo in &self.silent_payment_utxo_list {
+            db.put_silent_payment_utxo(utxo);
+        }
+        for (script, key_index, timelock) in &self.timelock_script_list {
+            db.put_timelock_script(script, *key_index, timelock);
+        }
+        for utxo in &self.timelocked_utxo_list {
+            db.put_timelocked_utxo(utxo);
+        }
+        for account in &self.script_account_list {
+            db.put_script_account(account);
+        }
+        for utxo in &self.script_utxo_list {
+            db.put_script_utxo(utxo);
+        }
+        if let Some(ref vault) = self.vault {
+            db.put_vault(vault);
+        }
+        for (script, key_index, vault) in &self.vault_script_list {
+            db.put_vault_script(script, *key_index, vault);
+        }
+        for utxo in &self.vault_utxo_list {
+            db.put_vault_utxo(utxo);
+        }
+        for txid in &self.unvault_txid_list {
+            db.put_unvault_txid(txid);
+        }
+    }
+
+    pub fn master_fingerprint(&self) -> &str {
+        &self.master_fingerprint
+    }
+
+    pub fn last_seen_block_height(&self) -> usize {
+        self.last_seen_block_height
+    }
+
+    /// the snapshot with its version and checksum
+    pub fn encode(&self) -> Result<Vec<u8>, WalletError> {
+        let payload = serde_json::to_vec(self).map_err(|_| WalletError::InvalidSnapshot)?;
+        let mut data = Vec::with_capacity(MAGIC.len() + 1 + payload.len() + CHECKSUM_LENGTH);
+        data.extend_from_slice(MAGIC);
+        data.push(VERSION);
+        data.extend_from_slice(&payload);
+        let checksum = sha256::Hash::hash(&data);
+        data.extend_from_slice(&checksum[..]);
+        Ok(data)
+    }
+
+    /// check and parse a snapshot produced by `StateSnapshot::encode`
+    pub fn decode(data: &[u8]) -> Result<Self, WalletError> {
+        let header_length = MAGIC.len() + 1;
+        if data.len() < header_length + CHECKSUM_LENGTH || &data[..MAGIC.len()] != MAGIC {
+            return Err(WalletError::InvalidSnapshot);
+        }
+        if data[MAGIC.len()] != VERSION {
+            return Err(WalletError::InvalidSnapshot);
+        }
+        let (checked, checksum) = data.split_at(data.len() - CHECKSUM_LENGTH);
+        if sha256::Hash::hash(checked)[..] != *checksum {
+            return Err(WalletError::SnapshotChecksumNotMatch);
+        }
+        serde_json::from_slice(&checked[header_length..]).map_err(|_| WalletError::InvalidSnapshot)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), WalletError> {
+        fs::write(path, self.encode()?)?;
+        Ok(())
+    }
+
+    pub fn read(path: &Path) -> Result<Self, WalletError> {
+        StateSnapshot::decode(&fs::read(path)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::network::constants::Network;
+
+    use crate::walletlibrary::{WalletConfig, WalletLibraryMode, KeyGenConfig};
+    use crate::default::WalletWithTrustedFullNode;
+    use crate::memory::{MemoryChain, test::payment};
+    use crate::account::AccountAddressType;
+    use crate::interface::Wallet;
+    use super::StateSnapshot;
+
+    fn snapshot() -> StateSnapshot {
+        StateSnapshot {
+            master_fingerprint: "0badcafe".to_owned(),
+            last_seen_block_height: 42,
+            tip: None,
+            external_public_key_list: Vec::new(),
+            internal_public_key_list: Vec::new(),
+            address_list: Vec::new(),
+            script_index: Vec::new(),
+            used_script_list: Vec::new(),
+            utxo_list: Vec::new(),
+            spent_utxo_list: Vec::new(),
+            frozen_utxo_list: Vec::new(),
+            unconfirmed_tx_list: Vec::new(),
+            tx_history: Vec::new(),
+            address_stats: Vec::new(),
+            imported_key_list: Vec::new(),
+            imported_utxo_list: Vec::new(),
+            watched_script_list: Vec::new(),
+            watched_utxo_list: Vec::new(),
+            sent_payment_code_list: Vec::new(),
+            received_payment_code_list: Vec::new(),
+            silent_payment_utxo_list: Vec::new(),
+            timelock_script_list: Vec::new(),
+            timelocked_utxo_list: Vec::new(),
+            script_account_list: Vec::new(),
+            script_utxo_list: Vec::new(),
+            vault: None,
+            vault_script_list: Vec::new(),
+            vault_utxo_list: Vec::new(),
+            unvault_txid_list: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn encode_decode() {
+        let data = snapshot().encode().unwrap();
+        let decoded = StateSnapshot::decode(&data).unwrap();
+        assert_eq!(decoded.master_fingerprint(), "0badcafe");
+        assert_eq!(decoded.last_seen_block_height(), 42);
+    }
+
+    #[test]
+    fn corrupted() {
+        let mut data = snapshot().encode().unwrap();
+        let middle = data.len() / 2;
+        data[middle] ^= 1;
+        assert!(StateSnapshot::decode(&data).is_err());
+
+        let mut data = snapshot().encode().unwrap();
+        data[4] = 2;
+        assert!(StateSnapshot::decode(&data).is_err());
+        assert!(StateSnapshot::decode(&data[..10]).is_err());
+    }
+
+    #[test]
+    fn state_snapshot() {
+        let chain = MemoryChain::new(Network::Regtest);
+        let open = |key_gen_cfg: KeyGenConfig| {
+            WalletWithTrustedFullNode::new(
+                WalletConfig::in_memory(),
+                chain.clone(),
+                WalletLibraryMode::Create(key_gen_cfg),
+            )
+            .unwrap()
+            .0
+        };
+        let mut wallet = open(KeyGenConfig::debug());
+        let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        chain.push_block(Vec::new());
+        chain.push_block(vec![payment(&addr, 50_000_000)]);
+        chain.push_block(Vec::new());
+        wallet.sync_with_tip().unwrap();
+        let path = std::env::temp_dir().join("rust_wallet_state_snapshot");
+        wallet.wallet_lib().export_state_snapshot(&path).unwrap();
+
+        // the same seed elsewhere, it hasn't synced yet
+        let mut migrated = open(KeyGenConfig::debug());
+        let state = migrated.wallet_lib().state();
+        migrated.wallet_lib_mut().import_state_snapshot(&path).unwrap();
+        assert_eq!(migrated.wallet_lib().get_last_seen_block_height_from_memory(), 3);
+        // a handle taken before the import follows the reloaded wallet
+        assert_eq!(state.wallet_balance(), 50_000_000);
+        assert_eq!(migrated.wallet_lib().wallet_balance(), 50_000_000);
+        assert_eq!(migrated.wallet_lib().get_tx_history().len(), 1);
+        assert_ne!(migrated.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap(), addr);
+        chain.push_block(Vec::new());
+        migrated.sync_with_tip().unwrap();
+        assert_eq!(migrated.wallet_lib().get_last_seen_block_height_from_memory(), 4);
+
+        let mut other = open(KeyGenConfig::default());
+        assert!(other.wallet_lib_mut().import_state_snapshot(&path).is_err());
+        assert_eq!(other.wallet_lib().wallet_balance(), 0);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,27 +1,145 @@
-use super::account::{Utxo, SecretKeyHelper, AccountAddressType};
-use super::walletlibrary::{LockId, LockGroup};
+use super::account::{
+    Utxo, SecretKeyHelper, AccountAddressType, AddressChain, KeyPath, DerivationMode,
+    DerivationScheme,
+};
+use super::walletlibrary::{
+    LockId, LockGroup, PendingRotation, ImportedUtxo, WatchedUtxo, SilentPaymentUtxo,
+    SpendingPolicy, PendingSpend, SpentUtxo, TimelockedUtxo, ScriptAccount, ScriptUtxo, UnsignedTx,
+    IdempotentSend, VaultUtxo,
+};
+use super::timelock::Timelock;
+use super::vault::Vault;
+use super::history::{TxRecord, AddressStats};
+use super::audit::AuditEntry;
+use super::headers::HeaderRecord;
+use super::signer::WatchOnlyKeys;
+use super::error::WalletError;
+use super::interface::BackendKind;
 
 use serde::{Serialize, Deserialize};
-use bitcoin::{OutPoint, util::key::PublicKey};
+use bitcoin::{OutPoint, Script, Transaction, util::key::PublicKey, network::constants::Network};
+use bitcoin_hashes::sha256d::Hash as Sha256dHash;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, BTreeMap};
+use std::str::FromStr;
+
+/// version of the layout of `State`, raised along with a new migration
+/// whenever the states stored by older versions have to be changed
+pub const SCHEMA_VERSION: u32 = 4;
+
+// `MIGRATIONS[i]` upgrades a state of version `i` to version `i + 1`
+static MIGRATIONS: &[fn(&mut State)] = &[
+    // fields added before the version was stored have defaults
+    |_| (),
+    // wallets created before the standard derivation keep the coin type of their accounts
+    |state| {
+        if state.bip39_randomness.is_some() {
+            state.derivation_mode = DerivationMode::Legacy;
+        }
+    },
+    // the totals of the addresses are counted again with the transactions paying to them
+    |state| state.address_stats.clear(),
+    // the next index of each chain is stored instead of being looked up in the script index
+    |state| {
+        let entries: Vec<_> = state.script_index.values().cloned().collect();
+        for (addr_type, key_path) in entries {
+            state.advance_next_index(&addr_type, &key_path);
+        }
+    },
+];
 
 pub struct DB {
-    path: String,
+    /// `None` for an ephemeral wallet
+    path: Option<String>,
     state: State,
 }
 
 impl DB {
-    pub fn new(db_path: String) -> Self {
-        DB {
-            path: db_path,
+    pub fn new(db_path: String) -> Result<Self, WalletError> {
+        let mut db = DB {
+            path: Some(db_path),
+            state: State::default(),
+        };
+        db.migrate()?;
+        Ok(db)
+    }
+
+    /// database of an ephemeral wallet, its state is never stored
+    pub fn in_memory() -> Result<Self, WalletError> {
+        let mut db = DB {
+            path: None,
             state: State::default(),
+        };
+        db.migrate()?;
+        Ok(db)
+    }
+
+    fn migrate(&mut self) -> Result<(), WalletError> {
+        let version = self.state.schema_version;
+        if version > SCHEMA_VERSION {
+            return Err(WalletError::UnsupportedSchemaVersion(version));
+        }
+        for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+            migration(&mut self.state);
+            self.state.schema_version = from as u32 + 1;
         }
+        // the upgraded state is stored along with the next change
+        Ok(())
     }
 
     fn store(&self) {
-        let _ = self.path;
-        unimplemented!()
+        if self.path.is_some() {
+            unimplemented!()
+        }
+    }
+
+    /// remove all data of the wallet but the audit log, which is append-only,
+    /// the schema version, the network and the backend
+    pub fn clear(&mut self) {
+        let audit_log = std::mem::replace(&mut self.state.audit_log, Vec::new());
+        self.state = State {
+            schema_version: self.state.schema_version,
+            network: self.state.network.take(),
+            backend: self.state.backend,
+            audit_log,
+            ..State::default()
+        };
+        self.store();
+    }
+
+    /// everything `clear` removes but the seed randomness, which a backup keeps apart
+    pub fn dump(&self) -> serde_json::Value {
+        let mut dump = serde_json::to_value(&self.state).unwrap();
+        let fields = dump.as_object_mut().unwrap();
+        for field in &["bip39_randomness", "audit_log", "network", "backend"] {
+            fields.remove(*field);
+        }
+        dump
+    }
+
+    /// replace the data of the wallet with a `dump` and the seed randomness,
+    /// the dump of an older wallet is upgraded
+    pub fn restore_dump(
+        &mut self,
+        dump: &serde_json::Value,
+        bip39_randomness: &[u8],
+    ) -> Result<(), WalletError> {
+        let state: State =
+            serde_json::from_value(dump.clone()).map_err(|_| WalletError::InvalidBackup)?;
+        if state.schema_version > SCHEMA_VERSION {
+            return Err(WalletError::UnsupportedSchemaVersion(state.schema_version));
+        }
+        let audit_log = std::mem::replace(&mut self.state.audit_log, Vec::new());
+        self.state = State {
+            bip39_randomness: Some(bip39_randomness.to_vec()),
+            network: self.state.network.take(),
+            backend: self.state.backend,
+            audit_log,
+            ..state
+        };
+        self.migrate()?;
+        self.store();
+        Ok(())
     }
 
     pub fn get_bip39_randomness(&self) -> Option<Vec<u8>> {
@@ -42,6 +160,238 @@ impl DB {
         self.store();
     }
 
+    pub fn get_pending_rotation(&self) -> Option<PendingRotation> {
+        self.state.pending_rotation.clone()
+    }
+
+    pub fn put_pending_rotation(&mut self, rotation: &PendingRotation) {
+        self.state.pending_rotation = Some(rotation.clone());
+        self.store();
+    }
+
+    pub fn get_spending_policy(&self) -> SpendingPolicy {
+        self.state.spending_policy.clone()
+    }
+
+    pub fn put_spending_policy(&mut self, policy: &SpendingPolicy) {
+        self.state.spending_policy = policy.clone();
+        self.store();
+    }
+
+    pub fn get_derivation_schemes(&self) -> Vec<(AccountAddressType, DerivationScheme)> {
+        self.state.derivation_schemes.clone()
+    }
+
+    pub fn put_derivation_schemes(&mut self, schemes: &[(AccountAddressType, DerivationScheme)]) {
+        self.state.derivation_schemes = schemes.to_vec();
+        self.store();
+    }
+
+    pub fn get_derivation_mode(&self) -> DerivationMode {
+        self.state.derivation_mode
+    }
+
+    pub fn put_derivation_mode(&mut self, mode: DerivationMode) {
+        self.state.derivation_mode = mode;
+        self.store();
+    }
+
+    pub fn get_birthday(&self) -> Option<u64> {
+        self.state.birthday
+    }
+
+    pub fn put_birthday(&mut self, birthday: u64) {
+        self.state.birthday = Some(birthday);
+        self.store();
+    }
+
+    pub fn get_master_fingerprint(&self) -> Option<Vec<u8>> {
+        self.state.master_fingerprint.clone()
+    }
+
+    pub fn put_master_fingerprint(&mut self, fingerprint: &[u8]) {
+        self.state.master_fingerprint = Some(fingerprint.to_vec());
+        self.store();
+    }
+
+    pub fn get_watch_only_keys(&self) -> Option<WatchOnlyKeys> {
+        self.state.watch_only_keys.clone()
+    }
+
+    pub fn put_watch_only_keys(&mut self, keys: &WatchOnlyKeys) {
+        self.state.watch_only_keys = Some(keys.clone());
+        self.store();
+    }
+
+    /// network the wallet was created on, `None` until a wallet is opened in the database
+    pub fn get_network(&self) -> Option<Network> {
+        self.state.network.as_ref().map(|network| Network::from_str(network).unwrap())
+    }
+
+    pub fn put_network(&mut self, network: Network) {
+        self.state.network = Some(network.to_string());
+        self.store();
+    }
+
+    /// kind of backend the wallet was first opened with
+    pub fn get_backend(&self) -> Option<BackendKind> {
+        self.state.backend
+    }
+
+    pub fn put_backend(&mut self, backend: BackendKind) {
+        self.state.backend = Some(backend);
+        self.store();
+    }
+
+    pub fn get_policy_spends(&self) -> Vec<(u64, u64)> {
+        self.state.policy_spends.clone()
+    }
+
+    pub fn put_policy_spends(&mut self, spends: &[(u64, u64)]) {
+        self.state.policy_spends = spends.to_vec();
+        self.store();
+    }
+
+    /// ids of the pending spends start at 1
+    pub fn get_next_spend_id(&self) -> u64 {
+        self.state.next_spend_id.unwrap_or(1)
+    }
+
+    pub fn put_next_spend_id(&mut self, id: u64) {
+        self.state.next_spend_id = Some(id);
+        self.store();
+    }
+
+    pub fn get_pending_spends(&self) -> HashMap<u64, PendingSpend> {
+        self.state.pending_spends.clone()
+    }
+
+    pub fn put_pending_spend(&mut self, spend: &PendingSpend) {
+        self.state.pending_spends.insert(spend.id, spend.clone());
+        self.store();
+    }
+
+    pub fn delete_pending_spend(&mut self, id: u64) {
+        self.state.pending_spends.remove(&id);
+        self.store();
+    }
+
+    /// ids of the unsigned transactions start at 1
+    pub fn get_next_unsigned_id(&self) -> u64 {
+        self.state.next_unsigned_id.unwrap_or(1)
+    }
+
+    pub fn put_next_unsigned_id(&mut self, id: u64) {
+        self.state.next_unsigned_id = Some(id);
+        self.store();
+    }
+
+    pub fn get_unsigned_txs(&self) -> HashMap<u64, UnsignedTx> {
+        self.state.unsigned_txs.clone()
+    }
+
+    pub fn put_unsigned_tx(&mut self, unsigned: &UnsignedTx) {
+        self.state.unsigned_txs.insert(unsigned.id, unsigned.clone());
+        self.store();
+    }
+
+    pub fn delete_unsigned_tx(&mut self, id: u64) {
+        self.state.unsigned_txs.remove(&id);
+        self.store();
+    }
+
+    pub fn get_idempotent_sends(&self) -> HashMap<String, IdempotentSend> {
+        self.state.idempotent_sends.clone()
+    }
+
+    pub fn put_idempotent_send(&mut self, send: &IdempotentSend) {
+        self.state.idempotent_sends.insert(send.key.clone(), send.clone());
+        self.store();
+    }
+
+    pub fn delete_idempotent_send(&mut self, key: &str) {
+        self.state.idempotent_sends.remove(key);
+        self.store();
+    }
+
+    pub fn get_address_stats(&self) -> HashMap<Script, AddressStats> {
+        self.state.address_stats.clone()
+    }
+
+    pub fn put_address_stats(&mut self, script: &Script, stats: &AddressStats) {
+        self.state.address_stats.insert(script.clone(), *stats);
+        self.store();
+    }
+
+    pub fn get_address_labels(&self) -> HashMap<Script, String> {
+        self.state.address_labels.clone()
+    }
+
+    pub fn put_address_label(&mut self, script: &Script, label: &str) {
+        self.state.address_labels.insert(script.clone(), label.to_owned());
+        self.store();
+    }
+
+    pub fn delete_address_label(&mut self, script: &Script) {
+        self.state.address_labels.remove(script);
+        self.store();
+    }
+
+    pub fn get_script_statuses(&self) -> HashMap<String, String> {
+        self.state.script_statuses.clone()
+    }
+
+    pub fn put_script_status(&mut self, script_hash: &str, status: &str) {
+        self.state.script_statuses.insert(script_hash.to_owned(), status.to_owned());
+        self.store();
+    }
+
+    pub fn get_script_status_tip(&self) -> Option<(usize, Sha256dHash)> {
+        self.state.script_status_tip
+    }
+
+    pub fn put_script_status_tip(&mut self, height: usize, hash: &Sha256dHash) {
+        self.state.script_status_tip = Some((height, *hash));
+        self.store();
+    }
+
+    pub fn clear_script_statuses(&mut self) {
+        self.state.script_statuses.clear();
+        self.state.script_status_tip = None;
+        self.store();
+    }
+
+    pub fn get_last_audit_entry(&self) -> Option<AuditEntry> {
+        self.state.audit_log.last().cloned()
+    }
+
+    pub fn get_audit_log(&self, start: u64, limit: usize) -> Vec<AuditEntry> {
+        self.state.audit_log.iter().skip(start as usize).take(limit).cloned().collect()
+    }
+
+    pub fn put_audit_entry(&mut self, entry: &AuditEntry) {
+        self.state.audit_log.push(entry.clone());
+        self.store();
+    }
+
+    pub fn get_block_header(&self, height: usize) -> Option<HeaderRecord> {
+        self.state.block_headers.get(&height).cloned()
+    }
+
+    pub fn get_last_block_header(&self) -> Option<HeaderRecord> {
+        self.state.block_headers.values().next_back().cloned()
+    }
+
+    pub fn put_block_header(&mut self, header: &HeaderRecord) {
+        self.state.block_headers.insert(header.height, header.clone());
+        self.store();
+    }
+
+    pub fn delete_block_headers_above(&mut self, height: usize) {
+        self.state.block_headers.split_off(&(height + 1));
+        self.store();
+    }
+
     pub fn get_utxo_map(&self) -> HashMap<OutPoint, Utxo> {
         self.state.utxo_map.clone()
     }
@@ -64,6 +414,25 @@ impl DB {
         self.state.internal_public_key_list.clone()
     }
 
+    pub fn get_external_public_key(&self, key_helper: &SecretKeyHelper) -> Option<PublicKey> {
+        let list = &self.state.external_public_key_list;
+        list.iter().find(|(key, _)| key == key_helper).map(|(_, pk)| *pk)
+    }
+
+    pub fn get_internal_public_key(&self, key_helper: &SecretKeyHelper) -> Option<PublicKey> {
+        let list = &self.state.internal_public_key_list;
+        list.iter().find(|(key, _)| key == key_helper).map(|(_, pk)| *pk)
+    }
+
+    pub fn has_address(&self, addr_type: &AccountAddressType, address: &str) -> bool {
+        let list = match *addr_type {
+            AccountAddressType::P2PKH => &self.state.p2pkh_address_list,
+            AccountAddressType::P2SHWH => &self.state.p2shwh_address_list,
+            AccountAddressType::P2WKH => &self.state.p2wkh_address_list,
+        };
+        list.iter().any(|stored| stored == address)
+    }
+
     pub fn get_full_address_list(&self) -> Vec<String> {
         [
             self.state.p2pkh_address_list.clone(),
@@ -103,17 +472,402 @@ impl DB {
         self.state.lock_group.insert(lock_id.clone(), lock_group.clone());
         self.store();
     }
+
+    pub fn delete_lock_group(&mut self, lock_id: &LockId) {
+        self.state.lock_group.remove(lock_id);
+        self.store();
+    }
+
+    pub fn get_script_index(&self) -> HashMap<Script, (AccountAddressType, KeyPath)> {
+        self.state.script_index.clone()
+    }
+
+    pub fn has_script_index(&self) -> bool {
+        !self.state.script_index.is_empty()
+    }
+
+    pub fn get_script_index_entry(&self, script: &Script) -> Option<(AccountAddressType, KeyPath)> {
+        self.state.script_index.get(script).cloned()
+    }
+
+    pub fn put_script_index(
+        &mut self,
+        script: &Script,
+        addr_type: &AccountAddressType,
+        key_path: &KeyPath,
+    ) {
+        self.state.script_index.insert(script.clone(), (addr_type.clone(), key_path.clone()));
+        self.state.advance_next_index(addr_type, key_path);
+        self.store();
+    }
+
+    pub fn get_next_index(&self, addr_type: &AccountAddressType, addr_chain: &AddressChain) -> u32 {
+        let key = next_index_key(addr_type, addr_chain);
+        self.state.next_indexes.get(&key).cloned().unwrap_or(0)
+    }
+
+    pub fn get_imported_keys(&self) -> HashMap<Script, (PublicKey, AccountAddressType)> {
+        self.state.imported_keys.clone()
+    }
+
+    pub fn put_imported_key(
+        &mut self,
+        script: &Script,
+        pk: &PublicKey,
+        addr_type: &AccountAddressType,
+    ) {
+        self.state.imported_keys.insert(script.clone(), (pk.clone(), addr_type.clone()));
+        self.store();
+    }
+
+    pub fn get_imported_utxo_map(&self) -> HashMap<OutPoint, ImportedUtxo> {
+        self.state.imported_utxo_map.clone()
+    }
+
+    pub fn put_imported_utxo(&mut self, op: &OutPoint, utxo: &ImportedUtxo) {
+        self.state.imported_utxo_map.insert(op.clone(), utxo.clone());
+        self.store();
+    }
+
+    pub fn delete_imported_utxo(&mut self, op: &OutPoint) {
+        self.state.imported_utxo_map.remove(op);
+        self.store();
+    }
+
+    pub fn get_watched_scripts(&self) -> HashMap<Script, String> {
+        self.state.watched_scripts.clone()
+    }
+
+    pub fn put_watched_script(&mut self, script: &Script, label: &str) {
+        self.state.watched_scripts.insert(script.clone(), label.to_owned());
+        self.store();
+    }
+
+    pub fn get_watched_utxo_map(&self) -> HashMap<OutPoint, WatchedUtxo> {
+        self.state.watched_utxo_map.clone()
+    }
+
+    pub fn put_watched_utxo(&mut self, utxo: &WatchedUtxo) {
+        self.state.watched_utxo_map.insert(utxo.out_point, utxo.clone());
+        self.store();
+    }
+
+    pub fn delete_watched_utxo(&mut self, op: &OutPoint) {
+        self.state.watched_utxo_map.remove(op);
+        self.store();
+    }
+
+    pub fn get_silent_payment_utxo_map(&self) -> HashMap<OutPoint, SilentPaymentUtxo> {
+        self.state.silent_payment_utxo_map.clone()
+    }
+
+    pub fn put_silent_payment_utxo(&mut self, utxo: &SilentPaymentUtxo) {
+        self.state.silent_payment_utxo_map.insert(utxo.out_point, utxo.clone());
+        self.store();
+    }
+
+    pub fn delete_silent_payment_utxo(&mut self, op: &OutPoint) {
+        self.state.silent_payment_utxo_map.remove(op);
+        self.store();
+    }
+
+    pub fn get_timelock_scripts(&self) -> HashMap<Script, (u32, Timelock)> {
+        self.state.timelock_scripts.clone()
+    }
+
+    pub fn put_timelock_script(&mut self, script: &Script, key_index: u32, timelock: &Timelock) {
+        self.state.timelock_scripts.insert(script.clone(), (key_index, *timelock));
+        self.store();
+    }
+
+    pub fn get_timelocked_utxo_map(&self) -> HashMap<OutPoint, TimelockedUtxo> {
+        self.state.timelocked_utxo_map.clone()
+    }
+
+    pub fn put_timelocked_utxo(&mut self, utxo: &TimelockedUtxo) {
+        self.state.timelocked_utxo_map.insert(utxo.out_point, utxo.clone());
+        self.store();
+    }
+
+    pub fn delete_timelocked_utxo(&mut self, op: &OutPoint) {
+        self.state.timelocked_utxo_map.remove(op);
+        self.store();
+    }
+
+    pub fn get_vault(&self) -> Option<Vault> {
+        self.state.vault
+    }
+
+    pub fn put_vault(&mut self, vault: &Vault) {
+        self.state.vault = Some(*vault);
+        self.store();
+    }
+
+    pub fn get_vault_scripts(&self) -> HashMap<Script, (u32, Vault)> {
+        self.state.vault_scripts.clone()
+    }
+
+    pub fn put_vault_script(&mut self, script: &Script, key_index: u32, vault: &Vault) {
+        self.state.vault_scripts.insert(script.clone(), (key_index, *vault));
+        self.store();
+    }
+
+    pub fn get_vault_utxo_map(&self) -> HashMap<OutPoint, VaultUtxo> {
+        self.state.vault_utxo_map.clone()
+    }
+
+    pub fn put_vault_utxo(&mut self, utxo: &VaultUtxo) {
+        self.state.vault_utxo_map.insert(utxo.out_point, utxo.clone());
+        self.store();
+    }
+
+    pub fn delete_vault_utxo(&mut self, op: &OutPoint) {
+        self.state.vault_utxo_map.remove(op);
+        self.store();
+    }
+
+    pub fn get_unvault_txids(&self) -> HashSet<Sha256dHash> {
+        self.state.unvault_txids.clone()
+    }
+
+    pub fn put_unvault_txid(&mut self, txid: &Sha256dHash) {
+        self.state.unvault_txids.insert(*txid);
+        self.store();
+    }
+
+    pub fn get_script_accounts(&self) -> HashMap<String, ScriptAccount> {
+        self.state.script_accounts.clone()
+    }
+
+    pub fn put_script_account(&mut self, account: &ScriptAccount) {
+        self.state.script_accounts.insert(account.name.clone(), account.clone());
+        self.store();
+    }
+
+    pub fn get_script_utxo_map(&self) -> HashMap<OutPoint, ScriptUtxo> {
+        self.state.script_utxo_map.clone()
+    }
+
+    pub fn put_script_utxo(&mut self, utxo: &ScriptUtxo) {
+        self.state.script_utxo_map.insert(utxo.out_point, utxo.clone());
+        self.store();
+    }
+
+    pub fn delete_script_utxo(&mut self, op: &OutPoint) {
+        self.state.script_utxo_map.remove(op);
+        self.store();
+    }
+
+    pub fn get_spent_utxo_map(&self) -> HashMap<OutPoint, SpentUtxo> {
+        self.state.spent_utxo_map.clone()
+    }
+
+    pub fn put_spent_utxo(&mut self, utxo: &SpentUtxo) {
+        self.state.spent_utxo_map.insert(utxo.utxo.out_point, utxo.clone());
+        self.store();
+    }
+
+    pub fn delete_spent_utxo(&mut self, op: &OutPoint) {
+        self.state.spent_utxo_map.remove(op);
+        self.store();
+    }
+
+    pub fn get_sent_payment_codes(&self) -> HashMap<String, u32> {
+        self.state.sent_payment_codes.clone()
+    }
+
+    pub fn put_sent_payment_code(&mut self, code: &str, next_index: u32) {
+        self.state.sent_payment_codes.insert(code.to_owned(), next_index);
+        self.store();
+    }
+
+    pub fn get_received_payment_codes(&self) -> HashMap<String, u32> {
+        self.state.received_payment_codes.clone()
+    }
+
+    pub fn put_received_payment_code(&mut self, code: &str, watched_count: u32) {
+        self.state.received_payment_codes.insert(code.to_owned(), watched_count);
+        self.store();
+    }
+
+    pub fn get_used_scripts(&self) -> HashMap<Script, (AccountAddressType, OutPoint)> {
+        self.state.used_scripts.clone()
+    }
+
+    pub fn put_used_script(
+        &mut self,
+        script: &Script,
+        addr_type: &AccountAddressType,
+        first_funding: &OutPoint,
+    ) {
+        self.state.used_scripts.insert(script.clone(), (addr_type.clone(), first_funding.clone()));
+        self.store();
+    }
+
+    pub fn get_frozen_utxo_set(&self) -> HashSet<OutPoint> {
+        self.state.frozen_utxo_set.clone()
+    }
+
+    pub fn put_frozen_utxo(&mut self, op: &OutPoint) {
+        self.state.frozen_utxo_set.insert(op.clone());
+        self.store();
+    }
+
+    pub fn delete_frozen_utxo(&mut self, op: &OutPoint) {
+        self.state.frozen_utxo_set.remove(op);
+        self.store();
+    }
+
+    pub fn get_unconfirmed_tx_map(&self) -> HashMap<Sha256dHash, Transaction> {
+        self.state.unconfirmed_tx_map.clone()
+    }
+
+    pub fn put_unconfirmed_tx(&mut self, tx: &Transaction) {
+        self.state.unconfirmed_tx_map.insert(tx.txid(), tx.clone());
+        self.store();
+    }
+
+    pub fn delete_unconfirmed_tx(&mut self, txid: &Sha256dHash) {
+        self.state.unconfirmed_tx_map.remove(txid);
+        self.store();
+    }
+
+    /// price of a bitcoin in `currency` at unix time `time`, as stored by `put_fiat_price`
+    pub fn get_fiat_price(&self, currency: &str, time: u32) -> Option<f64> {
+        self.state.fiat_prices.get(&format!("{}:{}", currency, time)).cloned()
+    }
+
+    pub fn put_fiat_price(&mut self, currency: &str, time: u32, price: f64) {
+        self.state.fiat_prices.insert(format!("{}:{}", currency, time), price);
+        self.store();
+    }
+
+    pub fn get_tx_history(&self) -> HashMap<Sha256dHash, TxRecord> {
+        self.state.tx_history.clone()
+    }
+
+    pub fn put_tx_record(&mut self, record: &TxRecord) {
+        self.state.tx_history.insert(record.txid(), record.clone());
+        self.store();
+    }
 }
 
 #[derive(Default, Serialize, Deserialize)]
 pub struct State {
+    /// 0 for the states stored before the version was
+    #[serde(default)]
+    schema_version: u32,
     bip39_randomness: Option<Vec<u8>>,
     last_seen_block_height: u32,
+    pending_rotation: Option<PendingRotation>,
     utxo_map: HashMap<OutPoint, Utxo>,
     external_public_key_list: Vec<(SecretKeyHelper, PublicKey)>,
     internal_public_key_list: Vec<(SecretKeyHelper, PublicKey)>,
     p2pkh_address_list: Vec<String>,
     p2shwh_address_list: Vec<String>,
     p2wkh_address_list: Vec<String>,
-    lock_group: HashMap<LockId, LockGroup>
+    lock_group: HashMap<LockId, LockGroup>,
+    script_index: HashMap<Script, (AccountAddressType, KeyPath)>,
+    imported_keys: HashMap<Script, (PublicKey, AccountAddressType)>,
+    imported_utxo_map: HashMap<OutPoint, ImportedUtxo>,
+    #[serde(default)]
+    used_scripts: HashMap<Script, (AccountAddressType, OutPoint)>,
+    #[serde(default)]
+    frozen_utxo_set: HashSet<OutPoint>,
+    #[serde(default)]
+    unconfirmed_tx_map: HashMap<Sha256dHash, Transaction>,
+    #[serde(default)]
+    tx_history: HashMap<Sha256dHash, TxRecord>,
+    #[serde(default)]
+    watched_scripts: HashMap<Script, String>,
+    #[serde(default)]
+    watched_utxo_map: HashMap<OutPoint, WatchedUtxo>,
+    #[serde(default)]
+    sent_payment_codes: HashMap<String, u32>,
+    #[serde(default)]
+    received_payment_codes: HashMap<String, u32>,
+    #[serde(default)]
+    silent_payment_utxo_map: HashMap<OutPoint, SilentPaymentUtxo>,
+    #[serde(default)]
+    spending_policy: SpendingPolicy,
+    #[serde(default)]
+    policy_spends: Vec<(u64, u64)>,
+    #[serde(default)]
+    next_spend_id: Option<u64>,
+    #[serde(default)]
+    pending_spends: HashMap<u64, PendingSpend>,
+    #[serde(default)]
+    audit_log: Vec<AuditEntry>,
+    #[serde(default)]
+    block_headers: BTreeMap<usize, HeaderRecord>,
+    #[serde(default)]
+    spent_utxo_map: HashMap<OutPoint, SpentUtxo>,
+    /// keyed by currency and unix time
+    #[serde(default)]
+    fiat_prices: HashMap<String, f64>,
+    #[serde(default)]
+    timelock_scripts: HashMap<Script, (u32, Timelock)>,
+    #[serde(default)]
+    timelocked_utxo_map: HashMap<OutPoint, TimelockedUtxo>,
+    #[serde(default)]
+    script_accounts: HashMap<String, ScriptAccount>,
+    #[serde(default)]
+    script_utxo_map: HashMap<OutPoint, ScriptUtxo>,
+    #[serde(default)]
+    derivation_schemes: Vec<(AccountAddressType, DerivationScheme)>,
+    #[serde(default)]
+    derivation_mode: DerivationMode,
+    #[serde(default)]
+    birthday: Option<u64>,
+    #[serde(default)]
+    watch_only_keys: Option<WatchOnlyKeys>,
+    #[serde(default)]
+    next_unsigned_id: Option<u64>,
+    #[serde(default)]
+    unsigned_txs: HashMap<u64, UnsignedTx>,
+    #[serde(default)]
+    idempotent_sends: HashMap<String, IdempotentSend>,
+    #[serde(default)]
+    address_stats: HashMap<Script, AddressStats>,
+    #[serde(default)]
+    address_labels: HashMap<Script, String>,
+    /// electrum status of each script hash and the tip they were seen at
+    #[serde(default)]
+    script_statuses: HashMap<String, String>,
+    #[serde(default)]
+    script_status_tip: Option<(usize, Sha256dHash)>,
+    #[serde(default)]
+    vault: Option<Vault>,
+    #[serde(default)]
+    vault_scripts: HashMap<Script, (u32, Vault)>,
+    #[serde(default)]
+    vault_utxo_map: HashMap<OutPoint, VaultUtxo>,
+    /// the unvaults the wallet signed
+    #[serde(default)]
+    unvault_txids: HashSet<Sha256dHash>,
+    #[serde(default)]
+    master_fingerprint: Option<Vec<u8>>,
+    /// `Network` by its name, a state of another network isn't opened
+    #[serde(default)]
+    network: Option<String>,
+    #[serde(default)]
+    backend: Option<BackendKind>,
+    /// the index after the last key indexed, keyed by address type and chain
+    #[serde(default)]
+    next_indexes: HashMap<String, u32>,
+}
+
+impl State {
+    fn advance_next_index(&mut self, addr_type: &AccountAddressType, key_path: &KeyPath) {
+        let next = self
+            .next_indexes
+            .entry(next_index_key(addr_type, key_path.addr_chain()))
+            .or_insert(0);
+        *next = (*next).max(key_path.addr_index() + 1);
+    }
+}
+
+fn next_index_key(addr_type: &AccountAddressType, addr_chain: &AddressChain) -> String {
+    let chain: u32 = addr_chain.clone().into();
+    format!("{}:{}", addr_type.as_str(), chain)
 }
@@ -0,0 +1,219 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Process supervisor
+//!
+//! Keeps the bitcoind and electrs the wallet started running. A process which exits
+//! is started again, after a delay doubling with every failure in a row,
+//! and whoever runs the wallets is told so they can reconnect.
+//! The processes are killed when the supervisor is dropped.
+//!
+use std::{
+    io,
+    process::Child,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+const POLL_INTERVAL_MS: u64 = 500;
+const INITIAL_BACKOFF_MS: u64 = 1_000;
+const MAX_BACKOFF_MS: u64 = 60_000;
+// a process running that long counts as recovered, the next failure starts over with no delay
+const STABLE_AFTER_MS: u64 = MAX_BACKOFF_MS;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProcessEvent {
+    /// the process `name` is gone, `status` is how it exited
+    Exited { name: String, status: String },
+    /// the process `name` was started again, it may take a while until it serves requests
+    Restarted { name: String },
+    /// starting the process `name` again failed, the next attempt is after a longer delay
+    RestartFailed { name: String, error: String },
+}
+
+type Spawn = Box<dyn Fn() -> io::Result<Child> + Send>;
+
+struct ManagedProcess {
+    name: String,
+    spawn: Spawn,
+    child: Option<Child>,
+    started: Instant,
+    failures: u32,
+    next_attempt: Instant,
+}
+
+impl ManagedProcess {
+    fn backoff(&self) -> Duration {
+        let backoff = INITIAL_BACKOFF_MS.saturating_mul(1 << self.failures.min(16));
+        Duration::from_millis(backoff.min(MAX_BACKOFF_MS))
+    }
+
+    // a restart is due after a delay unless the process was up for long
+    fn schedule_restart(&mut self, now: Instant) {
+        if now.duration_since(self.started) > Duration::from_millis(STABLE_AFTER_MS) {
+            self.failures = 0;
+        }
+        self.next_attempt = now + self.backoff();
+        self.failures += 1;
+    }
+
+    fn check(&mut self, now: Instant) -> Option<ProcessEvent> {
+        let name = self.name.clone();
+        match self.child {
+            Some(ref mut child) => match child.try_wait() {
+                Ok(None) => None,
+                Ok(Some(status)) => {
+                    self.child = None;
+                    self.schedule_restart(now);
+                    let status = status.to_string();
+                    Some(ProcessEvent::Exited { name, status })
+                }
+                Err(err) => {
+                    log::warn!("failed to check on {}: {}", name, err);
+                    None
+                }
+            },
+            None if now >= self.next_attempt => match (self.spawn)() {
+                Ok(child) => {
+                    self.child = Some(child);
+                    self.started = now;
+                    Some(ProcessEvent::Restarted { name })
+                }
+                Err(err) => {
+                    self.schedule_restart(now);
+                    let error = err.to_string();
+                    Some(ProcessEvent::RestartFailed { name, error })
+                }
+            },
+            None => None,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Supervisor {
+    processes: Arc<Mutex<Vec<ManagedProcess>>>,
+    stop: Arc<AtomicBool>,
+    monitor: Option<JoinHandle<()>>,
+}
+
+impl Supervisor {
+    pub fn new() -> Self {
+        Supervisor::default()
+    }
+
+    /// keep `child` running under `name`, `spawn` starts it again once it exits
+    pub fn manage<F>(&mut self, name: &str, child: Child, spawn: F)
+    where
+        F: Fn() -> io::Result<Child> + Send + 'static,
+    {
+        let now = Instant::now();
+        self.processes.lock().unwrap().push(ManagedProcess {
+            name: name.to_owned(),
+            spawn: Box::new(spawn),
+            child: Some(child),
+            started: now,
+            failures: 0,
+            next_attempt: now,
+        });
+    }
+
+    /// watch the processes in a thread of their own, `on_event` is called from that thread
+    pub fn start<F>(&mut self, mut on_event: F)
+    where
+        F: FnMut(ProcessEvent) + Send + 'static,
+    {
+        let processes = Arc::clone(&self.processes);
+        let stop = Arc::clone(&self.stop);
+        self.monitor = Some(thread::spawn(move || {
+            while !stop.load(Ordering::SeqCst) {
+                let now = Instant::now();
+                let events: Vec<ProcessEvent> = processes
+                    .lock()
+                    .unwrap()
+                    .iter_mut()
+                    .filter_map(|process| process.check(now))
+                    .collect();
+                for event in events {
+                    log::warn!("{:?}", event);
+                    on_event(event);
+                }
+                thread::sleep(Duration::from_millis(POLL_INTERVAL_MS));
+            }
+        }));
+    }
+}
+
+impl Drop for Supervisor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(monitor) = self.monitor.take() {
+            let _ = monitor.join();
+        }
+        for process in self.processes.lock().unwrap().iter_mut() {
+            if let Some(ref mut child) = process.child {
+                log::info!("kill {}", process.name);
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{process::Command, sync::mpsc, time::Duration};
+    use super::{Supervisor, ProcessEvent};
+
+    #[test]
+    fn restart() {
+        let mut supervisor = Supervisor::new();
+        let child = Command::new("true").spawn().unwrap();
+        supervisor.manage("true", child, || Command::new("sleep").arg("10").spawn());
+        let (sender, receiver) = mpsc::channel();
+        supervisor.start(move |event| sender.send(event).unwrap());
+
+        let timeout = Duration::from_secs(10);
+        match receiver.recv_timeout(timeout).unwrap() {
+            ProcessEvent::Exited { name, .. } => assert_eq!(name, "true"),
+            event => panic!("unexpected {:?}", event),
+        }
+        let event = receiver.recv_timeout(timeout).unwrap();
+        assert_eq!(event, ProcessEvent::Restarted { name: "true".to_owned() });
+        // the restarted process keeps running until the supervisor kills it
+        assert!(receiver.recv_timeout(Duration::from_secs(2)).is_err());
+    }
+
+    #[test]
+    fn backoff() {
+        let mut supervisor = Supervisor::new();
+        let child = Command::new("true").spawn().unwrap();
+        supervisor.manage("missing", child, || Command::new("/nonexistent/binary").spawn());
+        let mut processes = supervisor.processes.lock().unwrap();
+        let process = &mut processes[0];
+        let delays: Vec<u64> = (0..8)
+            .map(|_| {
+                let now = process.started;
+                process.schedule_restart(now);
+                process.next_attempt.duration_since(now).as_secs()
+            })
+            .collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 16, 32, 60, 60]);
+    }
+}
@@ -0,0 +1,188 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Taproot key path spending
+//!
+//! P2TR output scripts, the BIP341 signature hash of a key path spend
+//! and the BIP340 Schnorr signature of it
+//!
+use bitcoin::{
+    util::key::{PublicKey, PrivateKey},
+    blockdata::transaction::{Transaction, TxOut},
+    blockdata::script::Script,
+    consensus::encode::serialize,
+};
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+use secp256k1::{self, Secp256k1, SecretKey};
+use zeroize::Zeroizing;
+
+use std::error::Error;
+
+use super::keyfactory::wipe_private_key;
+
+// the order of the curve minus one, multiplying by it negates a secret key
+const MINUS_ONE: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe,
+    0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c, 0xd0, 0x36, 0x41, 0x40,
+];
+
+/// SHA256 of the data prefixed with the SHA256 of the tag twice (BIP340)
+pub fn tagged_hash(tag: &str, data: &[&[u8]]) -> [u8; 32] {
+    let mut tag_hash = [0u8; 32];
+    let mut sha = Sha256::new();
+    sha.input_str(tag);
+    sha.result(&mut tag_hash);
+
+    let mut sha = Sha256::new();
+    sha.input(&tag_hash);
+    sha.input(&tag_hash);
+    for item in data {
+        sha.input(item);
+    }
+    let mut hash = [0u8; 32];
+    sha.result(&mut hash);
+    hash
+}
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    let mut sha = Sha256::new();
+    sha.input(data);
+    sha.result(&mut hash);
+    hash
+}
+
+/// P2TR output script paying to the x coordinate of the key
+pub fn output_script(pk: &PublicKey) -> Script {
+    let mut script = vec![0x51, 0x20];
+    script.extend_from_slice(&pk.key.serialize()[1..]);
+    Script::from(script)
+}
+
+/// x-only output key of a P2TR output script
+pub fn output_key(script: &Script) -> Option<&[u8]> {
+    let script = script.as_bytes();
+    if script.len() == 34 && script[0] == 0x51 && script[1] == 0x20 {
+        Some(&script[2..])
+    } else {
+        None
+    }
+}
+
+/// message signed by a key path spend of the input `index` with SIGHASH_DEFAULT,
+/// `spent` are the outputs spent by all inputs of the transaction in their order
+pub fn sighash(tx: &Transaction, index: usize, spent: &[TxOut]) -> [u8; 32] {
+    let mut prevouts = Vec::new();
+    let mut sequences = Vec::new();
+    for input in &tx.input {
+        prevouts.extend(serialize(&input.previous_output));
+        sequences.extend_from_slice(&input.sequence.to_le_bytes());
+    }
+    let mut amounts = Vec::new();
+    let mut script_pubkeys = Vec::new();
+    for output in spent {
+        amounts.extend_from_slice(&output.value.to_le_bytes());
+        script_pubkeys.extend(serialize(&output.script_pubkey));
+    }
+    let outputs: Vec<u8> = tx.output.iter().flat_map(serialize).collect();
+
+    // epoch 0 and the hash type, then the spend type 0 (key path without annex)
+    tagged_hash(
+        "TapSighash",
+        &[
+            &[0x00, 0x00],
+            &tx.version.to_le_bytes(),
+            &tx.lock_time.to_le_bytes(),
+            &sha256(&prevouts),
+            &sha256(&amounts),
+            &sha256(&script_pubkeys),
+            &sha256(&sequences),
+            &sha256(&outputs),
+            &[0x00],
+            &(index as u32).to_le_bytes(),
+        ],
+    )
+}
+
+/// BIP340 signature of `msg`, the key is negated if its point has an odd y,
+/// `aux` is fresh randomness guarding the nonce against side channels
+pub fn sign(sk: &PrivateKey, msg: &[u8; 32], aux: &[u8; 32]) -> Result<[u8; 64], Box<dyn Error>> {
+    let ctx = Secp256k1::new();
+    let mut d = *sk;
+    let mut k = *sk;
+    let sig = sign_with(&ctx, &mut d, &mut k, msg, aux);
+    wipe_private_key(&mut d);
+    wipe_private_key(&mut k);
+    sig
+}
+
+// `d` and `k` hold the secret key and the nonce while signing, the caller wipes them
+fn sign_with(
+    ctx: &Secp256k1<secp256k1::All>,
+    d: &mut PrivateKey,
+    k: &mut PrivateKey,
+    msg: &[u8; 32],
+    aux: &[u8; 32],
+) -> Result<[u8; 64], Box<dyn Error>> {
+    let p = secp256k1::PublicKey::from_secret_key(ctx, &d.key).serialize();
+    if p[0] == 0x03 {
+        d.key.mul_assign(&MINUS_ONE)?;
+    }
+
+    let mut t = Zeroizing::new(tagged_hash("BIP0340/aux", &[aux]));
+    for (t, d) in t.iter_mut().zip(d.key[..].iter()) {
+        *t ^= d;
+    }
+    // a hash out of the range of the curve order is as unlikely as finding a collision
+    k.key = SecretKey::from_slice(&tagged_hash("BIP0340/nonce", &[&t[..], &p[1..], msg]))?;
+    let r = secp256k1::PublicKey::from_secret_key(ctx, &k.key).serialize();
+    if r[0] == 0x03 {
+        k.key.mul_assign(&MINUS_ONE)?;
+    }
+
+    let e = tagged_hash("BIP0340/challenge", &[&r[1..], &p[1..], msg]);
+    d.key.mul_assign(&e)?;
+    d.key.add_assign(&k.key[..])?;
+
+    let mut sig = [0u8; 64];
+    sig[..32].copy_from_slice(&r[1..]);
+    sig[32..].copy_from_slice(&d.key[..]);
+    Ok(sig)
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::network::constants::Network;
+    use bitcoin::util::key::PrivateKey;
+    use secp256k1::SecretKey;
+
+    #[test]
+    fn bip340_test_vector() {
+        let mut key = [0u8; 32];
+        key[31] = 3;
+        let sk = PrivateKey {
+            compressed: true,
+            network: Network::Bitcoin,
+            key: SecretKey::from_slice(&key).unwrap(),
+        };
+        let sig = super::sign(&sk, &[0u8; 32], &[0u8; 32]).unwrap();
+        assert_eq!(
+            hex::encode_upper(&sig[..]),
+            "E907831F80848D1069A5371B402410364BDF1C5F8307B0084C55F1CE2DCA8215\
+             25F66A4A85EA8B71E482A74F382D2CE5EBEEE8FDB2172F477DF4900D310536C0"
+        );
+    }
+}
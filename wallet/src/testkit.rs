@@ -0,0 +1,228 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Regtest harness
+//!
+//! Runs bitcoind, and electrs if asked for, on the free ports and in the temp directory
+//! `GlobalContext::default()` picks, so tests neither need a node started by hand
+//! nor get in each other's way.
+//! The processes are killed and their data is removed when the harness is dropped.
+//! Enabled by the `testkit` feature
+//!
+//! ```ignore
+//! let harness = RegtestHarness::new()?;
+//! let mode = WalletLibraryMode::Create(KeyGenConfig::default());
+//! let (mut context, _) = harness.default_context(mode)?;
+//! let addr = context.wallet_mut().wallet_lib_mut().new_address(AccountAddressType::P2WKH)?;
+//! harness.fund_address(&addr, 1.0)?;
+//! context.wallet_mut().sync_with_tip()?;
+//! ```
+//!
+use bitcoin::Address;
+use bitcoin_hashes::sha256d::Hash as Sha256dHash;
+use bitcoin_rpc_client::{Client, RpcApi};
+
+use std::{
+    error::Error,
+    fs,
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpStream},
+    path::Path,
+    process::Child,
+    str::FromStr,
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::context::{GlobalContext, WalletContext};
+use super::walletlibrary::WalletLibraryMode;
+use super::mnemonic::Mnemonic;
+
+const STARTUP_TIMEOUT_SECS: u64 = 30;
+const SYNC_TIMEOUT_SECS: u64 = 30;
+const POLL_INTERVAL_MS: u64 = 100;
+// coinbase outputs can be spent once they have this many confirmations
+const COINBASE_MATURITY: u64 = 100;
+
+pub struct RegtestHarness {
+    context: GlobalContext,
+    client: Client,
+    bitcoind: Child,
+    electrs: Option<Child>,
+    electrum_address: SocketAddr,
+}
+
+// poll `ready` until it gives a value or `timeout_secs` pass, `what` names it in the error
+fn wait_for<T, F>(what: &str, timeout_secs: u64, mut ready: F) -> Result<T, Box<dyn Error>>
+where
+    F: FnMut() -> Result<Option<T>, Box<dyn Error>>,
+{
+    let start = Instant::now();
+    loop {
+        if let Some(value) = ready()? {
+            return Ok(value);
+        }
+        if start.elapsed() > Duration::from_secs(timeout_secs) {
+            return Err(From::from(format!("timed out waiting for {}", what)));
+        }
+        thread::sleep(Duration::from_millis(POLL_INTERVAL_MS));
+    }
+}
+
+// fails if the process is gone, e.g. because it couldn't bind its port
+fn check_running(name: &str, process: &mut Child) -> Result<(), Box<dyn Error>> {
+    match process.try_wait()? {
+        Some(status) => Err(From::from(format!("{} exited with {}", name, status))),
+        None => Ok(()),
+    }
+}
+
+impl RegtestHarness {
+    /// bitcoind only, for wallets of the trusted full node backend
+    pub fn new() -> Result<Self, Box<dyn Error>> {
+        RegtestHarness::launch(false)
+    }
+
+    /// bitcoind along with electrs, for wallets of both backends
+    pub fn with_electrs() -> Result<Self, Box<dyn Error>> {
+        RegtestHarness::launch(true)
+    }
+
+    fn launch(electrs: bool) -> Result<Self, Box<dyn Error>> {
+        let context = GlobalContext::default();
+        let client = context.client()?;
+        let electrum_address = context.electrum_socket_address().unwrap();
+        let bitcoind = context.spawn_bitcoind()?;
+        // from here on the drop cleans up whatever was started
+        let mut harness = RegtestHarness {
+            context,
+            client,
+            bitcoind,
+            electrs: None,
+            electrum_address,
+        };
+
+        wait_for("bitcoind", STARTUP_TIMEOUT_SECS, || {
+            check_running("bitcoind", &mut harness.bitcoind)?;
+            Ok(harness.client.get_block_count().ok())
+        })?;
+        // the wallets start scanning above the first block
+        harness.generate(1)?;
+
+        if electrs {
+            harness.electrs = Some(harness.context.spawn_electrs()?);
+            wait_for("electrs", STARTUP_TIMEOUT_SECS, || {
+                check_running("electrs", harness.electrs.as_mut().unwrap())?;
+                Ok(TcpStream::connect(harness.electrum_address).ok())
+            })?;
+            harness.wait_for_sync()?;
+        }
+        Ok(harness)
+    }
+
+    /// ports and directories the processes use
+    pub fn context(&self) -> &GlobalContext {
+        &self.context
+    }
+
+    /// settings of the wallets made by `default_context` and `electrs_context`
+    pub fn context_mut(&mut self) -> &mut GlobalContext {
+        &mut self.context
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    pub fn default_context(
+        &self,
+        mode: WalletLibraryMode,
+    ) -> Result<(WalletContext, Mnemonic), Box<dyn Error>> {
+        self.context.default_context(mode)
+    }
+
+    pub fn electrs_context(
+        &self,
+        mode: WalletLibraryMode,
+    ) -> Result<(WalletContext, Mnemonic), Box<dyn Error>> {
+        if self.electrs.is_none() {
+            return Err(From::from("the harness runs no electrs, see `with_electrs`"));
+        }
+        self.context.electrs_context(mode)
+    }
+
+    /// mine `count` blocks paying to the node, waits until electrs indexed them if it runs
+    pub fn generate(&self, count: u64) -> Result<Vec<Sha256dHash>, Box<dyn Error>> {
+        let hashes = self.client.generate(count, None)?;
+        self.wait_for_sync()?;
+        Ok(hashes)
+    }
+
+    /// send `btc` from the node to `addr` and confirm it in a block,
+    /// the node mines coins to send first unless it has mature ones
+    pub fn fund_address(&self, addr: &str, btc: f64) -> Result<Sha256dHash, Box<dyn Error>> {
+        let height = self.client.get_block_count()?;
+        if height <= COINBASE_MATURITY {
+            self.generate(COINBASE_MATURITY + 1 - height)?;
+        }
+        let addr = Address::from_str(addr)?;
+        let txid = self.client.send_to_address(&addr, btc, None, None, None, None, None, None)?;
+        self.generate(1)?;
+        Ok(txid)
+    }
+
+    /// wait until electrs indexed the tip of the node, the wallets of its backend
+    /// see a transaction only then, with no electrs this returns right away
+    pub fn wait_for_sync(&self) -> Result<(), Box<dyn Error>> {
+        if self.electrs.is_none() {
+            return Ok(());
+        }
+        let tip = self.client.get_block_count()?;
+        wait_for("electrs to sync", SYNC_TIMEOUT_SECS, || {
+            Ok(Some(self.electrum_tip()?).filter(|&height| height >= tip).map(|_| ()))
+        })
+    }
+
+    // height of the tip electrs indexed, asked on a connection of its own
+    fn electrum_tip(&self) -> Result<u64, Box<dyn Error>> {
+        let stream = TcpStream::connect(self.electrum_address)?;
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "method": "blockchain.headers.subscribe",
+            "params": [],
+        });
+        (&stream).write_all(format!("{}\n", request).as_bytes())?;
+        let mut response = String::new();
+        BufReader::new(&stream).read_line(&mut response)?;
+        let response: serde_json::Value = serde_json::from_str(&response)?;
+        response["result"]["height"]
+            .as_u64()
+            .ok_or_else(|| From::from(format!("unexpected response {}", response)))
+    }
+}
+
+impl Drop for RegtestHarness {
+    fn drop(&mut self) {
+        for process in self.electrs.iter_mut().chain(Some(&mut self.bitcoind)) {
+            let _ = process.kill();
+            let _ = process.wait();
+        }
+        // the wallet database and the data of bitcoind are in the same temp dir
+        if let Some(dir) = self.context.bitcoind_data_dir().and_then(Path::parent) {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+}
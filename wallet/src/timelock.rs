@@ -0,0 +1,292 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Timelocked outputs
+//!
+//! P2WSH outputs of `<lock> OP_CHECKSEQUENCEVERIFY|OP_CHECKLOCKTIMEVERIFY OP_DROP <pk>
+//! OP_CHECKSIG`, savings nobody, the wallet included, can spend before the lock expires.
+//! A relative lock counts the blocks since the output is confirmed (BIP68, BIP112),
+//! an absolute one waits for a block height (BIP65).
+//!
+use bitcoin::{
+    blockdata::{
+        opcodes,
+        script::{Builder, Script},
+        transaction::{SigHashType, Transaction},
+    },
+    util::key::{PrivateKey, PublicKey},
+};
+use secp256k1::{Secp256k1, Message};
+use serde::{Serialize, Deserialize};
+
+use std::error::Error;
+
+use super::sighash;
+
+/// nLockTime below it is a block height, a unix time otherwise
+const LOCKTIME_THRESHOLD: u32 = 500_000_000;
+/// sequence of an input which is neither final nor relatively locked, it signals BIP125 too
+const NON_FINAL_SEQUENCE: u32 = 0xFFFFFFFD;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timelock {
+    /// OP_CHECKSEQUENCEVERIFY, spendable this many blocks after the output is confirmed
+    Blocks(u16),
+    /// OP_CHECKLOCKTIMEVERIFY, spendable in the blocks after this height
+    Height(u32),
+}
+
+impl Timelock {
+    /// refuse a lock which is no lock, or one of a unix time
+    pub fn check(&self) -> Result<(), Box<dyn Error>> {
+        match *self {
+            Timelock::Blocks(0) => Err(From::from("a relative timelock of 0 blocks locks nothing")),
+            Timelock::Height(height) if height == 0 || height >= LOCKTIME_THRESHOLD => {
+                Err(From::from(format!("{} is not a block height to lock until", height)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// the script the P2WSH output commits to, locking the coins of `pk`
+    pub fn witness_script(&self, pk: &PublicKey) -> Script {
+        let (lock, opcode) = match *self {
+            Timelock::Blocks(blocks) => (blocks as i64, opcodes::all::OP_CSV),
+            Timelock::Height(height) => (height as i64, opcodes::all::OP_CLTV),
+        };
+        Builder::new()
+            .push_int(lock)
+            .push_opcode(opcode)
+            .push_opcode(opcodes::all::OP_DROP)
+            .push_slice(&pk.key.serialize())
+            .push_opcode(opcodes::all::OP_CHECKSIG)
+            .into_script()
+    }
+
+    /// bytes of the witness of an input spending the output: the count of its items,
+    /// a signature of the largest size and the witness script, each with its length
+    pub fn witness_size(&self) -> usize {
+        let lock = match *self {
+            Timelock::Blocks(blocks) => blocks as i64,
+            Timelock::Height(height) => height as i64,
+        };
+        // the opcode checking the lock, OP_DROP, the push of the key and OP_CHECKSIG
+        let script_size = Builder::new().push_int(lock).into_script().len() + 1 + 1 + 34 + 1;
+        1 + (1 + 72) + (1 + script_size)
+    }
+
+    /// nSequence of an input spending the output
+    pub fn sequence(&self) -> u32 {
+        match *self {
+            // the type flag is unset, so the lock counts blocks
+            Timelock::Blocks(blocks) => blocks as u32,
+            Timelock::Height(_) => NON_FINAL_SEQUENCE,
+        }
+    }
+
+    /// the smallest nLockTime of a transaction spending the output
+    pub fn lock_time(&self) -> u32 {
+        match *self {
+            Timelock::Blocks(_) => 0,
+            Timelock::Height(height) => height,
+        }
+    }
+
+    /// height of the first block which may include a spend of an output confirmed at `height`,
+    /// `None` for a relative lock of an unconfirmed output
+    pub fn mature_height(&self, height: Option<usize>) -> Option<usize> {
+        match *self {
+            Timelock::Blocks(blocks) => height.map(|height| height + blocks as usize),
+            Timelock::Height(lock_height) => Some(lock_height as usize + 1),
+        }
+    }
+}
+
+/// sign the input `i` spending a timelocked output of `value` paying to the key of `sk`,
+/// the sequence of the input and the lock time of `tx` have to be set before
+pub(crate) fn sign_input(
+    tx: &mut Transaction,
+    i: usize,
+    sk: &PrivateKey,
+    timelock: &Timelock,
+    value: u64,
+) -> Result<(), Box<dyn Error>> {
+    let ctx = Secp256k1::new();
+    let pk = PublicKey::from_private_key(&ctx, sk);
+    let witness_script = timelock.witness_script(&pk);
+    let sighash_type = SigHashType::All;
+    let hash = sighash::signature_hash(tx, i, &witness_script, value, sighash_type, true)?;
+    let signature = ctx.sign(&Message::from_slice(&hash[..])?, &sk.key);
+
+    let mut serialized_sig = signature.serialize_der().to_vec();
+    serialized_sig.push(sighash_type.as_u32() as u8);
+    tx.input[i].witness = vec![serialized_sig, witness_script.to_bytes()];
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::{
+        blockdata::{
+            opcodes,
+            script::{Builder, Script},
+            transaction::{OutPoint, SigHashType, Transaction, TxIn, TxOut},
+        },
+        util::key::{PrivateKey, PublicKey},
+        network::constants::Network,
+    };
+    use bitcoin_hashes::{sha256d::Hash as Sha256dHash, Hash};
+    use secp256k1::{Secp256k1, SecretKey, Message, Signature};
+    use crate::sighash::signature_hash;
+    use crate::walletlibrary::{WalletConfig, SendOptions, DEFAULT_FEE_RATE};
+    use crate::default::WalletWithTrustedFullNode;
+    use crate::memory::{MemoryChain, test::{new_wallet, payment}};
+    use crate::account::AccountAddressType;
+    use crate::interface::Wallet;
+    use super::{Timelock, sign_input};
+
+    fn key() -> PrivateKey {
+        PrivateKey {
+            compressed: true,
+            network: Network::Regtest,
+            key: SecretKey::from_slice(&[0x22; 32]).unwrap(),
+        }
+    }
+
+    #[test]
+    fn scripts() {
+        let pk = PublicKey::from_private_key(&Secp256k1::new(), &key());
+        let expected = Builder::new()
+            .push_int(1000)
+            .push_opcode(opcodes::all::OP_CSV)
+            .push_opcode(opcodes::all::OP_DROP)
+            .push_slice(&pk.key.serialize())
+            .push_opcode(opcodes::all::OP_CHECKSIG)
+            .into_script();
+        assert_eq!(Timelock::Blocks(1000).witness_script(&pk), expected);
+        let script = Timelock::Height(700_000).witness_script(&pk);
+        assert_eq!(script.as_bytes()[4], opcodes::all::OP_CLTV.into_u8());
+
+        assert!(Timelock::Blocks(0).check().is_err());
+        assert!(Timelock::Height(500_000_000).check().is_err());
+        assert!(Timelock::Height(700_000).check().is_ok());
+    }
+
+    #[test]
+    fn maturity() {
+        assert_eq!(Timelock::Blocks(10).mature_height(None), None);
+        assert_eq!(Timelock::Blocks(10).mature_height(Some(100)), Some(110));
+        assert_eq!(Timelock::Height(150).mature_height(None), Some(151));
+        assert_eq!(Timelock::Blocks(10).sequence(), 10);
+        assert_eq!(Timelock::Height(150).lock_time(), 150);
+    }
+
+    #[test]
+    fn sign() {
+        let sk = key();
+        let pk = PublicKey::from_private_key(&Secp256k1::new(), &sk);
+        let timelock = Timelock::Blocks(144);
+        let mut tx = Transaction {
+            version: 2,
+            lock_time: timelock.lock_time(),
+            input: vec![TxIn {
+                previous_output: OutPoint {
+                    txid: Sha256dHash::hash(b"savings"),
+                    vout: 0,
+                },
+                script_sig: Script::new(),
+                sequence: timelock.sequence(),
+                witness: Vec::new(),
+            }],
+            output: vec![TxOut {
+                value: 90_000,
+                script_pubkey: Script::from(vec![0x51]),
+            }],
+        };
+        sign_input(&mut tx, 0, &sk, &timelock, 100_000).unwrap();
+
+        let witness = &tx.input[0].witness;
+        assert_eq!(witness.len(), 2);
+        // the signature may be shorter than the largest one counted
+        let size = 1 + (1 + witness[0].len()) + (1 + witness[1].len());
+        assert!(size <= timelock.witness_size() && size + 2 >= timelock.witness_size());
+        let witness_script = timelock.witness_script(&pk);
+        assert_eq!(witness[1], witness_script.to_bytes());
+        let hash =
+            signature_hash(&tx, 0, &witness_script, 100_000, SigHashType::All, true).unwrap();
+        let sig = Signature::from_der(&witness[0][..witness[0].len() - 1]).unwrap();
+        let msg = Message::from_slice(&hash[..]).unwrap();
+        Secp256k1::new().verify(&msg, &sig, &pk.key).unwrap();
+    }
+
+    #[test]
+    fn timelocked_savings() {
+        let (mut wallet, chain) = new_wallet(WalletConfig::in_memory());
+        let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        chain.push_block(Vec::new());
+        chain.push_block(vec![payment(&addr, 100_000_000)]);
+        wallet.sync_with_tip().unwrap();
+
+        let timelock = Timelock::Blocks(3);
+        let savings = wallet.wallet_lib_mut().new_timelocked_address(timelock).unwrap();
+        let deposit = wallet.send_coins(
+            savings,
+            60_000_000,
+            true,
+            SendOptions::default(),
+        ).unwrap().sent().unwrap();
+        chain.mine();
+        wallet.sync_with_tip().unwrap();
+        let locked = wallet.wallet_lib().get_timelocked_utxo_list();
+        assert_eq!(locked.len(), 1);
+        assert_eq!(locked[0].value, 60_000_000);
+        let height = wallet.wallet_lib().get_last_seen_block_height_from_memory();
+        assert_eq!(locked[0].mature_height(), Some(height + 3));
+        assert!(!locked[0].is_mature(height));
+        assert_eq!(wallet.wallet_lib().wallet_balance(), 40_000_000 - deposit.fee);
+
+        // the change alone can't pay until the lock expires
+        let dest_addr = "bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49".to_owned();
+        let send = |wallet: &mut WalletWithTrustedFullNode<MemoryChain>| {
+            wallet.send_coins(dest_addr.clone(), 50_000_000, false, SendOptions::default())
+        };
+        assert!(send(&mut wallet).is_err());
+        chain.push_block(Vec::new());
+        chain.push_block(Vec::new());
+        wallet.sync_with_tip().unwrap();
+        let sent = send(&mut wallet).unwrap().sent().unwrap();
+        assert!(sent.selected_inputs.contains(&locked[0].out_point));
+        let input = sent
+            .tx
+            .input
+            .iter()
+            .find(|input| input.previous_output == locked[0].out_point)
+            .unwrap();
+        assert_eq!(input.sequence, timelock.sequence());
+        // the change has no lock, its input signals replaceability
+        let change_input = sent
+            .tx
+            .input
+            .iter()
+            .find(|input| input.previous_output != locked[0].out_point)
+            .unwrap();
+        assert_eq!(change_input.sequence, 0xFFFFFFFD);
+        assert_eq!(sent.tx.version, 2);
+        // the fee rate of the wallet, the signatures may be shorter than the ones counted
+        let vsize = (sent.tx.get_weight() as u64 + 3) / 4;
+        assert!(sent.fee >= vsize * DEFAULT_FEE_RATE);
+        assert!(sent.fee <= (vsize + 2) * DEFAULT_FEE_RATE);
+    }
+}
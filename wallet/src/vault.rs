@@ -0,0 +1,276 @@
+//
+// Copyright 2018 rust-wallet developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//!
+//! # Vaults
+//!
+//! Deposits to a vault address pay to a P2WSH script of two branches,
+//! `OP_IF <recovery pk> OP_CHECKSIG OP_ELSE <delay> OP_CHECKSEQUENCEVERIFY OP_DROP <hot pk>
+//! OP_CHECKSIG OP_ENDIF`. The recovery key, held offline, spends a deposit at any time,
+//! the hot key of the wallet only once it was confirmed for `delay` blocks.
+//!
+//! Spending deposits with the hot key is an unvault. The wallet remembers the unvaults it
+//! started, a deposit spent by any other transaction means a key of the vault is used
+//! elsewhere, and the deposits left can still be moved by the recovery key in time.
+//!
+use bitcoin::{
+    blockdata::{
+        opcodes,
+        script::{Builder, Script},
+        transaction::{SigHashType, Transaction},
+    },
+    util::key::{PrivateKey, PublicKey},
+};
+use secp256k1::{Secp256k1, Message};
+use serde::{Serialize, Deserialize};
+
+use std::error::Error;
+
+use super::sighash;
+
+/// the keys and the delay the deposits to the vault addresses are locked with
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Vault {
+    /// key of the recovery branch, its private key never touches the wallet
+    pub recovery_pk: PublicKey,
+    /// blocks a deposit has to be confirmed for before the hot key can spend it
+    pub delay: u16,
+}
+
+impl Vault {
+    /// refuse a vault whose hot key isn't delayed
+    pub fn check(&self) -> Result<(), Box<dyn Error>> {
+        if self.delay == 0 {
+            return Err(From::from("a vault needs a delay of at least 1 block"));
+        }
+        Ok(())
+    }
+
+    /// the script the P2WSH output of a deposit commits to, `hot_pk` is a key of the wallet
+    pub fn witness_script(&self, hot_pk: &PublicKey) -> Script {
+        Builder::new()
+            .push_opcode(opcodes::all::OP_IF)
+            .push_slice(&self.recovery_pk.key.serialize())
+            .push_opcode(opcodes::all::OP_CHECKSIG)
+            .push_opcode(opcodes::all::OP_ELSE)
+            .push_int(self.delay as i64)
+            .push_opcode(opcodes::all::OP_CSV)
+            .push_opcode(opcodes::all::OP_DROP)
+            .push_slice(&hot_pk.key.serialize())
+            .push_opcode(opcodes::all::OP_CHECKSIG)
+            .push_opcode(opcodes::all::OP_ENDIF)
+            .into_script()
+    }
+
+    /// nSequence of an input spending a deposit with the hot key
+    pub fn sequence(&self) -> u32 {
+        self.delay as u32
+    }
+
+    /// height of the first block which may include an unvault of a deposit confirmed at
+    /// `height`, `None` while the deposit is unconfirmed
+    pub fn mature_height(&self, height: Option<usize>) -> Option<usize> {
+        height.map(|height| height + self.delay as usize)
+    }
+}
+
+/// sign the input `i` unvaulting a deposit of `value` with the hot key `sk`,
+/// the sequence of the input has to be set before
+pub(crate) fn sign_unvault_input(
+    tx: &mut Transaction,
+    i: usize,
+    sk: &PrivateKey,
+    vault: &Vault,
+    value: u64,
+) -> Result<(), Box<dyn Error>> {
+    let ctx = Secp256k1::new();
+    let pk = PublicKey::from_private_key(&ctx, sk);
+    let witness_script = vault.witness_script(&pk);
+    let sighash_type = SigHashType::All;
+    let hash = sighash::signature_hash(tx, i, &witness_script, value, sighash_type, true)?;
+    let signature = ctx.sign(&Message::from_slice(&hash[..])?, &sk.key);
+
+    let mut serialized_sig = signature.serialize_der().to_vec();
+    serialized_sig.push(sighash_type.as_u32() as u8);
+    // the empty element takes the OP_ELSE branch
+    tx.input[i].witness = vec![serialized_sig, Vec::new(), witness_script.to_bytes()];
+    Ok(())
+}
+
+/// witness of an input spending a deposit by the recovery branch, for the offline signer
+/// holding the recovery key; `signature` carries its sighash type
+pub fn recovery_witness(signature: Vec<u8>, witness_script: &Script) -> Vec<Vec<u8>> {
+    vec![signature, vec![1], witness_script.to_bytes()]
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::{
+        blockdata::{
+            opcodes,
+            script::Script,
+            transaction::{OutPoint, SigHashType, Transaction, TxIn, TxOut},
+        },
+        util::key::{PrivateKey, PublicKey},
+        network::constants::Network,
+    };
+    use bitcoin_hashes::{sha256d::Hash as Sha256dHash, Hash};
+    use secp256k1::{Secp256k1, SecretKey, Message, Signature};
+    use crate::sighash::signature_hash;
+    use crate::walletlibrary::WalletConfig;
+    use crate::memory::test::{new_wallet, payment};
+    use crate::events::WalletEvent;
+    use crate::interface::Wallet;
+    use super::{Vault, sign_unvault_input};
+
+    fn key(byte: u8) -> PrivateKey {
+        PrivateKey {
+            compressed: true,
+            network: Network::Regtest,
+            key: SecretKey::from_slice(&[byte; 32]).unwrap(),
+        }
+    }
+
+    fn vault() -> Vault {
+        Vault {
+            recovery_pk: PublicKey::from_private_key(&Secp256k1::new(), &key(0x33)),
+            delay: 144,
+        }
+    }
+
+    #[test]
+    fn script() {
+        let hot_pk = PublicKey::from_private_key(&Secp256k1::new(), &key(0x22));
+        let script = vault().witness_script(&hot_pk);
+        let bytes = script.as_bytes();
+        assert_eq!(bytes[0], opcodes::all::OP_IF.into_u8());
+        assert_eq!(&bytes[2..35], &vault().recovery_pk.key.serialize()[..]);
+        assert_eq!(bytes[35], opcodes::all::OP_CHECKSIG.into_u8());
+        assert_eq!(bytes[bytes.len() - 1], opcodes::all::OP_ENDIF.into_u8());
+
+        assert_eq!(vault().mature_height(Some(100)), Some(244));
+        assert_eq!(vault().mature_height(None), None);
+        assert!(Vault { delay: 0, ..vault() }.check().is_err());
+    }
+
+    #[test]
+    fn sign() {
+        let sk = key(0x22);
+        let pk = PublicKey::from_private_key(&Secp256k1::new(), &sk);
+        let mut tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output: OutPoint {
+                    txid: Sha256dHash::hash(b"vault"),
+                    vout: 0,
+                },
+                script_sig: Script::new(),
+                sequence: vault().sequence(),
+                witness: Vec::new(),
+            }],
+            output: vec![TxOut {
+                value: 90_000,
+                script_pubkey: Script::from(vec![0x51]),
+            }],
+        };
+        sign_unvault_input(&mut tx, 0, &sk, &vault(), 100_000).unwrap();
+
+        let witness = &tx.input[0].witness;
+        assert_eq!(witness.len(), 3);
+        assert!(witness[1].is_empty());
+        let witness_script = vault().witness_script(&pk);
+        assert_eq!(witness[2], witness_script.to_bytes());
+        let hash =
+            signature_hash(&tx, 0, &witness_script, 100_000, SigHashType::All, true).unwrap();
+        let sig = Signature::from_der(&witness[0][..witness[0].len() - 1]).unwrap();
+        let msg = Message::from_slice(&hash[..]).unwrap();
+        Secp256k1::new().verify(&msg, &sig, &pk.key).unwrap();
+    }
+
+    #[test]
+    fn vault_account() {
+        let (mut wallet, chain) = new_wallet(WalletConfig::in_memory());
+        assert!(wallet.wallet_lib_mut().new_vault_address().is_err());
+        let recovery_sk = SecretKey::from_slice(&[0x44; 32]).unwrap();
+        let recovery_pk = PublicKey {
+            compressed: true,
+            key: secp256k1::PublicKey::from_secret_key(&Secp256k1::new(), &recovery_sk),
+        };
+        let vault = Vault { recovery_pk, delay: 2 };
+        assert!(wallet.wallet_lib_mut().set_vault(Vault { delay: 0, ..vault }).is_err());
+        wallet.wallet_lib_mut().set_vault(vault).unwrap();
+        let first = wallet.wallet_lib_mut().new_vault_address().unwrap();
+        let second = wallet.wallet_lib_mut().new_vault_address().unwrap();
+        assert_ne!(first, second);
+
+        chain.push_block(Vec::new());
+        chain.push_block(vec![payment(&first, 50_000_000)]);
+        wallet.sync_with_tip().unwrap();
+        let events = wallet.wallet_lib_mut().subscribe();
+        assert_eq!(wallet.wallet_lib().get_vault_utxo_list().len(), 1);
+        assert_eq!(wallet.wallet_lib().wallet_balance(), 0);
+        let dest_addr = "bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49".to_owned();
+        assert!(wallet.unvault(dest_addr.clone(), 10).is_err());
+
+        // the first deposit matures as the second one is confirmed
+        chain.push_block(Vec::new());
+        chain.push_block(vec![payment(&second, 30_000_000)]);
+        wallet.sync_with_tip().unwrap();
+        let tx = wallet.unvault(dest_addr, 10).unwrap();
+        assert_eq!(tx.input.len(), 1);
+        assert_eq!(tx.input[0].sequence, 2);
+        assert_eq!(tx.input[0].witness.len(), 3);
+        assert!(tx.input[0].witness[1].is_empty());
+        assert!(tx.output[0].value < 50_000_000);
+        chain.mine();
+        wallet.sync_with_tip().unwrap();
+        let deposits = wallet.wallet_lib().get_vault_utxo_list();
+        assert_eq!(deposits.len(), 1);
+        assert_eq!(deposits[0].value, 30_000_000);
+
+        // someone else moves the second deposit, the wallet doesn't check the witness
+        let recovery = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output: deposits[0].out_point,
+                script_sig: Script::new(),
+                sequence: 0xFFFFFFFF,
+                witness: Vec::new(),
+            }],
+            output: vec![TxOut {
+                value: 29_990_000,
+                script_pubkey: Script::from(vec![0x51]),
+            }],
+        };
+        chain.push_block(vec![recovery.clone()]);
+        wallet.sync_with_tip().unwrap();
+        assert!(wallet.wallet_lib().get_vault_utxo_list().is_empty());
+        let alerts: Vec<WalletEvent> = events
+            .try_iter()
+            .filter(|event| match event {
+                WalletEvent::UnexpectedUnvault { .. } => true,
+                _ => false,
+            })
+            .collect();
+        assert_eq!(
+            alerts,
+            vec![WalletEvent::UnexpectedUnvault {
+                out_point: deposits[0].out_point,
+                txid: recovery.txid(),
+            }],
+        );
+    }
+}
@@ -20,36 +20,95 @@
 
 use bitcoin::{
     util::{
-        bip32::{ExtendedPubKey, ExtendedPrivKey,ChildNumber},
-        bip143,
+        bip32::{self, ExtendedPubKey, ExtendedPrivKey, ChildNumber, DerivationPath, Fingerprint},
         address::Address,
-        key::PublicKey,
+        key::{PublicKey, PrivateKey},
+        psbt::PartiallySignedTransaction,
     },
 
-    blockdata::transaction::{OutPoint, Transaction, TxIn, TxOut},
+    blockdata::transaction::{OutPoint, Transaction, TxIn, TxOut, SigHashType},
     blockdata::script::{Script, Builder},
+    blockdata::block::BlockHeader,
 
     network::constants::Network,
+    consensus::encode::serialize,
 };
+use bitcoin_hashes::sha256d::Hash as Sha256dHash;
 use secp256k1::{Secp256k1, Message};
 
 use std::{
+    cmp,
+    mem,
+    fs,
+    fmt,
+    ops::{Deref, DerefMut},
     error::Error,
-    sync::{Arc, RwLock},
-    collections::HashMap,
+    io::Write,
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard, mpsc::Receiver},
+    collections::{HashMap, HashSet, BTreeMap},
     str::FromStr,
+    path::Path,
+    time::Duration,
 };
 
 use serde::{Serialize, Deserialize};
+use zeroize::Zeroize;
+use rand::{rngs::OsRng, RngCore};
 
 use super::error::WalletError;
 use super::mnemonic::Mnemonic;
-use super::keyfactory::{KeyFactory, MasterKeyEntropy};
-use super::account::{Account, AccountAddressType, Utxo, KeyPath, AddressChain};
+use super::keyfactory::{KeyFactory, MasterKeyEntropy, wipe_extended_private_key, wipe_private_key};
+use super::bip47::{self, PaymentCode};
+use super::bip352::{self, SilentPaymentAddress};
+use super::address;
+use super::taproot;
+use super::timelock::{self, Timelock};
+use super::vault::{self, Vault};
+use super::policy::Policy;
+use super::sighash;
+use super::signer::{self, WatchOnlyAccount, WatchOnlyKeys};
+use super::reserves;
+use super::audit::AuditEntry;
+use super::headers::{self, HeaderRecord};
+use super::account::{
+    Account, AccountAddressType, AddressChain, AddressInfo, DerivationMode, DerivationScheme, Utxo,
+    KeyPath,
+};
+use super::history::{
+    self, TxRecord, TxStatus, FeeStats, ExportFormat, HistoryEntry, Direction, AddressStats,
+    fee_paid,
+};
+use super::price::PriceSource;
+use super::events::{EventSink, WalletEvent};
+use super::backup::Backup;
+use super::snapshot::StateSnapshot;
 use super::DB;
-use super::interface::WalletLibraryInterface;
+use super::interface::{WalletLibraryInterface, BackendKind};
+use super::retry::RetryPolicy;
 
 pub static DEFAULT_BITCOIND_RPC_CONNECT: &'static str = "http://127.0.0.1:18332";
+/// outputs below it are not relayed, such a change is left to the fee instead
+const DUST_LIMIT: u64 = 546;
+/// branches the search for coins paying a payment exactly tries before it gives up
+const EXACT_MATCH_TRIES: usize = 100_000;
+/// sequence of the inputs signaling that the transaction may be replaced (BIP125)
+const RBF_SEQUENCE: u32 = 0xFFFFFFFD;
+/// version of the transactions the wallet creates, relative lock times (BIP68) of
+/// the inputs are enforced from version 2 on
+const TX_VERSION: i32 = 2;
+/// number of unpaid addresses watched ahead for every payment code which notified the wallet
+const PAYMENT_CODE_LOOKAHEAD: u32 = 10;
+/// seconds the daily limit of the spending policy looks back
+const POLICY_WINDOW: u64 = 24 * 60 * 60;
+/// seconds a payment waits for approval before it is rejected
+const APPROVAL_TIMEOUT: u64 = 24 * 60 * 60;
+/// BIP84 account of the keys of the timelocked outputs, the coins of the wallet are in 0'
+const TIMELOCK_ACCOUNT: u32 = 1;
+/// BIP84 account of the keys the wallet contributes to script accounts
+const SCRIPT_ACCOUNT: u32 = 2;
+/// BIP84 account of the hot keys of the vault addresses
+const VAULT_ACCOUNT: u32 = 3;
+
 pub static DEFAULT_BITCOIND_RPC_USER: &'static str = "user";
 pub static DEFAULT_BITCOIND_RPC_PASSWORD: &'static str = "password";
 pub static DEFAULT_ZMQ_PUB_RAW_BLOCK_ENDPOINT: &'static str = "tcp://localhost:18501";
@@ -60,6 +119,12 @@ pub const DEFAULT_ENTROPY: MasterKeyEntropy = MasterKeyEntropy::Recommended;
 pub static DEFAULT_PASSPHRASE: &'static str = "";
 pub static DEFAULT_SALT: &'static str = "easy";
 pub static DEFAULT_DB_PATH: &'static str = "rocks.db";
+pub const DEFAULT_CHANGE_ADDR_TYPE: AccountAddressType = AccountAddressType::P2WKH;
+// satoshi per virtual byte, the fallback fee rate of bitcoind
+pub const DEFAULT_FEE_RATE: u64 = 20;
+pub const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 5;
+// a large block may take bitcoind a while to serialize
+pub const DEFAULT_READ_TIMEOUT_SECS: u64 = 60;
 
 #[derive(Clone)]
 pub struct BitcoindConfig {
@@ -121,6 +186,86 @@ impl WalletConfigBuilder {
         self
     }
 
+    pub fn change_addr_type(mut self, change_addr_type: AccountAddressType) -> WalletConfigBuilder {
+        self.inner.change_addr_type = change_addr_type;
+        self
+    }
+
+    pub fn raw_blocks(mut self, raw_blocks: bool) -> WalletConfigBuilder {
+        self.inner.raw_blocks = raw_blocks;
+        self
+    }
+
+    pub fn verify_proofs(mut self, verify_proofs: bool) -> WalletConfigBuilder {
+        self.inner.verify_proofs = verify_proofs;
+        self
+    }
+
+    pub fn silent_payments(mut self, silent_payments: bool) -> WalletConfigBuilder {
+        self.inner.silent_payments = silent_payments;
+        self
+    }
+
+    pub fn prune_spent_after(mut self, confirmations: Option<usize>) -> WalletConfigBuilder {
+        self.inner.prune_spent_after = confirmations;
+        self
+    }
+
+    pub fn spend_unconfirmed_change(mut self, spend: bool) -> WalletConfigBuilder {
+        self.inner.spend_unconfirmed_change = spend;
+        self
+    }
+
+    pub fn spend_uneconomical(mut self, spend: bool) -> WalletConfigBuilder {
+        self.inner.spend_uneconomical = spend;
+        self
+    }
+
+    pub fn privacy(mut self, privacy: bool) -> WalletConfigBuilder {
+        self.inner.privacy = privacy;
+        self
+    }
+
+    pub fn avoid_reuse(mut self, avoid_reuse: bool) -> WalletConfigBuilder {
+        self.inner.avoid_reuse = avoid_reuse;
+        self
+    }
+
+    pub fn change_tolerance(mut self, tolerance: Option<u64>) -> WalletConfigBuilder {
+        self.inner.change_tolerance = tolerance;
+        self
+    }
+
+    pub fn fee_rate(mut self, fee_rate: u64) -> WalletConfigBuilder {
+        self.inner.fee_rate = fee_rate;
+        self
+    }
+
+    pub fn in_memory(mut self, in_memory: bool) -> WalletConfigBuilder {
+        self.inner.in_memory = in_memory;
+        self
+    }
+
+    pub fn force_overwrite(mut self, force_overwrite: bool) -> WalletConfigBuilder {
+        self.inner.force_overwrite = force_overwrite;
+        self
+    }
+
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> WalletConfigBuilder {
+        self.inner.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> WalletConfigBuilder {
+        self.inner.connect_timeout = connect_timeout;
+        self
+    }
+
+    pub fn read_timeout(mut self, read_timeout: Duration) -> WalletConfigBuilder {
+        self.inner.read_timeout = read_timeout;
+        self
+    }
+
     pub fn finalize(self) -> WalletConfig {
         self.inner
     }
@@ -128,45 +273,253 @@ impl WalletConfigBuilder {
 
 pub struct KeyGenConfig {
     entropy: MasterKeyEntropy,
+    passphrase: String,
+    salt: String,
     // TODO(evg): use enum instead?
     debug: bool,
+    /// accounts derived at other paths than m/purpose'/coin'/0' of their address type
+    derivation_schemes: Vec<(AccountAddressType, DerivationScheme)>,
+    derivation_mode: DerivationMode,
 }
 
 impl KeyGenConfig {
+    pub fn new(entropy: MasterKeyEntropy, passphrase: String, salt: String) -> Self {
+        Self {
+            entropy,
+            passphrase,
+            salt,
+            debug: false,
+            derivation_schemes: Vec::new(),
+            derivation_mode: DerivationMode::Standard,
+        }
+    }
+
     pub fn debug() -> Self {
         let mut key_gen_cfg = Self::default();
         key_gen_cfg.debug = true;
         key_gen_cfg
     }
+
+    /// derive the account of `addr_type` at `scheme`, the wallet keeps it for good
+    pub fn set_derivation_scheme(
+        &mut self,
+        addr_type: AccountAddressType,
+        scheme: DerivationScheme,
+    ) {
+        self.derivation_schemes.retain(|(t, _)| *t != addr_type);
+        self.derivation_schemes.push((addr_type, scheme));
+    }
+
+    /// a wallet of the legacy derivation finds the coins of one created by an older version
+    /// from the same mnemonic
+    pub fn set_derivation_mode(&mut self, mode: DerivationMode) {
+        self.derivation_mode = mode;
+    }
 }
 
 impl Default for KeyGenConfig {
     fn default() -> Self {
-        Self {
-            entropy: DEFAULT_ENTROPY,
-            debug: false,
-        }
+        KeyGenConfig::new(
+            DEFAULT_ENTROPY,
+            DEFAULT_PASSPHRASE.to_string(),
+            DEFAULT_SALT.to_string(),
+        )
+    }
+}
+
+impl Drop for KeyGenConfig {
+    fn drop(&mut self) {
+        self.passphrase.zeroize();
+        self.salt.zeroize();
+    }
+}
+
+/// secrets of an existing wallet
+/// `passphrase` encrypts the entropy stored in the database,
+/// `salt` is the BIP39 passphrase the seed is derived with, a salt other than the one
+/// of the stored seed opens a hidden wallet with a database of its own
+pub struct DecryptConfig {
+    passphrase: String,
+    salt: String,
+}
+
+impl DecryptConfig {
+    pub fn new(passphrase: String, salt: String) -> Self {
+        Self { passphrase, salt }
+    }
+}
+
+impl Default for DecryptConfig {
+    fn default() -> Self {
+        DecryptConfig::new(DEFAULT_PASSPHRASE.to_string(), DEFAULT_SALT.to_string())
+    }
+}
+
+impl Drop for DecryptConfig {
+    fn drop(&mut self) {
+        self.passphrase.zeroize();
+        self.salt.zeroize();
     }
 }
 
 #[derive(Clone)]
 pub struct WalletConfig {
     network: Network,
-    passphrase: String,
-    salt: String,
     db_path: String,
+    /// account the change goes to unless a transaction asks for another one
+    change_addr_type: AccountAddressType,
+    /// fetch blocks from bitcoind as raw hex and deserialize them locally
+    raw_blocks: bool,
+    /// check the transactions reported by an electrum server against their merkle proofs
+    verify_proofs: bool,
+    /// scan the transactions for BIP352 silent payments, the outputs their taproot inputs
+    /// spend are fetched from bitcoind along with the blocks
+    silent_payments: bool,
+    /// drop archived coins once their spending transaction has this many confirmations
+    prune_spent_after: Option<usize>,
+    /// the change of a transaction the wallet published can be spent before it is confirmed,
+    /// other unconfirmed coins are never selected
+    spend_unconfirmed_change: bool,
+    /// coins worth less than the fee of their input at the fee rate of a transaction
+    /// are selected too, e.g. to consolidate dust
+    spend_uneconomical: bool,
+    /// payments spend coins of a single account, of one address type, and avoid change
+    /// where a coin pays them exactly enough, unless a call asks otherwise
+    privacy: bool,
+    /// the coins of an address which received more than one payment are spent all together
+    /// or not at all, so that spending one of them doesn't link the others later
+    avoid_reuse: bool,
+    /// payments look for coins paying them exactly, up to this much more, before they
+    /// fall back to a transaction with change; change below it is left to the fee too
+    change_tolerance: Option<u64>,
+    /// satoshi per virtual byte payments pay unless a call asks for another fee rate
+    fee_rate: u64,
+    /// keep the data in memory only, `db_path` is ignored
+    in_memory: bool,
+    /// recovering from a mnemonic into a database holding a wallet of another seed
+    /// wipes that wallet instead of failing
+    force_overwrite: bool,
+    /// how calls to bitcoind failing on the connection are made again
+    retry_policy: RetryPolicy,
+    /// how long connecting to bitcoind or the electrum server may take
+    connect_timeout: Duration,
+    /// how long the backend may take to answer a call
+    read_timeout: Duration,
 }
 
 impl WalletConfig {
-    pub fn new(network: Network, passphrase: String, salt: String, db_path: String) -> Self {
+    pub fn new(network: Network, db_path: String) -> Self {
         Self {
             network,
-            passphrase,
-            salt,
             db_path,
+            change_addr_type: DEFAULT_CHANGE_ADDR_TYPE,
+            raw_blocks: false,
+            verify_proofs: false,
+            silent_payments: false,
+            prune_spent_after: None,
+            spend_unconfirmed_change: false,
+            spend_uneconomical: false,
+            privacy: false,
+            avoid_reuse: false,
+            change_tolerance: None,
+            fee_rate: DEFAULT_FEE_RATE,
+            in_memory: false,
+            force_overwrite: false,
+            retry_policy: RetryPolicy::default(),
+            connect_timeout: Duration::from_secs(DEFAULT_CONNECT_TIMEOUT_SECS),
+            read_timeout: Duration::from_secs(DEFAULT_READ_TIMEOUT_SECS),
         }
     }
 
+    /// config of an ephemeral wallet on regtest, nothing is written to disk,
+    /// e.g. for unit tests against a `wallet::memory::MemoryChain`
+    pub fn in_memory() -> Self {
+        let mut wc = Self::default();
+        wc.in_memory = true;
+        wc
+    }
+
+    pub fn set_change_addr_type(&mut self, change_addr_type: AccountAddressType) {
+        self.change_addr_type = change_addr_type;
+    }
+
+    pub fn set_raw_blocks(&mut self, raw_blocks: bool) {
+        self.raw_blocks = raw_blocks;
+    }
+
+    pub fn raw_blocks(&self) -> bool {
+        self.raw_blocks
+    }
+
+    pub fn set_verify_proofs(&mut self, verify_proofs: bool) {
+        self.verify_proofs = verify_proofs;
+    }
+
+    pub fn verify_proofs(&self) -> bool {
+        self.verify_proofs
+    }
+
+    pub fn set_silent_payments(&mut self, silent_payments: bool) {
+        self.silent_payments = silent_payments;
+    }
+
+    pub fn silent_payments(&self) -> bool {
+        self.silent_payments
+    }
+
+    pub fn set_prune_spent_after(&mut self, confirmations: Option<usize>) {
+        self.prune_spent_after = confirmations;
+    }
+
+    pub fn set_spend_unconfirmed_change(&mut self, spend: bool) {
+        self.spend_unconfirmed_change = spend;
+    }
+
+    pub fn set_spend_uneconomical(&mut self, spend: bool) {
+        self.spend_uneconomical = spend;
+    }
+
+    pub fn set_privacy(&mut self, privacy: bool) {
+        self.privacy = privacy;
+    }
+
+    pub fn set_avoid_reuse(&mut self, avoid_reuse: bool) {
+        self.avoid_reuse = avoid_reuse;
+    }
+
+    pub fn set_change_tolerance(&mut self, tolerance: Option<u64>) {
+        self.change_tolerance = tolerance;
+    }
+
+    pub fn set_fee_rate(&mut self, fee_rate: u64) {
+        self.fee_rate = fee_rate;
+    }
+
+    pub fn set_force_overwrite(&mut self, force_overwrite: bool) {
+        self.force_overwrite = force_overwrite;
+    }
+
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.retry_policy = retry_policy;
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    pub fn set_timeouts(&mut self, connect_timeout: Duration, read_timeout: Duration) {
+        self.connect_timeout = connect_timeout;
+        self.read_timeout = read_timeout;
+    }
+
+    pub fn connect_timeout(&self) -> Duration {
+        self.connect_timeout
+    }
+
+    pub fn read_timeout(&self) -> Duration {
+        self.read_timeout
+    }
+
     pub fn with_db_path(db_path: String) -> Self {
         let mut wc = Self::default();
         wc.db_path = db_path;
@@ -176,16 +529,11 @@ impl WalletConfig {
 
 impl Default for WalletConfig {
     fn default() -> Self {
-        WalletConfig::new(
-            DEFAULT_NETWORK,
-            DEFAULT_PASSPHRASE.to_string(),
-            DEFAULT_SALT.to_string(),
-            DEFAULT_DB_PATH.to_string(),
-        )
+        WalletConfig::new(DEFAULT_NETWORK, DEFAULT_DB_PATH.to_string())
     }
 }
 
-#[derive(Eq, PartialEq, Hash, Clone, Serialize, Deserialize)]
+#[derive(Eq, PartialEq, Hash, Clone, Debug, Serialize, Deserialize)]
 pub struct LockId(u64);
 
 impl LockId {
@@ -210,6 +558,74 @@ impl From<LockId> for u64 {
     }
 }
 
+/// restricts the coins `send_coins` selects from
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum InputFilter {
+    /// any coin which is not locked or frozen
+    Any,
+    /// coins of a single address type
+    AddressType(AccountAddressType),
+    /// coins of the account with the given index
+    Account(u32),
+    /// coins of the addresses with the given label, e.g. to keep the deposits of customers
+    /// apart from the funds of the business
+    Label(String),
+}
+
+impl InputFilter {
+    /// whether `utxo`, paying to an address labeled `label` if any, may be selected
+    pub fn matches(&self, utxo: &Utxo, label: Option<&String>) -> bool {
+        match self {
+            &InputFilter::Any => true,
+            &InputFilter::AddressType(ref addr_type) => utxo.addr_type == *addr_type,
+            &InputFilter::Account(account_index) => utxo.account_index == account_index,
+            &InputFilter::Label(ref wanted) => label == Some(wanted),
+        }
+    }
+}
+
+impl Default for InputFilter {
+    fn default() -> Self {
+        InputFilter::Any
+    }
+}
+
+/// how `send_coins` pays, the default selects from any coin with the change address type,
+/// privacy mode, change tolerance and fee rate of `WalletConfig`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SendOptions {
+    /// keep the selected coins from other payments until `unlock_coins`
+    pub lock_coins: bool,
+    pub input_filter: InputFilter,
+    pub change_addr_type: Option<AccountAddressType>,
+    /// turns the privacy mode of `WalletConfig` on or off for this payment
+    pub privacy: Option<bool>,
+    pub change_tolerance: Option<u64>,
+    /// satoshi per virtual byte
+    pub fee_rate: Option<u64>,
+}
+
+/// a coin spent by `make_tx_advanced`, with the sequence of its input
+/// and the sighash type of its signature
+#[derive(Clone, Debug, PartialEq)]
+pub struct InputSpec {
+    pub out_point: OutPoint,
+    /// e.g. a relative lock time by BIP68
+    pub sequence: u32,
+    pub sighash_type: SigHashType,
+}
+
+impl InputSpec {
+    /// spent the way `make_tx` spends its coins
+    pub fn new(out_point: OutPoint) -> Self {
+        InputSpec {
+            out_point,
+            sequence: RBF_SEQUENCE,
+            sighash_type: SigHashType::All,
+        }
+    }
+}
+
 // TODO(evg): impl iter?
 #[derive(Serialize, Deserialize,  Clone)]
 pub struct LockGroup(Vec<OutPoint>);
@@ -241,582 +657,5785 @@ impl LockGroupMap {
     }
 }
 
-pub struct WalletLibrary {
-    master_key: ExtendedPrivKey,
-    p2pkh_account: Account,
-    p2shwh_account: Account,
-    p2wkh_account: Account,
-    #[allow(dead_code)]
-    network: Network,
-
-    last_seen_block_height: usize,
-    op_to_utxo: HashMap<OutPoint, Utxo>,
-    next_lock_id: LockId,
-    locked_coins: LockGroupMap,
-    db: Arc<RwLock<DB>>,
+/// an inconsistency of the stored state found by `verify_state`
+#[derive(Clone, Debug, PartialEq)]
+pub enum StateIssue {
+    /// a coin of an account pays to a script other than the one of the key it is recorded with
+    UnknownUtxoScript(OutPoint),
+    /// the key at the path, below the derivation index of the account, isn't stored
+    MissingKey(AccountAddressType, KeyPath),
+    /// the script of the key at the path isn't indexed, or indexed with another path
+    MissingScript(AccountAddressType, KeyPath),
+    /// the address of the key at the path isn't in the address list
+    MissingAddress(AccountAddressType, KeyPath),
+    /// an address, or the address of a stored key, which none of the derived keys has
+    UnderivedAddress(AccountAddressType, String),
+    /// a lock group none of whose coins is unspent, and which no funding or queued payment
+    /// holds
+    OrphanedLockGroup(LockId),
 }
 
-impl WalletLibraryInterface for WalletLibrary {
-    fn new_address(&mut self, address_type: AccountAddressType) -> Result<String, Box<dyn Error>> {
-        self.get_account_mut(address_type)
-            .new_address()
-            // converts Bip32Error into `Box<dyn Error>`
-            .map_err(Into::into)
+impl StateIssue {
+    /// `repair_state` restores the missing entries and drops orphaned lock groups,
+    /// the other issues are left to a rescan
+    pub fn is_repairable(&self) -> bool {
+        match self {
+            StateIssue::UnknownUtxoScript(_) | StateIssue::UnderivedAddress(..) => false,
+            _ => true,
+        }
     }
+}
 
-    fn new_change_address(
-        &mut self,
-        address_type: AccountAddressType,
-    ) -> Result<String, Box<dyn Error>> {
-        self.get_account_mut(address_type)
-            .new_change_address()
-            .map_err(Into::into)
-    }
+impl fmt::Display for StateIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn path(key_path: &KeyPath) -> String {
+            let addr_chain: u32 = key_path.addr_chain().clone().into();
+            format!("{}/{}", addr_chain, key_path.addr_index())
+        }
 
-    fn get_utxo_list(&self) -> Vec<Utxo> {
-        let mut joined = Vec::new();
-        let accounts = [
-            &self.p2pkh_account,
-            &self.p2shwh_account,
-            &self.p2wkh_account,
-        ];
-        for account in &accounts {
-            let account_utxo_list = &account.get_utxo_list();
-            for (_, val) in *account_utxo_list {
-                joined.push(val.clone());
+        match self {
+            StateIssue::UnknownUtxoScript(op) => {
+                write!(f, "coin {}:{} pays to an unknown script", op.txid, op.vout)
+            }
+            StateIssue::MissingKey(addr_type, key_path) => {
+                write!(f, "{} key {} is not stored", addr_type.as_str(), path(key_path))
+            }
+            StateIssue::MissingScript(addr_type, key_path) => {
+                write!(f, "{} key {} is not indexed", addr_type.as_str(), path(key_path))
+            }
+            StateIssue::MissingAddress(addr_type, key_path) => {
+                write!(f, "{} address {} is not listed", addr_type.as_str(), path(key_path))
+            }
+            StateIssue::UnderivedAddress(addr_type, addr) => {
+                write!(f, "{} address {} is not derived", addr_type.as_str(), addr)
+            }
+            StateIssue::OrphanedLockGroup(lock_id) => {
+                write!(f, "lock group {} holds no unspent coin", lock_id.0)
             }
         }
-        joined
     }
+}
 
-    fn wallet_balance(&self) -> u64 {
-        let utxo_list = self.get_utxo_list();
+/// a new seed the wallet switches to once the transaction sweeping the coins to it is confirmed
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PendingRotation {
+    bip39_randomness: Vec<u8>,
+    txid: Sha256dHash,
+    /// master public key of the new seed, tells whether the randomness is decrypted correctly
+    master_public: String,
+}
 
-        let mut balance: u64 = 0;
-        for utxo in utxo_list {
-            balance += utxo.value;
-        }
-        balance
-    }
+/// an output paying to an imported key, it isn't part of the balance until swept into the wallet
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ImportedUtxo {
+    pub value: u64,
+    pub out_point: OutPoint,
+    pub pk_script: Script,
+    pub addr_type: AccountAddressType,
+}
 
-    fn unlock_coins(&mut self, lock_id: LockId) {
-        self.locked_coins.unlock_group(lock_id);
-    }
+/// an output paying to a watched script, it is counted in the watched balance only
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WatchedUtxo {
+    pub value: u64,
+    pub out_point: OutPoint,
+    pub script_pubkey: Script,
+    pub label: String,
+}
 
-    fn send_coins(
-        &mut self,
-        addr_str: String,
-        amt: u64,
-        lock_coins: bool,
-        witness_only: bool,
-    ) -> Result<(Transaction, LockId), Box<dyn Error>> {
-        let utxo_list = self.get_utxo_list();
+/// limits on the payments signed by `send_coins` and `sign_prepared_tx`,
+/// a safety net around a hot wallet
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct SpendingPolicy {
+    /// satoshi paid within the last 24 hours, whether the transactions are published or not
+    pub daily_limit: Option<u64>,
+    /// satoshi paid by a single transaction
+    pub max_per_tx: Option<u64>,
+    /// the only destinations allowed, any unless it is empty
+    pub whitelist: Vec<String>,
+    pub blacklist: Vec<String>,
+    /// payments above it wait for `approve_spend` instead of being signed
+    pub approval_threshold: Option<u64>,
+}
 
-        let mut total = 0;
-        let mut subset = Vec::new();
-        for utxo in utxo_list {
-            if self.locked_coins.is_locked(&utxo.out_point) {
-                continue;
-            }
+/// a payment of `send_coins` above the approval threshold, its coins are selected
+/// and signed once it is approved
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PendingSpend {
+    pub id: u64,
+    pub dest_addr: String,
+    pub amt: u64,
+    pub input_filter: InputFilter,
+    pub change_addr_type: Option<AccountAddressType>,
+    /// unix time the payment is rejected at unless it is approved before
+    pub expires_at: u64,
+    /// the privacy mode asked for by the call, see `WalletConfig`
+    #[serde(default)]
+    pub privacy: Option<bool>,
+    /// the change tolerance asked for by the call, see `WalletConfig`
+    #[serde(default)]
+    pub change_tolerance: Option<u64>,
+    /// the fee rate asked for by the call, see `WalletConfig`
+    #[serde(default)]
+    pub fee_rate: Option<u64>,
+}
 
-            if witness_only {
-                if utxo.addr_type != AccountAddressType::P2WKH {
-                    continue;
-                }
-            }
+/// a payment built without signing it, it waits for an offline signer as a PSBT
+/// and its coins are locked until it is finalized or cancelled
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UnsignedTx {
+    pub id: u64,
+    pub tx: Transaction,
+    pub dest_addr: String,
+    pub amt: u64,
+    pub fee: u64,
+}
 
-            total += utxo.value;
-            subset.push(utxo.out_point);
+/// a taproot output found paying to the silent payment address,
+/// like an imported coin it isn't part of the balance until swept into the wallet
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SilentPaymentUtxo {
+    pub value: u64,
+    pub out_point: OutPoint,
+    pub script_pubkey: Script,
+    /// added to the spend key it gives the key of the output
+    pub tweak: [u8; 32],
+}
 
-            if total >= amt + 10000 {
-                break;
-            }
-        }
+/// an output paying to a timelock script of the wallet, it isn't part of the balance,
+/// but coin selection takes it once the lock expired
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TimelockedUtxo {
+    pub value: u64,
+    pub out_point: OutPoint,
+    pub script_pubkey: Script,
+    /// index of the key in the timelock account
+    pub key_index: u32,
+    pub timelock: Timelock,
+    /// height of the block the output is in, `None` while it is unconfirmed
+    pub height: Option<usize>,
+}
 
-        let tx = self.make_tx(subset.clone(), addr_str, amt)?;
-        if lock_coins {
-            let lock_group = LockGroup(subset);
-            self.locked_coins
-                .lock_group(self.next_lock_id.clone(), lock_group.clone());
+impl TimelockedUtxo {
+    /// height of the first block which may include a spend, see `Timelock::mature_height`
+    pub fn mature_height(&self) -> Option<usize> {
+        self.timelock.mature_height(self.height)
+    }
 
-            self.db
-                .write()
-                .unwrap()
-                .put_lock_group(&self.next_lock_id, &lock_group);
+    /// a spend is valid in the block following `tip_height`
+    pub fn is_mature(&self, tip_height: usize) -> bool {
+        self.mature_height().map_or(false, |height| tip_height + 1 >= height)
+    }
+}
 
-            let rez = self.next_lock_id.clone();
-            self.next_lock_id.incr();
-            return Ok((tx, rez));
-        };
+/// a deposit to a vault address, spent by `unvault` once it is mature
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct VaultUtxo {
+    pub value: u64,
+    pub out_point: OutPoint,
+    pub script_pubkey: Script,
+    /// index of the hot key in the vault account
+    pub key_index: u32,
+    pub vault: Vault,
+    /// height of the block the output is in, `None` while it is unconfirmed
+    pub height: Option<usize>,
+}
 
-        Ok((tx, LockId::new()))
+impl VaultUtxo {
+    /// height of the first block which may include the unvault, see `Vault::mature_height`
+    pub fn mature_height(&self) -> Option<usize> {
+        self.vault.mature_height(self.height)
     }
 
-    // TODO(evg): add version, lock_time param?
-    fn make_tx(
-        &mut self,
-        ops: Vec<OutPoint>,
-        addr_str: String,
-        amt: u64,
-    ) -> Result<Transaction, Box<dyn Error>> {
-        let addr: Address = Address::from_str(&addr_str).unwrap();
+    /// an unvault is valid in the block following `tip_height`
+    pub fn is_mature(&self, tip_height: usize) -> bool {
+        self.mature_height().map_or(false, |height| tip_height + 1 >= height)
+    }
+}
 
-        let mut tx = Transaction {
-            version: 0,
-            lock_time: 0,
-            input: Vec::new(),
-            output: Vec::new(),
-        };
+/// a spending policy registered by `register_policy`,
+/// its addresses are those of the indexes below `next_index`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ScriptAccount {
+    pub name: String,
+    /// the policy as `Policy` displays it
+    pub policy: String,
+    pub next_index: u32,
+}
 
-        let mut total = 0;
-        for op in &ops {
-            let utxo = self.op_to_utxo.get(op).unwrap();
-            total += utxo.value;
+/// an output paying to an address of a script account, spent by `spend_script_coins` only
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ScriptUtxo {
+    pub value: u64,
+    pub out_point: OutPoint,
+    pub script_pubkey: Script,
+    pub account: String,
+    /// index of the address in the account
+    pub index: u32,
+    /// height of the block the output is in, `None` while it is unconfirmed
+    pub height: Option<usize>,
+}
+
+/// what `spend_script_coins` made, the PSBT carries the signatures of the wallet,
+/// the transaction is there if they satisfy every input
+#[derive(Clone, Debug)]
+pub struct ScriptSpend {
+    pub psbt: PartiallySignedTransaction,
+    pub tx: Option<Transaction>,
+}
+
+/// a coin of the wallet spent by a transaction of the history,
+/// it is archived rather than forgotten
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SpentUtxo {
+    pub utxo: Utxo,
+    pub spent_by: Sha256dHash,
+    /// height of the block the spending transaction is in, `None` while it is unconfirmed
+    pub spent_height: Option<usize>,
+}
+
+/// an unsigned transaction with everything a confirmation screen shows,
+/// kept by the wallet until it is signed or the wallet is restarted
+#[derive(Clone, Debug)]
+pub struct PreparedTx {
+    pub tx: Transaction,
+    pub inputs: Vec<Utxo>,
+    pub dest_addr: String,
+    /// empty if the change would be dust and is left to the fee
+    pub change_addr: String,
+    pub change: u64,
+    pub fee: u64,
+    /// estimated virtual size of the signed transaction
+    pub vsize: usize,
+}
+
+/// a channel funding transaction handed to a Lightning node, its coins stay locked
+/// until it is released or published
+#[derive(Clone, Debug)]
+pub struct ChannelFunding {
+    /// unsigned, spending segwit coins only, so the txid is final before signing
+    pub psbt: PartiallySignedTransaction,
+    /// index of the P2WSH output paying to the channel
+    pub output_index: u32,
+    pub fee: u64,
+}
+
+/// a payment made by `send_coins`, a transaction spending the change
+/// can be made right away without parsing this one
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SendResult {
+    pub tx: Transaction,
+    /// the selected coins stay locked by it if `send_coins` was asked to lock them,
+    /// it is `LockId::new()` otherwise
+    pub lock_id: LockId,
+    pub fee: u64,
+    /// `None` if the payment has no change output
+    pub change_outpoint: Option<OutPoint>,
+    pub selected_inputs: Vec<OutPoint>,
+}
+
+impl SendResult {
+    pub fn txid(&self) -> Sha256dHash {
+        self.tx.txid()
+    }
+}
+
+/// seconds a payment made under an idempotency key is remembered
+pub const IDEMPOTENCY_TTL: u64 = 24 * 60 * 60;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum SendOutcome {
+    Sent(SendResult),
+    /// the payment waits for approval under this id
+    ApprovalRequired(u64),
+}
+
+impl SendOutcome {
+    /// the payment signed, `None` if it waits for approval
+    pub fn sent(self) -> Option<SendResult> {
+        match self {
+            SendOutcome::Sent(sent) => Some(sent),
+            SendOutcome::ApprovalRequired(_) => None,
+        }
+    }
+}
+
+/// a payment made under an idempotency key of the client, a retry of the call
+/// with the key gets its outcome back instead of paying a second time
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IdempotentSend {
+    pub key: String,
+    /// unix time of the payment
+    pub created_at: u64,
+    pub dest_addr: String,
+    pub amt: u64,
+    pub outcome: SendOutcome,
+}
+
+impl IdempotentSend {
+    pub fn new(key: String, dest_addr: String, amt: u64, outcome: SendOutcome) -> Self {
+        IdempotentSend {
+            key,
+            created_at: now(),
+            dest_addr,
+            amt,
+            outcome,
+        }
+    }
+
+    fn is_expired(&self) -> bool {
+        now() >= self.created_at + IDEMPOTENCY_TTL
+    }
+}
+
+/// the master key and the accounts of a wallet
+struct KeyMaterial {
+    /// `None` in a watch-only wallet, its transactions are signed offline, and in a locked one
+    master_key: Option<ExtendedPrivKey>,
+    p2pkh_account: Account,
+    p2shwh_account: Account,
+    p2wkh_account: Account,
+}
+
+impl KeyMaterial {
+    fn account(&self, address_type: AccountAddressType) -> &Account {
+        match address_type {
+            AccountAddressType::P2PKH => &self.p2pkh_account,
+            AccountAddressType::P2SHWH => &self.p2shwh_account,
+            AccountAddressType::P2WKH => &self.p2wkh_account,
+        }
+    }
+
+    fn account_mut(&mut self, address_type: AccountAddressType) -> &mut Account {
+        match address_type {
+            AccountAddressType::P2PKH => &mut self.p2pkh_account,
+            AccountAddressType::P2SHWH => &mut self.p2shwh_account,
+            AccountAddressType::P2WKH => &mut self.p2wkh_account,
+        }
+    }
+
+    fn accounts(&self) -> [&Account; 3] {
+        [&self.p2pkh_account, &self.p2shwh_account, &self.p2wkh_account]
+    }
+
+    fn accounts_mut(&mut self) -> [&mut Account; 3] {
+        [&mut self.p2pkh_account, &mut self.p2shwh_account, &mut self.p2wkh_account]
+    }
+}
+
+/// the coins of a wallet, the ones it spent and the ones it only watches
+struct CoinSet {
+    op_to_utxo: HashMap<OutPoint, Utxo>,
+    spent_utxos: HashMap<OutPoint, SpentUtxo>,
+    /// coins excluded from coin selection until they are unfrozen
+    frozen_coins: HashSet<OutPoint>,
+    watched_utxos: HashMap<OutPoint, WatchedUtxo>,
+    timelocked_utxos: HashMap<OutPoint, TimelockedUtxo>,
+}
+
+impl CoinSet {
+    // nSequence of an input spending `op`: the relative lock of a timelocked coin, or,
+    // for the coins without a lock, signaling that the transaction may be replaced
+    fn input_sequence(&self, op: &OutPoint) -> u32 {
+        match self.timelocked_utxos.get(op) {
+            Some(utxo) => utxo.timelock.sequence(),
+            None => RBF_SEQUENCE,
+        }
+    }
+
+    // bytes of the script sig and of the witness of the signed input spending `op`,
+    // a timelocked coin or one of the accounts
+    fn signed_input_size(&self, op: &OutPoint) -> (usize, usize) {
+        match self.timelocked_utxos.get(op) {
+            Some(utxo) => (0, utxo.timelock.witness_size()),
+            None => InputType::from(&self.op_to_utxo[op].addr_type).signed_size(),
+        }
+    }
+}
+
+/// the transactions of a wallet
+struct History {
+    tx_history: HashMap<Sha256dHash, TxRecord>,
+    // outputs spent by transactions of the history which are not conflicted
+    spent_by: HashMap<OutPoint, Sha256dHash>,
+}
+
+/// the keys, the coins and the history of a wallet, each behind a lock of its own.
+/// A clone reads them while the wallet is in use, e.g. processing the blocks of a sync,
+/// it stays the one of the wallet when it reloads, see `WalletLibraryInterface::state`.
+/// Between two locks a read may see a transaction in the coins before the history
+#[derive(Clone)]
+pub struct WalletState {
+    keys: Arc<RwLock<KeyMaterial>>,
+    coins: Arc<RwLock<CoinSet>>,
+    history: Arc<RwLock<History>>,
+}
+
+impl WalletState {
+    fn new(keys: KeyMaterial, coins: CoinSet, history: History) -> Self {
+        WalletState {
+            keys: Arc::new(RwLock::new(keys)),
+            coins: Arc::new(RwLock::new(coins)),
+            history: Arc::new(RwLock::new(history)),
+        }
+    }
+
+    // takes the state of `other`, the clones of `self` see it
+    fn replace(&self, other: WalletState) {
+        mem::swap(&mut *self.keys.write().unwrap(), &mut *other.keys.write().unwrap());
+        mem::swap(&mut *self.coins.write().unwrap(), &mut *other.coins.write().unwrap());
+        mem::swap(&mut *self.history.write().unwrap(), &mut *other.history.write().unwrap());
+    }
+
+    pub fn get_utxo_list(&self) -> Vec<Utxo> {
+        let keys = self.keys();
+        let mut joined = Vec::new();
+        for account in &keys.accounts() {
+            joined.extend(account.get_utxo_list().values().cloned());
+        }
+        joined
+    }
+
+    pub fn wallet_balance(&self) -> u64 {
+        self.get_utxo_list().iter().map(|utxo| utxo.value).sum()
+    }
+
+    pub fn get_frozen_list(&self) -> Vec<OutPoint> {
+        self.coins().frozen_coins.iter().cloned().collect()
+    }
+
+    pub fn get_watched_utxo_list(&self) -> Vec<WatchedUtxo> {
+        self.coins().watched_utxos.values().cloned().collect()
+    }
+
+    pub fn watched_balance(&self) -> u64 {
+        self.coins().watched_utxos.values().map(|utxo| utxo.value).sum()
+    }
+
+    pub fn get_timelocked_utxo_list(&self) -> Vec<TimelockedUtxo> {
+        self.coins().timelocked_utxos.values().cloned().collect()
+    }
+
+    pub fn get_tx_history(&self) -> Vec<TxRecord> {
+        self.history().tx_history.values().cloned().collect()
+    }
+
+    pub fn knows_tx(&self, txid: &Sha256dHash) -> bool {
+        self.history().tx_history.contains_key(txid)
+    }
+
+    pub fn get_tx(&self, txid: &Sha256dHash) -> Option<TxRecord> {
+        self.history().tx_history.get(txid).cloned()
+    }
+
+    fn keys(&self) -> RwLockReadGuard<KeyMaterial> {
+        self.keys.read().unwrap()
+    }
+
+    fn keys_mut(&self) -> RwLockWriteGuard<KeyMaterial> {
+        self.keys.write().unwrap()
+    }
+
+    fn coins(&self) -> RwLockReadGuard<CoinSet> {
+        self.coins.read().unwrap()
+    }
+
+    fn coins_mut(&self) -> RwLockWriteGuard<CoinSet> {
+        self.coins.write().unwrap()
+    }
+
+    fn history(&self) -> RwLockReadGuard<History> {
+        self.history.read().unwrap()
+    }
+
+    fn history_mut(&self) -> RwLockWriteGuard<History> {
+        self.history.write().unwrap()
+    }
+}
+
+/// an account of a wallet, the other accounts and the master key stay locked as long as
+/// it is held, see `WalletLibraryInterface::get_account_mut`
+pub struct AccountGuard<'a> {
+    keys: RwLockWriteGuard<'a, KeyMaterial>,
+    address_type: AccountAddressType,
+}
+
+impl<'a> Deref for AccountGuard<'a> {
+    type Target = Account;
+
+    fn deref(&self) -> &Account {
+        self.keys.account(self.address_type.clone())
+    }
+}
+
+impl<'a> DerefMut for AccountGuard<'a> {
+    fn deref_mut(&mut self) -> &mut Account {
+        self.keys.account_mut(self.address_type.clone())
+    }
+}
+
+pub struct WalletLibrary {
+    // the master key, the accounts, the coins and the history
+    state: WalletState,
+    locked: bool,
+    // unix time the keys of an unlocked wallet stop being used at
+    relock_at: Option<u64>,
+    master_fingerprint: Fingerprint,
+    network: Network,
+    /// coin type of the accounts at default paths
+    derivation_mode: DerivationMode,
+    change_addr_type: AccountAddressType,
+
+    last_seen_block_height: usize,
+    prune_spent_after: Option<usize>,
+    spend_unconfirmed_change: bool,
+    spend_uneconomical: bool,
+    privacy: bool,
+    avoid_reuse: bool,
+    change_tolerance: Option<u64>,
+    fee_rate: u64,
+    next_lock_id: LockId,
+    locked_coins: LockGroupMap,
+    // published transactions waiting for a block, they are rebroadcast on every sync
+    unconfirmed_txs: HashMap<Sha256dHash, Transaction>,
+    events: EventSink,
+    next_prepared_id: u64,
+    prepared_txs: HashMap<u64, PreparedTx>,
+    channel_fundings: HashMap<LockId, ChannelFunding>,
+    pending_rotation: Option<(PendingRotation, ExtendedPrivKey)>,
+    imported_keys: HashMap<Script, (PublicKey, AccountAddressType)>,
+    imported_utxos: HashMap<OutPoint, ImportedUtxo>,
+    // scripts which don't belong to the wallet, tracked by their labels
+    watched_scripts: HashMap<Script, String>,
+    /// BIP47 payment code of the seed, m/47'/coin'/0'
+    payment_code: PaymentCode,
+    // outputs paying to it may carry the payment code of a sender
+    notification_script: Script,
+    /// notified payment codes, with the index of the next address paying to them
+    sent_payment_codes: HashMap<String, u32>,
+    /// payment codes which notified the wallet, with the number of their addresses watched
+    received_payment_codes: HashMap<String, u32>,
+    // scripts of the watched addresses by their payment code and index,
+    // the keys of the addresses are imported
+    payment_code_scripts: HashMap<Script, (String, u32)>,
+    // BIP352 keys, m/352'/coin'/0'/1'/0 and m/352'/coin'/0'/0'/0, the scan key is used
+    // for every transaction, the spend key is derived when the outputs are swept
+    silent_payment_scan_sk: PrivateKey,
+    silent_payment_spend_pk: PublicKey,
+    silent_payment_utxos: HashMap<OutPoint, SilentPaymentUtxo>,
+    // whether transactions are scanned for silent payments, see `WalletConfig`
+    silent_payments: bool,
+    // outputs spent by the taproot inputs of the transactions processed next, see
+    // `set_spent_outputs`
+    spent_outputs: HashMap<OutPoint, TxOut>,
+    // P2WSH scripts of the timelocked outputs, with the index of their key and their lock
+    timelock_scripts: HashMap<Script, (u32, Timelock)>,
+    vault: Option<Vault>,
+    // P2WSH scripts of the vault addresses, with the index of their hot key and their vault
+    vault_scripts: HashMap<Script, (u32, Vault)>,
+    vault_utxos: HashMap<OutPoint, VaultUtxo>,
+    // the unvaults signed by the wallet, a deposit spent by another transaction is an alert
+    unvault_txids: HashSet<Sha256dHash>,
+    script_accounts: HashMap<String, ScriptAccount>,
+    // P2WSH scripts of the addresses of the script accounts, by account and index
+    script_account_scripts: HashMap<Script, (String, u32)>,
+    script_utxos: HashMap<OutPoint, ScriptUtxo>,
+    spending_policy: SpendingPolicy,
+    // unix time and amount of the payments counted against the daily limit
+    policy_spends: Vec<(u64, u64)>,
+    // payments signed but not published yet by txid, they count once they are published
+    unpublished_spends: HashMap<Sha256dHash, u64>,
+    pending_spends: HashMap<u64, PendingSpend>,
+    unsigned_txs: HashMap<u64, UnsignedTx>,
+    idempotent_sends: HashMap<String, IdempotentSend>,
+    // what the scripts of the accounts received and sent, by the transactions of the history
+    address_stats: HashMap<Script, AddressStats>,
+    address_labels: HashMap<Script, String>,
+    // locks of the coins of the unsigned transactions
+    unsigned_locks: HashMap<u64, LockId>,
+    // recorded with the operations until it is set again
+    audit_caller: String,
+    // the entry appended last, the next one is chained to it
+    last_audit_entry: Option<AuditEntry>,
+    db: Arc<RwLock<DB>>,
+}
+
+impl WalletLibraryInterface for WalletLibrary {
+    fn new_address(&mut self, address_type: AccountAddressType) -> Result<String, Box<dyn Error>> {
+        let params = format!("addr_type={:?}", address_type);
+        let addr = self.get_account_mut(address_type).new_address()?;
+        self.audit("new_address", format!("{} address={}", params, addr), None);
+        Ok(addr)
+    }
+
+    fn new_change_address(
+        &mut self,
+        address_type: AccountAddressType,
+    ) -> Result<String, Box<dyn Error>> {
+        let params = format!("addr_type={:?}", address_type);
+        let addr = self.get_account_mut(address_type).new_change_address()?;
+        self.audit("new_change_address", format!("{} address={}", params, addr), None);
+        Ok(addr)
+    }
+
+    fn get_unused_address(
+        &mut self,
+        address_type: AccountAddressType,
+    ) -> Result<String, Box<dyn Error>> {
+        self.get_account_mut(address_type)
+            .unused_address()
+            .map_err(Into::into)
+    }
+
+    fn get_address_list(&self) -> Vec<AddressInfo> {
+        let mut address_list: Vec<AddressInfo> = self
+            .state
+            .keys()
+            .accounts()
+            .iter()
+            .flat_map(|account| account.address_list())
+            .collect();
+        if !self.address_labels.is_empty() {
+            for info in &mut address_list {
+                let script = address::script_pubkey(&info.address, self.network).unwrap();
+                info.label = self.address_labels.get(&script).cloned();
+            }
+        }
+        address_list
+    }
+
+    fn set_address_label(&mut self, addr: &str, label: &str) -> Result<(), Box<dyn Error>> {
+        let script = address::script_pubkey(addr, self.network)?;
+        if !self.is_ours(&script) {
+            return Err(From::from(format!("{} is not an address of the wallet", addr)));
+        }
+        if label.is_empty() {
+            if self.address_labels.remove(&script).is_some() {
+                self.db.write().unwrap().delete_address_label(&script);
+            }
+        } else {
+            self.db.write().unwrap().put_address_label(&script, label);
+            self.address_labels.insert(script, label.to_owned());
+        }
+        Ok(())
+    }
+
+    fn get_utxo_list(&self) -> Vec<Utxo> {
+        self.state.get_utxo_list()
+    }
+
+    fn wallet_balance(&self) -> u64 {
+        self.state.wallet_balance()
+    }
+
+    fn state(&self) -> WalletState {
+        self.state.clone()
+    }
+
+    fn unlock_coins(&mut self, lock_id: LockId) {
+        self.locked_coins.unlock_group(lock_id);
+    }
+
+    fn freeze_utxo(&mut self, op: OutPoint) -> Result<(), Box<dyn Error>> {
+        if !self.state.coins().op_to_utxo.contains_key(&op) {
+            return Err(From::from(format!(
+                "{}:{} is not an unspent output of the wallet",
+                op.txid, op.vout
+            )));
+        }
+        self.db.write().unwrap().put_frozen_utxo(&op);
+        self.state.coins_mut().frozen_coins.insert(op);
+        Ok(())
+    }
+
+    fn unfreeze_utxo(&mut self, op: OutPoint) -> Result<(), Box<dyn Error>> {
+        if !self.state.coins_mut().frozen_coins.remove(&op) {
+            return Err(From::from(format!("{}:{} is not frozen", op.txid, op.vout)));
+        }
+        self.db.write().unwrap().delete_frozen_utxo(&op);
+        Ok(())
+    }
+
+    fn get_frozen_list(&self) -> Vec<OutPoint> {
+        self.state.get_frozen_list()
+    }
+
+    fn send_coins(
+        &mut self,
+        addr_str: String,
+        amt: u64,
+        options: SendOptions,
+    ) -> Result<SendOutcome, Box<dyn Error>> {
+        let SendOptions {
+            lock_coins,
+            input_filter,
+            change_addr_type,
+            privacy,
+            change_tolerance,
+            fee_rate,
+        } = options;
+        self.check_policy(&addr_str, amt)?;
+        if self.spending_policy.approval_threshold.map_or(false, |threshold| amt > threshold) {
+            let id = self.db.read().unwrap().get_next_spend_id();
+            let spend = PendingSpend {
+                id,
+                dest_addr: addr_str,
+                amt,
+                input_filter,
+                change_addr_type,
+                expires_at: now() + APPROVAL_TIMEOUT,
+                privacy,
+                change_tolerance,
+                fee_rate,
+            };
+            let mut db = self.db.write().unwrap();
+            db.put_next_spend_id(id + 1);
+            db.put_pending_spend(&spend);
+            self.pending_spends.insert(id, spend);
+            return Ok(SendOutcome::ApprovalRequired(id));
+        }
+
+        let private = privacy.unwrap_or(self.privacy);
+        let tolerance = change_tolerance.or(self.change_tolerance);
+        let fee_rate = fee_rate.unwrap_or(self.fee_rate);
+        let script_pubkey = address::script_pubkey(&addr_str, self.network)?;
+        let subset =
+            self.select_coins(amt, &script_pubkey, &input_filter, private, tolerance, fee_rate);
+        let (mut tx, _) =
+            self.build_tx(subset.clone(), addr_str, amt, change_addr_type, tolerance, fee_rate)?;
+        self.sign_tx(&mut tx)?;
+        self.count_when_published(&tx, amt);
+
+        let total: u64 = self.input_values(&tx).iter().sum();
+        let output_total: u64 = tx.output.iter().map(|output| output.value).sum();
+        // `build_tx` puts the change after the payment
+        let change_outpoint = if tx.output.len() > 1 {
+            Some(OutPoint {
+                txid: tx.txid(),
+                vout: 1,
+            })
+        } else {
+            None
+        };
+        let mut sent = SendResult {
+            fee: total - output_total,
+            change_outpoint,
+            selected_inputs: subset.clone(),
+            lock_id: LockId::new(),
+            tx,
+        };
+        if lock_coins {
+            let lock_group = LockGroup(subset);
+            self.locked_coins
+                .lock_group(self.next_lock_id.clone(), lock_group.clone());
+
+            self.db
+                .write()
+                .unwrap()
+                .put_lock_group(&self.next_lock_id, &lock_group);
+
+            sent.lock_id = self.next_lock_id.clone();
+            self.next_lock_id.incr();
+        };
+
+        Ok(SendOutcome::Sent(sent))
+    }
+
+    // TODO(evg): add version, lock_time param?
+    fn make_tx(
+        &mut self,
+        ops: Vec<OutPoint>,
+        addr_str: String,
+        amt: u64,
+        change_addr_type: Option<AccountAddressType>,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let tolerance = self.change_tolerance;
+        let (mut tx, _) =
+            self.build_tx(ops, addr_str, amt, change_addr_type, tolerance, self.fee_rate)?;
+        self.sign_tx(&mut tx)?;
+        Ok(tx)
+    }
+
+    fn make_tx_advanced(
+        &mut self,
+        inputs: Vec<InputSpec>,
+        addr_str: String,
+        amt: u64,
+        change_addr_type: Option<AccountAddressType>,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        self.master_key()?;
+        for input in &inputs {
+            if !self.state.coins().op_to_utxo.contains_key(&input.out_point) {
+                let op = input.out_point;
+                let err = format!("{}:{} is not a coin of the wallet", op.txid, op.vout);
+                return Err(From::from(err));
+            }
+        }
+        let ops = inputs.iter().map(|input| input.out_point).collect();
+        let tolerance = self.change_tolerance;
+        let (mut tx, _) =
+            self.build_tx(ops, addr_str, amt, change_addr_type, tolerance, self.fee_rate)?;
+        for (input, spec) in tx.input.iter_mut().zip(&inputs) {
+            input.sequence = spec.sequence;
+        }
+
+        // the sequences are final before any input is signed, they are committed to
+        let coins = self.state.coins();
+        let keys = self.state.keys();
+        for (i, spec) in inputs.iter().enumerate() {
+            let utxo = &coins.op_to_utxo[&spec.out_point];
+            let account = keys.account((utxo.account_index as usize).into());
+            let mut sk = account.get_sk(&utxo.key_path);
+            let signed =
+                sign_input_with(&mut tx, i, &sk, &utxo.addr_type, utxo.value, spec.sighash_type);
+            wipe_private_key(&mut sk);
+            signed?;
+        }
+        for (i, spec) in inputs.iter().enumerate() {
+            let utxo = &coins.op_to_utxo[&spec.out_point];
+            let spent = TxOut {
+                value: utxo.value,
+                script_pubkey: utxo.pk_script.clone(),
+            };
+            sighash::verify_input(&tx, i, &spent)?;
+        }
+        Ok(tx)
+    }
+
+    fn prepare_tx(
+        &mut self,
+        addr_str: String,
+        amt: u64,
+        input_filter: InputFilter,
+        change_addr_type: Option<AccountAddressType>,
+        privacy: Option<bool>,
+    ) -> Result<(u64, PreparedTx), Box<dyn Error>> {
+        let private = privacy.unwrap_or(self.privacy);
+        let tolerance = self.change_tolerance;
+        let fee_rate = self.fee_rate;
+        let script_pubkey = address::script_pubkey(&addr_str, self.network)?;
+        let mut ops =
+            self.select_coins(amt, &script_pubkey, &input_filter, private, tolerance, fee_rate);
+        // the preview lists its inputs as coins of the accounts, timelocked ones are left out
+        ops.retain(|op| self.state.coins().op_to_utxo.contains_key(op));
+        let (tx, change_addr) =
+            self.build_tx(ops, addr_str.clone(), amt, change_addr_type, tolerance, fee_rate)?;
+
+        let inputs: Vec<Utxo> = tx
+            .input
+            .iter()
+            .map(|input| self.state.coins().op_to_utxo[&input.previous_output].clone())
+            .collect();
+        let input_types: Vec<AccountAddressType> =
+            inputs.iter().map(|utxo| utxo.addr_type.clone()).collect();
+        let total: u64 = inputs.iter().map(|utxo| utxo.value).sum();
+        let change = tx.output.get(1).map_or(0, |output| output.value);
+
+        let prepared = PreparedTx {
+            vsize: estimate_vsize(&tx, &input_types),
+            fee: total - amt - change,
+            change,
+            dest_addr: addr_str,
+            change_addr,
+            inputs,
+            tx,
+        };
+        let prepared_id = self.next_prepared_id;
+        self.next_prepared_id += 1;
+        self.prepared_txs.insert(prepared_id, prepared.clone());
+        Ok((prepared_id, prepared))
+    }
+
+    fn sign_prepared_tx(&mut self, prepared_id: u64) -> Result<Transaction, Box<dyn Error>> {
+        let prepared = self
+            .prepared_txs
+            .remove(&prepared_id)
+            .ok_or("unknown prepared transaction")?;
+
+        // the coins might have been spent, locked or frozen since the preview
+        for input in &prepared.tx.input {
+            let op = &input.previous_output;
+            if !self.state.coins().op_to_utxo.contains_key(op)
+                || self.locked_coins.is_locked(op)
+                || self.is_frozen(op)
+            {
+                return Err(From::from(
+                    "inputs of the prepared transaction are no longer available",
+                ));
+            }
+        }
+        // the policy may have changed since the preview
+        let amt = prepared.tx.output[0].value;
+        self.check_policy(&prepared.dest_addr, amt)?;
+
+        let mut tx = prepared.tx;
+        self.sign_tx(&mut tx)?;
+        self.count_when_published(&tx, amt);
+        Ok(tx)
+    }
+
+    fn fund_psbt_for_channel(
+        &mut self,
+        witness_script: &Script,
+        amt: u64,
+        fee_rate: u64,
+    ) -> Result<(LockId, ChannelFunding), Box<dyn Error>> {
+        let script_pubkey = Address::p2wsh(witness_script, self.network).script_pubkey();
+        let (tx, fee) = self.build_funding_tx(script_pubkey, amt, fee_rate)?;
+
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx)?;
+        {
+            let coins = self.state.coins();
+            let keys = self.state.keys();
+            for (i, input) in psbt.global.unsigned_tx.input.iter().enumerate() {
+                let utxo = &coins.op_to_utxo[&input.previous_output];
+                psbt.inputs[i].witness_utxo = Some(TxOut {
+                    value: utxo.value,
+                    script_pubkey: utxo.pk_script.clone(),
+                });
+                let account = keys.account(utxo.addr_type.clone());
+                let pk = account.get_pk(&utxo.key_path);
+                psbt.inputs[i].hd_keypaths.insert(pk, account.key_origin(&utxo.key_path));
+            }
+        }
+        self.add_output_origins(&mut psbt);
+
+        let ops = psbt
+            .global
+            .unsigned_tx
+            .input
+            .iter()
+            .map(|input| input.previous_output)
+            .collect();
+        let lock_id = self.next_lock_id.clone();
+        let lock_group = LockGroup(ops);
+        self.locked_coins.lock_group(lock_id.clone(), lock_group.clone());
+        self.db.write().unwrap().put_lock_group(&lock_id, &lock_group);
+        self.next_lock_id.incr();
+
+        let funding = ChannelFunding {
+            psbt,
+            output_index: 0,
+            fee,
+        };
+        self.channel_fundings.insert(lock_id.clone(), funding.clone());
+        Ok((lock_id, funding))
+    }
+
+    fn release_funding(&mut self, funding_id: LockId) -> Result<(), Box<dyn Error>> {
+        self.channel_fundings
+            .remove(&funding_id)
+            .ok_or("unknown channel funding")?;
+        self.locked_coins.unlock_group(funding_id);
+        Ok(())
+    }
+
+    fn sign_funding(&mut self, funding_id: LockId) -> Result<Transaction, Box<dyn Error>> {
+        let funding = self
+            .channel_fundings
+            .remove(&funding_id)
+            .ok_or("unknown channel funding")?;
+        // the coins stay locked, they are gone once the transaction is processed
+        let mut tx = funding.psbt.global.unsigned_tx;
+        self.sign_tx(&mut tx)?;
+        Ok(tx)
+    }
+
+    fn accelerate(
+        &mut self,
+        txid: &Sha256dHash,
+        fee_rate: u64,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let record = self.state.get_tx(txid).ok_or("unknown transaction")?;
+        if record.status != TxStatus::Unconfirmed {
+            return Err(From::from("the transaction is not waiting for a block"));
+        }
+        let parent = record.tx.clone();
+
+        // the fee of the parent is known only if it spends coins of the wallet alone,
+        // otherwise the child pays for the whole package
+        let parent_fee = record.fee.unwrap_or(0);
+        let parent_vsize = (parent.get_weight() as u64 + 3) / 4;
+
+        let coins: Vec<Utxo> = (0..parent.output.len())
+            .map(|vout| OutPoint {
+                txid: *txid,
+                vout: vout as u32,
+            })
+            .filter(|op| !self.locked_coins.is_locked(op) && !self.is_frozen(op))
+            .filter_map(|op| self.state.coins().op_to_utxo.get(&op).cloned())
+            .collect();
+        if coins.is_empty() {
+            return Err(From::from("the transaction has no spendable outputs of the wallet"));
+        }
+        // the child needs one of them to depend on the parent, the others are spent
+        // if they pay for their inputs
+        let coins: Vec<Utxo> =
+            coins.into_iter().filter(|utxo| self.is_economical(utxo, fee_rate)).collect();
+        if coins.is_empty() {
+            return Err(From::from("the outputs of the transaction can't pay for the child"));
+        }
+
+        let change_addr_type = self.change_addr_type.clone();
+        let change_addr = self.get_account_mut(change_addr_type).new_change_address()?;
+        let mut tx = Transaction {
+            version: TX_VERSION,
+            lock_time: 0,
+            input: coins
+                .iter()
+                .map(|utxo| TxIn {
+                    previous_output: utxo.out_point,
+                    script_sig: Script::new(),
+                    sequence: self.input_sequence(&utxo.out_point),
+                    witness: Vec::new(),
+                })
+                .collect(),
+            output: vec![TxOut {
+                value: 0,
+                script_pubkey: Address::from_str(&change_addr)?.script_pubkey(),
+            }],
+        };
+        let input_types: Vec<AccountAddressType> =
+            coins.iter().map(|utxo| utxo.addr_type.clone()).collect();
+        let child_vsize = estimate_vsize(&tx, &input_types) as u64;
+
+        // miners take the parent along with the child if the package pays `fee_rate`
+        let package_fee = (parent_vsize + child_vsize) * fee_rate;
+        let fee = package_fee.saturating_sub(parent_fee).max(child_vsize * fee_rate);
+        let total: u64 = coins.iter().map(|utxo| utxo.value).sum();
+        if total < fee + DUST_LIMIT {
+            return Err(From::from("the outputs of the transaction can't pay for the child"));
+        }
+        tx.output[0].value = total - fee;
+        self.sign_tx(&mut tx)?;
+        Ok(tx)
+    }
+
+    fn cancel_tx(
+        &mut self,
+        txid: &Sha256dHash,
+        fee_rate: u64,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let record = self.state.get_tx(txid).ok_or("unknown transaction")?;
+        if record.status != TxStatus::Unconfirmed {
+            return Err(From::from("the transaction is not waiting for a block"));
+        }
+        if record.spent.len() != record.tx.input.len() {
+            return Err(From::from("the transaction spends coins of others"));
+        }
+        if !record.tx.input.iter().any(|input| input.sequence < 0xFFFFFFFE) {
+            return Err(From::from("the transaction doesn't signal replaceability"));
+        }
+        // a coin spent by yet another transaction can't be taken back
+        let taken = record.tx.input.iter().any(|input| {
+            self.state
+                .history()
+                .spent_by
+                .get(&input.previous_output)
+                .map_or(false, |spending_txid| spending_txid != txid)
+        });
+        if taken {
+            return Err(From::from("coins of the transaction are spent by another one"));
+        }
+        let original = record;
+
+        let change_addr_type = self.change_addr_type.clone();
+        let change_addr = self.get_account_mut(change_addr_type).new_change_address()?;
+        let mut tx = Transaction {
+            version: TX_VERSION,
+            lock_time: 0,
+            input: original
+                .spent
+                .iter()
+                .map(|utxo| TxIn {
+                    previous_output: utxo.out_point,
+                    script_sig: Script::new(),
+                    sequence: self.input_sequence(&utxo.out_point),
+                    witness: Vec::new(),
+                })
+                .collect(),
+            output: vec![TxOut {
+                value: 0,
+                script_pubkey: Address::from_str(&change_addr)?.script_pubkey(),
+            }],
+        };
+        let input_types: Vec<AccountAddressType> =
+            original.spent.iter().map(|utxo| utxo.addr_type.clone()).collect();
+        let vsize = estimate_vsize(&tx, &input_types) as u64;
+
+        // BIP125: the replacement pays more than the original
+        // and at least the minimal relay fee of 1 satoshi per byte for itself on top
+        let total: u64 = original.spent.iter().map(|utxo| utxo.value).sum();
+        let output_value: u64 = original.tx.output.iter().map(|output| output.value).sum();
+        let original_fee = total - output_value;
+        let fee = (vsize * fee_rate).max(original_fee + vsize);
+        if total < fee + DUST_LIMIT {
+            return Err(From::from("the coins of the transaction can't pay the fee"));
+        }
+        tx.output[0].value = total - fee;
+
+        // gives the coins back, they are signed from `op_to_utxo`
+        self.master_key()?;
+        self.mark_conflicted(txid, TxStatus::Replaced(tx.txid()));
+        self.sign_tx(&mut tx)?;
+        Ok(tx)
+    }
+
+    fn get_account_mut(&mut self, address_type: AccountAddressType) -> AccountGuard {
+        AccountGuard {
+            keys: self.state.keys_mut(),
+            address_type,
+        }
+    }
+
+    fn descriptors(&self) -> Vec<String> {
+        let mut descriptors = Vec::new();
+        for account in &self.state.keys().accounts() {
+            descriptors.push(account.descriptor(AddressChain::External));
+            descriptors.push(account.descriptor(AddressChain::Internal));
+        }
+        descriptors
+    }
+
+    fn import_descriptors(&self) -> serde_json::Value {
+        let birthday = self.db.read().unwrap().get_birthday();
+        let mut requests = Vec::new();
+        for account in &self.state.keys().accounts() {
+            requests.push(account.import_descriptor(AddressChain::External, birthday));
+            requests.push(account.import_descriptor(AddressChain::Internal, birthday));
+        }
+        serde_json::Value::Array(requests)
+    }
+
+    fn add_unconfirmed_tx(&mut self, tx: &Transaction) {
+        if let Some(amt) = self.unpublished_spends.remove(&tx.txid()) {
+            self.record_spend(amt);
+        }
+        let spent = tx
+            .input
+            .iter()
+            .filter_map(|input| self.state.coins().op_to_utxo.get(&input.previous_output).cloned())
+            .collect();
+        let fee = fee_paid(tx, &self.input_values(tx));
+        self.add_to_history(tx, spent, fee);
+
+        self.db.write().unwrap().put_unconfirmed_tx(tx);
+        self.unconfirmed_txs.insert(tx.txid(), tx.clone());
+        // the change is a coin right away rather than on the next sync
+        if self.spend_unconfirmed_change {
+            self.process_tx(tx);
+        }
+    }
+
+    fn get_unconfirmed_txs(&self) -> Vec<Transaction> {
+        self.unconfirmed_txs.values().cloned().collect()
+    }
+
+    fn process_confirmed_tx(&mut self, tx: &Transaction, block_height: usize) {
+        let txid = tx.txid();
+        let mut losers: Vec<Sha256dHash> = tx
+            .input
+            .iter()
+            .filter_map(|input| self.state.history().spent_by.get(&input.previous_output).cloned())
+            .filter(|spending_txid| *spending_txid != txid)
+            .collect();
+        losers.dedup();
+        for loser in losers {
+            self.mark_conflicted(&loser, TxStatus::Conflicted(txid));
+        }
+
+        self.apply_tx(tx);
+        // relative timelocks count the blocks from here
+        for utxo in self.state.coins_mut().timelocked_utxos.values_mut() {
+            if utxo.out_point.txid == txid && utxo.height != Some(block_height) {
+                utxo.height = Some(block_height);
+                self.db.write().unwrap().put_timelocked_utxo(utxo);
+            }
+        }
+        for utxo in self.vault_utxos.values_mut() {
+            if utxo.out_point.txid == txid && utxo.height != Some(block_height) {
+                utxo.height = Some(block_height);
+                self.db.write().unwrap().put_vault_utxo(utxo);
+            }
+        }
+        for utxo in self.script_utxos.values_mut() {
+            if utxo.out_point.txid == txid && utxo.height != Some(block_height) {
+                utxo.height = Some(block_height);
+                self.db.write().unwrap().put_script_utxo(utxo);
+            }
+        }
+        for input in &tx.input {
+            let mut coins = self.state.coins_mut();
+            if let Some(spent_utxo) = coins.spent_utxos.get_mut(&input.previous_output) {
+                if spent_utxo.spent_by == txid {
+                    spent_utxo.spent_height = Some(block_height);
+                    self.db.write().unwrap().put_spent_utxo(spent_utxo);
+                }
+            }
+        }
+        self.prune_spent_utxos(block_height);
+
+        let mut confirmed = None;
+        if let Some(record) = self.state.history_mut().tx_history.get_mut(&txid) {
+            if record.status != TxStatus::Confirmed(block_height) {
+                record.status = TxStatus::Confirmed(block_height);
+                self.db.write().unwrap().put_tx_record(record);
+                confirmed = Some(!record.spent.is_empty());
+            }
+        }
+        if let Some(outgoing) = confirmed {
+            self.events.emit(WalletEvent::Confirmed {
+                txid,
+                block_height,
+                outgoing,
+            });
+        }
+        if self.unconfirmed_txs.remove(&txid).is_some() {
+            self.db.write().unwrap().delete_unconfirmed_tx(&txid);
+        }
+    }
+
+    fn get_tx_history(&self) -> Vec<TxRecord> {
+        self.state.get_tx_history()
+    }
+
+    fn knows_tx(&self, txid: &Sha256dHash) -> bool {
+        self.state.knows_tx(txid)
+    }
+
+    fn backend(&self) -> Option<BackendKind> {
+        self.db.read().unwrap().get_backend()
+    }
+
+    fn record_backend(&mut self, backend: BackendKind) {
+        let mut db = self.db.write().unwrap();
+        if db.get_backend().is_none() {
+            db.put_backend(backend);
+        }
+    }
+
+    fn get_spent_utxos(&self) -> Vec<SpentUtxo> {
+        self.state.coins().spent_utxos.values().cloned().collect()
+    }
+
+    fn fee_stats(&self) -> FeeStats {
+        FeeStats::of(self.state.history().tx_history.values())
+    }
+
+    fn address_stats(&self) -> Vec<(String, AccountAddressType, AddressStats)> {
+        let mut address_stats = Vec::new();
+        for account in &self.state.keys().accounts() {
+            let mut stats: Vec<(String, AccountAddressType, AddressStats)> = account
+                .scripts()
+                .into_iter()
+                .filter_map(|(script, _)| {
+                    let stats = self.address_stats.get(&script)?;
+                    let addr = address::from_script(&script, self.network)?;
+                    Some((addr, account.address_type.clone(), *stats))
+                })
+                .collect();
+            stats.sort_by(|a, b| a.0.cmp(&b.0));
+            address_stats.extend(stats);
+        }
+        address_stats
+    }
+
+    fn account_stats(&self, addr_type: AccountAddressType) -> AddressStats {
+        let mut total = AddressStats::default();
+        for (script, _) in self.state.keys().account(addr_type).scripts() {
+            if let Some(stats) = self.address_stats.get(&script) {
+                total.add(stats);
+            }
+        }
+        total
+    }
+
+    fn is_reused(&self, script: &Script) -> bool {
+        self.address_stats.get(script).map_or(false, |stats| stats.receipts > 1)
+    }
+
+    fn export_history(
+        &self,
+        format: ExportFormat,
+        out: &mut dyn Write,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut records: Vec<TxRecord> = self
+            .state
+            .history()
+            .tx_history
+            .values()
+            .filter(|record| !record.is_conflicted())
+            .cloned()
+            .collect();
+        records.sort_by_key(|record| match record.status {
+            TxStatus::Confirmed(height) => height,
+            _ => usize::max_value(),
+        });
+        let entries: Vec<HistoryEntry> = records
+            .iter()
+            .map(|record| self.history_entry(record))
+            .collect();
+        history::write_history(&entries, format, out)
+    }
+
+    fn tx_value(&self, record: &TxRecord) -> i64 {
+        let keys = self.state.keys();
+        let accounts = keys.accounts();
+        let received: u64 = record
+            .tx
+            .output
+            .iter()
+            .filter(|output| {
+                accounts
+                    .iter()
+                    .any(|account| account.is_mine(&output.script_pubkey))
+            })
+            .map(|output| output.value)
+            .sum();
+        let spent: u64 = record.spent.iter().map(|utxo| utxo.value).sum();
+        received as i64 - spent as i64
+    }
+
+    fn confirmation_price(
+        &mut self,
+        source: &dyn PriceSource,
+        currency: &str,
+        height: usize,
+    ) -> Result<Option<f64>, Box<dyn Error>> {
+        let time = match self.get_block_header(height) {
+            Some(ref header) if header.time != 0 => header.time,
+            _ => return Ok(None),
+        };
+        if let Some(price) = self.db.read().unwrap().get_fiat_price(currency, time) {
+            return Ok(Some(price));
+        }
+        let price = source.historical_price(currency, time as u64)?;
+        self.db.write().unwrap().put_fiat_price(currency, time, price);
+        Ok(Some(price))
+    }
+
+    fn subscribe(&mut self) -> Receiver<WalletEvent> {
+        self.events.subscribe()
+    }
+
+    fn emit(&mut self, event: WalletEvent) {
+        self.events.emit(event);
+    }
+
+    fn abandon_tx(&mut self, txid: &Sha256dHash) -> Result<(), Box<dyn Error>> {
+        if self.unconfirmed_txs.remove(txid).is_none() {
+            return Err(From::from(format!("{} is not an unconfirmed transaction", txid)));
+        }
+        self.db.write().unwrap().delete_unconfirmed_tx(txid);
+        Ok(())
+    }
+
+    fn get_last_seen_block_height_from_memory(&self) -> usize {
+        self.last_seen_block_height
+    }
+
+    fn update_last_seen_block_height_in_memory(&mut self, block_height: usize) {
+        if block_height > self.last_seen_block_height {
+            self.events.emit(WalletEvent::NewTip {
+                height: block_height,
+            });
+        }
+        self.last_seen_block_height = block_height;
+        self.prune_spent_utxos(block_height);
+    }
+
+    fn update_last_seen_block_height_in_db(&mut self, block_height: usize) {
+        self.db
+            .write()
+            .unwrap()
+            .put_last_seen_block_height(block_height as u32);
+    }
+
+    fn add_block_header(&mut self, height: usize, header: &BlockHeader) -> Result<(), WalletError> {
+        let record = HeaderRecord::new(height, header);
+        let mut db = self.db.write().unwrap();
+        let prev = height
+            .checked_sub(1)
+            .and_then(|prev_height| db.get_block_header(prev_height));
+        headers::check(self.network, &record, prev.as_ref())?;
+        db.put_block_header(&record);
+        Ok(())
+    }
+
+    fn get_block_header(&self, height: usize) -> Option<HeaderRecord> {
+        self.db.read().unwrap().get_block_header(height)
+    }
+
+    fn get_header_tip(&self) -> Option<HeaderRecord> {
+        self.db.read().unwrap().get_last_block_header()
+    }
+
+    fn rewind_headers(&mut self, height: usize) {
+        let mut db = self.db.write().unwrap();
+        db.delete_block_headers_above(height);
+        // the histories of the scripts may have lost the transactions of those blocks
+        db.clear_script_statuses();
+    }
+
+    fn script_statuses(&self) -> HashMap<String, String> {
+        self.db.read().unwrap().get_script_statuses()
+    }
+
+    fn put_script_status(&mut self, script_hash: &str, status: &str) {
+        self.db.write().unwrap().put_script_status(script_hash, status);
+    }
+
+    fn script_status_tip(&self) -> Option<(usize, Sha256dHash)> {
+        self.db.read().unwrap().get_script_status_tip()
+    }
+
+    fn put_script_status_tip(&mut self, height: usize, hash: &Sha256dHash) {
+        self.db.write().unwrap().put_script_status_tip(height, hash);
+    }
+
+    fn clear_script_statuses(&mut self) {
+        self.db.write().unwrap().clear_script_statuses();
+    }
+
+    fn get_full_address_list(&self) -> Vec<String> {
+        let imported_address_list = self
+            .imported_keys
+            .values()
+            .map(|(pk, addr_type)| address_from_pk(pk, addr_type, self.network).to_string())
+            .collect();
+        // scripts without an address form can't be looked up by address
+        let watched_address_list = self
+            .watched_scripts
+            .keys()
+            .filter_map(|script| address::from_script(script, self.network))
+            .collect();
+        // notification transactions pay to it
+        let notification_address = Address::from_script(&self.notification_script, self.network)
+            .unwrap()
+            .to_string();
+        [
+            self.db.read().unwrap().get_full_address_list(),
+            imported_address_list,
+            watched_address_list,
+            vec![notification_address],
+        ]
+        .concat()
+    }
+
+    fn export_backup(&self, path: &Path, passphrase: &str) -> Result<(), Box<dyn Error>> {
+        let master_public = KeyFactory::extended_public_from_private(&self.master_key()?);
+        let backup = Backup::from_db(&self.db.read().unwrap(), master_public.to_string())?;
+        backup.write(path, passphrase)?;
+        Ok(())
+    }
+
+    fn export_state_snapshot(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let fingerprint = hex::encode(&self.master_fingerprint[..]);
+        StateSnapshot::from_db(&self.db.read().unwrap(), fingerprint).write(path)?;
+        Ok(())
+    }
+
+    fn import_state_snapshot(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        // the accounts of a locked wallet can't be built again without its master key
+        if self.is_locked() {
+            return Err(WalletError::Locked.into());
+        }
+        if self.pending_rotation.is_some() {
+            return Err(From::from("a seed rotation waits for its sweep, import after it"));
+        }
+        let snapshot = StateSnapshot::read(path)?;
+        if snapshot.master_fingerprint() != hex::encode(&self.master_fingerprint[..]) {
+            return Err(WalletError::SnapshotWalletNotMatch.into());
+        }
+
+        snapshot.to_db(&mut self.db.write().unwrap());
+        let master_key = self.state.keys().master_key;
+        self.reload(master_key);
+        let params = format!("height={}", snapshot.last_seen_block_height());
+        self.audit("import_state_snapshot", params, None);
+        Ok(())
+    }
+
+    fn verify_state(&self) -> Vec<StateIssue> {
+        let mut issues = Vec::new();
+        let keys = self.state.keys();
+        for utxo in self.state.coins().op_to_utxo.values() {
+            let account = keys.account(utxo.addr_type.clone());
+            let pk = account.get_pk(&utxo.key_path);
+            if account.script_from_pk(&pk) != utxo.pk_script {
+                issues.push(StateIssue::UnknownUtxoScript(utxo.out_point));
+            }
+        }
+
+        for account in &keys.accounts() {
+            let addr_type = &account.address_type;
+            let (stored_pks, account_addresses) = {
+                let db = self.db.read().unwrap();
+                let stored_pks: Vec<PublicKey> = db
+                    .get_external_public_key_list()
+                    .into_iter()
+                    .chain(db.get_internal_public_key_list())
+                    .filter(|(key_helper, _)| key_helper.addr_type == *addr_type)
+                    .map(|(_, pk)| pk)
+                    .collect();
+                (stored_pks, db.get_account_address_list(addr_type.clone()))
+            };
+            let pk_set: HashSet<[u8; 33]> =
+                stored_pks.iter().map(|pk| pk.key.serialize()).collect();
+            let address_set: HashSet<&String> = account_addresses.iter().collect();
+
+            let mut derived_addresses = HashSet::new();
+            for key_path in account.derived_paths() {
+                let pk = account.get_pk(&key_path);
+                if !pk_set.contains(&pk.key.serialize()) {
+                    issues.push(StateIssue::MissingKey(addr_type.clone(), key_path.clone()));
+                }
+                let script = account.script_from_pk(&pk);
+                if account.key_path(&script).as_ref() != Some(&key_path) {
+                    issues.push(StateIssue::MissingScript(addr_type.clone(), key_path.clone()));
+                }
+                let addr = account.addr_from_pk(&pk);
+                if !address_set.contains(&addr) {
+                    issues.push(StateIssue::MissingAddress(addr_type.clone(), key_path));
+                }
+                derived_addresses.insert(addr);
+            }
+
+            let mut stored_addresses: Vec<String> =
+                stored_pks.iter().map(|pk| account.addr_from_pk(pk)).collect();
+            stored_addresses.extend(account_addresses.iter().cloned());
+            stored_addresses.sort();
+            stored_addresses.dedup();
+            for addr in stored_addresses {
+                if !derived_addresses.contains(&addr) {
+                    issues.push(StateIssue::UnderivedAddress(addr_type.clone(), addr));
+                }
+            }
+        }
+
+        let mut lock_ids: Vec<&LockId> = self.locked_coins.0.keys().collect();
+        lock_ids.sort_by_key(|lock_id| lock_id.0);
+        for lock_id in lock_ids {
+            let held = self.channel_fundings.contains_key(lock_id)
+                || self.unsigned_locks.values().any(|id| id == lock_id);
+            let spent = self.locked_coins.0[lock_id].0.iter().all(|op| !self.is_unspent(op));
+            if !held && spent {
+                issues.push(StateIssue::OrphanedLockGroup(lock_id.clone()));
+            }
+        }
+        issues
+    }
+
+    fn repair_state(&mut self) -> Result<Vec<StateIssue>, Box<dyn Error>> {
+        let issues = self.verify_state();
+        let mut repaired = 0;
+        for issue in issues.iter().filter(|issue| issue.is_repairable()) {
+            match issue {
+                StateIssue::MissingKey(addr_type, key_path)
+                | StateIssue::MissingScript(addr_type, key_path)
+                | StateIssue::MissingAddress(addr_type, key_path) => {
+                    self.get_account_mut(addr_type.clone()).rederive(key_path);
+                }
+                StateIssue::OrphanedLockGroup(lock_id) => {
+                    self.locked_coins.unlock_group(lock_id.clone());
+                    self.db.write().unwrap().delete_lock_group(lock_id);
+                }
+                _ => continue,
+            }
+            repaired += 1;
+        }
+        for account in &mut self.state.keys_mut().accounts_mut() {
+            account.restore_indexes();
+        }
+
+        let left = self.verify_state();
+        let params = format!("repaired={} left={}", repaired, left.len());
+        self.audit("repair_state", params, None);
+        Ok(left)
+    }
+
+    fn export_electrum_wallet(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        fs::create_dir_all(path)?;
+        for account in &self.state.keys().accounts() {
+            let wallet = serde_json::to_vec_pretty(&account.electrum_wallet())?;
+            fs::write(path.join(account.address_type.as_str()), wallet)?;
+        }
+        Ok(())
+    }
+
+    fn import_backup(
+        &mut self,
+        path: &Path,
+        passphrase: &str,
+        decrypt_cfg: DecryptConfig,
+    ) -> Result<(), Box<dyn Error>> {
+        // a backup holds the seed, it would turn the wallet into a full one
+        self.master_key()?;
+        let backup = Backup::read(path, passphrase)?;
+        let (mut master_key, _) = KeyFactory::decrypt(
+            backup.bip39_randomness(),
+            self.network,
+            &decrypt_cfg.passphrase,
+            &decrypt_cfg.salt,
+        )?;
+        // check before touching the database, wrong secrets would give an unrelated wallet
+        if KeyFactory::extended_public_from_private(&master_key).to_string()
+            != backup.master_public()
+        {
+            wipe_extended_private_key(&mut master_key);
+            return Err(WalletError::BackupKeyNotMatch.into());
+        }
+
+        backup.to_db(&mut self.db.write().unwrap())?;
+        // subscribers and the handles of the state keep following the reloaded wallet
+        self.reload(Some(master_key));
+        wipe_extended_private_key(&mut master_key);
+        Ok(())
+    }
+
+    fn is_watch_only(&self) -> bool {
+        self.state.keys().master_key.is_none() && !self.locked
+    }
+
+    fn lock(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.is_watch_only() {
+            return Err(WalletError::WatchOnly.into());
+        }
+        // the sweep of a rotation is signed and switched to with the new seed
+        if self.pending_rotation.is_some() {
+            return Err(From::from("a seed rotation waits for its sweep, lock the wallet after it"));
+        }
+        {
+            let mut keys = self.state.keys_mut();
+            if let Some(ref mut master_key) = keys.master_key {
+                wipe_extended_private_key(master_key);
+            }
+            keys.master_key = None;
+            for account in &mut keys.accounts_mut() {
+                account.lock();
+            }
+        }
+        self.locked = true;
+        self.relock_at = None;
+        self.audit("lock", String::new(), None);
+        Ok(())
+    }
+
+    fn unlock(
+        &mut self,
+        decrypt_cfg: &DecryptConfig,
+        timeout: Option<u64>,
+    ) -> Result<(), Box<dyn Error>> {
+        if self.is_watch_only() {
+            return Err(WalletError::WatchOnly.into());
+        }
+        let randomness = self
+            .db
+            .read()
+            .unwrap()
+            .get_bip39_randomness()
+            .ok_or(WalletError::HasNoWalletInDatabase)?;
+        let (mut master_key, _) = KeyFactory::decrypt(
+            &randomness,
+            self.network,
+            &decrypt_cfg.passphrase,
+            &decrypt_cfg.salt,
+        )?;
+        // other secrets decrypt to an unrelated seed
+        if master_key.fingerprint(&Secp256k1::new()) != self.master_fingerprint {
+            wipe_extended_private_key(&mut master_key);
+            return Err(WalletError::WrongPassphrase.into());
+        }
+
+        {
+            let mut keys = self.state.keys_mut();
+            for account in &mut keys.accounts_mut() {
+                let account_key = WalletLibrary::derive_account_key(master_key, &account.scheme)?;
+                account.unlock(account_key);
+            }
+            if let Some(ref mut previous_key) = keys.master_key {
+                wipe_extended_private_key(previous_key);
+            }
+            keys.master_key = Some(master_key);
+        }
+        wipe_extended_private_key(&mut master_key);
+        self.locked = false;
+        self.relock_at = timeout.map(|timeout| now() + timeout);
+        self.audit("unlock", format!("timeout={:?}", timeout), None);
+        Ok(())
+    }
+
+    fn is_locked(&self) -> bool {
+        self.locked || self.relock_at.map_or(false, |relock_at| now() >= relock_at)
+    }
+
+    fn relock_expired(&mut self) {
+        if !self.locked && self.is_locked() {
+            // a rotation started after unlocking keeps the keys until its sweep
+            if let Err(e) = self.lock() {
+                log::warn!("wallet stays unlocked: {}", e);
+            }
+        }
+    }
+
+    fn create_unsigned_tx(
+        &mut self,
+        addr_str: String,
+        amt: u64,
+        input_filter: InputFilter,
+        change_addr_type: Option<AccountAddressType>,
+        privacy: Option<bool>,
+    ) -> Result<(UnsignedTx, PartiallySignedTransaction), Box<dyn Error>> {
+        self.check_policy(&addr_str, amt)?;
+        let private = privacy.unwrap_or(self.privacy);
+        let tolerance = self.change_tolerance;
+        let fee_rate = self.fee_rate;
+        let script_pubkey = address::script_pubkey(&addr_str, self.network)?;
+        let mut ops =
+            self.select_coins(amt, &script_pubkey, &input_filter, private, tolerance, fee_rate);
+        // the signer has the keys of the accounts only
+        ops.retain(|op| self.state.coins().op_to_utxo.contains_key(op));
+        let (tx, _) =
+            self.build_tx(ops, addr_str.clone(), amt, change_addr_type, tolerance, fee_rate)?;
+        let psbt = self.account_psbt(&tx)?;
+
+        let input_total: u64 = self.input_values(&tx).iter().sum();
+        let output_total: u64 = tx.output.iter().map(|output| output.value).sum();
+        let id = self.db.read().unwrap().get_next_unsigned_id();
+        let unsigned = UnsignedTx {
+            id,
+            tx,
+            dest_addr: addr_str,
+            amt,
+            fee: input_total - output_total,
+        };
+        {
+            let mut db = self.db.write().unwrap();
+            db.put_next_unsigned_id(id + 1);
+            db.put_unsigned_tx(&unsigned);
+        }
+        self.unsigned_txs.insert(id, unsigned.clone());
+        self.lock_unsigned(id);
+        let params = format!("id={} dest_addr={} amt={}", id, unsigned.dest_addr, amt);
+        self.audit("create_unsigned_tx", params, None);
+        Ok((unsigned, psbt))
+    }
+
+    fn get_idempotent_send(&self, key: &str) -> Option<IdempotentSend> {
+        self.idempotent_sends.get(key).filter(|send| !send.is_expired()).cloned()
+    }
+
+    fn put_idempotent_send(&mut self, send: IdempotentSend) {
+        let expired: Vec<String> = self
+            .idempotent_sends
+            .values()
+            .filter(|send| send.is_expired())
+            .map(|send| send.key.clone())
+            .collect();
+        let mut db = self.db.write().unwrap();
+        for key in expired {
+            db.delete_idempotent_send(&key);
+            self.idempotent_sends.remove(&key);
+        }
+        db.put_idempotent_send(&send);
+        self.idempotent_sends.insert(send.key.clone(), send);
+    }
+
+    fn get_unsigned_txs(&self) -> Vec<UnsignedTx> {
+        let mut unsigned_txs: Vec<UnsignedTx> = self.unsigned_txs.values().cloned().collect();
+        unsigned_txs.sort_by_key(|unsigned| unsigned.id);
+        unsigned_txs
+    }
+
+    fn unsigned_psbt(&self, id: u64) -> Result<PartiallySignedTransaction, Box<dyn Error>> {
+        let unsigned = self.unsigned_txs.get(&id).ok_or("unknown unsigned transaction")?;
+        self.account_psbt(&unsigned.tx)
+    }
+
+    fn finalize_unsigned_tx(
+        &mut self,
+        id: u64,
+        signed: &PartiallySignedTransaction,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let unsigned = self
+            .unsigned_txs
+            .get(&id)
+            .cloned()
+            .ok_or("unknown unsigned transaction")?;
+        if signed.global.unsigned_tx.txid() != unsigned.tx.txid() {
+            return Err(From::from("the PSBT is not of the unsigned transaction"));
+        }
+        // only the signatures are taken from the signer, the outputs spent are the wallet's own
+        let mut psbt = self.account_psbt(&unsigned.tx)?;
+        for (input, signed_input) in psbt.inputs.iter_mut().zip(&signed.inputs) {
+            input.partial_sigs = signed_input.partial_sigs.clone();
+        }
+        let tx = signer::finalize_psbt(&psbt)?;
+        // the policy may have changed since the payment was queued
+        self.check_policy(&unsigned.dest_addr, unsigned.amt)?;
+        self.count_when_published(&tx, unsigned.amt);
+
+        // the coins stay locked, they are gone once the transaction is processed
+        self.unsigned_txs.remove(&id);
+        self.unsigned_locks.remove(&id);
+        self.db.write().unwrap().delete_unsigned_tx(id);
+        Ok(tx)
+    }
+
+    fn cancel_unsigned_tx(&mut self, id: u64) -> Result<(), Box<dyn Error>> {
+        self.unsigned_txs.remove(&id).ok_or("unknown unsigned transaction")?;
+        let lock_id = self.unsigned_locks.remove(&id).unwrap();
+        self.locked_coins.unlock_group(lock_id);
+        self.db.write().unwrap().delete_unsigned_tx(id);
+        Ok(())
+    }
+
+    fn rotate_seed(
+        &mut self,
+        key_gen_cfg: KeyGenConfig,
+    ) -> Result<(Transaction, Mnemonic), Box<dyn Error>> {
+        self.master_key()?;
+        self.check_seed_bound_coins()?;
+        // the payment codes and silent payments of the old seed are dropped along with it
+        let payment_code_coins = self
+            .imported_utxos
+            .values()
+            .any(|utxo| self.payment_code_scripts.contains_key(&utxo.pk_script));
+        if payment_code_coins || !self.silent_payment_utxos.is_empty() {
+            return Err(From::from("sweep the payment code and silent payment coins first"));
+        }
+        let (mut master_key, mnemonic, encrypted) = KeyFactory::new_master_private_key(
+            key_gen_cfg.entropy,
+            self.network,
+            &key_gen_cfg.passphrase,
+            &key_gen_cfg.salt,
+            key_gen_cfg.debug,
+        )?;
+
+        let tx = self.sweep_to_seed(master_key, encrypted);
+        wipe_extended_private_key(&mut master_key);
+        Ok((tx?, mnemonic))
+    }
+
+    fn get_derivation_mode(&self) -> DerivationMode {
+        self.derivation_mode
+    }
+
+    fn network(&self) -> Network {
+        self.network
+    }
+
+    fn migrate_derivation(&mut self) -> Result<Transaction, Box<dyn Error>> {
+        if self.derivation_mode == DerivationMode::Standard {
+            return Err(From::from("the accounts are derived by the standard already"));
+        }
+        self.check_seed_bound_coins()?;
+        let encrypted = self
+            .db
+            .read()
+            .unwrap()
+            .get_bip39_randomness()
+            .ok_or(WalletError::HasNoWalletInDatabase)?;
+        self.sweep_to_seed(self.master_key()?, encrypted)
+    }
+
+    fn import_key(&mut self, pk: &PublicKey) {
+        // witness outputs require compressed keys
+        let addr_type_list = if pk.compressed {
+            vec![
+                AccountAddressType::P2PKH,
+                AccountAddressType::P2SHWH,
+                AccountAddressType::P2WKH,
+            ]
+        } else {
+            vec![AccountAddressType::P2PKH]
+        };
+        for addr_type in addr_type_list {
+            let script = address_from_pk(pk, &addr_type, self.network).script_pubkey();
+            self.db
+                .write()
+                .unwrap()
+                .put_imported_key(&script, pk, &addr_type);
+            self.imported_keys.insert(script, (pk.clone(), addr_type));
+        }
+    }
+
+    fn get_imported_utxo_list(&self) -> Vec<ImportedUtxo> {
+        self.imported_utxos.values().cloned().collect()
+    }
+
+    fn watch_script(&mut self, script_pubkey: Script, label: String) {
+        self.db.write().unwrap().put_watched_script(&script_pubkey, &label);
+        self.watched_scripts.insert(script_pubkey, label);
+    }
+
+    fn get_watched_utxo_list(&self) -> Vec<WatchedUtxo> {
+        self.state.get_watched_utxo_list()
+    }
+
+    fn watched_balance(&self) -> u64 {
+        self.state.watched_balance()
+    }
+
+    fn payment_code(&self) -> String {
+        self.payment_code.to_string()
+    }
+
+    fn notify_payment_code(&mut self, recipient: &str) -> Result<Transaction, Box<dyn Error>> {
+        self.master_key()?;
+        let notification_key = PaymentCode::from_str(recipient)?.child_key(0)?;
+        let addr = Address::p2pkh(&notification_key, self.network);
+        let fee_rate = self.fee_rate;
+        let filter = InputFilter::default();
+        let script_pubkey = addr.script_pubkey();
+        let ops =
+            self.select_coins(DUST_LIMIT, &script_pubkey, &filter, self.privacy, None, fee_rate);
+        let (mut tx, _) = self.build_tx(ops, addr.to_string(), DUST_LIMIT, None, None, fee_rate)?;
+
+        // the first input is the designated one, its key blinds the payment code
+        let payload = {
+            let coins = self.state.coins();
+            let utxo = &coins.op_to_utxo[&tx.input[0].previous_output];
+            let keys = self.state.keys();
+            let mut sk = keys.account((utxo.account_index as usize).into()).get_sk(&utxo.key_path);
+            let payload =
+                bip47::blind(&self.payment_code, &sk, &notification_key, &utxo.out_point);
+            wipe_private_key(&mut sk);
+            payload
+        };
+        tx.output.insert(
+            1,
+            TxOut {
+                value: 0,
+                script_pubkey: bip47::notification_script(&payload?),
+            },
+        );
+        // the change pays for the output carrying the payload too
+        let payload_fee = serialize(&tx.output[1]).len() as u64 * fee_rate;
+        if let Some(change) = tx.output.get_mut(2) {
+            change.value = change.value.saturating_sub(payload_fee);
+        }
+        self.sign_tx(&mut tx)?;
+
+        if !self.sent_payment_codes.contains_key(recipient) {
+            self.db.write().unwrap().put_sent_payment_code(recipient, 0);
+            self.sent_payment_codes.insert(recipient.to_owned(), 0);
+        }
+        Ok(tx)
+    }
+
+    fn payment_code_address(&mut self, recipient: &str) -> Result<String, Box<dyn Error>> {
+        let index = *self
+            .sent_payment_codes
+            .get(recipient)
+            .ok_or("the payment code is not notified")?;
+        let recipient_code = PaymentCode::from_str(recipient)?;
+
+        let mut sk = WalletLibrary::payment_code_sk(&self.master_key()?, self.network, 0);
+        let pk = bip47::send_key(&sk, &recipient_code, index);
+        wipe_private_key(&mut sk);
+        let addr = Address::p2pkh(&pk?, self.network);
+
+        self.db.write().unwrap().put_sent_payment_code(recipient, index + 1);
+        self.sent_payment_codes.insert(recipient.to_owned(), index + 1);
+        Ok(addr.to_string())
+    }
+
+    fn sweep_payment_codes(
+        &mut self,
+        dest_addr_type: AccountAddressType,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let mut utxo_list = Vec::new();
+        for utxo in self.imported_utxos.values() {
+            if let Some((code, index)) = self.payment_code_scripts.get(&utxo.pk_script) {
+                // the key was derived already when its addresses got watched
+                let sender = PaymentCode::from_str(code).unwrap();
+                let sk = self.payment_code_receive_sk(&sender, *index).unwrap();
+                utxo_list.push((utxo.clone(), sk));
+            }
+        }
+        self.sweep_with_keys(utxo_list, dest_addr_type)
+    }
+
+    fn silent_payment_address(&self) -> Result<String, Box<dyn Error>> {
+        // payments to it would go unnoticed
+        if !self.silent_payments {
+            return Err(From::from("silent payments are disabled"));
+        }
+        let addr = SilentPaymentAddress {
+            scan: PublicKey::from_private_key(&Secp256k1::new(), &self.silent_payment_scan_sk),
+            spend: self.silent_payment_spend_pk,
+            network: self.network,
+        };
+        Ok(addr.to_string())
+    }
+
+    fn get_silent_payment_utxo_list(&self) -> Vec<SilentPaymentUtxo> {
+        self.silent_payment_utxos.values().cloned().collect()
+    }
+
+    fn set_spent_outputs(&mut self, outputs: HashMap<OutPoint, TxOut>) {
+        self.spent_outputs = outputs;
+    }
+
+    fn sweep_silent_payments(
+        &mut self,
+        dest_addr_type: AccountAddressType,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let utxo_list: Vec<SilentPaymentUtxo> =
+            self.silent_payment_utxos.values().cloned().collect();
+        let total: u64 = utxo_list.iter().map(|utxo| utxo.value).sum();
+        let mut rng = OsRng::new().map_err(|_| WalletError::CannotObtainRandomSource)?;
+
+        let mut tx = Transaction {
+            version: TX_VERSION,
+            lock_time: 0,
+            input: Vec::new(),
+            output: Vec::new(),
+        };
+        let mut spent = Vec::new();
+        for utxo in &utxo_list {
+            tx.input.push(TxIn {
+                previous_output: utxo.out_point,
+                script_sig: Script::new(),
+                sequence: self.input_sequence(&utxo.out_point),
+                witness: Vec::new(),
+            });
+            spent.push(TxOut {
+                value: utxo.value,
+                script_pubkey: utxo.script_pubkey.clone(),
+            });
+        }
+        // as long as the longest destination script, replaced once the sweep is worth it
+        tx.output.push(TxOut {
+            value: 0,
+            script_pubkey: Script::from(vec![0u8; 25]),
+        });
+        let input_sizes = vec![InputType::P2TR.signed_size(); utxo_list.len()];
+        let fee = signed_vsize(&tx, &input_sizes) as u64 * self.fee_rate;
+        if total < fee + DUST_LIMIT {
+            return Err(From::from("not enough coins to sweep"));
+        }
+
+        let dest_addr = self.get_account_mut(dest_addr_type).new_address()?;
+        tx.output[0] = TxOut {
+            value: total - fee,
+            script_pubkey: Address::from_str(&dest_addr).unwrap().script_pubkey(),
+        };
+
+        let mut spend_sk = WalletLibrary::silent_payment_sk(&self.master_key()?, self.network, 0);
+        let mut signatures = Vec::new();
+        for (i, utxo) in utxo_list.iter().enumerate() {
+            let mut aux = [0u8; 32];
+            rng.fill_bytes(&mut aux);
+            let sighash = taproot::sighash(&tx, i, &spent);
+            let signature = bip352::spend_key(&spend_sk, &utxo.tweak).and_then(|mut sk| {
+                let signature = taproot::sign(&sk, &sighash, &aux);
+                wipe_private_key(&mut sk);
+                signature
+            });
+            match signature {
+                Ok(signature) => signatures.push(signature),
+                Err(e) => {
+                    wipe_private_key(&mut spend_sk);
+                    return Err(e);
+                }
+            }
+        }
+        wipe_private_key(&mut spend_sk);
+        for (input, signature) in tx.input.iter_mut().zip(signatures) {
+            input.witness = vec![signature.to_vec()];
+        }
+
+        Ok(tx)
+    }
+
+    fn new_timelocked_address(&mut self, timelock: Timelock) -> Result<String, Box<dyn Error>> {
+        timelock.check()?;
+        // the offline signer doesn't know the timelock scripts
+        self.master_key()?;
+        let key_index = self.timelock_scripts.len() as u32;
+        let mut sk = self.timelock_sk(key_index);
+        let pk = PublicKey::from_private_key(&Secp256k1::new(), &sk);
+        wipe_private_key(&mut sk);
+
+        let addr = Address::p2wsh(&timelock.witness_script(&pk), self.network);
+        let script_pubkey = addr.script_pubkey();
+        self.db
+            .write()
+            .unwrap()
+            .put_timelock_script(&script_pubkey, key_index, &timelock);
+        self.timelock_scripts.insert(script_pubkey, (key_index, timelock));
+        let params = format!("timelock={:?} address={}", timelock, addr);
+        self.audit("new_timelocked_address", params, None);
+        Ok(addr.to_string())
+    }
+
+    fn get_timelocked_utxo_list(&self) -> Vec<TimelockedUtxo> {
+        self.state.get_timelocked_utxo_list()
+    }
+
+    fn set_vault(&mut self, vault: Vault) -> Result<(), Box<dyn Error>> {
+        vault.check()?;
+        self.db.write().unwrap().put_vault(&vault);
+        self.vault = Some(vault);
+        self.audit("set_vault", format!("vault={:?}", vault), None);
+        Ok(())
+    }
+
+    fn get_vault(&self) -> Option<Vault> {
+        self.vault
+    }
+
+    fn new_vault_address(&mut self) -> Result<String, Box<dyn Error>> {
+        let vault = self.vault.ok_or("no vault is set")?;
+        self.master_key()?;
+        let key_index = self.vault_scripts.len() as u32;
+        let mut sk = self.vault_sk(key_index);
+        let pk = PublicKey::from_private_key(&Secp256k1::new(), &sk);
+        wipe_private_key(&mut sk);
+
+        let addr = Address::p2wsh(&vault.witness_script(&pk), self.network);
+        let script_pubkey = addr.script_pubkey();
+        self.db
+            .write()
+            .unwrap()
+            .put_vault_script(&script_pubkey, key_index, &vault);
+        self.vault_scripts.insert(script_pubkey, (key_index, vault));
+        self.audit("new_vault_address", format!("address={}", addr), None);
+        Ok(addr.to_string())
+    }
+
+    fn get_vault_utxo_list(&self) -> Vec<VaultUtxo> {
+        self.vault_utxos.values().cloned().collect()
+    }
+
+    fn unvault(&mut self, dest_addr: String, fee_rate: u64) -> Result<Transaction, Box<dyn Error>> {
+        self.master_key()?;
+        let tip_height = self.last_seen_block_height;
+        let coins: Vec<VaultUtxo> = self
+            .vault_utxos
+            .values()
+            .filter(|utxo| {
+                utxo.is_mature(tip_height) && !self.locked_coins.is_locked(&utxo.out_point)
+            })
+            .cloned()
+            .collect();
+        if coins.is_empty() {
+            return Err(From::from("no vault coins are mature yet"));
+        }
+
+        let mut tx = Transaction {
+            version: TX_VERSION,
+            lock_time: 0,
+            input: coins
+                .iter()
+                .map(|utxo| TxIn {
+                    previous_output: utxo.out_point,
+                    script_sig: Script::new(),
+                    sequence: utxo.vault.sequence(),
+                    witness: Vec::new(),
+                })
+                .collect(),
+            output: vec![TxOut {
+                value: 0,
+                script_pubkey: address::script_pubkey(&dest_addr, self.network)?,
+            }],
+        };
+        // a signature, the empty branch selector and the script of two keys
+        let witness_size = coins.len() * (1 + 73 + 1 + 1 + 80);
+        let fee = (serialize(&tx).len() + (witness_size + 5) / 4) as u64 * fee_rate;
+        let total: u64 = coins.iter().map(|utxo| utxo.value).sum();
+        if total < fee + DUST_LIMIT {
+            return Err(From::from("the vault coins can't pay the fee"));
+        }
+        tx.output[0].value = total - fee;
+
+        for (i, utxo) in coins.iter().enumerate() {
+            let mut sk = self.vault_sk(utxo.key_index);
+            let signed = vault::sign_unvault_input(&mut tx, i, &sk, &utxo.vault, utxo.value);
+            wipe_private_key(&mut sk);
+            signed?;
+        }
+
+        let txid = tx.txid();
+        self.db.write().unwrap().put_unvault_txid(&txid);
+        self.unvault_txids.insert(txid);
+        Ok(tx)
+    }
+
+    fn script_account_xpub(&self) -> Result<ExtendedPubKey, Box<dyn Error>> {
+        let ctx = Secp256k1::new();
+        let scheme = self.bip84_scheme(SCRIPT_ACCOUNT);
+        let mut account_key = WalletLibrary::derive_account_key(self.master_key()?, &scheme)?;
+        let key = account_key.derive_priv(&ctx, &[ChildNumber::Normal { index: 0 }]);
+        wipe_extended_private_key(&mut account_key);
+        let mut key = key?;
+        let xpub = ExtendedPubKey::from_private(&ctx, &key);
+        wipe_extended_private_key(&mut key);
+        Ok(xpub)
+    }
+
+    fn script_account_key(&self) -> Result<String, Box<dyn Error>> {
+        let xpub = self.script_account_xpub()?;
+        let origin = self.bip84_scheme(SCRIPT_ACCOUNT).to_string().replacen("m", "", 1);
+        let fingerprint = hex::encode(&self.master_fingerprint[..]);
+        Ok(format!("[{}{}/0]{}", fingerprint, origin, xpub))
+    }
+
+    fn register_policy(&mut self, name: &str, policy: &str) -> Result<(), Box<dyn Error>> {
+        if self.script_accounts.contains_key(name) {
+            return Err(From::from(format!("the script account {} exists already", name)));
+        }
+        let policy = Policy::from_str(policy)?;
+        // the offline signer doesn't know the policies
+        self.master_key()?;
+        let account = ScriptAccount {
+            name: name.to_string(),
+            policy: policy.to_string(),
+            next_index: 0,
+        };
+        self.db.write().unwrap().put_script_account(&account);
+        self.script_accounts.insert(name.to_string(), account);
+        self.audit("register_policy", format!("name={} policy={}", name, policy), None);
+        Ok(())
+    }
+
+    fn new_script_address(&mut self, name: &str) -> Result<String, Box<dyn Error>> {
+        let policy = self.script_policy(name)?;
+        let mut account = self.script_accounts[name].clone();
+        // like BIP32 the account moves on to the next index if a key has no child at one
+        let addr = loop {
+            let index = account.next_index;
+            account.next_index += 1;
+            match self.index_script_address(name, &policy, index) {
+                Ok(addr) => break addr,
+                Err(e) => log::warn!("skipping the address {} of {}: {}", index, name, e),
+            }
+        };
+        self.db.write().unwrap().put_script_account(&account);
+        self.script_accounts.insert(name.to_string(), account);
+        self.audit("new_script_address", format!("name={} address={}", name, addr), None);
+        Ok(addr.to_string())
+    }
+
+    fn get_script_utxo_list(&self) -> Vec<ScriptUtxo> {
+        self.script_utxos.values().cloned().collect()
+    }
+
+    fn spend_script_coins(
+        &mut self,
+        name: &str,
+        dest_addr: String,
+        fee_rate: u64,
+    ) -> Result<ScriptSpend, Box<dyn Error>> {
+        let policy = self.script_policy(name)?;
+        let ctx = Secp256k1::new();
+        let xpub = self.script_account_xpub()?;
+        let own_pk = |index: u32| {
+            xpub.ckd_pub(&ctx, ChildNumber::Normal { index }).map(|child| child.public_key)
+        };
+
+        // the timelocks of the branches have to be expired in the next block
+        let tip_height = self.last_seen_block_height;
+        let mut coins = Vec::new();
+        for utxo in self.script_utxos.values().filter(|utxo| utxo.account == name) {
+            let pk = own_pk(utxo.index)?;
+            let confirmations = utxo.height.map_or(0, |height| tip_height + 1 - height);
+            let plan =
+                policy.plan(utxo.index, &|key| *key == pk, confirmations as u32, tip_height as u32);
+            if let Some(plan) = plan {
+                coins.push((utxo.clone(), plan));
+            }
+        }
+        if coins.is_empty() {
+            return Err(From::from(format!("no coins of {} are spendable yet", name)));
+        }
+
+        let mut tx = Transaction {
+            version: TX_VERSION,
+            lock_time: coins.iter().map(|(_, plan)| plan.after).max().unwrap(),
+            input: coins
+                .iter()
+                .map(|(utxo, plan)| TxIn {
+                    previous_output: utxo.out_point,
+                    script_sig: Script::new(),
+                    sequence: if plan.older > 0 { plan.older } else { RBF_SEQUENCE },
+                    witness: Vec::new(),
+                })
+                .collect(),
+            output: vec![TxOut {
+                value: 0,
+                script_pubkey: address::script_pubkey(&dest_addr, self.network)?,
+            }],
+        };
+        // the signatures of the planned branch, its selectors and the script
+        let mut witness_size = 0;
+        for (utxo, plan) in &coins {
+            witness_size += policy.witness_script(utxo.index)?.len() + 73 * plan.signatures + 8;
+        }
+        let fee = (serialize(&tx).len() + (witness_size + 5) / 4) as u64 * fee_rate;
+        let total: u64 = coins.iter().map(|(utxo, _)| utxo.value).sum();
+        if total < fee + DUST_LIMIT {
+            return Err(From::from("the coins of the account can't pay the fee"));
+        }
+        tx.output[0].value = total - fee;
+
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx.clone())?;
+        for (i, (utxo, _)) in coins.iter().enumerate() {
+            let witness_script = policy.witness_script(utxo.index)?;
+            let pk = own_pk(utxo.index)?;
+            if policy.keys(utxo.index)?.contains(&pk) {
+                let sighash_type = SigHashType::All;
+                let hash = sighash::signature_hash(
+                    &tx,
+                    i,
+                    &witness_script,
+                    utxo.value,
+                    sighash_type,
+                    true,
+                )?;
+                let mut sk = self.script_account_sk(utxo.index);
+                let signature = ctx.sign(&Message::from_slice(&hash[..])?, &sk.key);
+                wipe_private_key(&mut sk);
+                let mut serialized_sig = signature.serialize_der().to_vec();
+                serialized_sig.push(sighash_type.as_u32() as u8);
+                psbt.inputs[i].partial_sigs.insert(pk, serialized_sig);
+                psbt.inputs[i].hd_keypaths.insert(pk, self.script_account_origin(utxo.index));
+            }
+            // the cosigners find their keys by the origins given in the policy
+            for (pk, origin) in policy.key_origins(utxo.index)? {
+                psbt.inputs[i].hd_keypaths.entry(pk).or_insert(origin);
+            }
+            psbt.inputs[i].witness_utxo = Some(TxOut {
+                value: utxo.value,
+                script_pubkey: utxo.script_pubkey.clone(),
+            });
+            psbt.inputs[i].witness_script = Some(witness_script);
+        }
+        self.add_output_origins(&mut psbt);
+        let tx = self.finalize_script_psbt(psbt.clone()).ok();
+        Ok(ScriptSpend { psbt, tx })
+    }
+
+    fn finalize_script_psbt(
+        &self,
+        psbt: PartiallySignedTransaction,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let mut tx = psbt.global.unsigned_tx;
+        for (i, input) in psbt.inputs.iter().enumerate() {
+            let op = tx.input[i].previous_output;
+            let utxo = self
+                .script_utxos
+                .get(&op)
+                .ok_or_else(|| format!("input {} spends no coin of a script account", i))?;
+            let policy = self.script_policy(&utxo.account)?;
+            let mut witness = policy
+                .satisfy(utxo.index, &input.partial_sigs, tx.input[i].sequence, tx.lock_time)
+                .ok_or_else(|| format!("the signatures of input {} don't satisfy its policy", i))?;
+            witness.push(policy.witness_script(utxo.index)?.to_bytes());
+            tx.input[i].witness = witness;
+        }
+        Ok(tx)
+    }
+
+    fn sweep_address(
+        &mut self,
+        addr_str: &str,
+        dest_addr: String,
+        fee_rate: u64,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let script = address::script_pubkey(addr_str, self.network)?;
+        if !self.is_ours(&script) {
+            return Err(From::from(format!("{} is not an address of the wallet", addr_str)));
+        }
+        let utxo_list: Vec<Utxo> = self
+            .get_utxo_list()
+            .into_iter()
+            .filter(|utxo| utxo.pk_script == script)
+            .filter(|utxo| {
+                !self.locked_coins.is_locked(&utxo.out_point) && !self.is_frozen(&utxo.out_point)
+            })
+            .collect();
+        if utxo_list.is_empty() {
+            return Err(From::from(format!("no coins to sweep at {}", addr_str)));
+        }
+
+        let dest_script = address::script_pubkey(&dest_addr, self.network)?;
+        let mut tx = Transaction {
+            version: TX_VERSION,
+            lock_time: 0,
+            input: utxo_list
+                .iter()
+                .map(|utxo| TxIn {
+                    previous_output: utxo.out_point,
+                    script_sig: Script::new(),
+                    sequence: self.input_sequence(&utxo.out_point),
+                    witness: Vec::new(),
+                })
+                .collect(),
+            output: vec![TxOut {
+                value: 0,
+                script_pubkey: dest_script.clone(),
+            }],
+        };
+        let input_types: Vec<AccountAddressType> =
+            utxo_list.iter().map(|utxo| utxo.addr_type.clone()).collect();
+        let fee = estimate_vsize(&tx, &input_types) as u64 * fee_rate;
+        let total: u64 = utxo_list.iter().map(|utxo| utxo.value).sum();
+        if total < fee + DUST_LIMIT {
+            return Err(From::from("the coins of the address can't pay the fee"));
+        }
+        let amt = total - fee;
+
+        // moving coins within the wallet pays nobody
+        let payment = !self.is_ours(&dest_script);
+        if payment {
+            self.check_policy(&dest_addr, amt)?;
+            if self.spending_policy.approval_threshold.map_or(false, |threshold| amt > threshold) {
+                return Err(From::from("the sweep needs approval, send the coins by send_coins"));
+            }
+        }
+        tx.output[0].value = amt;
+        self.sign_tx(&mut tx)?;
+        if payment {
+            self.count_when_published(&tx, amt);
+        }
+        Ok(tx)
+    }
+
+    fn get_spending_policy(&self) -> SpendingPolicy {
+        self.spending_policy.clone()
+    }
+
+    fn set_spending_policy(&mut self, policy: SpendingPolicy) -> Result<(), Box<dyn Error>> {
+        for addr in policy.whitelist.iter().chain(policy.blacklist.iter()) {
+            address::script_pubkey(addr, self.network)?;
+        }
+        self.db.write().unwrap().put_spending_policy(&policy);
+        self.spending_policy = policy;
+        Ok(())
+    }
+
+    fn spent_in_window(&self) -> u64 {
+        let start = now().saturating_sub(POLICY_WINDOW);
+        self.policy_spends
+            .iter()
+            .filter(|(time, _)| *time > start)
+            .map(|(_, amt)| amt)
+            .sum()
+    }
+
+    fn get_pending_spends(&self) -> Vec<PendingSpend> {
+        let now = now();
+        self.pending_spends
+            .values()
+            .filter(|spend| spend.expires_at > now)
+            .cloned()
+            .collect()
+    }
+
+    fn approve_spend(&mut self, id: u64) -> Result<Transaction, Box<dyn Error>> {
+        let spend = self.pending_spends.get(&id).cloned().ok_or("unknown pending spend")?;
+        if spend.expires_at <= now() {
+            self.reject_spend(id)?;
+            return Err(From::from("the pending spend expired"));
+        }
+        // the limits apply when the payment is signed, they may have changed meanwhile
+        self.check_policy(&spend.dest_addr, spend.amt)?;
+        let private = spend.privacy.unwrap_or(self.privacy);
+        let tolerance = spend.change_tolerance.or(self.change_tolerance);
+        let fee_rate = spend.fee_rate.unwrap_or(self.fee_rate);
+        let script_pubkey = address::script_pubkey(&spend.dest_addr, self.network)?;
+        let filter = &spend.input_filter;
+        let ops =
+            self.select_coins(spend.amt, &script_pubkey, filter, private, tolerance, fee_rate);
+        let change_addr_type = spend.change_addr_type;
+        let (mut tx, _) =
+            self.build_tx(ops, spend.dest_addr, spend.amt, change_addr_type, tolerance, fee_rate)?;
+        self.sign_tx(&mut tx)?;
+        self.count_when_published(&tx, spend.amt);
+        self.reject_spend(id)?;
+        Ok(tx)
+    }
+
+    fn reject_spend(&mut self, id: u64) -> Result<(), Box<dyn Error>> {
+        self.pending_spends.remove(&id).ok_or("unknown pending spend")?;
+        self.db.write().unwrap().delete_pending_spend(id);
+        Ok(())
+    }
+
+    fn set_audit_caller(&mut self, caller: String) {
+        self.audit_caller = caller;
+    }
+
+    fn audit(&mut self, operation: &str, params: String, txid: Option<Sha256dHash>) {
+        let entry = AuditEntry {
+            seq: self.last_audit_entry.as_ref().map_or(0, |entry| entry.seq + 1),
+            timestamp: now(),
+            operation: operation.to_owned(),
+            params,
+            txid,
+            caller: self.audit_caller.clone(),
+            prev_hash: self.last_audit_entry.as_ref().map_or(Default::default(), AuditEntry::hash),
+        };
+        self.db.write().unwrap().put_audit_entry(&entry);
+        self.last_audit_entry = Some(entry);
+    }
+
+    fn get_audit_log(&self, start: u64, limit: usize) -> Vec<AuditEntry> {
+        self.db.read().unwrap().get_audit_log(start, limit)
+    }
+
+    fn prove_reserves(&self, message: &str) -> Result<Transaction, Box<dyn Error>> {
+        // coins of unconfirmed transactions may still be double spent
+        let coin_set = self.state.coins();
+        let history = self.state.history();
+        let utxo_list: Vec<&Utxo> = coin_set
+            .op_to_utxo
+            .values()
+            .filter(|utxo| match history.tx_history.get(&utxo.out_point.txid) {
+                Some(record) => match record.status {
+                    TxStatus::Confirmed(_) => true,
+                    _ => false,
+                },
+                None => false,
+            })
+            .collect();
+        if utxo_list.is_empty() {
+            return Err(From::from("the wallet has no confirmed coins"));
+        }
+
+        let coins: Vec<(OutPoint, u64)> =
+            utxo_list.iter().map(|utxo| (utxo.out_point, utxo.value)).collect();
+        let mut proof = reserves::unsigned_proof(message, &coins);
+        // the first input is the commitment to the message, it has no key
+        let keys = self.state.keys();
+        for (i, utxo) in utxo_list.iter().enumerate() {
+            let account = keys.account((utxo.account_index as usize).into());
+            let mut sk = account.get_sk(&utxo.key_path);
+            sign_input(&mut proof, i + 1, &sk, &utxo.addr_type, utxo.value);
+            wipe_private_key(&mut sk);
+        }
+        Ok(proof)
+    }
+
+    fn sweep_imported(
+        &mut self,
+        sk: &PrivateKey,
+        dest_addr_type: AccountAddressType,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let pk = PublicKey::from_private_key(&Secp256k1::new(), sk);
+        let utxo_list = self
+            .imported_utxos
+            .values()
+            .filter(|utxo| match self.imported_keys.get(&utxo.pk_script) {
+                Some((key, _)) => *key == pk,
+                None => false,
+            })
+            .map(|utxo| (utxo.clone(), *sk))
+            .collect();
+        self.sweep_with_keys(utxo_list, dest_addr_type)
+    }
+
+    fn process_tx(&mut self, tx: &Transaction) {
+        let txid = tx.txid();
+        if self.state.history().tx_history.get(&txid).map_or(false, TxRecord::is_conflicted) {
+            return;
+        }
+
+        // of two unconfirmed transactions spending the same coins the first seen one is kept,
+        // a block decides in the end
+        let winner = {
+            let history = self.state.history();
+            tx.input
+                .iter()
+                .filter_map(|input| history.spent_by.get(&input.previous_output))
+                .find(|spending_txid| **spending_txid != txid)
+                .cloned()
+        };
+        match winner {
+            Some(winner) => {
+                let record = TxRecord {
+                    tx: tx.clone(),
+                    status: TxStatus::Conflicted(winner),
+                    spent: Vec::new(),
+                    fee: None,
+                };
+                self.db.write().unwrap().put_tx_record(&record);
+                self.state.history_mut().tx_history.insert(txid, record);
+                self.events.emit(WalletEvent::Conflict {
+                    txid,
+                    conflicting_txid: winner,
+                });
+            }
+            None => self.apply_tx(tx),
+        }
+    }
+}
+
+impl Drop for WalletLibrary {
+    fn drop(&mut self) {
+        if let Some(ref mut master_key) = self.state.keys_mut().master_key {
+            wipe_extended_private_key(master_key);
+        }
+        wipe_private_key(&mut self.silent_payment_scan_sk);
+        if let Some((_, ref mut master_key)) = self.pending_rotation {
+            wipe_extended_private_key(master_key);
+        }
+    }
+}
+
+pub enum WalletLibraryMode {
+    Create(KeyGenConfig),
+    Decrypt(DecryptConfig),
+    /// the mnemonic is restored under the given passphrase and salt
+    RecoverFromMnemonic(Mnemonic, DecryptConfig),
+    /// same as `RecoverFromMnemonic`, the mnemonic is reassembled from SLIP-39 shares
+    RecoverFromShares(Vec<String>, DecryptConfig),
+    /// a wallet of the public keys exported by the offline signer, it has no mnemonic.
+    /// It is opened by `Decrypt` later on, whatever the passphrase and salt
+    WatchOnly(WatchOnlyKeys),
+    /// `Decrypt` with the passphrase and salt of the config if the database holds a wallet,
+    /// `Create` otherwise. Secrets decrypting to another seed than the stored one fail
+    /// with `WrongPassphrase`. The mnemonic is only returned for a new wallet
+    Auto(KeyGenConfig),
+}
+
+impl WalletLibrary {
+    pub fn new(
+        wc: WalletConfig,
+        mode: WalletLibraryMode,
+    ) -> Result<(WalletLibrary, Mnemonic), WalletError> {
+        let mut db = if wc.in_memory {
+            DB::in_memory()?
+        } else {
+            DB::new(wc.db_path.clone())?
+        };
+        // the keys and addresses of a wallet are of its network,
+        // opened on another one they would pay to coins the wallet never sees
+        match db.get_network() {
+            Some(network) if network != wc.network => {
+                return Err(WalletError::DatabaseNetworkMismatch(network.to_string()));
+            }
+            _ => (),
+        }
+        // `Auto` opens the wallet in the database, a new one is only created in an empty one
+        let (mode, opened) = match mode {
+            WalletLibraryMode::Auto(key_gen_cfg) => {
+                if db.get_bip39_randomness().is_some() || db.get_watch_only_keys().is_some() {
+                    let decrypt_cfg = DecryptConfig::new(
+                        key_gen_cfg.passphrase.clone(),
+                        key_gen_cfg.salt.clone(),
+                    );
+                    (WalletLibraryMode::Decrypt(decrypt_cfg), true)
+                } else {
+                    (WalletLibraryMode::Create(key_gen_cfg), false)
+                }
+            }
+            mode => (mode, false),
+        };
+        let mut pending_rotation = None;
+        let (master_key, mnemonic) = match mode {
+            WalletLibraryMode::Create(key_gen_cfg) => {
+                let (master_key, mnemonic, encrypted) = KeyFactory::new_master_private_key(
+                    key_gen_cfg.entropy,
+                    wc.network,
+                    &key_gen_cfg.passphrase,
+                    &key_gen_cfg.salt,
+                    key_gen_cfg.debug,
+                )?;
+                db.put_bip39_randomness(&encrypted);
+                db.put_derivation_schemes(&key_gen_cfg.derivation_schemes);
+                db.put_derivation_mode(key_gen_cfg.derivation_mode);
+                db.put_birthday(now());
+                (Some(master_key), mnemonic)
+            }
+            WalletLibraryMode::Decrypt(_) if db.get_watch_only_keys().is_some() => {
+                (None, Mnemonic::empty())
+            }
+            WalletLibraryMode::Decrypt(decrypt_cfg) => {
+                let randomness = db
+                    .get_bip39_randomness()
+                    .ok_or(WalletError::HasNoWalletInDatabase)?;
+                let (mut master_key, mnemonic) = KeyFactory::decrypt(
+                    &randomness,
+                    wc.network,
+                    &decrypt_cfg.passphrase,
+                    &decrypt_cfg.salt,
+                )?;
+                // other secrets decrypt to an unrelated seed, which `Decrypt` opens on purpose,
+                // e.g. of another salt. The stored fingerprint tells it for `Auto`
+                let fingerprint = master_key.fingerprint(&Secp256k1::new());
+                match db.get_master_fingerprint() {
+                    Some(ref stored) if opened && stored[..] != fingerprint[..] => {
+                        wipe_extended_private_key(&mut master_key);
+                        return Err(WalletError::WrongPassphrase);
+                    }
+                    Some(ref stored) if stored[..] != fingerprint[..] && !wc.in_memory => {
+                        db = WalletLibrary::hidden_db(&db, &wc.db_path, &fingerprint[..])?;
+                    }
+                    _ => (),
+                }
+                pending_rotation = WalletLibrary::pending_rotation(&db, wc.network, &decrypt_cfg);
+                (Some(master_key), mnemonic)
+            }
+            WalletLibraryMode::RecoverFromMnemonic(mnemonic, decrypt_cfg) => {
+                let (master_key, mnemonic) = WalletLibrary::recover(
+                    &mut db,
+                    mnemonic,
+                    &decrypt_cfg,
+                    wc.network,
+                    wc.force_overwrite,
+                )?;
+                (Some(master_key), mnemonic)
+            }
+            WalletLibraryMode::RecoverFromShares(mut shares, decrypt_cfg) => {
+                let mnemonic = Mnemonic::from_shares(&shares);
+                shares.zeroize();
+                let (master_key, mnemonic) = WalletLibrary::recover(
+                    &mut db,
+                    mnemonic?,
+                    &decrypt_cfg,
+                    wc.network,
+                    wc.force_overwrite,
+                )?;
+                (Some(master_key), mnemonic)
+            }
+            WalletLibraryMode::Auto(_) => unreachable!("resolved to Create or Decrypt"),
+            WalletLibraryMode::WatchOnly(keys) => {
+                // checked here, loading trusts the stored keys.
+                // The seed may have received coins long before, its birthday is unknown
+                keys.check().map_err(|_| WalletError::InvalidWatchOnlyKeys)?;
+                db.put_watch_only_keys(&keys);
+                (None, Mnemonic::empty())
+            }
+        };
+        let mnemonic = if opened { Mnemonic::empty() } else { mnemonic };
+        // databases of older versions are tagged with the network they are opened on
+        if db.get_network().is_none() {
+            db.put_network(wc.network);
+        }
+        let db = Arc::new(RwLock::new(db));
+        let mut wallet_lib = WalletLibrary::load(master_key, wc.network, wc.change_addr_type, db);
+        wallet_lib.pending_rotation = pending_rotation;
+        wallet_lib.silent_payments = wc.silent_payments;
+        wallet_lib.prune_spent_after = wc.prune_spent_after;
+        wallet_lib.spend_unconfirmed_change = wc.spend_unconfirmed_change;
+        wallet_lib.spend_uneconomical = wc.spend_uneconomical;
+        wallet_lib.privacy = wc.privacy;
+        wallet_lib.avoid_reuse = wc.avoid_reuse;
+        wallet_lib.change_tolerance = wc.change_tolerance;
+        wallet_lib.fee_rate = wc.fee_rate;
+        Ok((wallet_lib, mnemonic))
+    }
+
+    // the seed of another salt is a hidden wallet, its keys and coins are kept in a database
+    // of their own next to the one of the seed, named after its fingerprint. A new one starts
+    // with the derivation settings and the birthday of the seed
+    fn hidden_db(db: &DB, db_path: &str, fingerprint: &[u8]) -> Result<DB, WalletError> {
+        let mut hidden = DB::new(format!("{}.{}", db_path, hex::encode(fingerprint)))?;
+        if hidden.get_bip39_randomness().is_none() {
+            let randomness = db.get_bip39_randomness().ok_or(WalletError::HasNoWalletInDatabase)?;
+            hidden.put_bip39_randomness(&randomness);
+            hidden.put_derivation_schemes(&db.get_derivation_schemes());
+            hidden.put_derivation_mode(db.get_derivation_mode());
+            if let Some(birthday) = db.get_birthday() {
+                hidden.put_birthday(birthday);
+            }
+        }
+        Ok(hidden)
+    }
+
+    // build accounts for the master key, or the stored watch-only keys without one,
+    // and fill them with the state stored in the database
+    fn load(
+        master_key: Option<ExtendedPrivKey>,
+        network: Network,
+        change_addr_type: AccountAddressType,
+        db: Arc<RwLock<DB>>,
+    ) -> WalletLibrary {
+        let last_seen_block_height = db.read().unwrap().get_last_seen_block_height();
+        let op_to_utxo = db.read().unwrap().get_utxo_map();
+
+        let derivation_mode = db.read().unwrap().get_derivation_mode();
+        let schemes = db.read().unwrap().get_derivation_schemes();
+        let keys = match master_key {
+            Some(master_key) => {
+                WalletLibrary::watch_only_keys(&master_key, network, derivation_mode, &schemes)
+                    .unwrap()
+            }
+            None => db.read().unwrap().get_watch_only_keys().unwrap(),
+        };
+        let master_fingerprint = keys.master_fingerprint().unwrap();
+        db.write().unwrap().put_master_fingerprint(&master_fingerprint[..]);
+        let account = |addr_type: AccountAddressType| {
+            let (scheme, xpub) = keys.account(&addr_type).unwrap();
+            match master_key {
+                Some(master_key) => WalletLibrary::new_account(
+                    master_key,
+                    scheme,
+                    addr_type,
+                    Network::Regtest,
+                    Arc::clone(&db),
+                ),
+                None => Account::watch_only(
+                    xpub,
+                    addr_type,
+                    Network::Regtest,
+                    scheme,
+                    master_fingerprint,
+                    Arc::clone(&db),
+                ),
+            }
+        };
+
+        let p2pkh_account = account(AccountAddressType::P2PKH);
+        let p2shwh_account = account(AccountAddressType::P2SHWH);
+        let p2wkh_account = account(AccountAddressType::P2WKH);
+
+        let mut tx_history = db.read().unwrap().get_tx_history();
+        // recorded before their fees were, the coins spent tell it
+        for record in tx_history.values_mut().filter(|record| record.fee.is_none()) {
+            let input_values: Vec<u64> = record.spent.iter().map(|utxo| utxo.value).collect();
+            record.fee = fee_paid(&record.tx, &input_values);
+        }
+        let mut spent_by = HashMap::new();
+        for (txid, record) in &tx_history {
+            if !record.is_conflicted() {
+                for input in &record.tx.input {
+                    spent_by.insert(input.previous_output, *txid);
+                }
+            }
+        }
+
+        let payment_code = PaymentCode::from_str(&keys.payment_code).unwrap();
+        let notification_key = payment_code.child_key(0).unwrap();
+        let silent_payment_scan_sk = keys.silent_payment_scan_sk().unwrap();
+        let silent_payment_spend_pk = keys.silent_payment_spend_pk().unwrap();
+
+        let key_material = KeyMaterial {
+            master_key,
+            p2pkh_account,
+            p2shwh_account,
+            p2wkh_account,
+        };
+        let coin_set = CoinSet {
+            op_to_utxo,
+            spent_utxos: db.read().unwrap().get_spent_utxo_map(),
+            frozen_coins: db.read().unwrap().get_frozen_utxo_set(),
+            watched_utxos: db.read().unwrap().get_watched_utxo_map(),
+            timelocked_utxos: db.read().unwrap().get_timelocked_utxo_map(),
+        };
+        let history = History {
+            tx_history,
+            spent_by,
+        };
+
+        let mut wallet_lib = WalletLibrary {
+            state: WalletState::new(key_material, coin_set, history),
+            locked: false,
+            relock_at: None,
+            master_fingerprint,
+            network,
+            derivation_mode,
+            change_addr_type,
+            last_seen_block_height,
+            prune_spent_after: None,
+            spend_unconfirmed_change: false,
+            spend_uneconomical: false,
+            privacy: false,
+            avoid_reuse: false,
+            change_tolerance: None,
+            fee_rate: DEFAULT_FEE_RATE,
+            next_lock_id: LockId::new(),
+            locked_coins: LockGroupMap::new(),
+            unconfirmed_txs: db.read().unwrap().get_unconfirmed_tx_map(),
+            events: EventSink::default(),
+            next_prepared_id: 0,
+            prepared_txs: HashMap::new(),
+            channel_fundings: HashMap::new(),
+            pending_rotation: None,
+            imported_keys: db.read().unwrap().get_imported_keys(),
+            imported_utxos: db.read().unwrap().get_imported_utxo_map(),
+            watched_scripts: db.read().unwrap().get_watched_scripts(),
+            notification_script: Address::p2pkh(&notification_key, network).script_pubkey(),
+            payment_code,
+            sent_payment_codes: db.read().unwrap().get_sent_payment_codes(),
+            received_payment_codes: db.read().unwrap().get_received_payment_codes(),
+            payment_code_scripts: HashMap::new(),
+            silent_payment_scan_sk,
+            silent_payment_spend_pk,
+            silent_payment_utxos: db.read().unwrap().get_silent_payment_utxo_map(),
+            silent_payments: false,
+            spent_outputs: HashMap::new(),
+            timelock_scripts: db.read().unwrap().get_timelock_scripts(),
+            vault: db.read().unwrap().get_vault(),
+            vault_scripts: db.read().unwrap().get_vault_scripts(),
+            vault_utxos: db.read().unwrap().get_vault_utxo_map(),
+            unvault_txids: db.read().unwrap().get_unvault_txids(),
+            script_accounts: db.read().unwrap().get_script_accounts(),
+            script_account_scripts: HashMap::new(),
+            script_utxos: db.read().unwrap().get_script_utxo_map(),
+            spending_policy: db.read().unwrap().get_spending_policy(),
+            policy_spends: db.read().unwrap().get_policy_spends(),
+            unpublished_spends: HashMap::new(),
+            pending_spends: db.read().unwrap().get_pending_spends(),
+            unsigned_txs: db.read().unwrap().get_unsigned_txs(),
+            idempotent_sends: db.read().unwrap().get_idempotent_sends(),
+            address_stats: HashMap::new(),
+            address_labels: db.read().unwrap().get_address_labels(),
+            unsigned_locks: HashMap::new(),
+            audit_caller: String::new(),
+            last_audit_entry: db.read().unwrap().get_last_audit_entry(),
+            db,
+        };
+
+        let received_payment_codes = wallet_lib.received_payment_codes.clone();
+        for (code, watched_count) in received_payment_codes {
+            let sender = PaymentCode::from_str(&code).unwrap();
+            for index in 0..watched_count {
+                wallet_lib.index_payment_code_key(&code, &sender, index);
+            }
+        }
+        let script_accounts: Vec<ScriptAccount> =
+            wallet_lib.script_accounts.values().cloned().collect();
+        for account in script_accounts {
+            let policy = Policy::from_str(&account.policy).unwrap();
+            for index in 0..account.next_index {
+                // the indexes skipped by `new_script_address` have no address
+                wallet_lib.index_script_address(&account.name, &policy, index).ok();
+            }
+        }
+        // locks aren't stored, the queue of unsigned payments is
+        let unsigned_ids: Vec<u64> = wallet_lib.unsigned_txs.keys().cloned().collect();
+        for id in unsigned_ids {
+            wallet_lib.lock_unsigned(id);
+        }
+
+        //        let mut ac = AccountFactory{
+        //            wallet_lib,
+        //            bio,
+        //        };
+        let op_to_utxo = wallet_lib.state.coins().op_to_utxo.clone();
+        for (_, val) in &op_to_utxo {
+            wallet_lib
+                .get_account_mut(val.addr_type.clone())
+                .utxo_list
+                .insert(val.out_point, val.clone());
+        }
+
+        if !wallet_lib.db.read().unwrap().has_script_index() {
+            // database was created before the script index existed, build it from the stored keys
+            let mut public_key_list = wallet_lib.db.read().unwrap().get_external_public_key_list();
+            public_key_list.extend(wallet_lib.db.read().unwrap().get_internal_public_key_list());
+            for (key_helper, pk) in public_key_list {
+                wallet_lib
+                    .get_account_mut(key_helper.addr_type.clone())
+                    .reindex_pk(&key_helper, &pk);
+            }
+        }
+        for account in &mut wallet_lib.state.keys_mut().accounts_mut() {
+            account.restore_indexes();
+        }
+
+        let used_scripts = wallet_lib.db.read().unwrap().get_used_scripts();
+        for (script, (addr_type, first_funding)) in used_scripts {
+            wallet_lib
+                .get_account_mut(addr_type)
+                .used_scripts
+                .insert(script, first_funding);
+        }
+
+        let address_stats = wallet_lib.db.read().unwrap().get_address_stats();
+        if address_stats.is_empty() {
+            // database was created before the totals were kept, count the history
+            let records: Vec<TxRecord> =
+                wallet_lib.state.history().tx_history.values().cloned().collect();
+            for record in records.iter().filter(|record| !record.is_conflicted()) {
+                wallet_lib.count_address_stats(record, false);
+            }
+        } else {
+            wallet_lib.address_stats = address_stats;
+        }
+
+        wallet_lib
+    }
+
+    // a rotation started before the restart goes on if its seed opens with the same secrets
+    fn pending_rotation(
+        db: &DB,
+        network: Network,
+        decrypt_cfg: &DecryptConfig,
+    ) -> Option<(PendingRotation, ExtendedPrivKey)> {
+        let rotation = db.get_pending_rotation()?;
+        let (mut master_key, _) = KeyFactory::decrypt(
+            &rotation.bip39_randomness,
+            network,
+            &decrypt_cfg.passphrase,
+            &decrypt_cfg.salt,
+        )
+        .ok()?;
+        if KeyFactory::extended_public_from_private(&master_key).to_string()
+            != rotation.master_public
+        {
+            wipe_extended_private_key(&mut master_key);
+            return None;
+        }
+        Some((rotation, master_key))
+    }
+
+    // update the coins of the wallet with the transaction
+    fn apply_tx(&mut self, tx: &Transaction) {
+        let txid = tx.txid();
+        let fee = fee_paid(tx, &self.input_values(tx));
+        let mut spent = Vec::new();
+        for input in &tx.input {
+            if self.imported_utxos.remove(&input.previous_output).is_some() {
+                self.db
+                    .write()
+                    .unwrap()
+                    .delete_imported_utxo(&input.previous_output);
+            }
+
+            if let Some(utxo) = self.remove_utxo(&input.previous_output) {
+                let spent_utxo = SpentUtxo {
+                    utxo: utxo.clone(),
+                    spent_by: txid,
+                    spent_height: None,
+                };
+                self.db.write().unwrap().put_spent_utxo(&spent_utxo);
+                self.state.coins_mut().spent_utxos.insert(utxo.out_point, spent_utxo);
+                spent.push(utxo);
+            }
+        }
+
+        if tx.output.iter().any(|output| output.script_pubkey == self.notification_script) {
+            self.process_notification(tx);
+        }
+        self.scan_silent_payments(tx);
+
+        for input in &tx.input {
+            if self.silent_payment_utxos.remove(&input.previous_output).is_some() {
+                self.db
+                    .write()
+                    .unwrap()
+                    .delete_silent_payment_utxo(&input.previous_output);
+            }
+            if self.state.coins_mut().timelocked_utxos.remove(&input.previous_output).is_some() {
+                self.db
+                    .write()
+                    .unwrap()
+                    .delete_timelocked_utxo(&input.previous_output);
+            }
+            if self.script_utxos.remove(&input.previous_output).is_some() {
+                self.db.write().unwrap().delete_script_utxo(&input.previous_output);
+            }
+            if self.vault_utxos.remove(&input.previous_output).is_some() {
+                self.db.write().unwrap().delete_vault_utxo(&input.previous_output);
+                // the recovery key moving a deposit, or a stolen hot key
+                if !self.unvault_txids.contains(&txid) {
+                    self.events.emit(WalletEvent::UnexpectedUnvault {
+                        out_point: input.previous_output,
+                        txid,
+                    });
+                }
+            }
+            let watched = self.state.coins_mut().watched_utxos.remove(&input.previous_output);
+            if let Some(utxo) = watched {
+                self.db.write().unwrap().delete_watched_utxo(&utxo.out_point);
+                self.events.emit(WalletEvent::WatchedSpent {
+                    label: utxo.label,
+                    out_point: utxo.out_point,
+                    txid,
+                });
+            }
+        }
+        for (output_index, output) in tx.output.iter().enumerate() {
+            if let Some((_, addr_type)) = self.imported_keys.get(&output.script_pubkey) {
+                let utxo = ImportedUtxo {
+                    value: output.value,
+                    out_point: OutPoint {
+                        txid,
+                        vout: output_index as u32,
+                    },
+                    pk_script: output.script_pubkey.clone(),
+                    addr_type: addr_type.clone(),
+                };
+                self.db
+                    .write()
+                    .unwrap()
+                    .put_imported_utxo(&utxo.out_point, &utxo);
+                self.imported_utxos.insert(utxo.out_point, utxo);
+            }
+
+            if let Some(&(key_index, timelock)) = self.timelock_scripts.get(&output.script_pubkey) {
+                let out_point = OutPoint {
+                    txid,
+                    vout: output_index as u32,
+                };
+                // seen before, its height is set once it is confirmed
+                if !self.state.coins().timelocked_utxos.contains_key(&out_point) {
+                    let utxo = TimelockedUtxo {
+                        value: output.value,
+                        out_point,
+                        script_pubkey: output.script_pubkey.clone(),
+                        key_index,
+                        timelock,
+                        height: None,
+                    };
+                    self.db.write().unwrap().put_timelocked_utxo(&utxo);
+                    self.state.coins_mut().timelocked_utxos.insert(out_point, utxo);
+                }
+            }
+
+            if let Some(&(key_index, vault)) = self.vault_scripts.get(&output.script_pubkey) {
+                let out_point = OutPoint {
+                    txid,
+                    vout: output_index as u32,
+                };
+                if !self.vault_utxos.contains_key(&out_point) {
+                    let utxo = VaultUtxo {
+                        value: output.value,
+                        out_point,
+                        script_pubkey: output.script_pubkey.clone(),
+                        key_index,
+                        vault,
+                        height: None,
+                    };
+                    self.db.write().unwrap().put_vault_utxo(&utxo);
+                    self.vault_utxos.insert(out_point, utxo);
+                }
+            }
+
+            if let Some((account, index)) = self.script_account_scripts.get(&output.script_pubkey) {
+                let out_point = OutPoint {
+                    txid,
+                    vout: output_index as u32,
+                };
+                if !self.script_utxos.contains_key(&out_point) {
+                    let utxo = ScriptUtxo {
+                        value: output.value,
+                        out_point,
+                        script_pubkey: output.script_pubkey.clone(),
+                        account: account.clone(),
+                        index: *index,
+                        height: None,
+                    };
+                    self.db.write().unwrap().put_script_utxo(&utxo);
+                    self.script_utxos.insert(out_point, utxo);
+                }
+            }
+
+            if let Some(label) = self.watched_scripts.get(&output.script_pubkey) {
+                let out_point = OutPoint {
+                    txid,
+                    vout: output_index as u32,
+                };
+                // seen before in the mempool
+                if self.state.coins().watched_utxos.contains_key(&out_point) {
+                    continue;
+                }
+                let utxo = WatchedUtxo {
+                    value: output.value,
+                    out_point,
+                    script_pubkey: output.script_pubkey.clone(),
+                    label: label.clone(),
+                };
+                self.db.write().unwrap().put_watched_utxo(&utxo);
+                self.state.coins_mut().watched_utxos.insert(out_point, utxo);
+                self.events.emit(WalletEvent::WatchedFunded {
+                    label: label.clone(),
+                    out_point,
+                    value: output.value,
+                });
+            }
+        }
+
+        // a paid address of a payment code moves the window of the watched ones
+        let paid: Vec<(String, u32)> = tx
+            .output
+            .iter()
+            .filter_map(|output| self.payment_code_scripts.get(&output.script_pubkey))
+            .cloned()
+            .collect();
+        for (code, index) in paid {
+            if self.received_payment_codes[&code] < index + 1 + PAYMENT_CODE_LOOKAHEAD {
+                self.watch_payment_code(&code, index + 1 + PAYMENT_CODE_LOOKAHEAD);
+            }
+        }
+
+        let mut received = 0;
+        let mut receives = false;
+        let mut keys = self.state.keys_mut();
+        let mut coins = self.state.coins_mut();
+        for (account_index, account) in keys.accounts_mut().iter_mut().enumerate() {
+            for (output_index, output) in tx.output.iter().enumerate() {
+                let key_path = match account.key_path(&output.script_pubkey) {
+                    Some(key_path) => key_path,
+                    None => continue,
+                };
+
+                let op = OutPoint {
+                    txid,
+                    vout: output_index as u32,
+                };
+
+                if !account.mark_used(&output.script_pubkey, op) {
+                    log::warn!(
+                        "address reuse, output {}:{} pays to a script which already received funds",
+                        op.txid,
+                        op.vout,
+                    );
+                }
+
+                let utxo = Utxo::new(
+                    output.value,
+                    key_path,
+                    op,
+                    account_index as u32,
+                    output.script_pubkey.clone(),
+                    account.address_type.clone(),
+                );
+
+                account.grab_utxo(utxo.clone());
+                coins.op_to_utxo.insert(op, utxo);
+                received += output.value;
+                receives = true;
+            }
+        }
+        drop(coins);
+        drop(keys);
+
+        if receives || !spent.is_empty() {
+            let _span = tracing::debug_span!("tx", %txid, received, spent = spent.len()).entered();
+            tracing::debug!("transaction of the wallet");
+            let incoming = spent.is_empty();
+            if self.add_to_history(tx, spent, fee) && incoming {
+                self.events.emit(WalletEvent::Deposit {
+                    txid,
+                    value: received,
+                });
+            }
+        }
+
+        let sweeps = match self.pending_rotation {
+            Some((ref rotation, _)) => rotation.txid == txid,
+            None => false,
+        };
+        if sweeps {
+            self.switch_seed(tx);
+        }
+    }
+
+    fn history_entry(&self, record: &TxRecord) -> HistoryEntry {
+        let value = self.tx_value(record);
+        let fee = record.fee.unwrap_or(0);
+        let (direction, amount) = if value >= 0 {
+            (Direction::Incoming, value as u64)
+        } else {
+            (Direction::Outgoing, (-value as u64).saturating_sub(fee))
+        };
+        let date = match record.status {
+            TxStatus::Confirmed(height) => self
+                .get_block_header(height)
+                .filter(|header| header.time != 0)
+                .map(|header| history::format_date(header.time as u64))
+                .unwrap_or_default(),
+            _ => String::new(),
+        };
+        // an incoming payment names the address of the wallet, an outgoing one the payee
+        let keys = self.state.keys();
+        let accounts = keys.accounts();
+        let address = record
+            .tx
+            .output
+            .iter()
+            .find(|output| {
+                let ours = accounts
+                    .iter()
+                    .any(|account| account.is_mine(&output.script_pubkey));
+                ours == (direction == Direction::Incoming)
+            })
+            .and_then(|output| address::from_script(&output.script_pubkey, self.network))
+            .unwrap_or_default();
+        let label = record
+            .tx
+            .output
+            .iter()
+            .filter_map(|output| self.watched_scripts.get(&output.script_pubkey))
+            .next()
+            .cloned()
+            .unwrap_or_default();
+        HistoryEntry {
+            date,
+            txid: record.txid().to_string(),
+            direction,
+            amount,
+            fee,
+            label,
+            address,
+        }
+    }
+
+    // the script pays to an address of one of the accounts
+    fn is_ours(&self, script: &Script) -> bool {
+        self.state.keys().accounts().iter().any(|account| account.is_mine(script))
+    }
+
+    // values of the inputs of `tx` which are coins of the wallet, of any kind
+    fn input_values(&self, tx: &Transaction) -> Vec<u64> {
+        let coins = self.state.coins();
+        tx.input
+            .iter()
+            .filter_map(|input| {
+                let op = &input.previous_output;
+                coins
+                    .op_to_utxo
+                    .get(op)
+                    .map(|utxo| utxo.value)
+                    .or_else(|| self.imported_utxos.get(op).map(|utxo| utxo.value))
+                    .or_else(|| self.silent_payment_utxos.get(op).map(|utxo| utxo.value))
+                    .or_else(|| coins.timelocked_utxos.get(op).map(|utxo| utxo.value))
+                    .or_else(|| self.vault_utxos.get(op).map(|utxo| utxo.value))
+                    .or_else(|| self.script_utxos.get(op).map(|utxo| utxo.value))
+            })
+            .collect()
+    }
+
+    // whether `op` is a coin of the wallet, of any kind, which isn't spent
+    fn is_unspent(&self, op: &OutPoint) -> bool {
+        let coins = self.state.coins();
+        coins.op_to_utxo.contains_key(op)
+            || self.imported_utxos.contains_key(op)
+            || self.silent_payment_utxos.contains_key(op)
+            || coins.timelocked_utxos.contains_key(op)
+            || self.vault_utxos.contains_key(op)
+            || self.script_utxos.contains_key(op)
+    }
+
+    // a coin of the wallet is spent, returns it if the wallet had it
+    fn remove_utxo(&mut self, op: &OutPoint) -> Option<Utxo> {
+        let utxo = self.state.coins_mut().op_to_utxo.remove(op)?;
+        self.get_account_mut(utxo.addr_type.clone())
+            .utxo_list
+            .remove(op)
+            .unwrap();
+        self.db.write().unwrap().delete_utxo(op);
+
+        if self.state.coins_mut().frozen_coins.remove(op) {
+            self.db.write().unwrap().delete_frozen_utxo(op);
+        }
+        Some(utxo)
+    }
+
+    // drop the archived coins spent deep enough in the chain, if the wallet is set to
+    fn prune_spent_utxos(&mut self, tip_height: usize) {
+        let confirmations = match self.prune_spent_after {
+            Some(confirmations) => confirmations,
+            None => return,
+        };
+        let pruned: Vec<OutPoint> = self
+            .state
+            .coins()
+            .spent_utxos
+            .values()
+            .filter(|spent_utxo| match spent_utxo.spent_height {
+                Some(height) => tip_height + 1 >= height + confirmations,
+                None => false,
+            })
+            .map(|spent_utxo| spent_utxo.utxo.out_point)
+            .collect();
+        for op in pruned {
+            self.state.coins_mut().spent_utxos.remove(&op);
+            self.db.write().unwrap().delete_spent_utxo(&op);
+        }
+    }
+
+    // returns whether the transaction is new to the history
+    fn add_to_history(&mut self, tx: &Transaction, spent: Vec<Utxo>, fee: Option<u64>) -> bool {
+        let txid = tx.txid();
+        // a conflicted transaction is only replaced when it gets into a block after all
+        if self.state.get_tx(&txid).map_or(false, |record| !record.is_conflicted()) {
+            return false;
+        }
+
+        let mut history = self.state.history_mut();
+        for input in &tx.input {
+            history.spent_by.insert(input.previous_output, txid);
+        }
+        drop(history);
+        let record = TxRecord {
+            tx: tx.clone(),
+            status: TxStatus::Unconfirmed,
+            spent,
+            fee,
+        };
+        self.db.write().unwrap().put_tx_record(&record);
+        self.count_address_stats(&record, false);
+        self.state.history_mut().tx_history.insert(txid, record);
+        true
+    }
+
+    // add what `record` received and sent to the totals of the scripts, or take it away
+    fn count_address_stats(&mut self, record: &TxRecord, undo: bool) {
+        let stats = AddressStats::of_tx(record, |script| self.is_ours(script));
+        for (script, tx_stats) in stats {
+            let total = self.address_stats.entry(script.clone()).or_default();
+            if undo {
+                total.subtract(&tx_stats);
+            } else {
+                total.add(&tx_stats);
+            }
+            self.db.write().unwrap().put_address_stats(&script, total);
+        }
+    }
+
+    // the transaction lost to another one or was replaced by it as the `status` says,
+    // undo what it did to the coins of the wallet
+    fn mark_conflicted(&mut self, txid: &Sha256dHash, status: TxStatus) {
+        let conflicting_txid = match status {
+            TxStatus::Conflicted(conflicting_txid) | TxStatus::Replaced(conflicting_txid) => {
+                conflicting_txid
+            }
+            _ => return,
+        };
+        let record = match self.state.history_mut().tx_history.get_mut(txid) {
+            Some(record) => {
+                record.status = status;
+                record.clone()
+            }
+            None => return,
+        };
+        self.db.write().unwrap().put_tx_record(&record);
+        self.count_address_stats(&record, true);
+
+        {
+            let mut history = self.state.history_mut();
+            for input in &record.tx.input {
+                if history.spent_by.get(&input.previous_output) == Some(txid) {
+                    history.spent_by.remove(&input.previous_output);
+                }
+            }
+        }
+        for vout in 0..record.tx.output.len() {
+            let op = OutPoint {
+                txid: *txid,
+                vout: vout as u32,
+            };
+            self.remove_utxo(&op);
+            if self.state.coins_mut().watched_utxos.remove(&op).is_some() {
+                self.db.write().unwrap().delete_watched_utxo(&op);
+            }
+        }
+        // coins spent by other transactions stay spent
+        for utxo in record.spent {
+            if !self.state.history().spent_by.contains_key(&utxo.out_point) {
+                if self.state.coins_mut().spent_utxos.remove(&utxo.out_point).is_some() {
+                    self.db.write().unwrap().delete_spent_utxo(&utxo.out_point);
+                }
+                self.get_account_mut(utxo.addr_type.clone())
+                    .grab_utxo(utxo.clone());
+                self.state.coins_mut().op_to_utxo.insert(utxo.out_point, utxo);
+            }
+        }
+
+        if self.unconfirmed_txs.remove(txid).is_some() {
+            self.db.write().unwrap().delete_unconfirmed_tx(txid);
+        }
+        self.events.emit(WalletEvent::Conflict {
+            txid: *txid,
+            conflicting_txid,
+        });
+    }
+
+    // sign a transaction sweeping the spendable coins to the seed of `master_key`,
+    // the wallet switches to it once the transaction is confirmed
+    fn sweep_to_seed(
+        &mut self,
+        master_key: ExtendedPrivKey,
+        encrypted: Vec<u8>,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        // the old seed is forgotten after the switch, frozen coins would be lost with it
+        if !self.state.coins().frozen_coins.is_empty() {
+            return Err(From::from("spend or unfreeze frozen coins before rotating the seed"));
+        }
+
+        let ops: Vec<OutPoint> = self
+            .get_utxo_list()
+            .into_iter()
+            .map(|utxo| utxo.out_point)
+            .filter(|op| !self.locked_coins.is_locked(op))
+            .collect();
+        let total: u64 = {
+            let coins = self.state.coins();
+            ops.iter().map(|op| coins.op_to_utxo[op].value).sum()
+        };
+
+        // the sweep pays to the first external key of the p2wkh account of the seed,
+        // the account derives it again after the switch
+        let keys = self.state.keys();
+        let scheme = if self.is_default_scheme(&keys.p2wkh_account) {
+            DerivationScheme::default_for(
+                &AccountAddressType::P2WKH,
+                self.network,
+                DerivationMode::Standard,
+            )
+        } else {
+            keys.p2wkh_account.scheme
+        };
+        drop(keys);
+        let mut account_key = WalletLibrary::derive_account_key(master_key, &scheme)?;
+        let path = &[ChildNumber::Normal { index: 0 }, ChildNumber::Normal { index: 0 }];
+        let mut key = account_key.derive_priv(&Secp256k1::new(), path)?;
+        let pk = ExtendedPubKey::from_private(&Secp256k1::new(), &key).public_key;
+        wipe_extended_private_key(&mut key);
+        wipe_extended_private_key(&mut account_key);
+
+        let mut tx = Transaction {
+            version: TX_VERSION,
+            lock_time: 0,
+            input: Vec::new(),
+            output: Vec::new(),
+        };
+        for op in &ops {
+            tx.input.push(TxIn {
+                previous_output: *op,
+                script_sig: Script::new(),
+                sequence: self.input_sequence(op),
+                witness: Vec::new(),
+            });
+        }
+        tx.output.push(TxOut {
+            value: 0,
+            script_pubkey: Address::p2wpkh(&pk, self.network).script_pubkey(),
+        });
+        let input_types: Vec<AccountAddressType> = {
+            let coins = self.state.coins();
+            ops.iter().map(|op| coins.op_to_utxo[op].addr_type.clone()).collect()
+        };
+        let fee = estimate_vsize(&tx, &input_types) as u64 * self.fee_rate;
+        if total < fee + DUST_LIMIT {
+            return Err(From::from("not enough coins to sweep"));
+        }
+        tx.output[0].value = total - fee;
+        self.sign_tx(&mut tx)?;
+
+        // swept coins must not be spent by anything else until the switch
+        let lock_group = LockGroup(ops);
+        self.locked_coins
+            .lock_group(self.next_lock_id.clone(), lock_group.clone());
+        self.db
+            .write()
+            .unwrap()
+            .put_lock_group(&self.next_lock_id, &lock_group);
+        self.next_lock_id.incr();
+
+        let rotation = PendingRotation {
+            bip39_randomness: encrypted,
+            txid: tx.txid(),
+            master_public: KeyFactory::extended_public_from_private(&master_key).to_string(),
+        };
+        self.db.write().unwrap().put_pending_rotation(&rotation);
+        if let Some((_, mut previous_key)) = self.pending_rotation.take() {
+            wipe_extended_private_key(&mut previous_key);
+        }
+        self.pending_rotation = Some((rotation, master_key));
+
+        Ok(tx)
+    }
+
+    // refuse to give up the derivation of the seed while coins of the accounts which aren't
+    // swept are left, their keys are derived at the coin type of the wallet as well
+    fn check_seed_bound_coins(&self) -> Result<(), Box<dyn Error>> {
+        if !self.state.coins().timelocked_utxos.is_empty()
+            || !self.script_utxos.is_empty()
+            || !self.vault_utxos.is_empty()
+        {
+            return Err(From::from("spend the timelocked, script account and vault coins first"));
+        }
+        Ok(())
+    }
+
+    // the account is derived at the path of its address type
+    fn is_default_scheme(&self, account: &Account) -> bool {
+        let mode = self.derivation_mode;
+        account.scheme == DerivationScheme::default_for(&account.address_type, self.network, mode)
+    }
+
+    // the sweep is confirmed, replace the old seed and all of its state with the new one
+    fn switch_seed(&mut self, sweep_tx: &Transaction) {
+        let (rotation, mut master_key) = self.pending_rotation.take().unwrap();
+        let last_seen_block_height = self.last_seen_block_height;
+        {
+            // imported keys, watched scripts and the spending policy along with the payments
+            // waiting for approval have nothing to do with the seed, they are kept,
+            // the payment codes and silent payments are derived from the seed, so they are dropped
+            // unless the seed stays and only the derivation of the accounts changes.
+            // The accounts at default paths follow the standard from now on
+            let mut db = self.db.write().unwrap();
+            let next_spend_id = db.get_next_spend_id();
+            let birthday = db.get_birthday();
+            db.clear();
+            db.put_bip39_randomness(&rotation.bip39_randomness);
+            db.put_last_seen_block_height(last_seen_block_height as u32);
+            db.put_next_spend_id(next_spend_id);
+            let custom_schemes: Vec<_> = self
+                .state
+                .keys()
+                .accounts()
+                .iter()
+                .filter(|account| !self.is_default_scheme(account))
+                .map(|account| (account.address_type.clone(), account.scheme))
+                .collect();
+            db.put_derivation_schemes(&custom_schemes);
+            // nothing paid to a new seed before the rotation
+            let same_seed =
+                self.master_public().map_or(false, |key| key.to_string() == rotation.master_public);
+            if !same_seed {
+                db.put_birthday(now());
+            } else if let Some(birthday) = birthday {
+                db.put_birthday(birthday);
+            }
+            if same_seed {
+                for (code, next_index) in &self.sent_payment_codes {
+                    db.put_sent_payment_code(code, *next_index);
+                }
+                for (code, watched_count) in &self.received_payment_codes {
+                    db.put_received_payment_code(code, *watched_count);
+                }
+                for utxo in self.silent_payment_utxos.values() {
+                    db.put_silent_payment_utxo(utxo);
+                }
+                for record in self.state.history().tx_history.values() {
+                    db.put_tx_record(record);
+                }
+            }
+            for (script, (pk, addr_type)) in &self.imported_keys {
+                db.put_imported_key(script, pk, addr_type);
+            }
+            for (op, utxo) in &self.imported_utxos {
+                db.put_imported_utxo(op, utxo);
+            }
+            for (script, label) in &self.watched_scripts {
+                db.put_watched_script(script, label);
+            }
+            for utxo in self.state.coins().watched_utxos.values() {
+                db.put_watched_utxo(utxo);
+            }
+            db.put_spending_policy(&self.spending_policy);
+            db.put_policy_spends(&self.policy_spends);
+            for spend in self.pending_spends.values() {
+                db.put_pending_spend(spend);
+            }
+        }
+        // subscribers follow the wallet to the new seed
+        self.reload(Some(master_key));
+        wipe_extended_private_key(&mut master_key);
+
+        self.get_account_mut(AccountAddressType::P2WKH).new_address().unwrap();
+        self.process_tx(sweep_tx);
+    }
+
+    // build the wallet from the database again, with the subscribers and the configuration
+    // it had
+    fn reload(&mut self, master_key: Option<ExtendedPrivKey>) {
+        let events = mem::replace(&mut self.events, EventSink::default());
+        let audit_caller = mem::replace(&mut self.audit_caller, String::new());
+        let silent_payments = self.silent_payments;
+        let prune_spent_after = self.prune_spent_after;
+        let spend_unconfirmed_change = self.spend_unconfirmed_change;
+        let spend_uneconomical = self.spend_uneconomical;
+        let privacy = self.privacy;
+        let avoid_reuse = self.avoid_reuse;
+        let change_tolerance = self.change_tolerance;
+        let fee_rate = self.fee_rate;
+        let state = self.state.clone();
+        *self = WalletLibrary::load(
+            master_key,
+            self.network,
+            self.change_addr_type.clone(),
+            Arc::clone(&self.db),
+        );
+        // the handles given out by `state` see the reloaded coins
+        state.replace(self.state.clone());
+        self.state = state;
+        self.events = events;
+        self.audit_caller = audit_caller;
+        self.silent_payments = silent_payments;
+        self.prune_spent_after = prune_spent_after;
+        self.spend_unconfirmed_change = spend_unconfirmed_change;
+        self.spend_uneconomical = spend_uneconomical;
+        self.privacy = privacy;
+        self.avoid_reuse = avoid_reuse;
+        self.change_tolerance = change_tolerance;
+        self.fee_rate = fee_rate;
+    }
+
+    // transaction moving imported coins to a new address of `dest_addr_type` with the fee rate
+    // of the wallet, each coin is signed with the key paired with it, the keys are wiped
+    fn sweep_with_keys(
+        &mut self,
+        mut utxo_list: Vec<(ImportedUtxo, PrivateKey)>,
+        dest_addr_type: AccountAddressType,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let fee_rate = self.fee_rate;
+        let tx = self.build_sweep_tx(&utxo_list, dest_addr_type, fee_rate);
+        for (_, sk) in utxo_list.iter_mut() {
+            wipe_private_key(sk);
+        }
+        tx
+    }
+
+    fn build_sweep_tx(
+        &mut self,
+        utxo_list: &[(ImportedUtxo, PrivateKey)],
+        dest_addr_type: AccountAddressType,
+        fee_rate: u64,
+    ) -> Result<Transaction, Box<dyn Error>> {
+        let total: u64 = utxo_list.iter().map(|(utxo, _)| utxo.value).sum();
+        let mut tx = Transaction {
+            version: TX_VERSION,
+            lock_time: 0,
+            input: Vec::new(),
+            output: Vec::new(),
+        };
+        for (utxo, _) in utxo_list {
+            tx.input.push(TxIn {
+                previous_output: utxo.out_point,
+                script_sig: Script::new(),
+                sequence: self.input_sequence(&utxo.out_point),
+                witness: Vec::new(),
+            });
+        }
+        // as long as the longest destination script, replaced once the sweep is worth it
+        tx.output.push(TxOut {
+            value: 0,
+            script_pubkey: Script::from(vec![0u8; 25]),
+        });
+        let input_types: Vec<AccountAddressType> =
+            utxo_list.iter().map(|(utxo, _)| utxo.addr_type.clone()).collect();
+        let fee = estimate_vsize(&tx, &input_types) as u64 * fee_rate;
+        if total < fee + DUST_LIMIT {
+            return Err(From::from("not enough coins to sweep"));
+        }
+
+        let dest_addr = self.get_account_mut(dest_addr_type).new_address()?;
+        tx.output[0] = TxOut {
+            value: total - fee,
+            script_pubkey: Address::from_str(&dest_addr).unwrap().script_pubkey(),
+        };
+        for (i, (utxo, sk)) in utxo_list.iter().enumerate() {
+            sign_input(&mut tx, i, sk, &utxo.addr_type, utxo.value);
+        }
+
+        Ok(tx)
+    }
+
+    // m/47'/coin'/0', the coin type 1 stands for all the test networks
+    fn payment_code_path(network: Network) -> [ChildNumber; 3] {
+        let coin_type = if network == Network::Bitcoin { 0 } else { 1 };
+        [
+            ChildNumber::Hardened { index: 47 },
+            ChildNumber::Hardened { index: coin_type },
+            ChildNumber::Hardened { index: 0 },
+        ]
+    }
+
+    // key of the child `index` of the payment code, the child 0 is the notification key
+    fn payment_code_sk(master_key: &ExtendedPrivKey, network: Network, index: u32) -> PrivateKey {
+        let mut path = WalletLibrary::payment_code_path(network).to_vec();
+        path.push(ChildNumber::Normal { index });
+        let mut key = master_key.derive_priv(&Secp256k1::new(), &path).unwrap();
+        let sk = key.private_key;
+        wipe_extended_private_key(&mut key);
+        sk
+    }
+
+    // key of the `index`-th address `sender` pays to
+    fn payment_code_receive_sk(
+        &self,
+        sender: &PaymentCode,
+        index: u32,
+    ) -> Result<PrivateKey, Box<dyn Error>> {
+        let mut sk = WalletLibrary::payment_code_sk(&self.master_key()?, self.network, index);
+        let receive_sk = bip47::receive_key(&sk, sender);
+        wipe_private_key(&mut sk);
+        receive_sk
+    }
+
+    // index the scripts of the `index`-th address `sender` pays to, returns its key
+    fn index_payment_code_key(
+        &mut self,
+        code: &str,
+        sender: &PaymentCode,
+        index: u32,
+    ) -> Option<PublicKey> {
+        // an index giving an invalid key is skipped by the sender as well
+        let mut sk = self.payment_code_receive_sk(sender, index).ok()?;
+        let pk = PublicKey::from_private_key(&Secp256k1::new(), &sk);
+        wipe_private_key(&mut sk);
+        for addr_type in &[
+            AccountAddressType::P2PKH,
+            AccountAddressType::P2SHWH,
+            AccountAddressType::P2WKH,
+        ] {
+            let script = address_from_pk(&pk, addr_type, self.network).script_pubkey();
+            self.payment_code_scripts.insert(script, (code.to_owned(), index));
+        }
+        Some(pk)
+    }
+
+    // import the keys of the addresses `code` pays to, up to the index `watched_count`
+    fn watch_payment_code(&mut self, code: &str, watched_count: u32) {
+        let sender = PaymentCode::from_str(code).unwrap();
+        let start = self.received_payment_codes.get(code).cloned().unwrap_or(0);
+        for index in start..watched_count {
+            if let Some(pk) = self.index_payment_code_key(code, &sender, index) {
+                self.import_key(&pk);
+            }
+        }
+        self.db.write().unwrap().put_received_payment_code(code, watched_count);
+        self.received_payment_codes.insert(code.to_owned(), watched_count);
+    }
+
+    // a notification transaction carries the blinded payment code of a sender
+    fn process_notification(&mut self, tx: &Transaction) {
+        let (payload, (out_point, pk)) =
+            match (bip47::find_payload(tx), bip47::designated_input(tx)) {
+                (Some(payload), Some(designated)) => (payload, designated),
+                _ => return,
+            };
+        // a watch-only or locked wallet can't unblind the payment codes
+        let master_key = match self.state.keys().master_key {
+            Some(master_key) => master_key,
+            None => return,
+        };
+        let mut sk = WalletLibrary::payment_code_sk(&master_key, self.network, 0);
+        let sender = bip47::unblind(payload, &sk, &pk, &out_point);
+        wipe_private_key(&mut sk);
+
+        let code = match sender {
+            Ok(sender) => sender.to_string(),
+            Err(_) => return,
+        };
+        if !self.received_payment_codes.contains_key(&code) {
+            log::info!("notified by the payment code {}", code);
+            self.watch_payment_code(&code, PAYMENT_CODE_LOOKAHEAD);
+        }
+    }
+
+    // m/352'/coin'/0'/`key_type`'/0, the key type 0 is the spend key and 1 the scan key
+    fn silent_payment_sk(
+        master_key: &ExtendedPrivKey,
+        network: Network,
+        key_type: u32,
+    ) -> PrivateKey {
+        let coin_type = if network == Network::Bitcoin { 0 } else { 1 };
+        let path = [
+            ChildNumber::Hardened { index: 352 },
+            ChildNumber::Hardened { index: coin_type },
+            ChildNumber::Hardened { index: 0 },
+            ChildNumber::Hardened { index: key_type },
+            ChildNumber::Normal { index: 0 },
+        ];
+        let mut key = master_key.derive_priv(&Secp256k1::new(), &path).unwrap();
+        let sk = key.private_key;
+        wipe_extended_private_key(&mut key);
+        sk
+    }
+
+    // m/84'/coin'/1'/0/`index`, the keys of the timelocked outputs
+    fn timelock_sk(&self, index: u32) -> PrivateKey {
+        self.bip84_sk(TIMELOCK_ACCOUNT, index)
+    }
+
+    // m/84'/coin'/3'/0/`index`, the hot keys of the vault addresses
+    fn vault_sk(&self, index: u32) -> PrivateKey {
+        self.bip84_sk(VAULT_ACCOUNT, index)
+    }
+
+    // m/84'/coin'/2'/0/`index`, the keys of the wallet in the script accounts
+    fn script_account_sk(&self, index: u32) -> PrivateKey {
+        self.bip84_sk(SCRIPT_ACCOUNT, index)
+    }
+
+    // fingerprint of the master key and m/84'/coin'/2'/0/`index`
+    fn script_account_origin(&self, index: u32) -> (Fingerprint, DerivationPath) {
+        let mut path = self.bip84_scheme(SCRIPT_ACCOUNT).path();
+        path.push(ChildNumber::Normal { index: 0 });
+        path.push(ChildNumber::Normal { index });
+        (self.master_fingerprint, DerivationPath::from(path))
+    }
+
+    // m/84'/coin'/`account`'
+    fn bip84_scheme(&self, account: u32) -> DerivationScheme {
+        let mut scheme = DerivationScheme::default_for(
+            &AccountAddressType::P2WKH,
+            self.network,
+            self.derivation_mode,
+        );
+        scheme.account = account;
+        scheme
+    }
+
+    // m/84'/coin'/`account`'/0/`index`
+    fn bip84_sk(&self, account: u32, index: u32) -> PrivateKey {
+        // timelocked and script account coins are refused to a watch-only wallet
+        let master_key = self.master_private().expect("a watch-only wallet has no private keys");
+        let mut account_key =
+            WalletLibrary::derive_account_key(master_key, &self.bip84_scheme(account)).unwrap();
+        let path = [ChildNumber::Normal { index: 0 }, ChildNumber::Normal { index }];
+        let mut key = account_key.derive_priv(&Secp256k1::new(), &path).unwrap();
+        wipe_extended_private_key(&mut account_key);
+        let sk = key.private_key;
+        wipe_extended_private_key(&mut key);
+        sk
+    }
+
+    // watch the address `index` of the script account `name`
+    fn index_script_address(
+        &mut self,
+        name: &str,
+        policy: &Policy,
+        index: u32,
+    ) -> Result<Address, bip32::Error> {
+        let addr = Address::p2wsh(&policy.witness_script(index)?, self.network);
+        self.script_account_scripts.insert(addr.script_pubkey(), (name.to_string(), index));
+        Ok(addr)
+    }
+
+    fn script_policy(&self, name: &str) -> Result<Policy, Box<dyn Error>> {
+        let account = self
+            .script_accounts
+            .get(name)
+            .ok_or_else(|| format!("no script account {}", name))?;
+        Policy::from_str(&account.policy)
+    }
+
+    // BIP352 detection with the keys of the inputs, the outputs it finds are added to the
+    // silent payment coins
+    fn scan_silent_payments(&mut self, tx: &Transaction) {
+        if !self.silent_payments {
+            return;
+        }
+        let found = match bip352::scan(
+            tx,
+            &self.spent_outputs,
+            &self.silent_payment_scan_sk,
+            &self.silent_payment_spend_pk,
+        ) {
+            Ok(found) => found,
+            Err(e) => {
+                log::warn!("failed to scan {} for silent payments: {}", tx.txid(), e);
+                return;
+            }
+        };
+        let txid = tx.txid();
+        for (vout, tweak) in found {
+            let output = &tx.output[vout as usize];
+            let utxo = SilentPaymentUtxo {
+                value: output.value,
+                out_point: OutPoint { txid, vout },
+                script_pubkey: output.script_pubkey.clone(),
+                tweak,
+            };
+            if !self.silent_payment_utxos.contains_key(&utxo.out_point) {
+                log::info!("silent payment of {} received in {}:{}", utxo.value, txid, vout);
+            }
+            self.db.write().unwrap().put_silent_payment_utxo(&utxo);
+            self.silent_payment_utxos.insert(utxo.out_point, utxo);
+        }
+    }
+
+    // rejects a payment of `amt` to `addr_str` which the spending policy doesn't allow
+    fn check_policy(&self, addr_str: &str, amt: u64) -> Result<(), Box<dyn Error>> {
+        let policy = &self.spending_policy;
+        // addresses are compared by their scripts, bech32 ones may be upper case
+        let script = address::script_pubkey(addr_str, self.network)?;
+        let listed = |list: &[String]| {
+            list.iter().any(|addr| {
+                address::script_pubkey(addr, self.network).ok().as_ref() == Some(&script)
+            })
+        };
+        if listed(&policy.blacklist) || !(policy.whitelist.is_empty() || listed(&policy.whitelist))
+        {
+            return Err(Box::new(WalletError::DestinationNotAllowed(addr_str.to_owned())));
+        }
+        if let Some(limit) = policy.max_per_tx {
+            if amt > limit {
+                return Err(Box::new(WalletError::TransactionLimitExceeded(limit)));
+            }
+        }
+        if let Some(limit) = policy.daily_limit {
+            let left = limit.saturating_sub(self.spent_in_window());
+            if amt > left {
+                return Err(Box::new(WalletError::DailyLimitExceeded(left)));
+            }
+        }
+        Ok(())
+    }
+
+    // count the payment of `amt` signed by `tx` against the daily limit once it is published,
+    // see `add_unconfirmed_tx`
+    fn count_when_published(&mut self, tx: &Transaction, amt: u64) {
+        self.unpublished_spends.insert(tx.txid(), amt);
+    }
+
+    // count a published payment against the daily limit, older ones are forgotten
+    fn record_spend(&mut self, amt: u64) {
+        let now = now();
+        self.policy_spends.retain(|(time, _)| *time + POLICY_WINDOW > now);
+        self.policy_spends.push((now, amt));
+        self.db.write().unwrap().put_policy_spends(&self.policy_spends);
+    }
+
+    // pick spendable coins until they cover `amt` paid to `script_pubkey` and the fee at
+    // `fee_rate`, all of one account if `private`.
+    // Coins paying them with less than `tolerance` left over are preferred
+    fn select_coins(
+        &self,
+        amt: u64,
+        script_pubkey: &Script,
+        input_filter: &InputFilter,
+        private: bool,
+        tolerance: Option<u64>,
+        fee_rate: u64,
+    ) -> Vec<OutPoint> {
+        let candidates: Vec<Utxo> = self
+            .get_utxo_list()
+            .into_iter()
+            .filter(|utxo| {
+                let label = self.address_labels.get(&utxo.pk_script);
+                !self.locked_coins.is_locked(&utxo.out_point)
+                    && !self.is_frozen(&utxo.out_point)
+                    && input_filter.matches(utxo, label)
+                    && self.is_selectable(utxo)
+            })
+            .collect();
+        let change_type = InputType::from(&self.change_addr_type);
+        let target = PaymentTarget::new(amt, script_pubkey, change_type, fee_rate);
+        let groups = self.economical_groups(candidates, fee_rate);
+        if let Some(tolerance) = tolerance {
+            // anything below the cost of the change is better left to the fee than kept
+            let tolerance = cmp::max(tolerance, target.cost_of_change as u64);
+            let exact = if private {
+                by_account(groups.clone())
+                    .values()
+                    .filter_map(|groups| select_exact(groups, fee_rate, &target, tolerance))
+                    .min_by_key(|ops| ops.len())
+            } else {
+                select_exact(&groups, fee_rate, &target, tolerance)
+            };
+            if let Some(ops) = exact {
+                return ops;
+            }
+        }
+        if private {
+            return select_private_coins(groups, &target, fee_rate);
+        }
+
+        let (selected, mut effective_total) = take_until(groups, fee_rate, target.with_change);
+        let mut subset: Vec<OutPoint> = selected.iter().map(|utxo| utxo.out_point).collect();
+
+        // matured timelocked coins make up for what the accounts lack,
+        // unless the inputs are restricted to some of them
+        if let InputFilter::Any = input_filter {
+            for utxo in self.state.coins().timelocked_utxos.values() {
+                if effective_total >= target.with_change {
+                    break;
+                }
+                if self.locked_coins.is_locked(&utxo.out_point)
+                    || !utxo.is_mature(self.last_seen_block_height)
+                {
+                    continue;
+                }
+                let input_fee = timelocked_input_fee(utxo, fee_rate);
+                let effective_value = utxo.value as i64 - input_fee as i64;
+                if effective_value <= 0 && !self.spend_uneconomical {
+                    continue;
+                }
+                effective_total += effective_value;
+                subset.push(utxo.out_point);
+            }
+        }
+        subset
+    }
+
+    // the coins selection picks from, one by one unless the wallet avoids reuse: then the
+    // coins of a reused address come together, and not at all if one of them is missing
+    // from `coins`, e.g. because it is frozen or unconfirmed
+    fn group_reused(&self, coins: Vec<Utxo>) -> Vec<Vec<Utxo>> {
+        if !self.avoid_reuse {
+            return coins.into_iter().map(|utxo| vec![utxo]).collect();
+        }
+        let mut groups: Vec<Vec<Utxo>> = Vec::new();
+        let mut reused: HashMap<Script, usize> = HashMap::new();
+        for utxo in coins {
+            if !self.is_reused(&utxo.pk_script) {
+                groups.push(vec![utxo]);
+                continue;
+            }
+            match reused.get(&utxo.pk_script) {
+                Some(&i) => groups[i].push(utxo),
+                None => {
+                    reused.insert(utxo.pk_script.clone(), groups.len());
+                    groups.push(vec![utxo]);
+                }
+            }
+        }
+        let utxo_list = self.get_utxo_list();
+        groups
+            .into_iter()
+            .filter(|group| {
+                let script = &group[0].pk_script;
+                !reused.contains_key(script)
+                    || utxo_list.iter().filter(|utxo| utxo.pk_script == *script).count()
+                        == group.len()
+            })
+            .collect()
+    }
+
+    // a coin worth less than the fee of its input would lower what the inputs pay,
+    // it is spent only if the wallet is configured to consolidate such coins
+    fn is_economical(&self, utxo: &Utxo, fee_rate: u64) -> bool {
+        self.spend_uneconomical || utxo.value > input_fee(utxo, fee_rate)
+    }
+
+    // the first of `coins` whose effective values, what is left of them once they paid
+    // for their inputs at `fee_rate`, cover `amt` and the fee of the other `base_vsize` bytes.
+    // The coins of a reused address count together if the wallet avoids reuse
+    fn select_by_effective_value<I: IntoIterator<Item = Utxo>>(
+        &self,
+        coins: I,
+        amt: u64,
+        fee_rate: u64,
+        base_vsize: usize,
+    ) -> Option<Vec<Utxo>> {
+        let target = (amt + base_vsize as u64 * fee_rate) as i64;
+        let groups = self.economical_groups(coins.into_iter().collect(), fee_rate);
+        let (selected, effective_total) = take_until(groups, fee_rate, target);
+        if effective_total >= target {
+            Some(selected)
+        } else {
+            None
+        }
+    }
+
+    // the groups of `coins`, see `group_reused`, but those costing more to spend at `fee_rate`
+    // than they are worth, unless the wallet is configured to consolidate such coins
+    fn economical_groups(&self, coins: Vec<Utxo>, fee_rate: u64) -> Vec<Vec<Utxo>> {
+        self.group_reused(coins)
+            .into_iter()
+            .filter(|group| {
+                if group.len() == 1 {
+                    self.is_economical(&group[0], fee_rate)
+                } else {
+                    self.spend_uneconomical || effective_value(group, fee_rate) > 0
+                }
+            })
+            .collect()
+    }
+
+    fn input_sequence(&self, op: &OutPoint) -> u32 {
+        self.state.coins().input_sequence(op)
+    }
+
+    fn signed_input_size(&self, op: &OutPoint) -> (usize, usize) {
+        self.state.coins().signed_input_size(op)
+    }
+
+    fn is_frozen(&self, op: &OutPoint) -> bool {
+        self.state.coins().frozen_coins.contains(op)
+    }
+
+    // the sender of an unconfirmed coin may still double spend it, unless it is the wallet
+    fn is_selectable(&self, utxo: &Utxo) -> bool {
+        match self.state.history().tx_history.get(&utxo.out_point.txid) {
+            Some(record) if record.status == TxStatus::Unconfirmed => {
+                self.spend_unconfirmed_change && !record.spent.is_empty()
+            }
+            _ => true,
+        }
+    }
+
+    // unsigned transaction paying `amt` to `addr_str` with the fee of `fee_rate` satoshi per
+    // virtual byte and the rest to a new change address, returned along with the change address,
+    // empty if the change is dust or below `tolerance`
+    fn build_tx(
+        &mut self,
+        ops: Vec<OutPoint>,
+        addr_str: String,
+        amt: u64,
+        change_addr_type: Option<AccountAddressType>,
+        tolerance: Option<u64>,
+        fee_rate: u64,
+    ) -> Result<(Transaction, String), Box<dyn Error>> {
+        let script_pubkey = address::script_pubkey(&addr_str, self.network)?;
+
+        let mut tx = Transaction {
+            version: TX_VERSION,
+            lock_time: 0,
+            input: Vec::new(),
+            output: Vec::new(),
+        };
+
+        let mut total = 0;
+        let coins = self.state.coins();
+        for op in &ops {
+            if let Some(utxo) = coins.timelocked_utxos.get(op) {
+                total += utxo.value;
+                tx.lock_time = tx.lock_time.max(utxo.timelock.lock_time());
+            } else {
+                total += coins.op_to_utxo.get(op).unwrap().value;
+            }
+
+            let input = TxIn {
+                previous_output: *op,
+                script_sig: Script::new(),
+                sequence: coins.input_sequence(op),
+                witness: Vec::new(),
+            };
+            tx.input.push(input);
+        }
+        let input_sizes: Vec<(usize, usize)> =
+            ops.iter().map(|op| coins.signed_input_size(op)).collect();
+        drop(coins);
+
+        // dest output
+        let output = TxOut {
+            value: amt,
+            script_pubkey,
+        };
+        tx.output.push(output);
+        let fee = signed_vsize(&tx, &input_sizes) as u64 * fee_rate;
+        if total < amt + fee {
+            return Err(Box::new(WalletError::InsufficientFunds));
+        }
+
+        // as long as the longest change script, replaced once the change is known to be worth it
+        tx.output.push(TxOut {
+            value: 0,
+            script_pubkey: Script::from(vec![0u8; 25]),
+        });
+        let fee = signed_vsize(&tx, &input_sizes) as u64 * fee_rate;
+        tx.output.pop();
+
+        // change worth less than its output, or than the input spending it, is left to the fee
+        // as well
+        let change = (total - amt).saturating_sub(fee);
+        let change_addr_type = change_addr_type.unwrap_or(self.change_addr_type.clone());
+        let spend_fee = InputType::from(&change_addr_type).vsize() as u64 * fee_rate;
+        if change < DUST_LIMIT
+            || change <= spend_fee
+            || tolerance.map_or(false, |tolerance| change < tolerance)
+        {
+            return Ok((tx, String::new()));
+        }
+        let change_addr = self
+            .get_account_mut(change_addr_type)
+            .new_change_address()
+            .unwrap();
+
+        let change_output = TxOut {
+            value: change,
+            script_pubkey: Address::from_str(&change_addr).unwrap().script_pubkey(),
+        };
+        tx.output.push(change_output);
+
+        Ok((tx, change_addr))
+    }
+
+    // transaction paying `amt` to `script_pubkey` from segwit coins with the fee of `fee_rate`
+    // satoshi per virtual byte, the funding output comes first, returned along with the fee
+    fn build_funding_tx(
+        &mut self,
+        script_pubkey: Script,
+        amt: u64,
+        fee_rate: u64,
+    ) -> Result<(Transaction, u64), Box<dyn Error>> {
+        let mut tx = Transaction {
+            version: TX_VERSION,
+            lock_time: 0,
+            input: Vec::new(),
+            output: vec![TxOut {
+                value: amt,
+                script_pubkey,
+            }],
+        };
+        // as long as the longest change script, replaced once the inputs are known
+        tx.output.push(TxOut {
+            value: 0,
+            script_pubkey: Script::from(vec![0u8; 25]),
+        });
+
+        // signing a legacy input changes the txid, the channel needs it before that
+        let coins = self.get_utxo_list().into_iter().filter(|utxo| {
+            utxo.addr_type != AccountAddressType::P2PKH
+                && !self.locked_coins.is_locked(&utxo.out_point)
+                && !self.is_frozen(&utxo.out_point)
+        });
+        // the outputs, version, lock time, the count of the inputs and the segwit marker
+        let base_vsize = (serialize(&tx).len() * 4 + 2 + 3) / 4;
+        let selected = self
+            .select_by_effective_value(coins, amt, fee_rate, base_vsize)
+            .ok_or("not enough segwit coins to fund the channel")?;
+        let mut total = 0;
+        let mut input_types = Vec::new();
+        for utxo in selected {
+            tx.input.push(TxIn {
+                previous_output: utxo.out_point,
+                script_sig: Script::new(),
+                sequence: self.input_sequence(&utxo.out_point),
+                witness: Vec::new(),
+            });
+            total += utxo.value;
+            input_types.push(utxo.addr_type);
+        }
+        // the sizes of the inputs, rounded up one by one, leave enough for the exact fee
+        // unless the count of the inputs takes a longer varint
+        let fee = estimate_vsize(&tx, &input_types) as u64 * fee_rate;
+        if total < amt + fee {
+            return Err(From::from("not enough segwit coins to fund the channel"));
+        }
+
+        let change = total - amt - fee;
+        if change < DUST_LIMIT {
+            tx.output.pop();
+            return Ok((tx, total - amt));
+        }
+        let change_addr_type = self.change_addr_type.clone();
+        let change_addr = self.get_account_mut(change_addr_type).new_change_address()?;
+        tx.output[1] = TxOut {
+            value: change,
+            script_pubkey: Address::from_str(&change_addr)?.script_pubkey(),
+        };
+        Ok((tx, fee))
+    }
+
+    // PSBT of a transaction spending coins of the accounts, with the outputs it spends
+    // and the origins of their keys for the offline signer
+    fn account_psbt(&self, tx: &Transaction) -> Result<PartiallySignedTransaction, Box<dyn Error>> {
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx.clone())?;
+        let keys = self.state.keys();
+        let coins = self.state.coins();
+        let history = self.state.history();
+        for (i, input) in tx.input.iter().enumerate() {
+            let op = input.previous_output;
+            let utxo = coins
+                .op_to_utxo
+                .get(&op)
+                .ok_or_else(|| format!("input {} spends no coin of the accounts", i))?;
+            let account = keys.account(utxo.addr_type.clone());
+            let pk = account.get_pk(&utxo.key_path);
+            if utxo.addr_type == AccountAddressType::P2PKH {
+                // the value of a legacy input is proven by the whole transaction only
+                let record = history
+                    .tx_history
+                    .get(&op.txid)
+                    .ok_or_else(|| format!("the transaction input {} spends is unknown", i))?;
+                psbt.inputs[i].non_witness_utxo = Some(record.tx.clone());
+            } else {
+                psbt.inputs[i].witness_utxo = Some(TxOut {
+                    value: utxo.value,
+                    script_pubkey: utxo.pk_script.clone(),
+                });
+            }
+            if utxo.addr_type == AccountAddressType::P2SHWH {
+                psbt.inputs[i].redeem_script =
+                    Some(Address::p2wpkh(&pk, self.network).script_pubkey());
+            }
+            psbt.inputs[i].hd_keypaths.insert(pk, account.key_origin(&utxo.key_path));
+        }
+        // taken again by `add_output_origins`
+        drop(keys);
+        self.add_output_origins(&mut psbt);
+        Ok(psbt)
+    }
+
+    // origins of the keys of the outputs paying back to the accounts, a signer tells
+    // the change from a payment by them
+    fn add_output_origins(&self, psbt: &mut PartiallySignedTransaction) {
+        let keys = self.state.keys();
+        let accounts = keys.accounts();
+        for (i, output) in psbt.global.unsigned_tx.output.iter().enumerate() {
+            for account in accounts.iter() {
+                let key_path = match account.key_path(&output.script_pubkey) {
+                    Some(key_path) => key_path,
+                    None => continue,
+                };
+                let pk = account.get_pk(&key_path);
+                if account.address_type == AccountAddressType::P2SHWH {
+                    psbt.outputs[i].redeem_script =
+                        Some(Address::p2wpkh(&pk, self.network).script_pubkey());
+                }
+                psbt.outputs[i].hd_keypaths.insert(pk, account.key_origin(&key_path));
+            }
+        }
+    }
+
+    // lock the coins of the queued payment `id` until it is finalized or cancelled
+    fn lock_unsigned(&mut self, id: u64) {
+        let ops = self.unsigned_txs[&id]
+            .tx
+            .input
+            .iter()
+            .map(|input| input.previous_output)
+            .collect();
+        let lock_id = self.next_lock_id.clone();
+        self.locked_coins.lock_group(lock_id.clone(), LockGroup(ops));
+        self.next_lock_id.incr();
+        self.unsigned_locks.insert(id, lock_id);
+    }
+
+    fn sign_tx(&self, tx: &mut Transaction) -> Result<(), WalletError> {
+        self.master_key()?;
+        for i in 0..tx.input.len() {
+            let op = tx.input[i].previous_output;
+            let timelocked = self.state.coins().timelocked_utxos.get(&op).cloned();
+            if let Some(utxo) = timelocked {
+                let mut sk = self.timelock_sk(utxo.key_index);
+                timelock::sign_input(tx, i, &sk, &utxo.timelock, utxo.value).unwrap();
+                wipe_private_key(&mut sk);
+                continue;
+            }
+            let utxo = self.state.coins().op_to_utxo[&op].clone();
+            let keys = self.state.keys();
+            let mut sk = keys.account((utxo.account_index as usize).into()).get_sk(&utxo.key_path);
+            sign_input(tx, i, &sk, &utxo.addr_type, utxo.value);
+            wipe_private_key(&mut sk);
+        }
+        Ok(())
+    }
+
+    // the keys and coins of a wallet of another seed would mix with the recovered ones,
+    // its state is wiped with `force_overwrite`, the recovery fails otherwise
+    fn recover(
+        db: &mut DB,
+        mnemonic: Mnemonic,
+        decrypt_cfg: &DecryptConfig,
+        network: Network,
+        force_overwrite: bool,
+    ) -> Result<(ExtendedPrivKey, Mnemonic), WalletError> {
+        let master_key = KeyFactory::recover_from_mnemonic(&mnemonic, network, &decrypt_cfg.salt)?;
+        let fingerprint = master_key.fingerprint(&Secp256k1::new());
+        match WalletLibrary::stored_fingerprint(db, decrypt_cfg, network) {
+            Some(ref stored) if stored[..] != fingerprint[..] => {
+                if !force_overwrite {
+                    return Err(WalletError::DatabaseHoldsOtherWallet);
+                }
+                db.clear();
+            }
+            _ => (),
+        }
+        let encrypted = mnemonic.restore(&decrypt_cfg.passphrase)?;
+        db.put_bip39_randomness(&encrypted);
+        Ok((master_key, mnemonic))
+    }
+
+    // fingerprint of the master key of the wallet in `db`, `None` if it holds none.
+    // A database written before the fingerprint was stored tells it by its seed,
+    // one `decrypt_cfg` doesn't open counts as another wallet
+    fn stored_fingerprint(
+        db: &DB,
+        decrypt_cfg: &DecryptConfig,
+        network: Network,
+    ) -> Option<Vec<u8>> {
+        if let Some(fingerprint) = db.get_master_fingerprint() {
+            return Some(fingerprint);
+        }
+        if let Some(keys) = db.get_watch_only_keys() {
+            return Some(keys.master_fingerprint().map_or(Vec::new(), |f| f[..].to_vec()));
+        }
+        let randomness = db.get_bip39_randomness()?;
+        let decrypted =
+            KeyFactory::decrypt(&randomness, network, &decrypt_cfg.passphrase, &decrypt_cfg.salt);
+        Some(decrypted.map_or(Vec::new(), |(master_key, _)| {
+            master_key.fingerprint(&Secp256k1::new())[..].to_vec()
+        }))
+    }
+
+    /// get a copy of the master private key, a watch-only wallet has none
+    pub fn master_private(&self) -> Option<ExtendedPrivKey> {
+        self.state.keys().master_key
+    }
+
+    /// get a copy of the master public key, a watch-only wallet has none
+    pub fn master_public(&self) -> Option<ExtendedPubKey> {
+        self.master_private().map(|key| KeyFactory::extended_public_from_private(&key))
+    }
+
+    // the master key, a watch-only wallet has none to sign with
+    fn master_key(&self) -> Result<ExtendedPrivKey, WalletError> {
+        if self.is_locked() {
+            return Err(WalletError::Locked);
+        }
+        self.master_private().ok_or(WalletError::WatchOnly)
+    }
+
+    //    pub fn mnemonic (&self) -> String {
+    //        self.mnemonic.to_string()
+    //    }
+    //
+    //    pub fn encrypted (&self) -> Vec<u8> {
+    //        self.encrypted.clone()
+    //    }
+
+    /// the account key at m/purpose'/coin_type'/account' of `scheme`
+    pub fn derive_account_key(
+        master_key: ExtendedPrivKey,
+        scheme: &DerivationScheme,
+    ) -> Result<ExtendedPrivKey, WalletError> {
+        let mut key = master_key;
+        for child in scheme.path() {
+            key = KeyFactory::private_child(&key, child)?;
+        }
+        Ok(key)
+    }
+
+    /// the public keys of `master_key` a watch-only wallet follows, with the accounts at
+    /// `schemes` and at the default paths of `mode` for the address types missing from them
+    pub fn watch_only_keys(
+        master_key: &ExtendedPrivKey,
+        network: Network,
+        mode: DerivationMode,
+        schemes: &[(AccountAddressType, DerivationScheme)],
+    ) -> Result<WatchOnlyKeys, WalletError> {
+        let ctx = Secp256k1::new();
+        let mut accounts = Vec::new();
+        for addr_type in &[
+            AccountAddressType::P2PKH,
+            AccountAddressType::P2SHWH,
+            AccountAddressType::P2WKH,
+        ] {
+            let scheme = schemes
+                .iter()
+                .find(|(t, _)| t == addr_type)
+                .map(|(_, scheme)| *scheme)
+                .unwrap_or_else(|| DerivationScheme::default_for(addr_type, network, mode));
+            let mut key = WalletLibrary::derive_account_key(*master_key, &scheme)?;
+            accounts.push(WatchOnlyAccount {
+                addr_type: addr_type.clone(),
+                scheme,
+                xpub: ExtendedPubKey::from_private(&ctx, &key).to_string(),
+            });
+            wipe_extended_private_key(&mut key);
+        }
+
+        let mut payment_code_key =
+            master_key.derive_priv(&ctx, &WalletLibrary::payment_code_path(network))?;
+        let payment_code =
+            PaymentCode::from_xpub(&KeyFactory::extended_public_from_private(&payment_code_key));
+        wipe_extended_private_key(&mut payment_code_key);
+
+        let mut scan_sk = WalletLibrary::silent_payment_sk(master_key, network, 1);
+        let mut spend_sk = WalletLibrary::silent_payment_sk(master_key, network, 0);
+        let keys = WatchOnlyKeys {
+            master_fingerprint: hex::encode(&master_key.fingerprint(&ctx)[..]),
+            accounts,
+            payment_code: payment_code.to_string(),
+            silent_payment_scan_key: scan_sk.to_wif(),
+            silent_payment_spend_pk: PublicKey::from_private_key(&ctx, &spend_sk).to_string(),
+        };
+        wipe_private_key(&mut scan_sk);
+        wipe_private_key(&mut spend_sk);
+        Ok(keys)
+    }
+
+    fn new_account(
+        master_key: ExtendedPrivKey,
+        scheme: DerivationScheme,
+        address_type: AccountAddressType,
+        network: Network,
+        db: Arc<RwLock<DB>>,
+    ) -> Account {
+        let key = WalletLibrary::derive_account_key(master_key, &scheme).unwrap();
+        let fingerprint = master_key.fingerprint(&Secp256k1::new());
+
+        Account::new(key, address_type, network, scheme, fingerprint, Arc::clone(&db))
+    }
+}
+
+// sign the input `i` spending an output of type `addr_type` which pays to the key of `sk`
+pub(crate) fn sign_input(
+    tx: &mut Transaction,
+    i: usize,
+    sk: &PrivateKey,
+    addr_type: &AccountAddressType,
+    value: u64,
+) {
+    // only SIGHASH_SINGLE can lack something to sign
+    sign_input_with(tx, i, sk, addr_type, value, SigHashType::All).unwrap();
+}
+
+// `sign_input` with a signature of `sighash_type`
+pub(crate) fn sign_input_with(
+    tx: &mut Transaction,
+    i: usize,
+    sk: &PrivateKey,
+    addr_type: &AccountAddressType,
+    value: u64,
+    sighash_type: SigHashType,
+) -> Result<(), Box<dyn Error>> {
+    let ctx = Secp256k1::new();
+    let pk = PublicKey::from_private_key(&ctx, sk);
+    // TODO(evg): do not hardcode bitcoin's network param
+    // the script of P2PKH is the script code of P2WPKH too
+    let pk_script = Address::p2pkh(&pk, Network::Bitcoin).script_pubkey();
+    let segwit = *addr_type != AccountAddressType::P2PKH;
+    let hash = sighash::signature_hash(tx, i, &pk_script, value, sighash_type, segwit)?;
+    let signature = ctx.sign(&Message::from_slice(&hash[..]).unwrap(), &sk.key);
+
+    let mut serialized_sig = signature.serialize_der().to_vec();
+    serialized_sig.push(sighash_type.as_u32() as u8);
+
+    match addr_type {
+        AccountAddressType::P2PKH => {
+            let script = Builder::new()
+                .push_slice(serialized_sig.as_slice())
+                .push_slice(&pk.key.serialize())
+                .into_script();
+            tx.input[i].script_sig = script;
+        }
+        AccountAddressType::P2SHWH => {
+            let pk_script_p2wpkh = Address::p2wpkh(&pk, Network::Bitcoin).script_pubkey();
+
+            tx.input[i].witness.push(serialized_sig);
+            tx.input[i].witness.push(pk.key.serialize().to_vec());
+
+            tx.input[i].script_sig = Builder::new()
+                .push_slice(pk_script_p2wpkh.as_bytes())
+                .into_script();
+        }
+        AccountAddressType::P2WKH => {
+            tx.input[i].witness.push(serialized_sig);
+            tx.input[i].witness.push(pk.key.serialize().to_vec());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn now() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+// `SystemTime::now` panics in the browser
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn now() -> u64 {
+    (js_sys::Date::now() / 1000.0) as u64
+}
+
+/// the script type of a coin a transaction spends, by which the size of its input is estimated
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputType {
+    P2PKH,
+    P2SHWH,
+    P2WKH,
+    /// spent by the key path, such as a silent payment
+    P2TR,
+}
+
+impl<'a> From<&'a AccountAddressType> for InputType {
+    fn from(addr_type: &'a AccountAddressType) -> InputType {
+        match addr_type {
+            AccountAddressType::P2PKH => InputType::P2PKH,
+            AccountAddressType::P2SHWH => InputType::P2SHWH,
+            AccountAddressType::P2WKH => InputType::P2WKH,
+        }
+    }
+}
+
+impl InputType {
+    // bytes of the script sig and of the witness of a signed input,
+    // signatures are counted at their maximal size
+    fn signed_size(self) -> (usize, usize) {
+        // DER signature with the sighash byte and a compressed public key, each with a push opcode
+        const SIG_PUSH: usize = 1 + 72;
+        const PK_PUSH: usize = 1 + 33;
+        // Schnorr signature with a sighash byte other than SIGHASH_DEFAULT
+        const SCHNORR_SIG_PUSH: usize = 1 + 65;
+
+        match self {
+            // the empty witness of a legacy input in a segwit transaction
+            InputType::P2PKH => (SIG_PUSH + PK_PUSH, 1),
+            // push of the P2WPKH redeem script
+            InputType::P2SHWH => (1 + 22, 1 + SIG_PUSH + PK_PUSH),
+            InputType::P2WKH => (0, 1 + SIG_PUSH + PK_PUSH),
+            InputType::P2TR => (0, 1 + SCHNORR_SIG_PUSH),
+        }
+    }
+
+    /// weight of a signed input, the witness count of a legacy input included,
+    /// which it only has in a segwit transaction
+    pub fn weight(self) -> usize {
+        let (script_sig_size, witness_size) = self.signed_size();
+        (TXIN_SIZE + script_sig_size) * 4 + witness_size
+    }
+
+    /// the weight in virtual bytes rounded up, the vsizes of the inputs and of the rest
+    /// of a transaction add up to at least its vsize
+    pub fn vsize(self) -> usize {
+        (self.weight() + 3) / 4
+    }
+}
+
+// previous outpoint, script sig length and sequence of an input
+const TXIN_SIZE: usize = 32 + 4 + 1 + 4;
+
+/// estimated size of a signed transaction
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TxSize {
+    pub weight: usize,
+    /// the weight divided by 4 and rounded up, fee rates are per virtual byte
+    pub vsize: usize,
+}
+
+impl TxSize {
+    // the size of a transaction of `base_size` bytes without its witness
+    // and `witness_size` bytes of witness, marker and flag excluded
+    fn new(base_size: usize, witness_size: usize, is_segwit: bool) -> TxSize {
+        // segwit marker and flag
+        let weight = if is_segwit { base_size * 4 + witness_size + 2 } else { base_size * 4 };
+        TxSize {
+            weight,
+            vsize: (weight + 3) / 4,
+        }
+    }
+}
+
+// the bytes of a Bitcoin varint of `n`
+fn varint_len(n: usize) -> usize {
+    match n {
+        0..=0xfc => 1,
+        0xfd..=0xffff => 3,
+        0x10000..=0xffff_ffff => 5,
+        _ => 9,
+    }
+}
+
+/// the size of a signed transaction spending `inputs`, the count of the coins of each type,
+/// and paying to `n_outputs` outputs. The outputs are counted at the size of a P2WSH or P2TR
+/// one, the longest standard output, so a fee quoted by it is enough for any destination.
+pub fn estimate_tx_size(inputs: &[(InputType, usize)], n_outputs: usize) -> TxSize {
+    // value, script length and a 34 byte script
+    const OUTPUT_SIZE: usize = 8 + 1 + 34;
+
+    let n_inputs: usize = inputs.iter().map(|&(_, n)| n).sum();
+    // version and lock time
+    let mut base_size = 4 + varint_len(n_inputs) + varint_len(n_outputs) + 4;
+    base_size += n_outputs * OUTPUT_SIZE;
+    let mut witness_size = 0;
+    for &(input_type, n) in inputs {
+        let (script_sig_size, input_witness_size) = input_type.signed_size();
+        base_size += n * (TXIN_SIZE + script_sig_size);
+        witness_size += n * input_witness_size;
+    }
+    let is_segwit = inputs.iter().any(|&(input_type, n)| n > 0 && input_type != InputType::P2PKH);
+    TxSize::new(base_size, witness_size, is_segwit)
+}
+
+// the inputs of `tx`, not signed yet, spend coins of `input_types`
+fn estimate_vsize(tx: &Transaction, input_types: &[AccountAddressType]) -> usize {
+    let input_sizes: Vec<(usize, usize)> =
+        input_types.iter().map(|addr_type| InputType::from(addr_type).signed_size()).collect();
+    signed_vsize(tx, &input_sizes)
+}
+
+// vsize of `tx` once its inputs, not signed yet, have script sigs and witnesses of the sizes
+// of `input_sizes`. The empty witness of a legacy input counts as 1 byte
+fn signed_vsize(tx: &Transaction, input_sizes: &[(usize, usize)]) -> usize {
+    let mut base_size = serialize(tx).len();
+    let mut witness_size = 0;
+    for &(script_sig_size, input_witness_size) in input_sizes {
+        base_size += script_sig_size;
+        witness_size += input_witness_size;
+    }
+    let is_segwit = input_sizes.iter().any(|&(_, input_witness_size)| input_witness_size > 1);
+    TxSize::new(base_size, witness_size, is_segwit).vsize
+}
+
+// what the coins paying `amt` to a script have to be worth once they paid for their inputs
+struct PaymentTarget {
+    // the amount and the fee of the rest of the transaction, a change output included
+    with_change: i64,
+    // the same without the change output
+    changeless: i64,
+    // the fee of the change output and of the input spending it later, coins worth less than
+    // it above `changeless` are better off without change
+    cost_of_change: i64,
+}
+
+impl PaymentTarget {
+    fn new(
+        amt: u64,
+        script_pubkey: &Script,
+        change_type: InputType,
+        fee_rate: u64,
+    ) -> PaymentTarget {
+        let mut tx = Transaction {
+            version: TX_VERSION,
+            lock_time: 0,
+            input: Vec::new(),
+            output: vec![TxOut {
+                value: amt,
+                script_pubkey: script_pubkey.clone(),
+            }],
+        };
+        // the outputs, version, lock time, the count of the inputs and the segwit marker
+        let target = |tx: &Transaction| {
+            let base_vsize = (serialize(tx).len() * 4 + 2 + 3) / 4;
+            (amt + base_vsize as u64 * fee_rate) as i64
+        };
+        let changeless = target(&tx);
+        // as long as the longest change script
+        tx.output.push(TxOut {
+            value: 0,
+            script_pubkey: Script::from(vec![0u8; 25]),
+        });
+        let with_change = target(&tx);
+        let spend_fee = (change_type.vsize() as u64 * fee_rate) as i64;
+        PaymentTarget {
+            with_change,
+            changeless,
+            cost_of_change: with_change - changeless + spend_fee,
+        }
+    }
+}
+
+// coins of the account which pays `target` with the fewest of them, the largest first,
+// so that a transaction doesn't link coins of different accounts or address types.
+// A coin paying `target` without change worth an output goes first. The coins of a group,
+// see `WalletLibrary::group_reused`, are taken together and count with their effective value
+fn select_private_coins(
+    groups: Vec<Vec<Utxo>>,
+    target: &PaymentTarget,
+    fee_rate: u64,
+) -> Vec<OutPoint> {
+    let value = |group: &Vec<Utxo>| effective_value(group, fee_rate);
+    let changeless = groups
+        .iter()
+        .filter(|group| {
+            value(group) >= target.changeless
+                && value(group) < target.changeless + target.cost_of_change
+        })
+        .min_by_key(|group| (group.len(), value(group)));
+    if let Some(group) = changeless {
+        return group.iter().map(|utxo| utxo.out_point).collect();
+    }
+
+    let mut best: Option<Vec<OutPoint>> = None;
+    for groups in by_account(groups).values_mut() {
+        groups.sort_by(|a, b| value(b).cmp(&value(a)));
+        let mut total = 0;
+        let mut subset = Vec::new();
+        for group in groups.iter() {
+            total += value(group);
+            subset.extend(group.iter().map(|utxo| utxo.out_point));
+            if total >= target.with_change {
+                break;
+            }
+        }
+        let fewer = best.as_ref().map_or(true, |best| subset.len() < best.len());
+        if total >= target.with_change && fewer {
+            best = Some(subset);
+        }
+    }
+    best.unwrap_or_default()
+}
 
-            let input = TxIn {
-                previous_output: *op,
-                script_sig: Script::new(),
-                sequence: 0xFFFFFFFF,
-                witness: Vec::new(),
-            };
-            tx.input.push(input);
+// the groups of coins by the account of their first coin
+fn by_account(groups: Vec<Vec<Utxo>>) -> BTreeMap<u32, Vec<Vec<Utxo>>> {
+    let mut accounts: BTreeMap<u32, Vec<Vec<Utxo>>> = BTreeMap::new();
+    for group in groups {
+        accounts.entry(group[0].account_index).or_insert_with(Vec::new).push(group);
+    }
+    accounts
+}
+
+// groups of coins paying `target` without change, worth up to `tolerance` more, the least of
+// them left over, see `ExactMatch`. The groups count with their effective value at `fee_rate`
+fn select_exact(
+    groups: &[Vec<Utxo>],
+    fee_rate: u64,
+    target: &PaymentTarget,
+    tolerance: u64,
+) -> Option<Vec<OutPoint>> {
+    let mut groups: Vec<(u64, &Vec<Utxo>)> = groups
+        .iter()
+        .map(|group| (effective_value(group, fee_rate), group))
+        .filter(|&(value, _)| value > 0)
+        .map(|(value, group)| (value as u64, group))
+        .collect();
+    groups.sort_by(|a, b| b.0.cmp(&a.0));
+    let values: Vec<u64> = groups.iter().map(|(value, _)| *value).collect();
+    let mut search = ExactMatch::new(values, target.changeless as u64, tolerance);
+    search.search(0, 0, &mut Vec::new());
+    let (_, selected) = search.best?;
+    Some(
+        selected
+            .into_iter()
+            .flat_map(|i| groups[i].1.iter().map(|utxo| utxo.out_point))
+            .collect(),
+    )
+}
+
+// branch and bound over values sorted from the largest down: each value is taken or left
+// out in turn, branches exceeding the tolerance or unable to reach the target any more
+// are cut, and the search gives up after `EXACT_MATCH_TRIES` branches
+struct ExactMatch {
+    values: Vec<u64>,
+    // what the values from an index on are worth together
+    remaining: Vec<u64>,
+    target: u64,
+    tolerance: u64,
+    tries: usize,
+    // what the best selection leaves over, and the indices of its values
+    best: Option<(u64, Vec<usize>)>,
+}
+
+impl ExactMatch {
+    fn new(values: Vec<u64>, target: u64, tolerance: u64) -> ExactMatch {
+        let mut remaining = vec![0; values.len() + 1];
+        for i in (0..values.len()).rev() {
+            remaining[i] = remaining[i + 1] + values[i];
         }
+        ExactMatch {
+            values,
+            remaining,
+            target,
+            tolerance,
+            tries: 0,
+            best: None,
+        }
+    }
 
-        if total < (amt + 10_000) {
-            return Err(From::from("something went wrong..."));
+    fn search(&mut self, i: usize, total: u64, selected: &mut Vec<usize>) {
+        if self.tries >= EXACT_MATCH_TRIES || total > self.target + self.tolerance {
+            return;
+        }
+        self.tries += 1;
+        if total >= self.target {
+            let excess = total - self.target;
+            if self.best.as_ref().map_or(true, |(best, _)| excess < *best) {
+                self.best = Some((excess, selected.clone()));
+            }
+            return;
         }
+        if i == self.values.len() || total + self.remaining[i] < self.target {
+            return;
+        }
+        selected.push(i);
+        self.search(i + 1, total + self.values[i], selected);
+        selected.pop();
+        if self.best.as_ref().map_or(false, |(excess, _)| *excess == 0) {
+            return;
+        }
+        self.search(i + 1, total, selected);
+    }
+}
 
-        // dest output
-        let output = TxOut {
-            value: amt,
-            script_pubkey: addr.script_pubkey(),
-        };
-        tx.output.push(output);
+// what the input spending `utxo` adds to the fee at `fee_rate`
+fn input_fee(utxo: &Utxo, fee_rate: u64) -> u64 {
+    InputType::from(&utxo.addr_type).vsize() as u64 * fee_rate
+}
 
-        let change_addr = {
-            let change_addr = self
-                .get_account_mut(AccountAddressType::P2WKH)
-                .new_change_address()
-                .unwrap();
-            Address::from_str(&change_addr).unwrap()
-        };
+// what the input spending the timelocked `utxo` adds to the fee at `fee_rate`
+fn timelocked_input_fee(utxo: &TimelockedUtxo, fee_rate: u64) -> u64 {
+    let weight = TXIN_SIZE * 4 + utxo.timelock.witness_size();
+    ((weight + 3) / 4) as u64 * fee_rate
+}
 
-        let change_output = TxOut {
-            value: total - amt - 10_000, // subtract fee
-            script_pubkey: change_addr.script_pubkey(),
-        };
-        tx.output.push(change_output);
+// what the coins of `group` are worth once they paid for their inputs at `fee_rate`
+fn effective_value(group: &[Utxo], fee_rate: u64) -> i64 {
+    group.iter().map(|utxo| utxo.value as i64 - input_fee(utxo, fee_rate) as i64).sum()
+}
 
-        // sign tx
-        for i in 0..ops.len() {
-            let op = &ops[i];
-            let utxo = self.op_to_utxo.get(op).unwrap();
-
-            let account = self.get_account((utxo.account_index as usize).into());
-
-            let ctx = Secp256k1::new();
-            let sk = account.get_sk(&utxo.key_path);
-            let pk = PublicKey::from_private_key(&ctx, &sk);
-            // TODO(evg): do not hardcode bitcoin's network param
-            match utxo.addr_type {
-                AccountAddressType::P2PKH => {
-                    let pk_script = Address::p2pkh(&pk, Network::Bitcoin).script_pubkey();
-
-                    // TODO(evg): use SigHashType enum
-                    let hash = tx.signature_hash(i, &pk_script, 0x1);
-                    let signature = ctx.sign(&Message::from_slice(&hash[..]).unwrap(), &sk.key);
-
-                    let mut serialized_sig = signature.serialize_der().to_vec();
-                    serialized_sig.push(0x1);
-
-                    let script = Builder::new()
-                        .push_slice(serialized_sig.as_slice())
-                        .push_slice(&pk.key.serialize())
-                        .into_script();
-                    tx.input[i].script_sig = script;
-                }
-                AccountAddressType::P2SHWH => {
-                    let pk_script = Address::p2pkh(&pk, Network::Bitcoin).script_pubkey();
-                    let pk_script_p2wpkh = Address::p2wpkh(&pk, Network::Bitcoin).script_pubkey();
-
-                    let tx_sig_hash = bip143::SighashComponents::new(&tx).sighash_all(
-                        &tx.input[i],
-                        &pk_script,
-                        utxo.value,
-                    );
+// the first of `groups` whose effective values cover `target`, along with what they are worth
+// together, all of them if they fall short
+fn take_until(groups: Vec<Vec<Utxo>>, fee_rate: u64, target: i64) -> (Vec<Utxo>, i64) {
+    let mut effective_total = 0;
+    let mut selected = Vec::new();
+    for group in groups {
+        if effective_total >= target {
+            break;
+        }
+        effective_total += effective_value(&group, fee_rate);
+        selected.extend(group);
+    }
+    (selected, effective_total)
+}
+
+fn address_from_pk(pk: &PublicKey, addr_type: &AccountAddressType, network: Network) -> Address {
+    match addr_type {
+        AccountAddressType::P2PKH => Address::p2pkh(pk, network),
+        AccountAddressType::P2SHWH => Address::p2shwpkh(pk, network),
+        AccountAddressType::P2WKH => Address::p2wpkh(pk, network),
+    }
+}
 
-                    let signature =
-                        ctx.sign(&Message::from_slice(&tx_sig_hash[..]).unwrap(), &sk.key);
+#[cfg(test)]
+mod test {
+    use bitcoin::{Transaction, TxOut, OutPoint, Script, Address, network::constants::Network};
 
-                    let mut serialized_sig = signature.serialize_der().to_vec();
-                    serialized_sig.push(0x1);
+    use std::{str::FromStr, sync::{mpsc, Arc, Mutex}, thread};
 
-                    tx.input[i].witness.push(serialized_sig);
-                    tx.input[i].witness.push(pk.key.serialize().to_vec());
+    use crate::default::WalletWithTrustedFullNode;
+    use crate::memory::{MemoryChain, test::{new_wallet, payment}};
+    use crate::mnemonic::Mnemonic;
+    use crate::address;
+    use crate::error::WalletError;
+    use crate::account::AccountAddressType;
+    use crate::interface::{Wallet, WalletLibraryInterface, BackendKind};
+    use crate::timelock::Timelock;
+    use super::{
+        WalletLibrary, WalletConfig, WalletConfigBuilder, WalletLibraryMode, KeyGenConfig,
+        InputFilter, IdempotentSend, SendOutcome, InputType, TxSize, StateIssue, DecryptConfig,
+        SendOptions, estimate_tx_size, DEFAULT_ENTROPY, DEFAULT_PASSPHRASE, DEFAULT_SALT,
+        DEFAULT_FEE_RATE, RBF_SEQUENCE, TX_VERSION,
+    };
 
-                    tx.input[i].script_sig = Builder::new()
-                        .push_slice(pk_script_p2wpkh.as_bytes())
-                        .into_script();
-                }
-                AccountAddressType::P2WKH => {
-                    let pk_script = Address::p2pkh(&pk, Network::Bitcoin).script_pubkey();
+    #[test]
+    fn unconfirmed_change() {
+        let dest_addr = "bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49".to_owned();
+        for &spend in &[false, true] {
+            let mut config = WalletConfig::in_memory();
+            config.set_spend_unconfirmed_change(spend);
+            let (mut wallet, chain) = new_wallet(config);
+            let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+            chain.push_block(Vec::new());
+            chain.push_block(vec![payment(&addr, 100_000_000)]);
+            wallet.sync_with_tip().unwrap();
 
-                    let tx_sig_hash = bip143::SighashComponents::new(&tx).sighash_all(
-                        &tx.input[i],
-                        &pk_script,
-                        utxo.value,
-                    );
+            let first = wallet
+                .send_coins(dest_addr.clone(), 30_000_000, true, SendOptions::default())
+                .unwrap()
+                .sent()
+                .unwrap();
+            wallet.sync_with_tip().unwrap();
+            let second = wallet.send_coins(
+                dest_addr.clone(),
+                50_000_000,
+                true,
+                SendOptions::default(),
+            );
+            if !spend {
+                // the only coin left is the unconfirmed change
+                assert!(second.is_err());
+                continue;
+            }
+            let second = second.unwrap().sent().unwrap();
+            assert_eq!(second.selected_inputs, vec![first.change_outpoint.unwrap()]);
 
-                    let signature =
-                        ctx.sign(&Message::from_slice(&tx_sig_hash[..]).unwrap(), &sk.key);
+            chain.mine();
+            wallet.sync_with_tip().unwrap();
+            let utxos = wallet.wallet_lib().get_utxo_list();
+            assert_eq!(utxos.len(), 1);
+            assert_eq!(utxos[0].out_point, second.change_outpoint.unwrap());
+        }
+    }
 
-                    let mut serialized_sig = signature.serialize_der().to_vec();
-                    serialized_sig.push(0x1);
+    #[test]
+    fn verify_state() {
+        let (mut wallet, chain) = new_wallet(WalletConfig::in_memory());
+        let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        wallet.wallet_lib_mut().new_address(AccountAddressType::P2PKH).unwrap();
+        chain.push_block(Vec::new());
+        chain.push_block(vec![payment(&addr, 100_000_000)]);
+        wallet.sync_with_tip().unwrap();
+        assert_eq!(wallet.wallet_lib().verify_state(), Vec::new());
 
-                    tx.input[i].witness.push(serialized_sig);
-                    tx.input[i].witness.push(pk.key.serialize().to_vec());
-                }
-            }
+        // the coins locked for the payment are spent once it is mined, nothing unlocks them
+        let dest_addr = "bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49".to_owned();
+        let sent = wallet
+            .send_coins(
+                dest_addr,
+                50_000_000,
+                true,
+                SendOptions { lock_coins: true, ..SendOptions::default() },
+            )
+            .unwrap()
+            .sent()
+            .unwrap();
+        assert_eq!(wallet.wallet_lib().verify_state(), Vec::new());
+        chain.mine();
+        wallet.sync_with_tip().unwrap();
+        let issues = wallet.wallet_lib().verify_state();
+        assert_eq!(issues, vec![StateIssue::OrphanedLockGroup(sent.lock_id)]);
+        assert!(issues[0].is_repairable());
+
+        assert_eq!(wallet.wallet_lib_mut().repair_state().unwrap(), Vec::new());
+        assert_eq!(wallet.wallet_lib().verify_state(), Vec::new());
+        let audit_log = wallet.wallet_lib().get_audit_log(0, 100);
+        assert_eq!(audit_log.last().unwrap().operation, "repair_state");
+    }
+
+    #[test]
+    fn recover_into_other_wallet() {
+        let db_path = std::env::temp_dir().join("rust_wallet_recover_into_other");
+        let _ = std::fs::remove_dir_all(&db_path);
+        let wc = |force_overwrite: bool| {
+            let mut wc = WalletConfig::with_db_path(db_path.to_str().unwrap().to_owned());
+            wc.set_force_overwrite(force_overwrite);
+            wc
+        };
+        let recover = |mnemonic: &Mnemonic| {
+            let mnemonic = Mnemonic::from(&mnemonic.to_string()).unwrap();
+            WalletLibraryMode::RecoverFromMnemonic(mnemonic, DecryptConfig::default())
+        };
+        let (mut wallet, mnemonic) =
+            WalletLibrary::new(wc(false), WalletLibraryMode::Create(KeyGenConfig::default()))
+                .unwrap();
+        let addr = wallet.new_address(AccountAddressType::P2WKH).unwrap();
+        drop(wallet);
+
+        // the same seed recovers into its own database and keeps its state
+        let (wallet, _) = WalletLibrary::new(wc(false), recover(&mnemonic)).unwrap();
+        assert_eq!(wallet.get_address_list()[0].address, addr);
+        drop(wallet);
+
+        let (_, other) = WalletLibrary::new(
+            WalletConfig::in_memory(),
+            WalletLibraryMode::Create(KeyGenConfig::debug()),
+        )
+        .unwrap();
+        match WalletLibrary::new(wc(false), recover(&other)) {
+            Err(WalletError::DatabaseHoldsOtherWallet) => (),
+            _ => panic!("recovered into the database of another wallet"),
         }
+        let (wallet, _) = WalletLibrary::new(wc(true), recover(&other)).unwrap();
+        assert!(wallet.get_address_list().is_empty());
+        drop(wallet);
+        std::fs::remove_dir_all(&db_path).unwrap();
+    }
 
-        Ok(tx)
+    #[test]
+    fn network_of_database() {
+        let db_path = std::env::temp_dir().join("rust_wallet_network_of_database");
+        let _ = std::fs::remove_dir_all(&db_path);
+        let wc = |network: Network| {
+            WalletConfigBuilder::new()
+                .network(network)
+                .db_path(db_path.to_str().unwrap().to_owned())
+                .finalize()
+        };
+        let decrypt = || WalletLibraryMode::Decrypt(DecryptConfig::default());
+        let (wallet, _) = WalletWithTrustedFullNode::new(
+            wc(Network::Regtest),
+            MemoryChain::new(Network::Regtest),
+            WalletLibraryMode::Create(KeyGenConfig::default()),
+        )
+        .unwrap();
+        assert_eq!(wallet.wallet_lib().backend(), Some(BackendKind::FullNode));
+        drop(wallet);
+
+        match WalletLibrary::new(wc(Network::Testnet), decrypt()) {
+            Err(WalletError::DatabaseNetworkMismatch(ref network)) => {
+                assert_eq!(network, "regtest")
+            }
+            _ => panic!("a regtest wallet was opened on testnet"),
+        }
+        let (wallet, _) = WalletLibrary::new(wc(Network::Regtest), decrypt()).unwrap();
+        assert_eq!(wallet.backend(), Some(BackendKind::FullNode));
+        drop(wallet);
+        std::fs::remove_dir_all(&db_path).unwrap();
     }
 
-    fn get_account_mut(&mut self, address_type: AccountAddressType) -> &mut Account {
-        match address_type {
-            AccountAddressType::P2PKH => &mut self.p2pkh_account,
-            AccountAddressType::P2SHWH => &mut self.p2shwh_account,
-            AccountAddressType::P2WKH => &mut self.p2wkh_account,
+    #[test]
+    fn hidden_wallet() {
+        let db_path = std::env::temp_dir().join("rust_wallet_hidden");
+        // the database of the seed and the ones of its hidden wallets
+        let paths = || -> Vec<_> {
+            std::fs::read_dir(std::env::temp_dir())
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .filter(|path| path.to_str().unwrap().starts_with(db_path.to_str().unwrap()))
+                .collect()
+        };
+        for path in paths() {
+            std::fs::remove_dir_all(&path).unwrap();
+        }
+        let wc = || WalletConfig::with_db_path(db_path.to_str().unwrap().to_owned());
+        let open = |salt: &str| {
+            let decrypt_cfg = DecryptConfig::new(DEFAULT_PASSPHRASE.to_owned(), salt.to_owned());
+            WalletLibrary::new(wc(), WalletLibraryMode::Decrypt(decrypt_cfg)).unwrap().0
+        };
+        let (mut wallet, _) =
+            WalletLibrary::new(wc(), WalletLibraryMode::Create(KeyGenConfig::default())).unwrap();
+        let addr = wallet.new_address(AccountAddressType::P2WKH).unwrap();
+        drop(wallet);
+
+        // another salt opens a wallet of its own, the one of the seed doesn't see it
+        let mut hidden = open("duress");
+        assert!(hidden.get_address_list().is_empty());
+        let hidden_addr = hidden.new_address(AccountAddressType::P2WKH).unwrap();
+        assert_ne!(hidden_addr, addr);
+        drop(hidden);
+
+        let wallet = open(DEFAULT_SALT);
+        let addrs: Vec<_> = wallet.get_address_list().into_iter().map(|a| a.address).collect();
+        assert_eq!(addrs, vec![addr]);
+        drop(wallet);
+        assert_eq!(open("duress").get_address_list()[0].address, hidden_addr);
+
+        assert_eq!(paths().len(), 2);
+        for path in paths() {
+            std::fs::remove_dir_all(&path).unwrap();
         }
     }
 
-    fn get_last_seen_block_height_from_memory(&self) -> usize {
-        self.last_seen_block_height
+    #[test]
+    fn auto_mode() {
+        let db_path = std::env::temp_dir().join("rust_wallet_auto_mode");
+        let _ = std::fs::remove_dir_all(&db_path);
+        let open = |passphrase: &str| {
+            let wc = WalletConfig::with_db_path(db_path.to_str().unwrap().to_owned());
+            let key_gen_cfg =
+                KeyGenConfig::new(DEFAULT_ENTROPY, passphrase.to_owned(), String::new());
+            WalletLibrary::new(wc, WalletLibraryMode::Auto(key_gen_cfg))
+        };
+        let (mut wallet, mnemonic) = open("secret").unwrap();
+        assert!(!mnemonic.is_empty());
+        let addr = wallet.new_address(AccountAddressType::P2WKH).unwrap();
+        drop(wallet);
+
+        // the second start opens the wallet, its mnemonic was shown already
+        let (wallet, mnemonic) = open("secret").unwrap();
+        assert!(mnemonic.is_empty());
+        assert_eq!(wallet.get_address_list()[0].address, addr);
+        drop(wallet);
+        assert!(open("typo").is_err());
+        std::fs::remove_dir_all(&db_path).unwrap();
     }
 
-    fn update_last_seen_block_height_in_memory(&mut self, block_height: usize) {
-        self.last_seen_block_height = block_height;
+    #[test]
+    fn idempotent_send() {
+        let (mut wallet, chain) = new_wallet(WalletConfig::in_memory());
+        let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        chain.push_block(Vec::new());
+        chain.push_block(vec![payment(&addr, 100_000_000)]);
+        wallet.sync_with_tip().unwrap();
+
+        let dest_addr = "bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49".to_owned();
+        assert!(wallet.wallet_lib().get_idempotent_send("payout-1").is_none());
+        let sent = wallet
+            .send_coins(dest_addr.clone(), 10_000_000, true, SendOptions::default())
+            .unwrap()
+            .sent()
+            .unwrap();
+        let txid = sent.tx.txid();
+        let send = IdempotentSend::new(
+            "payout-1".to_owned(),
+            dest_addr.clone(),
+            10_000_000,
+            SendOutcome::Sent(sent),
+        );
+        wallet.wallet_lib_mut().put_idempotent_send(send);
+        let send = wallet.wallet_lib().get_idempotent_send("payout-1").unwrap();
+        assert_eq!((send.dest_addr.as_str(), send.amt), (dest_addr.as_str(), 10_000_000));
+        match send.outcome {
+            SendOutcome::Sent(ref result) => assert_eq!(result.tx.txid(), txid),
+            _ => panic!("unexpected outcome {:?}", send.outcome),
+        }
+        assert!(wallet.wallet_lib().get_idempotent_send("payout-2").is_none());
     }
 
-    fn update_last_seen_block_height_in_db(&mut self, block_height: usize) {
-        self.db
-            .write()
+    #[test]
+    fn size_estimate() {
+        let legacy = estimate_tx_size(&[(InputType::P2PKH, 1)], 1);
+        assert_eq!(legacy, TxSize { weight: 804, vsize: 201 });
+        assert_eq!(estimate_tx_size(&[(InputType::P2TR, 1)], 1).vsize, 112);
+        let mixed = estimate_tx_size(&[(InputType::P2PKH, 1), (InputType::P2WKH, 1)], 2);
+        assert!(mixed.weight % 4 != 0 && mixed.vsize == (mixed.weight + 3) / 4);
+
+        let (mut wallet, chain) = new_wallet(WalletConfig::in_memory());
+        let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        chain.push_block(Vec::new());
+        chain.push_block(vec![payment(&addr, 100_000_000)]);
+        wallet.sync_with_tip().unwrap();
+        let dest_addr = "bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49".to_owned();
+        let tx = wallet
+            .send_coins(dest_addr, 50_000_000, true, SendOptions::default())
             .unwrap()
-            .put_last_seen_block_height(block_height as u32);
+            .sent()
+            .unwrap()
+            .tx;
+        // the P2WPKH outputs are 12 bytes shorter than the ones counted
+        let estimate = estimate_tx_size(&[(InputType::P2WKH, 1)], 2);
+        assert!(tx.get_weight() <= estimate.weight);
+        assert!(estimate.weight <= tx.get_weight() + 2 * 12 * 4 + 2);
     }
 
-    fn get_full_address_list(&self) -> Vec<String> {
-        [
-            self.p2pkh_account.btc_address_list.clone(),
-            self.p2shwh_account.btc_address_list.clone(),
-            self.p2wkh_account.btc_address_list.clone(),
-        ]
-        .concat()
+    #[test]
+    fn uneconomical_coins() {
+        let (mut wallet, chain) = new_wallet(WalletConfig::in_memory());
+        let small = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        let big = wallet.wallet_lib_mut().new_address(AccountAddressType::P2SHWH).unwrap();
+        chain.push_block(Vec::new());
+        chain.push_block(vec![payment(&small, 1_000), payment(&big, 100_000_000)]);
+        wallet.sync_with_tip().unwrap();
+
+        // the input of the small coin costs more than the coin at 20 satoshi per byte
+        assert!(InputType::P2WKH.vsize() as u64 * 20 > 1_000);
+        let (_, funding) = wallet
+            .wallet_lib_mut()
+            .fund_psbt_for_channel(&Script::from(vec![0x51]), 10_000_000, 20)
+            .unwrap();
+        assert_eq!(funding.psbt.inputs.len(), 1);
+        let spent = funding.psbt.inputs[0].witness_utxo.as_ref().unwrap();
+        assert_eq!(spent.value, 100_000_000);
+
+        // the redeem script and the witness of the P2SH input are paid for
+        let unsigned = &funding.psbt.global.unsigned_tx;
+        let signed_vsize = (unsigned.get_weight() + 2 + 3) / 4 + InputType::P2SHWH.vsize() - 41;
+        assert!(funding.fee >= signed_vsize as u64 * 20);
+        assert_eq!(funding.fee % 20, 0);
     }
 
-    fn process_tx(&mut self, tx: &Transaction) {
-        for input in &tx.input {
-            if self.op_to_utxo.contains_key(&input.previous_output) {
-                let (addr_type_to_remove, out_point_to_remove) = {
-                    let utxo = &self.op_to_utxo[&input.previous_output];
-                    (utxo.addr_type.clone(), utxo.out_point)
-                };
+    #[test]
+    fn fee_rates() {
+        let mut config = WalletConfig::in_memory();
+        config.set_fee_rate(5);
+        let (mut wallet, chain) = new_wallet(config);
+        let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        chain.push_block(Vec::new());
+        chain.push_block(vec![payment(&addr, 100_000_000)]);
+        wallet.sync_with_tip().unwrap();
 
-                // remove from account utxo map
-                let acc = self.get_account_mut(addr_type_to_remove);
-                acc.utxo_list.remove(&input.previous_output).unwrap();
+        // the rate of the wallet unless the call asks for another one
+        let dest_addr = "bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49".to_owned();
+        for &(fee_rate, expected) in &[(None, 5), (Some(50), 50)] {
+            let sent = wallet
+                .wallet_lib_mut()
+                .send_coins(
+                    dest_addr.clone(),
+                    50_000_000,
+                    SendOptions { fee_rate, ..SendOptions::default() },
+                )
+                .unwrap()
+                .sent()
+                .unwrap();
+            // the change script is shorter than the one counted, the signature may be too
+            let vsize = (sent.tx.get_weight() as u64 + 3) / 4;
+            assert!(sent.fee >= vsize * expected && sent.fee <= (vsize + 4) * expected);
+        }
+    }
 
-                self.db.write().unwrap().delete_utxo(&out_point_to_remove);
+    #[test]
+    fn uneconomical_outputs() {
+        for &spend in &[false, true] {
+            let mut config = WalletConfig::in_memory();
+            config.set_spend_uneconomical(spend);
+            let (mut wallet, chain) = new_wallet(config);
+            let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+            let dust = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+            let mut parent = payment(&addr, 50_000_000);
+            parent.output.push(TxOut {
+                value: 1_000,
+                script_pubkey: Address::from_str(&dust).unwrap().script_pubkey(),
+            });
+            wallet.wallet_lib_mut().process_tx(&parent);
 
-                // remove from account_factory utxo_map
-                self.op_to_utxo.remove(&input.previous_output).unwrap();
-            }
+            // the dust output is left to the parent unless the wallet consolidates
+            let child = wallet.wallet_lib_mut().accelerate(&parent.txid(), 20).unwrap();
+            assert_eq!(child.input.len(), if spend { 2 } else { 1 });
         }
+    }
 
-        let mut account_list = [
-            &mut self.p2pkh_account,
-            &mut self.p2shwh_account,
-            &mut self.p2wkh_account,
-        ];
-        for (account_index, account) in account_list.iter_mut().enumerate() {
-            for (output_index, output) in tx.output.iter().enumerate() {
-                let actual = &output.script_pubkey.to_bytes();
-                let mut joined = account.external_pk_list.clone();
-                joined.extend_from_slice(&account.internal_pk_list);
-
-                // TODO(evg): something better?
-                let external_pk_list_len = account.external_pk_list.len();
-                let get_pk_index = |raw: usize| -> KeyPath {
-                    let cache = if raw >= external_pk_list_len {
-                        (raw - external_pk_list_len, AddressChain::Internal)
-                    } else {
-                        (raw, AddressChain::External)
-                    };
-                    KeyPath::new(cache.1, cache.0 as u32)
-                };
+    #[test]
+    fn version_and_sequence() {
+        let mut config = WalletConfig::in_memory();
+        config.set_spend_unconfirmed_change(true);
+        let (mut wallet, chain) = new_wallet(config);
+        let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        let exposed = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        let timelock = Timelock::Blocks(2);
+        let savings = wallet.wallet_lib_mut().new_timelocked_address(timelock).unwrap();
+        chain.push_block(Vec::new());
+        chain.push_block(vec![
+            payment(&addr, 100_000_000),
+            payment(&exposed, 20_000_000),
+            payment(&savings, 30_000_000),
+        ]);
+        wallet.sync_with_tip().unwrap();
+        let is_replaceable = |tx: &Transaction| {
+            tx.version == TX_VERSION && tx.input.iter().all(|input| input.sequence == RBF_SEQUENCE)
+        };
 
-                let op = OutPoint {
-                    txid: tx.txid(),
-                    vout: output_index as u32,
-                };
+        let dest_addr = "bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49".to_owned();
+        let sent = wallet
+            .send_coins(dest_addr.clone(), 10_000_000, true, SendOptions::default())
+            .unwrap()
+            .sent()
+            .unwrap();
+        assert!(is_replaceable(&sent.tx));
+        let child = wallet.accelerate(&sent.tx.txid(), 20).unwrap();
+        assert!(is_replaceable(&child));
+        let sweep = wallet.sweep_address(&exposed, dest_addr.clone(), 10).unwrap();
+        assert!(is_replaceable(&sweep));
 
-                if (output.script_pubkey.is_p2pkh()
-                    && account.address_type == AccountAddressType::P2PKH)
-                    || (output.script_pubkey.is_p2sh()
-                        && account.address_type == AccountAddressType::P2SHWH)
-                    || (output.script_pubkey.is_v0_p2wpkh()
-                        && account.address_type == AccountAddressType::P2WKH)
-                {
-                    // TODO(evg): use correct index
-                    for pk_index in 0..joined.len() {
-                        let pk = &joined[pk_index];
-                        let script = account.script_from_pk(pk);
-                        let expected = &script.to_bytes();
-                        if actual == expected {
-                            let key_path = get_pk_index(pk_index);
-
-                            let utxo = Utxo::new(
-                                output.value,
-                                key_path,
-                                op,
-                                account_index as u32,
-                                script,
-                                account.address_type.clone(),
-                            );
-
-                            account.grab_utxo(utxo.clone());
-                            self.op_to_utxo.insert(op, utxo);
-                        }
-                    }
-                }
+        // the timelocked coin is needed once it matured, its input carries the lock
+        chain.mine();
+        chain.push_block(Vec::new());
+        wallet.sync_with_tip().unwrap();
+        let locked = wallet.wallet_lib().get_timelocked_utxo_list();
+        assert_eq!(locked.len(), 1);
+        let amt = wallet.wallet_lib().wallet_balance() + 10_000_000;
+        let sent = wallet
+            .send_coins(dest_addr, amt, false, SendOptions::default())
+            .unwrap()
+            .sent()
+            .unwrap();
+        assert_eq!(sent.tx.version, TX_VERSION);
+        for input in &sent.tx.input {
+            if input.previous_output == locked[0].out_point {
+                assert_eq!(input.sequence, timelock.sequence());
+            } else {
+                assert_eq!(input.sequence, RBF_SEQUENCE);
             }
         }
+        assert!(sent.selected_inputs.contains(&locked[0].out_point));
     }
-}
 
-pub enum WalletLibraryMode {
-    Create(KeyGenConfig),
-    Decrypt,
-    RecoverFromMnemonic(Mnemonic),
-}
-
-impl WalletLibrary {
-    pub fn new(
-        wc: WalletConfig,
-        mode: WalletLibraryMode,
-    ) -> Result<(WalletLibrary, Mnemonic), WalletError> {
-        let mut db = DB::new(wc.db_path);
-        let last_seen_block_height = db.get_last_seen_block_height();
-        let op_to_utxo = db.get_utxo_map();
-        let (master_key, mnemonic) = match mode {
-            WalletLibraryMode::Create(key_gen_cfg) => {
-                let (master_key, mnemonic, encrypted) = KeyFactory::new_master_private_key(
-                    key_gen_cfg.entropy,
-                    wc.network,
-                    &wc.passphrase,
-                    &wc.salt,
-                    key_gen_cfg.debug,
-                )?;
-                db.put_bip39_randomness(&encrypted);
-                (master_key, mnemonic)
-            }
-            WalletLibraryMode::Decrypt => {
-                let randomness = db
-                    .get_bip39_randomness()
-                    .ok_or(WalletError::HasNoWalletInDatabase)?;
-                let (master_key, mnemonic) =
-                    KeyFactory::decrypt(&randomness, wc.network, &wc.passphrase, &wc.salt)?;
-                (master_key, mnemonic)
-            }
-            WalletLibraryMode::RecoverFromMnemonic(mnemonic) => {
-                let encrypted = mnemonic.restore(&wc.passphrase)?;
-                db.put_bip39_randomness(&encrypted);
-                let master_key =
-                    KeyFactory::recover_from_mnemonic(&mnemonic, wc.network, &wc.salt)?;
-                (master_key, mnemonic)
-            }
-        };
-        let db = Arc::new(RwLock::new(db));
+    #[test]
+    fn privacy_mode() {
+        let mut config = WalletConfig::in_memory();
+        config.set_privacy(true);
+        let (mut wallet, chain) = new_wallet(config);
+        let legacy = wallet.wallet_lib_mut().new_address(AccountAddressType::P2PKH).unwrap();
+        let segwit = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        let exact = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        chain.push_block(Vec::new());
+        chain.push_block(vec![
+            payment(&legacy, 30_000_000),
+            payment(&segwit, 30_000_000),
+            payment(&exact, 20_002_200),
+        ]);
+        wallet.sync_with_tip().unwrap();
 
-        let p2pkh_account = WalletLibrary::new_account(
-            master_key,
-            0,
-            AccountAddressType::P2PKH,
-            Network::Regtest,
-            Arc::clone(&db),
+        // no account has enough alone, unless the call mixes them
+        let dest_addr = "bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49".to_owned();
+        let amt = 55_000_000;
+        let filter = InputFilter::Any;
+        let mixed = wallet.send_coins(
+            dest_addr.clone(),
+            amt,
+            false,
+            SendOptions { input_filter: filter, ..SendOptions::default() },
         );
+        assert!(mixed.is_err());
+        let sent = wallet
+            .send_coins(
+                dest_addr.clone(),
+                amt,
+                false,
+                SendOptions { privacy: Some(false), ..SendOptions::default() },
+            )
+            .unwrap()
+            .sent()
+            .unwrap();
+        assert!(sent.tx.input.len() >= 2);
 
-        let p2shwh_account = WalletLibrary::new_account(
-            master_key,
-            0,
-            AccountAddressType::P2SHWH,
-            Network::Regtest,
-            Arc::clone(&db),
-        );
+        // a coin paying the amount and the fee leaves no change worth an output
+        let sent = wallet
+            .send_coins(dest_addr, 20_000_000, false, SendOptions::default())
+            .unwrap()
+            .sent()
+            .unwrap();
+        assert_eq!(sent.tx.input.len(), 1);
+        assert_eq!(sent.tx.output.len(), 1);
+        assert_eq!(sent.change_outpoint, None);
+        // 110 virtual bytes at 20 satoshis each
+        assert_eq!(sent.fee, 2_200);
+    }
 
-        let p2wkh_account = WalletLibrary::new_account(
-            master_key,
-            0,
-            AccountAddressType::P2WKH,
-            Network::Regtest,
-            Arc::clone(&db),
-        );
+    #[test]
+    fn spend_labeled_coins() {
+        let (mut wallet, chain) = new_wallet(WalletConfig::in_memory());
+        let deposits = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        let operations = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        wallet.wallet_lib_mut().set_address_label(&deposits, "deposits").unwrap();
+        let foreign = "bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49";
+        assert!(wallet.wallet_lib_mut().set_address_label(foreign, "deposits").is_err());
+        chain.push_block(Vec::new());
+        chain.push_block(vec![payment(&operations, 100_000_000)]);
+        chain.push_block(vec![payment(&deposits, 30_000_000)]);
+        wallet.sync_with_tip().unwrap();
 
-        let mut wallet_lib = WalletLibrary {
-            master_key,
-            p2pkh_account,
-            p2shwh_account,
-            p2wkh_account,
-            network: wc.network,
-            last_seen_block_height,
-            op_to_utxo,
-            next_lock_id: LockId::new(),
-            locked_coins: LockGroupMap::new(),
-            db,
-        };
+        let info = wallet
+            .wallet_lib()
+            .get_address_list()
+            .into_iter()
+            .find(|info| info.address == deposits)
+            .unwrap();
+        assert_eq!(info.label, Some("deposits".to_owned()));
 
-        //        let mut ac = AccountFactory{
-        //            wallet_lib,
-        //            bio,
-        //        };
-        let op_to_utxo = wallet_lib.op_to_utxo.clone();
-        for (_, val) in &op_to_utxo {
-            wallet_lib
-                .get_account_mut(val.addr_type.clone())
-                .utxo_list
-                .insert(val.out_point, val.clone());
-        }
+        let filter = InputFilter::Label("deposits".to_owned());
+        let sent = wallet
+            .wallet_lib_mut()
+            .send_coins(
+                foreign.to_owned(),
+                10_000_000,
+                SendOptions { input_filter: filter, ..SendOptions::default() },
+            )
+            .unwrap()
+            .sent()
+            .unwrap();
+        let deposit_coin = payment(&deposits, 30_000_000).txid();
+        assert_eq!(sent.tx.input.len(), 1);
+        assert_eq!(sent.tx.input[0].previous_output.txid, deposit_coin);
 
-        let external_public_key_list = wallet_lib.db.read().unwrap().get_external_public_key_list();
-        for (key_helper, pk) in external_public_key_list {
-            wallet_lib
-                .get_account_mut(key_helper.addr_type.clone())
-                .external_pk_list
-                .push(pk);
-        }
+        // the deposits alone can't pay it
+        let filter = InputFilter::Label("deposits".to_owned());
+        let result = wallet
+            .wallet_lib_mut()
+            .send_coins(
+                foreign.to_owned(),
+                50_000_000,
+                SendOptions { input_filter: filter, ..SendOptions::default() },
+            );
+        assert!(result.is_err());
 
-        let internal_public_key_list = wallet_lib.db.read().unwrap().get_internal_public_key_list();
-        for (key_helper, pk) in internal_public_key_list {
-            wallet_lib
-                .get_account_mut(key_helper.addr_type.clone())
-                .internal_pk_list
-                .push(pk);
-        }
+        wallet.wallet_lib_mut().set_address_label(&deposits, "").unwrap();
+        let filter = InputFilter::Label("deposits".to_owned());
+        let result = wallet
+            .wallet_lib_mut()
+            .send_coins(
+                foreign.to_owned(),
+                1_000_000,
+                SendOptions { input_filter: filter, ..SendOptions::default() },
+            );
+        assert!(result.is_err());
+    }
 
-        let p2pkh_addr_list = wallet_lib
-            .db
-            .read()
+    #[test]
+    fn avoid_reuse() {
+        let mut config = WalletConfig::in_memory();
+        config.set_avoid_reuse(true);
+        let (mut wallet, chain) = new_wallet(config);
+        let reused = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        let fresh = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        let first = payment(&reused, 30_000_000);
+        let mut second = payment(&reused, 20_000_000);
+        second.input[0].previous_output.vout = 1;
+        let third = payment(&fresh, 50_000_000);
+        chain.push_block(Vec::new());
+        chain.push_block(vec![first.clone(), second.clone(), third.clone()]);
+        wallet.sync_with_tip().unwrap();
+
+        let reused_script = address::script_pubkey(&reused, Network::Regtest).unwrap();
+        let fresh_script = address::script_pubkey(&fresh, Network::Regtest).unwrap();
+        assert!(wallet.wallet_lib().is_reused(&reused_script));
+        assert!(!wallet.wallet_lib().is_reused(&fresh_script));
+
+        // one coin of the reused address can't be spent, so neither can the other
+        let op = |tx: &Transaction| OutPoint { txid: tx.txid(), vout: 0 };
+        let dest_addr = "bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49".to_owned();
+        wallet.wallet_lib_mut().freeze_utxo(op(&first)).unwrap();
+        let sent = wallet
+            .send_coins(dest_addr.clone(), 10_000_000, false, SendOptions::default())
             .unwrap()
-            .get_account_address_list(AccountAddressType::P2PKH);
-        for addr in p2pkh_addr_list {
-            wallet_lib
-                .get_account_mut(AccountAddressType::P2PKH)
-                .btc_address_list
-                .push(addr);
-        }
-        let p2shwh_addr_list = wallet_lib
-            .db
-            .read()
+            .sent()
+            .unwrap();
+        assert_eq!(sent.tx.input.len(), 1);
+        assert_eq!(sent.tx.input[0].previous_output, op(&third));
+
+        // either coin pays the amount alone, both are spent
+        wallet.wallet_lib_mut().unfreeze_utxo(op(&first)).unwrap();
+        wallet.wallet_lib_mut().freeze_utxo(op(&third)).unwrap();
+        let sent = wallet
+            .send_coins(dest_addr, 10_000_000, false, SendOptions::default())
             .unwrap()
-            .get_account_address_list(AccountAddressType::P2SHWH);
-        for addr in p2shwh_addr_list {
-            wallet_lib
-                .get_account_mut(AccountAddressType::P2SHWH)
-                .btc_address_list
-                .push(addr);
+            .sent()
+            .unwrap();
+        let inputs: Vec<OutPoint> =
+            sent.tx.input.iter().map(|input| input.previous_output).collect();
+        assert_eq!(inputs.len(), 2);
+        assert!(inputs.contains(&op(&first)) && inputs.contains(&op(&second)));
+    }
+
+    #[test]
+    fn changeless_payments() {
+        let (mut wallet, chain) = new_wallet(WalletConfig::in_memory());
+        let mut block = Vec::new();
+        for &value in &[7_000_000, 3_003_560, 50_000_000] {
+            let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+            block.push(payment(&addr, value));
         }
-        let p2wkh_addr_list = wallet_lib
-            .db
-            .read()
+        chain.push_block(Vec::new());
+        chain.push_block(block);
+        wallet.sync_with_tip().unwrap();
+
+        // two coins pay the amount and the fee exactly
+        let dest_addr = "bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49".to_owned();
+        let sent = wallet
+            .send_coins(
+                dest_addr.clone(),
+                10_000_000,
+                false,
+                SendOptions { change_tolerance: Some(1_000), ..SendOptions::default() },
+            )
             .unwrap()
-            .get_account_address_list(AccountAddressType::P2WKH);
-        for addr in p2wkh_addr_list {
-            wallet_lib
-                .get_account_mut(AccountAddressType::P2WKH)
-                .btc_address_list
-                .push(addr);
-        }
-        Ok((wallet_lib, mnemonic))
-    }
+            .sent()
+            .unwrap();
+        assert_eq!(sent.tx.input.len(), 2);
+        assert_eq!(sent.tx.output.len(), 1);
+        // 178 virtual bytes at 20 satoshis each
+        assert_eq!(sent.fee, 3_560);
 
-    /// get a copy of the master private key
-    pub fn master_private(&self) -> ExtendedPrivKey {
-        self.master_key.clone()
+        // what is left below the tolerance goes to the fee
+        let sent = wallet
+            .send_coins(
+                dest_addr,
+                49_950_000,
+                false,
+                SendOptions { change_tolerance: Some(100_000), ..SendOptions::default() },
+            )
+            .unwrap()
+            .sent()
+            .unwrap();
+        assert_eq!(sent.tx.input.len(), 1);
+        assert_eq!(sent.change_outpoint, None);
+        assert_eq!(sent.fee, 50_000);
     }
 
-    /// get a copy of the master public key
-    pub fn master_public(&self) -> ExtendedPubKey {
-        KeyFactory::extended_public_from_private(&self.master_key)
-    }
+    #[test]
+    fn seed_rotation_with_other_coins() {
+        let (mut wallet, chain) = new_wallet(WalletConfig::in_memory());
+        let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        let timelocked =
+            wallet.wallet_lib_mut().new_timelocked_address(Timelock::Blocks(1)).unwrap();
+        let deposit = payment(&timelocked, 20_000_000);
+        let deposit_txid = deposit.txid();
+        chain.push_block(Vec::new());
+        chain.push_block(vec![payment(&addr, 100_000_000), deposit]);
+        wallet.sync_with_tip().unwrap();
+        // the wallet pays to its own payment code
+        let code = wallet.wallet_lib().payment_code();
+        wallet.notify_payment_code(&code).unwrap();
+        chain.mine();
+        wallet.sync_with_tip().unwrap();
+        let code_addr = wallet.wallet_lib_mut().payment_code_address(&code).unwrap();
+        chain.push_block(vec![payment(&code_addr, 10_000_000)]);
+        wallet.sync_with_tip().unwrap();
 
-    //    pub fn mnemonic (&self) -> String {
-    //        self.mnemonic.to_string()
-    //    }
-    //
-    //    pub fn encrypted (&self) -> Vec<u8> {
-    //        self.encrypted.clone()
-    //    }
+        // the coins of the timelock account would be lost along with the old seed
+        assert!(wallet.rotate_seed(KeyGenConfig::default()).is_err());
+        let dest_addr = "bcrt1qku9u0rtxy5t9uxnyp2nqt9z33ffxy6qhjlcz49".to_owned();
+        let sent = wallet
+            .send_coins(dest_addr, 110_000_000, true, SendOptions::default())
+            .unwrap()
+            .sent()
+            .unwrap();
+        assert!(sent.selected_inputs.iter().any(|op| op.txid == deposit_txid));
+        chain.mine();
+        wallet.sync_with_tip().unwrap();
+        assert!(wallet.wallet_lib().get_timelocked_utxo_list().is_empty());
 
-    /// get an account
-    pub fn extract_account_key(
-        master_key: ExtendedPrivKey,
-        account_number: u32,
-        address_type: AccountAddressType,
-    ) -> Result<ExtendedPrivKey, WalletError> {
-        let mut key = match address_type {
-            AccountAddressType::P2PKH => {
-                KeyFactory::private_child(&master_key, ChildNumber::Hardened { index: 44 })?
-            }
-            AccountAddressType::P2SHWH => {
-                KeyFactory::private_child(&master_key, ChildNumber::Hardened { index: 49 })?
-            }
-            AccountAddressType::P2WKH => {
-                KeyFactory::private_child(&master_key, ChildNumber::Hardened { index: 84 })?
-            }
-        };
+        // so would the coins of the payment code
+        assert!(wallet.rotate_seed(KeyGenConfig::default()).is_err());
+        wallet.sweep_payment_codes(AccountAddressType::P2WKH).unwrap();
+        chain.mine();
+        let mut block = Vec::new();
+        for &(addr_type, value) in &[
+            (AccountAddressType::P2PKH, 50_000_000),
+            (AccountAddressType::P2SHWH, 30_000_000),
+        ] {
+            let addr = wallet.wallet_lib_mut().new_address(addr_type).unwrap();
+            block.push(payment(&addr, value));
+        }
+        chain.push_block(block);
+        wallet.sync_with_tip().unwrap();
 
-        key = match key.network {
-            Network::Bitcoin => {
-                KeyFactory::private_child(&key, ChildNumber::Hardened { index: 0 })?
-            }
-            Network::Testnet => {
-                KeyFactory::private_child(&key, ChildNumber::Hardened { index: 1 })?
-            }
-            // TODO(evg): `ChildNumber::Hardened{index: 2}` is it correct?
-            Network::Regtest => {
-                KeyFactory::private_child(&key, ChildNumber::Hardened { index: 2 })?
-            }
-        };
+        // the coins of all the address types move to the new seed
+        let balance = wallet.wallet_lib().wallet_balance();
+        let utxo_list = wallet.wallet_lib().get_utxo_list();
+        for addr_type in &[
+            AccountAddressType::P2PKH,
+            AccountAddressType::P2SHWH,
+            AccountAddressType::P2WKH,
+        ] {
+            assert!(utxo_list.iter().any(|utxo| utxo.addr_type == *addr_type));
+        }
+        let (sweep, _) = wallet.rotate_seed(KeyGenConfig::default()).unwrap();
+        assert_eq!(sweep.input.len(), utxo_list.len());
+        chain.mine();
+        wallet.sync_with_tip().unwrap();
+        let fee = balance - sweep.output[0].value;
+        assert_eq!(wallet.wallet_lib().wallet_balance(), balance - fee);
+        // the fee rate of the wallet, the signatures may be shorter than the ones counted
+        let vsize = (sweep.get_weight() as u64 + 3) / 4;
+        assert!(fee >= vsize * DEFAULT_FEE_RATE);
+        assert!(fee <= (vsize + sweep.input.len() as u64) * DEFAULT_FEE_RATE);
+    }
 
-        key = KeyFactory::private_child(
-            &key,
-            ChildNumber::Hardened {
-                index: account_number,
-            },
-        )?;
+    #[test]
+    fn export_electrum_wallet() {
+        let (wallet, _) = new_wallet(WalletConfig::in_memory());
+        let dir = std::env::temp_dir().join("rust_wallet_electrum_export");
+        wallet.wallet_lib().export_electrum_wallet(&dir).unwrap();
 
-        Ok(key)
+        let file = std::fs::read_to_string(dir.join("p2wkh")).unwrap();
+        let electrum: serde_json::Value = serde_json::from_str(&file).unwrap();
+        assert_eq!(electrum["wallet_type"], "standard");
+        assert_eq!(electrum["keystore"]["derivation"], "m/84'/1'/0'");
+        assert!(electrum["keystore"]["xpub"].as_str().unwrap().starts_with("vpub"));
+        let file = std::fs::read_to_string(dir.join("p2pkh")).unwrap();
+        let electrum: serde_json::Value = serde_json::from_str(&file).unwrap();
+        assert!(electrum["keystore"]["xpub"].as_str().unwrap().starts_with("tpub"));
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 
-    fn new_account(
-        master_key: ExtendedPrivKey,
-        account_number: u32,
-        address_type: AccountAddressType,
-        network: Network,
-        db: Arc<RwLock<DB>>,
-    ) -> Account {
-        let key =
-            WalletLibrary::extract_account_key(master_key, account_number, address_type.clone())
-                .unwrap();
+    #[test]
+    fn state_read_while_the_wallet_is_held() {
+        let (mut wallet, chain) = new_wallet(WalletConfig::in_memory());
+        let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        let state = wallet.wallet_lib().state();
+        chain.push_block(Vec::new());
+        chain.push_block(vec![payment(&addr, 100_000_000)]);
+        wallet.sync_with_tip().unwrap();
 
-        Account::new(key, address_type, network, Arc::clone(&db))
+        // e.g. by a sync of the daemon
+        let wallet = Arc::new(Mutex::new(wallet));
+        let held = wallet.lock().unwrap();
+        let reader = thread::spawn(move || (state.wallet_balance(), state.get_tx_history().len()));
+        assert_eq!(reader.join().unwrap(), (100_000_000, 1));
+        drop(held);
     }
 
-    fn get_account(&self, address_type: AccountAddressType) -> &Account {
-        match address_type {
-            AccountAddressType::P2PKH => &self.p2pkh_account,
-            AccountAddressType::P2SHWH => &self.p2shwh_account,
-            AccountAddressType::P2WKH => &self.p2wkh_account,
+    #[test]
+    fn state_read_during_a_sync() {
+        let (mut wallet, chain) = new_wallet(WalletConfig::in_memory());
+        let state = wallet.wallet_lib().state();
+        chain.push_block(Vec::new());
+        let mut expected = 0;
+        for i in 0..50 {
+            let addr = wallet.wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+            chain.push_block(vec![payment(&addr, 1_000_000 + i)]);
+            expected += 1_000_000 + i;
+        }
+
+        let (done, synced) = mpsc::channel();
+        let syncing = thread::spawn(move || {
+            wallet.sync_with_tip().unwrap();
+            done.send(()).unwrap();
+        });
+        // the blocks are processed one by one, the balance never goes back
+        let mut last = 0;
+        loop {
+            // a failed sync hangs up too
+            let finished = synced.try_recv() != Err(mpsc::TryRecvError::Empty);
+            let balance = state.wallet_balance();
+            assert!(balance >= last);
+            last = balance;
+            if finished {
+                break;
+            }
+            thread::yield_now();
         }
+        syncing.join().unwrap();
+        assert_eq!(last, expected);
+        assert_eq!(state.get_tx_history().len(), 50);
     }
 }
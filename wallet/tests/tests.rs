@@ -3,18 +3,26 @@ extern crate bitcoin;
 extern crate hex;
 extern crate rand;
 extern crate log;
-extern crate simple_logger;
 
 extern crate wallet;
 
 use wallet::{
     account::AccountAddressType,
-    context::{GlobalContext, WalletContext},
-    walletlibrary::{WalletLibraryMode, KeyGenConfig},
+    context::WalletContext,
+    testkit::RegtestHarness,
+    walletlibrary::{
+        WalletLibraryMode, KeyGenConfig, DecryptConfig, InputFilter, SpendingPolicy, SendOutcome,
+        SendOptions, DEFAULT_ENTROPY,
+    },
+    error::WalletError,
+    history::TxStatus,
+    events::WalletEvent,
     mnemonic::Mnemonic,
+    reserves,
+    audit,
 };
 use bitcoin_rpc_client::RpcApi;
-use bitcoin::Address;
+use bitcoin::{Address, Script, network::constants::Network};
 
 fn generate_money_for_wallet(context: &mut WalletContext) {
     use std::str::FromStr;
@@ -42,19 +50,74 @@ fn generate_money_for_wallet(context: &mut WalletContext) {
     assert_eq!(context.wallet_mut().wallet_lib().wallet_balance(), 600_000_000);
 }
 
+// blocks parsed by the wallet from raw hex are the ones bitcoind decodes,
+// `cargo test raw_blocks -- --nocapture` prints how long each way takes
+#[test]
+fn raw_blocks() {
+    use std::time::Instant;
+    use bitcoin::{Block, consensus::encode::deserialize};
+    use wallet::interface::BlockChainIO;
+
+    let mut harness = RegtestHarness::new().unwrap();
+    harness.context_mut().set_raw_blocks(true);
+    let (mut context, _) = harness.default_context(WalletLibraryMode::Create(KeyGenConfig::default())).unwrap();
+    context.bitcoind_mut().generate(110, None).unwrap();
+    generate_money_for_wallet(&mut context);
+
+    let client = context.bitcoind_mut();
+    let tip = BlockChainIO::get_block_count(client).unwrap();
+    let hashes: Vec<_> = (1..tip + 1)
+        .map(|height| BlockChainIO::get_block_hash(client, height).unwrap())
+        .collect();
+
+    let start = Instant::now();
+    let decoded: Vec<Block> = hashes
+        .iter()
+        .map(|hash| BlockChainIO::get_block(client, hash).unwrap())
+        .collect();
+    let decoded_time = start.elapsed();
+
+    let start = Instant::now();
+    let raw: Vec<Block> = hashes
+        .iter()
+        .map(|hash| {
+            let raw = hex::decode(BlockChainIO::get_raw_block(client, hash).unwrap()).unwrap();
+            deserialize(&raw).unwrap()
+        })
+        .collect();
+    let raw_time = start.elapsed();
+
+    assert_eq!(decoded, raw);
+    println!("{} blocks decoded by bitcoind in {:?}, raw in {:?}", tip, decoded_time, raw_time);
+}
+
+// transactions of an honest server pass the check
+#[test]
+fn verify_proofs() {
+    let mut harness = RegtestHarness::with_electrs().unwrap();
+    harness.context_mut().set_verify_proofs(true);
+    let (mut context, _) = harness.electrs_context(WalletLibraryMode::Create(KeyGenConfig::default())).unwrap();
+    context.bitcoind_mut().generate(110, None).unwrap();
+    generate_money_for_wallet(&mut context);
+
+    let tip_height = context.bitcoind_mut().get_block_count().unwrap() as usize;
+    let header = context.wallet_mut().wallet_lib().get_block_header(tip_height).unwrap();
+    assert_eq!(header.hash, context.bitcoind_mut().get_block_hash(tip_height as u64).unwrap());
+}
+
 macro_rules! test {
     ($base:ident) => {
         mod $base {
-            use super::{GlobalContext, WalletLibraryMode, $base};
+            use super::{RegtestHarness, WalletLibraryMode, $base};
             #[test]
             fn trusted_full_node() {
-                let global = GlobalContext::default();
-                $base(|mode: WalletLibraryMode| global.default_context(mode).unwrap());
+                let harness = RegtestHarness::new().unwrap();
+                $base(|mode: WalletLibraryMode| harness.default_context(mode).unwrap());
             }
             #[test]
             fn electrumx() {
-                let global = GlobalContext::default();
-                $base(|mode: WalletLibraryMode| global.electrs_context(mode).unwrap());
+                let harness = RegtestHarness::with_electrs().unwrap();
+                $base(|mode: WalletLibraryMode| harness.electrs_context(mode).unwrap());
             }
         }
     };
@@ -65,11 +128,37 @@ test!(base_wallet_functionality);
 test!(base_persistent_storage);
 test!(extended_persistent_storage);
 test!(restore_from_mnemonic);
+test!(restore_from_shares);
+test!(custom_passphrase);
+test!(backup_export_import);
 test!(make_tx_call);
 test!(send_coins_call);
+test!(unused_address);
+test!(change_address_type);
 test!(lock_coins_flag_success);
 test!(lock_coins_flag_fail);
+test!(freeze_utxo);
+test!(input_filter);
+test!(prepare_commit_tx);
+test!(channel_funding);
+test!(watch_script);
+test!(rebroadcast_queue);
+test!(child_pays_for_parent);
+test!(cancel_tx);
+test!(conflict_detection);
 test!(coinbase);
+test!(seed_rotation);
+test!(sweep_wif);
+test!(payment_code);
+test!(proof_of_reserves);
+test!(spending_policy);
+test!(spend_approval);
+test!(audit_log);
+test!(sync_progress);
+test!(mempool_on_startup);
+test!(header_chain);
+test!(spent_utxo_archive);
+test!(wallet_lock);
 
 fn sanity_check<F>(make_context: F)
 where
@@ -115,7 +204,7 @@ where
         .wallet_lib_mut()
         .new_address(AccountAddressType::P2WKH)
         .unwrap();
-    let tx = context.wallet_mut().make_tx(ops, dest_addr, 150_000_000, true).unwrap();
+    let tx = context.wallet_mut().make_tx(ops, dest_addr, 150_000_000, true, None).unwrap();
     context.bitcoind_mut()
         .get_raw_transaction(&tx.txid(), None)
         .unwrap();
@@ -145,7 +234,7 @@ where
         assert_eq!(context.wallet_mut().wallet_lib().wallet_balance(), 100_000_000);
     }
 
-    let (mut context, _) = make_context(WalletLibraryMode::Decrypt);
+    let (mut context, _) = make_context(WalletLibraryMode::Decrypt(DecryptConfig::default()));
 
     // balance should not change after restart
     assert_eq!(context.wallet_mut().wallet_lib().wallet_balance(), 100_000_000);
@@ -175,10 +264,10 @@ where
         generate_money_for_wallet(&mut context);
     }
 
-    {
+    let fee = {
         // recover wallet's state from persistent storage
         // additional scope destroys wallet object(aka wallet restart)
-        let (mut context, _) = make_context(WalletLibraryMode::Decrypt);
+        let (mut context, _) = make_context(WalletLibraryMode::Decrypt(DecryptConfig::default()));
 
         // select all available utxos
         // generate destination address
@@ -193,7 +282,7 @@ where
             .iter()
             .map(|utxo| utxo.out_point)
             .collect();
-        let tx = context.wallet_mut().make_tx(ops, dest_addr, 150_000_000, true).unwrap();
+        let tx = context.wallet_mut().make_tx(ops, dest_addr, 150_000_000, true, None).unwrap();
         context.bitcoind_mut()
             .get_raw_transaction(&tx.txid(), None)
             .unwrap();
@@ -203,12 +292,14 @@ where
         context.wallet_mut().sync_with_tip().unwrap();
 
         // wallet send money to itself, so balance decreased only by fee
-        assert_eq!(context.wallet_mut().wallet_lib().wallet_balance(), 600_000_000 - 10_000);
-    }
+        let fee = 600_000_000 - tx.output.iter().map(|output| output.value).sum::<u64>();
+        assert_eq!(context.wallet_mut().wallet_lib().wallet_balance(), 600_000_000 - fee);
+        fee
+    };
 
-    let (mut context, _) = make_context(WalletLibraryMode::Decrypt);
+    let (mut context, _) = make_context(WalletLibraryMode::Decrypt(DecryptConfig::default()));
     // balance should not change after restart
-    assert_eq!(context.wallet_mut().wallet_lib().wallet_balance(), 600_000_000 - 10_000);
+    assert_eq!(context.wallet_mut().wallet_lib().wallet_balance(), 600_000_000 - fee);
 }
 
 fn restore_from_mnemonic<F>(make_context: F)
@@ -232,7 +323,7 @@ where
     let mnemonic = Mnemonic::from(words_string.as_str()).unwrap();
 
     // recover wallet's state from mnemonic
-    let (mut context, _) = make_context(WalletLibraryMode::RecoverFromMnemonic(mnemonic));
+    let (mut context, _) = make_context(WalletLibraryMode::RecoverFromMnemonic(mnemonic, DecryptConfig::default()));
 
     // balance should not change after restart
     assert_eq!(context.wallet_mut().wallet_lib().wallet_balance(), 600_000_000);
@@ -252,6 +343,100 @@ where
     assert_eq!(context.wallet_mut().wallet_lib().wallet_balance(), 700_000_000);
 }
 
+fn restore_from_shares<F>(make_context: F)
+where
+    F: Fn(WalletLibraryMode) -> (WalletContext, Mnemonic),
+{
+    let shares = {
+        // initialize wallet with blockchain source and generated money
+        // additional scope destroys wallet object(aka wallet restart)
+        let (mut context, mnemonic) = make_context(WalletLibraryMode::Create(KeyGenConfig::default()));
+        context.bitcoind_mut().generate(110, None).unwrap();
+        generate_money_for_wallet(&mut context);
+        mnemonic.to_shares(2, 3).unwrap()
+    };
+
+    // any two of three shares are enough
+    let shares = vec![shares[2].clone(), shares[0].clone()];
+    let (mut context, _) = make_context(WalletLibraryMode::RecoverFromShares(shares, DecryptConfig::default()));
+    assert_eq!(context.wallet_mut().wallet_lib().wallet_balance(), 600_000_000);
+}
+
+fn custom_passphrase<F>(make_context: F)
+where
+    F: Fn(WalletLibraryMode) -> (WalletContext, Mnemonic),
+{
+    {
+        let key_gen_cfg = KeyGenConfig::new(DEFAULT_ENTROPY, "secret".to_owned(), "salt".to_owned());
+        let (mut context, _) = make_context(WalletLibraryMode::Create(key_gen_cfg));
+        context.bitcoind_mut().generate(110, None).unwrap();
+        generate_money_for_wallet(&mut context);
+    }
+
+    // the same passphrase and salt open the same wallet
+    let keys = {
+        let decrypt_cfg = DecryptConfig::new("secret".to_owned(), "salt".to_owned());
+        let (mut context, _) = make_context(WalletLibraryMode::Decrypt(decrypt_cfg));
+        assert_eq!(context.wallet_mut().wallet_lib().wallet_balance(), 600_000_000);
+        let keys = context.wallet_mut()
+            .wallet_lib_mut()
+            .get_account_mut(AccountAddressType::P2WKH)
+            .external_pks();
+        keys
+    };
+
+    // the salt is the BIP39 passphrase, another one derives another wallet
+    let decrypt_cfg = DecryptConfig::new("secret".to_owned(), "other".to_owned());
+    let (mut context, _) = make_context(WalletLibraryMode::Decrypt(decrypt_cfg));
+    let pk = context.wallet_mut()
+        .wallet_lib_mut()
+        .get_account_mut(AccountAddressType::P2WKH)
+        .next_external_pk()
+        .unwrap();
+    assert!(!keys.contains(&pk));
+}
+
+fn backup_export_import<F>(make_context: F)
+where
+    F: Fn(WalletLibraryMode) -> (WalletContext, Mnemonic),
+{
+    let path = std::env::temp_dir().join("rust_wallet_backup_test");
+    let keys = {
+        let (mut context, _) = make_context(WalletLibraryMode::Create(KeyGenConfig::default()));
+        context.bitcoind_mut().generate(110, None).unwrap();
+        generate_money_for_wallet(&mut context);
+        context.wallet_mut()
+            .wallet_lib()
+            .export_backup(&path, "backup")
+            .unwrap();
+        let keys = context.wallet_mut()
+            .wallet_lib_mut()
+            .get_account_mut(AccountAddressType::P2WKH)
+            .external_pks();
+        keys
+    };
+
+    // a brand new wallet takes over the state of the backed up one
+    let (mut context, _) = make_context(WalletLibraryMode::Create(KeyGenConfig::default()));
+    assert!(context.wallet_mut()
+        .wallet_lib_mut()
+        .import_backup(&path, "wrong", DecryptConfig::default())
+        .is_err());
+    context.wallet_mut()
+        .wallet_lib_mut()
+        .import_backup(&path, "backup", DecryptConfig::default())
+        .unwrap();
+    assert_eq!(context.wallet_mut().wallet_lib().wallet_balance(), 600_000_000);
+    assert_eq!(
+        context.wallet_mut()
+            .wallet_lib_mut()
+            .get_account_mut(AccountAddressType::P2WKH)
+            .external_pks(),
+        keys,
+    );
+    std::fs::remove_file(&path).unwrap();
+}
+
 fn make_tx_call<F>(make_context: F)
 where
     F: Fn(WalletLibraryMode) -> (WalletContext, Mnemonic),
@@ -275,7 +460,7 @@ where
         .wallet_lib_mut()
         .new_address(AccountAddressType::P2WKH)
         .unwrap();
-    let tx = context.wallet_mut().make_tx(ops, dest_addr, 150_000_000, true).unwrap();
+    let tx = context.wallet_mut().make_tx(ops, dest_addr, 150_000_000, true, None).unwrap();
     context.bitcoind_mut()
         .get_raw_transaction(&tx.txid(), None)
         .unwrap();
@@ -285,14 +470,15 @@ where
     context.wallet_mut().sync_with_tip().unwrap();
 
     // wallet send money to itself, so balance decreased only by fee
-    assert_eq!(context.wallet_mut().wallet_lib().wallet_balance(), 600_000_000 - 10_000);
+    let fee = 200_000_000 - tx.output.iter().map(|output| output.value).sum::<u64>();
+    assert_eq!(context.wallet_mut().wallet_lib().wallet_balance(), 600_000_000 - fee);
 
     // we should be able to find utxo with change of previous transaction
     let ok = context.wallet_mut()
         .wallet_lib()
         .get_utxo_list()
         .iter()
-        .any(|utxo| utxo.value == 200_000_000 - 150_000_000 - 10_000);
+        .any(|utxo| utxo.value == 200_000_000 - 150_000_000 - fee);
     assert!(ok);
 }
 
@@ -312,11 +498,13 @@ where
         .wallet_lib_mut()
         .new_address(AccountAddressType::P2WKH)
         .unwrap();
-    let (tx, _) = context.wallet_mut()
-        .send_coins(dest_addr, 150_000_000, false, false, true)
+    let sent = context.wallet_mut()
+        .send_coins(dest_addr, 150_000_000, true, SendOptions::default())
+        .unwrap()
+        .sent()
         .unwrap();
     context.bitcoind_mut()
-        .get_raw_transaction(&tx.txid(), None)
+        .get_raw_transaction(&sent.tx.txid(), None)
         .unwrap();
     context.bitcoind_mut().generate(1, None).unwrap();
 
@@ -324,14 +512,106 @@ where
     context.wallet_mut().sync_with_tip().unwrap();
 
     // wallet send money to itself, so balance decreased only by fee
-    assert_eq!(context.wallet_mut().wallet_lib().wallet_balance(), 600_000_000 - 10_000);
+    assert_eq!(context.wallet_mut().wallet_lib().wallet_balance(), 600_000_000 - sent.fee);
 
     // we should be able to find utxo with change of previous transaction
     let ok = context.wallet_mut()
         .wallet_lib()
         .get_utxo_list()
         .iter()
-        .any(|utxo| utxo.value == 200_000_000 - 150_000_000 - 10_000);
+        .any(|utxo| utxo.value == 200_000_000 - 150_000_000 - sent.fee);
+    assert!(ok);
+}
+
+fn unused_address<F>(make_context: F)
+where
+    F: Fn(WalletLibraryMode) -> (WalletContext, Mnemonic),
+{
+    use std::str::FromStr;
+
+    {
+        let (mut context, _) = make_context(WalletLibraryMode::Create(KeyGenConfig::default()));
+        context.bitcoind_mut().generate(110, None).unwrap();
+
+        // an address is handed out again until it receives funds
+        let addr = context.wallet_mut()
+            .wallet_lib_mut()
+            .get_unused_address(AccountAddressType::P2WKH)
+            .unwrap();
+        let same_addr = context.wallet_mut()
+            .wallet_lib_mut()
+            .get_unused_address(AccountAddressType::P2WKH)
+            .unwrap();
+        assert_eq!(addr, same_addr);
+
+        context.bitcoind_mut()
+            .send_to_address(&Address::from_str(&addr).unwrap(), 1.0, None, None, None, None, None, None)
+            .unwrap();
+        context.bitcoind_mut().generate(1, None).unwrap();
+        context.block_for_sync();
+        context.wallet_mut().sync_with_tip().unwrap();
+
+        let address_list = context.wallet_mut().wallet_lib().get_address_list();
+        assert!(address_list.iter().any(|info| info.address == addr && info.used));
+    }
+
+    // used addresses and derivation indexes survive a restart
+    let (mut context, _) = make_context(WalletLibraryMode::Decrypt(DecryptConfig::default()));
+    let address_list = context.wallet_mut().wallet_lib().get_address_list();
+    let used_addr = address_list.iter().find(|info| info.used).unwrap().address.clone();
+
+    let unused_addr = context.wallet_mut()
+        .wallet_lib_mut()
+        .get_unused_address(AccountAddressType::P2WKH)
+        .unwrap();
+    assert_ne!(unused_addr, used_addr);
+    let new_addr = context.wallet_mut()
+        .wallet_lib_mut()
+        .new_address(AccountAddressType::P2WKH)
+        .unwrap();
+    assert_ne!(new_addr, used_addr);
+    assert_ne!(new_addr, unused_addr);
+}
+
+fn change_address_type<F>(make_context: F)
+where
+    F: Fn(WalletLibraryMode) -> (WalletContext, Mnemonic),
+{
+    let (mut context, _) = make_context(WalletLibraryMode::Create(KeyGenConfig::default()));
+    context.bitcoind_mut().generate(110, None).unwrap();
+    generate_money_for_wallet(&mut context);
+
+    // the change goes to the requested account instead of the default P2WKH one
+    let dest_addr = context.wallet_mut()
+        .wallet_lib_mut()
+        .new_address(AccountAddressType::P2WKH)
+        .unwrap();
+    let sent = context.wallet_mut()
+        .send_coins(
+            dest_addr,
+            150_000_000,
+            true,
+            SendOptions {
+                change_addr_type: Some(AccountAddressType::P2PKH),
+                ..SendOptions::default()
+            },
+        )
+        .unwrap()
+        .sent()
+        .unwrap();
+    context.bitcoind_mut().generate(1, None).unwrap();
+
+    context.block_for_sync();
+    context.wallet_mut().sync_with_tip().unwrap();
+
+    let ok = context.wallet_mut()
+        .wallet_lib()
+        .get_utxo_list()
+        .iter()
+        .any(|utxo| {
+            utxo.value == 200_000_000 - 150_000_000 - sent.fee
+                && utxo.addr_type == AccountAddressType::P2PKH
+        });
     assert!(ok);
 }
 
@@ -354,19 +634,49 @@ where
         .new_address(AccountAddressType::P2WKH)
         .unwrap();
     context.wallet_mut()
-        .send_coins(dest_addr.clone(), 200_000_000 - 10_000, true, false, false)
+        .send_coins(
+            dest_addr.clone(),
+            200_000_000 - 10_000,
+            false,
+            SendOptions { lock_coins: true, ..SendOptions::default() },
+        )
+        .unwrap()
+        .sent()
         .unwrap();
     context.wallet_mut()
-        .send_coins(dest_addr.clone(), 200_000_000 - 10_000, true, false, false)
-        .unwrap();
-    let (_, lock_id) = context.wallet_mut()
-        .send_coins(dest_addr.clone(), 200_000_000 - 10_000, true, false, false)
+        .send_coins(
+            dest_addr.clone(),
+            200_000_000 - 10_000,
+            false,
+            SendOptions { lock_coins: true, ..SendOptions::default() },
+        )
+        .unwrap()
+        .sent()
         .unwrap();
+    let lock_id = context.wallet_mut()
+        .send_coins(
+            dest_addr.clone(),
+            200_000_000 - 10_000,
+            false,
+            SendOptions { lock_coins: true, ..SendOptions::default() },
+        )
+        .unwrap()
+        .sent()
+        .unwrap()
+        .lock_id;
     context.wallet_mut().wallet_lib_mut().unlock_coins(lock_id);
 
-    let (tx, _) = context.wallet_mut()
-        .send_coins(dest_addr, 200_000_000 - 10_000, true, false, false)
-        .unwrap();
+    let tx = context.wallet_mut()
+        .send_coins(
+            dest_addr,
+            200_000_000 - 10_000,
+            false,
+            SendOptions { lock_coins: true, ..SendOptions::default() },
+        )
+        .unwrap()
+        .sent()
+        .unwrap()
+        .tx;
     context.wallet_mut().publish_tx(&tx).unwrap();
 }
 
@@ -388,42 +698,934 @@ where
         .new_address(AccountAddressType::P2WKH)
         .unwrap();
     context.wallet_mut()
-        .send_coins(dest_addr.clone(), 200_000_000 - 10_000, true, false, false)
+        .send_coins(
+            dest_addr.clone(),
+            200_000_000 - 10_000,
+            false,
+            SendOptions { lock_coins: true, ..SendOptions::default() },
+        )
+        .unwrap()
+        .sent()
         .unwrap();
     context.wallet_mut()
-        .send_coins(dest_addr.clone(), 200_000_000 - 10_000, true, false, false)
+        .send_coins(
+            dest_addr.clone(),
+            200_000_000 - 10_000,
+            false,
+            SendOptions { lock_coins: true, ..SendOptions::default() },
+        )
+        .unwrap()
+        .sent()
         .unwrap();
     context.wallet_mut()
-        .send_coins(dest_addr.clone(), 200_000_000 - 10_000, true, false, false)
+        .send_coins(
+            dest_addr.clone(),
+            200_000_000 - 10_000,
+            false,
+            SendOptions { lock_coins: true, ..SendOptions::default() },
+        )
+        .unwrap()
+        .sent()
         .unwrap();
 
     // should finish with error, no available coins left
-    let result = context.wallet_mut().send_coins(dest_addr, 200_000_000 - 10_000, false, false, true);
+    let result = context.wallet_mut().send_coins(
+        dest_addr,
+        200_000_000 - 10_000,
+        true,
+        SendOptions::default(),
+    );
     assert!(result.is_err());
 }
 
-fn coinbase<F>(make_context: F)
+fn freeze_utxo<F>(make_context: F)
 where
     F: Fn(WalletLibraryMode) -> (WalletContext, Mnemonic),
 {
-    use std::str::FromStr;
+    {
+        let (mut context, _) = make_context(WalletLibraryMode::Create(KeyGenConfig::default()));
+        context.bitcoind_mut().generate(110, None).unwrap();
+        generate_money_for_wallet(&mut context);
+
+        // leave a single coin of 100_000_000 for coin selection
+        let ops: Vec<_> = context.wallet_mut()
+            .wallet_lib()
+            .get_utxo_list()
+            .iter()
+            .map(|utxo| utxo.out_point)
+            .collect();
+        for op in &ops[1..] {
+            context.wallet_mut().wallet_lib_mut().freeze_utxo(*op).unwrap();
+        }
+
+        let dest_addr = context.wallet_mut()
+            .wallet_lib_mut()
+            .new_address(AccountAddressType::P2WKH)
+            .unwrap();
+        let result = context.wallet_mut()
+            .send_coins(dest_addr, 150_000_000, false, SendOptions::default());
+        assert!(result.is_err());
+
+        context.wallet_mut().wallet_lib_mut().unfreeze_utxo(ops[1]).unwrap();
+        assert!(context.wallet_mut().wallet_lib_mut().unfreeze_utxo(ops[1]).is_err());
+    }
+
+    // frozen coins stay frozen after restart
+    let (mut context, _) = make_context(WalletLibraryMode::Decrypt(DecryptConfig::default()));
+    let frozen = context.wallet_mut().wallet_lib().get_frozen_list();
+    assert_eq!(frozen.len(), 4);
+
+    let dest_addr = context.wallet_mut()
+        .wallet_lib_mut()
+        .new_address(AccountAddressType::P2WKH)
+        .unwrap();
+    let tx = context.wallet_mut()
+        .send_coins(dest_addr, 150_000_000, false, SendOptions::default())
+        .unwrap()
+        .sent()
+        .unwrap()
+        .tx;
+    assert!(tx.input.iter().all(|input| !frozen.contains(&input.previous_output)));
+}
 
+fn input_filter<F>(make_context: F)
+where
+    F: Fn(WalletLibraryMode) -> (WalletContext, Mnemonic),
+{
     let (mut context, _) = make_context(WalletLibraryMode::Create(KeyGenConfig::default()));
-    let _ = context.bitcoind_mut().generate(110, None).unwrap();
+    context.bitcoind_mut().generate(110, None).unwrap();
+    generate_money_for_wallet(&mut context);
 
-    let destination_address = {
-        let s = context.wallet_mut()
-            .wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
-        Address::from_str(s.as_str()).unwrap()
+    let utxo_list = context.wallet_mut().wallet_lib().get_utxo_list();
+    let addr_type_of = |op: &bitcoin::OutPoint| {
+        utxo_list
+            .iter()
+            .find(|utxo| utxo.out_point == *op)
+            .map(|utxo| utxo.addr_type.clone())
+            .unwrap()
     };
+    let dest_addr = context.wallet_mut()
+        .wallet_lib_mut()
+        .new_address(AccountAddressType::P2WKH)
+        .unwrap();
 
-    let _ = context.bitcoind_mut()
-        .generate_to_address(1, &destination_address).unwrap();
-    context.block_for_sync();
-    context.wallet_mut().sync_with_tip().unwrap();
-    let balance_satoshi = context.wallet_mut().wallet_lib().wallet_balance();
-    assert!(balance_satoshi > 0);
+    let filter = InputFilter::AddressType(AccountAddressType::P2PKH);
+    let tx = context.wallet_mut()
+        .send_coins(
+            dest_addr.clone(),
+            150_000_000,
+            false,
+            SendOptions { input_filter: filter, ..SendOptions::default() },
+        )
+        .unwrap()
+        .sent()
+        .unwrap()
+        .tx;
+    assert!(tx.input.iter().all(|input| addr_type_of(&input.previous_output) == AccountAddressType::P2PKH));
+
+    // the second account is the P2SHWH one
+    let tx = context.wallet_mut()
+        .send_coins(
+            dest_addr.clone(),
+            150_000_000,
+            false,
+            SendOptions { input_filter: InputFilter::Account(1), ..SendOptions::default() },
+        )
+        .unwrap()
+        .sent()
+        .unwrap()
+        .tx;
+    assert!(tx.input.iter().all(|input| addr_type_of(&input.previous_output) == AccountAddressType::P2SHWH));
+
+    // each account holds 200_000_000 only
+    let filter = InputFilter::AddressType(AccountAddressType::P2WKH);
+    let result = context.wallet_mut()
+        .send_coins(
+            dest_addr,
+            250_000_000,
+            false,
+            SendOptions { input_filter: filter, ..SendOptions::default() },
+        );
+    assert!(result.is_err());
 }
 
-// TODO(evg): tests for lock persistence
-// TODO(evg): tests for witness_only flag
+fn prepare_commit_tx<F>(make_context: F)
+where
+    F: Fn(WalletLibraryMode) -> (WalletContext, Mnemonic),
+{
+    let (mut context, _) = make_context(WalletLibraryMode::Create(KeyGenConfig::default()));
+    context.bitcoind_mut().generate(110, None).unwrap();
+    generate_money_for_wallet(&mut context);
+
+    let dest_addr = context.wallet_mut()
+        .wallet_lib_mut()
+        .new_address(AccountAddressType::P2WKH)
+        .unwrap();
+    let (prepared_id, prepared) = context.wallet_mut()
+        .wallet_lib_mut()
+        .prepare_tx(dest_addr.clone(), 150_000_000, InputFilter::Any, None, None)
+        .unwrap();
+    assert_eq!(prepared.inputs.len(), 2);
+    assert!(prepared.vsize > 0);
+    // the change script may be shorter than the one the fee counted
+    assert!(prepared.fee >= prepared.vsize as u64 * 20);
+    assert!(prepared.fee <= (prepared.vsize as u64 + 3) * 20);
+    assert_eq!(prepared.change, 200_000_000 - 150_000_000 - prepared.fee);
+
+    // preview neither locks the coins nor publishes anything
+    let (other_id, other) = context.wallet_mut()
+        .wallet_lib_mut()
+        .prepare_tx(dest_addr, 150_000_000, InputFilter::Any, None, None)
+        .unwrap();
+    assert_ne!(other_id, prepared_id);
+    assert_eq!(other.tx.input, prepared.tx.input);
+    assert_eq!(context.wallet_mut().wallet_lib().wallet_balance(), 600_000_000);
+
+    let tx = context.wallet_mut().commit_tx(prepared_id).unwrap();
+    context.bitcoind_mut()
+        .get_raw_transaction(&tx.txid(), None)
+        .unwrap();
+    assert!(context.wallet_mut().commit_tx(prepared_id).is_err());
+}
+
+fn channel_funding<F>(make_context: F)
+where
+    F: Fn(WalletLibraryMode) -> (WalletContext, Mnemonic),
+{
+    let (mut context, _) = make_context(WalletLibraryMode::Create(KeyGenConfig::default()));
+    context.bitcoind_mut().generate(110, None).unwrap();
+    generate_money_for_wallet(&mut context);
+
+    // OP_1, stands for the 2-of-2 script of a channel
+    let witness_script = Script::from(vec![0x51]);
+    let (funding_id, funding) = context.wallet_mut()
+        .wallet_lib_mut()
+        .fund_psbt_for_channel(&witness_script, 150_000_000, 10)
+        .unwrap();
+    let unsigned_tx = funding.psbt.global.unsigned_tx.clone();
+    let output = &unsigned_tx.output[funding.output_index as usize];
+    assert_eq!(output.value, 150_000_000);
+    let script_pubkey = Address::p2wsh(&witness_script, Network::Regtest).script_pubkey();
+    assert_eq!(output.script_pubkey, script_pubkey);
+    assert_eq!(unsigned_tx.input.len(), 2);
+    assert!(funding.psbt.inputs.iter().all(|input| input.witness_utxo.is_some()));
+    assert!(funding.fee > 0 && funding.fee < 10_000);
+
+    // the coins of the first funding are locked
+    let (other_id, other) = context.wallet_mut()
+        .wallet_lib_mut()
+        .fund_psbt_for_channel(&witness_script, 150_000_000, 10)
+        .unwrap();
+    for input in &other.psbt.global.unsigned_tx.input {
+        assert!(!unsigned_tx.input.contains(input));
+    }
+    context.wallet_mut().wallet_lib_mut().release_funding(other_id).unwrap();
+    assert!(context.wallet_mut().publish_funding(other_id).is_err());
+
+    // the channel already knows the txid, signing must keep it
+    let tx = context.wallet_mut().publish_funding(funding_id).unwrap();
+    assert_eq!(tx.txid(), unsigned_tx.txid());
+    context.bitcoind_mut()
+        .get_raw_transaction(&tx.txid(), None)
+        .unwrap();
+}
+
+fn watch_script<F>(make_context: F)
+where
+    F: Fn(WalletLibraryMode) -> (WalletContext, Mnemonic),
+{
+    // OP_1, stands for the script of a channel
+    let addr = Address::p2wsh(&Script::from(vec![0x51]), Network::Regtest);
+    let script_pubkey = addr.script_pubkey();
+    {
+        let (mut context, _) = make_context(WalletLibraryMode::Create(KeyGenConfig::default()));
+        context.bitcoind_mut().generate(110, None).unwrap();
+        generate_money_for_wallet(&mut context);
+        context.wallet_mut()
+            .wallet_lib_mut()
+            .watch_script(script_pubkey.clone(), "channel".to_owned());
+        let events = context.wallet_mut().wallet_lib_mut().subscribe();
+
+        context.bitcoind_mut()
+            .send_to_address(&addr, 0.5, None, None, None, None, None, None)
+            .unwrap();
+        context.bitcoind_mut().generate(1, None).unwrap();
+        context.block_for_sync();
+        context.wallet_mut().sync_with_tip().unwrap();
+
+        // the watched output is not the wallet's money
+        assert_eq!(context.wallet_mut().wallet_lib().wallet_balance(), 600_000_000);
+        assert_eq!(context.wallet_mut().wallet_lib().watched_balance(), 50_000_000);
+        let funded = events.try_iter().any(|event| match event {
+            WalletEvent::WatchedFunded { label, value, .. } => {
+                label == "channel" && value == 50_000_000
+            }
+            _ => false,
+        });
+        assert!(funded);
+    }
+
+    // watched scripts and their outputs survive a restart
+    let (mut context, _) = make_context(WalletLibraryMode::Decrypt(DecryptConfig::default()));
+    let watched = context.wallet_mut().wallet_lib().get_watched_utxo_list();
+    assert_eq!(watched.len(), 1);
+    assert_eq!(watched[0].script_pubkey, script_pubkey);
+    assert_eq!(watched[0].label, "channel");
+}
+
+fn rebroadcast_queue<F>(make_context: F)
+where
+    F: Fn(WalletLibraryMode) -> (WalletContext, Mnemonic),
+{
+    let txid = {
+        let (mut context, _) = make_context(WalletLibraryMode::Create(KeyGenConfig::default()));
+        context.bitcoind_mut().generate(110, None).unwrap();
+        generate_money_for_wallet(&mut context);
+
+        let dest_addr = context.wallet_mut()
+            .wallet_lib_mut()
+            .new_address(AccountAddressType::P2WKH)
+            .unwrap();
+        let tx = context.wallet_mut()
+            .send_coins(dest_addr, 150_000_000, true, SendOptions::default())
+            .unwrap()
+            .sent()
+            .unwrap()
+            .tx;
+        assert_eq!(context.wallet_mut().wallet_lib().get_unconfirmed_txs(), vec![tx.clone()]);
+        tx.txid()
+    };
+
+    // published transactions survive a restart
+    let (mut context, _) = make_context(WalletLibraryMode::Decrypt(DecryptConfig::default()));
+    let unconfirmed = context.wallet_mut().wallet_lib().get_unconfirmed_txs();
+    assert_eq!(unconfirmed.len(), 1);
+    assert_eq!(unconfirmed[0].txid(), txid);
+
+    // sync without a new block keeps the transaction
+    context.wallet_mut().sync_with_tip().unwrap();
+    assert_eq!(context.wallet_mut().wallet_lib().get_unconfirmed_txs().len(), 1);
+
+    context.bitcoind_mut().generate(1, None).unwrap();
+    context.block_for_sync();
+    context.wallet_mut().sync_with_tip().unwrap();
+    assert!(context.wallet_mut().wallet_lib().get_unconfirmed_txs().is_empty());
+
+    // abandoned transactions are not rebroadcast
+    let dest_addr = context.wallet_mut()
+        .wallet_lib_mut()
+        .new_address(AccountAddressType::P2WKH)
+        .unwrap();
+    let tx = context.wallet_mut()
+        .send_coins(dest_addr, 50_000_000, true, SendOptions::default())
+        .unwrap()
+        .sent()
+        .unwrap()
+        .tx;
+    context.wallet_mut().wallet_lib_mut().abandon_tx(&tx.txid()).unwrap();
+    assert!(context.wallet_mut().wallet_lib().get_unconfirmed_txs().is_empty());
+    assert!(context.wallet_mut().wallet_lib_mut().abandon_tx(&tx.txid()).is_err());
+}
+
+fn child_pays_for_parent<F>(make_context: F)
+where
+    F: Fn(WalletLibraryMode) -> (WalletContext, Mnemonic),
+{
+    let (mut context, _) = make_context(WalletLibraryMode::Create(KeyGenConfig::default()));
+    context.bitcoind_mut().generate(110, None).unwrap();
+    generate_money_for_wallet(&mut context);
+
+    // pay to the wallet itself, both outputs of the parent are ours
+    let dest_addr = context.wallet_mut()
+        .wallet_lib_mut()
+        .new_address(AccountAddressType::P2WKH)
+        .unwrap();
+    let sent = context.wallet_mut()
+        .send_coins(dest_addr, 150_000_000, true, SendOptions::default())
+        .unwrap()
+        .sent()
+        .unwrap();
+    let parent = sent.tx;
+    context.wallet_mut().wallet_lib_mut().process_tx(&parent);
+
+    let child = context.wallet_mut().accelerate(&parent.txid(), 50).unwrap();
+    assert_eq!(child.input.len(), parent.output.len());
+    assert!(child.input.iter().all(|input| input.previous_output.txid == parent.txid()));
+    let child_fee = 200_000_000 - sent.fee - child.output[0].value;
+    assert!(child_fee > 0);
+    context.bitcoind_mut()
+        .get_raw_transaction(&child.txid(), None)
+        .unwrap();
+
+    // a confirmed transaction needs no help
+    context.bitcoind_mut().generate(1, None).unwrap();
+    context.block_for_sync();
+    context.wallet_mut().sync_with_tip().unwrap();
+    assert!(context.wallet_mut().accelerate(&parent.txid(), 50).is_err());
+}
+
+fn cancel_tx<F>(make_context: F)
+where
+    F: Fn(WalletLibraryMode) -> (WalletContext, Mnemonic),
+{
+    let (mut context, _) = make_context(WalletLibraryMode::Create(KeyGenConfig::default()));
+    context.bitcoind_mut().generate(110, None).unwrap();
+    generate_money_for_wallet(&mut context);
+
+    let dest_addr = Address::p2wsh(&Script::from(vec![0x51]), Network::Regtest);
+    let payment = context.wallet_mut()
+        .send_coins(dest_addr.to_string(), 150_000_000, true, SendOptions::default())
+        .unwrap()
+        .sent()
+        .unwrap()
+        .tx;
+    let replacement = context.wallet_mut().cancel_tx(&payment.txid(), 20).unwrap();
+    assert_eq!(replacement.input, payment.input);
+    assert_eq!(replacement.output.len(), 1);
+
+    let history = context.wallet_mut().wallet_lib().get_tx_history();
+    let record = history.iter().find(|record| record.txid() == payment.txid()).unwrap();
+    assert_eq!(record.status, TxStatus::Replaced(replacement.txid()));
+    assert_eq!(context.wallet_mut().wallet_lib().get_unconfirmed_txs(), vec![replacement.clone()]);
+
+    // only the fee of the replacement is gone
+    context.bitcoind_mut().generate(1, None).unwrap();
+    context.block_for_sync();
+    context.wallet_mut().sync_with_tip().unwrap();
+    let fee = 200_000_000 - replacement.output[0].value;
+    assert_eq!(context.wallet_mut().wallet_lib().wallet_balance(), 600_000_000 - fee);
+    assert!(context.bitcoind_mut().get_raw_transaction(&payment.txid(), None).is_err());
+}
+
+fn conflict_detection<F>(make_context: F)
+where
+    F: Fn(WalletLibraryMode) -> (WalletContext, Mnemonic),
+{
+    let (mut context, _) = make_context(WalletLibraryMode::Create(KeyGenConfig::default()));
+    context.bitcoind_mut().generate(110, None).unwrap();
+    generate_money_for_wallet(&mut context);
+    let events = context.wallet_mut().wallet_lib_mut().subscribe();
+
+    // two transactions spending the same coin
+    let op = context.wallet_mut().wallet_lib().get_utxo_list()[0].out_point;
+    let dest_addr = context.wallet_mut()
+        .wallet_lib_mut()
+        .new_address(AccountAddressType::P2WKH)
+        .unwrap();
+    let tx_a = context.wallet_mut()
+        .make_tx(vec![op], dest_addr.clone(), 50_000_000, false, None)
+        .unwrap();
+    let tx_b = context.wallet_mut()
+        .make_tx(vec![op], dest_addr, 60_000_000, false, None)
+        .unwrap();
+
+    // the wallet waits for `tx_a` while `tx_b` gets into a block
+    context.wallet_mut().wallet_lib_mut().add_unconfirmed_tx(&tx_a);
+    context.bitcoind_mut().send_raw_transaction(&tx_b).unwrap();
+    context.bitcoind_mut().generate(1, None).unwrap();
+    context.block_for_sync();
+    context.wallet_mut().sync_with_tip().unwrap();
+
+    let history = context.wallet_mut().wallet_lib().get_tx_history();
+    let status_of = |txid| {
+        history
+            .iter()
+            .find(|record| record.txid() == txid)
+            .map(|record| record.status.clone())
+            .unwrap()
+    };
+    assert_eq!(status_of(tx_a.txid()), TxStatus::Conflicted(tx_b.txid()));
+    match status_of(tx_b.txid()) {
+        TxStatus::Confirmed(_) => (),
+        status => panic!("unexpected status {:?}", status),
+    }
+    assert_eq!(
+        events.try_recv().unwrap(),
+        WalletEvent::Conflict { txid: tx_a.txid(), conflicting_txid: tx_b.txid() },
+    );
+    assert!(context.wallet_mut().wallet_lib().get_unconfirmed_txs().is_empty());
+
+    // the change of `tx_a` never existed, `tx_b` paid the fee
+    let fee = 100_000_000 - tx_b.output.iter().map(|output| output.value).sum::<u64>();
+    assert_eq!(context.wallet_mut().wallet_lib().wallet_balance(), 600_000_000 - fee);
+}
+
+fn coinbase<F>(make_context: F)
+where
+    F: Fn(WalletLibraryMode) -> (WalletContext, Mnemonic),
+{
+    use std::str::FromStr;
+
+    let (mut context, _) = make_context(WalletLibraryMode::Create(KeyGenConfig::default()));
+    let _ = context.bitcoind_mut().generate(110, None).unwrap();
+
+    let destination_address = {
+        let s = context.wallet_mut()
+            .wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+        Address::from_str(s.as_str()).unwrap()
+    };
+
+    let _ = context.bitcoind_mut()
+        .generate_to_address(1, &destination_address).unwrap();
+    context.block_for_sync();
+    context.wallet_mut().sync_with_tip().unwrap();
+    let balance_satoshi = context.wallet_mut().wallet_lib().wallet_balance();
+    assert!(balance_satoshi > 0);
+}
+
+// TODO(evg): tests for lock persistence
+
+fn seed_rotation<F>(make_context: F)
+where
+    F: Fn(WalletLibraryMode) -> (WalletContext, Mnemonic),
+{
+    let (old_keys, fee) = {
+        let (mut context, mnemonic) = make_context(WalletLibraryMode::Create(KeyGenConfig::default()));
+        context.bitcoind_mut().generate(110, None).unwrap();
+        generate_money_for_wallet(&mut context);
+        let old_keys = context.wallet_mut()
+            .wallet_lib_mut()
+            .get_account_mut(AccountAddressType::P2WKH)
+            .external_pks();
+
+        let (sweep, new_mnemonic) = context.wallet_mut()
+            .rotate_seed(KeyGenConfig::default())
+            .unwrap();
+        assert_ne!(new_mnemonic.to_string(), mnemonic.to_string());
+
+        // the wallet switches to the new seed with the sweep confirmed
+        context.bitcoind_mut().generate(1, None).unwrap();
+        context.block_for_sync();
+        context.wallet_mut().sync_with_tip().unwrap();
+        let fee = 600_000_000 - sweep.output[0].value;
+        assert_eq!(context.wallet_mut().wallet_lib().wallet_balance(), 600_000_000 - fee);
+        (old_keys, fee)
+    };
+
+    // the new seed is persisted, no key of the old one is left
+    let (mut context, _) = make_context(WalletLibraryMode::Decrypt(DecryptConfig::default()));
+    assert_eq!(context.wallet_mut().wallet_lib().wallet_balance(), 600_000_000 - fee);
+    let new_keys = context.wallet_mut()
+        .wallet_lib_mut()
+        .get_account_mut(AccountAddressType::P2WKH)
+        .external_pks();
+    assert!(!new_keys.is_empty());
+    assert!(new_keys.iter().all(|pk| !old_keys.contains(pk)));
+}
+
+fn sweep_wif<F>(make_context: F)
+where
+    F: Fn(WalletLibraryMode) -> (WalletContext, Mnemonic),
+{
+    use bitcoin::{PrivateKey, PublicKey, network::constants::Network};
+    use secp256k1::Secp256k1;
+
+    let (mut context, _) = make_context(WalletLibraryMode::Create(KeyGenConfig::default()));
+    context.bitcoind_mut().generate(110, None).unwrap();
+    generate_money_for_wallet(&mut context);
+
+    // fund a paper wallet
+    let wif = "cVt4o7BGAig1UXywgGSmARhxMdzP5qvQsxKkSsc1XEkw3tDTQFpy";
+    let pk = PublicKey::from_private_key(&Secp256k1::new(), &PrivateKey::from_wif(wif).unwrap());
+    context.bitcoind_mut()
+        .send_to_address(&Address::p2wpkh(&pk, Network::Regtest), 1.0, None, None, None, None, None, None)
+        .unwrap();
+    context.bitcoind_mut().generate(1, None).unwrap();
+    context.block_for_sync();
+
+    let sweep = context.wallet_mut()
+        .sweep_wif(wif, AccountAddressType::P2WKH)
+        .unwrap();
+    context.bitcoind_mut().generate(1, None).unwrap();
+    context.block_for_sync();
+    context.wallet_mut().sync_with_tip().unwrap();
+
+    assert_eq!(context.wallet_mut().wallet_lib().wallet_balance(), 600_000_000 + sweep.output[0].value);
+    assert!(sweep.output[0].value < 100_000_000);
+    assert!(context.wallet_mut().wallet_lib().get_imported_utxo_list().is_empty());
+}
+
+fn payment_code<F>(make_context: F)
+where
+    F: Fn(WalletLibraryMode) -> (WalletContext, Mnemonic),
+{
+    let (mut context, _) = make_context(WalletLibraryMode::Create(KeyGenConfig::default()));
+    context.bitcoind_mut().generate(110, None).unwrap();
+    generate_money_for_wallet(&mut context);
+
+    // the wallet pays to its own payment code, it is the recipient and the sender as well
+    let code = context.wallet_mut().wallet_lib().payment_code();
+    assert!(context.wallet_mut().wallet_lib_mut().payment_code_address(&code).is_err());
+    let notification = context.wallet_mut().notify_payment_code(&code).unwrap();
+    context.bitcoind_mut().generate(1, None).unwrap();
+    context.block_for_sync();
+    context.wallet_mut().sync_with_tip().unwrap();
+
+    let addr = context.wallet_mut()
+        .wallet_lib_mut()
+        .payment_code_address(&code)
+        .unwrap();
+    let next_addr = context.wallet_mut()
+        .wallet_lib_mut()
+        .payment_code_address(&code)
+        .unwrap();
+    assert_ne!(addr, next_addr);
+    let sent = context.wallet_mut()
+        .send_coins(addr, 100_000_000, true, SendOptions::default())
+        .unwrap()
+        .sent()
+        .unwrap();
+    context.bitcoind_mut().generate(1, None).unwrap();
+    context.block_for_sync();
+    context.wallet_mut().sync_with_tip().unwrap();
+
+    let imported_utxo_list = context.wallet_mut().wallet_lib().get_imported_utxo_list();
+    assert_eq!(imported_utxo_list.len(), 1);
+    assert_eq!(imported_utxo_list[0].value, 100_000_000);
+
+    let sweep = context.wallet_mut()
+        .sweep_payment_codes(AccountAddressType::P2WKH)
+        .unwrap();
+    context.bitcoind_mut().generate(1, None).unwrap();
+    context.block_for_sync();
+    context.wallet_mut().sync_with_tip().unwrap();
+
+    // the notification output and three fees are gone, the coins of the wallet were 1 BTC each
+    let outputs = |tx: &bitcoin::Transaction| tx.output.iter().map(|output| output.value).sum::<u64>();
+    let notification_fee = notification.input.len() as u64 * 100_000_000 - outputs(&notification);
+    let fees = notification_fee + sent.fee + 100_000_000 - outputs(&sweep);
+    assert_eq!(context.wallet_mut().wallet_lib().wallet_balance(), 600_000_000 - 546 - fees);
+    assert!(context.wallet_mut().wallet_lib().get_imported_utxo_list().is_empty());
+}
+
+fn proof_of_reserves<F>(make_context: F)
+where
+    F: Fn(WalletLibraryMode) -> (WalletContext, Mnemonic),
+{
+    let (mut context, _) = make_context(WalletLibraryMode::Create(KeyGenConfig::default()));
+    context.bitcoind_mut().generate(110, None).unwrap();
+    assert!(context.wallet_mut().wallet_lib().prove_reserves("audit").is_err());
+    generate_money_for_wallet(&mut context);
+
+    let proof = context.wallet_mut().wallet_lib().prove_reserves("audit").unwrap();
+    assert_eq!(proof.input.len(), 1 + 6);
+
+    // the auditor looks the coins up in the UTXO set, here the wallet knows them already
+    let utxo_list = context.wallet_mut().wallet_lib().get_utxo_list();
+    let lookup = |op: &bitcoin::OutPoint| {
+        utxo_list.iter().find(|utxo| utxo.out_point == *op).map(|utxo| bitcoin::TxOut {
+            value: utxo.value,
+            script_pubkey: utxo.pk_script.clone(),
+        })
+    };
+    assert_eq!(reserves::verify(&proof, "audit", lookup).unwrap(), 600_000_000);
+    assert!(reserves::verify(&proof, "another audit", lookup).is_err());
+}
+
+fn spending_policy<F>(make_context: F)
+where
+    F: Fn(WalletLibraryMode) -> (WalletContext, Mnemonic),
+{
+    let (mut context, _) = make_context(WalletLibraryMode::Create(KeyGenConfig::default()));
+    context.bitcoind_mut().generate(110, None).unwrap();
+    generate_money_for_wallet(&mut context);
+
+    let allowed = Address::p2wsh(&Script::from(vec![0x51]), Network::Regtest).to_string();
+    let other = Address::p2wsh(&Script::from(vec![0x52]), Network::Regtest).to_string();
+    let policy = SpendingPolicy {
+        daily_limit: Some(150_000_000),
+        max_per_tx: Some(100_000_000),
+        whitelist: vec![allowed.clone()],
+        blacklist: Vec::new(),
+        approval_threshold: None,
+    };
+    context.wallet_mut().wallet_lib_mut().set_spending_policy(policy.clone()).unwrap();
+    assert_eq!(context.wallet_mut().wallet_lib().get_spending_policy(), policy);
+
+    let mut send = |addr: &str, amt: u64| {
+        context.wallet_mut()
+            .send_coins(addr.to_owned(), amt, true, SendOptions::default())
+            .map(|_| ())
+            .map_err(|e| match e.downcast_ref::<WalletError>() {
+                Some(&WalletError::DestinationNotAllowed(_)) => "destination",
+                Some(&WalletError::TransactionLimitExceeded(100_000_000)) => "transaction",
+                Some(&WalletError::DailyLimitExceeded(50_000_000)) => "daily",
+                _ => panic!("unexpected error {}", e),
+            })
+    };
+    assert_eq!(send(&other, 10_000_000), Err("destination"));
+    assert_eq!(send(&allowed, 110_000_000), Err("transaction"));
+    assert_eq!(send(&allowed, 100_000_000), Ok(()));
+    assert_eq!(send(&allowed, 60_000_000), Err("daily"));
+    assert_eq!(send(&allowed, 50_000_000), Ok(()));
+    assert_eq!(context.wallet_mut().wallet_lib().spent_in_window(), 150_000_000);
+
+    // lifting the limits doesn't forget the payments made already
+    context.wallet_mut()
+        .wallet_lib_mut()
+        .set_spending_policy(SpendingPolicy::default())
+        .unwrap();
+    assert_eq!(context.wallet_mut().wallet_lib().spent_in_window(), 150_000_000);
+    // a payment counts once it is published
+    let sent = context.wallet_mut()
+        .send_coins(other, 10_000_000, false, SendOptions::default())
+        .unwrap()
+        .sent()
+        .unwrap();
+    assert_eq!(context.wallet_mut().wallet_lib().spent_in_window(), 150_000_000);
+    context.wallet_mut().publish_tx(&sent.tx).unwrap();
+    assert_eq!(context.wallet_mut().wallet_lib().spent_in_window(), 160_000_000);
+}
+
+fn spend_approval<F>(make_context: F)
+where
+    F: Fn(WalletLibraryMode) -> (WalletContext, Mnemonic),
+{
+    let dest_addr = Address::p2wsh(&Script::from(vec![0x51]), Network::Regtest).to_string();
+    let id = {
+        let (mut context, _) = make_context(WalletLibraryMode::Create(KeyGenConfig::default()));
+        context.bitcoind_mut().generate(110, None).unwrap();
+        generate_money_for_wallet(&mut context);
+
+        let policy = SpendingPolicy {
+            approval_threshold: Some(100_000_000),
+            ..SpendingPolicy::default()
+        };
+        context.wallet_mut().wallet_lib_mut().set_spending_policy(policy).unwrap();
+
+        // small payments go through at once
+        context.wallet_mut()
+            .send_coins(dest_addr.clone(), 10_000_000, true, SendOptions::default())
+            .unwrap()
+            .sent()
+            .unwrap();
+
+        let mut send = |amt: u64| {
+            let outcome = context.wallet_mut()
+                .send_coins(dest_addr.clone(), amt, true, SendOptions::default())
+                .unwrap();
+            match outcome {
+                SendOutcome::ApprovalRequired(id) => id,
+                SendOutcome::Sent(_) => panic!("a payment above the threshold was signed"),
+            }
+        };
+        let rejected = send(200_000_000);
+        let approved = send(300_000_000);
+        assert_ne!(rejected, approved);
+        assert_eq!(context.wallet_mut().wallet_lib().get_pending_spends().len(), 2);
+
+        context.wallet_mut().wallet_lib_mut().reject_spend(rejected).unwrap();
+        assert!(context.wallet_mut().approve_spend(rejected).is_err());
+        approved
+    };
+
+    // pending payments survive a restart
+    let (mut context, _) = make_context(WalletLibraryMode::Decrypt(DecryptConfig::default()));
+    let pending = context.wallet_mut().wallet_lib().get_pending_spends();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].id, id);
+    assert_eq!(pending[0].amt, 300_000_000);
+
+    let tx = context.wallet_mut().approve_spend(id).unwrap();
+    assert!(tx.output.iter().any(|output| output.value == 300_000_000));
+    assert!(context.wallet_mut().wallet_lib().get_pending_spends().is_empty());
+    assert!(context.wallet_mut().approve_spend(id).is_err());
+    assert_eq!(context.wallet_mut().wallet_lib().spent_in_window(), 310_000_000);
+}
+
+fn audit_log<F>(make_context: F)
+where
+    F: Fn(WalletLibraryMode) -> (WalletContext, Mnemonic),
+{
+    let dest_addr = Address::p2wsh(&Script::from(vec![0x51]), Network::Regtest).to_string();
+    let txid = {
+        let (mut context, _) = make_context(WalletLibraryMode::Create(KeyGenConfig::default()));
+        context.bitcoind_mut().generate(110, None).unwrap();
+        generate_money_for_wallet(&mut context);
+
+        context.wallet_mut().wallet_lib_mut().set_audit_caller("treasury".to_owned());
+        let tx = context.wallet_mut()
+            .send_coins(dest_addr.clone(), 10_000_000, true, SendOptions::default())
+            .unwrap()
+            .sent()
+            .unwrap()
+            .tx;
+        tx.txid()
+    };
+
+    // the log survives a restart and goes on from its last entry
+    let (mut context, _) = make_context(WalletLibraryMode::Decrypt(DecryptConfig::default()));
+    context.wallet_mut()
+        .wallet_lib_mut()
+        .new_address(AccountAddressType::P2WKH)
+        .unwrap();
+
+    let log = context.wallet_mut().wallet_lib().get_audit_log(0, 1000);
+    audit::verify(&log, Default::default()).unwrap();
+    let operations: Vec<&str> = log.iter().map(|entry| entry.operation.as_str()).collect();
+    // addresses funded by `generate_money_for_wallet`
+    assert_eq!(&operations[..6], &["new_address", "new_change_address"].repeat(3)[..]);
+    assert_eq!(&operations[6..], &["send_coins", "publish_tx", "new_address"]);
+
+    let send = &log[6];
+    assert_eq!(send.caller, "treasury");
+    assert_eq!(send.txid, Some(txid));
+    assert_eq!(send.params, format!("dest_addr={} amt=10000000 submit=true", dest_addr));
+    assert_eq!(log[7].txid, Some(txid));
+    // the caller isn't remembered across restarts
+    assert_eq!(log[8].caller, "");
+
+    // pages are chained to the entry before them
+    let page = context.wallet_mut().wallet_lib().get_audit_log(6, 2);
+    assert_eq!(page, &log[6..8]);
+    audit::verify(&page, log[5].hash()).unwrap();
+}
+
+fn sync_progress<F>(make_context: F)
+where
+    F: Fn(WalletLibraryMode) -> (WalletContext, Mnemonic),
+{
+    use std::str::FromStr;
+
+    let (mut context, _) = make_context(WalletLibraryMode::Create(KeyGenConfig::default()));
+    context.bitcoind_mut().generate(110, None).unwrap();
+    context.block_for_sync();
+    context.wallet_mut().sync_with_tip().unwrap();
+
+    let addr = context.wallet_mut()
+        .wallet_lib_mut()
+        .new_address(AccountAddressType::P2WKH)
+        .unwrap();
+    for _ in 0..2 {
+        context.bitcoind_mut()
+            .send_to_address(&Address::from_str(&addr).unwrap(), 1.0, None, None, None, None, None, None)
+            .unwrap();
+    }
+    context.bitcoind_mut().generate(1, None).unwrap();
+    context.block_for_sync();
+
+    let mut reports = Vec::new();
+    context.wallet_mut()
+        .sync_with_tip_progress(&mut |progress| reports.push(progress))
+        .unwrap();
+    let last = reports.last().unwrap();
+    assert_eq!(last.height_scanned, last.tip_height);
+    assert_eq!(last.utxos_found, 2);
+    // the coins found never decrease as the sync goes
+    assert!(reports.windows(2).all(|pair| pair[0].utxos_found <= pair[1].utxos_found));
+}
+
+fn mempool_on_startup<F>(make_context: F)
+where
+    F: Fn(WalletLibraryMode) -> (WalletContext, Mnemonic),
+{
+    use std::str::FromStr;
+
+    let txid = {
+        let (mut context, _) = make_context(WalletLibraryMode::Create(KeyGenConfig::default()));
+        context.bitcoind_mut().generate(110, None).unwrap();
+        generate_money_for_wallet(&mut context);
+
+        // the payment arrives while the wallet is down
+        let addr = context.wallet_mut()
+            .wallet_lib_mut()
+            .new_address(AccountAddressType::P2WKH)
+            .unwrap();
+        context.bitcoind_mut()
+            .send_to_address(&Address::from_str(&addr).unwrap(), 1.0, None, None, None, None, None, None)
+            .unwrap()
+    };
+
+    let (mut context, _) = make_context(WalletLibraryMode::Decrypt(DecryptConfig::default()));
+    // only the trusted full node scans the mempool on startup
+    if let WalletContext::Electrs { .. } = context {
+        return;
+    }
+    let history = context.wallet_mut().wallet_lib().get_tx_history();
+    let record = history.iter().find(|record| record.txid() == txid).unwrap();
+    assert_eq!(record.status, TxStatus::Unconfirmed);
+    assert_eq!(context.wallet_mut().wallet_lib().wallet_balance(), 700_000_000);
+}
+
+fn header_chain<F>(make_context: F)
+where
+    F: Fn(WalletLibraryMode) -> (WalletContext, Mnemonic),
+{
+    let (mut context, _) = make_context(WalletLibraryMode::Create(KeyGenConfig::default()));
+    context.bitcoind_mut().generate(110, None).unwrap();
+    generate_money_for_wallet(&mut context);
+    // the electrumx backend gets no blocks
+    if let WalletContext::Electrs { .. } = context {
+        return;
+    }
+
+    let tip_height = context.bitcoind_mut().get_block_count().unwrap() as usize;
+    let tip = context.wallet_mut().wallet_lib().get_header_tip().unwrap();
+    assert_eq!(tip.height, tip_height);
+    assert_eq!(tip.hash, context.bitcoind_mut().get_block_hash(tip_height as u64).unwrap());
+    let prev = context.wallet_mut().wallet_lib().get_block_header(tip_height - 1).unwrap();
+    assert_eq!(tip.prev_hash, prev.hash);
+}
+
+fn spent_utxo_archive<F>(make_context: F)
+where
+    F: Fn(WalletLibraryMode) -> (WalletContext, Mnemonic),
+{
+    let (mut context, _) = make_context(WalletLibraryMode::Create(KeyGenConfig::default()));
+    context.bitcoind_mut().generate(110, None).unwrap();
+    generate_money_for_wallet(&mut context);
+
+    let op = context.wallet_mut().wallet_lib().get_utxo_list()[0].out_point;
+    let dest_addr = Address::p2wsh(&Script::from(vec![0x51]), Network::Regtest).to_string();
+    let tx = context.wallet_mut()
+        .make_tx(vec![op], dest_addr, 50_000_000, true, None)
+        .unwrap();
+    context.bitcoind_mut().generate(1, None).unwrap();
+    context.block_for_sync();
+    context.wallet_mut().sync_with_tip().unwrap();
+
+    let block_height = context.bitcoind_mut().get_block_count().unwrap() as usize;
+    let spent_utxos = context.wallet_mut().wallet_lib().get_spent_utxos();
+    assert_eq!(spent_utxos.len(), 1);
+    assert_eq!(spent_utxos[0].utxo.out_point, op);
+    assert_eq!(spent_utxos[0].spent_by, tx.txid());
+    assert_eq!(spent_utxos[0].spent_height, Some(block_height));
+}
+
+fn wallet_lock<F>(make_context: F)
+where
+    F: Fn(WalletLibraryMode) -> (WalletContext, Mnemonic),
+{
+    let (mut context, _) = make_context(WalletLibraryMode::Create(KeyGenConfig::default()));
+    context.bitcoind_mut().generate(110, None).unwrap();
+    generate_money_for_wallet(&mut context);
+
+    let dest_addr = Address::p2wsh(&Script::from(vec![0x51]), Network::Regtest).to_string();
+    context.wallet_mut().wallet_lib_mut().lock().unwrap();
+    assert!(context.wallet_mut().wallet_lib().is_locked());
+    assert!(!context.wallet_mut().wallet_lib().is_watch_only());
+    // addresses are derived from the public keys
+    context.wallet_mut().wallet_lib_mut().new_address(AccountAddressType::P2WKH).unwrap();
+    let sent = context.wallet_mut()
+        .send_coins(dest_addr.clone(), 10_000_000, true, SendOptions::default());
+    match sent.err().as_ref().and_then(|e| e.downcast_ref::<WalletError>()) {
+        Some(&WalletError::Locked) => (),
+        _ => panic!("a locked wallet signed"),
+    }
+
+    let wrong = DecryptConfig::new("wrong".to_owned(), "easy".to_owned());
+    assert!(context.wallet_mut().wallet_lib_mut().unlock(&wrong, None).is_err());
+    let decrypt_cfg = DecryptConfig::default();
+    context.wallet_mut().wallet_lib_mut().unlock(&decrypt_cfg, None).unwrap();
+    context.wallet_mut()
+        .send_coins(dest_addr, 10_000_000, true, SendOptions::default())
+        .unwrap()
+        .sent()
+        .unwrap();
+
+    // a timeout in the past locks the wallet right away
+    context.wallet_mut().wallet_lib_mut().unlock(&decrypt_cfg, Some(0)).unwrap();
+    assert!(context.wallet_mut().wallet_lib().is_locked());
+    context.wallet_mut().wallet_lib_mut().relock_expired();
+    assert!(context.wallet_mut().wallet_lib().is_locked());
+}